@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use crate::{Result, Value};
+
+pub use turso_core::FunctionFlags;
+
+/// An aggregate function defined by the application. See
+/// [`Connection::create_aggregate_function`](crate::Connection::create_aggregate_function).
+pub trait Aggregate: Send + Sync + 'static {
+    /// The accumulator of a group of rows.
+    type State: 'static;
+
+    /// Creates the accumulator of a new group.
+    fn init(&self) -> Self::State;
+
+    /// Adds a row of the group to the accumulator.
+    fn step(&self, state: &mut Self::State, args: &[Value]) -> Result<()>;
+
+    /// Computes the result of the group.
+    fn finalize(&self, state: Self::State) -> Result<Value>;
+}
+
+/// Runs an [Aggregate] on behalf of the core, one per group.
+pub(crate) struct AggregateAdapter<A: Aggregate> {
+    aggregate: Arc<A>,
+    state: Option<A::State>,
+}
+
+impl<A: Aggregate> AggregateAdapter<A> {
+    pub(crate) fn new(aggregate: Arc<A>) -> Self {
+        let state = aggregate.init();
+        Self {
+            aggregate,
+            state: Some(state),
+        }
+    }
+}
+
+impl<A: Aggregate> turso_core::AggregateState for AggregateAdapter<A> {
+    fn step(&mut self, args: &[turso_core::Value]) -> turso_core::Result<()> {
+        let args = to_values(args);
+        let Some(state) = self.state.as_mut() else {
+            return Err(turso_core::LimboError::InternalError(
+                "aggregate stepped after finalize".to_string(),
+            ));
+        };
+        self.aggregate.step(state, &args).map_err(to_core_error)
+    }
+
+    fn finalize(&mut self) -> turso_core::Result<turso_core::Value> {
+        let Some(state) = self.state.take() else {
+            return Err(turso_core::LimboError::InternalError(
+                "aggregate finalized twice".to_string(),
+            ));
+        };
+        self.aggregate
+            .finalize(state)
+            .map(Into::into)
+            .map_err(to_core_error)
+    }
+}
+
+pub(crate) fn to_values(args: &[turso_core::Value]) -> Vec<Value> {
+    args.iter().cloned().map(Value::from).collect()
+}
+
+pub(crate) fn to_core_error(err: crate::Error) -> turso_core::LimboError {
+    turso_core::LimboError::ExtensionError(err.to_string())
+}
@@ -32,6 +32,7 @@
 //! # }
 //! ```
 
+mod function;
 pub mod params;
 mod rows;
 pub mod transaction;
@@ -42,6 +43,8 @@ use transaction::TransactionBehavior;
 use turso_core::types::WalFrameInfo;
 pub use value::Value;
 
+pub use function::{Aggregate, FunctionFlags};
+
 pub use params::params_from_iter;
 pub use params::IntoParams;
 
@@ -319,6 +322,59 @@ impl Connection {
         Ok(())
     }
 
+    /// Register a collation sequence on this connection that orders text with `compare`,
+    /// usable with `COLLATE name` in queries, column definitions and indexes.
+    pub fn create_collation<F>(&self, name: &str, compare: F) -> Result<()>
+    where
+        F: Fn(&str, &str) -> std::cmp::Ordering + Send + Sync + 'static,
+    {
+        let conn = self
+            .inner
+            .lock()
+            .map_err(|e| Error::MutexError(e.to_string()))?;
+        conn.create_collation(name, compare)?;
+        Ok(())
+    }
+
+    /// Register a scalar function callable from SQL as `name(...)` on this connection.
+    /// Pass [FunctionFlags::Deterministic] if the result only depends on the arguments, so
+    /// calls with constant arguments are evaluated once per statement.
+    pub fn create_scalar_function<F>(&self, name: &str, flags: FunctionFlags, func: F) -> Result<()>
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        let conn = self
+            .inner
+            .lock()
+            .map_err(|e| Error::MutexError(e.to_string()))?;
+        conn.create_scalar_function(name, flags, move |args| {
+            func(&function::to_values(args))
+                .map(Into::into)
+                .map_err(function::to_core_error)
+        })?;
+        Ok(())
+    }
+
+    /// Register an aggregate function taking `argc` arguments, callable from SQL as
+    /// `name(...)` on this connection.
+    pub fn create_aggregate_function<A: Aggregate>(
+        &self,
+        name: &str,
+        argc: usize,
+        flags: FunctionFlags,
+        aggregate: A,
+    ) -> Result<()> {
+        let conn = self
+            .inner
+            .lock()
+            .map_err(|e| Error::MutexError(e.to_string()))?;
+        let aggregate = Arc::new(aggregate);
+        conn.create_aggregate_function(name, argc, flags, move || {
+            Box::new(function::AggregateAdapter::new(aggregate.clone()))
+        })?;
+        Ok(())
+    }
+
     /// Returns the rowid of the last row inserted.
     pub fn last_insert_rowid(&self) -> i64 {
         let conn = self.inner.lock().unwrap();
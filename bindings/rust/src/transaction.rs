@@ -2,6 +2,10 @@ use std::ops::Deref;
 
 use crate::{Connection, Result};
 
+/// Name of the savepoint backing a nested transaction. Nested transactions are strictly
+/// stacked, so `RELEASE` and `ROLLBACK TO` always address the innermost one.
+const NESTED_TRANSACTION_SAVEPOINT: &str = "turso_nested_transaction";
+
 /// Options for transaction behavior. See [BEGIN
 /// TRANSACTION](http://www.sqlite.org/lang_transaction.html) for details.
 #[derive(Copy, Clone)]
@@ -44,6 +48,10 @@ pub enum DropBehavior {
 /// commit the transaction, or use `set_drop_behavior` to change what happens
 /// when the transaction is dropped.
 ///
+/// Transactions can be nested with [`Transaction::transaction`]. A nested
+/// transaction is backed by a savepoint: committing it releases the savepoint
+/// and rolling it back only undoes the changes made since it began.
+///
 /// ## Example
 ///
 /// ```rust,no_run
@@ -64,13 +72,15 @@ pub struct Transaction<'conn> {
     conn: &'conn Connection,
     drop_behavior: DropBehavior,
     must_finish: bool,
+    /// Whether this transaction is nested in another one and backed by a savepoint.
+    nested: bool,
 }
 
 impl Transaction<'_> {
-    /// Begin a new transaction. Cannot be nested;
+    /// Begin a new transaction.
     ///
     /// Even though we don't mutate the connection, we take a `&mut Connection`
-    /// to prevent nested transactions on the same connection. For cases
+    /// to make nesting explicit through [`Transaction::transaction`]. For cases
     /// where this is unacceptable, [`Transaction::new_unchecked`] is available.
     #[inline]
     pub async fn new(
@@ -80,16 +90,19 @@ impl Transaction<'_> {
         Self::new_unchecked(conn, behavior).await
     }
 
-    /// Begin a new transaction, failing if a transaction is open.
+    /// Begin a new transaction, nesting it in the open transaction if there is one.
     ///
-    /// If a transaction is already open, this will return an error. Where
-    /// possible, [`Transaction::new`] should be preferred, as it provides a
-    /// compile-time guarantee that transactions are not nested.
+    /// If a transaction is already open, the new transaction is backed by a
+    /// savepoint and `behavior` is ignored. Where possible, [`Transaction::new`]
+    /// should be preferred, as it makes nesting explicit at compile time.
     #[inline]
     pub async fn new_unchecked(
         conn: &Connection,
         behavior: TransactionBehavior,
     ) -> Result<Transaction<'_>> {
+        if !conn.is_autocommit()? {
+            return Self::new_nested(conn).await;
+        }
         let query = match behavior {
             TransactionBehavior::Deferred => "BEGIN DEFERRED",
             TransactionBehavior::Immediate => "BEGIN IMMEDIATE",
@@ -100,9 +113,54 @@ impl Transaction<'_> {
             conn,
             drop_behavior: DropBehavior::Rollback,
             must_finish: true,
+            nested: false,
+        })
+    }
+
+    async fn new_nested(conn: &Connection) -> Result<Transaction<'_>> {
+        let query = format!("SAVEPOINT {NESTED_TRANSACTION_SAVEPOINT}");
+        conn.execute(&query, ()).await.map(move |_| Transaction {
+            conn,
+            drop_behavior: DropBehavior::Rollback,
+            must_finish: true,
+            nested: true,
         })
     }
 
+    /// Begin a transaction nested in this one.
+    ///
+    /// The nested transaction is backed by a savepoint. Committing it makes its
+    /// changes part of this transaction, rolling it back undoes only the changes
+    /// made since it began. Like any transaction, it rolls back by default when
+    /// finished.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use turso::{Connection, Result};
+    /// async fn perform_queries(conn: &mut Connection) -> Result<()> {
+    ///     let mut tx = conn.transaction().await?;
+    ///     tx.execute("INSERT INTO users VALUES ('alice')", ()).await?;
+    ///     {
+    ///         let nested = tx.transaction().await?;
+    ///         nested.execute("INSERT INTO users VALUES ('bob')", ()).await?;
+    ///         nested.rollback().await?; // only bob is rolled back
+    ///     }
+    ///     tx.commit().await
+    /// }
+    /// ```
+    #[inline]
+    pub async fn transaction(&mut self) -> Result<Transaction<'_>> {
+        Self::new_nested(self.conn).await
+    }
+
+    /// Whether this transaction is nested in another one.
+    #[inline]
+    #[must_use]
+    pub fn is_nested(&self) -> bool {
+        self.nested
+    }
+
     /// Get the current setting for what happens to the transaction when it is
     /// dropped.
     #[inline]
@@ -127,7 +185,12 @@ impl Transaction<'_> {
     #[inline]
     async fn _commit(&mut self) -> Result<()> {
         self.must_finish = false;
-        self.conn.execute("COMMIT", ()).await?;
+        if self.nested {
+            let query = format!("RELEASE {NESTED_TRANSACTION_SAVEPOINT}");
+            self.conn.execute(&query, ()).await?;
+        } else {
+            self.conn.execute("COMMIT", ()).await?;
+        }
         Ok(())
     }
 
@@ -140,7 +203,14 @@ impl Transaction<'_> {
     #[inline]
     async fn _rollback(&mut self) -> Result<()> {
         self.must_finish = false;
-        self.conn.execute("ROLLBACK", ()).await?;
+        if self.nested {
+            let query = format!("ROLLBACK TO {NESTED_TRANSACTION_SAVEPOINT}");
+            self.conn.execute(&query, ()).await?;
+            let query = format!("RELEASE {NESTED_TRANSACTION_SAVEPOINT}");
+            self.conn.execute(&query, ()).await?;
+        } else {
+            self.conn.execute("ROLLBACK", ()).await?;
+        }
         Ok(())
     }
 
@@ -241,10 +311,9 @@ impl Connection {
 
     /// Begin a new transaction with the default behavior (DEFERRED).
     ///
-    /// Attempt to open a nested transaction will result in a SQLite error.
-    /// `Connection::transaction` prevents this at compile time by taking `&mut
-    /// self`, but `Connection::unchecked_transaction()` may be used to defer
-    /// the checking until runtime.
+    /// If a transaction is already open, the new transaction is nested in it
+    /// and backed by a savepoint. `Connection::transaction` makes nesting
+    /// explicit by taking `&mut self`, see [`Transaction::transaction`].
     ///
     /// See [`Connection::transaction`] and [`Transaction::new_unchecked`]
     /// (which can be used if the default transaction behavior is undesirable).
@@ -268,8 +337,7 @@ impl Connection {
     ///
     /// # Failure
     ///
-    /// Will return `Err` if the underlying SQLite call fails. The specific
-    /// error returned if transactions are nested is currently unspecified.
+    /// Will return `Err` if the underlying SQLite call fails.
     pub async fn unchecked_transaction(&self) -> Result<Transaction<'_>> {
         Transaction::new_unchecked(self, self.transaction_behavior).await
     }
@@ -306,7 +374,7 @@ impl Connection {
 
 #[cfg(test)]
 mod test {
-    use crate::{Builder, Connection, Error, Result};
+    use crate::{Builder, Connection, Result};
 
     use super::DropBehavior;
 
@@ -356,31 +424,26 @@ mod test {
         Ok(())
     }
 
-    fn assert_nested_tx_error(e: Error) {
-        if let Error::SqlExecutionFailure(e) = &e {
-            assert!(e.contains("transaction"));
-        } else {
-            panic!("Unexpected error type: {e:?}");
-        }
-    }
-
     #[tokio::test]
     async fn test_unchecked_nesting() -> Result<()> {
         let conn = checked_memory_handle().await?;
 
         {
             let tx = conn.unchecked_transaction().await?;
-            let e = tx.unchecked_transaction().await.unwrap_err();
-            assert_nested_tx_error(e);
-            tx.finish().await?;
+            let nested = tx.unchecked_transaction().await?;
+            assert!(nested.is_nested());
+            nested.execute("INSERT INTO foo VALUES(?)", &[1]).await?;
+            nested.finish().await?;
             // default: rollback
+            tx.finish().await?;
         }
         {
             let tx = conn.unchecked_transaction().await?;
             tx.execute("INSERT INTO foo VALUES(?)", &[1]).await?;
             // Ensure this doesn't interfere with ongoing transaction
-            let e = tx.unchecked_transaction().await.unwrap_err();
-            assert_nested_tx_error(e);
+            let nested = tx.unchecked_transaction().await?;
+            nested.execute("INSERT INTO foo VALUES(?)", &[10]).await?;
+            nested.rollback().await?;
 
             tx.execute("INSERT INTO foo VALUES(?)", &[1]).await?;
             tx.commit().await?;
@@ -403,9 +466,8 @@ mod test {
             tx.execute("INSERT INTO foo VALUES(?)", &[1]).await?;
             tx.rollback().await?;
 
-            // This is a current Turso's limitation.
-            // Since we don't have support for savepoints yet,
-            // a rollback ends with a transaction so we need to immediately open a new one.
+            // A rollback ends the transaction, so we need to open a new one.
+            // Use a nested transaction to roll back only part of the work.
             let tx = conn.transaction().await?;
             tx.execute("INSERT INTO foo VALUES(?)", &[2]).await?;
             tx.commit().await?;
@@ -425,4 +487,37 @@ mod test {
         }
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_nested_transaction() -> Result<()> {
+        let mut conn = checked_memory_handle().await?;
+        {
+            let mut tx = conn.transaction().await?;
+            tx.execute("INSERT INTO foo VALUES(?)", &[1]).await?;
+            {
+                let mut nested = tx.transaction().await?;
+                nested.execute("INSERT INTO foo VALUES(?)", &[2]).await?;
+                {
+                    let innermost = nested.transaction().await?;
+                    innermost.execute("INSERT INTO foo VALUES(?)", &[4]).await?;
+                    innermost.rollback().await?;
+                }
+                nested.commit().await?;
+            }
+            {
+                let mut nested = tx.transaction().await?;
+                nested.execute("INSERT INTO foo VALUES(?)", &[8]).await?;
+                nested.set_drop_behavior(DropBehavior::Commit);
+                nested.finish().await?;
+            }
+            tx.commit().await?;
+        }
+        let result = conn
+            .prepare("SELECT SUM(x) FROM foo")
+            .await?
+            .query_row(())
+            .await?;
+        assert_eq!(11, result.get::<i32>(0)?);
+        Ok(())
+    }
 }
@@ -1,5 +1,7 @@
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Arc;
 use tokio::fs;
-use turso::{Builder, Error, Value};
+use turso::{Aggregate, Builder, Error, FunctionFlags, Value};
 
 #[tokio::test]
 async fn test_rows_next() {
@@ -339,3 +341,223 @@ async fn test_index() {
     assert!(row.get::<String>(1).unwrap() == "b@d.e");
     assert!(rows.next().await.unwrap().is_none());
 }
+
+#[tokio::test]
+async fn test_create_collation() {
+    let db = Builder::new_local(":memory:").build().await.unwrap();
+    let conn = db.connect().unwrap();
+    conn.create_collation("reverse", |lhs, rhs| rhs.cmp(lhs))
+        .unwrap();
+    assert!(conn
+        .create_collation("nocase", |lhs, rhs| lhs.cmp(rhs))
+        .is_err());
+
+    conn.execute("CREATE TABLE words (word TEXT COLLATE reverse)", ())
+        .await
+        .unwrap();
+    conn.execute("CREATE INDEX words_idx ON words(word)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO words VALUES ('b'), ('c'), ('a')", ())
+        .await
+        .unwrap();
+
+    let mut rows = conn
+        .query("SELECT word FROM words ORDER BY word", ())
+        .await
+        .unwrap();
+    let mut words = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        words.push(row.get::<String>(0).unwrap());
+    }
+    assert_eq!(words, vec!["c", "b", "a"]);
+
+    let mut rows = conn
+        .query("SELECT word FROM words WHERE word > 'b'", ())
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<String>(0).unwrap(), "a");
+    assert!(rows.next().await.unwrap().is_none());
+
+    let mut rows = conn
+        .query("SELECT 'a' < 'b' COLLATE reverse", ())
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 0);
+
+    let other = db.connect().unwrap();
+    assert!(other
+        .query("SELECT 'a' < 'b' COLLATE reverse", ())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_create_collation_after_open() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("collation.db");
+    let path = path.to_str().unwrap();
+    {
+        let db = Builder::new_local(path).build().await.unwrap();
+        let conn = db.connect().unwrap();
+        conn.create_collation("reverse", |lhs, rhs| rhs.cmp(lhs))
+            .unwrap();
+        conn.execute("CREATE TABLE words (word TEXT COLLATE reverse)", ())
+            .await
+            .unwrap();
+        conn.execute("CREATE INDEX words_idx ON words(word)", ())
+            .await
+            .unwrap();
+        conn.execute("INSERT INTO words VALUES ('b'), ('c'), ('a')", ())
+            .await
+            .unwrap();
+        conn.execute("CREATE TABLE other (x)", ()).await.unwrap();
+        conn.execute("INSERT INTO other VALUES (1)", ())
+            .await
+            .unwrap();
+    }
+
+    let db = Builder::new_local(path).build().await.unwrap();
+    let conn = db.connect().unwrap();
+    // Only the table using the collation sequence is missing until it is registered.
+    let mut rows = conn.query("SELECT x FROM other", ()).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 1);
+    assert!(conn.query("SELECT word FROM words", ()).await.is_err());
+
+    conn.create_collation("reverse", |lhs, rhs| rhs.cmp(lhs))
+        .unwrap();
+    conn.execute("INSERT INTO words VALUES ('d')", ())
+        .await
+        .unwrap();
+    let mut rows = conn
+        .query("SELECT word FROM words ORDER BY word", ())
+        .await
+        .unwrap();
+    let mut words = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        words.push(row.get::<String>(0).unwrap());
+    }
+    assert_eq!(words, vec!["d", "c", "b", "a"]);
+
+    let mut rows = conn
+        .query("SELECT word FROM words WHERE word > 'b'", ())
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<String>(0).unwrap(), "a");
+    assert!(rows.next().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_create_scalar_function() {
+    let db = Builder::new_local(":memory:").build().await.unwrap();
+    let conn = db.connect().unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    for (name, flags) in [
+        ("add_one", FunctionFlags::empty()),
+        ("add_one_det", FunctionFlags::Deterministic),
+    ] {
+        let calls = calls.clone();
+        conn.create_scalar_function(name, flags, move |args| {
+            calls.fetch_add(1, AtomicOrdering::SeqCst);
+            match args {
+                [Value::Integer(i)] => Ok(Value::Integer(i + 1)),
+                _ => Err(Error::ConversionFailure("expected an integer".to_string())),
+            }
+        })
+        .unwrap();
+    }
+    conn.execute("CREATE TABLE t (x INTEGER)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO t VALUES (1), (2), (3)", ())
+        .await
+        .unwrap();
+
+    let mut rows = conn.query("SELECT add_one(x) FROM t", ()).await.unwrap();
+    let mut results = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        results.push(row.get::<i64>(0).unwrap());
+    }
+    assert_eq!(results, vec![2, 3, 4]);
+
+    // Calls with constant arguments are only evaluated once if the function is deterministic.
+    for (name, expected_calls) in [("add_one", 3), ("add_one_det", 1)] {
+        calls.store(0, AtomicOrdering::SeqCst);
+        let mut rows = conn
+            .query(&format!("SELECT {name}(41) FROM t"), ())
+            .await
+            .unwrap();
+        while let Some(row) = rows.next().await.unwrap() {
+            assert_eq!(row.get::<i64>(0).unwrap(), 42);
+        }
+        assert_eq!(calls.load(AtomicOrdering::SeqCst), expected_calls);
+    }
+
+    let mut rows = conn.query("SELECT add_one('a')", ()).await.unwrap();
+    let err = rows.next().await.unwrap_err();
+    assert!(err.to_string().contains("expected an integer"));
+}
+
+#[tokio::test]
+async fn test_create_aggregate_function() {
+    struct SumOfSquares;
+
+    impl Aggregate for SumOfSquares {
+        type State = i64;
+
+        fn init(&self) -> i64 {
+            0
+        }
+
+        fn step(&self, state: &mut i64, args: &[Value]) -> turso::Result<()> {
+            if let Some(i) = args[0].as_integer() {
+                *state += i * i;
+            }
+            Ok(())
+        }
+
+        fn finalize(&self, state: i64) -> turso::Result<Value> {
+            Ok(Value::Integer(state))
+        }
+    }
+
+    let db = Builder::new_local(":memory:").build().await.unwrap();
+    let conn = db.connect().unwrap();
+    conn.create_aggregate_function("sum_sq", 1, FunctionFlags::empty(), SumOfSquares)
+        .unwrap();
+    conn.execute("CREATE TABLE t (g TEXT, x INTEGER)", ())
+        .await
+        .unwrap();
+    conn.execute(
+        "INSERT INTO t VALUES ('a', 1), ('a', 2), ('b', 3), ('b', NULL)",
+        (),
+    )
+    .await
+    .unwrap();
+
+    let mut rows = conn.query("SELECT sum_sq(x) FROM t", ()).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 14);
+
+    // Without rows, the aggregate finalizes the state it was initialized with.
+    let mut rows = conn
+        .query("SELECT sum_sq(x) FROM t WHERE 0", ())
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 0);
+
+    let mut rows = conn
+        .query("SELECT g, sum_sq(x) FROM t GROUP BY g ORDER BY g", ())
+        .await
+        .unwrap();
+    let mut results = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        results.push((row.get::<String>(0).unwrap(), row.get::<i64>(1).unwrap()));
+    }
+    assert_eq!(results, vec![("a".to_string(), 5), ("b".to_string(), 9)]);
+}
@@ -1,5 +1,8 @@
 use crate::{
-    ext::{register_aggregate_function, register_scalar_function, register_vtab_module},
+    ext::{
+        register_aggregate_function, register_collation, register_scalar_function,
+        register_vtab_module,
+    },
     Connection, LimboError,
 };
 #[cfg(not(target_family = "wasm"))]
@@ -112,6 +115,7 @@ pub fn add_builtin_vfs_extensions(
                 builtin_vfs: vfslist.as_mut_ptr(),
                 builtin_vfs_count: 0,
             },
+            register_collation,
         },
         Some(mut api) => {
             api.vfs_interface.builtin_vfs = vfslist.as_mut_ptr();
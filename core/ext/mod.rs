@@ -16,7 +16,8 @@ use std::{
     sync::{Arc, Mutex},
 };
 use turso_ext::{
-    ExtensionApi, InitAggFunction, ResultCode, ScalarFunction, VTabKind, VTabModuleImpl,
+    CollationFunction, ExtensionApi, InitAggFunction, ResultCode, ScalarFunction, VTabKind,
+    VTabModuleImpl,
 };
 pub use turso_ext::{FinalizeFunction, StepFunction, Value as ExtValue, ValueType as ExtValueType};
 pub use vtab_xconnect::{execute, prepare_stmt};
@@ -133,6 +134,28 @@ pub(crate) unsafe extern "C" fn register_aggregate_function(
     ResultCode::OK
 }
 
+pub(crate) unsafe extern "C" fn register_collation(
+    ctx: *mut c_void,
+    name: *const c_char,
+    func: CollationFunction,
+) -> ResultCode {
+    if name.is_null() || ctx.is_null() {
+        return ResultCode::Error;
+    }
+    let c_str = unsafe { CStr::from_ptr(name) };
+    let Ok(name_str) = c_str.to_str() else {
+        return ResultCode::InvalidArgs;
+    };
+    let compare = move |lhs: &str, rhs: &str| {
+        unsafe { func(lhs.as_ptr(), lhs.len(), rhs.as_ptr(), rhs.len()) }.cmp(&0)
+    };
+    let ext_ctx = unsafe { &mut *(ctx as *mut ExtensionCtx) };
+    match unsafe { (*ext_ctx.syms).register_collation(name_str, Box::new(compare)) } {
+        Ok(()) => ResultCode::OK,
+        Err(_) => ResultCode::InvalidArgs,
+    }
+}
+
 impl Database {
     #[cfg(feature = "fs")]
     #[allow(clippy::arc_with_non_send_sync, dead_code)]
@@ -182,12 +205,15 @@ impl Database {
                 builtin_vfs: std::ptr::null_mut(),
                 builtin_vfs_count: 0,
             },
+            register_collation,
         };
 
         #[cfg(feature = "uuid")]
         crate::uuid::register_extension(&mut ext_api);
         #[cfg(feature = "series")]
         crate::series::register_extension(&mut ext_api);
+        #[cfg(feature = "json")]
+        crate::json::vtab::register_extension(&mut ext_api);
         #[cfg(feature = "fs")]
         {
             let vfslist = add_builtin_vfs_extensions(Some(ext_api)).map_err(|e| e.to_string())?;
@@ -237,6 +263,7 @@ impl Connection {
                 builtin_vfs: std::ptr::null_mut(),
                 builtin_vfs_count: 0,
             },
+            register_collation,
         }
     }
 
@@ -1,23 +1,58 @@
+use bitflags::bitflags;
 use std::fmt;
 use std::fmt::{Debug, Display};
 use std::rc::Rc;
 use std::sync::Arc;
 use turso_ext::{FinalizeFunction, InitAggFunction, ScalarFunction, StepFunction};
 
-use crate::LimboError;
+use crate::{LimboError, Value};
+
+bitflags! {
+    // Flag names and values match those used in SQLite:
+    // https://www.sqlite.org/c3ref/c_deterministic.html
+    /// Properties of a function defined with [crate::Connection::create_scalar_function] or
+    /// [crate::Connection::create_aggregate_function].
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct FunctionFlags: u32 {
+        /// Always returns the same result for the same arguments, so calls with constant
+        /// arguments are only evaluated once per statement and the function may be used in
+        /// expression indexes.
+        const Deterministic = 0x000000800;
+        /// Has no side effects and does not leak information, making it safe to use from the
+        /// schema. Recorded for compatibility: the schema is always trusted.
+        const Innocuous = 0x000200000;
+    }
+}
+
+/// Scalar function defined by the application.
+pub type NativeScalarFn = dyn Fn(&[Value]) -> crate::Result<Value> + Send + Sync;
+
+/// Creates the accumulator of an aggregate function defined by the application, once per group.
+pub type NativeAggregateInit = dyn Fn() -> Box<dyn AggregateState> + Send + Sync;
+
+/// Accumulator of an aggregate function defined with
+/// [crate::Connection::create_aggregate_function].
+pub trait AggregateState {
+    /// Adds a row to the group.
+    fn step(&mut self, args: &[Value]) -> crate::Result<()>;
+    /// Returns the result for the group. Called once, after the last step.
+    fn finalize(&mut self) -> crate::Result<Value>;
+}
 
 pub struct ExternalFunc {
     pub name: String,
     pub func: ExtFunc,
+    pub flags: FunctionFlags,
 }
 
 impl ExternalFunc {
     pub fn is_deterministic(&self) -> bool {
-        false // external functions can be whatever so let's just default to false
+        // extension functions can be whatever, only the application can tell us otherwise
+        self.flags.contains(FunctionFlags::Deterministic)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub enum ExtFunc {
     Scalar(ScalarFunction),
     Aggregate {
@@ -26,14 +61,39 @@ pub enum ExtFunc {
         step: StepFunction,
         finalize: FinalizeFunction,
     },
+    NativeScalar(Arc<NativeScalarFn>),
+    NativeAggregate {
+        argc: usize,
+        init: Arc<NativeAggregateInit>,
+    },
 }
 
 impl ExtFunc {
     pub fn agg_args(&self) -> Result<usize, ()> {
-        if let ExtFunc::Aggregate { argc, .. } = self {
-            return Ok(*argc);
+        match self {
+            ExtFunc::Aggregate { argc, .. } | ExtFunc::NativeAggregate { argc, .. } => Ok(*argc),
+            ExtFunc::Scalar(_) | ExtFunc::NativeScalar(_) => Err(()),
+        }
+    }
+
+    pub fn is_scalar(&self) -> bool {
+        matches!(self, ExtFunc::Scalar(_) | ExtFunc::NativeScalar(_))
+    }
+}
+
+impl Debug for ExtFunc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scalar(func) => f.debug_tuple("Scalar").field(func).finish(),
+            Self::Aggregate { argc, .. } => {
+                f.debug_struct("Aggregate").field("argc", argc).finish()
+            }
+            Self::NativeScalar(_) => f.debug_tuple("NativeScalar").finish(),
+            Self::NativeAggregate { argc, .. } => f
+                .debug_struct("NativeAggregate")
+                .field("argc", argc)
+                .finish(),
         }
-        Err(())
     }
 }
 
@@ -42,6 +102,7 @@ impl ExternalFunc {
         Self {
             name,
             func: ExtFunc::Scalar(func),
+            flags: FunctionFlags::empty(),
         }
     }
 
@@ -58,6 +119,32 @@ impl ExternalFunc {
                 step: func.1,
                 finalize: func.2,
             },
+            flags: FunctionFlags::empty(),
+        }
+    }
+
+    pub fn new_native_scalar(
+        name: String,
+        flags: FunctionFlags,
+        func: Arc<NativeScalarFn>,
+    ) -> Self {
+        Self {
+            name,
+            func: ExtFunc::NativeScalar(func),
+            flags,
+        }
+    }
+
+    pub fn new_native_aggregate(
+        name: String,
+        argc: usize,
+        flags: FunctionFlags,
+        init: Arc<NativeAggregateInit>,
+    ) -> Self {
+        Self {
+            name,
+            func: ExtFunc::NativeAggregate { argc, init },
+            flags,
         }
     }
 }
@@ -272,6 +359,81 @@ impl AggFunc {
     }
 }
 
+/// Built-in window functions. Aggregate functions can also be used as window functions,
+/// those are represented by [AggFunc].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowFunc {
+    RowNumber,
+    Rank,
+    DenseRank,
+    PercentRank,
+    CumeDist,
+    Ntile,
+    Lag,
+    Lead,
+    FirstValue,
+    LastValue,
+    NthValue,
+}
+
+impl WindowFunc {
+    /// Resolves a built-in window function by name, checking the number of arguments.
+    /// Returns `Ok(None)` if `name` is not a built-in window function.
+    pub fn resolve(name: &str, arg_count: usize) -> Result<Option<Self>, LimboError> {
+        let func = match name.to_lowercase().as_str() {
+            "row_number" => Self::RowNumber,
+            "rank" => Self::Rank,
+            "dense_rank" => Self::DenseRank,
+            "percent_rank" => Self::PercentRank,
+            "cume_dist" => Self::CumeDist,
+            "ntile" => Self::Ntile,
+            "lag" => Self::Lag,
+            "lead" => Self::Lead,
+            "first_value" => Self::FirstValue,
+            "last_value" => Self::LastValue,
+            "nth_value" => Self::NthValue,
+            _ => return Ok(None),
+        };
+        let valid = match func {
+            Self::RowNumber | Self::Rank | Self::DenseRank | Self::PercentRank | Self::CumeDist => {
+                arg_count == 0
+            }
+            Self::Ntile | Self::FirstValue | Self::LastValue => arg_count == 1,
+            Self::Lag | Self::Lead => (1..=3).contains(&arg_count),
+            Self::NthValue => arg_count == 2,
+        };
+        if !valid {
+            crate::bail_parse_error!("wrong number of arguments to function {}()", func);
+        }
+        Ok(Some(func))
+    }
+
+    /// Whether the result depends on the frame of the window, as opposed to the position
+    /// of the row in its partition.
+    pub fn uses_frame(&self) -> bool {
+        matches!(self, Self::FirstValue | Self::LastValue | Self::NthValue)
+    }
+}
+
+impl Display for WindowFunc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str = match self {
+            Self::RowNumber => "row_number",
+            Self::Rank => "rank",
+            Self::DenseRank => "dense_rank",
+            Self::PercentRank => "percent_rank",
+            Self::CumeDist => "cume_dist",
+            Self::Ntile => "ntile",
+            Self::Lag => "lag",
+            Self::Lead => "lead",
+            Self::FirstValue => "first_value",
+            Self::LastValue => "last_value",
+            Self::NthValue => "nth_value",
+        };
+        write!(f, "{str}")
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ScalarFunc {
     Cast,
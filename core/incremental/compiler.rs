@@ -5,12 +5,15 @@
 //!
 //! Based on the DBSP paper: "DBSP: Automatic Incremental View Maintenance for Rich Query Languages"
 
+use crate::function::Func;
 use crate::incremental::expr_compiler::CompiledExpression;
 use crate::incremental::operator::{
-    Delta, FilterOperator, FilterPredicate, IncrementalOperator, ProjectOperator,
+    Delta, DeltaPair, FilterOperator, FilterPredicate, IncrementalOperator, JoinOperator, JoinType,
+    ProjectOperator, StateEntries,
 };
 // Note: logical module must be made pub(crate) in translate/mod.rs
-use crate::translate::logical::{BinaryOperator, LogicalExpr, LogicalPlan, SchemaRef};
+use crate::translate::expr::{walk_expr, WalkControl};
+use crate::translate::logical::{self, BinaryOperator, LogicalExpr, LogicalPlan, SchemaRef};
 use crate::types::Value;
 use crate::{LimboError, Result};
 use std::collections::HashMap;
@@ -51,6 +54,21 @@ impl DeltaSet {
             .cloned()
             .unwrap_or_else(Delta::new)
     }
+
+    /// Get a mutable delta for a table, inserting an empty one if not found
+    pub fn get_mut(&mut self, table_name: &str) -> &mut Delta {
+        self.deltas.entry(table_name.to_string()).or_default()
+    }
+
+    /// Whether there are no changes for any table
+    pub fn is_empty(&self) -> bool {
+        self.deltas.values().all(|delta| delta.is_empty())
+    }
+
+    /// Convert into the deltas keyed by table name
+    pub fn into_map(self) -> HashMap<String, Delta> {
+        self.deltas
+    }
 }
 
 /// Represents a DBSP operator in the compiled circuit
@@ -69,6 +87,12 @@ pub enum DbspOperator {
         aggr_exprs: Vec<crate::incremental::operator::AggregateFunction>,
         schema: SchemaRef,
     },
+    /// Join operator (⋈) - equi-join of its two inputs
+    Join {
+        join_type: JoinType,
+        on: Vec<(DbspExpr, DbspExpr)>,
+        schema: SchemaRef,
+    },
     /// Input operator - source of data
     Input { name: String, schema: SchemaRef },
 }
@@ -219,11 +243,27 @@ impl DbspCircuit {
                     }
                 }
             }
+            DbspOperator::Join { .. } => {
+                if let Some(ref mut op) = node.executable {
+                    op.initialize_pair(Self::delta_pair(input_deltas));
+                    op.get_current_state()
+                } else {
+                    Delta::new()
+                }
+            }
         };
 
         Ok(result)
     }
 
+    /// Split the input deltas of a node with two inputs into a pair
+    fn delta_pair(input_deltas: Vec<Delta>) -> DeltaPair {
+        let mut inputs = input_deltas.into_iter();
+        let left = inputs.next().unwrap_or_default();
+        let right = inputs.next().unwrap_or_default();
+        DeltaPair::new(left, right)
+    }
+
     /// Execute the circuit with incremental input data (deltas).
     /// Call initialize() first for initial data, then use execute() for updates.
     ///
@@ -258,6 +298,40 @@ impl DbspCircuit {
         Ok(())
     }
 
+    /// Take the changes to the persistent state of the circuit's operators since the last call,
+    /// as (node id, key, state) entries. A `None` state means the entry was removed.
+    pub fn take_state_changes(&mut self) -> Vec<(usize, Vec<Value>, Option<Vec<Value>>)> {
+        let mut changes = Vec::new();
+        for (node_id, node) in self.nodes.iter_mut() {
+            if let Some(ref mut op) = node.executable {
+                changes.extend(
+                    op.take_state_changes()
+                        .into_iter()
+                        .map(|(key, state)| (*node_id, key, state)),
+                );
+            }
+        }
+        changes
+    }
+
+    /// Restore the persistent state of the circuit's operators, keyed by node id. The circuit
+    /// doesn't need to be initialized afterwards.
+    pub fn restore_state(&mut self, entries: HashMap<usize, StateEntries>) -> Result<()> {
+        for (node_id, node_entries) in entries {
+            let Some(op) = self
+                .nodes
+                .get_mut(&node_id)
+                .and_then(|node| node.executable.as_mut())
+            else {
+                return Err(LimboError::Corrupt(format!(
+                    "view state refers to unknown operator {node_id}"
+                )));
+            };
+            op.restore_state(node_entries)?;
+        }
+        Ok(())
+    }
+
     /// Commit a specific node in the circuit
     fn commit_node(
         &mut self,
@@ -312,6 +386,13 @@ impl DbspCircuit {
                     }
                 }
             }
+            DbspOperator::Join { .. } => {
+                if let Some(ref mut op) = node.executable {
+                    op.commit_pair(Self::delta_pair(input_deltas))
+                } else {
+                    Delta::new()
+                }
+            }
         };
         Ok(result)
     }
@@ -384,6 +465,13 @@ impl DbspCircuit {
                     }
                 }
             }
+            DbspOperator::Join { .. } => {
+                if let Some(ref op) = node.executable {
+                    op.eval_pair(Self::delta_pair(input_deltas))
+                } else {
+                    Delta::new()
+                }
+            }
         };
         Ok(result)
     }
@@ -420,6 +508,9 @@ impl DbspCircuit {
                         "{indent}Aggregate[{node_id}]: GROUP BY {group_exprs:?}, AGGR {aggr_exprs:?}"
                     )?;
                 }
+                DbspOperator::Join { join_type, on, .. } => {
+                    writeln!(f, "{indent}Join[{node_id}]: {join_type:?} ON {on:?}")?;
+                }
                 DbspOperator::Input { name, .. } => {
                     writeln!(f, "{indent}Input[{node_id}]: {name}")?;
                 }
@@ -516,7 +607,8 @@ impl DbspCompiler {
                 let dbsp_predicate = Self::compile_expr(&filter.predicate)?;
 
                 // Convert to FilterPredicate
-                let filter_predicate = Self::compile_filter_predicate(&filter.predicate)?;
+                let filter_predicate =
+                    Self::compile_filter_predicate(&filter.predicate, &column_names)?;
 
                 // Create executable operator
                 let executable: Box<dyn IncrementalOperator> =
@@ -558,54 +650,58 @@ impl DbspCompiler {
                 // Compile aggregate expressions
                 let mut aggregate_functions = Vec::new();
                 for expr in &agg.aggr_expr {
-                    if let LogicalExpr::AggregateFunction { fun, args, .. } = expr {
+                    if let LogicalExpr::AggregateFunction { fun, args, distinct } = expr {
                         use crate::function::AggFunc;
                         use crate::incremental::operator::AggregateFunction;
 
+                        // Extract the column name from an argument
+                        let column_arg = |idx: usize| -> Result<Option<String>> {
+                            match args.get(idx) {
+                                Some(LogicalExpr::Column(col)) => Ok(Some(col.name.clone())),
+                                Some(_) => Err(LimboError::ParseError(
+                                    "Only column references are supported in aggregate functions for incremental views".to_string()
+                                )),
+                                None => Ok(None),
+                            }
+                        };
+                        let required_column_arg = |name: &str| -> Result<String> {
+                            column_arg(0)?.ok_or_else(|| {
+                                LimboError::ParseError(format!("{name} requires an argument"))
+                            })
+                        };
+
+                        if *distinct && !matches!(fun, AggFunc::Count) {
+                            return Err(LimboError::ParseError(
+                                format!("DISTINCT is only supported with COUNT in incremental views, got: {fun:?}")
+                            ));
+                        }
+
                         let agg_fn = match fun {
+                            AggFunc::Count if *distinct => {
+                                AggregateFunction::CountDistinct(required_column_arg("COUNT(DISTINCT)")?)
+                            }
                             AggFunc::Count | AggFunc::Count0 => {
                                 AggregateFunction::Count
                             }
-                            AggFunc::Sum => {
-                                if args.is_empty() {
-                                    return Err(LimboError::ParseError("SUM requires an argument".to_string()));
-                                }
-                                // Extract column name from the argument
-                                if let LogicalExpr::Column(col) = &args[0] {
-                                    AggregateFunction::Sum(col.name.clone())
-                                } else {
-                                    return Err(LimboError::ParseError(
-                                        "Only column references are supported in aggregate functions for incremental views".to_string()
-                                    ));
-                                }
-                            }
-                            AggFunc::Avg => {
-                                if args.is_empty() {
-                                    return Err(LimboError::ParseError("AVG requires an argument".to_string()));
-                                }
-                                if let LogicalExpr::Column(col) = &args[0] {
-                                    AggregateFunction::Avg(col.name.clone())
-                                } else {
-                                    return Err(LimboError::ParseError(
-                                        "Only column references are supported in aggregate functions for incremental views".to_string()
-                                    ));
-                                }
-                            }
-                            // MIN and MAX are not supported in incremental views due to storage overhead.
-                            // To correctly handle deletions, these operators would need to track all values
-                            // in each group, resulting in O(n) storage overhead. This is prohibitive for
-                            // large datasets. Alternative approaches like maintaining sorted indexes still
-                            // require O(n) storage. Until a more efficient solution is found, MIN/MAX
-                            // aggregations are not supported in materialized views.
-                            AggFunc::Min => {
-                                return Err(LimboError::ParseError(
-                                    "MIN aggregation is not supported in incremental materialized views due to O(n) storage overhead required for handling deletions".to_string()
-                                ));
-                            }
-                            AggFunc::Max => {
-                                return Err(LimboError::ParseError(
-                                    "MAX aggregation is not supported in incremental materialized views due to O(n) storage overhead required for handling deletions".to_string()
-                                ));
+                            AggFunc::Sum => AggregateFunction::Sum(required_column_arg("SUM")?),
+                            AggFunc::Avg => AggregateFunction::Avg(required_column_arg("AVG")?),
+                            AggFunc::Total => AggregateFunction::Total(required_column_arg("TOTAL")?),
+                            // MIN and MAX keep every value of each group, so that deleting the
+                            // current minimum or maximum can fall back to the next one
+                            AggFunc::Min => AggregateFunction::Min(required_column_arg("MIN")?),
+                            AggFunc::Max => AggregateFunction::Max(required_column_arg("MAX")?),
+                            AggFunc::GroupConcat => AggregateFunction::GroupConcat(
+                                required_column_arg("GROUP_CONCAT")?,
+                                column_arg(1)?,
+                            ),
+                            AggFunc::StringAgg => {
+                                let separator = column_arg(1)?.ok_or_else(|| {
+                                    LimboError::ParseError("STRING_AGG requires a separator".to_string())
+                                })?;
+                                AggregateFunction::GroupConcat(
+                                    required_column_arg("STRING_AGG")?,
+                                    Some(separator),
+                                )
                             }
                             _ => {
                                 return Err(LimboError::ParseError(
@@ -643,6 +739,71 @@ impl DbspCompiler {
                 );
                 Ok(node_id)
             }
+            LogicalPlan::Join(join) => {
+                // Compile both inputs first
+                let left_id = self.compile_plan(&join.left)?;
+                let right_id = self.compile_plan(&join.right)?;
+
+                let join_type = match join.join_type {
+                    logical::JoinType::Inner => JoinType::Inner,
+                    logical::JoinType::Left => JoinType::Left,
+                    other => {
+                        return Err(LimboError::ParseError(format!(
+                            "{other:?} JOIN is not supported in incremental views"
+                        )))
+                    }
+                };
+                if join.filter.is_some() {
+                    return Err(LimboError::ParseError(
+                        "Only equality conditions between the joined tables are supported in incremental views".to_string()
+                    ));
+                }
+
+                // Resolve the equi-join columns to their positions in each input
+                let left_schema = join.left.schema();
+                let right_schema = join.right.schema();
+                let mut left_key_indices = Vec::new();
+                let mut right_key_indices = Vec::new();
+                let mut dbsp_on = Vec::new();
+                for (left, right) in &join.on {
+                    let (LogicalExpr::Column(left_col), LogicalExpr::Column(right_col)) = (left, right) else {
+                        return Err(LimboError::ParseError(
+                            "Only column references are supported in join conditions for incremental views".to_string()
+                        ));
+                    };
+                    let (left_idx, _) = left_schema.find_column(&left_col.name).ok_or_else(|| {
+                        LimboError::ParseError(format!("Column '{left_col}' not found in join input"))
+                    })?;
+                    let (right_idx, _) = right_schema.find_column(&right_col.name).ok_or_else(|| {
+                        LimboError::ParseError(format!("Column '{right_col}' not found in join input"))
+                    })?;
+                    left_key_indices.push(left_idx);
+                    right_key_indices.push(right_idx);
+                    dbsp_on.push((
+                        DbspExpr::Column(left_col.name.clone()),
+                        DbspExpr::Column(right_col.name.clone()),
+                    ));
+                }
+
+                let executable: Box<dyn IncrementalOperator> = Box::new(JoinOperator::new(
+                    join_type.clone(),
+                    left_key_indices,
+                    right_key_indices,
+                    right_schema.column_count(),
+                )?);
+
+                // Create join node
+                let node_id = self.circuit.add_node(
+                    DbspOperator::Join {
+                        join_type,
+                        on: dbsp_on,
+                        schema: join.schema.clone(),
+                    },
+                    vec![left_id, right_id],
+                    Some(executable),
+                );
+                Ok(node_id)
+            }
             LogicalPlan::TableScan(scan) => {
                 // Create input node (no executable needed for input)
                 let node_id = self.circuit.add_node(
@@ -656,7 +817,7 @@ impl DbspCompiler {
                 Ok(node_id)
             }
             _ => Err(LimboError::ParseError(
-                format!("Unsupported operator in DBSP compiler: only Filter, Projection and Aggregate are supported, got: {:?}",
+                format!("Unsupported operator in DBSP compiler: only Filter, Projection, Aggregate and Join are supported, got: {:?}",
                     match plan {
                         LogicalPlan::Sort(_) => "Sort",
                         LogicalPlan::Limit(_) => "Limit",
@@ -761,13 +922,115 @@ impl DbspCompiler {
             LogicalExpr::Literal(val) => {
                 let lit = match val {
                     Value::Integer(i) => ast::Literal::Numeric(i.to_string()),
-                    Value::Float(f) => ast::Literal::Numeric(f.to_string()),
-                    Value::Text(t) => ast::Literal::String(t.to_string()),
-                    Value::Blob(b) => ast::Literal::Blob(format!("{b:?}")),
+                    // Debug formatting keeps the decimal point, so the literal stays a float
+                    Value::Float(f) => ast::Literal::Numeric(format!("{f:?}")),
+                    Value::Text(t) => ast::Literal::String(Self::string_literal(t.as_str())),
+                    Value::Blob(b) => {
+                        ast::Literal::Blob(b.iter().map(|byte| format!("{byte:02X}")).collect())
+                    }
                     Value::Null => ast::Literal::Null,
                 };
                 Ok(ast::Expr::Literal(lit))
             }
+            LogicalExpr::UnaryExpr { op, expr } => Ok(ast::Expr::Unary(
+                *op,
+                Box::new(Self::logical_to_ast_expr(expr)?),
+            )),
+            LogicalExpr::Case {
+                expr,
+                when_then,
+                else_expr,
+            } => {
+                let base = match expr {
+                    Some(expr) => Some(Box::new(Self::logical_to_ast_expr(expr)?)),
+                    None => None,
+                };
+                let when_then_pairs = when_then
+                    .iter()
+                    .map(|(when, then)| {
+                        Ok((
+                            Box::new(Self::logical_to_ast_expr(when)?),
+                            Box::new(Self::logical_to_ast_expr(then)?),
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let else_expr = match else_expr {
+                    Some(expr) => Some(Box::new(Self::logical_to_ast_expr(expr)?)),
+                    None => None,
+                };
+                Ok(ast::Expr::Case {
+                    base,
+                    when_then_pairs,
+                    else_expr,
+                })
+            }
+            LogicalExpr::InList {
+                expr,
+                list,
+                negated,
+            } => Ok(ast::Expr::InList {
+                lhs: Box::new(Self::logical_to_ast_expr(expr)?),
+                not: *negated,
+                rhs: list
+                    .iter()
+                    .map(|item| Ok(Box::new(Self::logical_to_ast_expr(item)?)))
+                    .collect::<Result<Vec<_>>>()?,
+            }),
+            LogicalExpr::IsNull { expr, negated } => {
+                let expr = Box::new(Self::logical_to_ast_expr(expr)?);
+                if *negated {
+                    Ok(ast::Expr::NotNull(expr))
+                } else {
+                    Ok(ast::Expr::IsNull(expr))
+                }
+            }
+            LogicalExpr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                // BETWEEN is rewritten by the optimizer before translation, so expand it here
+                let expr = Self::logical_to_ast_expr(expr)?;
+                let between = ast::Expr::Binary(
+                    Box::new(ast::Expr::Binary(
+                        Box::new(expr.clone()),
+                        ast::Operator::GreaterEquals,
+                        Box::new(Self::logical_to_ast_expr(low)?),
+                    )),
+                    ast::Operator::And,
+                    Box::new(ast::Expr::Binary(
+                        Box::new(expr),
+                        ast::Operator::LessEquals,
+                        Box::new(Self::logical_to_ast_expr(high)?),
+                    )),
+                );
+                if *negated {
+                    Ok(ast::Expr::Unary(
+                        ast::UnaryOperator::Not,
+                        Box::new(ast::Expr::Parenthesized(vec![Box::new(between)])),
+                    ))
+                } else {
+                    Ok(between)
+                }
+            }
+            LogicalExpr::Like {
+                expr,
+                op,
+                pattern,
+                escape,
+                negated,
+            } => Ok(ast::Expr::Like {
+                lhs: Box::new(Self::logical_to_ast_expr(expr)?),
+                not: *negated,
+                op: *op,
+                rhs: Box::new(Self::logical_to_ast_expr(pattern)?),
+                escape: escape.map(|c| {
+                    Box::new(ast::Expr::Literal(ast::Literal::String(
+                        Self::string_literal(&c.to_string()),
+                    )))
+                }),
+            }),
             LogicalExpr::BinaryExpr { left, op, right } => {
                 let left_expr = Self::logical_to_ast_expr(left)?;
                 let right_expr = Self::logical_to_ast_expr(right)?;
@@ -811,6 +1074,9 @@ impl DbspCompiler {
                     crate::function::AggFunc::Avg => "AVG",
                     crate::function::AggFunc::Min => "MIN",
                     crate::function::AggFunc::Max => "MAX",
+                    crate::function::AggFunc::Total => "TOTAL",
+                    crate::function::AggFunc::GroupConcat => "GROUP_CONCAT",
+                    crate::function::AggFunc::StringAgg => "STRING_AGG",
                     _ => {
                         return Err(LimboError::ParseError(format!(
                             "Unsupported aggregate function: {fun:?}"
@@ -839,8 +1105,19 @@ impl DbspCompiler {
         }
     }
 
+    /// Quote a string as a SQL string literal
+    fn string_literal(s: &str) -> String {
+        format!("'{}'", s.replace('\'', "''"))
+    }
+
     /// Compile a logical expression to a FilterPredicate for execution
-    fn compile_filter_predicate(expr: &LogicalExpr) -> Result<FilterPredicate> {
+    ///
+    /// Comparisons of a column with a literal are evaluated directly. Anything else is compiled
+    /// to a program, like the expressions of projections.
+    fn compile_filter_predicate(
+        expr: &LogicalExpr,
+        input_column_names: &[String],
+    ) -> Result<FilterPredicate> {
         match expr {
             LogicalExpr::BinaryExpr { left, op, right } => {
                 // Extract column name and value for simple predicates
@@ -874,8 +1151,10 @@ impl DbspCompiler {
                         }),
                         BinaryOperator::And => {
                             // Handle AND of two predicates
-                            let left_pred = Self::compile_filter_predicate(left)?;
-                            let right_pred = Self::compile_filter_predicate(right)?;
+                            let left_pred =
+                                Self::compile_filter_predicate(left, input_column_names)?;
+                            let right_pred =
+                                Self::compile_filter_predicate(right, input_column_names)?;
                             Ok(FilterPredicate::And(
                                 Box::new(left_pred),
                                 Box::new(right_pred),
@@ -883,21 +1162,21 @@ impl DbspCompiler {
                         }
                         BinaryOperator::Or => {
                             // Handle OR of two predicates
-                            let left_pred = Self::compile_filter_predicate(left)?;
-                            let right_pred = Self::compile_filter_predicate(right)?;
+                            let left_pred =
+                                Self::compile_filter_predicate(left, input_column_names)?;
+                            let right_pred =
+                                Self::compile_filter_predicate(right, input_column_names)?;
                             Ok(FilterPredicate::Or(
                                 Box::new(left_pred),
                                 Box::new(right_pred),
                             ))
                         }
-                        _ => Err(LimboError::ParseError(format!(
-                            "Unsupported operator in filter: {op:?}"
-                        ))),
+                        _ => Self::compile_filter_expression(expr, input_column_names),
                     }
                 } else if matches!(op, BinaryOperator::And | BinaryOperator::Or) {
                     // Handle logical operators
-                    let left_pred = Self::compile_filter_predicate(left)?;
-                    let right_pred = Self::compile_filter_predicate(right)?;
+                    let left_pred = Self::compile_filter_predicate(left, input_column_names)?;
+                    let right_pred = Self::compile_filter_predicate(right, input_column_names)?;
                     match op {
                         BinaryOperator::And => Ok(FilterPredicate::And(
                             Box::new(left_pred),
@@ -910,16 +1189,38 @@ impl DbspCompiler {
                         _ => unreachable!(),
                     }
                 } else {
-                    Err(LimboError::ParseError(
-                        "Filter predicate must be column op value".to_string(),
-                    ))
+                    Self::compile_filter_expression(expr, input_column_names)
                 }
             }
-            _ => Err(LimboError::ParseError(format!(
-                "Unsupported filter expression: {expr:?}"
-            ))),
+            _ => Self::compile_filter_expression(expr, input_column_names),
         }
     }
+
+    /// Compile a filter condition to a program evaluated on each row
+    fn compile_filter_expression(
+        expr: &LogicalExpr,
+        input_column_names: &[String],
+    ) -> Result<FilterPredicate> {
+        use turso_parser::ast;
+
+        // Rows are filtered as they change, so the condition must only depend on the row
+        let ast_expr = Self::logical_to_ast_expr(expr)?;
+        walk_expr(&ast_expr, &mut |expr: &ast::Expr| -> Result<WalkControl> {
+            if let ast::Expr::FunctionCall { name, args, .. } = expr {
+                if let Ok(func) = Func::resolve_function(name.as_str(), args.len()) {
+                    if !func.is_deterministic() {
+                        return Err(LimboError::ParseError(format!(
+                            "non-deterministic function {name}() prohibited in materialized views"
+                        )));
+                    }
+                }
+            }
+            Ok(WalkControl::Continue)
+        })?;
+
+        let (compiled, _) = Self::compile_expression(expr, input_column_names)?;
+        Ok(FilterPredicate::Expression(compiled))
+    }
 }
 
 #[cfg(test)]
@@ -953,6 +1254,7 @@ mod tests {
                         unique: false,
                         collation: None,
                         hidden: false,
+                        generated: None,
                     },
                     SchemaColumn {
                         name: Some("name".to_string()),
@@ -965,6 +1267,7 @@ mod tests {
                         unique: false,
                         collation: None,
                         hidden: false,
+                        generated: None,
                     },
                     SchemaColumn {
                         name: Some("age".to_string()),
@@ -977,11 +1280,15 @@ mod tests {
                         unique: false,
                         collation: None,
                         hidden: false,
+                        generated: None,
                     },
                 ],
                 has_rowid: true,
                 is_strict: false,
                 unique_sets: None,
+                foreign_keys: vec![],
+                check_constraints: vec![],
+                temporary: false,
             };
             schema.add_btree_table(Arc::new(users_table));
             schema
@@ -1282,6 +1589,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_execute_filter_with_general_expressions() {
+        let cases = [
+            ("SELECT * FROM users WHERE name LIKE 'a%'", vec!["Alice"]),
+            ("SELECT * FROM users WHERE name GLOB 'B*'", vec!["Bob"]),
+            (
+                "SELECT * FROM users WHERE age IN (17, 30)",
+                vec!["Bob", "Charlie"],
+            ),
+            (
+                "SELECT * FROM users WHERE age NOT IN (17, 30)",
+                vec!["Alice"],
+            ),
+            ("SELECT * FROM users WHERE age IS NULL", vec!["Dave"]),
+            (
+                "SELECT * FROM users WHERE age BETWEEN 18 AND 30",
+                vec!["Alice", "Charlie"],
+            ),
+            (
+                "SELECT * FROM users WHERE age NOT BETWEEN 18 AND 29",
+                vec!["Bob", "Charlie"],
+            ),
+            ("SELECT * FROM users WHERE length(name) = 3", vec!["Bob"]),
+            (
+                "SELECT * FROM users WHERE id < age",
+                vec!["Alice", "Bob", "Charlie"],
+            ),
+            (
+                "SELECT * FROM users WHERE 20 < age AND name != 'Alice'",
+                vec!["Charlie"],
+            ),
+            (
+                "SELECT * FROM users WHERE CASE WHEN age > 20 THEN 1 ELSE id = 2 END",
+                vec!["Alice", "Bob", "Charlie"],
+            ),
+        ];
+
+        for (sql, expected) in cases {
+            let mut circuit = compile_sql!(sql);
+
+            let mut input_delta = Delta::new();
+            for (id, name, age) in [
+                (1, "Alice", Value::Integer(25)),
+                (2, "Bob", Value::Integer(17)),
+                (3, "Charlie", Value::Integer(30)),
+                (4, "Dave", Value::Null),
+            ] {
+                input_delta.insert(id, vec![Value::Integer(id), Value::Text(name.into()), age]);
+            }
+            let mut inputs = HashMap::new();
+            inputs.insert("users".to_string(), input_delta);
+
+            let result = circuit.initialize(inputs).unwrap();
+            let mut names: Vec<String> = result
+                .changes
+                .iter()
+                .map(|(row, weight)| {
+                    assert_eq!(*weight, 1);
+                    row.values[1].to_string()
+                })
+                .collect();
+            names.sort();
+            assert_eq!(names, expected, "unexpected rows for {sql}");
+        }
+    }
+
+    #[test]
+    fn test_filter_rejects_non_deterministic_functions() {
+        let schema = test_schema!();
+        let sql = "SELECT * FROM users WHERE age > random()";
+        let mut parser = Parser::new(sql.as_bytes());
+        let ast::Cmd::Stmt(stmt) = parser.next().unwrap().unwrap() else {
+            panic!("Only SQL statements are supported");
+        };
+        let logical_plan = LogicalPlanBuilder::new(&schema)
+            .build_statement(&stmt)
+            .unwrap();
+        assert!(DbspCompiler::new().compile(&logical_plan).is_err());
+    }
+
     #[test]
     fn test_simple_column_projection() {
         let mut circuit = compile_sql!("SELECT name, age FROM users");
@@ -1445,6 +1832,81 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_min_max_count_distinct_aggregation() {
+        let mut circuit = compile_sql!(
+            "SELECT name, MIN(age), MAX(age), COUNT(DISTINCT age) FROM users GROUP BY name"
+        );
+
+        let mut input_delta = Delta::new();
+        input_delta.insert(
+            1,
+            vec![
+                Value::Integer(1),
+                Value::Text("Alice".into()),
+                Value::Integer(25),
+            ],
+        );
+        input_delta.insert(
+            2,
+            vec![
+                Value::Integer(2),
+                Value::Text("Alice".into()),
+                Value::Integer(30),
+            ],
+        );
+        input_delta.insert(
+            3,
+            vec![
+                Value::Integer(3),
+                Value::Text("Alice".into()),
+                Value::Integer(30),
+            ],
+        );
+
+        let mut inputs = HashMap::new();
+        inputs.insert("users".to_string(), input_delta);
+        let result = circuit.initialize(inputs).unwrap();
+
+        assert_eq!(result.changes.len(), 1);
+        assert_eq!(
+            result.changes[0].0.values,
+            vec![
+                Value::Text("Alice".into()),
+                Value::Integer(25),
+                Value::Integer(30),
+                Value::Integer(2),
+            ]
+        );
+
+        // Deleting the minimum makes the next smallest value the new one
+        let mut delete_delta = Delta::new();
+        delete_delta.delete(
+            1,
+            vec![
+                Value::Integer(1),
+                Value::Text("Alice".into()),
+                Value::Integer(25),
+            ],
+        );
+        let mut inputs = HashMap::new();
+        inputs.insert("users".to_string(), delete_delta);
+        let result = circuit.execute(inputs.clone(), DeltaSet::empty()).unwrap();
+        circuit.commit(inputs).unwrap();
+
+        let inserted: Vec<_> = result.changes.iter().filter(|(_, w)| *w > 0).collect();
+        assert_eq!(inserted.len(), 1);
+        assert_eq!(
+            inserted[0].0.values,
+            vec![
+                Value::Text("Alice".into()),
+                Value::Integer(30),
+                Value::Integer(30),
+                Value::Integer(1),
+            ]
+        );
+    }
+
     #[test]
     fn test_aggregation_without_group_by() {
         // Test aggregation without GROUP BY - should produce a single row
@@ -2336,6 +2798,7 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
                 SchemaColumn {
                     name: Some("amount".to_string()),
@@ -2348,11 +2811,15 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
             ],
             has_rowid: true,
             is_strict: false,
             unique_sets: None,
+            foreign_keys: vec![],
+            check_constraints: vec![],
+            temporary: false,
         };
         schema.add_btree_table(Arc::new(sales_table));
 
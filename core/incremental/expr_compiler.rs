@@ -78,6 +78,58 @@ fn transform_expr_for_dbsp(expr: &Expr, input_column_names: &[String]) -> Expr {
                 .map(|e| Box::new(transform_expr_for_dbsp(e, input_column_names)))
                 .collect(),
         ),
+        Expr::Like {
+            lhs,
+            not,
+            op,
+            rhs,
+            escape,
+        } => Expr::Like {
+            lhs: Box::new(transform_expr_for_dbsp(lhs, input_column_names)),
+            not: *not,
+            op: *op,
+            rhs: Box::new(transform_expr_for_dbsp(rhs, input_column_names)),
+            escape: escape
+                .as_ref()
+                .map(|e| Box::new(transform_expr_for_dbsp(e, input_column_names))),
+        },
+        Expr::InList { lhs, not, rhs } => Expr::InList {
+            lhs: Box::new(transform_expr_for_dbsp(lhs, input_column_names)),
+            not: *not,
+            rhs: rhs
+                .iter()
+                .map(|e| Box::new(transform_expr_for_dbsp(e, input_column_names)))
+                .collect(),
+        },
+        Expr::IsNull(operand) => Expr::IsNull(Box::new(transform_expr_for_dbsp(
+            operand,
+            input_column_names,
+        ))),
+        Expr::NotNull(operand) => Expr::NotNull(Box::new(transform_expr_for_dbsp(
+            operand,
+            input_column_names,
+        ))),
+        Expr::Case {
+            base,
+            when_then_pairs,
+            else_expr,
+        } => Expr::Case {
+            base: base
+                .as_ref()
+                .map(|e| Box::new(transform_expr_for_dbsp(e, input_column_names))),
+            when_then_pairs: when_then_pairs
+                .iter()
+                .map(|(when, then)| {
+                    (
+                        Box::new(transform_expr_for_dbsp(when, input_column_names)),
+                        Box::new(transform_expr_for_dbsp(then, input_column_names)),
+                    )
+                })
+                .collect(),
+            else_expr: else_expr
+                .as_ref()
+                .map(|e| Box::new(transform_expr_for_dbsp(e, input_column_names))),
+        },
         // For other expression types, keep as is
         _ => expr.clone(),
     }
@@ -385,6 +437,17 @@ impl CompiledExpression {
         })
     }
 
+    /// Execute the compiled expression with the pager of the connection it was compiled with
+    pub fn evaluate(&self, values: &[Value]) -> Result<Value> {
+        match &self.executor {
+            ExpressionExecutor::Trivial(trivial) => Ok(trivial.evaluate(values)),
+            ExpressionExecutor::Compiled(program) => {
+                let pager = program.connection.pager.borrow().clone();
+                self.execute(values, pager)
+            }
+        }
+    }
+
     /// Execute the compiled expression with the given input values
     pub fn execute(&self, values: &[Value], pager: Rc<Pager>) -> Result<Value> {
         match &self.executor {
@@ -3,4 +3,5 @@ pub mod dbsp;
 pub mod expr_compiler;
 pub mod hashable_row;
 pub mod operator;
+pub mod persistence;
 pub mod view;
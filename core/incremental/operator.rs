@@ -6,7 +6,7 @@ use crate::incremental::expr_compiler::CompiledExpression;
 use crate::incremental::hashable_row::HashableRow;
 use crate::types::Text;
 use crate::{Connection, Database, SymbolTable, Value};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{self, Debug, Display};
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -232,6 +232,8 @@ pub enum FilterPredicate {
     And(Box<FilterPredicate>, Box<FilterPredicate>),
     /// Logical OR of two predicates
     Or(Box<FilterPredicate>, Box<FilterPredicate>),
+    /// Any other expression, compiled like the projections. Rows pass when it is true.
+    Expression(CompiledExpression),
     /// No predicate (accept all rows)
     None,
 }
@@ -353,7 +355,7 @@ pub struct ProjectColumn {
     pub compiled: CompiledExpression,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum JoinType {
     Inner,
     Left,
@@ -363,17 +365,30 @@ pub enum JoinType {
 #[derive(Debug, Clone, PartialEq)]
 pub enum AggregateFunction {
     Count,
+    CountDistinct(String),
     Sum(String),
     Avg(String),
-    // MIN and MAX are not supported - see comment in compiler.rs for explanation
+    Total(String),
+    Min(String),
+    Max(String),
+    /// GROUP_CONCAT over a column, with the column holding the separator if one was given
+    GroupConcat(String, Option<String>),
 }
 
 impl Display for AggregateFunction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AggregateFunction::Count => write!(f, "COUNT(*)"),
+            AggregateFunction::CountDistinct(col) => write!(f, "COUNT(DISTINCT {col})"),
             AggregateFunction::Sum(col) => write!(f, "SUM({col})"),
             AggregateFunction::Avg(col) => write!(f, "AVG({col})"),
+            AggregateFunction::Total(col) => write!(f, "TOTAL({col})"),
+            AggregateFunction::Min(col) => write!(f, "MIN({col})"),
+            AggregateFunction::Max(col) => write!(f, "MAX({col})"),
+            AggregateFunction::GroupConcat(col, None) => write!(f, "GROUP_CONCAT({col})"),
+            AggregateFunction::GroupConcat(col, Some(separator)) => {
+                write!(f, "GROUP_CONCAT({col}, {separator})")
+            }
         }
     }
 }
@@ -399,8 +414,12 @@ impl AggregateFunction {
                     AggFunc::Count | AggFunc::Count0 => Some(AggregateFunction::Count),
                     AggFunc::Sum => input_column.map(AggregateFunction::Sum),
                     AggFunc::Avg => input_column.map(AggregateFunction::Avg),
-                    // MIN and MAX are not supported in incremental views - see compiler.rs
-                    AggFunc::Min | AggFunc::Max => None,
+                    AggFunc::Total => input_column.map(AggregateFunction::Total),
+                    AggFunc::Min => input_column.map(AggregateFunction::Min),
+                    AggFunc::Max => input_column.map(AggregateFunction::Max),
+                    AggFunc::GroupConcat => {
+                        input_column.map(|col| AggregateFunction::GroupConcat(col, None))
+                    }
                     _ => None, // Other aggregate functions not yet supported in DBSP
                 }
             }
@@ -409,6 +428,22 @@ impl AggregateFunction {
     }
 }
 
+/// Input deltas for an operator that consumes two inputs, like a join
+#[derive(Debug, Clone, Default)]
+pub struct DeltaPair {
+    pub left: Delta,
+    pub right: Delta,
+}
+
+impl DeltaPair {
+    pub fn new(left: Delta, right: Delta) -> Self {
+        Self { left, right }
+    }
+}
+
+/// Persistent state entries of an operator, as (key, state) pairs
+pub type StateEntries = Vec<(Vec<Value>, Vec<Value>)>;
+
 /// Operator DAG (Directed Acyclic Graph)
 /// Base trait for incremental operators
 pub trait IncrementalOperator: Debug {
@@ -433,6 +468,91 @@ pub trait IncrementalOperator: Debug {
 
     /// Set computation tracker
     fn set_tracker(&mut self, tracker: Arc<Mutex<ComputationTracker>>);
+
+    /// Initialize an operator with two inputs. Operators with a single input only see the left
+    /// side.
+    fn initialize_pair(&mut self, data: DeltaPair) {
+        self.initialize(data.left);
+    }
+
+    /// Like `eval`, for operators with two inputs
+    fn eval_pair(&self, deltas: DeltaPair) -> Delta {
+        self.eval(deltas.left, None)
+    }
+
+    /// Like `commit`, for operators with two inputs
+    fn commit_pair(&mut self, deltas: DeltaPair) -> Delta {
+        self.commit(deltas.left)
+    }
+
+    /// Take the entries of the operator's persistent state changed since the last call, as
+    /// (key, state) pairs. A `None` state means the entry was removed. Operators that can
+    /// rebuild their state from their inputs have nothing to persist.
+    fn take_state_changes(&mut self) -> Vec<(Vec<Value>, Option<Vec<Value>>)> {
+        Vec::new()
+    }
+
+    /// Restore the persistent state of the operator from the entries returned by
+    /// `take_state_changes`
+    fn restore_state(&mut self, _entries: StateEntries) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads back the flat list of values an operator state was encoded into
+struct StateReader<I: Iterator<Item = Value>> {
+    values: I,
+}
+
+impl<I: Iterator<Item = Value>> StateReader<I> {
+    fn new(values: impl IntoIterator<Item = Value, IntoIter = I>) -> Self {
+        Self {
+            values: values.into_iter(),
+        }
+    }
+
+    fn value(&mut self) -> crate::Result<Value> {
+        self.values
+            .next()
+            .ok_or_else(|| crate::LimboError::Corrupt("truncated operator state".to_string()))
+    }
+
+    fn integer(&mut self) -> crate::Result<i64> {
+        match self.value()? {
+            Value::Integer(i) => Ok(i),
+            value => Err(crate::LimboError::Corrupt(format!(
+                "expected an integer in operator state, got {value:?}"
+            ))),
+        }
+    }
+
+    fn float(&mut self) -> crate::Result<f64> {
+        match self.value()? {
+            Value::Float(f) => Ok(f),
+            Value::Integer(i) => Ok(i as f64),
+            value => Err(crate::LimboError::Corrupt(format!(
+                "expected a number in operator state, got {value:?}"
+            ))),
+        }
+    }
+
+    fn text(&mut self) -> crate::Result<String> {
+        match self.value()? {
+            Value::Text(text) => Ok(text.as_str().to_string()),
+            value => Err(crate::LimboError::Corrupt(format!(
+                "expected a column name in operator state, got {value:?}"
+            ))),
+        }
+    }
+
+    fn finish(mut self) -> crate::Result<()> {
+        match self.values.next() {
+            None => Ok(()),
+            Some(_) => Err(crate::LimboError::Corrupt(
+                "trailing values in operator state".to_string(),
+            )),
+        }
+    }
 }
 
 /// Filter operator - filters rows based on predicate
@@ -544,6 +664,10 @@ impl FilterOperator {
                     FilterOperator::new((**right).clone(), self.column_names.clone());
                 left_filter.evaluate_predicate(values) || right_filter.evaluate_predicate(values)
             }
+            FilterPredicate::Expression(compiled) => compiled
+                .evaluate(values)
+                .expect("Failed to execute compiled expression for the Filter operator")
+                .exec_if(false, false),
         }
     }
 }
@@ -1054,11 +1178,16 @@ pub struct AggregateOperator {
     group_states: HashMap<String, AggregateState>,
     // Map to keep track of actual group key values for output
     group_key_values: HashMap<String, Vec<Value>>,
+    // Groups changed since their state was last persisted, with their key values
+    dirty_groups: HashMap<String, Vec<Value>>,
     // Current output state as a Delta
     current_state: Delta,
     tracker: Option<Arc<Mutex<ComputationTracker>>>,
 }
 
+/// The values and separators of the rows of a GROUP_CONCAT, by input rowid
+type ConcatRows = BTreeMap<i64, Vec<(Value, Value)>>;
+
 /// State for a single group's aggregates
 #[derive(Debug, Clone)]
 struct AggregateState {
@@ -1068,8 +1197,15 @@ struct AggregateState {
     sums: HashMap<String, f64>,
     // For AVG: column_name -> (sum, count) for computing average
     avgs: HashMap<String, (f64, i64)>,
-    // MIN/MAX are not supported - they require O(n) storage overhead for handling deletions
-    // correctly. See comment in apply_delta() for details.
+    // For TOTAL: column_name -> total
+    totals: HashMap<String, f64>,
+    // For MIN, MAX and COUNT(DISTINCT): column_name -> number of occurrences of each non-NULL
+    // value. Deleting the current MIN or MAX needs the remaining values of the group, so they
+    // are all kept.
+    value_counts: HashMap<String, BTreeMap<Value, isize>>,
+    // For GROUP_CONCAT: (column_name, separator column_name) -> input rowid -> the values and
+    // separators of the rows with that rowid. Values are concatenated in rowid order.
+    concat_values: HashMap<(String, Option<String>), ConcatRows>,
 }
 
 impl AggregateState {
@@ -1078,12 +1214,47 @@ impl AggregateState {
             count: 0,
             sums: HashMap::new(),
             avgs: HashMap::new(),
+            totals: HashMap::new(),
+            value_counts: HashMap::new(),
+            concat_values: HashMap::new(),
+        }
+    }
+
+    /// Add `weight` occurrences of a value to a multiset, dropping it once none are left
+    fn update_value_count(counts: &mut BTreeMap<Value, isize>, value: &Value, weight: isize) {
+        let count = counts.entry(value.clone()).or_insert(0);
+        *count += weight;
+        if *count <= 0 {
+            counts.remove(value);
+        }
+    }
+
+    /// Add or remove the value and separator of a row for GROUP_CONCAT
+    fn update_concat_values(
+        rows: &mut ConcatRows,
+        rowid: i64,
+        entry: (Value, Value),
+        weight: isize,
+    ) {
+        let entries = rows.entry(rowid).or_default();
+        if weight > 0 {
+            entries.extend(std::iter::repeat_n(entry, weight as usize));
+        } else {
+            for _ in 0..weight.unsigned_abs() {
+                if let Some(pos) = entries.iter().position(|e| *e == entry) {
+                    entries.remove(pos);
+                }
+            }
+        }
+        if entries.is_empty() {
+            rows.remove(&rowid);
         }
     }
 
     /// Apply a delta to this aggregate state
     fn apply_delta(
         &mut self,
+        rowid: i64,
         values: &[Value],
         weight: isize,
         aggregates: &[AggregateFunction],
@@ -1092,6 +1263,33 @@ impl AggregateState {
         // Update COUNT
         self.count += weight as i64;
 
+        let column_value = |col_name: &String| {
+            column_names
+                .iter()
+                .position(|c| c == col_name)
+                .and_then(|idx| values.get(idx))
+        };
+
+        // MIN, MAX and COUNT(DISTINCT) over the same column share its multiset of values, so
+        // each column is only updated once
+        let mut counted_columns: Vec<&String> = Vec::new();
+        for agg in aggregates {
+            if let AggregateFunction::Min(col_name)
+            | AggregateFunction::Max(col_name)
+            | AggregateFunction::CountDistinct(col_name) = agg
+            {
+                if !counted_columns.contains(&col_name) {
+                    counted_columns.push(col_name);
+                }
+            }
+        }
+        for col_name in counted_columns {
+            let counts = self.value_counts.entry(col_name.clone()).or_default();
+            if let Some(val) = column_value(col_name).filter(|v| **v != Value::Null) {
+                Self::update_value_count(counts, val, weight);
+            }
+        }
+
         // Update other aggregates
         for agg in aggregates {
             match agg {
@@ -1126,6 +1324,35 @@ impl AggregateState {
                         }
                     }
                 }
+                AggregateFunction::Total(col_name) => {
+                    let num_val = match column_value(col_name) {
+                        Some(Value::Integer(i)) => *i as f64,
+                        Some(Value::Float(f)) => *f,
+                        _ => 0.0,
+                    };
+                    *self.totals.entry(col_name.clone()).or_insert(0.0) += num_val * weight as f64;
+                }
+                AggregateFunction::GroupConcat(col_name, separator_col) => {
+                    let Some(val) = column_value(col_name).filter(|v| **v != Value::Null) else {
+                        continue;
+                    };
+                    let rows = self
+                        .concat_values
+                        .entry((col_name.clone(), separator_col.clone()))
+                        .or_default();
+                    let separator = match separator_col {
+                        Some(separator_col) => {
+                            column_value(separator_col).cloned().unwrap_or(Value::Null)
+                        }
+                        None => Value::build_text(","),
+                    };
+                    Self::update_concat_values(rows, rowid, (val.clone(), separator), weight);
+                }
+                AggregateFunction::Min(_)
+                | AggregateFunction::Max(_)
+                | AggregateFunction::CountDistinct(_) => {
+                    // Already handled above
+                }
             }
         }
     }
@@ -1159,11 +1386,165 @@ impl AggregateState {
                         result.push(Value::Null);
                     }
                 }
+                AggregateFunction::Total(col_name) => {
+                    let total = self.totals.get(col_name).copied().unwrap_or(0.0);
+                    result.push(Value::Float(total));
+                }
+                AggregateFunction::Min(col_name) => {
+                    let min = self
+                        .value_counts
+                        .get(col_name)
+                        .and_then(|counts| counts.keys().next());
+                    result.push(min.cloned().unwrap_or(Value::Null));
+                }
+                AggregateFunction::Max(col_name) => {
+                    let max = self
+                        .value_counts
+                        .get(col_name)
+                        .and_then(|counts| counts.keys().next_back());
+                    result.push(max.cloned().unwrap_or(Value::Null));
+                }
+                AggregateFunction::CountDistinct(col_name) => {
+                    let count = self.value_counts.get(col_name).map_or(0, |c| c.len());
+                    result.push(Value::Integer(count as i64));
+                }
+                AggregateFunction::GroupConcat(col_name, separator_col) => {
+                    let key = (col_name.clone(), separator_col.clone());
+                    let mut concatenated: Option<String> = None;
+                    let entries = self.concat_values.get(&key).into_iter().flatten();
+                    for (value, separator) in entries.flat_map(|(_, entries)| entries) {
+                        match concatenated.as_mut() {
+                            // Like SQLite, a NULL separator is treated as an empty string
+                            Some(acc) => {
+                                if *separator != Value::Null {
+                                    acc.push_str(&separator.to_string());
+                                }
+                                acc.push_str(&value.to_string());
+                            }
+                            None => concatenated = Some(value.to_string()),
+                        }
+                    }
+                    result.push(concatenated.map_or(Value::Null, Value::build_text));
+                }
             }
         }
 
         result
     }
+
+    /// Encode the state as a flat list of values, each map being prefixed by its length
+    fn encode(&self) -> Vec<Value> {
+        let mut values = vec![Value::Integer(self.count)];
+
+        values.push(Value::Integer(self.sums.len() as i64));
+        for (col_name, sum) in &self.sums {
+            values.extend([Value::build_text(col_name), Value::Float(*sum)]);
+        }
+
+        values.push(Value::Integer(self.avgs.len() as i64));
+        for (col_name, (sum, count)) in &self.avgs {
+            values.extend([
+                Value::build_text(col_name),
+                Value::Float(*sum),
+                Value::Integer(*count),
+            ]);
+        }
+
+        values.push(Value::Integer(self.totals.len() as i64));
+        for (col_name, total) in &self.totals {
+            values.extend([Value::build_text(col_name), Value::Float(*total)]);
+        }
+
+        values.push(Value::Integer(self.value_counts.len() as i64));
+        for (col_name, counts) in &self.value_counts {
+            values.extend([
+                Value::build_text(col_name),
+                Value::Integer(counts.len() as i64),
+            ]);
+            for (value, count) in counts {
+                values.extend([value.clone(), Value::Integer(*count as i64)]);
+            }
+        }
+
+        values.push(Value::Integer(self.concat_values.len() as i64));
+        for ((col_name, separator_col), rows) in &self.concat_values {
+            values.extend([
+                Value::build_text(col_name),
+                separator_col
+                    .as_ref()
+                    .map_or(Value::Null, Value::build_text),
+                Value::Integer(rows.len() as i64),
+            ]);
+            for (rowid, entries) in rows {
+                values.extend([Value::Integer(*rowid), Value::Integer(entries.len() as i64)]);
+                for (value, separator) in entries {
+                    values.extend([value.clone(), separator.clone()]);
+                }
+            }
+        }
+
+        values
+    }
+
+    /// Decode a state encoded with `encode`
+    fn decode(values: Vec<Value>) -> crate::Result<Self> {
+        let mut reader = StateReader::new(values);
+        let mut state = Self::new();
+        state.count = reader.integer()?;
+
+        for _ in 0..reader.integer()? {
+            let col_name = reader.text()?;
+            state.sums.insert(col_name, reader.float()?);
+        }
+
+        for _ in 0..reader.integer()? {
+            let col_name = reader.text()?;
+            let sum = reader.float()?;
+            state.avgs.insert(col_name, (sum, reader.integer()?));
+        }
+
+        for _ in 0..reader.integer()? {
+            let col_name = reader.text()?;
+            state.totals.insert(col_name, reader.float()?);
+        }
+
+        for _ in 0..reader.integer()? {
+            let col_name = reader.text()?;
+            let counts = state.value_counts.entry(col_name).or_default();
+            for _ in 0..reader.integer()? {
+                let value = reader.value()?;
+                counts.insert(value, reader.integer()? as isize);
+            }
+        }
+
+        for _ in 0..reader.integer()? {
+            let col_name = reader.text()?;
+            let separator_col = match reader.value()? {
+                Value::Null => None,
+                Value::Text(text) => Some(text.as_str().to_string()),
+                value => {
+                    return Err(crate::LimboError::Corrupt(format!(
+                        "expected a column name in operator state, got {value:?}"
+                    )))
+                }
+            };
+            let rows = state
+                .concat_values
+                .entry((col_name, separator_col))
+                .or_default();
+            for _ in 0..reader.integer()? {
+                let rowid = reader.integer()?;
+                let entries = rows.entry(rowid).or_default();
+                for _ in 0..reader.integer()? {
+                    let value = reader.value()?;
+                    entries.push((value, reader.value()?));
+                }
+            }
+        }
+
+        reader.finish()?;
+        Ok(state)
+    }
 }
 
 impl AggregateOperator {
@@ -1178,6 +1559,7 @@ impl AggregateOperator {
             input_column_names,
             group_states: HashMap::new(),
             group_key_values: HashMap::new(),
+            dirty_groups: HashMap::new(),
             current_state: Delta::new(),
             tracker: None,
         }
@@ -1214,6 +1596,14 @@ impl AggregateOperator {
             .join(",")
     }
 
+    /// Row key of the output row of a group, derived from its group key so that it stays the
+    /// same across updates
+    fn group_result_key(group_key_str: &str) -> i64 {
+        group_key_str
+            .bytes()
+            .fold(0i64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as i64))
+    }
+
     /// Process a delta and update aggregate state incrementally
     pub fn process_delta(&mut self, delta: Delta) -> Delta {
         let mut output_delta = Delta::new();
@@ -1240,6 +1630,8 @@ impl AggregateOperator {
                     old_row.extend(state.to_values(&self.aggregates));
                     old_values.insert(group_key_str.clone(), old_row);
                 }
+                self.dirty_groups
+                    .insert(group_key_str.clone(), group_key.clone());
             }
 
             modified_groups.insert(group_key_str.clone());
@@ -1256,6 +1648,7 @@ impl AggregateOperator {
 
             // Apply the delta to the aggregate state
             state.apply_delta(
+                row.rowid,
                 &row.values,
                 *weight,
                 &self.aggregates,
@@ -1274,9 +1667,7 @@ impl AggregateOperator {
 
             // Generate a unique key for this group
             // We use a hash of the group key to ensure consistency
-            let result_key = group_key_str
-                .bytes()
-                .fold(0i64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as i64));
+            let result_key = Self::group_result_key(&group_key_str);
 
             // Emit retraction for old value if it existed
             if let Some(old_row_values) = old_values.get(&group_key_str) {
@@ -1372,6 +1763,7 @@ impl IncrementalOperator for AggregateOperator {
 
             // Apply the delta to the temporary aggregate state
             state.apply_delta(
+                row.rowid,
                 &row.values,
                 *weight,
                 &self.aggregates,
@@ -1387,9 +1779,7 @@ impl IncrementalOperator for AggregateOperator {
                 .unwrap_or_default();
 
             // Generate a unique key for this group
-            let result_key = group_key_str
-                .bytes()
-                .fold(0i64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as i64));
+            let result_key = Self::group_result_key(&group_key_str);
 
             // Emit retraction for old value if it existed
             if let Some(old_row_values) = old_values.get(&group_key_str) {
@@ -1428,6 +1818,396 @@ impl IncrementalOperator for AggregateOperator {
     fn set_tracker(&mut self, tracker: Arc<Mutex<ComputationTracker>>) {
         self.tracker = Some(tracker);
     }
+
+    fn take_state_changes(&mut self) -> Vec<(Vec<Value>, Option<Vec<Value>>)> {
+        // Groups are keyed by their group key values, and removed groups have no state
+        std::mem::take(&mut self.dirty_groups)
+            .into_iter()
+            .map(|(group_key_str, group_key)| {
+                let state = self.group_states.get(&group_key_str).map(|s| s.encode());
+                (group_key, state)
+            })
+            .collect()
+    }
+
+    fn restore_state(&mut self, entries: StateEntries) -> crate::Result<()> {
+        for (group_key, state) in entries {
+            let state = AggregateState::decode(state)?;
+            let group_key_str = Self::group_key_to_string(&group_key);
+
+            let mut output_values = group_key.clone();
+            output_values.extend(state.to_values(&self.aggregates));
+            let result_key = Self::group_result_key(&group_key_str);
+            self.current_state
+                .changes
+                .push((HashableRow::new(result_key, output_values), 1));
+
+            self.group_key_values
+                .insert(group_key_str.clone(), group_key);
+            self.group_states.insert(group_key_str, state);
+        }
+        Ok(())
+    }
+}
+
+/// Rows of one join input grouped by join key, with their weights
+type JoinIndex = HashMap<HashableRow, HashMap<HashableRow, isize>>;
+
+/// Join operator - incrementally maintains an equi-join of two inputs
+///
+/// Both inputs are indexed by join key, so a change on one side only looks up the rows of the
+/// other side that share its key: Δ(L ⋈ R) = ΔL ⋈ R + L ⋈ ΔR + ΔL ⋈ ΔR.
+///
+/// For LEFT joins, left rows without a match are emitted padded with NULLs. They are retracted
+/// when the first matching right row shows up, and emitted again when the last one goes away.
+#[derive(Debug)]
+pub struct JoinOperator {
+    join_type: JoinType,
+    // Positions of the join key columns in the left and right rows
+    left_key_indices: Vec<usize>,
+    right_key_indices: Vec<usize>,
+    // Number of columns of the right input, used to pad unmatched rows of a LEFT join
+    right_column_count: usize,
+    left_index: JoinIndex,
+    right_index: JoinIndex,
+    // Rows of each input whose weight changed since the indexes were last persisted
+    dirty_left: HashSet<HashableRow>,
+    dirty_right: HashSet<HashableRow>,
+    current_state: Delta,
+    tracker: Option<Arc<Mutex<ComputationTracker>>>,
+}
+
+impl JoinOperator {
+    pub fn new(
+        join_type: JoinType,
+        left_key_indices: Vec<usize>,
+        right_key_indices: Vec<usize>,
+        right_column_count: usize,
+    ) -> crate::Result<Self> {
+        if matches!(join_type, JoinType::Right) {
+            return Err(crate::LimboError::ParseError(
+                "RIGHT JOIN is not supported in incremental views".to_string(),
+            ));
+        }
+        if left_key_indices.is_empty() || left_key_indices.len() != right_key_indices.len() {
+            return Err(crate::LimboError::ParseError(
+                "Joins in incremental views require an equality condition between both tables"
+                    .to_string(),
+            ));
+        }
+        Ok(Self {
+            join_type,
+            left_key_indices,
+            right_key_indices,
+            right_column_count,
+            left_index: HashMap::new(),
+            right_index: HashMap::new(),
+            dirty_left: HashSet::new(),
+            dirty_right: HashSet::new(),
+            current_state: Delta::new(),
+            tracker: None,
+        })
+    }
+
+    /// Extract the join key of a row. Returns None if any key column is NULL, since NULL never
+    /// compares equal to anything.
+    fn extract_key(values: &[Value], indices: &[usize]) -> Option<HashableRow> {
+        let mut key = Vec::with_capacity(indices.len());
+        for idx in indices {
+            match values.get(*idx) {
+                None | Some(Value::Null) => return None,
+                Some(value) => key.push(value.clone()),
+            }
+        }
+        Some(HashableRow::new(0, key))
+    }
+
+    /// Row key of an output row. Unmatched rows of a LEFT join have no right row key.
+    fn joined_rowid(left_rowid: i64, right_rowid: Option<i64>) -> i64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        left_rowid.hash(&mut hasher);
+        right_rowid.hash(&mut hasher);
+        hasher.finish() as i64
+    }
+
+    fn joined_row(left: &HashableRow, right: &HashableRow) -> HashableRow {
+        let mut values = left.values.clone();
+        values.extend(right.values.iter().cloned());
+        HashableRow::new(Self::joined_rowid(left.rowid, Some(right.rowid)), values)
+    }
+
+    fn padded_row(&self, left: &HashableRow) -> HashableRow {
+        let mut values = left.values.clone();
+        values.extend(std::iter::repeat_n(Value::Null, self.right_column_count));
+        HashableRow::new(Self::joined_rowid(left.rowid, None), values)
+    }
+
+    fn record_lookup(&self) {
+        if let Some(tracker) = &self.tracker {
+            tracker.lock().unwrap().record_join_lookup();
+        }
+    }
+
+    /// Group the changes of a delta by join key, dropping rows with a NULL key
+    fn index_delta(delta: &Delta, indices: &[usize]) -> JoinIndex {
+        let mut index: JoinIndex = HashMap::new();
+        for (row, weight) in &delta.changes {
+            if let Some(key) = Self::extract_key(&row.values, indices) {
+                *index
+                    .entry(key)
+                    .or_default()
+                    .entry(row.clone())
+                    .or_insert(0) += weight;
+            }
+        }
+        index
+    }
+
+    fn total_weight(rows: Option<&HashMap<HashableRow, isize>>) -> isize {
+        rows.map_or(0, |rows| rows.values().sum())
+    }
+
+    /// Compute the output delta for the given input deltas, against the current state
+    fn compute(&self, deltas: &DeltaPair) -> Delta {
+        let mut output = Delta::new();
+        let left_delta = Self::index_delta(&deltas.left, &self.left_key_indices);
+        let right_delta = Self::index_delta(&deltas.right, &self.right_key_indices);
+
+        // ΔL ⋈ R and ΔL ⋈ ΔR
+        for (key, left_rows) in &left_delta {
+            self.record_lookup();
+            let matches = self
+                .right_index
+                .get(key)
+                .into_iter()
+                .chain(right_delta.get(key))
+                .flatten();
+            for (right, right_weight) in matches {
+                for (left, left_weight) in left_rows {
+                    output
+                        .changes
+                        .push((Self::joined_row(left, right), left_weight * right_weight));
+                }
+            }
+        }
+
+        // L ⋈ ΔR
+        for (key, right_rows) in &right_delta {
+            self.record_lookup();
+            let Some(left_rows) = self.left_index.get(key) else {
+                continue;
+            };
+            for (right, right_weight) in right_rows {
+                for (left, left_weight) in left_rows {
+                    output
+                        .changes
+                        .push((Self::joined_row(left, right), left_weight * right_weight));
+                }
+            }
+        }
+
+        if matches!(self.join_type, JoinType::Left) {
+            self.compute_unmatched(deltas, &left_delta, &right_delta, &mut output);
+        }
+
+        Self::order_output(output)
+    }
+
+    /// Emit the changes to the NULL-padded rows of a LEFT join
+    fn compute_unmatched(
+        &self,
+        deltas: &DeltaPair,
+        left_delta: &JoinIndex,
+        right_delta: &JoinIndex,
+        output: &mut Delta,
+    ) {
+        // Rows with a NULL key never match anything
+        for (row, weight) in &deltas.left.changes {
+            if Self::extract_key(&row.values, &self.left_key_indices).is_none() {
+                output.changes.push((self.padded_row(row), *weight));
+            }
+        }
+
+        let keys: HashSet<&HashableRow> = left_delta.keys().chain(right_delta.keys()).collect();
+        for key in keys {
+            let old_matches = Self::total_weight(self.right_index.get(key));
+            let new_matches = old_matches + Self::total_weight(right_delta.get(key));
+            let old_rows = self.left_index.get(key).into_iter().flatten();
+            let new_rows = left_delta.get(key).into_iter().flatten();
+            match (old_matches > 0, new_matches > 0) {
+                // Still unmatched: only the left changes show up
+                (false, false) => {
+                    for (row, weight) in new_rows {
+                        output.changes.push((self.padded_row(row), *weight));
+                    }
+                }
+                // Got its first match: retract the padded rows
+                (false, true) => {
+                    for (row, weight) in old_rows {
+                        output.changes.push((self.padded_row(row), -weight));
+                    }
+                }
+                // Lost its last match: emit the padded rows, including the new ones
+                (true, false) => {
+                    for (row, weight) in old_rows.chain(new_rows) {
+                        output.changes.push((self.padded_row(row), *weight));
+                    }
+                }
+                (true, true) => {}
+            }
+        }
+    }
+
+    /// Consolidate the output and put retractions first, so that an update of a row that keeps
+    /// its row key is applied as a delete followed by an insert
+    fn order_output(mut output: Delta) -> Delta {
+        output.consolidate();
+        output.changes.sort_by_key(|(_, weight)| *weight > 0);
+        output
+    }
+
+    fn apply_to_index(index: &mut JoinIndex, delta: JoinIndex) {
+        for (key, rows) in delta {
+            let entry = index.entry(key.clone()).or_default();
+            for (row, weight) in rows {
+                let row_weight = entry.entry(row.clone()).or_insert(0);
+                *row_weight += weight;
+                if *row_weight == 0 {
+                    entry.remove(&row);
+                }
+            }
+            if entry.is_empty() {
+                index.remove(&key);
+            }
+        }
+    }
+
+    /// Entries of the persistent state for the rows of one input: the key is the side (0 for
+    /// left, 1 for right), the row key and the row values, and the state is the row's weight
+    fn index_state_changes(
+        side: i64,
+        rows: HashSet<HashableRow>,
+        index: &JoinIndex,
+        indices: &[usize],
+    ) -> Vec<(Vec<Value>, Option<Vec<Value>>)> {
+        rows.into_iter()
+            .map(|row| {
+                let weight = Self::extract_key(&row.values, indices)
+                    .and_then(|key| index.get(&key))
+                    .and_then(|key_rows| key_rows.get(&row));
+                let mut key = vec![Value::Integer(side), Value::Integer(row.rowid)];
+                key.extend(row.values);
+                (key, weight.map(|w| vec![Value::Integer(*w as i64)]))
+            })
+            .collect()
+    }
+
+    fn process_delta(&mut self, deltas: DeltaPair) -> Delta {
+        let output = self.compute(&deltas);
+
+        let left_delta = Self::index_delta(&deltas.left, &self.left_key_indices);
+        let right_delta = Self::index_delta(&deltas.right, &self.right_key_indices);
+        self.dirty_left
+            .extend(left_delta.values().flat_map(|rows| rows.keys().cloned()));
+        self.dirty_right
+            .extend(right_delta.values().flat_map(|rows| rows.keys().cloned()));
+        Self::apply_to_index(&mut self.left_index, left_delta);
+        Self::apply_to_index(&mut self.right_index, right_delta);
+
+        self.current_state.merge(&output);
+        self.current_state.consolidate();
+        output
+    }
+}
+
+impl IncrementalOperator for JoinOperator {
+    fn initialize(&mut self, data: Delta) {
+        self.initialize_pair(DeltaPair::new(data, Delta::new()));
+    }
+
+    fn eval(&self, delta: Delta, uncommitted: Option<Delta>) -> Delta {
+        // A single delta is a change to the left input
+        let mut combined = delta;
+        if let Some(uncommitted) = uncommitted {
+            combined.merge(&uncommitted);
+        }
+        self.eval_pair(DeltaPair::new(combined, Delta::new()))
+    }
+
+    fn commit(&mut self, delta: Delta) -> Delta {
+        self.commit_pair(DeltaPair::new(delta, Delta::new()))
+    }
+
+    fn get_current_state(&self) -> Delta {
+        let mut consolidated = self.current_state.clone();
+        consolidated.consolidate();
+        consolidated
+    }
+
+    fn set_tracker(&mut self, tracker: Arc<Mutex<ComputationTracker>>) {
+        self.tracker = Some(tracker);
+    }
+
+    fn initialize_pair(&mut self, data: DeltaPair) {
+        let _ = self.process_delta(data);
+    }
+
+    fn eval_pair(&self, deltas: DeltaPair) -> Delta {
+        self.compute(&deltas)
+    }
+
+    fn commit_pair(&mut self, deltas: DeltaPair) -> Delta {
+        self.process_delta(deltas)
+    }
+
+    fn take_state_changes(&mut self) -> Vec<(Vec<Value>, Option<Vec<Value>>)> {
+        let mut changes = Self::index_state_changes(
+            0,
+            std::mem::take(&mut self.dirty_left),
+            &self.left_index,
+            &self.left_key_indices,
+        );
+        changes.extend(Self::index_state_changes(
+            1,
+            std::mem::take(&mut self.dirty_right),
+            &self.right_index,
+            &self.right_key_indices,
+        ));
+        changes
+    }
+
+    fn restore_state(&mut self, entries: StateEntries) -> crate::Result<()> {
+        // Replaying the rows of both inputs against empty indexes rebuilds the indexes and the
+        // output, without any change left to persist
+        let mut deltas = DeltaPair::new(Delta::new(), Delta::new());
+        for (key, state) in entries {
+            let mut key = StateReader::new(key);
+            let side = key.integer()?;
+            let rowid = key.integer()?;
+            let values: Vec<Value> = key.values.collect();
+            let mut state = StateReader::new(state);
+            let weight = state.integer()? as isize;
+            state.finish()?;
+            let delta = match side {
+                0 => &mut deltas.left,
+                1 => &mut deltas.right,
+                _ => {
+                    return Err(crate::LimboError::Corrupt(format!(
+                        "invalid join input {side} in operator state"
+                    )))
+                }
+            };
+            delta
+                .changes
+                .push((HashableRow::new(rowid, values), weight));
+        }
+        let _ = self.process_delta(deltas);
+        self.dirty_left.clear();
+        self.dirty_right.clear();
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -2319,6 +3099,160 @@ mod tests {
         assert_eq!(group_a.0.values[3], Value::Float(125.25)); // AVG = 125.25
     }
 
+    #[test]
+    fn test_min_max_aggregation_with_deletions() {
+        let aggregates = vec![
+            AggregateFunction::Min("value".to_string()),
+            AggregateFunction::Max("value".to_string()),
+        ];
+        let group_by = vec!["category".to_string()];
+        let input_columns = vec!["category".to_string(), "value".to_string()];
+
+        let mut agg = AggregateOperator::new(group_by, aggregates.clone(), input_columns);
+
+        // Initialize with data, including a NULL that MIN and MAX must ignore
+        let mut init_data = Delta::new();
+        init_data.insert(1, vec![Value::Text("A".into()), Value::Integer(10)]);
+        init_data.insert(2, vec![Value::Text("A".into()), Value::Integer(30)]);
+        init_data.insert(3, vec![Value::Text("A".into()), Value::Integer(20)]);
+        init_data.insert(4, vec![Value::Text("A".into()), Value::Null]);
+        agg.initialize(init_data);
+
+        let state = agg.get_current_state();
+        assert_eq!(state.changes.len(), 1);
+        assert_eq!(state.changes[0].0.values[1], Value::Integer(10)); // MIN
+        assert_eq!(state.changes[0].0.values[2], Value::Integer(30)); // MAX
+
+        // Delete the current minimum and maximum, the next values take over
+        let mut delete_delta = Delta::new();
+        delete_delta.delete(1, vec![Value::Text("A".into()), Value::Integer(10)]);
+        delete_delta.delete(2, vec![Value::Text("A".into()), Value::Integer(30)]);
+
+        let output = agg.eval(delete_delta.clone(), None);
+        agg.commit(delete_delta);
+
+        // The old extremes are retracted and the new ones emitted
+        assert_eq!(output.changes.len(), 2);
+        assert_eq!(output.changes[0].1, -1);
+        assert_eq!(output.changes[0].0.values[1], Value::Integer(10));
+        assert_eq!(output.changes[1].1, 1);
+        assert_eq!(output.changes[1].0.values[1], Value::Integer(20));
+        assert_eq!(output.changes[1].0.values[2], Value::Integer(20));
+
+        // Deleting the last non-NULL value leaves NULL for both
+        let mut delete_last = Delta::new();
+        delete_last.delete(3, vec![Value::Text("A".into()), Value::Integer(20)]);
+
+        let _ = agg.eval(delete_last.clone(), None);
+        agg.commit(delete_last);
+
+        let state = agg.get_current_state();
+        assert_eq!(state.changes.len(), 1);
+        assert_eq!(state.changes[0].0.values[1], Value::Null);
+        assert_eq!(state.changes[0].0.values[2], Value::Null);
+    }
+
+    #[test]
+    fn test_count_distinct_with_duplicates() {
+        let aggregates = vec![
+            AggregateFunction::Count,
+            AggregateFunction::CountDistinct("value".to_string()),
+        ];
+        let group_by = vec![];
+        let input_columns = vec!["value".to_string()];
+
+        let mut agg = AggregateOperator::new(group_by, aggregates.clone(), input_columns);
+
+        let mut init_data = Delta::new();
+        init_data.insert(1, vec![Value::Integer(5)]);
+        init_data.insert(2, vec![Value::Integer(5)]);
+        init_data.insert(3, vec![Value::Integer(7)]);
+        init_data.insert(4, vec![Value::Null]);
+        agg.initialize(init_data);
+
+        let state = agg.get_current_state();
+        assert_eq!(state.changes[0].0.values[0], Value::Integer(4)); // COUNT(*)
+        assert_eq!(state.changes[0].0.values[1], Value::Integer(2)); // COUNT(DISTINCT value)
+
+        // Deleting one of the duplicates keeps the value counted
+        let mut delete_delta = Delta::new();
+        delete_delta.delete(1, vec![Value::Integer(5)]);
+        agg.commit(delete_delta);
+
+        let state = agg.get_current_state();
+        assert_eq!(state.changes[0].0.values[1], Value::Integer(2));
+
+        // Deleting the last one does not
+        let mut delete_delta = Delta::new();
+        delete_delta.delete(2, vec![Value::Integer(5)]);
+        agg.commit(delete_delta);
+
+        let state = agg.get_current_state();
+        assert_eq!(state.changes[0].0.values[1], Value::Integer(1));
+    }
+
+    #[test]
+    fn test_group_concat_and_total() {
+        let aggregates = vec![
+            AggregateFunction::GroupConcat("name".to_string(), None),
+            AggregateFunction::GroupConcat("name".to_string(), Some("sep".to_string())),
+            AggregateFunction::Total("score".to_string()),
+        ];
+        let group_by = vec![];
+        let input_columns = vec!["name".to_string(), "sep".to_string(), "score".to_string()];
+
+        let mut agg = AggregateOperator::new(group_by, aggregates.clone(), input_columns);
+
+        let row = |name: &str, score: i64| {
+            vec![
+                Value::Text(name.into()),
+                Value::Text(";".into()),
+                Value::Integer(score),
+            ]
+        };
+
+        // Rows are concatenated in rowid order, regardless of the order they arrive in
+        let mut init_data = Delta::new();
+        init_data.insert(3, row("carol", 3));
+        init_data.insert(1, row("alice", 1));
+        init_data.insert(2, row("bob", 2));
+        agg.initialize(init_data);
+
+        let state = agg.get_current_state();
+        assert_eq!(
+            state.changes[0].0.values[0],
+            Value::Text("alice,bob,carol".into())
+        );
+        assert_eq!(
+            state.changes[0].0.values[1],
+            Value::Text("alice;bob;carol".into())
+        );
+        assert_eq!(state.changes[0].0.values[2], Value::Float(6.0));
+
+        // Update bob's row
+        let mut update_delta = Delta::new();
+        update_delta.delete(2, row("bob", 2));
+        update_delta.insert(2, row("bobby", 4));
+        agg.commit(update_delta);
+
+        let state = agg.get_current_state();
+        assert_eq!(
+            state.changes[0].0.values[0],
+            Value::Text("alice,bobby,carol".into())
+        );
+        assert_eq!(state.changes[0].0.values[2], Value::Float(8.0));
+
+        // Deleting every row retracts the group
+        let mut delete_delta = Delta::new();
+        delete_delta.delete(1, row("alice", 1));
+        delete_delta.delete(2, row("bobby", 4));
+        delete_delta.delete(3, row("carol", 3));
+        let output = agg.eval(delete_delta, None);
+
+        assert_eq!(output.changes.len(), 1);
+        assert_eq!(output.changes[0].1, -1);
+    }
+
     #[test]
     fn test_filter_operator_rowid_update() {
         // When a row's rowid changes (e.g., UPDATE t SET a=1 WHERE a=3 on INTEGER PRIMARY KEY),
@@ -2721,4 +3655,309 @@ mod tests {
             .unwrap();
         assert_eq!(x.0.values[1], Value::Integer(2));
     }
+
+    // Join tests
+    fn order(id: i64, customer_id: i64, amount: i64) -> Vec<Value> {
+        vec![
+            Value::Integer(id),
+            Value::Integer(customer_id),
+            Value::Integer(amount),
+        ]
+    }
+
+    fn customer(id: i64, name: &str) -> Vec<Value> {
+        vec![Value::Integer(id), Value::Text(name.into())]
+    }
+
+    /// Sorted (order id, customer name) pairs of a join state
+    fn joined_pairs(state: &Delta) -> Vec<(Value, Value)> {
+        let mut pairs: Vec<_> = state
+            .changes
+            .iter()
+            .map(|(row, weight)| {
+                assert_eq!(*weight, 1);
+                (row.values[0].clone(), row.values[4].clone())
+            })
+            .collect();
+        pairs.sort_by_key(|(id, name)| (format!("{id:?}"), format!("{name:?}")));
+        pairs
+    }
+
+    #[test]
+    fn test_join_updates_from_both_sides() {
+        // orders(id, customer_id, amount) JOIN customers(id, name) ON customer_id = id
+        let mut join = JoinOperator::new(JoinType::Inner, vec![1], vec![0], 2).unwrap();
+        let tracker = Arc::new(Mutex::new(ComputationTracker::new()));
+        join.set_tracker(tracker.clone());
+
+        let mut orders = Delta::new();
+        let mut customers = Delta::new();
+        for i in 1..=100 {
+            orders.insert(i, order(i, i % 10, i * 10));
+        }
+        for i in 0..10 {
+            customers.insert(i, customer(i, &format!("c{i}")));
+        }
+        join.initialize_pair(DeltaPair::new(orders, customers));
+        assert_eq!(join.get_current_state().changes.len(), 100);
+
+        // A new order only looks up its own customer
+        tracker.lock().unwrap().join_lookups = 0;
+        let mut new_order = Delta::new();
+        new_order.insert(101, order(101, 3, 5));
+        let output = join.commit_pair(DeltaPair::new(new_order, Delta::new()));
+        assert_eq!(output.changes.len(), 1);
+        assert_eq!(output.changes[0].1, 1);
+        assert_eq!(output.changes[0].0.values[4], Value::Text("c3".into()));
+        assert_incremental(&tracker.lock().unwrap(), 1, 100);
+
+        // Renaming a customer retracts and re-emits all of its orders
+        let mut rename = Delta::new();
+        rename.delete(3, customer(3, "c3"));
+        rename.insert(3, customer(3, "renamed"));
+        let output = join.commit_pair(DeltaPair::new(Delta::new(), rename));
+        assert_eq!(output.changes.len(), 22);
+        assert!(output.changes[..11].iter().all(|(_, weight)| *weight == -1));
+        assert!(output.changes[11..].iter().all(|(row, weight)| {
+            *weight == 1 && row.values[4] == Value::Text("renamed".into())
+        }));
+
+        // Changes on both sides in the same batch also join with each other
+        let mut orders = Delta::new();
+        orders.insert(200, order(200, 42, 1));
+        let mut customers = Delta::new();
+        customers.insert(42, customer(42, "new"));
+        let output = join.commit_pair(DeltaPair::new(orders, customers));
+        assert_eq!(output.changes.len(), 1);
+        assert_eq!(output.changes[0].0.values[4], Value::Text("new".into()));
+
+        // Updating an order keeps its row key, and the retraction comes first
+        let mut update = Delta::new();
+        update.delete(200, order(200, 42, 1));
+        update.insert(200, order(200, 42, 2));
+        let output = join.commit_pair(DeltaPair::new(update, Delta::new()));
+        assert_eq!(output.changes.len(), 2);
+        assert_eq!(output.changes[0].1, -1);
+        assert_eq!(output.changes[1].1, 1);
+        assert_eq!(output.changes[0].0.rowid, output.changes[1].0.rowid);
+        assert_eq!(output.changes[1].0.values[2], Value::Integer(2));
+        assert_eq!(join.get_current_state().changes.len(), 102);
+    }
+
+    #[test]
+    fn test_left_join_pads_unmatched_rows() {
+        let mut join = JoinOperator::new(JoinType::Left, vec![1], vec![0], 2).unwrap();
+
+        let mut orders = Delta::new();
+        orders.insert(1, order(1, 10, 100));
+        orders.insert(2, order(2, 10, 200));
+        orders.insert(3, vec![Value::Integer(3), Value::Null, Value::Integer(300)]);
+        join.initialize_pair(DeltaPair::new(orders, Delta::new()));
+        assert_eq!(
+            joined_pairs(&join.get_current_state()),
+            vec![
+                (Value::Integer(1), Value::Null),
+                (Value::Integer(2), Value::Null),
+                (Value::Integer(3), Value::Null),
+            ]
+        );
+
+        // The first match replaces the padded rows
+        let mut customers = Delta::new();
+        customers.insert(10, customer(10, "alice"));
+        let output = join.eval_pair(DeltaPair::new(Delta::new(), customers.clone()));
+        assert_eq!(output.changes.len(), 4);
+        join.commit_pair(DeltaPair::new(Delta::new(), customers));
+        assert_eq!(
+            joined_pairs(&join.get_current_state()),
+            vec![
+                (Value::Integer(1), Value::Text("alice".into())),
+                (Value::Integer(2), Value::Text("alice".into())),
+                (Value::Integer(3), Value::Null),
+            ]
+        );
+
+        // A second match doesn't bring the padded rows back
+        let mut customers = Delta::new();
+        customers.insert(11, customer(10, "bob"));
+        join.commit_pair(DeltaPair::new(Delta::new(), customers));
+        assert_eq!(join.get_current_state().changes.len(), 5);
+
+        // Losing the last match pads the rows again
+        let mut customers = Delta::new();
+        customers.delete(10, customer(10, "alice"));
+        customers.delete(11, customer(10, "bob"));
+        join.commit_pair(DeltaPair::new(Delta::new(), customers));
+        assert_eq!(
+            joined_pairs(&join.get_current_state()),
+            vec![
+                (Value::Integer(1), Value::Null),
+                (Value::Integer(2), Value::Null),
+                (Value::Integer(3), Value::Null),
+            ]
+        );
+
+        // Deleting an unmatched order retracts its padded row
+        let mut orders = Delta::new();
+        orders.delete(3, vec![Value::Integer(3), Value::Null, Value::Integer(300)]);
+        let output = join.commit_pair(DeltaPair::new(orders, Delta::new()));
+        assert_eq!(output.changes.len(), 1);
+        assert_eq!(output.changes[0].1, -1);
+        assert_eq!(join.get_current_state().changes.len(), 2);
+    }
+
+    /// Feed the state changes of an operator to another one, as if read back from storage
+    fn restore_from(
+        source: &mut dyn IncrementalOperator,
+        target: &mut dyn IncrementalOperator,
+    ) -> usize {
+        let entries: Vec<_> = source
+            .take_state_changes()
+            .into_iter()
+            .filter_map(|(key, state)| state.map(|state| (key, state)))
+            .collect();
+        let count = entries.len();
+        target.restore_state(entries).unwrap();
+        count
+    }
+
+    fn sorted_changes(delta: &Delta) -> Vec<(HashableRow, isize)> {
+        let mut changes = delta.changes.clone();
+        changes.sort_by_key(|(row, weight)| (row.rowid, *weight));
+        changes
+    }
+
+    #[test]
+    fn test_aggregate_state_round_trip() {
+        let aggregates = vec![
+            AggregateFunction::Count,
+            AggregateFunction::Sum("amount".to_string()),
+            AggregateFunction::Avg("amount".to_string()),
+            AggregateFunction::Total("amount".to_string()),
+            AggregateFunction::Min("amount".to_string()),
+            AggregateFunction::Max("amount".to_string()),
+            AggregateFunction::CountDistinct("amount".to_string()),
+            AggregateFunction::GroupConcat("name".to_string(), Some("sep".to_string())),
+        ];
+        let input_columns = vec![
+            "category".to_string(),
+            "name".to_string(),
+            "sep".to_string(),
+            "amount".to_string(),
+        ];
+        let new_operator = || {
+            AggregateOperator::new(
+                vec!["category".to_string()],
+                aggregates.clone(),
+                input_columns.clone(),
+            )
+        };
+        let row = |category: &str, name: &str, amount: f64| {
+            vec![
+                Value::Text(category.into()),
+                Value::Text(name.into()),
+                Value::Null,
+                Value::Float(amount),
+            ]
+        };
+
+        let mut agg = new_operator();
+        let mut init_data = Delta::new();
+        init_data.insert(1, row("a", "x", 1.5));
+        init_data.insert(2, row("a", "y", 1.5));
+        init_data.insert(3, row("a", "z", 4.0));
+        init_data.insert(4, row("b", "w", 2.0));
+        agg.initialize(init_data);
+
+        let mut restored = new_operator();
+        assert_eq!(restore_from(&mut agg, &mut restored), 2);
+        assert_eq!(
+            sorted_changes(restored.get_current_state()),
+            sorted_changes(agg.get_current_state())
+        );
+
+        // Both operators keep evolving the same way, including for MIN and GROUP_CONCAT,
+        // which depend on the values kept in the state
+        let mut delta = Delta::new();
+        delta.delete(1, row("a", "x", 1.5));
+        delta.delete(4, row("b", "w", 2.0));
+        assert_eq!(
+            sorted_changes(&restored.commit(delta.clone())),
+            sorted_changes(&agg.commit(delta))
+        );
+        assert_eq!(
+            sorted_changes(restored.get_current_state()),
+            sorted_changes(agg.get_current_state())
+        );
+
+        // The emptied group is removed from the persistent state
+        let changes = agg.take_state_changes();
+        assert_eq!(changes.len(), 2);
+        let removed: Vec<_> = changes
+            .iter()
+            .filter(|(_, state)| state.is_none())
+            .collect();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].0, vec![Value::Text("b".into())]);
+        assert!(agg.take_state_changes().is_empty());
+    }
+
+    #[test]
+    fn test_aggregate_state_rejects_truncated_state() {
+        let mut agg = AggregateOperator::new(
+            vec![],
+            vec![AggregateFunction::Sum("amount".to_string())],
+            vec!["amount".to_string()],
+        );
+        let entries = vec![(vec![], vec![Value::Integer(1), Value::Integer(1)])];
+        assert!(agg.restore_state(entries).is_err());
+    }
+
+    #[test]
+    fn test_join_state_round_trip() {
+        let new_operator = || JoinOperator::new(JoinType::Left, vec![1], vec![0], 2).unwrap();
+
+        let mut join = new_operator();
+        let mut orders = Delta::new();
+        orders.insert(1, order(1, 10, 100));
+        orders.insert(2, order(2, 11, 200));
+        let mut customers = Delta::new();
+        customers.insert(10, customer(10, "alice"));
+        join.initialize_pair(DeltaPair::new(orders, customers));
+
+        let mut restored = new_operator();
+        assert_eq!(restore_from(&mut join, &mut restored), 3);
+        assert!(restored.take_state_changes().is_empty());
+        assert_eq!(
+            joined_pairs(&restored.get_current_state()),
+            joined_pairs(&join.get_current_state())
+        );
+
+        // The restored indexes match new rows from both sides
+        let mut customers = Delta::new();
+        customers.insert(11, customer(11, "bob"));
+        customers.delete(10, customer(10, "alice"));
+        let deltas = DeltaPair::new(Delta::new(), customers);
+        assert_eq!(
+            sorted_changes(&restored.commit_pair(deltas.clone())),
+            sorted_changes(&join.commit_pair(deltas))
+        );
+        assert_eq!(
+            joined_pairs(&restored.get_current_state()),
+            vec![
+                (Value::Integer(1), Value::Null),
+                (Value::Integer(2), Value::Text("bob".into())),
+            ]
+        );
+
+        // The deleted customer is removed from the persistent state
+        let changes = join.take_state_changes();
+        assert_eq!(changes.len(), 2);
+        let removed: Vec<_> = changes
+            .iter()
+            .filter(|(_, state)| state.is_none())
+            .collect();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].0[..2], [Value::Integer(1), Value::Integer(10)]);
+    }
 }
@@ -0,0 +1,257 @@
+//! Storage of incremental views in the database file
+//!
+//! Every materialized view has a state table, `__turso_internal_dbsp_state_<view>`, holding the
+//! rows of the view and the state of the operators of its circuit that cannot be recomputed from
+//! their inputs, like aggregate accumulators. Each entry of the table is identified by the id of
+//! the operator it belongs to and a key chosen by that operator:
+//!
+//! ```text
+//! CREATE TABLE __turso_internal_dbsp_state_<view>(operator_id INTEGER, key BLOB, state BLOB)
+//! ```
+//!
+//! Keys and states are lists of values, stored as records. The table is written in the same
+//! transaction as the changes to the base tables, and read back when the database is opened so
+//! that the view doesn't have to be populated from its base tables again.
+//!
+//! Views created in MVCC mode have no state table, and are populated on every open.
+
+use super::hashable_row::HashableRow;
+use crate::storage::btree::{BTreeCursor, BTreeKey};
+use crate::storage::pager::Pager;
+use crate::types::{ImmutableRecord, RecordCursor, SeekKey, SeekOp, SeekResult, Value};
+use crate::util::IOExt;
+use crate::{LimboError, Result};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Prefix of the names of the state tables of materialized views
+pub const DBSP_STATE_TABLE_PREFIX: &str = "__turso_internal_dbsp_state_";
+
+/// Operator id of the entries holding the rows of the view, keyed by row key
+pub const VIEW_ROWS_OPERATOR_ID: i64 = -1;
+
+/// Operator id of the entry holding the version of the state format. It is written once the
+/// view has been populated, so a state table without it is ignored.
+pub const METADATA_OPERATOR_ID: i64 = -2;
+
+/// Version of the layout of the state table and of the operator states
+pub const STATE_FORMAT_VERSION: i64 = 1;
+
+/// Name of the state table of a materialized view
+pub fn state_table_name(view_name: &str) -> String {
+    format!("{DBSP_STATE_TABLE_PREFIX}{view_name}")
+}
+
+/// SQL definition of the state table of a materialized view
+pub fn state_table_sql(view_name: &str) -> String {
+    format!(
+        "CREATE TABLE {}(operator_id INTEGER, key BLOB, state BLOB)",
+        state_table_name(view_name)
+    )
+}
+
+/// Query reading the entries of the state table of a materialized view
+pub fn state_table_query(view_name: &str) -> String {
+    format!(
+        "SELECT rowid, operator_id, key, state FROM {}",
+        state_table_name(view_name)
+    )
+}
+
+/// An entry of the state table of a view
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateEntry {
+    pub rowid: i64,
+    pub operator_id: i64,
+    pub key: Vec<Value>,
+    pub state: Vec<Value>,
+}
+
+impl StateEntry {
+    /// Decode a row returned by the query built by [state_table_query]
+    pub fn from_row(values: &[Value]) -> Result<Self> {
+        let [Value::Integer(rowid), Value::Integer(operator_id), key, state] = values else {
+            return Err(LimboError::Corrupt(format!(
+                "invalid entry in a view state table: {values:?}"
+            )));
+        };
+        Ok(Self {
+            rowid: *rowid,
+            operator_id: *operator_id,
+            key: decode_values(key)?,
+            state: decode_values(state)?,
+        })
+    }
+}
+
+/// Encode a list of values as a record blob
+pub fn encode_values(values: &[Value]) -> Value {
+    let record = ImmutableRecord::from_values(values.iter(), values.len());
+    Value::Blob(record.get_payload().to_vec())
+}
+
+/// Decode a list of values encoded with [encode_values]
+pub fn decode_values(value: &Value) -> Result<Vec<Value>> {
+    let Value::Blob(payload) = value else {
+        return Err(LimboError::Corrupt(format!(
+            "expected a record blob in a view state table, got {value:?}"
+        )));
+    };
+    let record = ImmutableRecord::from_bin_record(payload.clone());
+    let values = RecordCursor::new().get_values(&record)?;
+    Ok(values.iter().map(|value| value.to_owned()).collect())
+}
+
+/// Tracks the entries of the state table of a view, and the changes to them that still have to
+/// be written
+#[derive(Debug)]
+pub struct ViewStorage {
+    table_name: String,
+    // Row ids of the entries in the state table, keyed by operator id and entry key
+    rowids: HashMap<HashableRow, i64>,
+    next_rowid: i64,
+    // Entries changed since they were last written, with None for removed entries
+    pending: HashMap<HashableRow, Option<Vec<Value>>>,
+}
+
+impl ViewStorage {
+    pub fn new(view_name: &str) -> Self {
+        Self {
+            table_name: state_table_name(view_name),
+            rowids: HashMap::new(),
+            next_rowid: 1,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// Register an entry read from the state table
+    pub fn load_entry(&mut self, rowid: i64, operator_id: i64, key: Vec<Value>) {
+        self.rowids
+            .insert(HashableRow::new(operator_id, key), rowid);
+        self.next_rowid = self.next_rowid.max(rowid + 1);
+    }
+
+    /// Record a change to an entry. A `None` state removes the entry.
+    pub fn record_change(&mut self, operator_id: i64, key: Vec<Value>, state: Option<Vec<Value>>) {
+        self.pending
+            .insert(HashableRow::new(operator_id, key), state);
+    }
+
+    /// Write the pending changes to the state table rooted at `root_page`
+    ///
+    /// This must be called while the connection holds a write transaction, so that the changes
+    /// are committed together with the changes to the base tables they come from.
+    pub fn write_pending(&mut self, pager: &Rc<Pager>, root_page: usize) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut cursor = BTreeCursor::new_table(None, pager.clone(), root_page, 3);
+        for (entry, state) in std::mem::take(&mut self.pending) {
+            match state {
+                Some(state) => {
+                    let rowid = match self.rowids.get(&entry) {
+                        Some(rowid) => *rowid,
+                        None => {
+                            let rowid = self.next_rowid;
+                            self.next_rowid += 1;
+                            self.rowids.insert(entry.clone(), rowid);
+                            rowid
+                        }
+                    };
+                    let values = [
+                        Value::Integer(entry.rowid),
+                        encode_values(&entry.values),
+                        encode_values(&state),
+                    ];
+                    let record = ImmutableRecord::from_values(values.iter(), values.len());
+                    pager.io.block(|| {
+                        cursor.seek(SeekKey::TableRowId(rowid), SeekOp::GE { eq_only: true })
+                    })?;
+                    pager.io.block(|| {
+                        cursor.insert(&BTreeKey::new_table_rowid(rowid, Some(&record)))
+                    })?;
+                }
+                None => {
+                    let Some(rowid) = self.rowids.remove(&entry) else {
+                        continue;
+                    };
+                    let seek_result = pager.io.block(|| {
+                        cursor.seek(SeekKey::TableRowId(rowid), SeekOp::GE { eq_only: true })
+                    })?;
+                    if matches!(seek_result, SeekResult::Found) {
+                        pager.io.block(|| cursor.delete())?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_values() {
+        let values = vec![
+            Value::Null,
+            Value::Integer(-42),
+            Value::Float(1.5),
+            Value::build_text("group"),
+            Value::Blob(vec![1, 2, 3]),
+        ];
+        let encoded = encode_values(&values);
+        assert!(matches!(encoded, Value::Blob(_)));
+        assert_eq!(decode_values(&encoded).unwrap(), values);
+
+        assert_eq!(
+            decode_values(&encode_values(&[])).unwrap(),
+            Vec::<Value>::new()
+        );
+        assert!(decode_values(&Value::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn test_state_entry_from_row() {
+        let row = [
+            Value::Integer(3),
+            Value::Integer(VIEW_ROWS_OPERATOR_ID),
+            encode_values(&[Value::Integer(42)]),
+            encode_values(&[Value::build_text("a"), Value::Float(2.5)]),
+        ];
+        assert_eq!(
+            StateEntry::from_row(&row).unwrap(),
+            StateEntry {
+                rowid: 3,
+                operator_id: VIEW_ROWS_OPERATOR_ID,
+                key: vec![Value::Integer(42)],
+                state: vec![Value::build_text("a"), Value::Float(2.5)],
+            }
+        );
+        assert!(StateEntry::from_row(&row[..3]).is_err());
+    }
+
+    #[test]
+    fn test_pending_changes_keep_the_last_state() {
+        let mut storage = ViewStorage::new("v");
+        assert_eq!(storage.table_name(), "__turso_internal_dbsp_state_v");
+
+        storage.load_entry(7, VIEW_ROWS_OPERATOR_ID, vec![Value::Integer(1)]);
+        storage.record_change(VIEW_ROWS_OPERATOR_ID, vec![Value::Integer(1)], None);
+        storage.record_change(
+            VIEW_ROWS_OPERATOR_ID,
+            vec![Value::Integer(1)],
+            Some(vec![Value::Integer(10)]),
+        );
+
+        assert_eq!(storage.pending.len(), 1);
+        assert_eq!(storage.next_rowid, 8);
+        let entry = HashableRow::new(VIEW_ROWS_OPERATOR_ID, vec![Value::Integer(1)]);
+        assert_eq!(storage.pending[&entry], Some(vec![Value::Integer(10)]));
+    }
+}
@@ -1,13 +1,20 @@
 use super::compiler::{DbspCircuit, DbspCompiler, DeltaSet};
 use super::dbsp::{RowKeyStream, RowKeyZSet};
-use super::operator::{ComputationTracker, Delta, FilterPredicate};
+use super::hashable_row::HashableRow;
+use super::operator::{ComputationTracker, Delta, FilterPredicate, StateEntries};
+use super::persistence::{
+    state_table_name, state_table_query, StateEntry, ViewStorage, METADATA_OPERATOR_ID,
+    STATE_FORMAT_VERSION, VIEW_ROWS_OPERATOR_ID,
+};
 use crate::schema::{BTreeTable, Column, Schema};
+use crate::storage::pager::Pager;
 use crate::translate::logical::LogicalPlanBuilder;
 use crate::types::{IOCompletions, IOResult, Value};
 use crate::util::extract_view_columns;
-use crate::{io_yield_one, Completion, LimboError, Result, Statement};
-use std::collections::{BTreeMap, HashMap};
+use crate::{io_yield_one, Completion, LimboError, Result, Statement, TransactionState};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use turso_parser::ast;
 use turso_parser::{
@@ -19,9 +26,15 @@ use turso_parser::{
 pub enum PopulateState {
     /// Initial state - need to prepare the query
     Start,
-    /// Actively processing rows from the query
+    /// Reading the state of the view from its state table
+    Loading {
+        stmt: Box<Statement>,
+        entries: Vec<StateEntry>,
+    },
+    /// Actively processing rows from the query over one of the base tables
     Processing {
         stmt: Box<Statement>,
+        table_idx: usize,
         rows_processed: usize,
     },
     /// Population complete
@@ -32,8 +45,17 @@ impl fmt::Debug for PopulateState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             PopulateState::Start => write!(f, "Start"),
-            PopulateState::Processing { rows_processed, .. } => f
+            PopulateState::Loading { entries, .. } => f
+                .debug_struct("Loading")
+                .field("entries", &entries.len())
+                .finish(),
+            PopulateState::Processing {
+                table_idx,
+                rows_processed,
+                ..
+            } => f
                 .debug_struct("Processing")
+                .field("table_idx", table_idx)
                 .field("rows_processed", rows_processed)
                 .finish(),
             PopulateState::Done => write!(f, "Done"),
@@ -44,20 +66,30 @@ impl fmt::Debug for PopulateState {
 /// Per-connection transaction state for incremental views
 #[derive(Debug, Clone, Default)]
 pub struct ViewTransactionState {
-    // Per-connection delta for uncommitted changes (contains both weights and values)
-    pub delta: Delta,
+    // Per-connection deltas for uncommitted changes, keyed by base table name
+    pub deltas: DeltaSet,
+}
+
+impl ViewTransactionState {
+    /// Record the insertion of a row into one of the view's base tables
+    pub fn insert(&mut self, table_name: &str, row_key: i64, values: Vec<Value>) {
+        self.deltas.get_mut(table_name).insert(row_key, values);
+    }
+
+    /// Record the deletion of a row from one of the view's base tables
+    pub fn delete(&mut self, table_name: &str, row_key: i64, values: Vec<Value>) {
+        self.deltas.get_mut(table_name).delete(row_key, values);
+    }
 }
 
 /// Incremental view that maintains a stream of row keys using DBSP-style computation
 /// The actual row data is stored as transformed Values
 ///
-/// This version keeps everything in-memory. This is acceptable for small views, since DBSP
-/// doesn't have to track the history of changes. Still for very large views (think of the result
-/// of create view v as select * from tbl where x > 1; and that having 1B values.
-///
-/// We should have a version of this that materializes the results. Materializing will also be good
-/// for large aggregations, because then we don't have to re-compute when opening the database
-/// again.
+/// The rows of the view and the state of its operators are kept in memory, and written to the
+/// view's state table when the transactions changing its base tables commit (see
+/// [super::persistence]). Opening the database loads them back instead of scanning the base
+/// tables again. Still for very large views (think of the result of create view v as select *
+/// from tbl where x > 1; and that having 1B values), everything has to fit in memory.
 ///
 /// Uses DBSP circuits for incremental computation.
 #[derive(Debug)]
@@ -79,11 +111,14 @@ pub struct IncrementalView {
     circuit_initialized: bool,
 
     // Tables referenced by this view (extracted from FROM clause and JOINs)
-    base_table: Arc<BTreeTable>,
+    base_tables: Vec<Arc<BTreeTable>>,
     // The view's output columns with their types
     pub columns: Vec<Column>,
     // State machine for population
     populate_state: PopulateState,
+    // Changes to the state table, if the view has one. In MVCC mode views are kept in memory
+    // only.
+    storage: Option<ViewStorage>,
     // Computation tracker for statistics
     // We will use this one day to export rows_read, but for now, will just test that we're doing the expected amount of compute
     #[cfg_attr(not(test), allow(dead_code))]
@@ -94,23 +129,36 @@ impl IncrementalView {
     /// Validate that a CREATE MATERIALIZED VIEW statement can be handled by IncrementalView
     /// This should be called early, before updating sqlite_master
     pub fn can_create_view(select: &ast::Select) -> Result<()> {
-        // Check for JOINs
-        let (join_tables, join_condition) = Self::extract_join_info(select);
-        if join_tables.is_some() || join_condition.is_some() {
-            return Err(LimboError::ParseError(
-                "JOINs in views are not yet supported".to_string(),
-            ));
+        Self::extract_base_tables(select)?;
+
+        // Only INNER and LEFT equi-joins can be maintained incrementally
+        if let ast::OneSelect::Select {
+            from: Some(ref from),
+            ..
+        } = select.body.select
+        {
+            for join in &from.joins {
+                let supported_type = match join.operator {
+                    ast::JoinOperator::Comma => false,
+                    ast::JoinOperator::TypedJoin(None) => true,
+                    ast::JoinOperator::TypedJoin(Some(join_type)) => {
+                        !join_type.intersects(ast::JoinType::RIGHT | ast::JoinType::NATURAL)
+                    }
+                };
+                if !supported_type || join.constraint.is_none() {
+                    return Err(LimboError::ParseError(
+                        "Only INNER and LEFT JOINs with an ON or USING clause are supported in views"
+                            .to_string(),
+                    ));
+                }
+            }
         }
 
         Ok(())
     }
 
     /// Try to compile the SELECT statement into a DBSP circuit
-    fn try_compile_circuit(
-        select: &ast::Select,
-        schema: &Schema,
-        _base_table: &Arc<BTreeTable>,
-    ) -> Result<DbspCircuit> {
+    fn try_compile_circuit(select: &ast::Select, schema: &Schema) -> Result<DbspCircuit> {
         // Build the logical plan from the SELECT statement
         let mut builder = LogicalPlanBuilder::new(schema);
         // Convert Select to a Stmt for the builder
@@ -169,38 +217,44 @@ impl IncrementalView {
     ) -> Result<Self> {
         let name = view_name.name.as_str().to_string();
 
-        let where_predicate = FilterPredicate::from_select(&select)?;
+        Self::can_create_view(&select)?;
+
+        // The WHERE clause is pushed down to the query that populates the view. With joins it
+        // can refer to any of the tables, so it is only evaluated by the circuit. The same goes
+        // for anything but comparisons of columns with literals.
+        let where_predicate = if Self::has_joins(&select) {
+            FilterPredicate::None
+        } else {
+            FilterPredicate::from_select(&select).unwrap_or(FilterPredicate::None)
+        };
 
         // Extract output columns using the shared function
         let view_columns = extract_view_columns(&select, schema);
 
-        let (join_tables, join_condition) = Self::extract_join_info(&select);
-        if join_tables.is_some() || join_condition.is_some() {
-            return Err(LimboError::ParseError(
-                "JOINs in views are not yet supported".to_string(),
-            ));
-        }
-
-        // Get the base table from FROM clause (when no joins)
-        let base_table = if let Some(base_table_name) = Self::extract_base_table(&select) {
-            if let Some(table) = schema.get_btree_table(&base_table_name) {
-                table.clone()
-            } else {
+        // Get the base tables from the FROM clause and JOINs. A table joined with itself is
+        // only read once.
+        let mut base_tables: Vec<Arc<BTreeTable>> = Vec::new();
+        for base_table_name in Self::extract_base_tables(&select)? {
+            let Some(table) = schema.get_btree_table(&base_table_name) else {
                 return Err(LimboError::ParseError(format!(
                     "Table '{base_table_name}' not found in schema"
                 )));
+            };
+            if !base_tables.iter().any(|t| t.name == table.name) {
+                base_tables.push(table);
             }
-        } else {
+        }
+        if base_tables.is_empty() {
             return Err(LimboError::ParseError(
                 "views without a base table not supported yet".to_string(),
             ));
-        };
+        }
 
         Self::new(
             name,
             where_predicate,
             select.clone(),
-            base_table,
+            base_tables,
             view_columns,
             schema,
         )
@@ -210,7 +264,7 @@ impl IncrementalView {
         name: String,
         where_predicate: FilterPredicate,
         select_stmt: ast::Select,
-        base_table: Arc<BTreeTable>,
+        base_tables: Vec<Arc<BTreeTable>>,
         columns: Vec<Column>,
         schema: &Schema,
     ) -> Result<Self> {
@@ -220,7 +274,7 @@ impl IncrementalView {
         let tracker = Arc::new(Mutex::new(ComputationTracker::new()));
 
         // Compile the SELECT statement into a DBSP circuit
-        let circuit = Self::try_compile_circuit(&select_stmt, schema, &base_table)?;
+        let circuit = Self::try_compile_circuit(&select_stmt, schema)?;
 
         // Circuit will be initialized when we first call merge_delta
         let circuit_initialized = false;
@@ -233,9 +287,10 @@ impl IncrementalView {
             select_stmt,
             circuit,
             circuit_initialized,
-            base_table,
+            base_tables,
             columns,
             populate_state: PopulateState::Start,
+            storage: None,
             tracker,
         })
     }
@@ -246,32 +301,54 @@ impl IncrementalView {
 
     /// Get all table names referenced by this view
     pub fn get_referenced_table_names(&self) -> Vec<String> {
-        vec![self.base_table.name.clone()]
+        self.base_tables
+            .iter()
+            .map(|table| table.name.clone())
+            .collect()
     }
 
     /// Get all tables referenced by this view
     pub fn get_referenced_tables(&self) -> Vec<Arc<BTreeTable>> {
-        vec![self.base_table.clone()]
+        self.base_tables.clone()
     }
 
-    /// Extract the base table name from a SELECT statement (for non-join cases)
-    fn extract_base_table(select: &ast::Select) -> Option<String> {
-        if let ast::OneSelect::Select {
+    /// Extract the names of the tables in the FROM clause of a SELECT statement, including the
+    /// joined ones
+    fn extract_base_tables(select: &ast::Select) -> Result<Vec<String>> {
+        let ast::OneSelect::Select {
             from: Some(ref from),
             ..
         } = select.body.select
-        {
-            if let ast::SelectTable::Table(name, _, _) = from.select.as_ref() {
-                return Some(name.name.as_str().to_string());
-            }
-        }
-        None
+        else {
+            return Ok(Vec::new());
+        };
+
+        let selected = std::iter::once(from.select.as_ref())
+            .chain(from.joins.iter().map(|join| join.table.as_ref()));
+        selected
+            .map(|table| match table {
+                ast::SelectTable::Table(name, _, _) => Ok(name.name.as_str().to_string()),
+                _ => Err(LimboError::ParseError(
+                    "Only tables are supported in the FROM clause of views".to_string(),
+                )),
+            })
+            .collect()
+    }
+
+    /// Whether the FROM clause of a SELECT statement has joins
+    fn has_joins(select: &ast::Select) -> bool {
+        matches!(
+            select.body.select,
+            ast::OneSelect::Select {
+                from: Some(ref from),
+                ..
+            } if !from.joins.is_empty()
+        )
     }
 
-    /// Generate the SQL query for populating the view from its source table
-    fn sql_for_populate(&self) -> crate::Result<String> {
-        // Get the base table from referenced tables
-        let table = &self.base_table;
+    /// Generate the SQL query for populating the view from one of its source tables
+    fn sql_for_populate(&self, table_idx: usize) -> crate::Result<String> {
+        let table = &self.base_tables[table_idx];
 
         // Check if the table has a rowid alias (INTEGER PRIMARY KEY column)
         let has_rowid_alias = table.columns.iter().any(|col| col.is_rowid_alias);
@@ -332,6 +409,9 @@ impl IncrementalView {
                 let right_clause = self.build_where_clause(right)?;
                 Ok(format!("({left_clause} OR {right_clause})"))
             }
+            FilterPredicate::Expression(_) => Err(LimboError::InternalError(
+                "compiled filter expressions cannot be pushed down".to_string(),
+            )),
         }
     }
 
@@ -346,8 +426,11 @@ impl IncrementalView {
         }
     }
 
-    /// Populate the view by scanning the source table using a state machine
+    /// Populate the view by scanning the source tables using a state machine
     /// This can be called multiple times and will resume from where it left off
+    ///
+    /// Tables are scanned one after the other. Each batch is merged as a change to its table,
+    /// so joins see the rows of the tables scanned before it.
     pub fn populate_from_table(
         &mut self,
         conn: &std::sync::Arc<crate::Connection>,
@@ -362,31 +445,44 @@ impl IncrementalView {
         loop {
             match &mut self.populate_state {
                 PopulateState::Start => {
-                    // Generate the SQL query for populating the view
-                    // It is best to use a standard query than a cursor for two reasons:
-                    // 1) Using a sql query will allow us to be much more efficient in cases where we only want
-                    //    some rows, in particular for indexed filters
-                    // 2) There are two types of cursors: index and table. In some situations (like for example
-                    //    if the table has an integer primary key), the key will be exclusively in the index
-                    //    btree and not in the table btree. Using cursors would force us to be aware of this
-                    //    distinction (and others), and ultimately lead to reimplementing the whole query
-                    //    machinery (next step is which index is best to use, etc)
-                    let query = self.sql_for_populate()?;
-
-                    // Prepare the statement
-                    let stmt = conn.prepare(&query)?;
-
-                    self.populate_state = PopulateState::Processing {
-                        stmt: Box::new(stmt),
-                        rows_processed: 0,
-                    };
+                    if !self.start_loading(conn)? {
+                        self.start_populating_table(conn, 0)?;
+                    }
                     // Continue to next state
                 }
 
+                PopulateState::Loading { stmt, entries } => loop {
+                    match stmt.step()? {
+                        crate::vdbe::StepResult::Row => {
+                            let row = stmt.row().unwrap();
+                            let values: Vec<Value> = row.get_values().cloned().collect();
+                            entries.push(StateEntry::from_row(&values)?);
+                        }
+                        crate::vdbe::StepResult::Done => {
+                            let entries = std::mem::take(entries);
+                            if self.restore(entries)? {
+                                self.populate_state = PopulateState::Done;
+                                return Ok(IOResult::Done(()));
+                            }
+                            self.start_populating_table(conn, 0)?;
+                            break;
+                        }
+                        crate::vdbe::StepResult::Interrupt | crate::vdbe::StepResult::Busy => {
+                            return Err(LimboError::Busy);
+                        }
+                        crate::vdbe::StepResult::IO => {
+                            io_yield_one!(Completion::new_dummy());
+                        }
+                    }
+                },
+
                 PopulateState::Processing {
                     stmt,
+                    table_idx,
                     rows_processed,
                 } => {
+                    let table_idx = *table_idx;
+                    let table_name = self.base_tables[table_idx].name.clone();
                     // Collect rows into a delta batch
                     let mut batch_delta = Delta::new();
                     let mut batch_count = 0;
@@ -394,7 +490,7 @@ impl IncrementalView {
                     loop {
                         if batch_count >= BATCH_SIZE {
                             // Process this batch through the standard pipeline
-                            self.merge_delta(&batch_delta);
+                            self.merge_table_delta(&table_name, batch_delta);
                             // Yield control after processing a batch
                             // TODO: currently this inner statement is the one that is tracking completions
                             // so as a stop gap we can just return a dummy completion here
@@ -415,7 +511,7 @@ impl IncrementalView {
                                 // If there's a rowid alias (INTEGER PRIMARY KEY), the rowid is one of the columns
                                 // Otherwise, it's the last value we explicitly selected
                                 let (rowid, values) = if let Some((idx, _)) =
-                                    self.base_table.get_rowid_alias_column()
+                                    self.base_tables[table_idx].get_rowid_alias_column()
                                 {
                                     // The rowid is the value at the rowid alias column index
                                     let rowid = match all_values.get(idx) {
@@ -453,9 +549,15 @@ impl IncrementalView {
                             }
                             crate::vdbe::StepResult::Done => {
                                 // Process any remaining rows in the batch
-                                self.merge_delta(&batch_delta);
-                                // All rows processed, move to Done state
+                                self.merge_table_delta(&table_name, batch_delta);
+                                // Move on to the next table, or to Done state if this was the
+                                // last one
+                                if table_idx + 1 < self.base_tables.len() {
+                                    self.start_populating_table(conn, table_idx + 1)?;
+                                    break;
+                                }
                                 self.populate_state = PopulateState::Done;
+                                self.finish_populating(conn)?;
                                 return Ok(IOResult::Done(()));
                             }
                             crate::vdbe::StepResult::Interrupt | crate::vdbe::StepResult::Busy => {
@@ -463,7 +565,7 @@ impl IncrementalView {
                             }
                             crate::vdbe::StepResult::IO => {
                                 // Process current batch before yielding
-                                self.merge_delta(&batch_delta);
+                                self.merge_table_delta(&table_name, batch_delta);
                                 // The Statement needs to wait for IO
                                 io_yield_one!(Completion::new_dummy());
                             }
@@ -479,80 +581,151 @@ impl IncrementalView {
         }
     }
 
-    /// Extract JOIN information from SELECT statement
-    #[allow(clippy::type_complexity)]
-    pub fn extract_join_info(
-        select: &ast::Select,
-    ) -> (Option<(String, String)>, Option<(String, String)>) {
-        use turso_parser::ast::*;
-
-        if let OneSelect::Select {
-            from: Some(ref from),
-            ..
-        } = select.body.select
-        {
-            // Check if there are any joins
-            if !from.joins.is_empty() {
-                // Get the first (left) table name
-                let left_table = match from.select.as_ref() {
-                    SelectTable::Table(name, _, _) => Some(name.name.as_str().to_string()),
-                    _ => None,
-                };
+    /// Prepare the query that reads the state table of the view. Returns false if the view has
+    /// no state table to load from.
+    fn start_loading(&mut self, conn: &std::sync::Arc<crate::Connection>) -> crate::Result<bool> {
+        let has_state_table = conn
+            .schema
+            .borrow()
+            .get_btree_table(&state_table_name(&self.name))
+            .is_some();
+        if !has_state_table || conn._db.mv_store.is_some() {
+            return Ok(false);
+        }
+        let stmt = conn.prepare(state_table_query(&self.name))?;
+        self.populate_state = PopulateState::Loading {
+            stmt: Box::new(stmt),
+            entries: Vec::new(),
+        };
+        Ok(true)
+    }
 
-                // Get the first join (right) table and condition
-                if let Some(first_join) = from.joins.first() {
-                    let right_table = match &first_join.table.as_ref() {
-                        SelectTable::Table(name, _, _) => Some(name.name.as_str().to_string()),
-                        _ => None,
-                    };
+    /// Restore the view from the entries of its state table. Returns false if the table doesn't
+    /// hold the state of a populated view, in which case the view must be populated from its
+    /// base tables and the state table rewritten.
+    fn restore(&mut self, entries: Vec<StateEntry>) -> crate::Result<bool> {
+        let mut storage = ViewStorage::new(&self.name);
+        let version = entries
+            .iter()
+            .find(|entry| entry.operator_id == METADATA_OPERATOR_ID)
+            .map(|entry| entry.state.clone());
+        match version.as_deref() {
+            Some([Value::Integer(STATE_FORMAT_VERSION)]) => {}
+            Some(version) => {
+                return Err(LimboError::Corrupt(format!(
+                    "unsupported state format {version:?} for view {}",
+                    self.name
+                )));
+            }
+            None => {
+                // Leftovers of an incomplete state are removed when the new one is written
+                for entry in entries {
+                    storage.load_entry(entry.rowid, entry.operator_id, entry.key.clone());
+                    storage.record_change(entry.operator_id, entry.key, None);
+                }
+                self.storage = Some(storage);
+                return Ok(false);
+            }
+        }
 
-                    // Extract join condition (simplified - assumes single equality)
-                    let join_condition = if let Some(ref constraint) = &first_join.constraint {
-                        match constraint {
-                            JoinConstraint::On(expr) => Self::extract_join_columns_from_expr(expr),
-                            _ => None,
-                        }
-                    } else {
-                        None
+        let mut operator_entries: HashMap<usize, StateEntries> = HashMap::new();
+        let mut zset_delta = RowKeyZSet::new();
+        for entry in entries {
+            storage.load_entry(entry.rowid, entry.operator_id, entry.key.clone());
+            match entry.operator_id {
+                VIEW_ROWS_OPERATOR_ID => {
+                    let [Value::Integer(row_key)] = entry.key[..] else {
+                        return Err(LimboError::Corrupt(format!(
+                            "invalid row key {:?} in the state of view {}",
+                            entry.key, self.name
+                        )));
                     };
-
-                    if let (Some(left), Some(right)) = (left_table, right_table) {
-                        return (Some((left, right)), join_condition);
-                    }
+                    zset_delta.insert(HashableRow::new(row_key, entry.state.clone()), 1);
+                    self.records.insert(row_key, entry.state);
+                }
+                METADATA_OPERATOR_ID => {}
+                operator_id => {
+                    let node_id = usize::try_from(operator_id).map_err(|_| {
+                        LimboError::Corrupt(format!(
+                            "invalid operator {operator_id} in the state of view {}",
+                            self.name
+                        ))
+                    })?;
+                    operator_entries
+                        .entry(node_id)
+                        .or_default()
+                        .push((entry.key, entry.state));
                 }
             }
         }
-
-        (None, None)
+        self.circuit.restore_state(operator_entries)?;
+        self.circuit_initialized = true;
+        self.stream.apply_delta(&zset_delta);
+        self.storage = Some(storage);
+        Ok(true)
     }
 
-    /// Extract join column names from a join condition expression
-    fn extract_join_columns_from_expr(expr: &ast::Expr) -> Option<(String, String)> {
-        use turso_parser::ast::*;
-
-        // Look for expressions like: t1.col = t2.col
-        if let Expr::Binary(left, op, right) = expr {
-            if matches!(op, Operator::Equals) {
-                // Extract column names from both sides
-                let left_col = match &**left {
-                    Expr::Qualified(name, _) => Some(name.as_str().to_string()),
-                    Expr::Id(name) => Some(name.as_str().to_string()),
-                    _ => None,
-                };
-
-                let right_col = match &**right {
-                    Expr::Qualified(name, _) => Some(name.as_str().to_string()),
-                    Expr::Id(name) => Some(name.as_str().to_string()),
-                    _ => None,
-                };
-
-                if let (Some(l), Some(r)) = (left_col, right_col) {
-                    return Some((l, r));
-                }
-            }
+    /// Mark the state of a freshly populated view as complete. When populating the view is part
+    /// of a write transaction, like in CREATE MATERIALIZED VIEW, the state is written right away.
+    /// Otherwise it is written by the next transaction changing the view.
+    fn finish_populating(&mut self, conn: &std::sync::Arc<crate::Connection>) -> Result<()> {
+        let Some(storage) = self.storage.as_mut() else {
+            return Ok(());
+        };
+        storage.record_change(
+            METADATA_OPERATOR_ID,
+            Vec::new(),
+            Some(vec![Value::Integer(STATE_FORMAT_VERSION)]),
+        );
+        if matches!(conn.transaction_state.get(), TransactionState::Write { .. }) {
+            let schema = conn.schema.borrow().clone();
+            let pager = conn.pager.borrow().clone();
+            self.persist_changes(&schema, &pager)?;
         }
+        Ok(())
+    }
 
-        None
+    /// Write the changes to the view since they were last written to its state table. This must
+    /// be called from the write transaction that made them.
+    pub fn persist_changes(&mut self, schema: &Schema, pager: &Rc<Pager>) -> Result<()> {
+        let Some(storage) = self.storage.as_mut() else {
+            return Ok(());
+        };
+        let Some(table) = schema.get_btree_table(storage.table_name()) else {
+            return Err(LimboError::InternalError(format!(
+                "state table of view {} not found",
+                self.name
+            )));
+        };
+        storage.write_pending(pager, table.root_page)
+    }
+
+    /// Prepare the query that scans one of the base tables
+    fn start_populating_table(
+        &mut self,
+        conn: &std::sync::Arc<crate::Connection>,
+        table_idx: usize,
+    ) -> crate::Result<()> {
+        // Generate the SQL query for populating the view
+        // It is best to use a standard query than a cursor for two reasons:
+        // 1) Using a sql query will allow us to be much more efficient in cases where we only want
+        //    some rows, in particular for indexed filters
+        // 2) There are two types of cursors: index and table. In some situations (like for example
+        //    if the table has an integer primary key), the key will be exclusively in the index
+        //    btree and not in the table btree. Using cursors would force us to be aware of this
+        //    distinction (and others), and ultimately lead to reimplementing the whole query
+        //    machinery (next step is which index is best to use, etc)
+        let query = self.sql_for_populate(table_idx)?;
+
+        // Prepare the statement
+        let stmt = conn.prepare(&query)?;
+
+        self.populate_state = PopulateState::Processing {
+            stmt: Box::new(stmt),
+            table_idx,
+            rows_processed: 0,
+        };
+        Ok(())
     }
 
     /// Get the current records as an iterator - for cursor-based access
@@ -568,11 +741,11 @@ impl IncrementalView {
     pub fn current_data(&self, tx_state: Option<&ViewTransactionState>) -> Vec<(i64, Vec<Value>)> {
         if let Some(tx_state) = tx_state {
             // Use circuit to process uncommitted changes
-            let mut uncommitted = DeltaSet::new();
-            uncommitted.insert(self.base_table.name.clone(), tx_state.delta.clone());
-
             // Execute with uncommitted changes (won't affect circuit state)
-            match self.circuit.execute(HashMap::new(), uncommitted) {
+            match self
+                .circuit
+                .execute(HashMap::new(), tx_state.deltas.clone())
+            {
                 Ok(processed_delta) => {
                     // Merge processed delta with committed records
                     let mut result_map: BTreeMap<i64, Vec<Value>> = self.records.clone();
@@ -596,16 +769,22 @@ impl IncrementalView {
         }
     }
 
-    /// Merge a delta of changes into the view's current state
-    pub fn merge_delta(&mut self, delta: &Delta) {
-        // Early return if delta is empty
-        if delta.is_empty() {
+    /// Merge a delta of changes to one of the base tables into the view's current state
+    fn merge_table_delta(&mut self, table_name: &str, delta: Delta) {
+        let mut deltas = DeltaSet::new();
+        deltas.insert(table_name.to_string(), delta);
+        self.merge_delta(&deltas);
+    }
+
+    /// Merge the changes to the base tables into the view's current state
+    pub fn merge_delta(&mut self, deltas: &DeltaSet) {
+        // Early return if there are no changes
+        if deltas.is_empty() {
             return;
         }
 
-        // Use the circuit to process the delta
-        let mut input_data = HashMap::new();
-        input_data.insert(self.base_table.name.clone(), delta.clone());
+        // Use the circuit to process the deltas
+        let input_data = deltas.clone().into_map();
 
         // If circuit hasn't been initialized yet, initialize it first
         // This happens during populate_from_table
@@ -633,6 +812,7 @@ impl IncrementalView {
 
         // Update records and stream with the processed delta
         let mut zset_delta = RowKeyZSet::new();
+        let mut changed_rows = HashSet::new();
 
         for (row, weight) in &current_delta.changes {
             if *weight > 0 {
@@ -642,8 +822,22 @@ impl IncrementalView {
                 self.records.remove(&row.rowid);
                 zset_delta.insert(row.clone(), -1);
             }
+            changed_rows.insert(row.rowid);
         }
 
         self.stream.apply_delta(&zset_delta);
+
+        // Record what has to be written to the state table. The operators track their changes
+        // either way, so they are always taken.
+        let state_changes = self.circuit.take_state_changes();
+        if let Some(storage) = self.storage.as_mut() {
+            for row_key in changed_rows {
+                let values = self.records.get(&row_key).cloned();
+                storage.record_change(VIEW_ROWS_OPERATOR_ID, vec![Value::Integer(row_key)], values);
+            }
+            for (node_id, key, state) in state_changes {
+                storage.record_change(node_id as i64, key, state);
+            }
+        }
     }
 }
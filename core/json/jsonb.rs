@@ -2171,6 +2171,24 @@ impl Jsonb {
         Ok(element_type)
     }
 
+    /// Returns the type of the element starting at `idx`, the position of its payload and the
+    /// position right after it.
+    pub fn element_bounds(&self, idx: usize) -> Result<(ElementType, usize, usize)> {
+        let (JsonbHeader(element_type, payload_size), header_size) = self.read_header(idx)?;
+        let payload_start = idx + header_size;
+        let end = payload_start + payload_size;
+        if end > self.data.len() {
+            bail_parse_error!("malformed JSON");
+        }
+        Ok((element_type, payload_start, end))
+    }
+
+    /// Copies the element starting at `idx` into a new `Jsonb`.
+    pub fn element_at(&self, idx: usize) -> Result<Jsonb> {
+        let (_, _, end) = self.element_bounds(idx)?;
+        Ok(Self::from_raw_data(&self.data[idx..end]))
+    }
+
     pub fn array_len(&self) -> Result<usize> {
         let (header, header_skip) = self.read_header(0)?;
         if header.0 != ElementType::ARRAY {
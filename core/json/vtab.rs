@@ -0,0 +1,756 @@
+//! The `json_each` and `json_tree` table-valued functions
+//!
+//! Both walk a JSON value, optionally starting at the element selected by a path. `json_each`
+//! returns the direct children of that element, while `json_tree` returns the element itself
+//! and all of its descendants, in pre-order.
+
+use std::sync::Arc;
+
+use turso_ext::{
+    Connection, ConstraintInfo, ConstraintOp, ConstraintUsage, ExtensionApi, IndexInfo,
+    OrderByInfo, ResultCode, VTabCursor, VTabKind, VTabModule, VTabModuleDerive, VTable,
+    Value as ExtValue, ValueType as ExtValueType,
+};
+
+use crate::json::jsonb::{unescape_string, ElementType, Jsonb, SearchOperation};
+use crate::json::path::{json_path, PathElement};
+use crate::json::{convert_dbtype_to_jsonb, json_string_to_db_type, Conv, OutputVariant};
+use crate::types::{Text, Value};
+use crate::{bail_parse_error, LimboError, Result};
+
+pub fn register_extension(ext_api: &mut ExtensionApi) {
+    unsafe {
+        JsonEachVTabModule::register_JsonEachVTabModule(ext_api);
+        JsonTreeVTabModule::register_JsonTreeVTabModule(ext_api);
+    }
+}
+
+const KEY_COLUMN: u32 = 0;
+const VALUE_COLUMN: u32 = 1;
+const TYPE_COLUMN: u32 = 2;
+const ATOM_COLUMN: u32 = 3;
+const ID_COLUMN: u32 = 4;
+const PARENT_COLUMN: u32 = 5;
+const FULLKEY_COLUMN: u32 = 6;
+const PATH_COLUMN: u32 = 7;
+const JSON_COLUMN: u32 = 8;
+const ROOT_COLUMN: u32 = 9;
+
+fn schema(name: &str) -> String {
+    format!(
+        "CREATE TABLE {name} (
+            key,
+            value,
+            type,
+            atom,
+            id,
+            parent,
+            fullkey,
+            path,
+            json HIDDEN,
+            root HIDDEN
+        )"
+    )
+}
+
+/// Returns the direct children of a JSON array or object
+#[derive(Debug, VTabModuleDerive, Default)]
+struct JsonEachVTabModule;
+
+impl VTabModule for JsonEachVTabModule {
+    type Table = JsonEachTable;
+    const NAME: &'static str = "json_each";
+    const VTAB_KIND: VTabKind = VTabKind::TableValuedFunction;
+
+    fn create(_args: &[ExtValue]) -> std::result::Result<(String, Self::Table), ResultCode> {
+        Ok((schema(Self::NAME), JsonEachTable { recursive: false }))
+    }
+}
+
+/// Returns a JSON value and all of its descendants
+#[derive(Debug, VTabModuleDerive, Default)]
+struct JsonTreeVTabModule;
+
+impl VTabModule for JsonTreeVTabModule {
+    type Table = JsonEachTable;
+    const NAME: &'static str = "json_tree";
+    const VTAB_KIND: VTabKind = VTabKind::TableValuedFunction;
+
+    fn create(_args: &[ExtValue]) -> std::result::Result<(String, Self::Table), ResultCode> {
+        Ok((schema(Self::NAME), JsonEachTable { recursive: true }))
+    }
+}
+
+struct JsonEachTable {
+    recursive: bool,
+}
+
+impl VTable for JsonEachTable {
+    type Cursor = JsonEachCursor;
+    type Error = ResultCode;
+
+    fn open(
+        &self,
+        _conn: Option<Arc<Connection>>,
+    ) -> std::result::Result<Self::Cursor, Self::Error> {
+        Ok(JsonEachCursor {
+            recursive: self.recursive,
+            json: Jsonb::new(0, None),
+            input: Value::Null,
+            root: "$".to_string(),
+            rows: Vec::new(),
+            current: 0,
+            error: None,
+        })
+    }
+
+    fn best_index(
+        constraints: &[ConstraintInfo],
+        _order_by: &[OrderByInfo],
+    ) -> std::result::Result<IndexInfo, ResultCode> {
+        // The bits of `idx_num` tell the filter method which arguments are available:
+        // - Bit 0 set -> 'json' is available
+        // - Bit 1 set -> 'root' is available
+        // - Bit 2 set -> one of the arguments is NULL, so there are no rows
+        let mut idx_num = 0;
+        let mut positions = [None; 2]; // maps hidden column to constraint position
+
+        for (i, c) in constraints.iter().enumerate() {
+            if c.column_index != JSON_COLUMN && c.column_index != ROOT_COLUMN {
+                continue;
+            }
+            if !c.usable {
+                return Err(ResultCode::ConstraintViolation);
+            }
+            let arg = (c.column_index - JSON_COLUMN) as usize;
+            match c.op {
+                ConstraintOp::Eq => {
+                    idx_num |= 1 << arg;
+                    positions[arg] = Some(i);
+                }
+                ConstraintOp::IsNull => idx_num |= 4,
+                _ => {}
+            }
+        }
+
+        let json_argv = positions[0].map(|_| 1);
+        let root_argv = positions[1].map(|_| json_argv.map_or(1, |argv| argv + 1));
+
+        let constraint_usages = (0..constraints.len())
+            .map(|idx| {
+                let argv_index = if positions[0] == Some(idx) {
+                    json_argv
+                } else if positions[1] == Some(idx) {
+                    root_argv
+                } else {
+                    None
+                };
+                ConstraintUsage {
+                    argv_index,
+                    omit: argv_index.is_some(),
+                }
+            })
+            .collect();
+
+        Ok(IndexInfo {
+            idx_num,
+            idx_str: Some(idx_num.to_string()),
+            constraint_usages,
+            ..Default::default()
+        })
+    }
+}
+
+/// The key of a row: the label of an object member or the index of an array element
+#[derive(Debug, Clone, PartialEq)]
+enum JsonEachKey {
+    Label(String),
+    Index(i64),
+}
+
+/// A row of `json_each` or `json_tree`, pointing at an element of the cursor's JSON
+#[derive(Debug)]
+struct JsonEachRow {
+    key: Option<JsonEachKey>,
+    element_type: ElementType,
+    /// Position of the element in the JSON, also used as its id
+    id: usize,
+    /// Id of the containing element, only set by `json_tree`
+    parent: Option<usize>,
+    fullkey: String,
+    path: String,
+}
+
+/// The cursor over the rows computed by the filter method
+#[derive(Debug)]
+struct JsonEachCursor {
+    recursive: bool,
+    /// The element selected by the root path
+    json: Jsonb,
+    input: Value,
+    root: String,
+    rows: Vec<JsonEachRow>,
+    current: usize,
+    /// Why the last `filter` failed
+    error: Option<String>,
+}
+
+impl JsonEachCursor {
+    /// Parses `input` and computes the rows for the element at the `root` path. A path that
+    /// matches nothing yields no rows.
+    fn load(&mut self, input: Value, root: Option<String>) -> Result<()> {
+        let mut json = convert_dbtype_to_jsonb(&input, Conv::Strict)?;
+        let root = root.unwrap_or_else(|| "$".to_string());
+        let path = json_path(&root)?;
+
+        let mut key = None;
+        let mut fullkey = String::from("$");
+        let mut parent_path = String::from("$");
+        for element in &path.elements {
+            match element {
+                PathElement::Root() => {}
+                PathElement::Key(label, raw) => {
+                    parent_path = fullkey.clone();
+                    if *raw {
+                        fullkey.push_str(&format!(".\"{label}\""));
+                        key = Some(JsonEachKey::Label(unescape_string(label)));
+                    } else {
+                        fullkey.push_str(&format!(".{label}"));
+                        key = Some(JsonEachKey::Label(label.to_string()));
+                    }
+                }
+                PathElement::ArrayLocator(index) => {
+                    parent_path = fullkey.clone();
+                    match index {
+                        Some(index) if *index >= 0 => {
+                            fullkey.push_str(&format!("[{index}]"));
+                            key = Some(JsonEachKey::Index(*index as i64));
+                        }
+                        Some(index) => {
+                            fullkey.push_str(&format!("[#{index}]"));
+                            key = None;
+                        }
+                        None => {
+                            fullkey.push_str("[#]");
+                            key = None;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut op = SearchOperation::new(json.len());
+        let found = json.operate_on_path(&path, &mut op).is_ok();
+        self.json = op.result();
+        self.input = input;
+        self.root = root;
+        if !found {
+            return Ok(());
+        }
+
+        let (element_type, payload_start, end) = self.json.element_bounds(0)?;
+        let is_container = matches!(element_type, ElementType::ARRAY | ElementType::OBJECT);
+        if self.recursive || !is_container {
+            self.rows.push(JsonEachRow {
+                key,
+                element_type,
+                id: 0,
+                parent: None,
+                fullkey: fullkey.clone(),
+                path: parent_path,
+            });
+        }
+        if is_container {
+            self.push_children(0, element_type, payload_start, end, &fullkey)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a row for each child of the container at `id`, and for their descendants when
+    /// walking the whole tree.
+    fn push_children(
+        &mut self,
+        id: usize,
+        element_type: ElementType,
+        payload_start: usize,
+        end: usize,
+        path: &str,
+    ) -> Result<()> {
+        let mut pos = payload_start;
+        let mut index = 0;
+        while pos < end {
+            let (key, fullkey, value_pos) = if element_type == ElementType::OBJECT {
+                let (key_type, _, key_end) = self.json.element_bounds(pos)?;
+                if !key_type.is_valid_key() {
+                    bail_parse_error!("malformed JSON");
+                }
+                let (escaped, label) = string_at(&self.json, pos)?;
+                let fullkey = format!("{path}{}", label_path_element(&escaped));
+                (JsonEachKey::Label(label), fullkey, key_end)
+            } else {
+                (JsonEachKey::Index(index), format!("{path}[{index}]"), pos)
+            };
+
+            let (child_type, child_payload_start, child_end) =
+                self.json.element_bounds(value_pos)?;
+            if matches!(
+                child_type,
+                ElementType::RESERVED1 | ElementType::RESERVED2 | ElementType::RESERVED3
+            ) {
+                bail_parse_error!("malformed JSON");
+            }
+            self.rows.push(JsonEachRow {
+                key: Some(key),
+                element_type: child_type,
+                id: value_pos,
+                parent: self.recursive.then_some(id),
+                fullkey: fullkey.clone(),
+                path: path.to_string(),
+            });
+            if self.recursive && matches!(child_type, ElementType::ARRAY | ElementType::OBJECT) {
+                self.push_children(
+                    value_pos,
+                    child_type,
+                    child_payload_start,
+                    child_end,
+                    &fullkey,
+                )?;
+            }
+
+            pos = child_end;
+            index += 1;
+        }
+        Ok(())
+    }
+
+    /// The value of a row, with containers returned as JSON text
+    fn value(&self, row: &JsonEachRow) -> Result<ExtValue> {
+        Ok(match row.element_type {
+            ElementType::ARRAY | ElementType::OBJECT => {
+                ExtValue::from_json(self.json.element_at(row.id)?.to_string())
+            }
+            element_type if element_type.is_valid_key() => {
+                ExtValue::from_text(string_at(&self.json, row.id)?.1)
+            }
+            element_type => json_string_to_db_type(
+                self.json.element_at(row.id)?,
+                element_type,
+                OutputVariant::String,
+            )?
+            .to_ffi(),
+        })
+    }
+}
+
+/// Returns a string element as it is written in the JSON, without the quotes, and unescaped
+fn string_at(json: &Jsonb, idx: usize) -> Result<(String, String)> {
+    let quoted = json.element_at(idx)?.to_string();
+    let escaped = quoted[1..quoted.len() - 1].to_string();
+    let unescaped = unescape_string(&escaped);
+    Ok((escaped, unescaped))
+}
+
+/// Formats an object label as a path element, quoting it unless it is a plain identifier
+fn label_path_element(label: &str) -> String {
+    let is_identifier = label.starts_with(|c: char| c.is_ascii_alphabetic())
+        && label.chars().all(|c| c.is_ascii_alphanumeric());
+    if is_identifier {
+        format!(".{label}")
+    } else {
+        format!(".\"{label}\"")
+    }
+}
+
+/// Converts an argument of the filter method into a database value
+fn value_from_arg(arg: &ExtValue) -> Value {
+    match arg.value_type() {
+        ExtValueType::Integer => arg.to_integer().map_or(Value::Null, Value::Integer),
+        ExtValueType::Float => arg.to_float().map_or(Value::Null, Value::Float),
+        ExtValueType::Text => match arg.to_text() {
+            Some(text) if arg.is_json() => Value::Text(Text::json(text.to_string())),
+            Some(text) => Value::build_text(text),
+            None => Value::Null,
+        },
+        ExtValueType::Blob => arg.to_blob().map_or(Value::Null, Value::Blob),
+        ExtValueType::Null | ExtValueType::Error => Value::Null,
+    }
+}
+
+impl VTabCursor for JsonEachCursor {
+    type Error = ResultCode;
+
+    fn filter(&mut self, args: &[ExtValue], idx_info: Option<(&str, i32)>) -> ResultCode {
+        self.rows.clear();
+        self.current = 0;
+        self.error = None;
+
+        // For the semantics of `idx_num`, see the comment in the `best_index` method.
+        let idx_num = idx_info.map_or(0, |(_, idx_num)| idx_num);
+        if idx_num & 1 == 0 || idx_num & 4 != 0 {
+            return ResultCode::EOF;
+        }
+        let input = match args.first().map(value_from_arg) {
+            Some(Value::Null) | None => return ResultCode::EOF,
+            Some(input) => input,
+        };
+        let root = if idx_num & 2 != 0 {
+            match args.get(1) {
+                Some(arg) if arg.value_type() == ExtValueType::Null => return ResultCode::EOF,
+                Some(arg) => match arg.to_text() {
+                    Some(root) => Some(root.to_string()),
+                    None => return ResultCode::InvalidArgs,
+                },
+                None => return ResultCode::InvalidArgs,
+            }
+        } else {
+            None
+        };
+
+        if let Err(e) = self.load(input, root) {
+            self.rows.clear();
+            self.error = Some(match e {
+                LimboError::ParseError(message) => message,
+                e => e.to_string(),
+            });
+            return ResultCode::InvalidArgs;
+        }
+        if self.rows.is_empty() {
+            return ResultCode::EOF;
+        }
+        ResultCode::OK
+    }
+
+    fn next(&mut self) -> ResultCode {
+        if self.eof() {
+            return ResultCode::EOF;
+        }
+        self.current += 1;
+        if self.eof() {
+            return ResultCode::EOF;
+        }
+        ResultCode::OK
+    }
+
+    fn eof(&self) -> bool {
+        self.current >= self.rows.len()
+    }
+
+    fn error_message(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    fn column(&self, idx: u32) -> std::result::Result<ExtValue, Self::Error> {
+        if idx == JSON_COLUMN {
+            return Ok(self.input.to_ffi());
+        }
+        if idx == ROOT_COLUMN {
+            return Ok(ExtValue::from_text(self.root.clone()));
+        }
+        let Some(row) = self.rows.get(self.current) else {
+            return Ok(ExtValue::null());
+        };
+        let is_container = matches!(row.element_type, ElementType::ARRAY | ElementType::OBJECT);
+        Ok(match idx {
+            KEY_COLUMN => match &row.key {
+                Some(JsonEachKey::Label(label)) => ExtValue::from_text(label.clone()),
+                Some(JsonEachKey::Index(index)) => ExtValue::from_integer(*index),
+                None => ExtValue::null(),
+            },
+            VALUE_COLUMN => self.value(row).map_err(|_| ResultCode::Error)?,
+            TYPE_COLUMN => ExtValue::from_text(String::from(row.element_type)),
+            ATOM_COLUMN if is_container => ExtValue::null(),
+            ATOM_COLUMN => self.value(row).map_err(|_| ResultCode::Error)?,
+            ID_COLUMN => ExtValue::from_integer(row.id as i64),
+            PARENT_COLUMN => match row.parent {
+                Some(parent) => ExtValue::from_integer(parent as i64),
+                None => ExtValue::null(),
+            },
+            FULLKEY_COLUMN => ExtValue::from_text(row.fullkey.clone()),
+            PATH_COLUMN => ExtValue::from_text(row.path.clone()),
+            _ => ExtValue::null(),
+        })
+    }
+
+    fn rowid(&self) -> i64 {
+        self.current as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Cell {
+        Null,
+        Integer(i64),
+        Text(String),
+    }
+
+    fn cell(value: ExtValue) -> Cell {
+        match value.value_type() {
+            ExtValueType::Integer => Cell::Integer(value.to_integer().unwrap()),
+            ExtValueType::Text => Cell::Text(value.to_text().unwrap().to_string()),
+            _ => Cell::Null,
+        }
+    }
+
+    fn text(s: &str) -> Cell {
+        Cell::Text(s.to_string())
+    }
+
+    // Collects the key, value, type, fullkey and path columns of every row
+    fn collect_rows(recursive: bool, json: &str, root: Option<&str>) -> Vec<Vec<Cell>> {
+        let table = JsonEachTable { recursive };
+        let mut cursor = table.open(None).unwrap();
+        let mut args = vec![ExtValue::from_text(json.to_string())];
+        let mut idx_num = 1;
+        if let Some(root) = root {
+            args.push(ExtValue::from_text(root.to_string()));
+            idx_num |= 2;
+        }
+
+        let mut rows = Vec::new();
+        let mut rc = cursor.filter(&args, Some(("", idx_num)));
+        while rc == ResultCode::OK {
+            let row = [
+                KEY_COLUMN,
+                VALUE_COLUMN,
+                TYPE_COLUMN,
+                FULLKEY_COLUMN,
+                PATH_COLUMN,
+            ]
+            .into_iter()
+            .map(|idx| cell(cursor.column(idx).unwrap()))
+            .collect();
+            rows.push(row);
+            rc = cursor.next();
+        }
+        assert_eq!(rc, ResultCode::EOF);
+        rows
+    }
+
+    #[test]
+    fn test_json_each_array_and_object() {
+        assert_eq!(
+            collect_rows(false, r#"[1, "two", null]"#, None),
+            vec![
+                vec![
+                    Cell::Integer(0),
+                    Cell::Integer(1),
+                    text("integer"),
+                    text("$[0]"),
+                    text("$")
+                ],
+                vec![
+                    Cell::Integer(1),
+                    text("two"),
+                    text("text"),
+                    text("$[1]"),
+                    text("$")
+                ],
+                vec![
+                    Cell::Integer(2),
+                    Cell::Null,
+                    text("null"),
+                    text("$[2]"),
+                    text("$")
+                ],
+            ]
+        );
+        assert_eq!(
+            collect_rows(false, r#"{"a": [1], "b c": true}"#, None),
+            vec![
+                vec![
+                    text("a"),
+                    text("[1]"),
+                    text("array"),
+                    text("$.a"),
+                    text("$")
+                ],
+                vec![
+                    text("b c"),
+                    Cell::Integer(1),
+                    text("true"),
+                    text("$.\"b c\""),
+                    text("$")
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_each_with_root() {
+        assert_eq!(
+            collect_rows(false, r#"{"a": {"x": 1, "y": 2}}"#, Some("$.a")),
+            vec![
+                vec![
+                    text("x"),
+                    Cell::Integer(1),
+                    text("integer"),
+                    text("$.a.x"),
+                    text("$.a")
+                ],
+                vec![
+                    text("y"),
+                    Cell::Integer(2),
+                    text("integer"),
+                    text("$.a.y"),
+                    text("$.a")
+                ],
+            ]
+        );
+        assert_eq!(
+            collect_rows(false, r#"{"a": [5, 6]}"#, Some("$.a[1]")),
+            vec![vec![
+                Cell::Integer(1),
+                Cell::Integer(6),
+                text("integer"),
+                text("$.a[1]"),
+                text("$.a")
+            ]]
+        );
+        assert_eq!(
+            collect_rows(false, "7", None),
+            vec![vec![
+                Cell::Null,
+                Cell::Integer(7),
+                text("integer"),
+                text("$"),
+                text("$")
+            ]]
+        );
+        assert!(collect_rows(false, r#"{"a": 1}"#, Some("$.b")).is_empty());
+        assert!(collect_rows(false, "[]", None).is_empty());
+    }
+
+    #[test]
+    fn test_json_tree() {
+        assert_eq!(
+            collect_rows(true, r#"{"a": [1, {"b": "x"}]}"#, None),
+            vec![
+                vec![
+                    Cell::Null,
+                    text(r#"{"a":[1,{"b":"x"}]}"#),
+                    text("object"),
+                    text("$"),
+                    text("$")
+                ],
+                vec![
+                    text("a"),
+                    text(r#"[1,{"b":"x"}]"#),
+                    text("array"),
+                    text("$.a"),
+                    text("$")
+                ],
+                vec![
+                    Cell::Integer(0),
+                    Cell::Integer(1),
+                    text("integer"),
+                    text("$.a[0]"),
+                    text("$.a")
+                ],
+                vec![
+                    Cell::Integer(1),
+                    text(r#"{"b":"x"}"#),
+                    text("object"),
+                    text("$.a[1]"),
+                    text("$.a")
+                ],
+                vec![
+                    text("b"),
+                    text("x"),
+                    text("text"),
+                    text("$.a[1].b"),
+                    text("$.a[1]")
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_tree_ids_and_parents() {
+        let table = JsonEachTable { recursive: true };
+        let mut cursor = table.open(None).unwrap();
+        let args = [ExtValue::from_text(r#"[[1]]"#.to_string())];
+        assert_eq!(cursor.filter(&args, Some(("", 1))), ResultCode::OK);
+
+        let mut ids = Vec::new();
+        loop {
+            let id = cursor.column(ID_COLUMN).unwrap().to_integer().unwrap();
+            let parent = cursor.column(PARENT_COLUMN).unwrap().to_integer();
+            ids.push((id, parent));
+            if cursor.next() == ResultCode::EOF {
+                break;
+            }
+        }
+        assert_eq!(ids.len(), 3);
+        assert_eq!(ids[0].1, None);
+        assert_eq!(ids[1].1, Some(ids[0].0));
+        assert_eq!(ids[2].1, Some(ids[1].0));
+    }
+
+    #[test]
+    fn test_json_each_escaped_strings() {
+        assert_eq!(
+            collect_rows(false, r#"{"a\"b": "line\nbreak"}"#, None),
+            vec![vec![
+                text("a\"b"),
+                text("line\nbreak"),
+                text("text"),
+                text(r#"$."a\"b""#),
+                text("$")
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_json_each_invalid_arguments() {
+        let table = JsonEachTable { recursive: false };
+        let mut cursor = table.open(None).unwrap();
+        let args = [ExtValue::from_text("{not json".to_string())];
+        assert_eq!(cursor.filter(&args, Some(("", 1))), ResultCode::InvalidArgs);
+
+        let args = [
+            ExtValue::from_text("[1]".to_string()),
+            ExtValue::from_text("not a path".to_string()),
+        ];
+        assert_eq!(cursor.filter(&args, Some(("", 3))), ResultCode::InvalidArgs);
+
+        let args = [ExtValue::null()];
+        assert_eq!(cursor.filter(&args, Some(("", 1))), ResultCode::EOF);
+        assert_eq!(cursor.filter(&[], Some(("", 4))), ResultCode::EOF);
+    }
+
+    #[test]
+    fn test_best_index_requires_usable_arguments() {
+        let constraint = |column_index, op, usable| ConstraintInfo {
+            column_index,
+            op,
+            usable,
+            index: 0,
+        };
+
+        let info = JsonEachTable::best_index(
+            &[
+                constraint(KEY_COLUMN, ConstraintOp::Eq, true),
+                constraint(ROOT_COLUMN, ConstraintOp::Eq, true),
+                constraint(JSON_COLUMN, ConstraintOp::Eq, true),
+            ],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(info.idx_num, 3);
+        let argv: Vec<_> = info
+            .constraint_usages
+            .iter()
+            .map(|u| u.argv_index)
+            .collect();
+        assert_eq!(argv, vec![None, Some(2), Some(1)]);
+
+        assert!(matches!(
+            JsonEachTable::best_index(&[constraint(JSON_COLUMN, ConstraintOp::Eq, false)], &[]),
+            Err(ResultCode::ConstraintViolation)
+        ));
+    }
+}
@@ -29,6 +29,7 @@ pub mod types;
 mod util;
 #[cfg(feature = "uuid")]
 mod uuid;
+mod vacuum;
 mod vdbe;
 mod vector;
 mod vtab;
@@ -42,6 +43,7 @@ mod numeric;
 
 use crate::incremental::view::ViewTransactionState;
 use crate::storage::encryption::CipherMode;
+use crate::translate::collate::{CollationFn, CollationSeq, CustomCollation};
 use crate::translate::optimizer::optimize_plan;
 use crate::translate::pragma::TURSO_CDC_DEFAULT_TABLE_NAME;
 #[cfg(all(feature = "fs", feature = "conn_raw_api"))]
@@ -49,9 +51,10 @@ use crate::types::{WalFrameInfo, WalState};
 #[cfg(feature = "fs")]
 use crate::util::{OpenMode, OpenOptions};
 use crate::vdbe::metrics::ConnectionMetrics;
-use crate::vtab::VirtualTable;
+use crate::vtab::{VTabTransactionOp, VirtualTable};
 use core::str;
 pub use error::{CompletionError, LimboError};
+pub use function::{AggregateState, FunctionFlags};
 pub use io::clock::{Clock, Instant};
 #[cfg(all(feature = "fs", target_family = "unix"))]
 pub use io::UnixIO;
@@ -66,11 +69,10 @@ use schema::Schema;
 use std::{
     borrow::Cow,
     cell::{Cell, RefCell, UnsafeCell},
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::{self, Display},
     io::Write,
     num::NonZero,
-    ops::Deref,
     rc::Rc,
     sync::{atomic::AtomicUsize, Arc, LazyLock, Mutex, Weak},
 };
@@ -156,12 +158,40 @@ enum TransactionState {
     None,
 }
 
+/// A savepoint opened with `SAVEPOINT name`.
+#[derive(Debug, Clone)]
+struct Savepoint {
+    name: String,
+    /// Whether the savepoint was opened outside of a transaction and therefore began one.
+    /// Releasing such a savepoint commits the transaction.
+    starts_transaction: bool,
+    /// Schema as of the savepoint, restored by `ROLLBACK TO`.
+    schema: Arc<Schema>,
+    /// Schema of the `temp` database as of the savepoint.
+    temp_schema: Arc<Schema>,
+    /// Uncommitted incremental view deltas as of the savepoint.
+    view_transaction_states: HashMap<String, ViewTransactionState>,
+    /// Marker into the MVCC transaction's change log, if MVCC is enabled.
+    mv_savepoint: usize,
+    /// Deferred foreign key violations as of the savepoint.
+    deferred_fk_violations: i64,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum SyncMode {
     Off = 0,
     Full = 2,
 }
 
+/// Where the `temp` database of a connection is stored, see `PRAGMA temp_store`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TempStore {
+    /// Same as `File`.
+    Default = 0,
+    File = 1,
+    Memory = 2,
+}
+
 pub(crate) type MvStore = mvcc::MvStore<mvcc::LocalClock>;
 
 pub(crate) type MvCursor = mvcc::cursor::MvccLazyCursor<mvcc::LocalClock>;
@@ -331,6 +361,9 @@ impl Database {
         if let Some(db) = registry.get(&canonical_path).and_then(Weak::upgrade) {
             return Ok(db);
         }
+        // Nobody has the database open, so a commit it took part in may have been interrupted.
+        #[cfg(feature = "fs")]
+        storage::super_journal::recover(&*io, &format!("{path}-wal"))?;
         let db = Self::open_with_flags_bypass_registry_internal(
             io,
             path,
@@ -368,10 +401,15 @@ impl Database {
         let maybe_shared_wal = WalFileShared::open_shared_if_exists(&io, wal_path)?;
 
         let mv_store = if opts.enable_mvcc {
-            Some(Arc::new(MvStore::new(
-                mvcc::LocalClock::new(),
-                mvcc::persistent_storage::Storage::new_noop(),
-            )))
+            let storage = if path.starts_with(":memory:") {
+                mvcc::persistent_storage::Storage::new_noop()
+            } else {
+                mvcc::persistent_storage::Storage::new_logical_log(
+                    io.clone(),
+                    &format!("{path}-log"),
+                )?
+            };
+            Some(MvStore::new(mvcc::LocalClock::new(), storage))
         } else {
             None
         };
@@ -411,6 +449,13 @@ impl Database {
         db.register_global_builtin_extensions()
             .expect("unable to register global extensions");
 
+        if let Some(mv_store) = &db.mv_store {
+            // Transactions that were only logged must reach the B-tree before the schema is read.
+            let conn = db.connect()?;
+            let pager = conn.pager.borrow().clone();
+            mv_store.recover(&pager, &conn)?;
+        }
+
         // Check: https://github.com/tursodatabase/turso/pull/1761#discussion_r2154013123
         if db_state.is_initialized() {
             // parse schema
@@ -460,19 +505,22 @@ impl Database {
             .unwrap_or_default()
             .get();
 
+        let schema = self
+            .schema
+            .lock()
+            .map_err(|_| LimboError::SchemaLocked)?
+            .clone();
+        let temp_schema = Arc::new(Schema::new(schema.indexes_enabled()));
         let conn = Arc::new(Connection {
             _db: self.clone(),
             pager: RefCell::new(Rc::new(pager)),
-            schema: RefCell::new(
-                self.schema
-                    .lock()
-                    .map_err(|_| LimboError::SchemaLocked)?
-                    .clone(),
-            ),
+            schema: RefCell::new(schema),
+            temp_schema: RefCell::new(temp_schema),
             database_schemas: RefCell::new(std::collections::HashMap::new()),
             auto_commit: Cell::new(true),
             mv_transactions: RefCell::new(Vec::new()),
             transaction_state: Cell::new(TransactionState::None),
+            savepoints: RefCell::new(Vec::new()),
             last_insert_rowid: Cell::new(0),
             last_change: Cell::new(0),
             total_changes: Cell::new(0),
@@ -484,14 +532,23 @@ impl Database {
             capture_data_changes: RefCell::new(CaptureDataChangesMode::Off),
             closed: Cell::new(false),
             attached_databases: RefCell::new(DatabaseCatalog::new()),
+            attached_transactions: RefCell::new(BTreeMap::new()),
+            attached_commit: RefCell::new(None),
             query_only: Cell::new(false),
+            foreign_keys: Cell::new(false),
+            defer_foreign_keys: Cell::new(false),
+            deferred_fk_violations: Cell::new(0),
             mv_tx_id: Cell::new(None),
             view_transaction_states: RefCell::new(HashMap::new()),
+            vtab_transactions: RefCell::new(Vec::new()),
             metrics: RefCell::new(ConnectionMetrics::new()),
             is_nested_stmt: Cell::new(false),
             encryption_key: RefCell::new(None),
             encryption_cipher_mode: Cell::new(None),
             sync_mode: Cell::new(SyncMode::Full),
+            temp_store: Cell::new(TempStore::Default),
+            isolation_level: Cell::new(mvcc::IsolationLevel::default()),
+            temp_database: RefCell::new(None),
         });
         self.n_connections
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -780,6 +837,37 @@ impl CaptureDataChangesMode {
     }
 }
 
+/// The `temp` database of a connection, which holds its TEMP tables, indexes and views.
+/// It has no WAL: the pages changed by a transaction stay in the page cache until it commits,
+/// and are then written straight to its file.
+struct TempDatabase {
+    pager: Rc<Pager>,
+    /// The file backing the database, `None` if it is kept in memory.
+    path: Option<String>,
+    /// The schema of the database as of the last commit, restored on rollback.
+    committed_schema: Arc<Schema>,
+}
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// The attached databases that committed as part of a transaction whose main database has not
+/// committed yet.
+struct AttachedCommit {
+    /// The size of the WAL of each database before the commit, by database index.
+    wal_sizes: Vec<(usize, u64)>,
+    /// The schemas the databases get once the whole transaction has committed.
+    schemas: Vec<(Arc<Schema>, Arc<Database>)>,
+    /// The super-journal of the commit, if it writes to more than one database file.
+    #[cfg(feature = "fs")]
+    journal: Option<storage::super_journal::SuperJournal>,
+}
+
 // Optimized for fast get() operations and supports unlimited attached databases.
 struct DatabaseCatalog {
     name_to_index: HashMap<String, usize>,
@@ -891,6 +979,8 @@ pub struct Connection {
     _db: Arc<Database>,
     pager: RefCell<Rc<Pager>>,
     schema: RefCell<Arc<Schema>>,
+    /// Schema of the `temp` database, see [Self::statement_schema].
+    temp_schema: RefCell<Arc<Schema>>,
     /// Per-database schema cache (database_index -> schema)
     /// Loaded lazily to avoid copying all schemas on connection open
     database_schemas: RefCell<std::collections::HashMap<usize, Arc<Schema>>>,
@@ -899,6 +989,8 @@ pub struct Connection {
     /// Transactions that are in progress.
     mv_transactions: RefCell<Vec<crate::mvcc::database::TxID>>,
     transaction_state: Cell<TransactionState>,
+    /// Open savepoints, oldest first.
+    savepoints: RefCell<Vec<Savepoint>>,
     last_insert_rowid: Cell<i64>,
     last_change: Cell<i64>,
     total_changes: Cell<i64>,
@@ -915,12 +1007,28 @@ pub struct Connection {
     closed: Cell<bool>,
     /// Attached databases
     attached_databases: RefCell<DatabaseCatalog>,
+    /// Transactions open on attached databases, by database index. The value tells whether the
+    /// transaction writes. They end together with the transaction of the main database.
+    attached_transactions: RefCell<BTreeMap<usize, bool>>,
+    /// Attached databases that committed before the main database did, see
+    /// [Self::end_attached_transactions].
+    attached_commit: RefCell<Option<AttachedCommit>>,
     query_only: Cell<bool>,
+    /// Whether foreign key constraints are enforced, see `PRAGMA foreign_keys`.
+    foreign_keys: Cell<bool>,
+    /// Whether every foreign key constraint is checked at commit until the current
+    /// transaction ends, see `PRAGMA defer_foreign_keys`.
+    defer_foreign_keys: Cell<bool>,
+    /// Number of foreign key violations that must be resolved before the current
+    /// transaction commits.
+    deferred_fk_violations: Cell<i64>,
     pub(crate) mv_tx_id: Cell<Option<crate::mvcc::database::TxID>>,
 
     /// Per-connection view transaction states for uncommitted changes. This represents
     /// one entry per view that was touched in the transaction.
     view_transaction_states: RefCell<HashMap<String, ViewTransactionState>>,
+    /// Virtual tables written by the current transaction, in the order they joined it.
+    vtab_transactions: RefCell<Vec<Arc<VirtualTable>>>,
     /// Connection-level metrics aggregation
     pub metrics: RefCell<ConnectionMetrics>,
     /// Whether the connection is executing a statement initiated by another statement.
@@ -929,6 +1037,11 @@ pub struct Connection {
     encryption_key: RefCell<Option<EncryptionKey>>,
     encryption_cipher_mode: Cell<Option<CipherMode>>,
     sync_mode: Cell<SyncMode>,
+    temp_store: Cell<TempStore>,
+    /// Isolation level of the MVCC transactions started by the connection.
+    isolation_level: Cell<mvcc::IsolationLevel>,
+    /// Opened on first use.
+    temp_database: RefCell<Option<TempDatabase>>,
 }
 
 impl Drop for Connection {
@@ -969,7 +1082,7 @@ impl Connection {
         match cmd {
             Cmd::Stmt(stmt) => {
                 let program = translate::translate(
-                    self.schema.borrow().deref(),
+                    &self.statement_schema(),
                     stmt,
                     pager.clone(),
                     self.clone(),
@@ -1080,7 +1193,14 @@ impl Connection {
         let stmt = self.prepare("SELECT * FROM sqlite_schema")?;
 
         // TODO: This function below is synchronous, make it async
-        parse_schema_rows(stmt, &mut fresh, &self.syms.borrow(), None, existing_views)?;
+        parse_schema_rows(
+            stmt,
+            &mut fresh,
+            &self.syms.borrow(),
+            None,
+            existing_views,
+            false,
+        )?;
 
         tracing::debug!(
             "reparse_schema: schema_version={}, tables={:?}",
@@ -1123,7 +1243,7 @@ impl Connection {
             match cmd {
                 Cmd::Stmt(stmt) => {
                     let program = translate::translate(
-                        self.schema.borrow().deref(),
+                        &self.statement_schema(),
                         stmt,
                         pager.clone(),
                         self.clone(),
@@ -1174,7 +1294,7 @@ impl Connection {
         match cmd {
             Cmd::Stmt(ref stmt) | Cmd::Explain(ref stmt) => {
                 let program = translate::translate(
-                    self.schema.borrow().deref(),
+                    &self.statement_schema(),
                     stmt.clone(),
                     pager.clone(),
                     self.clone(),
@@ -1189,8 +1309,9 @@ impl Connection {
                 let mut table_ref_counter = TableRefIdCounter::new();
                 match stmt {
                     ast::Stmt::Select(select) => {
+                        let schema = self.statement_schema();
                         let mut plan = prepare_select_plan(
-                            self.schema.borrow().deref(),
+                            &schema,
                             select,
                             &syms,
                             &[],
@@ -1198,7 +1319,7 @@ impl Connection {
                             translate::plan::QueryDestination::ResultRows,
                             &self.clone(),
                         )?;
-                        optimize_plan(&mut plan, self.schema.borrow().deref())?;
+                        optimize_plan(&mut plan, &schema)?;
                         let _ = std::io::stdout().write_all(plan.to_string().as_bytes());
                     }
                     _ => todo!(),
@@ -1232,7 +1353,7 @@ impl Connection {
             match cmd {
                 Cmd::Explain(stmt) => {
                     let program = translate::translate(
-                        self.schema.borrow().deref(),
+                        &self.statement_schema(),
                         stmt,
                         pager,
                         self.clone(),
@@ -1245,7 +1366,7 @@ impl Connection {
                 Cmd::ExplainQueryPlan(_stmt) => todo!(),
                 Cmd::Stmt(stmt) => {
                     let program = translate::translate(
-                        self.schema.borrow().deref(),
+                        &self.statement_schema(),
                         stmt,
                         pager.clone(),
                         self.clone(),
@@ -1314,9 +1435,7 @@ impl Connection {
 
     #[cfg(feature = "fs")]
     fn from_uri_attached(uri: &str, db_opts: DatabaseOpts) -> Result<Arc<Database>> {
-        let mut opts = OpenOptions::parse(uri)?;
-        // FIXME: for now, only support read only attach
-        opts.mode = OpenMode::ReadOnly;
+        let opts = OpenOptions::parse(uri)?;
         let flags = opts.get_flags()?;
         let (_io, db) = Database::open_new(&opts.path, opts.vfs.as_ref(), flags, db_opts)?;
         if let Some(modeof) = opts.modeof {
@@ -1536,6 +1655,12 @@ impl Connection {
         if self.closed.get() {
             return Err(LimboError::InternalError("Connection closed".to_string()));
         }
+        if let Some(mv_store) = &self._db.mv_store {
+            // The open transaction holds the pager, so the log is left to a later checkpoint.
+            if self.mv_tx_id.get().is_none() {
+                mv_store.checkpoint(&self.pager.borrow(), self)?;
+            }
+        }
         self.pager.borrow().wal_checkpoint(mode)
     }
 
@@ -1545,12 +1670,14 @@ impl Connection {
             return Ok(());
         }
         self.closed.set(true);
+        self.temp_database.replace(None);
 
         match self.transaction_state.get() {
             TransactionState::None => {
                 // No active transaction
             }
             _ => {
+                self.end_attached_transactions(true)?;
                 let pager = self.pager.borrow();
                 pager.io.block(|| {
                     pager.end_tx(
@@ -1568,6 +1695,15 @@ impl Connection {
             .fetch_sub(1, std::sync::atomic::Ordering::Relaxed)
             .eq(&1)
         {
+            if let Some(mv_store) = &self._db.mv_store {
+                // Like in [Self::checkpoint], the pager of an open transaction is left alone.
+                if !self.wal_auto_checkpoint_disabled.get() && self.mv_tx_id.get().is_none() {
+                    // A transaction begun on the store directly may still hold the read lock.
+                    let pager = self.pager.borrow();
+                    pager.end_read_tx()?;
+                    mv_store.checkpoint(&pager, self)?;
+                }
+            }
             self.pager
                 .borrow()
                 .checkpoint_shutdown(self.wal_auto_checkpoint_disabled.get())?;
@@ -1635,6 +1771,8 @@ impl Connection {
     pub fn is_readonly(&self, index: usize) -> bool {
         if index == 0 {
             self._db.is_readonly()
+        } else if index == 1 {
+            false
         } else {
             let db = self
                 .attached_databases
@@ -1693,10 +1831,256 @@ impl Connection {
         all_vfs
     }
 
+    /// Registers a collation sequence on this connection that orders text with `compare`,
+    /// usable with `COLLATE name` in expressions, `ORDER BY` terms, column definitions and
+    /// indexes.
+    ///
+    /// Registering an existing name replaces its comparison function; the built-in sequences
+    /// cannot be replaced. The tables using the collation sequence are loaded again with their
+    /// indexes, including the ones that could not be loaded before it was registered.
+    pub fn create_collation(
+        self: &Arc<Connection>,
+        name: &str,
+        compare: impl Fn(&str, &str) -> std::cmp::Ordering + Send + Sync + 'static,
+    ) -> Result<()> {
+        self.syms
+            .borrow_mut()
+            .register_collation(name, Box::new(compare))?;
+        if self.is_db_initialized() {
+            self.reload_tables_using_collation(name)?;
+        }
+        Ok(())
+    }
+
+    /// Parses the schema rows of the tables of the main database that use the collation
+    /// sequence `name` again, so that they resolve it to the one registered now.
+    fn reload_tables_using_collation(self: &Arc<Connection>, name: &str) -> Result<()> {
+        let tables = self.schema.borrow().tables_using_collation(name);
+        if tables.is_empty() {
+            return Ok(());
+        }
+        let table_names = tables
+            .iter()
+            .map(|table| format!("'{}'", table.replace('\'', "''")))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let rows = self
+            .query(format!(
+                "SELECT * FROM sqlite_schema WHERE type IN ('table', 'index') AND lower(tbl_name) IN ({table_names})"
+            ))?
+            .expect("query must be parsed to statement");
+        let syms = self.syms.borrow();
+        self.with_schema_mut(|schema| {
+            for table in &tables {
+                schema.unresolved_tables.remove(table);
+                schema.remove_table(table);
+                schema.remove_indices_for_table(table);
+            }
+            let existing_views = schema.materialized_views.clone();
+            parse_schema_rows(rows, schema, &syms, None, existing_views, false)
+        })
+    }
+
+    /// Registers a scalar function implemented by `func`, replacing any function with the same
+    /// name on this connection. `func` receives the arguments of each call.
+    pub fn create_scalar_function(
+        &self,
+        name: &str,
+        flags: FunctionFlags,
+        func: impl Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    ) -> Result<()> {
+        let func =
+            function::ExternalFunc::new_native_scalar(name.to_string(), flags, Arc::new(func));
+        self.syms
+            .borrow_mut()
+            .functions
+            .insert(name.to_string(), Arc::new(func));
+        Ok(())
+    }
+
+    /// Registers an aggregate function taking `argc` arguments, replacing any function with the
+    /// same name on this connection. `init` creates the accumulator of each group.
+    pub fn create_aggregate_function(
+        &self,
+        name: &str,
+        argc: usize,
+        flags: FunctionFlags,
+        init: impl Fn() -> Box<dyn AggregateState> + Send + Sync + 'static,
+    ) -> Result<()> {
+        let func = function::ExternalFunc::new_native_aggregate(
+            name.to_string(),
+            argc,
+            flags,
+            Arc::new(init),
+        );
+        self.syms
+            .borrow_mut()
+            .functions
+            .insert(name.to_string(), Arc::new(func));
+        Ok(())
+    }
+
     pub fn get_auto_commit(&self) -> bool {
         self.auto_commit.get()
     }
 
+    /// Returns the position of the most recent savepoint named `name`.
+    pub(crate) fn find_savepoint(&self, name: &str) -> Option<usize> {
+        self.savepoints
+            .borrow()
+            .iter()
+            .rposition(|savepoint| savepoint.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Opens a new savepoint on top of the savepoint stack.
+    pub(crate) fn open_savepoint(&self, name: &str, mv_store: Option<&Arc<MvStore>>) -> Result<()> {
+        let mv_savepoint = match (mv_store, self.mv_tx_id.get()) {
+            (Some(mv_store), Some(tx_id)) => mv_store.savepoint(tx_id)?,
+            _ => 0,
+        };
+        let position = self.savepoints.borrow().len();
+        self.vtab_transaction_op(VTabTransactionOp::Savepoint(position))?;
+        self.pager.borrow().open_savepoint()?;
+        if let Some(temp_pager) = self.opened_temp_pager() {
+            temp_pager.open_savepoint()?;
+        }
+        self.savepoints.borrow_mut().push(Savepoint {
+            name: name.to_string(),
+            starts_transaction: self.auto_commit.get(),
+            schema: self.schema.borrow().clone(),
+            temp_schema: self.temp_schema.borrow().clone(),
+            view_transaction_states: self.view_transaction_states.borrow().clone(),
+            mv_savepoint,
+            deferred_fk_violations: self.deferred_fk_violations.get(),
+        });
+        Ok(())
+    }
+
+    /// Whether the savepoint at `position` started the current transaction.
+    pub(crate) fn savepoint_starts_transaction(&self, position: usize) -> bool {
+        self.savepoints.borrow()[position].starts_transaction
+    }
+
+    /// Releases the savepoint at `position` and every savepoint opened after it.
+    /// Returns whether the released savepoint had started the current transaction.
+    pub(crate) fn release_savepoint(&self, position: usize) -> Result<bool> {
+        self.vtab_transaction_op(VTabTransactionOp::Release(position))?;
+        let mut savepoints = self.savepoints.borrow_mut();
+        let starts_transaction = savepoints[position].starts_transaction;
+        savepoints.truncate(position);
+        self.pager.borrow().release_savepoint(position);
+        if let Some(temp_pager) = self.opened_temp_pager() {
+            temp_pager.release_savepoint(position);
+        }
+        Ok(starts_transaction)
+    }
+
+    /// Undoes every change made since the savepoint at `position` was opened. The savepoint
+    /// stays open, every savepoint opened after it is released.
+    pub(crate) fn rollback_to_savepoint(
+        &self,
+        position: usize,
+        mv_store: Option<&Arc<MvStore>>,
+    ) -> Result<()> {
+        let mut savepoints = self.savepoints.borrow_mut();
+        savepoints.truncate(position + 1);
+        let savepoint = &savepoints[position];
+        if let (Some(mv_store), Some(tx_id)) = (mv_store, self.mv_tx_id.get()) {
+            mv_store.rollback_to_savepoint(tx_id, savepoint.mv_savepoint)?;
+        }
+        self.pager.borrow().rollback_to_savepoint(position)?;
+        if let Some(temp_pager) = self.opened_temp_pager() {
+            temp_pager.rollback_to_savepoint(position)?;
+        }
+        self.schema.replace(savepoint.schema.clone());
+        self.temp_schema.replace(savepoint.temp_schema.clone());
+        self.view_transaction_states
+            .replace(savepoint.view_transaction_states.clone());
+        self.deferred_fk_violations
+            .set(savepoint.deferred_fk_violations);
+        // Like a rollback, every table must be told even if one fails.
+        for vtab in self.vtab_transactions.borrow().iter() {
+            if let Err(e) = vtab.transaction(VTabTransactionOp::RollbackTo(position)) {
+                tracing::error!(
+                    "rollback to savepoint of virtual table {} failed: {e}",
+                    vtab.name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Forgets every savepoint, e.g. when the transaction commits or rolls back.
+    pub(crate) fn clear_savepoints(&self) {
+        self.savepoints.borrow_mut().clear();
+        self.pager.borrow().release_savepoint(0);
+        if let Some(temp_pager) = self.opened_temp_pager() {
+            temp_pager.release_savepoint(0);
+        }
+    }
+
+    /// Makes `vtab` take part in the current transaction, unless it already does.
+    pub(crate) fn begin_vtab_transaction(&self, vtab: &Arc<VirtualTable>) -> Result<()> {
+        if self
+            .vtab_transactions
+            .borrow()
+            .iter()
+            .any(|joined| joined.same_instance(vtab))
+        {
+            return Ok(());
+        }
+        vtab.transaction(VTabTransactionOp::Begin)?;
+        // A table joining the transaction inside a savepoint starts at the innermost one.
+        let savepoints = self.savepoints.borrow().len();
+        if savepoints > 0 {
+            vtab.transaction(VTabTransactionOp::Savepoint(savepoints - 1))?;
+        }
+        self.vtab_transactions.borrow_mut().push(vtab.clone());
+        Ok(())
+    }
+
+    /// Notifies every virtual table written by the current transaction of `op`.
+    fn vtab_transaction_op(&self, op: VTabTransactionOp) -> Result<()> {
+        for vtab in self.vtab_transactions.borrow().iter() {
+            vtab.transaction(op)?;
+        }
+        Ok(())
+    }
+
+    /// Syncs the virtual tables written by the current transaction, before it commits.
+    pub(crate) fn sync_vtab_transactions(&self) -> Result<()> {
+        self.vtab_transaction_op(VTabTransactionOp::Sync)
+    }
+
+    /// Commits the virtual tables written by the transaction that just committed.
+    pub(crate) fn commit_vtab_transactions(&self) -> Result<()> {
+        let vtabs = self.vtab_transactions.take();
+        let mut result = Ok(());
+        for vtab in vtabs {
+            // The transaction has committed, so every table must be told even if one fails.
+            if let Err(e) = vtab.transaction(VTabTransactionOp::Commit) {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+
+    /// Rolls back the virtual tables written by the current transaction.
+    pub(crate) fn rollback_vtab_transactions(&self) {
+        for vtab in self.vtab_transactions.take() {
+            if let Err(e) = vtab.transaction(VTabTransactionOp::Rollback) {
+                tracing::error!("rollback of virtual table {} failed: {e}", vtab.name);
+            }
+        }
+    }
+
+    /// Removes a virtual table that is being dropped from the current transaction.
+    pub(crate) fn forget_vtab_transaction(&self, vtab: &VirtualTable) {
+        self.vtab_transactions
+            .borrow_mut()
+            .retain(|joined| !joined.same_instance(vtab));
+    }
+
     pub fn parse_schema_rows(self: &Arc<Connection>) -> Result<()> {
         if self.closed.get() {
             return Err(LimboError::InternalError("Connection closed".to_string()));
@@ -1708,7 +2092,7 @@ impl Connection {
         self.with_schema_mut(|schema| {
             let existing_views = schema.materialized_views.clone();
             if let Err(LimboError::ExtensionError(e)) =
-                parse_schema_rows(rows, schema, &syms, None, existing_views)
+                parse_schema_rows(rows, schema, &syms, None, existing_views, false)
             {
                 // this means that a vtab exists and we no longer have the module loaded. we print
                 // a warning to the user to load the module
@@ -1798,12 +2182,135 @@ impl Connection {
         self._db.db_state.is_initialized()
     }
 
-    fn get_pager_from_database_index(&self, index: &usize) -> Rc<Pager> {
-        if *index < 2 {
-            self.pager.borrow().clone()
-        } else {
-            self.attached_databases.borrow().get_pager_by_index(index)
+    fn get_pager_from_database_index(&self, index: &usize) -> Result<Rc<Pager>> {
+        match *index {
+            0 => Ok(self.pager.borrow().clone()),
+            1 => self.temp_pager(),
+            _ => Ok(self.attached_databases.borrow().get_pager_by_index(index)),
+        }
+    }
+
+    /// Returns the pager of the `temp` database, opening the database on first use.
+    pub(crate) fn temp_pager(&self) -> Result<Rc<Pager>> {
+        if let Some(temp) = self.temp_database.borrow().as_ref() {
+            return Ok(temp.pager.clone());
+        }
+        let temp = self.open_temp_database()?;
+        let pager = temp.pager.clone();
+        // The savepoints opened so far roll the database back to its empty state.
+        for _ in 0..self.savepoints.borrow().len() {
+            pager.open_savepoint()?;
+        }
+        self.temp_database.replace(Some(temp));
+        Ok(pager)
+    }
+
+    /// Returns the pager of the `temp` database if it has been opened.
+    fn opened_temp_pager(&self) -> Option<Rc<Pager>> {
+        self.temp_database
+            .borrow()
+            .as_ref()
+            .map(|temp| temp.pager.clone())
+    }
+
+    #[cfg(not(feature = "fs"))]
+    fn open_temp_database(&self) -> Result<TempDatabase> {
+        Err(LimboError::InvalidArgument(
+            "temp database not available in this build (no-fs)".to_string(),
+        ))
+    }
+
+    #[cfg(feature = "fs")]
+    fn open_temp_database(&self) -> Result<TempDatabase> {
+        let main_pager = self.pager.borrow().clone();
+        let (io, path): (Arc<dyn IO>, Option<String>) = match self.temp_store.get() {
+            TempStore::Memory => (Arc::new(MemoryIO::new()), None),
+            TempStore::Default | TempStore::File => {
+                let rand_num = main_pager.io.generate_random_number();
+                let path = std::env::temp_dir().join(format!("tursodb-temp-{rand_num}"));
+                let Some(path) = path.to_str() else {
+                    return Err(LimboError::InternalError(
+                        "Failed to convert path to string".to_string(),
+                    ));
+                };
+                (main_pager.io.clone(), Some(path.to_string()))
+            }
+        };
+        let file = io.open_file(path.as_deref().unwrap_or("temp"), OpenFlags::Create, false)?;
+        let pager = Pager::new(
+            Arc::new(DatabaseFile::new(file)),
+            None,
+            io,
+            Arc::new(RwLock::new(DumbLruPageCache::default())),
+            self._db.buffer_pool.clone(),
+            Arc::new(AtomicDbState::new(DbState::Uninitialized)),
+            Arc::new(Mutex::new(())),
+        )?;
+        // The buffer pool is shared with the main database, so is its page size.
+        pager.page_size.set(main_pager.page_size.get());
+        let temp = TempDatabase {
+            pager: Rc::new(pager),
+            path,
+            committed_schema: self.temp_schema.borrow().clone(),
+        };
+        // Page 1 holds sqlite_temp_schema.
+        temp.pager.io.block(|| temp.pager.maybe_allocate_page1())?;
+        Ok(temp)
+    }
+
+    /// Ends the transaction of the `temp` database together with the one of the main database.
+    /// A commit writes the pages it changed to its file, a rollback drops them from the page
+    /// cache and restores the schema of its last commit.
+    fn end_temp_transaction(&self, rollback: bool) -> Result<()> {
+        let mut temp_database = self.temp_database.borrow_mut();
+        let Some(temp) = temp_database.as_mut() else {
+            return Ok(());
+        };
+        if rollback {
+            temp.pager.clear_page_cache();
+            self.temp_schema.replace(temp.committed_schema.clone());
+            return Ok(());
+        }
+        for c in temp.pager.flush_dirty_pages_to_db_file()? {
+            temp.pager.io.wait_for_completion(c)?;
+        }
+        temp.pager.release_savepoint(0);
+        temp.committed_schema = self.temp_schema.borrow().clone();
+        Ok(())
+    }
+
+    pub fn get_temp_store(&self) -> TempStore {
+        self.temp_store.get()
+    }
+
+    /// Changes where the `temp` database is stored. Like in SQLite, the current `temp`
+    /// database is dropped together with all TEMP objects when the setting changes.
+    pub fn set_temp_store(&self, temp_store: TempStore) -> Result<()> {
+        if temp_store == self.temp_store.get() {
+            return Ok(());
+        }
+        if !self.auto_commit.get() && self.temp_database.borrow().is_some() {
+            return Err(LimboError::TxError(
+                "temporary storage cannot be changed from within a transaction".to_string(),
+            ));
         }
+        self.temp_store.set(temp_store);
+        self.temp_database.replace(None);
+        let indexes_enabled = self.temp_schema.borrow().indexes_enabled();
+        self.temp_schema
+            .replace(Arc::new(Schema::new(indexes_enabled)));
+        Ok(())
+    }
+
+    /// Returns the schema statements are translated with: the schema of the main database,
+    /// where the objects of the `temp` database hide the main objects of the same name.
+    pub(crate) fn statement_schema(&self) -> Arc<Schema> {
+        let schema = self.schema.borrow().clone();
+        let temp_schema = self.temp_schema.borrow();
+        if !temp_schema.has_temp_objects() {
+            return schema;
+        }
+        Arc::new(schema.with_temp_objects(&temp_schema))
     }
 
     #[cfg(feature = "fs")]
@@ -1879,13 +2386,21 @@ impl Connection {
             )));
         }
 
-        // Remove from attached databases
         let mut attached_dbs = self.attached_databases.borrow_mut();
-        if attached_dbs.remove(alias).is_none() {
+        if let Some(&index) = attached_dbs.name_to_index.get(alias) {
+            if self.attached_transactions.borrow().contains_key(&index) {
+                return Err(LimboError::TxError(format!("database {alias} is locked")));
+            }
+        }
+
+        // Remove from attached databases
+        let Some(index) = attached_dbs.remove(alias) else {
             return Err(LimboError::InvalidArgument(format!(
                 "no such database: {alias}"
             )));
-        }
+        };
+        // The index may be reused by a database attached later.
+        self.database_schemas.borrow_mut().remove(&index);
 
         Ok(())
     }
@@ -1930,11 +2445,44 @@ impl Connection {
                 }
             })
         } else {
-            // Unqualified table name - use main database
-            Ok(0)
+            // Unqualified table name - use the temp database if it holds the table, main otherwise
+            let name = normalize_ident(qualified_name.name.as_str());
+            let schema = self.temp_schema.borrow();
+            let is_temporary = schema.get_table(&name).is_some_and(|t| t.is_temporary())
+                || schema.get_view(&name).is_some_and(|v| v.temporary);
+            Ok(if is_temporary { 1 } else { 0 })
         }
     }
 
+    /// Looks up the table `name` of the database `database_id`. The `sqlite_schema` of the
+    /// `temp` database is its `sqlite_temp_schema` table.
+    pub(crate) fn get_table_in_database(
+        &self,
+        database_id: usize,
+        name: &str,
+    ) -> Option<Arc<schema::Table>> {
+        self.with_schema(database_id, |schema| {
+            if database_id == 1
+                && (name.eq_ignore_ascii_case("sqlite_schema")
+                    || name.eq_ignore_ascii_case("sqlite_master"))
+            {
+                return schema.get_table(schema::TEMP_SCHEMA_TABLE_NAME);
+            }
+            schema.get_table(name)
+        })
+        .filter(|table| database_id > 1 || table.is_temporary() == (database_id == 1))
+    }
+
+    /// Looks up the view `name` of the database `database_id`, see [Self::get_table_in_database].
+    pub(crate) fn get_view_in_database(
+        &self,
+        database_id: usize,
+        name: &str,
+    ) -> Option<schema::View> {
+        self.with_schema(database_id, |schema| schema.get_view(name).cloned())
+            .filter(|view| database_id > 1 || view.temporary == (database_id == 1))
+    }
+
     /// Access schema for a database using a closure pattern to avoid cloning
     pub(crate) fn with_schema<T>(&self, database_id: usize, f: impl FnOnce(&Schema) -> T) -> T {
         if database_id == 0 {
@@ -1942,35 +2490,299 @@ impl Connection {
             let schema = self.schema.borrow();
             f(&schema)
         } else if database_id == 1 {
-            // Temp database - uses same schema as main for now, but this will change later.
-            let schema = self.schema.borrow();
+            let schema = self.temp_schema.borrow();
             f(&schema)
         } else {
-            // Attached database - check cache first, then load from database
-            let mut schemas = self.database_schemas.borrow_mut();
+            f(&self.database_schema(database_id))
+        }
+    }
 
-            if let Some(cached_schema) = schemas.get(&database_id) {
-                return f(cached_schema);
+    /// Returns the schema of the database `database_id`. The schema of an attached database is
+    /// loaded on first use and then cached by the connection, which also keeps the changes of its
+    /// own DDL there until they are committed.
+    pub(crate) fn database_schema(&self, database_id: usize) -> Arc<Schema> {
+        match database_id {
+            0 => return self.schema.borrow().clone(),
+            1 => return self.temp_schema.borrow().clone(),
+            _ => {}
+        }
+        // Attached database - check cache first, then load from database
+        let mut schemas = self.database_schemas.borrow_mut();
+        if let Some(cached_schema) = schemas.get(&database_id) {
+            return cached_schema.clone();
+        }
+
+        // Schema not cached, load it lazily from the attached database
+        let attached_dbs = self.attached_databases.borrow();
+        let (db, _pager) = attached_dbs
+            .index_to_data
+            .get(&database_id)
+            .expect("Database ID should be valid after resolve_database_id");
+
+        let schema = db
+            .schema
+            .lock()
+            .expect("Schema lock should not fail")
+            .clone();
+
+        // Cache the schema for future use
+        schemas.insert(database_id, schema.clone());
+        schema
+    }
+
+    /// Replaces the cached schema of the attached database `database_id`, see
+    /// [Self::database_schema].
+    pub(crate) fn set_database_schema(&self, database_id: usize, schema: Arc<Schema>) {
+        turso_assert!(database_id > 1, "not an attached database: {database_id}");
+        self.database_schemas
+            .borrow_mut()
+            .insert(database_id, schema);
+    }
+
+    /// Changes the schema of the database `database_id`, see [Self::database_schema].
+    pub(crate) fn with_database_schema_mut<T>(
+        &self,
+        database_id: usize,
+        f: impl FnOnce(&mut Schema) -> T,
+    ) -> T {
+        if database_id == 0 {
+            return self.with_schema_mut(f);
+        }
+        if database_id == 1 {
+            let mut schema = self.temp_schema.borrow_mut();
+            return f(Arc::make_mut(&mut schema));
+        }
+        let mut schema = self.database_schema(database_id);
+        let result = f(Arc::make_mut(&mut schema));
+        self.set_database_schema(database_id, schema);
+        result
+    }
+
+    /// Drops the cached schema of the attached database `database_id` after another connection
+    /// changed it. The shared schema of the database is read again too if it is outdated.
+    pub(crate) fn reload_database_schema(&self, database_id: usize) -> Result<()> {
+        if self.attached_transactions.borrow().get(&database_id) == Some(&true) {
+            // The cached schema holds the changes of the transaction in progress.
+            return Ok(());
+        }
+        self.database_schemas.borrow_mut().remove(&database_id);
+        let db = self
+            .attached_databases
+            .borrow()
+            .get_database_by_index(database_id);
+        match db {
+            Some(db) => db.connect()?.maybe_reparse_schema(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the name the attached database `database_id` was attached under.
+    pub(crate) fn attached_database_name(&self, database_id: usize) -> Option<String> {
+        self.attached_databases
+            .borrow()
+            .name_to_index
+            .iter()
+            .find(|(_, &index)| index == database_id)
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Begins a transaction on the attached database `database_id`, or upgrades the read
+    /// transaction open on it when `write` is set. Like the transactions of the main database,
+    /// a transaction begun by a statement in autocommit mode ends with that statement.
+    pub(crate) fn begin_attached_transaction(
+        &self,
+        database_id: usize,
+        write: bool,
+    ) -> Result<IOResult<result::LimboResult>> {
+        use result::LimboResult;
+
+        let current = self
+            .attached_transactions
+            .borrow()
+            .get(&database_id)
+            .copied();
+        if current.is_some_and(|is_write| is_write || !write) {
+            return Ok(IOResult::Done(LimboResult::Ok));
+        }
+        if write && self.is_readonly(database_id) {
+            return Err(LimboError::ReadOnly);
+        }
+        let pager = self.get_pager_from_database_index(&database_id)?;
+        if current.is_none() {
+            if let LimboResult::Busy = pager.begin_read_tx()? {
+                return Ok(IOResult::Done(LimboResult::Busy));
+            }
+            self.attached_transactions
+                .borrow_mut()
+                .insert(database_id, false);
+        }
+        if write {
+            // On busy, the read transaction stays open and ends with the rest of the transaction.
+            if let LimboResult::Busy = return_if_io!(pager.begin_write_tx()) {
+                return Ok(IOResult::Done(LimboResult::Busy));
             }
+            self.attached_transactions
+                .borrow_mut()
+                .insert(database_id, true);
+        }
+        Ok(IOResult::Done(LimboResult::Ok))
+    }
 
-            // Schema not cached, load it lazily from the attached database
-            let attached_dbs = self.attached_databases.borrow();
-            let (db, _pager) = attached_dbs
-                .index_to_data
-                .get(&database_id)
-                .expect("Database ID should be valid after resolve_database_id");
+    /// Ends the transactions open on attached databases and on the `temp` database. This happens
+    /// right before the transaction of the main database ends, which commits or rolls back the
+    /// same way.
+    ///
+    /// When the main database writes as well, the attached databases commit first and their
+    /// commits are undone if the main database then fails to commit. When a commit writes to more
+    /// than one database file, a super-journal is written first so that the commit stays atomic
+    /// across a crash, see [storage::super_journal]. The commit completes with
+    /// [Self::finish_attached_commit] once the main database has committed as well.
+    pub(crate) fn end_attached_transactions(&self, rollback: bool) -> Result<()> {
+        self.end_temp_transaction(rollback)?;
+        let transactions = std::mem::take(&mut *self.attached_transactions.borrow_mut());
+        if rollback {
+            for (&database_id, &is_write) in &transactions {
+                let pager = self.get_pager_from_database_index(&database_id)?;
+                pager
+                    .io
+                    .block(|| pager.end_untracked_tx(true, is_write, self))?;
+                if is_write {
+                    // The schema may hold changes of the rolled back transaction.
+                    self.database_schemas.borrow_mut().remove(&database_id);
+                }
+            }
+            // The attached databases may have committed already when the main database fails to.
+            return self.undo_attached_commit();
+        }
+        let main_is_write = matches!(self.transaction_state.get(), TransactionState::Write { .. });
+        #[cfg(feature = "fs")]
+        let journal = self.begin_super_journal(&transactions, main_is_write)?;
+        #[cfg(feature = "fs")]
+        let spans_databases = main_is_write || journal.is_some();
+        #[cfg(not(feature = "fs"))]
+        let spans_databases = main_is_write;
+        if spans_databases {
+            // Also holds back checkpoints, which would make the commits impossible to undo.
+            self.attached_commit.replace(Some(AttachedCommit {
+                wal_sizes: Vec::new(),
+                schemas: Vec::new(),
+                #[cfg(feature = "fs")]
+                journal,
+            }));
+        }
+        for (&database_id, &is_write) in &transactions {
+            let pager = self.get_pager_from_database_index(&database_id)?;
+            let wal_size = pager.wal_size_before_commit();
+            pager
+                .io
+                .block(|| pager.end_untracked_tx(false, is_write, self))?;
+            if !is_write {
+                continue;
+            }
+            let schema = self.database_schemas.borrow().get(&database_id).cloned();
+            let db = self
+                .attached_databases
+                .borrow()
+                .get_database_by_index(database_id);
+            let mut attached_commit = self.attached_commit.borrow_mut();
+            match attached_commit.as_mut() {
+                Some(commit) => {
+                    commit
+                        .wal_sizes
+                        .extend(wal_size.map(|size| (database_id, size)));
+                    commit.schemas.extend(schema.zip(db));
+                }
+                None => {
+                    if let (Some(schema), Some(db)) = (schema, db) {
+                        db.update_schema_if_newer(schema)?;
+                    }
+                }
+            }
+        }
+        if !main_is_write {
+            self.finish_attached_commit()?;
+        }
+        Ok(())
+    }
 
-            let schema = db
-                .schema
-                .lock()
-                .expect("Schema lock should not fail")
-                .clone();
+    /// Writes the super-journal of a commit when it spans several database files.
+    #[cfg(feature = "fs")]
+    fn begin_super_journal(
+        &self,
+        transactions: &BTreeMap<usize, bool>,
+        main_is_write: bool,
+    ) -> Result<Option<storage::super_journal::SuperJournal>> {
+        let mut wals = Vec::new();
+        let mut add_wal = |db: &Database, pager: &Pager| {
+            if db.path.starts_with(util::MEMORY_PATH) {
+                return;
+            }
+            if let Some(wal_size) = pager.wal_size_before_commit() {
+                wals.push((db.wal_path.clone(), wal_size));
+            }
+        };
+        if main_is_write {
+            add_wal(&self._db, &self.pager.borrow());
+        }
+        let attached_dbs = self.attached_databases.borrow();
+        for (database_id, _) in transactions.iter().filter(|(_, &is_write)| is_write) {
+            if let Some((db, pager)) = attached_dbs.index_to_data.get(database_id) {
+                add_wal(db, pager);
+            }
+        }
+        if wals.len() < 2 {
+            return Ok(None);
+        }
+        let journal = storage::super_journal::SuperJournal::create(
+            self._db.io.clone(),
+            &self._db.path,
+            &wals,
+        )?;
+        Ok(Some(journal))
+    }
 
-            // Cache the schema for future use
-            schemas.insert(database_id, schema.clone());
+    /// Completes a commit that spans several databases once all of them have committed, see
+    /// [Self::end_attached_transactions].
+    pub(crate) fn finish_attached_commit(&self) -> Result<()> {
+        let Some(commit) = self.attached_commit.take() else {
+            return Ok(());
+        };
+        #[cfg(feature = "fs")]
+        if let Some(journal) = commit.journal {
+            journal.finish()?;
+        }
+        for (schema, db) in commit.schemas {
+            db.update_schema_if_newer(schema)?;
+        }
+        Ok(())
+    }
 
-            f(&schema)
+    /// Undoes the commits of the attached databases when the main database failed to commit
+    /// after them. Their WAL files are cut back before the super-journal is deleted, so that a
+    /// crash in between still rolls them back on the next open.
+    fn undo_attached_commit(&self) -> Result<()> {
+        let Some(commit) = self.attached_commit.take() else {
+            return Ok(());
+        };
+        for (database_id, wal_size) in commit.wal_sizes {
+            let pager = self.get_pager_from_database_index(&database_id)?;
+            pager.undo_commits(wal_size)?;
+            self.database_schemas.borrow_mut().remove(&database_id);
+        }
+        #[cfg(feature = "fs")]
+        if let Some(journal) = commit.journal {
+            journal.finish()?;
         }
+        Ok(())
+    }
+
+    /// Whether commits must not checkpoint the WAL, see [Self::wal_auto_checkpoint_disable].
+    /// Checkpoints also wait for a commit spanning several databases to complete.
+    pub(crate) fn is_wal_auto_checkpoint_disabled(&self) -> bool {
+        if self.attached_commit.borrow().is_some() {
+            return true;
+        }
+        self.wal_auto_checkpoint_disabled.get()
     }
 
     // Get the canonical path for a database given its Database object
@@ -1996,6 +2808,11 @@ impl Connection {
         let main_path = Self::get_canonical_path_for_database(&self._db);
         databases.push((0, "main".to_string(), main_path));
 
+        // Like SQLite, list the temp database once it has been opened, without its file
+        if self.temp_database.borrow().is_some() {
+            databases.push((1, "temp".to_string(), String::new()));
+        }
+
         // Add attached databases
         let attached_dbs = self.attached_databases.borrow();
         for (alias, &seq_number) in attached_dbs.name_to_index.iter() {
@@ -2025,6 +2842,37 @@ impl Connection {
         self.query_only.set(value);
     }
 
+    pub fn foreign_keys_enabled(&self) -> bool {
+        self.foreign_keys.get()
+    }
+
+    pub fn set_foreign_keys_enabled(&self, value: bool) {
+        self.foreign_keys.set(value);
+    }
+
+    pub fn get_defer_foreign_keys(&self) -> bool {
+        self.defer_foreign_keys.get()
+    }
+
+    pub fn set_defer_foreign_keys(&self, value: bool) {
+        self.defer_foreign_keys.set(value);
+    }
+
+    pub(crate) fn deferred_fk_violations(&self) -> i64 {
+        self.deferred_fk_violations.get()
+    }
+
+    pub(crate) fn add_deferred_fk_violations(&self, amount: i64) {
+        self.deferred_fk_violations
+            .set(self.deferred_fk_violations.get() + amount);
+    }
+
+    /// Forgets the deferred foreign key state once the transaction commits or rolls back.
+    pub(crate) fn end_deferred_fk_checks(&self) {
+        self.deferred_fk_violations.set(0);
+        self.defer_foreign_keys.set(false);
+    }
+
     pub fn get_sync_mode(&self) -> SyncMode {
         self.sync_mode.get()
     }
@@ -2033,6 +2881,15 @@ impl Connection {
         self.sync_mode.set(mode);
     }
 
+    pub fn get_isolation_level(&self) -> mvcc::IsolationLevel {
+        self.isolation_level.get()
+    }
+
+    /// Sets the isolation level of the MVCC transactions the connection starts from now on.
+    pub fn set_isolation_level(&self, isolation_level: mvcc::IsolationLevel) {
+        self.isolation_level.set(isolation_level);
+    }
+
     /// Creates a HashSet of modules that have been loaded
     pub fn get_syms_vtab_mods(&self) -> std::collections::HashSet<String> {
         self.syms.borrow().vtab_modules.keys().cloned().collect()
@@ -2181,7 +3038,7 @@ impl Statement {
 
             match cmd {
                 Cmd::Stmt(stmt) => translate::translate(
-                    conn.schema.borrow().deref(),
+                    &conn.statement_schema(),
                     stmt,
                     self.pager.clone(),
                     conn.clone(),
@@ -2211,6 +3068,7 @@ impl Statement {
                 io.abort();
             }
             let state = self.program.connection.transaction_state.get();
+            self.program.connection.end_attached_transactions(true)?;
             if let TransactionState::Write { .. } = state {
                 let end_tx_res = self.pager.end_tx(true, &self.program.connection)?;
                 self.program
@@ -2306,6 +3164,8 @@ pub struct SymbolTable {
     pub functions: HashMap<String, Arc<function::ExternalFunc>>,
     pub vtabs: HashMap<String, Arc<VirtualTable>>,
     pub vtab_modules: HashMap<String, Rc<crate::ext::VTabImpl>>,
+    /// Collation sequences by lowercased name.
+    pub collations: HashMap<String, Arc<CustomCollation>>,
 }
 
 impl std::fmt::Debug for SymbolTable {
@@ -2347,6 +3207,7 @@ impl SymbolTable {
             functions: HashMap::new(),
             vtabs: HashMap::new(),
             vtab_modules: HashMap::new(),
+            collations: HashMap::new(),
         }
     }
     pub fn resolve_function(
@@ -2357,6 +3218,22 @@ impl SymbolTable {
         self.functions.get(name).cloned()
     }
 
+    /// Registers the collation sequence `name`, replacing the comparison function of a
+    /// collation sequence that was registered with the same name. Built-in collation sequences
+    /// cannot be replaced.
+    pub fn register_collation(&mut self, name: &str, compare: Box<CollationFn>) -> Result<()> {
+        if CollationSeq::is_builtin(name) {
+            return Err(LimboError::InvalidArgument(format!(
+                "cannot replace built-in collation sequence: {name}"
+            )));
+        }
+        self.collations.insert(
+            name.to_ascii_lowercase(),
+            Arc::new(CustomCollation::new(name, compare)),
+        );
+        Ok(())
+    }
+
     pub fn extend(&mut self, other: &SymbolTable) {
         for (name, func) in &other.functions {
             self.functions.insert(name.clone(), func.clone());
@@ -2367,6 +3244,9 @@ impl SymbolTable {
         for (name, module) in &other.vtab_modules {
             self.vtab_modules.insert(name.clone(), module.clone());
         }
+        for (name, collation) in &other.collations {
+            self.collations.insert(name.clone(), collation.clone());
+        }
     }
 }
 
@@ -1,6 +1,9 @@
 use crate::mvcc::clock::LogicalClock;
-use crate::mvcc::database::{MvStore, Row, RowID};
-use crate::types::{IOResult, SeekKey, SeekOp, SeekResult};
+use crate::mvcc::database::{IndexKey, MvStore, Row, RowID};
+use crate::translate::plan::IterationDirection;
+use crate::types::{
+    get_tie_breaker_from_seek_op, IOResult, ImmutableRecord, IndexInfo, SeekKey, SeekOp, SeekResult,
+};
 use crate::Result;
 use crate::{Pager, Value};
 use std::fmt::Debug;
@@ -8,12 +11,14 @@ use std::ops::Bound;
 use std::rc::Rc;
 use std::sync::Arc;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 enum CursorPosition {
     /// We haven't loaded any row yet.
     BeforeFirst,
     /// We have loaded a row.
     Loaded(RowID),
+    /// We have loaded an entry of the index.
+    LoadedIndexEntry(IndexKey),
     /// We have reached the end of the table.
     End,
 }
@@ -21,7 +26,10 @@ enum CursorPosition {
 pub struct MvccLazyCursor<Clock: LogicalClock> {
     pub db: Arc<MvStore<Clock>>,
     current_pos: CursorPosition,
+    /// The table ID, or the index ID for a cursor over an index.
     table_id: u64,
+    /// Set for a cursor over an index.
+    index_info: Option<Arc<IndexInfo>>,
     tx_id: u64,
 }
 
@@ -38,15 +46,36 @@ impl<Clock: LogicalClock> MvccLazyCursor<Clock> {
             tx_id,
             current_pos: CursorPosition::BeforeFirst,
             table_id,
+            index_info: None,
         };
         Ok(cursor)
     }
 
-    /// Insert a row into the table.
+    /// A cursor over the entries of the index `index_id`.
+    pub fn new_index(
+        db: Arc<MvStore<Clock>>,
+        tx_id: u64,
+        index_id: u64,
+        index_info: Arc<IndexInfo>,
+        pager: Rc<Pager>,
+    ) -> Result<MvccLazyCursor<Clock>> {
+        db.maybe_initialize_index(index_id, &index_info, pager)?;
+        let cursor = Self {
+            db,
+            tx_id,
+            current_pos: CursorPosition::BeforeFirst,
+            table_id: index_id,
+            index_info: Some(index_info),
+        };
+        Ok(cursor)
+    }
+
+    /// Insert a row into the table, ending the version of the row with the same id that the
+    /// transaction sees, if any.
     /// Sets the cursor to the inserted row.
-    pub fn insert(&mut self, row: Row) -> Result<()> {
+    pub fn insert(&mut self, row: Row, pager: Rc<Pager>) -> Result<()> {
         self.current_pos = CursorPosition::Loaded(row.id);
-        self.db.insert(self.tx_id, row).inspect_err(|_| {
+        self.db.upsert(self.tx_id, row, pager).inspect_err(|_| {
             self.current_pos = CursorPosition::BeforeFirst;
         })?;
         Ok(())
@@ -57,43 +86,93 @@ impl<Clock: LogicalClock> MvccLazyCursor<Clock> {
         Ok(())
     }
 
+    /// Insert an entry into the index.
+    /// Sets the cursor to the inserted entry.
+    pub fn insert_index_entry(&mut self, record: &ImmutableRecord) -> Result<()> {
+        let key = IndexKey::new(self.table_id, record.clone(), self.index_info().clone());
+        self.current_pos = CursorPosition::LoadedIndexEntry(key.clone());
+        self.db
+            .insert_index_entry(self.tx_id, key)
+            .inspect_err(|_| {
+                self.current_pos = CursorPosition::BeforeFirst;
+            })?;
+        Ok(())
+    }
+
+    /// Delete the index entry the cursor points to.
+    pub fn delete_index_entry(&mut self, pager: Rc<Pager>) -> Result<()> {
+        if let CursorPosition::LoadedIndexEntry(key) = &self.current_pos {
+            self.db.delete_index_entry(self.tx_id, key, pager)?;
+        }
+        Ok(())
+    }
+
     pub fn current_row_id(&mut self) -> Option<RowID> {
-        match self.current_pos {
-            CursorPosition::Loaded(id) => Some(id),
-            CursorPosition::BeforeFirst => {
-                // If we are before first, we need to try and find the first row.
-                let maybe_rowid =
-                    self.db
-                        .get_next_row_id_for_table(self.table_id, i64::MIN, self.tx_id);
-                if let Some(id) = maybe_rowid {
-                    self.current_pos = CursorPosition::Loaded(id);
-                    Some(id)
-                } else {
-                    self.current_pos = CursorPosition::BeforeFirst;
-                    None
-                }
-            }
-            CursorPosition::End => None,
+        if let CursorPosition::BeforeFirst = self.current_pos {
+            // If we are before first, we need to try and find the first row.
+            self.load_first();
+        }
+        match &self.current_pos {
+            CursorPosition::Loaded(id) => Some(*id),
+            CursorPosition::LoadedIndexEntry(key) => Some(key.to_row().id),
+            CursorPosition::BeforeFirst | CursorPosition::End => None,
         }
     }
 
     pub fn current_row(&mut self) -> Result<Option<Row>> {
-        match self.current_pos {
-            CursorPosition::Loaded(id) => self.db.read(self.tx_id, id),
-            CursorPosition::BeforeFirst => {
-                // If we are before first, we need to try and find the first row.
-                let maybe_rowid =
-                    self.db
-                        .get_next_row_id_for_table(self.table_id, i64::MIN, self.tx_id);
-                if let Some(id) = maybe_rowid {
-                    self.current_pos = CursorPosition::Loaded(id);
-                    self.db.read(self.tx_id, id)
-                } else {
-                    Ok(None)
-                }
-            }
-            CursorPosition::End => Ok(None),
+        if let CursorPosition::BeforeFirst = self.current_pos {
+            // If we are before first, we need to try and find the first row.
+            self.load_first();
         }
+        match &self.current_pos {
+            CursorPosition::Loaded(id) => self.db.read(self.tx_id, *id),
+            CursorPosition::LoadedIndexEntry(key) => self.db.read_index_entry(self.tx_id, key),
+            CursorPosition::BeforeFirst | CursorPosition::End => Ok(None),
+        }
+    }
+
+    /// Loads the first row or index entry, if there is one.
+    fn load_first(&mut self) {
+        self.db.record_scan(self.tx_id, self.table_id);
+        self.current_pos = match &self.index_info {
+            Some(_) => self
+                .db
+                .seek_index_entry(Bound::Included(&self.first_key()), true, self.tx_id)
+                .map_or(
+                    CursorPosition::BeforeFirst,
+                    CursorPosition::LoadedIndexEntry,
+                ),
+            None => self
+                .db
+                .get_next_row_id_for_table(self.table_id, i64::MIN, self.tx_id)
+                .map_or(CursorPosition::BeforeFirst, CursorPosition::Loaded),
+        };
+    }
+
+    fn index_info(&self) -> &Arc<IndexInfo> {
+        self.index_info
+            .as_ref()
+            .expect("index operation on a table cursor")
+    }
+
+    /// A key that sorts before every entry of the index.
+    fn first_key(&self) -> IndexKey {
+        self.boundary_key(std::cmp::Ordering::Greater)
+    }
+
+    /// A key that sorts after every entry of the index.
+    fn last_key(&self) -> IndexKey {
+        self.boundary_key(std::cmp::Ordering::Less)
+    }
+
+    fn boundary_key(&self, tie_breaker: std::cmp::Ordering) -> IndexKey {
+        let record = ImmutableRecord::from_values(std::iter::empty::<&Value>(), 0);
+        IndexKey::new_seek_key(
+            self.table_id,
+            record,
+            self.index_info().clone(),
+            tie_breaker,
+        )
     }
 
     pub fn close(self) -> Result<()> {
@@ -102,33 +181,64 @@ impl<Clock: LogicalClock> MvccLazyCursor<Clock> {
 
     /// Move the cursor to the next row. Returns true if the cursor moved to the next row, false if the cursor is at the end of the table.
     pub fn forward(&mut self) -> bool {
+        self.db.record_scan(self.tx_id, self.table_id);
         let before_first = matches!(self.current_pos, CursorPosition::BeforeFirst);
-        let min_id = match self.current_pos {
-            CursorPosition::Loaded(id) => id.row_id + 1,
+        let next = match &self.current_pos {
+            CursorPosition::Loaded(id) => self
+                .db
+                .get_next_row_id_for_table(self.table_id, id.row_id + 1, self.tx_id)
+                .map(CursorPosition::Loaded),
+            CursorPosition::LoadedIndexEntry(key) => self
+                .db
+                .seek_index_entry(Bound::Excluded(key), true, self.tx_id)
+                .map(CursorPosition::LoadedIndexEntry),
             // TODO: do we need to forward twice?
-            CursorPosition::BeforeFirst => i64::MIN, // we need to find first row, so we look from the first id,
+            CursorPosition::BeforeFirst => {
+                // we need to find first row, so we look from the first id,
+                self.load_first();
+                return !self.is_empty();
+            }
             CursorPosition::End => {
                 // let's keep same state, we reached the end so no point in moving forward.
                 return false;
             }
         };
-        self.current_pos =
-            match self
-                .db
-                .get_next_row_id_for_table(self.table_id, min_id, self.tx_id)
-            {
-                Some(id) => CursorPosition::Loaded(id),
-                None => {
-                    if before_first {
-                        // if it wasn't loaded and we didn't find anything, it means the table is empty.
-                        CursorPosition::BeforeFirst
-                    } else {
-                        // if we had something loaded, and we didn't find next key then it means we are at the end.
-                        CursorPosition::End
-                    }
+        self.current_pos = match next {
+            Some(pos) => pos,
+            None => {
+                if before_first {
+                    // if it wasn't loaded and we didn't find anything, it means the table is empty.
+                    CursorPosition::BeforeFirst
+                } else {
+                    // if we had something loaded, and we didn't find next key then it means we are at the end.
+                    CursorPosition::End
                 }
-            };
-        matches!(self.current_pos, CursorPosition::Loaded(_))
+            }
+        };
+        !self.is_empty()
+    }
+
+    /// Move the cursor to the previous row. Returns true if the cursor moved to the previous
+    /// row, false if the cursor is at the start of the table.
+    pub fn backward(&mut self) -> bool {
+        self.db.record_scan(self.tx_id, self.table_id);
+        let prev = match &self.current_pos {
+            CursorPosition::Loaded(id) => self
+                .db
+                .get_prev_row_id_for_table(self.table_id, id.row_id, self.tx_id)
+                .map(CursorPosition::Loaded),
+            CursorPosition::LoadedIndexEntry(key) => self
+                .db
+                .seek_index_entry(Bound::Excluded(key), false, self.tx_id)
+                .map(CursorPosition::LoadedIndexEntry),
+            CursorPosition::End => {
+                self.last();
+                return !self.is_empty();
+            }
+            CursorPosition::BeforeFirst => return false,
+        };
+        self.current_pos = prev.unwrap_or(CursorPosition::BeforeFirst);
+        !self.is_empty()
     }
 
     /// Returns true if the is not pointing to any row.
@@ -136,7 +246,7 @@ impl<Clock: LogicalClock> MvccLazyCursor<Clock> {
         // If we reached the end of the table, it means we traversed the whole table therefore there must be something in the table.
         // If we have loaded a row, it means there is something in the table.
         match self.current_pos {
-            CursorPosition::Loaded(_) => false,
+            CursorPosition::Loaded(_) | CursorPosition::LoadedIndexEntry(_) => false,
             CursorPosition::BeforeFirst => true,
             CursorPosition::End => true,
         }
@@ -146,33 +256,48 @@ impl<Clock: LogicalClock> MvccLazyCursor<Clock> {
         self.current_pos = CursorPosition::BeforeFirst;
     }
 
+    /// Move the cursor to the last row visible to the transaction.
     pub fn last(&mut self) {
-        let last_rowid = self.db.get_last_rowid(self.table_id);
-        if let Some(last_rowid) = last_rowid {
-            self.current_pos = CursorPosition::Loaded(RowID {
-                table_id: self.table_id,
-                row_id: last_rowid,
-            });
-        } else {
-            self.current_pos = CursorPosition::BeforeFirst;
-        }
+        self.db.record_scan(self.tx_id, self.table_id);
+        self.load_last();
+    }
+
+    /// Loads the last row or index entry visible to the transaction, if there is one.
+    fn load_last(&mut self) {
+        self.current_pos = match &self.index_info {
+            Some(_) => self
+                .db
+                .seek_index_entry(Bound::Included(&self.last_key()), false, self.tx_id)
+                .map_or(
+                    CursorPosition::BeforeFirst,
+                    CursorPosition::LoadedIndexEntry,
+                ),
+            None => self
+                .db
+                .seek_rowid(
+                    Bound::Included(&RowID {
+                        table_id: self.table_id,
+                        row_id: i64::MAX,
+                    }),
+                    false,
+                    self.tx_id,
+                )
+                .map_or(CursorPosition::BeforeFirst, CursorPosition::Loaded),
+        };
     }
 
     pub fn get_next_rowid(&mut self) -> i64 {
-        self.last();
-        match self.current_pos {
-            CursorPosition::Loaded(id) => id.row_id + 1,
-            CursorPosition::BeforeFirst => 1,
-            CursorPosition::End => i64::MAX,
+        // Rows of concurrent transactions count too, so that their rowids are not reused.
+        match self.db.get_last_rowid(self.table_id) {
+            Some(last_rowid) => last_rowid + 1,
+            None => 1,
         }
     }
 
     pub fn seek(&mut self, seek_key: SeekKey<'_>, op: SeekOp) -> Result<IOResult<SeekResult>> {
         let row_id = match seek_key {
             SeekKey::TableRowId(row_id) => row_id,
-            SeekKey::IndexKey(_) => {
-                todo!();
-            }
+            SeekKey::IndexKey(record) => return self.seek_index(record, op),
         };
         // gt -> lower_bound bound excluded, we want first row after row_id
         // ge -> lower_bound bound included, we want first row equal to row_id or first row after row_id
@@ -182,6 +307,12 @@ impl<Clock: LogicalClock> MvccLazyCursor<Clock> {
             table_id: self.table_id,
             row_id,
         };
+        // An equality seek only depends on the row it looks for, other seeks on a range.
+        if op.eq_only() {
+            self.db.record_read(self.tx_id, rowid);
+        } else {
+            self.db.record_scan(self.tx_id, self.table_id);
+        }
         let (bound, lower_bound) = match op {
             SeekOp::GT => (Bound::Excluded(&rowid), true),
             SeekOp::GE { eq_only: _ } => (Bound::Included(&rowid), true),
@@ -203,7 +334,7 @@ impl<Clock: LogicalClock> MvccLazyCursor<Clock> {
         } else {
             let forwards = matches!(op, SeekOp::GE { eq_only: _ } | SeekOp::GT);
             if forwards {
-                self.last();
+                self.load_last();
             } else {
                 self.rewind();
             }
@@ -211,11 +342,53 @@ impl<Clock: LogicalClock> MvccLazyCursor<Clock> {
         }
     }
 
+    fn seek_index(&mut self, record: &ImmutableRecord, op: SeekOp) -> Result<IOResult<SeekResult>> {
+        // Index seeks are tracked as scans of the whole index.
+        self.db.record_scan(self.tx_id, self.table_id);
+        // The tie breaker places the probe before or after the entries it is a prefix of, so
+        // that the first entry on the side of the probe is the one the seek is looking for.
+        let probe = IndexKey::new_seek_key(
+            self.table_id,
+            record.clone(),
+            self.index_info().clone(),
+            get_tie_breaker_from_seek_op(op),
+        );
+        let forwards = op.iteration_direction() == IterationDirection::Forwards;
+        let key = self
+            .db
+            .seek_index_entry(Bound::Included(&probe), forwards, self.tx_id);
+        match key {
+            Some(key) => {
+                // With eq_only the probe compares equal to the entries it is a prefix of.
+                let found = !op.eq_only() || key == probe;
+                self.current_pos = CursorPosition::LoadedIndexEntry(key);
+                Ok(IOResult::Done(match found {
+                    true => SeekResult::Found,
+                    false => SeekResult::NotFound,
+                }))
+            }
+            None => {
+                self.current_pos = match forwards {
+                    true => CursorPosition::End,
+                    false => CursorPosition::BeforeFirst,
+                };
+                Ok(IOResult::Done(SeekResult::NotFound))
+            }
+        }
+    }
+
     pub fn exists(&mut self, key: &Value) -> Result<IOResult<bool>> {
         let int_key = match key {
             Value::Integer(i) => i,
             _ => unreachable!("btree tables are indexed by integers!"),
         };
+        self.db.record_read(
+            self.tx_id,
+            RowID {
+                table_id: self.table_id,
+                row_id: *int_key,
+            },
+        );
         let exists = self
             .db
             .seek_rowid(
@@ -226,7 +399,7 @@ impl<Clock: LogicalClock> MvccLazyCursor<Clock> {
                 true,
                 self.tx_id,
             )
-            .is_some();
+            .is_some_and(|id| id.row_id == *int_key);
         if exists {
             self.current_pos = CursorPosition::Loaded(RowID {
                 table_id: self.table_id,
@@ -7,19 +7,20 @@ use crate::storage::btree::BTreeCursor;
 use crate::storage::btree::BTreeKey;
 use crate::types::IOResult;
 use crate::types::ImmutableRecord;
+use crate::types::{compare_immutable, IndexInfo, RecordCursor, RefValue};
 use crate::IOExt;
 use crate::LimboError;
 use crate::Result;
 use crate::{Connection, Pager};
 use crossbeam_skiplist::{SkipMap, SkipSet};
-use parking_lot::RwLock;
-use std::collections::HashSet;
+use parking_lot::{Mutex, RwLock};
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::ops::Bound;
 use std::rc::Rc;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
 
 #[cfg(test)]
 pub mod tests;
@@ -55,21 +56,168 @@ impl Row {
     }
 }
 
+/// The key of an index entry: its record, which ends with the rowid of the table row in the
+/// index of a rowid table. Keys are ordered like the cells of the index B-tree.
+#[derive(Clone, Debug)]
+pub struct IndexKey {
+    /// The index ID. Analogous to index's root page number.
+    pub index_id: u64,
+    pub record: ImmutableRecord,
+    pub info: Arc<IndexInfo>,
+    /// How an entry compares to this key if the columns of the key are the leading columns of
+    /// the entry. Seek keys may hold fewer columns than the entries, see
+    /// [crate::types::get_tie_breaker_from_seek_op].
+    tie_breaker: std::cmp::Ordering,
+}
+
+impl IndexKey {
+    pub fn new(index_id: u64, record: ImmutableRecord, info: Arc<IndexInfo>) -> Self {
+        Self {
+            index_id,
+            record,
+            info,
+            tie_breaker: std::cmp::Ordering::Equal,
+        }
+    }
+
+    /// A key to seek with, holding the leading columns of the entries it is compared to.
+    pub fn new_seek_key(
+        index_id: u64,
+        record: ImmutableRecord,
+        info: Arc<IndexInfo>,
+        tie_breaker: std::cmp::Ordering,
+    ) -> Self {
+        Self {
+            index_id,
+            record,
+            info,
+            tie_breaker,
+        }
+    }
+
+    /// The row that the versions of the entry hold: the record, with the rowid of the table
+    /// row as its id.
+    pub fn to_row(&self) -> Row {
+        let row_id = match self.info.has_rowid {
+            true => match self.record.last_value(&mut RecordCursor::new()) {
+                Some(Ok(RefValue::Integer(rowid))) => rowid,
+                _ => 0,
+            },
+            false => 0,
+        };
+        Row::new(
+            RowID::new(self.index_id, row_id),
+            self.record.get_payload().to_vec(),
+            self.record.column_count(),
+        )
+    }
+}
+
+impl Ord for IndexKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.index_id.cmp(&other.index_id).then_with(|| {
+            let key_info = &self.info.key_info;
+            let left = self.record.get_values();
+            let right = other.record.get_values();
+            // The clustered index of a WITHOUT ROWID table holds more columns than its key.
+            let left_len = left.len().min(key_info.len());
+            let right_len = right.len().min(key_info.len());
+            let len = left_len.min(right_len);
+            match compare_immutable(&left[..len], &right[..len], key_info) {
+                std::cmp::Ordering::Equal => match left_len.cmp(&right_len) {
+                    std::cmp::Ordering::Equal => std::cmp::Ordering::Equal,
+                    std::cmp::Ordering::Less => self.tie_breaker.reverse(),
+                    std::cmp::Ordering::Greater => other.tie_breaker,
+                },
+                ord => ord,
+            }
+        })
+    }
+}
+
+impl PartialOrd for IndexKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for IndexKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for IndexKey {}
+
 /// A row version.
 #[derive(Clone, Debug, PartialEq)]
 pub struct RowVersion {
-    begin: TxTimestampOrID,
-    end: Option<TxTimestampOrID>,
-    row: Row,
+    pub(crate) begin: TxTimestampOrID,
+    pub(crate) end: Option<TxTimestampOrID>,
+    pub(crate) row: Row,
 }
 
 pub type TxID = u64;
 
+/// The isolation level of a transaction, see `PRAGMA isolation_level`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// The transaction reads a snapshot of the database as of its start, and aborts only on
+    /// write-write conflicts. Concurrent transactions can produce write skew.
+    #[default]
+    Snapshot,
+    /// Like `Snapshot`, but the transaction is also aborted at commit with
+    /// [LimboError::SerializationFailure] if it has rw-antidependencies with concurrent
+    /// serializable transactions that could make their outcome differ from every serial order.
+    Serializable,
+}
+
+/// When the versions that no transaction can see anymore are garbage collected, see
+/// [MvStore::set_gc_policy] and the `mvcc_gc` pragmas.
+///
+/// A collection starts in the background once the transactions committed since the last one
+/// created or ended `version_threshold` versions, or versions holding `memory_threshold` bytes
+/// of row data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GcPolicy {
+    /// Whether commits start collections. [MvStore::drop_unused_row_versions] collects garbage
+    /// anyway.
+    pub enabled: bool,
+    pub version_threshold: u64,
+    pub memory_threshold: u64,
+}
+
+impl Default for GcPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            version_threshold: 10_000,
+            memory_threshold: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Version counters of a table or index, see [MvStore::version_stats].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VersionStats {
+    /// Rows or index entries with at least one version in memory.
+    pub rows: u64,
+    /// Versions of all the rows or index entries.
+    pub versions: u64,
+    /// The length of the longest version chain of a row or index entry.
+    pub max_chain_length: u64,
+    /// Versions dropped by garbage collection so far.
+    pub collected: u64,
+}
+
 /// A log record contains all the versions inserted and deleted by a transaction.
 #[derive(Clone, Debug)]
 pub struct LogRecord {
     pub(crate) tx_timestamp: TxID,
-    row_versions: Vec<RowVersion>,
+    pub(crate) row_versions: Vec<RowVersion>,
+    /// Versions of index entries, with the index they belong to. Their rows hold the index
+    /// record, see [IndexKey::to_row].
+    pub(crate) index_versions: Vec<(Arc<IndexInfo>, RowVersion)>,
 }
 
 impl LogRecord {
@@ -77,10 +225,19 @@ impl LogRecord {
         Self {
             tx_timestamp,
             row_versions: Vec::new(),
+            index_versions: Vec::new(),
         }
     }
 }
 
+/// A change written to the B-tree by a checkpoint of the logical log.
+enum CheckpointOp {
+    Write(Row),
+    Delete(RowID),
+    WriteIndexEntry(IndexKey),
+    DeleteIndexEntry(IndexKey),
+}
+
 /// A transaction timestamp or ID.
 ///
 /// Versions either track a timestamp or a transaction ID, depending on the
@@ -88,7 +245,7 @@ impl LogRecord {
 /// transaction ID in the `begin` and `end` fields. After a transaction commits,
 /// versions switch to tracking timestamps.
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
-enum TxTimestampOrID {
+pub(crate) enum TxTimestampOrID {
     /// A committed transaction's timestamp.
     Timestamp(u64),
     /// The ID of a non-committed transaction.
@@ -106,18 +263,90 @@ pub struct Transaction {
     begin_ts: u64,
     /// The transaction write set.
     write_set: SkipSet<RowID>,
+    /// The index entries written by the transaction.
+    index_write_set: SkipSet<IndexKey>,
     /// The transaction read set.
     read_set: SkipSet<RowID>,
+    /// The isolation level of the transaction.
+    isolation: IsolationLevel,
+    /// The tables and indexes scanned by a serializable transaction. A scan may have seen a
+    /// different result if any row or entry of them had changed.
+    scan_set: SkipSet<u64>,
+    /// Set when a concurrent serializable transaction read something this transaction wrote.
+    rw_in: AtomicBool,
+    /// Set when this transaction read something a concurrent serializable transaction wrote.
+    rw_out: AtomicBool,
+    /// Row changes made by the transaction, in order. Used to roll back to a savepoint.
+    changes: Vec<RowChange>,
+}
+
+/// A row change made by a transaction.
+#[derive(Clone, Debug, PartialEq)]
+enum RowChange {
+    /// A new version of the row was inserted.
+    Inserted(RowID),
+    /// The visible version of the row was ended.
+    Deleted(RowID),
+    /// A new version of the index entry was inserted.
+    IndexEntryInserted(IndexKey),
+    /// The visible version of the index entry was ended.
+    IndexEntryDeleted(IndexKey),
+}
+
+impl RowChange {
+    /// Whether both changes were made to the same row or index entry.
+    fn same_target(&self, other: &RowChange) -> bool {
+        match (self, other) {
+            (
+                RowChange::Inserted(a) | RowChange::Deleted(a),
+                RowChange::Inserted(b) | RowChange::Deleted(b),
+            ) => a == b,
+            (
+                RowChange::IndexEntryInserted(a) | RowChange::IndexEntryDeleted(a),
+                RowChange::IndexEntryInserted(b) | RowChange::IndexEntryDeleted(b),
+            ) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Undoes the change in the versions of its row or index entry.
+    fn undo(&self, versions: &mut Vec<RowVersion>, tx_id: TxID) {
+        match self {
+            RowChange::Inserted(_) | RowChange::IndexEntryInserted(_) => {
+                if let Some(position) = versions
+                    .iter()
+                    .rposition(|rv| rv.begin == TxTimestampOrID::TxID(tx_id))
+                {
+                    versions.remove(position);
+                }
+            }
+            RowChange::Deleted(_) | RowChange::IndexEntryDeleted(_) => {
+                if let Some(rv) = versions
+                    .iter_mut()
+                    .rev()
+                    .find(|rv| rv.end == Some(TxTimestampOrID::TxID(tx_id)))
+                {
+                    rv.end = None;
+                }
+            }
+        }
+    }
 }
 
 impl Transaction {
-    fn new(tx_id: u64, begin_ts: u64) -> Transaction {
+    fn new(tx_id: u64, begin_ts: u64, isolation: IsolationLevel) -> Transaction {
         Transaction {
             state: TransactionState::Active.into(),
             tx_id,
             begin_ts,
             write_set: SkipSet::new(),
+            index_write_set: SkipSet::new(),
             read_set: SkipSet::new(),
+            isolation,
+            scan_set: SkipSet::new(),
+            rw_in: AtomicBool::new(false),
+            rw_out: AtomicBool::new(false),
+            changes: Vec::new(),
         }
     }
 
@@ -128,6 +357,16 @@ impl Transaction {
     fn insert_to_write_set(&mut self, id: RowID) {
         self.write_set.insert(id);
     }
+
+    fn record_change(&mut self, change: RowChange) {
+        match &change {
+            RowChange::Inserted(id) | RowChange::Deleted(id) => self.insert_to_write_set(*id),
+            RowChange::IndexEntryInserted(key) | RowChange::IndexEntryDeleted(key) => {
+                self.index_write_set.insert(key.clone());
+            }
+        }
+        self.changes.push(change);
+    }
 }
 
 impl std::fmt::Display for Transaction {
@@ -151,6 +390,51 @@ impl std::fmt::Display for Transaction {
     }
 }
 
+/// The reads and writes of a serializable transaction, used to find the rw-antidependencies
+/// between concurrent transactions.
+#[derive(Debug, Default)]
+struct ReadWriteSets {
+    read_set: HashSet<RowID>,
+    scan_set: HashSet<u64>,
+    write_set: HashSet<RowID>,
+    /// The tables and indexes with a row or entry written by the transaction.
+    written: HashSet<u64>,
+}
+
+impl ReadWriteSets {
+    fn of(tx: &Transaction) -> Self {
+        let mut sets = Self::default();
+        sets.read_set
+            .extend(tx.read_set.iter().map(|id| *id.value()));
+        sets.scan_set
+            .extend(tx.scan_set.iter().map(|id| *id.value()));
+        for id in tx.write_set.iter() {
+            sets.write_set.insert(*id.value());
+            sets.written.insert(id.value().table_id);
+        }
+        sets.written
+            .extend(tx.index_write_set.iter().map(|key| key.value().index_id));
+        sets
+    }
+
+    /// Whether a transaction with these sets read something written by a transaction with the
+    /// `other` sets, i.e. whether there is a rw-antidependency from the first to the second.
+    fn reads_written_by(&self, other: &ReadWriteSets) -> bool {
+        self.read_set.iter().any(|id| other.write_set.contains(id))
+            || self.scan_set.iter().any(|id| other.written.contains(id))
+    }
+}
+
+/// A committed serializable transaction. It is kept while serializable transactions that ran
+/// concurrently with it are active, since they can still form rw-antidependencies with it.
+#[derive(Debug)]
+struct CommittedSerializableTx {
+    commit_ts: u64,
+    sets: ReadWriteSets,
+    rw_in: AtomicBool,
+    rw_out: AtomicBool,
+}
+
 /// Transaction state.
 #[derive(Debug, Clone, PartialEq)]
 enum TransactionState {
@@ -266,6 +550,7 @@ pub struct CommitStateMachine<Clock: LogicalClock> {
     tx_id: TxID,
     connection: Arc<Connection>,
     write_set: Vec<RowID>,
+    index_write_set: Vec<IndexKey>,
     write_row_state_machine: Option<StateMachine<WriteRowStateMachine>>,
     delete_row_state_machine: Option<StateMachine<DeleteRowStateMachine>>,
     _phantom: PhantomData<Clock>,
@@ -276,6 +561,8 @@ pub struct WriteRowStateMachine {
     is_finalized: bool,
     pager: Rc<Pager>,
     row: Row,
+    /// Set if the row is an entry of this index, see [IndexKey::to_row].
+    index_info: Option<Arc<IndexInfo>>,
     record: Option<ImmutableRecord>,
     cursor: Option<BTreeCursor>,
 }
@@ -285,6 +572,7 @@ pub enum DeleteRowState {
     Initial,
     CreateCursor,
     Seek,
+    Advance,
     Delete,
 }
 
@@ -294,6 +582,8 @@ pub struct DeleteRowStateMachine {
     pager: Rc<Pager>,
     rowid: RowID,
     column_count: usize,
+    /// Set if the row is an entry of an index, with the record of the entry.
+    index_entry: Option<(Arc<IndexInfo>, ImmutableRecord)>,
     cursor: Option<BTreeCursor>,
 }
 
@@ -306,6 +596,7 @@ impl<Clock: LogicalClock> CommitStateMachine<Clock> {
             tx_id,
             connection,
             write_set: Vec::new(),
+            index_write_set: Vec::new(),
             write_row_state_machine: None,
             delete_row_state_machine: None,
             _phantom: PhantomData,
@@ -314,12 +605,13 @@ impl<Clock: LogicalClock> CommitStateMachine<Clock> {
 }
 
 impl WriteRowStateMachine {
-    fn new(pager: Rc<Pager>, row: Row) -> Self {
+    fn new(pager: Rc<Pager>, row: Row, index_info: Option<Arc<IndexInfo>>) -> Self {
         Self {
             state: WriteRowState::Initial,
             is_finalized: false,
             pager,
             row,
+            index_info,
             record: None,
             cursor: None,
         }
@@ -341,6 +633,10 @@ impl<Clock: LogicalClock> StateTransition for CommitStateMachine<Clock> {
                     .txs
                     .get(&self.tx_id)
                     .ok_or(LimboError::TxTerminated)?;
+                let serializable = tx.value().read().isolation == IsolationLevel::Serializable;
+                // Taken before the transaction lock, since the validation locks the other
+                // transactions.
+                let serializable_guard = serializable.then(|| mvcc_store.serializable_lock.lock());
                 let tx = tx.value().write();
                 match tx.state.load() {
                     TransactionState::Terminated => {
@@ -350,17 +646,28 @@ impl<Clock: LogicalClock> StateTransition for CommitStateMachine<Clock> {
                         assert_eq!(tx.state, TransactionState::Active);
                     }
                 }
+                let serializable_sets = if serializable {
+                    match mvcc_store.validate_serializable(&tx) {
+                        Ok(sets) => Some(sets),
+                        Err(e) => {
+                            tracing::trace!("serialization_failure(tx_id={})", self.tx_id);
+                            drop(tx);
+                            drop(serializable_guard);
+                            mvcc_store.rollback_tx(self.tx_id, self.pager.clone());
+                            return Err(e);
+                        }
+                    }
+                } else {
+                    None
+                };
                 tx.state.store(TransactionState::Preparing);
                 tracing::trace!("prepare_tx(tx_id={})", self.tx_id);
 
-                /* TODO: The code we have here is sufficient for snapshot isolation.
-                ** In order to implement serializability, we need the following steps:
+                /* TODO: Serializable transactions are validated above, by checking for
+                ** rw-antidependencies with concurrent serializable transactions. What is still
+                ** missing is:
                 **
-                ** 1. Validate if all read versions are still visible by inspecting the read_set
-                ** 2. Validate if there are no phantoms by walking the scans from scan_set (which we don't even have yet)
-                **    - a phantom is a version that became visible in the middle of our transaction,
-                **      but wasn't taken into account during one of the scans from the scan_set
-                ** 3. Wait for commit dependencies, which we don't even track yet...
+                ** 1. Wait for commit dependencies, which we don't even track yet...
                 **    Excerpt from what's a commit dependency and how it's tracked in the original paper:
                 **    """
                         A transaction T1 has a commit dependency on another transaction
@@ -428,8 +735,21 @@ impl<Clock: LogicalClock> StateTransition for CommitStateMachine<Clock> {
                 */
                 tx.state.store(TransactionState::Committed(end_ts));
                 tracing::trace!("commit_tx(tx_id={})", self.tx_id);
+                if let Some(sets) = serializable_sets {
+                    mvcc_store.committed_serializable.insert(
+                        self.tx_id,
+                        CommittedSerializableTx {
+                            commit_ts: end_ts,
+                            sets,
+                            rw_in: AtomicBool::new(tx.rw_in.load(Ordering::Acquire)),
+                            rw_out: AtomicBool::new(tx.rw_out.load(Ordering::Acquire)),
+                        },
+                    );
+                }
                 self.write_set
                     .extend(tx.write_set.iter().map(|v| *v.value()));
+                self.index_write_set
+                    .extend(tx.index_write_set.iter().map(|v| v.value().clone()));
                 self.state = CommitState::BeginPagerTxn { end_ts };
                 Ok(TransitionResult::Continue)
             }
@@ -440,15 +760,28 @@ impl<Clock: LogicalClock> StateTransition for CommitStateMachine<Clock> {
                 // 2. Choose a txn to write depending on some heuristics like amount of frames will be written.
                 // 3. ..
                 //
-                let result = self.pager.io.block(|| self.pager.begin_write_tx())?;
+                // The store already checked the rows against concurrent transactions, so the
+                // pages are written on top of the latest snapshot rather than the one read when
+                // the transaction began, which a transaction that committed since has outdated.
+                self.pager.end_read_tx()?;
+                let mut result = self.pager.begin_read_tx()?;
+                if let crate::result::LimboResult::Ok = result {
+                    result = self.pager.io.block(|| self.pager.begin_write_tx())?;
+                }
                 if let crate::result::LimboResult::Busy = result {
                     return Err(LimboError::InternalError(
                         "Pager write transaction busy".to_string(),
                     ));
                 }
-                self.state = CommitState::WriteRow {
-                    end_ts,
-                    write_set_index: 0,
+                // With a logical log, the rows are written to the B-tree by checkpoints. The pager
+                // transaction still commits the pages allocated by schema changes.
+                self.state = if mvcc_store.storage.is_logical_log() {
+                    CommitState::CommitPagerTxn { end_ts }
+                } else {
+                    CommitState::WriteRow {
+                        end_ts,
+                        write_set_index: 0,
+                    }
                 };
                 return Ok(TransitionResult::Continue);
             }
@@ -456,43 +789,65 @@ impl<Clock: LogicalClock> StateTransition for CommitStateMachine<Clock> {
                 end_ts,
                 write_set_index,
             } => {
-                if write_set_index == self.write_set.len() {
+                let num_rows = self.write_set.len();
+                if write_set_index == num_rows + self.index_write_set.len() {
                     self.state = CommitState::CommitPagerTxn { end_ts };
                     return Ok(TransitionResult::Continue);
                 }
-                let id = &self.write_set[write_set_index];
-                if let Some(row_versions) = mvcc_store.rows.get(id) {
-                    let row_versions = row_versions.value().read();
-                    // Find rows that were written by this transaction
-                    for row_version in row_versions.iter() {
-                        if let TxTimestampOrID::TxID(row_tx_id) = row_version.begin {
-                            if row_tx_id == self.tx_id {
-                                let state_machine = mvcc_store
-                                    .write_row_to_pager(self.pager.clone(), &row_version.row)?;
-                                self.write_row_state_machine = Some(state_machine);
-                                self.state = CommitState::WriteRowStateMachine {
-                                    end_ts,
-                                    write_set_index,
-                                };
-                                break;
-                            }
-                        }
-                        if let Some(TxTimestampOrID::TxID(row_tx_id)) = row_version.end {
-                            if row_tx_id == self.tx_id {
-                                let column_count = row_version.row.column_count;
-                                let state_machine = mvcc_store.delete_row_from_pager(
-                                    self.pager.clone(),
-                                    row_version.row.id,
-                                    column_count,
-                                )?;
-                                self.delete_row_state_machine = Some(state_machine);
-                                self.state = CommitState::DeleteRowStateMachine {
-                                    end_ts,
-                                    write_set_index,
-                                };
-                                break;
-                            }
-                        }
+                let (write, index_info) = if write_set_index < num_rows {
+                    let id = &self.write_set[write_set_index];
+                    let write = mvcc_store.rows.get(id).and_then(|row_versions| {
+                        pending_write(&row_versions.value().read(), self.tx_id)
+                    });
+                    (write, None)
+                } else {
+                    let key = &self.index_write_set[write_set_index - num_rows];
+                    let write = mvcc_store.index_rows.get(key).and_then(|row_versions| {
+                        pending_write(&row_versions.value().read(), self.tx_id)
+                    });
+                    (write, Some(key.info.clone()))
+                };
+                match write {
+                    Some(PendingWrite::Write(row)) => {
+                        let state_machine = match index_info {
+                            Some(info) => mvcc_store.write_index_entry_to_pager(
+                                self.pager.clone(),
+                                &row,
+                                &info,
+                            ),
+                            None => mvcc_store.write_row_to_pager(self.pager.clone(), &row),
+                        }?;
+                        self.write_row_state_machine = Some(state_machine);
+                        self.state = CommitState::WriteRowStateMachine {
+                            end_ts,
+                            write_set_index,
+                        };
+                    }
+                    Some(PendingWrite::Delete(row)) => {
+                        let state_machine = match index_info {
+                            Some(info) => mvcc_store.delete_index_entry_from_pager(
+                                self.pager.clone(),
+                                &row,
+                                &info,
+                            ),
+                            None => mvcc_store.delete_row_from_pager(
+                                self.pager.clone(),
+                                row.id,
+                                row.column_count,
+                            ),
+                        }?;
+                        self.delete_row_state_machine = Some(state_machine);
+                        self.state = CommitState::DeleteRowStateMachine {
+                            end_ts,
+                            write_set_index,
+                        };
+                    }
+                    // The transaction created and ended the version, so the B-tree never saw it.
+                    None => {
+                        self.state = CommitState::WriteRow {
+                            end_ts,
+                            write_set_index: write_set_index + 1,
+                        };
                     }
                 }
                 Ok(TransitionResult::Continue)
@@ -567,33 +922,33 @@ impl<Clock: LogicalClock> StateTransition for CommitStateMachine<Clock> {
                 for id in &self.write_set {
                     if let Some(row_versions) = mvcc_store.rows.get(id) {
                         let mut row_versions = row_versions.value().write();
-                        for row_version in row_versions.iter_mut() {
-                            if let TxTimestampOrID::TxID(id) = row_version.begin {
-                                if id == self.tx_id {
-                                    // New version is valid STARTING FROM committing transaction's end timestamp
-                                    // See diagram on page 299: https://www.cs.cmu.edu/~15721-f24/papers/Hekaton.pdf
-                                    row_version.begin = TxTimestampOrID::Timestamp(end_ts);
-                                    mvcc_store.insert_version_raw(
-                                        &mut log_record.row_versions,
-                                        row_version.clone(),
-                                    ); // FIXME: optimize cloning out
-                                }
-                            }
-                            if let Some(TxTimestampOrID::TxID(id)) = row_version.end {
-                                if id == self.tx_id {
-                                    // Old version is valid UNTIL committing transaction's end timestamp
-                                    // See diagram on page 299: https://www.cs.cmu.edu/~15721-f24/papers/Hekaton.pdf
-                                    row_version.end = Some(TxTimestampOrID::Timestamp(end_ts));
-                                    mvcc_store.insert_version_raw(
-                                        &mut log_record.row_versions,
-                                        row_version.clone(),
-                                    ); // FIXME: optimize cloning out
-                                }
-                            }
+                        for row_version in commit_versions(&mut row_versions, self.tx_id, end_ts) {
+                            mvcc_store
+                                .insert_version_raw(&mut log_record.row_versions, row_version);
+                        }
+                    }
+                }
+                for key in &self.index_write_set {
+                    if let Some(row_versions) = mvcc_store.index_rows.get(key) {
+                        let mut row_versions = row_versions.value().write();
+                        for row_version in commit_versions(&mut row_versions, self.tx_id, end_ts) {
+                            log_record
+                                .index_versions
+                                .push((key.info.clone(), row_version));
                         }
                     }
                 }
                 tracing::trace!("updated(tx_id={})", self.tx_id);
+                if mvcc_store.storage.is_logical_log() {
+                    // Marked before logging, so that a checkpoint either writes the rows or leaves
+                    // the record in the log, see [MvStore::checkpoint].
+                    for id in &self.write_set {
+                        mvcc_store.dirty_rows.insert(*id);
+                    }
+                    for key in &self.index_write_set {
+                        mvcc_store.dirty_index_entries.insert(key.clone());
+                    }
+                }
 
                 // We have now updated all the versions with a reference to the
                 // transaction ID to a timestamp and can, therefore, remove the
@@ -604,10 +959,27 @@ impl<Clock: LogicalClock> StateTransition for CommitStateMachine<Clock> {
                 // FIXME: it actually just become a problem for today!!!
                 // TODO: test that reproduces this failure, and then a fix
                 mvcc_store.txs.remove(&self.tx_id);
-                if !log_record.row_versions.is_empty() {
-                    mvcc_store.storage.log_tx(log_record)?;
+                mvcc_store.prune_committed_serializable();
+                let versions =
+                    (log_record.row_versions.len() + log_record.index_versions.len()) as u64;
+                let bytes = log_record
+                    .row_versions
+                    .iter()
+                    .chain(log_record.index_versions.iter().map(|(_, rv)| rv))
+                    .map(|rv| rv.row.data.len() as u64)
+                    .sum::<u64>();
+                if !log_record.row_versions.is_empty() || !log_record.index_versions.is_empty() {
+                    mvcc_store
+                        .storage
+                        .log_tx(log_record, self.connection.get_sync_mode())?;
                 }
                 tracing::trace!("logged(tx_id={})", self.tx_id);
+                if mvcc_store.storage.should_checkpoint()
+                    && !self.connection.is_wal_auto_checkpoint_disabled()
+                {
+                    mvcc_store.checkpoint(&self.pager, &self.connection)?;
+                }
+                mvcc_store.maybe_collect_garbage(versions, bytes);
                 self.finalize(mvcc_store)?;
                 Ok(TransitionResult::Done(()))
             }
@@ -648,12 +1020,21 @@ impl StateTransition for WriteRowStateMachine {
                 let root_page = self.row.id.table_id as usize;
                 let num_columns = self.row.column_count;
 
-                let cursor = BTreeCursor::new_table(
-                    None, // Write directly to B-tree
-                    self.pager.clone(),
-                    root_page,
-                    num_columns,
-                );
+                let cursor = match &self.index_info {
+                    Some(info) => BTreeCursor::new_index_with_info(
+                        None,
+                        self.pager.clone(),
+                        root_page,
+                        info.as_ref().clone(),
+                        num_columns,
+                    ),
+                    None => BTreeCursor::new_table(
+                        None, // Write directly to B-tree
+                        self.pager.clone(),
+                        root_page,
+                        num_columns,
+                    ),
+                };
                 self.cursor = Some(cursor);
 
                 self.state = WriteRowState::Seek;
@@ -661,7 +1042,10 @@ impl StateTransition for WriteRowStateMachine {
             }
             WriteRowState::Seek => {
                 // Position the cursor by seeking to the row position
-                let seek_key = SeekKey::TableRowId(self.row.id.row_id);
+                let seek_key = match &self.index_info {
+                    Some(_) => SeekKey::IndexKey(self.record.as_ref().unwrap()),
+                    None => SeekKey::TableRowId(self.row.id.row_id),
+                };
                 let cursor = self.cursor.as_mut().unwrap();
 
                 match cursor.seek(seek_key, SeekOp::GE { eq_only: true })? {
@@ -677,7 +1061,10 @@ impl StateTransition for WriteRowStateMachine {
             WriteRowState::Insert => {
                 // Insert the record into the B-tree
                 let cursor = self.cursor.as_mut().unwrap();
-                let key = BTreeKey::new_table_rowid(self.row.id.row_id, self.record.as_ref());
+                let key = match &self.index_info {
+                    Some(_) => BTreeKey::new_index_key(self.record.as_ref().unwrap()),
+                    None => BTreeKey::new_table_rowid(self.row.id.row_id, self.record.as_ref()),
+                };
 
                 match cursor
                     .insert(&key)
@@ -717,7 +1104,7 @@ impl StateTransition for DeleteRowStateMachine {
     #[tracing::instrument(fields(state = ?self.state), skip(self, _context))]
     fn step(&mut self, _context: &Self::Context) -> Result<TransitionResult<Self::SMResult>> {
         use crate::storage::btree::BTreeCursor;
-        use crate::types::{IOResult, SeekKey, SeekOp};
+        use crate::types::{IOResult, SeekKey, SeekOp, SeekResult};
 
         match self.state {
             DeleteRowState::Initial => {
@@ -728,27 +1115,65 @@ impl StateTransition for DeleteRowStateMachine {
                 let root_page = self.rowid.table_id as usize;
                 let num_columns = self.column_count;
 
-                let cursor =
-                    BTreeCursor::new_table(None, self.pager.clone(), root_page, num_columns);
+                let cursor = match &self.index_entry {
+                    Some((info, _)) => BTreeCursor::new_index_with_info(
+                        None,
+                        self.pager.clone(),
+                        root_page,
+                        info.as_ref().clone(),
+                        num_columns,
+                    ),
+                    None => {
+                        BTreeCursor::new_table(None, self.pager.clone(), root_page, num_columns)
+                    }
+                };
                 self.cursor = Some(cursor);
 
                 self.state = DeleteRowState::Seek;
                 Ok(TransitionResult::Continue)
             }
             DeleteRowState::Seek => {
-                let seek_key = SeekKey::TableRowId(self.rowid.row_id);
+                let seek_key = match &self.index_entry {
+                    Some((_, record)) => SeekKey::IndexKey(record),
+                    None => SeekKey::TableRowId(self.rowid.row_id),
+                };
                 let cursor = self.cursor.as_mut().unwrap();
 
                 match cursor.seek(seek_key, SeekOp::GE { eq_only: true })? {
-                    IOResult::Done(_) => {
+                    IOResult::Done(SeekResult::Found) => {
                         self.state = DeleteRowState::Delete;
                         Ok(TransitionResult::Continue)
                     }
+                    IOResult::Done(SeekResult::TryAdvance) => {
+                        // An equal index key was seen in an interior page, the entry is next.
+                        self.state = DeleteRowState::Advance;
+                        Ok(TransitionResult::Continue)
+                    }
+                    IOResult::Done(_) => {
+                        // The row was created after the last checkpoint, so it never reached the
+                        // B-tree.
+                        self.finalize(&())?;
+                        Ok(TransitionResult::Done(()))
+                    }
                     IOResult::IO(io) => {
                         return Ok(TransitionResult::Io(io));
                     }
                 }
             }
+            DeleteRowState::Advance => {
+                let cursor = self.cursor.as_mut().unwrap();
+                match cursor.next()? {
+                    IOResult::Done(true) => {
+                        self.state = DeleteRowState::Delete;
+                        Ok(TransitionResult::Continue)
+                    }
+                    IOResult::Done(false) => {
+                        self.finalize(&())?;
+                        Ok(TransitionResult::Done(()))
+                    }
+                    IOResult::IO(io) => Ok(TransitionResult::Io(io)),
+                }
+            }
             DeleteRowState::Delete => {
                 // Insert the record into the B-tree
                 let cursor = self.cursor.as_mut().unwrap();
@@ -785,13 +1210,19 @@ impl StateTransition for DeleteRowStateMachine {
 }
 
 impl DeleteRowStateMachine {
-    fn new(pager: Rc<Pager>, rowid: RowID, column_count: usize) -> Self {
+    fn new(
+        pager: Rc<Pager>,
+        rowid: RowID,
+        column_count: usize,
+        index_entry: Option<(Arc<IndexInfo>, ImmutableRecord)>,
+    ) -> Self {
         Self {
             state: DeleteRowState::Initial,
             is_finalized: false,
             pager,
             rowid,
             column_count,
+            index_entry,
             cursor: None,
         }
     }
@@ -801,26 +1232,62 @@ impl DeleteRowStateMachine {
 #[derive(Debug)]
 pub struct MvStore<Clock: LogicalClock> {
     rows: SkipMap<RowID, RwLock<Vec<RowVersion>>>,
+    /// Versions of index entries. An entry has no other data than its key, so all versions of
+    /// an entry hold the same row.
+    index_rows: SkipMap<IndexKey, RwLock<Vec<RowVersion>>>,
     txs: SkipMap<TxID, RwLock<Transaction>>,
     tx_ids: AtomicU64,
     next_rowid: AtomicU64,
     clock: Clock,
     storage: Storage,
     loaded_tables: RwLock<HashSet<u64>>,
+    /// Rows changed by transactions logged since the last checkpoint of the logical log.
+    dirty_rows: SkipSet<RowID>,
+    /// Like `dirty_rows`, for index entries.
+    dirty_index_entries: SkipSet<IndexKey>,
+    /// Committed serializable transactions that active serializable transactions overlap with.
+    committed_serializable: SkipMap<TxID, CommittedSerializableTx>,
+    /// Serializes the validation of serializable transactions at commit, and their start.
+    serializable_lock: Mutex<()>,
+    gc_policy: RwLock<GcPolicy>,
+    /// Versions created or ended by the transactions committed since the last collection.
+    versions_since_gc: AtomicU64,
+    /// Bytes of row data of `versions_since_gc`.
+    bytes_since_gc: AtomicU64,
+    /// Set while garbage is collected, so that concurrent commits do not start a collection too.
+    gc_running: AtomicBool,
+    /// The thread of the last collection started by a commit.
+    gc_thread: Mutex<Option<std::thread::JoinHandle<()>>>,
+    /// The store itself, which the thread of a collection holds on to.
+    weak_self: Weak<Self>,
+    /// Versions dropped by garbage collection so far, per table or index.
+    collected: SkipMap<u64, AtomicU64>,
 }
 
 impl<Clock: LogicalClock> MvStore<Clock> {
     /// Creates a new database.
-    pub fn new(clock: Clock, storage: Storage) -> Self {
-        Self {
+    pub fn new(clock: Clock, storage: Storage) -> Arc<Self> {
+        Arc::new_cyclic(|weak_self| Self {
             rows: SkipMap::new(),
+            index_rows: SkipMap::new(),
             txs: SkipMap::new(),
             tx_ids: AtomicU64::new(1), // let's reserve transaction 0 for special purposes
             next_rowid: AtomicU64::new(0), // TODO: determine this from B-Tree
             clock,
             storage,
             loaded_tables: RwLock::new(HashSet::new()),
-        }
+            dirty_rows: SkipSet::new(),
+            dirty_index_entries: SkipSet::new(),
+            committed_serializable: SkipMap::new(),
+            serializable_lock: Mutex::new(()),
+            gc_policy: RwLock::new(GcPolicy::default()),
+            versions_since_gc: AtomicU64::new(0),
+            bytes_since_gc: AtomicU64::new(0),
+            gc_running: AtomicBool::new(false),
+            gc_thread: Mutex::new(None),
+            weak_self: weak_self.clone(),
+            collected: SkipMap::new(),
+        })
     }
 
     pub fn get_next_rowid(&self) -> i64 {
@@ -851,7 +1318,7 @@ impl<Clock: LogicalClock> MvStore<Clock> {
             end: None,
             row,
         };
-        tx.insert_to_write_set(id);
+        tx.record_change(RowChange::Inserted(id));
         drop(tx);
         self.insert_version(id, row_version);
         Ok(())
@@ -908,45 +1375,166 @@ impl<Clock: LogicalClock> MvStore<Clock> {
     ///
     pub fn delete(&self, tx_id: TxID, id: RowID, pager: Rc<Pager>) -> Result<bool> {
         tracing::trace!("delete(tx_id={}, id={:?})", tx_id, id);
-        let row_versions_opt = self.rows.get(&id);
-        if let Some(ref row_versions) = row_versions_opt {
-            let mut row_versions = row_versions.value().write();
-            for rv in row_versions.iter_mut().rev() {
-                let tx = self
-                    .txs
-                    .get(&tx_id)
-                    .ok_or(LimboError::NoSuchTransactionID(tx_id.to_string()))?;
-                let tx = tx.value().read();
-                assert_eq!(tx.state, TransactionState::Active);
-                // A transaction cannot delete a version that it cannot see,
-                // nor can it conflict with it.
-                if !rv.is_visible_to(&tx, &self.txs) {
-                    continue;
-                }
-                if is_write_write_conflict(&self.txs, &tx, rv) {
-                    drop(row_versions);
-                    drop(row_versions_opt);
-                    drop(tx);
-                    self.rollback_tx(tx_id, pager);
-                    return Err(LimboError::WriteWriteConflict);
-                }
+        let Some(row_versions) = self.rows.get(&id) else {
+            return Ok(false);
+        };
+        let ended = self.end_visible_version(tx_id, row_versions.value());
+        drop(row_versions);
+        match ended {
+            Ok(true) => {
+                self.record_change(tx_id, RowChange::Deleted(id))?;
+                Ok(true)
+            }
+            Ok(false) => Ok(false),
+            Err(e) => {
+                self.rollback_tx(tx_id, pager);
+                Err(e)
+            }
+        }
+    }
 
-                rv.end = Some(TxTimestampOrID::TxID(tx.tx_id));
-                drop(row_versions);
-                drop(row_versions_opt);
-                drop(tx);
-                let tx = self
-                    .txs
-                    .get(&tx_id)
-                    .ok_or(LimboError::NoSuchTransactionID(tx_id.to_string()))?;
-                let mut tx = tx.value().write();
-                tx.insert_to_write_set(id);
-                return Ok(true);
+    /// Ends the version in `versions` that is visible to transaction `tx_id`. Returns false if
+    /// no version is visible, and an error if another transaction is updating the version.
+    fn end_visible_version(&self, tx_id: TxID, versions: &RwLock<Vec<RowVersion>>) -> Result<bool> {
+        let mut versions = versions.write();
+        let tx = self
+            .txs
+            .get(&tx_id)
+            .ok_or(LimboError::NoSuchTransactionID(tx_id.to_string()))?;
+        let tx = tx.value().read();
+        assert_eq!(tx.state, TransactionState::Active);
+        for rv in versions.iter_mut().rev() {
+            // A transaction cannot delete a version that it cannot see,
+            // nor can it conflict with it.
+            if !rv.is_visible_to(&tx, &self.txs) {
+                continue;
             }
+            if is_write_write_conflict(&self.txs, &tx, rv) {
+                return Err(LimboError::WriteWriteConflict);
+            }
+            rv.end = Some(TxTimestampOrID::TxID(tx.tx_id));
+            return Ok(true);
         }
         Ok(false)
     }
 
+    fn record_change(&self, tx_id: TxID, change: RowChange) -> Result<()> {
+        let tx = self
+            .txs
+            .get(&tx_id)
+            .ok_or(LimboError::NoSuchTransactionID(tx_id.to_string()))?;
+        tx.value().write().record_change(change);
+        Ok(())
+    }
+
+    /// Inserts a new version of the index entry `key` within the transaction `tx_id`.
+    pub fn insert_index_entry(&self, tx_id: TxID, key: IndexKey) -> Result<()> {
+        tracing::trace!(
+            "insert_index_entry(tx_id={}, index_id={})",
+            tx_id,
+            key.index_id
+        );
+        let row_version = RowVersion {
+            begin: TxTimestampOrID::TxID(tx_id),
+            end: None,
+            row: key.to_row(),
+        };
+        self.record_change(tx_id, RowChange::IndexEntryInserted(key.clone()))?;
+        let versions = self
+            .index_rows
+            .get_or_insert_with(key, || RwLock::new(Vec::new()));
+        let mut versions = versions.value().write();
+        self.insert_version_raw(&mut versions, row_version);
+        Ok(())
+    }
+
+    /// Deletes the index entry `key` within the transaction `tx_id`, like [Self::delete].
+    pub fn delete_index_entry(
+        &self,
+        tx_id: TxID,
+        key: &IndexKey,
+        pager: Rc<Pager>,
+    ) -> Result<bool> {
+        tracing::trace!(
+            "delete_index_entry(tx_id={}, index_id={})",
+            tx_id,
+            key.index_id
+        );
+        let Some(row_versions) = self.index_rows.get(key) else {
+            return Ok(false);
+        };
+        let ended = self.end_visible_version(tx_id, row_versions.value());
+        drop(row_versions);
+        match ended {
+            Ok(true) => {
+                self.record_change(tx_id, RowChange::IndexEntryDeleted(key.clone()))?;
+                Ok(true)
+            }
+            Ok(false) => Ok(false),
+            Err(e) => {
+                self.rollback_tx(tx_id, pager);
+                Err(e)
+            }
+        }
+    }
+
+    /// Retrieves the row of the index entry `key` if a version of the entry is visible to
+    /// transaction `tx_id`, see [IndexKey::to_row].
+    pub fn read_index_entry(&self, tx_id: TxID, key: &IndexKey) -> Result<Option<Row>> {
+        let tx = self
+            .txs
+            .get(&tx_id)
+            .ok_or(LimboError::NoSuchTransactionID(tx_id.to_string()))?;
+        let tx = tx.value().read();
+        assert_eq!(tx.state, TransactionState::Active);
+        let Some(row_versions) = self.index_rows.get(key) else {
+            return Ok(None);
+        };
+        let row_versions = row_versions.value().read();
+        Ok(row_versions
+            .iter()
+            .rev()
+            .find(|rv| rv.is_visible_to(&tx, &self.txs))
+            .map(|rv| rv.row.clone()))
+    }
+
+    /// Returns the key of the first index entry from `bound` on that is visible to transaction
+    /// `tx_id`, going towards larger keys if `forwards` is set and towards smaller ones
+    /// otherwise. Only entries of the index of the bound are considered.
+    pub fn seek_index_entry(
+        &self,
+        bound: Bound<&IndexKey>,
+        forwards: bool,
+        tx_id: TxID,
+    ) -> Option<IndexKey> {
+        let index_id = match bound {
+            Bound::Included(key) | Bound::Excluded(key) => key.index_id,
+            Bound::Unbounded => unreachable!("index seeks are bounded by a key of the index"),
+        };
+        let tx = self.txs.get(&tx_id).unwrap();
+        let tx = tx.value().read();
+        let is_visible = |entry: &crossbeam_skiplist::map::Entry<'_, IndexKey, _>| {
+            let row_versions: &RwLock<Vec<RowVersion>> = entry.value();
+            row_versions
+                .read()
+                .iter()
+                .any(|rv| rv.is_visible_to(&tx, &self.txs))
+        };
+        let entry = if forwards {
+            self.index_rows
+                .range((bound, Bound::Unbounded))
+                .take_while(|entry| entry.key().index_id == index_id)
+                .find(is_visible)
+        } else {
+            self.index_rows
+                .range((Bound::Unbounded, bound))
+                .rev()
+                .take_while(|entry| entry.key().index_id == index_id)
+                .find(is_visible)
+        };
+        entry.map(|entry| entry.key().clone())
+    }
+
     /// Retrieves a row from the table with the given `id`.
     ///
     /// This operation is performed within the scope of the transaction identified
@@ -980,6 +1568,24 @@ impl<Clock: LogicalClock> MvStore<Clock> {
         Ok(None)
     }
 
+    /// Records that transaction `tx_id` looked up the row `id`, whether or not it found it.
+    pub fn record_read(&self, tx_id: TxID, id: RowID) {
+        if let Some(tx) = self.txs.get(&tx_id) {
+            tx.value().read().insert_to_read_set(id);
+        }
+    }
+
+    /// Records that transaction `tx_id` scanned the table or index `id`. Only serializable
+    /// transactions track their scans.
+    pub fn record_scan(&self, tx_id: TxID, id: u64) {
+        if let Some(tx) = self.txs.get(&tx_id) {
+            let tx = tx.value().read();
+            if tx.isolation == IsolationLevel::Serializable {
+                tx.scan_set.insert(id);
+            }
+        }
+    }
+
     /// Gets all row ids in the database.
     pub fn scan_row_ids(&self) -> Result<Vec<RowID>> {
         tracing::trace!("scan_row_ids");
@@ -1072,6 +1678,30 @@ impl<Clock: LogicalClock> MvStore<Clock> {
             .map(|_| *row.key())
     }
 
+    /// Returns the id of the last row of the table before `end` that is visible to the
+    /// transaction.
+    pub fn get_prev_row_id_for_table(&self, table_id: u64, end: i64, tx_id: TxID) -> Option<RowID> {
+        tracing::trace!(
+            "get_prev_row_id_for_table(table_id={}, range_end={})",
+            table_id,
+            end,
+        );
+        let min_bound = RowID {
+            table_id,
+            row_id: i64::MIN,
+        };
+        let max_bound = RowID {
+            table_id,
+            row_id: end,
+        };
+        let tx = self.txs.get(&tx_id).unwrap();
+        let tx = tx.value().read();
+        self.rows
+            .range(min_bound..max_bound)
+            .rev()
+            .find_map(|row| self.find_last_visible_version(&tx, row))
+    }
+
     pub fn seek_rowid(
         &self,
         bound: Bound<&RowID>,
@@ -1080,16 +1710,24 @@ impl<Clock: LogicalClock> MvStore<Clock> {
     ) -> Option<RowID> {
         tracing::trace!("seek_rowid(bound={:?}, lower_bound={})", bound, lower_bound,);
 
+        let table_id = match bound {
+            Bound::Included(id) | Bound::Excluded(id) => id.table_id,
+            Bound::Unbounded => unreachable!("rowid seeks are bounded by a row of the table"),
+        };
         let tx = self.txs.get(&tx_id).unwrap();
         let tx = tx.value().read();
+        // Rows that are not visible to the transaction are skipped, without leaving the table.
         if lower_bound {
             self.rows
-                .lower_bound(bound)
-                .and_then(|entry| self.find_last_visible_version(&tx, entry))
+                .range((bound, Bound::Unbounded))
+                .take_while(|entry| entry.key().table_id == table_id)
+                .find_map(|entry| self.find_last_visible_version(&tx, entry))
         } else {
             self.rows
-                .upper_bound(bound)
-                .and_then(|entry| self.find_last_visible_version(&tx, entry))
+                .range((Bound::Unbounded, bound))
+                .rev()
+                .take_while(|entry| entry.key().table_id == table_id)
+                .find_map(|entry| self.find_last_visible_version(&tx, entry))
         }
     }
 
@@ -1099,10 +1737,19 @@ impl<Clock: LogicalClock> MvStore<Clock> {
     /// that you can use to perform operations within the transaction. All changes made within the
     /// transaction are isolated from other transactions until you commit the transaction.
     pub fn begin_tx(&self, pager: Rc<Pager>) -> TxID {
+        self.begin_tx_with_isolation(pager, IsolationLevel::Snapshot)
+    }
+
+    /// Begins a new transaction with the given isolation level, see [Self::begin_tx].
+    pub fn begin_tx_with_isolation(&self, pager: Rc<Pager>, isolation: IsolationLevel) -> TxID {
+        // A serializable transaction must be visible to the pruning of committed serializable
+        // transactions as soon as it has a begin timestamp.
+        let _guard =
+            (isolation == IsolationLevel::Serializable).then(|| self.serializable_lock.lock());
         let tx_id = self.get_tx_id();
         let begin_ts = self.get_timestamp();
-        let tx = Transaction::new(tx_id, begin_ts);
-        tracing::trace!("begin_tx(tx_id={})", tx_id);
+        let tx = Transaction::new(tx_id, begin_ts, isolation);
+        tracing::trace!("begin_tx(tx_id={}, isolation={:?})", tx_id, isolation);
         self.txs.insert(tx_id, RwLock::new(tx));
 
         // TODO: we need to tie a pager's read transaction to a transaction ID, so that future refactors to read
@@ -1150,17 +1797,33 @@ impl<Clock: LogicalClock> MvStore<Clock> {
         tx.state.store(TransactionState::Aborted);
         tracing::trace!("abort(tx_id={})", tx_id);
         let write_set: Vec<RowID> = tx.write_set.iter().map(|v| *v.value()).collect();
+        let index_write_set: Vec<IndexKey> = tx
+            .index_write_set
+            .iter()
+            .map(|v| v.value().clone())
+            .collect();
         drop(tx);
 
         for ref id in write_set {
             if let Some(row_versions) = self.rows.get(id) {
                 let mut row_versions = row_versions.value().write();
                 row_versions.retain(|rv| rv.begin != TxTimestampOrID::TxID(tx_id));
+                restore_ended_versions(&mut row_versions, tx_id);
                 if row_versions.is_empty() {
                     self.rows.remove(id);
                 }
             }
         }
+        for ref key in index_write_set {
+            if let Some(row_versions) = self.index_rows.get(key) {
+                let mut row_versions = row_versions.value().write();
+                row_versions.retain(|rv| rv.begin != TxTimestampOrID::TxID(tx_id));
+                restore_ended_versions(&mut row_versions, tx_id);
+                if row_versions.is_empty() {
+                    self.index_rows.remove(key);
+                }
+            }
+        }
 
         let tx = tx_unlocked.value().read();
         tx.state.store(TransactionState::Terminated);
@@ -1169,6 +1832,199 @@ impl<Clock: LogicalClock> MvStore<Clock> {
         // FIXME: verify that we can already remove the transaction here!
         // Maybe it's fine for snapshot isolation, but too early for serializable?
         self.txs.remove(&tx_id);
+        self.prune_committed_serializable();
+    }
+
+    /// Finds the rw-antidependencies between the serializable transaction `tx`, which is about
+    /// to commit, and the concurrent serializable transactions, active or committed.
+    ///
+    /// Like in serializable snapshot isolation (Cahill et al., SIGMOD 2008), a transaction with
+    /// both an incoming and an outgoing rw-antidependency is the pivot of a potential cycle in
+    /// the serialization graph. The commit fails with [LimboError::SerializationFailure] if it
+    /// would make `tx` or a committed transaction a pivot. The check is conservative: it can
+    /// abort transactions that would have been serializable. Otherwise the dependencies are
+    /// recorded in the transactions, and the read and write sets of `tx` are returned.
+    ///
+    /// The caller holds `serializable_lock`.
+    fn validate_serializable(&self, tx: &Transaction) -> Result<ReadWriteSets> {
+        let sets = ReadWriteSets::of(tx);
+        let mut rw_in = tx.rw_in.load(Ordering::Acquire);
+        let mut rw_out = tx.rw_out.load(Ordering::Acquire);
+        // Active transactions that read what `tx` wrote, and that wrote what `tx` read.
+        let mut readers = Vec::new();
+        let mut writers = Vec::new();
+        for entry in self.txs.iter() {
+            if *entry.key() == tx.tx_id {
+                continue;
+            }
+            let other = entry.value().read();
+            if other.isolation != IsolationLevel::Serializable
+                || other.state != TransactionState::Active
+            {
+                continue;
+            }
+            let other_sets = ReadWriteSets::of(&other);
+            if other_sets.reads_written_by(&sets) {
+                rw_in = true;
+                readers.push(other.tx_id);
+            }
+            if sets.reads_written_by(&other_sets) {
+                rw_out = true;
+                writers.push(other.tx_id);
+            }
+        }
+        let mut committed_readers = Vec::new();
+        let mut committed_writers = Vec::new();
+        for entry in self.committed_serializable.iter() {
+            let other = entry.value();
+            if other.commit_ts <= tx.begin_ts {
+                continue;
+            }
+            if other.sets.reads_written_by(&sets) {
+                if other.rw_in.load(Ordering::Acquire) {
+                    return Err(LimboError::SerializationFailure);
+                }
+                rw_in = true;
+                committed_readers.push(*entry.key());
+            }
+            if sets.reads_written_by(&other.sets) {
+                if other.rw_out.load(Ordering::Acquire) {
+                    return Err(LimboError::SerializationFailure);
+                }
+                rw_out = true;
+                committed_writers.push(*entry.key());
+            }
+        }
+        if rw_in && rw_out {
+            return Err(LimboError::SerializationFailure);
+        }
+
+        tx.rw_in.store(rw_in, Ordering::Release);
+        tx.rw_out.store(rw_out, Ordering::Release);
+        for tx_id in readers {
+            if let Some(other) = self.txs.get(&tx_id) {
+                other.value().read().rw_out.store(true, Ordering::Release);
+            }
+        }
+        for tx_id in writers {
+            if let Some(other) = self.txs.get(&tx_id) {
+                other.value().read().rw_in.store(true, Ordering::Release);
+            }
+        }
+        for tx_id in committed_readers {
+            if let Some(other) = self.committed_serializable.get(&tx_id) {
+                other.value().rw_out.store(true, Ordering::Release);
+            }
+        }
+        for tx_id in committed_writers {
+            if let Some(other) = self.committed_serializable.get(&tx_id) {
+                other.value().rw_in.store(true, Ordering::Release);
+            }
+        }
+        Ok(sets)
+    }
+
+    /// Forgets the committed serializable transactions that no active serializable transaction
+    /// ran concurrently with.
+    fn prune_committed_serializable(&self) {
+        if self.committed_serializable.is_empty() {
+            return;
+        }
+        let _guard = self.serializable_lock.lock();
+        let oldest_begin_ts = self
+            .txs
+            .iter()
+            .filter_map(|entry| {
+                let tx = entry.value().read();
+                (tx.isolation == IsolationLevel::Serializable
+                    && tx.state == TransactionState::Active)
+                    .then_some(tx.begin_ts)
+            })
+            .min();
+        for entry in self.committed_serializable.iter() {
+            if oldest_begin_ts.is_none_or(|begin_ts| entry.value().commit_ts <= begin_ts) {
+                entry.remove();
+            }
+        }
+    }
+
+    /// Returns a savepoint marker for the transaction with the specified ID.
+    ///
+    /// The marker can later be passed to [MvStore::rollback_to_savepoint] to undo the changes
+    /// the transaction makes after this call.
+    pub fn savepoint(&self, tx_id: TxID) -> Result<usize> {
+        let tx = self
+            .txs
+            .get(&tx_id)
+            .ok_or(LimboError::NoSuchTransactionID(tx_id.to_string()))?;
+        let tx = tx.value().read();
+        assert_eq!(tx.state, TransactionState::Active);
+        Ok(tx.changes.len())
+    }
+
+    /// Undoes the changes made by a transaction after the given savepoint marker, most
+    /// recent first. The transaction itself stays active.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_id` - The ID of the transaction to roll back partially.
+    /// * `savepoint` - A marker returned by [MvStore::savepoint] for this transaction.
+    pub fn rollback_to_savepoint(&self, tx_id: TxID, savepoint: usize) -> Result<()> {
+        let tx_unlocked = self
+            .txs
+            .get(&tx_id)
+            .ok_or(LimboError::NoSuchTransactionID(tx_id.to_string()))?;
+        let mut tx = tx_unlocked.value().write();
+        assert_eq!(tx.state, TransactionState::Active);
+        tracing::trace!(
+            "rollback_to_savepoint(tx_id={}, savepoint={})",
+            tx_id,
+            savepoint
+        );
+        let savepoint = savepoint.min(tx.changes.len());
+        let undone = tx.changes.split_off(savepoint);
+        // Rows that are no longer touched by the transaction must leave the write set,
+        // otherwise commit would look for versions that don't exist anymore.
+        for change in undone.iter() {
+            if tx.changes.iter().any(|c| c.same_target(change)) {
+                continue;
+            }
+            match change {
+                RowChange::Inserted(id) | RowChange::Deleted(id) => {
+                    tx.write_set.remove(id);
+                }
+                RowChange::IndexEntryInserted(key) | RowChange::IndexEntryDeleted(key) => {
+                    tx.index_write_set.remove(key);
+                }
+            }
+        }
+        drop(tx);
+
+        for change in undone.into_iter().rev() {
+            match &change {
+                RowChange::Inserted(id) | RowChange::Deleted(id) => {
+                    let Some(row_versions) = self.rows.get(id) else {
+                        continue;
+                    };
+                    let mut row_versions = row_versions.value().write();
+                    change.undo(&mut row_versions, tx_id);
+                    if row_versions.is_empty() {
+                        self.rows.remove(id);
+                    }
+                }
+                RowChange::IndexEntryInserted(key) | RowChange::IndexEntryDeleted(key) => {
+                    let Some(row_versions) = self.index_rows.get(key) else {
+                        continue;
+                    };
+                    let mut row_versions = row_versions.value().write();
+                    change.undo(&mut row_versions, tx_id);
+                    if row_versions.is_empty() {
+                        self.index_rows.remove(key);
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Generates next unique transaction id
@@ -1181,8 +2037,8 @@ impl<Clock: LogicalClock> MvStore<Clock> {
         self.clock.get_timestamp()
     }
 
-    /// Removes unused row  versions with very loose heuristics,
-    /// which sometimes leaves versions intact for too long.
+    /// Removes the row and index versions that no active or future transaction can see
+    /// anymore, i.e. the versions that ended before the oldest active transaction began.
     /// Returns the number of removed versions.
     pub fn drop_unused_row_versions(&self) -> usize {
         tracing::trace!(
@@ -1190,36 +2046,18 @@ impl<Clock: LogicalClock> MvStore<Clock> {
             self.txs.len(),
             self.rows.len()
         );
+        // Taken before looking at the transactions, so that the transactions that begin in
+        // the meantime cannot see the versions that ended before it.
+        let now = self.get_timestamp();
+        let horizon = self.oldest_active_begin_ts().map_or(now, |ts| ts.min(now));
         let mut dropped = 0;
         let mut to_remove = Vec::new();
         for entry in self.rows.iter() {
             let mut row_versions = entry.value().write();
+            let before = row_versions.len();
             row_versions.retain(|rv| {
-                // FIXME: should take rv.begin into account as well
-                let should_stay = match rv.end {
-                    Some(TxTimestampOrID::Timestamp(version_end_ts)) => {
-                        // a transaction started before this row version ended, ergo row version is needed
-                        // NOTICE: O(row_versions x transactions), but also lock-free, so sounds acceptable
-                        self.txs.iter().any(|tx| {
-                            let tx = tx.value().read();
-                            // FIXME: verify!
-                            match tx.state.load() {
-                                TransactionState::Active | TransactionState::Preparing => {
-                                    version_end_ts > tx.begin_ts
-                                }
-                                _ => false,
-                            }
-                        })
-                    }
-                    // Let's skip potentially complex logic if the transafction is still
-                    // active/tracked. We will drop the row version when the transaction
-                    // gets garbage-collected itself, it will always happen eventually.
-                    Some(TxTimestampOrID::TxID(tx_id)) => !self.txs.contains_key(&tx_id),
-                    // this row version is current, ergo visible
-                    None => true,
-                };
+                let should_stay = is_version_needed(rv, horizon);
                 if !should_stay {
-                    dropped += 1;
                     tracing::trace!(
                         "Dropping row version {:?} {:?}-{:?}",
                         entry.key(),
@@ -1229,26 +2067,324 @@ impl<Clock: LogicalClock> MvStore<Clock> {
                 }
                 should_stay
             });
+            let table_dropped = before - row_versions.len();
+            if table_dropped > 0 {
+                self.count_collected(entry.key().table_id, table_dropped);
+                dropped += table_dropped;
+            }
             if row_versions.is_empty() {
                 to_remove.push(*entry.key());
             }
         }
         for id in to_remove {
-            self.rows.remove(&id);
+            if let Some(entry) = self.rows.get(&id) {
+                // A transaction may have inserted a version since.
+                if entry.value().read().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+        let mut to_remove = Vec::new();
+        for entry in self.index_rows.iter() {
+            let mut row_versions = entry.value().write();
+            let before = row_versions.len();
+            row_versions.retain(|rv| is_version_needed(rv, horizon));
+            let index_dropped = before - row_versions.len();
+            if index_dropped > 0 {
+                self.count_collected(entry.key().index_id, index_dropped);
+                dropped += index_dropped;
+            }
+            if row_versions.is_empty() {
+                to_remove.push(entry.key().clone());
+            }
+        }
+        for key in to_remove {
+            if let Some(entry) = self.index_rows.get(&key) {
+                if entry.value().read().is_empty() {
+                    entry.remove();
+                }
+            }
         }
         dropped
     }
 
-    pub fn recover(&self) -> Result<()> {
-        let tx_log = self.storage.read_tx_log()?;
+    /// Returns the begin timestamp of the oldest transaction that can still read versions.
+    fn oldest_active_begin_ts(&self) -> Option<u64> {
+        self.txs
+            .iter()
+            .filter_map(|entry| {
+                let tx = entry.value().read();
+                match tx.state.load() {
+                    TransactionState::Active | TransactionState::Preparing => Some(tx.begin_ts),
+                    _ => None,
+                }
+            })
+            .min()
+    }
+
+    fn count_collected(&self, id: u64, dropped: usize) {
+        self.collected
+            .get_or_insert_with(id, || AtomicU64::new(0))
+            .value()
+            .fetch_add(dropped as u64, Ordering::Relaxed);
+    }
+
+    /// Returns the garbage collection policy.
+    pub fn gc_policy(&self) -> GcPolicy {
+        *self.gc_policy.read()
+    }
+
+    /// Sets when commits start collecting the versions that no transaction can see anymore.
+    pub fn set_gc_policy(&self, policy: GcPolicy) {
+        *self.gc_policy.write() = policy;
+    }
+
+    /// Adds the `versions` that a transaction committed, holding `bytes` of row data, to the
+    /// versions committed since the last collection, and starts a collection if they exceed a
+    /// threshold of the GC policy.
+    fn maybe_collect_garbage(&self, versions: u64, bytes: u64) {
+        let policy = self.gc_policy();
+        let versions = self.versions_since_gc.fetch_add(versions, Ordering::AcqRel) + versions;
+        let bytes = self.bytes_since_gc.fetch_add(bytes, Ordering::AcqRel) + bytes;
+        if !policy.enabled
+            || (versions < policy.version_threshold && bytes < policy.memory_threshold)
+        {
+            return;
+        }
+        if self.gc_running.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        self.versions_since_gc.store(0, Ordering::Release);
+        self.bytes_since_gc.store(0, Ordering::Release);
+        self.start_garbage_collection();
+    }
+
+    /// Collects garbage on a thread of its own, so that the commit does not wait for it.
+    #[cfg(not(target_family = "wasm"))]
+    fn start_garbage_collection(&self) {
+        let Some(store) = self.weak_self.upgrade() else {
+            self.gc_running.store(false, Ordering::Release);
+            return;
+        };
+        // Held while spawning, so that the thread of a later collection cannot be stored first.
+        let mut gc_thread = self.gc_thread.lock();
+        *gc_thread = Some(std::thread::spawn(move || store.collect_garbage()));
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn start_garbage_collection(&self) {
+        self.collect_garbage();
+    }
+
+    fn collect_garbage(&self) {
+        let dropped = self.drop_unused_row_versions();
+        tracing::debug!("garbage collected {dropped} versions");
+        self.gc_running.store(false, Ordering::Release);
+    }
+
+    /// Waits for the collection started by the last commit, if any, to finish.
+    pub fn wait_for_garbage_collection(&self) {
+        let gc_thread = self.gc_thread.lock().take();
+        if let Some(gc_thread) = gc_thread {
+            gc_thread
+                .join()
+                .expect("garbage collection thread panicked");
+        }
+    }
+
+    /// Returns the version counters of every table and index with versions in memory or
+    /// collected versions, by table or index id.
+    pub fn version_stats(&self) -> BTreeMap<u64, VersionStats> {
+        let mut stats: BTreeMap<u64, VersionStats> = BTreeMap::new();
+        let mut add_chain = |id: u64, length: usize| {
+            if length == 0 {
+                return;
+            }
+            let stats = stats.entry(id).or_default();
+            stats.rows += 1;
+            stats.versions += length as u64;
+            stats.max_chain_length = stats.max_chain_length.max(length as u64);
+        };
+        for entry in self.rows.iter() {
+            add_chain(entry.key().table_id, entry.value().read().len());
+        }
+        for entry in self.index_rows.iter() {
+            add_chain(entry.key().index_id, entry.value().read().len());
+        }
+        for entry in self.collected.iter() {
+            stats.entry(*entry.key()).or_default().collected =
+                entry.value().load(Ordering::Relaxed);
+        }
+        stats
+    }
+
+    /// Writes the transactions left in the logical log by a process that stopped before
+    /// checkpointing them to the B-tree, then truncates the log.
+    pub fn recover(&self, pager: &Rc<Pager>, connection: &Connection) -> Result<()> {
+        if !self.storage.is_logical_log() {
+            return Ok(());
+        }
+        let tx_log = self.storage.read_tx_log(&connection.syms.borrow())?;
+        let mut ops = Vec::new();
         for record in tx_log {
             tracing::debug!("recover() -> tx_timestamp={}", record.tx_timestamp);
+            // The versions of a row are ordered by begin timestamp, so the version a transaction
+            // ended comes before the one that replaced it.
             for version in record.row_versions {
-                self.insert_version(version.row.id, version);
+                ops.push(match version.end {
+                    Some(_) => CheckpointOp::Delete(version.row.id),
+                    None => CheckpointOp::Write(version.row),
+                });
+            }
+            for (info, version) in record.index_versions {
+                let index_record = ImmutableRecord::from_bin_record(version.row.data);
+                let key = IndexKey::new(version.row.id.table_id, index_record, info);
+                ops.push(match version.end {
+                    Some(_) => CheckpointOp::DeleteIndexEntry(key),
+                    None => CheckpointOp::WriteIndexEntry(key),
+                });
             }
             self.clock.reset(record.tx_timestamp);
         }
-        Ok(())
+        self.storage.checkpoint(|| {
+            if !ops.is_empty() && !self.write_to_btree(&ops, pager, connection)? {
+                return Err(LimboError::Busy);
+            }
+            Ok(true)
+        })
+    }
+
+    /// Writes the rows changed by the transactions in the logical log to the B-tree and
+    /// truncates the log. Nothing happens if another connection is writing to the WAL.
+    ///
+    /// A commit marks its rows as dirty before logging them, and logging waits for the
+    /// checkpoint to finish. So either the checkpoint sees the rows and writes them, or their
+    /// record is appended after the log was truncated.
+    pub fn checkpoint(&self, pager: &Rc<Pager>, connection: &Connection) -> Result<()> {
+        self.storage.checkpoint(|| {
+            // The rows are unmarked before their versions are read: a transaction that commits
+            // in the meantime marks them again for the next checkpoint.
+            let ids: Vec<RowID> = self.dirty_rows.iter().map(|id| *id.value()).collect();
+            for id in &ids {
+                self.dirty_rows.remove(id);
+            }
+            let keys: Vec<IndexKey> = self
+                .dirty_index_entries
+                .iter()
+                .map(|key| key.value().clone())
+                .collect();
+            for key in &keys {
+                self.dirty_index_entries.remove(key);
+            }
+            let ops: Vec<CheckpointOp> = ids
+                .iter()
+                .map(|id| self.checkpoint_op(*id))
+                .chain(keys.iter().map(|key| self.checkpoint_index_op(key)))
+                .collect();
+            let result = self.write_to_btree(&ops, pager, connection);
+            if !matches!(result, Ok(true)) {
+                for id in ids {
+                    self.dirty_rows.insert(id);
+                }
+                for key in keys {
+                    self.dirty_index_entries.insert(key);
+                }
+            }
+            result
+        })
+    }
+
+    /// Returns the change that brings row `id` in the B-tree up to date with its last
+    /// committed version.
+    fn checkpoint_op(&self, id: RowID) -> CheckpointOp {
+        let current = self.rows.get(&id).and_then(|versions| {
+            versions
+                .value()
+                .read()
+                .iter()
+                .rev()
+                .find(|rv| {
+                    matches!(rv.begin, TxTimestampOrID::Timestamp(_))
+                        && !matches!(rv.end, Some(TxTimestampOrID::Timestamp(_)))
+                })
+                .map(|rv| rv.row.clone())
+        });
+        match current {
+            Some(row) => CheckpointOp::Write(row),
+            // Garbage collection may have dropped the versions of a deleted row already.
+            None => CheckpointOp::Delete(id),
+        }
+    }
+
+    /// Like [Self::checkpoint_op], for the index entry `key`.
+    fn checkpoint_index_op(&self, key: &IndexKey) -> CheckpointOp {
+        let is_current = self.index_rows.get(key).is_some_and(|versions| {
+            versions.value().read().iter().any(|rv| {
+                matches!(rv.begin, TxTimestampOrID::Timestamp(_))
+                    && !matches!(rv.end, Some(TxTimestampOrID::Timestamp(_)))
+            })
+        });
+        match is_current {
+            true => CheckpointOp::WriteIndexEntry(key.clone()),
+            false => CheckpointOp::DeleteIndexEntry(key.clone()),
+        }
+    }
+
+    /// Applies `ops` to the B-tree in a pager transaction of their own. Returns false if another
+    /// connection holds the write lock of the WAL.
+    fn write_to_btree(
+        &self,
+        ops: &[CheckpointOp],
+        pager: &Rc<Pager>,
+        connection: &Connection,
+    ) -> Result<bool> {
+        use crate::result::LimboResult;
+
+        if let LimboResult::Busy = pager.begin_read_tx()? {
+            return Ok(false);
+        }
+        if let LimboResult::Busy = pager.io.block(|| pager.begin_write_tx())? {
+            pager.end_read_tx()?;
+            return Ok(false);
+        }
+        if let Err(e) = ops
+            .iter()
+            .try_for_each(|op| self.write_op_to_btree(op, pager))
+        {
+            pager
+                .io
+                .block(|| pager.end_untracked_tx(true, true, connection))?;
+            return Err(e);
+        }
+        pager
+            .io
+            .block(|| pager.end_untracked_tx(false, true, connection))?;
+        tracing::debug!("checkpointed {} rows of the logical log", ops.len());
+        Ok(true)
+    }
+
+    fn write_op_to_btree(&self, op: &CheckpointOp, pager: &Rc<Pager>) -> Result<()> {
+        match op {
+            CheckpointOp::Write(row) => {
+                let mut state_machine = self.write_row_to_pager(pager.clone(), row)?;
+                step_to_completion(&mut state_machine, pager)
+            }
+            CheckpointOp::Delete(id) => {
+                // The column count only matters for reading the row, which a delete does not.
+                let mut state_machine = self.delete_row_from_pager(pager.clone(), *id, 1)?;
+                step_to_completion(&mut state_machine, pager)
+            }
+            CheckpointOp::WriteIndexEntry(key) => {
+                let mut state_machine =
+                    self.write_index_entry_to_pager(pager.clone(), &key.to_row(), &key.info)?;
+                step_to_completion(&mut state_machine, pager)
+            }
+            CheckpointOp::DeleteIndexEntry(key) => {
+                let mut state_machine =
+                    self.delete_index_entry_from_pager(pager.clone(), &key.to_row(), &key.info)?;
+                step_to_completion(&mut state_machine, pager)
+            }
+        }
     }
 
     // Extracts the begin timestamp from a transaction
@@ -1276,10 +2412,12 @@ impl<Clock: LogicalClock> MvStore<Clock> {
         // another data structure, e.g. a BTreeSet. If it proves to be too quadratic empirically,
         // we can either switch to a tree-like structure, or at least use partition_point()
         // which performs a binary search for the insertion point.
+        // Versions with the same begin timestamp, e.g. the ones written by a single transaction,
+        // stay in the order they were inserted: rolling back to a savepoint relies on it.
         let position = versions
             .iter()
             .rposition(|v| {
-                self.get_begin_timestamp(&v.begin) < self.get_begin_timestamp(&row_version.begin)
+                self.get_begin_timestamp(&v.begin) <= self.get_begin_timestamp(&row_version.begin)
             })
             .map(|p| p + 1)
             .unwrap_or(0);
@@ -1301,6 +2439,24 @@ impl<Clock: LogicalClock> MvStore<Clock> {
             StateMachine::<WriteRowStateMachine>::new(WriteRowStateMachine::new(
                 pager,
                 row.clone(),
+                None,
+            ));
+
+        Ok(state_machine)
+    }
+
+    /// Writes the index entry held by `row`, see [IndexKey::to_row], to the index B-tree.
+    pub fn write_index_entry_to_pager(
+        &self,
+        pager: Rc<Pager>,
+        row: &Row,
+        index_info: &Arc<IndexInfo>,
+    ) -> Result<StateMachine<WriteRowStateMachine>> {
+        let state_machine: StateMachine<WriteRowStateMachine> =
+            StateMachine::<WriteRowStateMachine>::new(WriteRowStateMachine::new(
+                pager,
+                row.clone(),
+                Some(index_info.clone()),
             ));
 
         Ok(state_machine)
@@ -1315,12 +2471,31 @@ impl<Clock: LogicalClock> MvStore<Clock> {
         let state_machine: StateMachine<DeleteRowStateMachine> = StateMachine::<
             DeleteRowStateMachine,
         >::new(
-            DeleteRowStateMachine::new(pager, rowid, column_count),
+            DeleteRowStateMachine::new(pager, rowid, column_count, None),
         );
 
         Ok(state_machine)
     }
 
+    /// Deletes the index entry held by `row`, see [IndexKey::to_row], from the index B-tree.
+    pub fn delete_index_entry_from_pager(
+        &self,
+        pager: Rc<Pager>,
+        row: &Row,
+        index_info: &Arc<IndexInfo>,
+    ) -> Result<StateMachine<DeleteRowStateMachine>> {
+        let record = ImmutableRecord::from_bin_record(row.data.clone());
+        let state_machine: StateMachine<DeleteRowStateMachine> =
+            StateMachine::<DeleteRowStateMachine>::new(DeleteRowStateMachine::new(
+                pager,
+                row.id,
+                row.column_count,
+                Some((index_info.clone(), record)),
+            ));
+
+        Ok(state_machine)
+    }
+
     /// Try to scan for row ids in the table.
     ///
     /// This function loads all row ids of a table if the rowids of table were not populated yet.
@@ -1420,6 +2595,75 @@ impl<Clock: LogicalClock> MvStore<Clock> {
         Ok(())
     }
 
+    /// Like [Self::maybe_initialize_table], for the entries of the index `index_id`.
+    pub fn maybe_initialize_index(
+        &self,
+        index_id: u64,
+        index_info: &Arc<IndexInfo>,
+        pager: Rc<Pager>,
+    ) -> Result<()> {
+        tracing::trace!("scan_index_entries(index_id={})", index_id);
+
+        // Index root pages never collide with table root pages, so both share the set.
+        if self.loaded_tables.read().contains(&index_id) {
+            return Ok(());
+        }
+
+        self.scan_load_index(index_id, index_info, pager)?;
+
+        self.loaded_tables.write().insert(index_id);
+
+        Ok(())
+    }
+
+    /// Scans the index B-tree and inserts its entries into the database.
+    fn scan_load_index(
+        &self,
+        index_id: u64,
+        index_info: &Arc<IndexInfo>,
+        pager: Rc<Pager>,
+    ) -> Result<()> {
+        let mut cursor = BTreeCursor::new_index_with_info(
+            None, // No MVCC cursor for scanning
+            pager.clone(),
+            index_id as usize,
+            index_info.as_ref().clone(),
+            index_info.num_cols,
+        );
+        pager.io.block(|| cursor.rewind())?;
+        while !cursor.is_empty() {
+            let record = pager.io.block(|| {
+                Ok(match cursor.record()? {
+                    IOResult::Done(record) => {
+                        IOResult::Done(record.map(|r| r.get_payload().to_vec()))
+                    }
+                    IOResult::IO(io) => IOResult::IO(io),
+                })
+            })?;
+            let Some(payload) = record else {
+                break;
+            };
+            let key = IndexKey::new(
+                index_id,
+                ImmutableRecord::from_bin_record(payload),
+                index_info.clone(),
+            );
+            // We insert the entry with 0 timestamp, because it's the only version we have on
+            // initialization.
+            let row_version = RowVersion {
+                begin: TxTimestampOrID::Timestamp(0),
+                end: None,
+                row: key.to_row(),
+            };
+            let versions = self
+                .index_rows
+                .get_or_insert_with(key, || RwLock::new(Vec::new()));
+            self.insert_version_raw(&mut versions.value().write(), row_version);
+            pager.io.block(|| cursor.next())?;
+        }
+        Ok(())
+    }
+
     pub fn get_last_rowid(&self, table_id: u64) -> Option<i64> {
         let last_rowid = self
             .rows
@@ -1433,6 +2677,92 @@ impl<Clock: LogicalClock> MvStore<Clock> {
     }
 }
 
+/// Switches the versions that transaction `tx_id` created or ended to its commit timestamp
+/// `end_ts`, and returns them. A version both created and ended by the transaction is
+/// returned once, with both timestamps.
+fn commit_versions(versions: &mut [RowVersion], tx_id: TxID, end_ts: u64) -> Vec<RowVersion> {
+    let mut committed = Vec::new();
+    for row_version in versions.iter_mut() {
+        let mut updated = false;
+        if row_version.begin == TxTimestampOrID::TxID(tx_id) {
+            // New version is valid STARTING FROM committing transaction's end timestamp
+            // See diagram on page 299: https://www.cs.cmu.edu/~15721-f24/papers/Hekaton.pdf
+            row_version.begin = TxTimestampOrID::Timestamp(end_ts);
+            updated = true;
+        }
+        if row_version.end == Some(TxTimestampOrID::TxID(tx_id)) {
+            // Old version is valid UNTIL committing transaction's end timestamp
+            // See diagram on page 299: https://www.cs.cmu.edu/~15721-f24/papers/Hekaton.pdf
+            row_version.end = Some(TxTimestampOrID::Timestamp(end_ts));
+            updated = true;
+        }
+        if updated {
+            committed.push(row_version.clone()); // FIXME: optimize cloning out
+        }
+    }
+    committed
+}
+
+/// Whether a version may still be visible to a transaction, given the begin timestamp of the
+/// oldest transaction that can still read versions, or a timestamp taken before it began.
+fn is_version_needed(rv: &RowVersion, horizon: u64) -> bool {
+    match rv.end {
+        // A transaction that began before the version ended can see it.
+        Some(TxTimestampOrID::Timestamp(version_end_ts)) => version_end_ts > horizon,
+        // The transaction ending the version has not committed yet.
+        Some(TxTimestampOrID::TxID(_)) => true,
+        // this row version is current, ergo visible
+        None => true,
+    }
+}
+
+/// Makes the versions that the aborted transaction `tx_id` ended current again.
+fn restore_ended_versions(versions: &mut [RowVersion], tx_id: TxID) {
+    for row_version in versions.iter_mut() {
+        if row_version.end == Some(TxTimestampOrID::TxID(tx_id)) {
+            row_version.end = None;
+        }
+    }
+}
+
+/// What a committing transaction has to write to the B-tree for a row or index entry.
+enum PendingWrite {
+    Write(Row),
+    Delete(Row),
+}
+
+/// Returns the write that brings the B-tree in line with the versions that transaction
+/// `tx_id` created or ended, if any.
+fn pending_write(versions: &[RowVersion], tx_id: TxID) -> Option<PendingWrite> {
+    let created = versions
+        .iter()
+        .find(|rv| rv.begin == TxTimestampOrID::TxID(tx_id) && rv.end.is_none());
+    if let Some(rv) = created {
+        return Some(PendingWrite::Write(rv.row.clone()));
+    }
+    // A version created before the transaction and ended by it is in the B-tree.
+    versions
+        .iter()
+        .find(|rv| {
+            rv.begin != TxTimestampOrID::TxID(tx_id) && rv.end == Some(TxTimestampOrID::TxID(tx_id))
+        })
+        .map(|rv| PendingWrite::Delete(rv.row.clone()))
+}
+
+/// Runs a state machine that writes to the B-tree, waiting for its IO.
+fn step_to_completion<S: StateTransition<Context = ()>>(
+    state_machine: &mut StateMachine<S>,
+    pager: &Pager,
+) -> Result<()> {
+    loop {
+        match state_machine.step(&())? {
+            TransitionResult::Io(io) => io.wait(pager.io.as_ref())?,
+            TransitionResult::Continue => {}
+            TransitionResult::Done(_) => return Ok(()),
+        }
+    }
+}
+
 /// A write-write conflict happens when transaction T_current attempts to update a
 /// row version that is:
 /// a) currently being updated by an active transaction T_previous, or
@@ -267,6 +267,92 @@ fn test_commit() {
     db.mvcc_store.drop_unused_row_versions();
 }
 
+fn update_row(db: &MvccTestDb, row_id: i64, data: &str) {
+    let tx = db.mvcc_store.begin_tx(db.conn.pager.borrow().clone());
+    let row = generate_simple_string_row(1, row_id, data);
+    assert!(db
+        .mvcc_store
+        .update(tx, row, db.conn.pager.borrow().clone())
+        .unwrap());
+    commit_tx(db.mvcc_store.clone(), &db.conn, tx).unwrap();
+    db.mvcc_store.wait_for_garbage_collection();
+}
+
+#[test]
+fn test_gc_on_commit() {
+    let db = MvccTestDb::new();
+    db.mvcc_store.set_gc_policy(GcPolicy {
+        enabled: true,
+        version_threshold: 4,
+        memory_threshold: u64::MAX,
+    });
+    let tx = db.mvcc_store.begin_tx(db.conn.pager.borrow().clone());
+    let row = generate_simple_string_row(1, 1, "Hello");
+    db.mvcc_store.insert(tx, row).unwrap();
+    commit_tx(db.mvcc_store.clone(), &db.conn, tx).unwrap();
+
+    // An update commits two versions, the one it ended and the one it created.
+    update_row(&db, 1, "World");
+    assert_eq!(db.mvcc_store.version_stats()[&1].versions, 2);
+    update_row(&db, 1, "Hello, world!");
+    assert_eq!(
+        db.mvcc_store.version_stats()[&1],
+        VersionStats {
+            rows: 1,
+            versions: 1,
+            max_chain_length: 1,
+            collected: 2,
+        }
+    );
+    update_row(&db, 1, "Bye");
+    assert_eq!(db.mvcc_store.version_stats()[&1].max_chain_length, 2);
+}
+
+#[test]
+fn test_gc_keeps_versions_visible_to_active_tx() {
+    let db = MvccTestDb::new();
+    let tx = db.mvcc_store.begin_tx(db.conn.pager.borrow().clone());
+    let row = generate_simple_string_row(1, 1, "Hello");
+    db.mvcc_store.insert(tx, row.clone()).unwrap();
+    commit_tx(db.mvcc_store.clone(), &db.conn, tx).unwrap();
+
+    let conn2 = db.db.connect().unwrap();
+    let reader = db.mvcc_store.begin_tx(conn2.pager.borrow().clone());
+    update_row(&db, 1, "World");
+    assert_eq!(db.mvcc_store.drop_unused_row_versions(), 0);
+    let id = RowID {
+        table_id: 1,
+        row_id: 1,
+    };
+    assert_eq!(db.mvcc_store.read(reader, id).unwrap(), Some(row));
+    commit_tx(db.mvcc_store.clone(), &conn2, reader).unwrap();
+    assert_eq!(db.mvcc_store.drop_unused_row_versions(), 1);
+}
+
+#[test]
+fn test_rollback_restores_deleted_version() {
+    let db = MvccTestDb::new();
+    let tx = db.mvcc_store.begin_tx(db.conn.pager.borrow().clone());
+    let row = generate_simple_string_row(1, 1, "Hello");
+    db.mvcc_store.insert(tx, row.clone()).unwrap();
+    commit_tx(db.mvcc_store.clone(), &db.conn, tx).unwrap();
+
+    let id = RowID {
+        table_id: 1,
+        row_id: 1,
+    };
+    let tx = db.mvcc_store.begin_tx(db.conn.pager.borrow().clone());
+    assert!(db
+        .mvcc_store
+        .delete(tx, id, db.conn.pager.borrow().clone())
+        .unwrap());
+    db.mvcc_store
+        .rollback_tx(tx, db.conn.pager.borrow().clone());
+    assert_eq!(db.mvcc_store.drop_unused_row_versions(), 0);
+    let tx = db.mvcc_store.begin_tx(db.conn.pager.borrow().clone());
+    assert_eq!(db.mvcc_store.read(tx, id).unwrap(), Some(row));
+}
+
 #[test]
 fn test_rollback() {
     let db = MvccTestDb::new();
@@ -317,6 +403,88 @@ fn test_rollback() {
     assert_eq!(row5, None);
 }
 
+#[test]
+fn test_rollback_to_savepoint() {
+    let db = MvccTestDb::new();
+    let tx1 = db.mvcc_store.begin_tx(db.conn.pager.borrow().clone());
+    let row1 = generate_simple_string_row(1, 1, "Hello");
+    db.mvcc_store.insert(tx1, row1.clone()).unwrap();
+    let savepoint = db.mvcc_store.savepoint(tx1).unwrap();
+    let row2 = generate_simple_string_row(1, 1, "World");
+    db.mvcc_store
+        .update(tx1, row2.clone(), db.conn.pager.borrow().clone())
+        .unwrap();
+    let row3 = generate_simple_string_row(1, 2, "Again");
+    db.mvcc_store.insert(tx1, row3).unwrap();
+    db.mvcc_store.rollback_to_savepoint(tx1, savepoint).unwrap();
+    let row = db
+        .mvcc_store
+        .read(
+            tx1,
+            RowID {
+                table_id: 1,
+                row_id: 1,
+            },
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(row1, row);
+    let row = db
+        .mvcc_store
+        .read(
+            tx1,
+            RowID {
+                table_id: 1,
+                row_id: 2,
+            },
+        )
+        .unwrap();
+    assert_eq!(row, None);
+
+    // The transaction is still usable after rolling back to the savepoint.
+    db.mvcc_store
+        .update(tx1, row2.clone(), db.conn.pager.borrow().clone())
+        .unwrap();
+    let row = db
+        .mvcc_store
+        .read(
+            tx1,
+            RowID {
+                table_id: 1,
+                row_id: 1,
+            },
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(row2, row);
+}
+
+#[test]
+fn test_rollback_to_savepoint_after_repeated_updates() {
+    let db = MvccTestDb::new();
+    let pager = db.conn.pager.borrow().clone();
+    let id = RowID {
+        table_id: 1,
+        row_id: 1,
+    };
+    let tx1 = db.mvcc_store.begin_tx(pager.clone());
+    db.mvcc_store
+        .insert(tx1, generate_simple_string_row(1, 1, "Hello"))
+        .unwrap();
+    let row2 = generate_simple_string_row(1, 1, "World");
+    db.mvcc_store
+        .update(tx1, row2.clone(), pager.clone())
+        .unwrap();
+    let savepoint = db.mvcc_store.savepoint(tx1).unwrap();
+    for value in ["Again", "And again"] {
+        let row = generate_simple_string_row(1, 1, value);
+        db.mvcc_store.update(tx1, row, pager.clone()).unwrap();
+    }
+    db.mvcc_store.rollback_to_savepoint(tx1, savepoint).unwrap();
+    let row = db.mvcc_store.read(tx1, id).unwrap().unwrap();
+    assert_eq!(row2, row);
+}
+
 #[test]
 fn test_dirty_write() {
     let db = MvccTestDb::new();
@@ -561,6 +729,85 @@ fn test_lost_update() {
     assert_eq!(tx2_row, row);
 }
 
+/// Runs two transactions with the given isolation level that both read rows 1 and 2, after
+/// which the first one updates row `updates.0` and the second one row `updates.1`. Returns the
+/// results of committing the first and then the second transaction.
+fn run_read_update_pair(
+    db: &MvccTestDb,
+    isolation: IsolationLevel,
+    updates: (i64, i64),
+) -> (Result<()>, Result<()>) {
+    let conn1 = db.db.connect().unwrap();
+    let conn2 = db.db.connect().unwrap();
+    let tx1 = db
+        .mvcc_store
+        .begin_tx_with_isolation(conn1.pager.borrow().clone(), isolation);
+    let tx2 = db
+        .mvcc_store
+        .begin_tx_with_isolation(conn2.pager.borrow().clone(), isolation);
+    for tx_id in [tx1, tx2] {
+        for row_id in [1, 2] {
+            let id = RowID {
+                table_id: 1,
+                row_id,
+            };
+            assert!(db.mvcc_store.read(tx_id, id).unwrap().is_some());
+        }
+    }
+    let row1 = generate_simple_string_row(1, updates.0, "tx1");
+    assert!(db
+        .mvcc_store
+        .update(tx1, row1, conn1.pager.borrow().clone())
+        .unwrap());
+    let row2 = generate_simple_string_row(1, updates.1, "tx2");
+    assert!(db
+        .mvcc_store
+        .update(tx2, row2, conn2.pager.borrow().clone())
+        .unwrap());
+    (
+        commit_tx(db.mvcc_store.clone(), &conn1, tx1),
+        commit_tx(db.mvcc_store.clone(), &conn2, tx2),
+    )
+}
+
+#[test]
+fn test_write_skew() {
+    let db = MvccTestDb::new();
+    let tx = db.mvcc_store.begin_tx(db.conn.pager.borrow().clone());
+    for row_id in [1, 2] {
+        let row = generate_simple_string_row(1, row_id, "Hello");
+        db.mvcc_store.insert(tx, row).unwrap();
+    }
+    commit_tx(db.mvcc_store.clone(), &db.conn, tx).unwrap();
+
+    // Each transaction updates a row the other one read, which snapshot isolation allows.
+    let (res1, res2) = run_read_update_pair(&db, IsolationLevel::Snapshot, (1, 2));
+    res1.unwrap();
+    res2.unwrap();
+
+    // Under serializable isolation the first one to commit becomes the pivot of a cycle.
+    let (res1, res2) = run_read_update_pair(&db, IsolationLevel::Serializable, (1, 2));
+    assert!(matches!(res1, Err(LimboError::SerializationFailure)));
+    res2.unwrap();
+}
+
+#[test]
+fn test_serializable_rw_antidependency_without_cycle() {
+    let db = MvccTestDb::new();
+    let tx = db.mvcc_store.begin_tx(db.conn.pager.borrow().clone());
+    for row_id in [1, 2, 3] {
+        let row = generate_simple_string_row(1, row_id, "Hello");
+        db.mvcc_store.insert(tx, row).unwrap();
+    }
+    commit_tx(db.mvcc_store.clone(), &db.conn, tx).unwrap();
+
+    // The second transaction read the row the first one updates, but the first one did not
+    // read the row the second one updates, so both can commit.
+    let (res1, res2) = run_read_update_pair(&db, IsolationLevel::Serializable, (1, 3));
+    res1.unwrap();
+    res2.unwrap();
+}
+
 // Test for the visibility to check if a new transaction can see old committed values.
 // This test checks for the typo present in the paper, explained in https://github.com/penberg/mvcc-rs/issues/15
 #[test]
@@ -653,7 +900,7 @@ fn test_future_row() {
 
 use crate::mvcc::cursor::MvccLazyCursor;
 use crate::mvcc::database::{MvStore, Row, RowID};
-use crate::types::Text;
+use crate::types::{KeyInfo, Text};
 use crate::MemoryIO;
 use crate::RefValue;
 use crate::Value;
@@ -906,7 +1153,9 @@ fn test_cursor_modification_during_scan() {
     let new_row_id = RowID::new(table_id, 3);
     let new_row = generate_simple_string_row(table_id, new_row_id.row_id, "new_row");
 
-    cursor.insert(new_row).unwrap();
+    cursor
+        .insert(new_row, db.conn.pager.borrow().clone())
+        .unwrap();
     let row = db.mvcc_store.read(tx_id, new_row_id).unwrap().unwrap();
     let mut record = ImmutableRecord::new(1024);
     record.start_serialization(&row.data);
@@ -984,14 +1233,9 @@ or not found |                    | the timestamp.
 */
 
 fn new_tx(tx_id: TxID, begin_ts: u64, state: TransactionState) -> RwLock<Transaction> {
-    let state = state.into();
-    RwLock::new(Transaction {
-        state,
-        tx_id,
-        begin_ts,
-        write_set: SkipSet::new(),
-        read_set: SkipSet::new(),
-    })
+    let tx = Transaction::new(tx_id, begin_ts, IsolationLevel::default());
+    tx.state.store(state);
+    RwLock::new(tx)
 }
 
 #[test]
@@ -1124,6 +1368,50 @@ fn test_restart() {
     }
 }
 
+#[test]
+fn test_recover_logical_log() {
+    let mut db = MvccTestDbNoConn::new_with_random_db();
+    {
+        let conn = db.connect();
+        conn.execute("CREATE TABLE test (x)").unwrap();
+        conn.execute("INSERT INTO test (x) VALUES (1), (2), (3)")
+            .unwrap();
+        conn.execute("UPDATE test SET x = 20 WHERE x = 2").unwrap();
+        conn.execute("DELETE FROM test WHERE x = 3").unwrap();
+        // Dropping the connection without closing it skips the checkpoint, like a crash.
+    }
+    db.db.take();
+    db.restart();
+
+    let conn = db.connect();
+    let rows = get_rows(&conn, "SELECT x FROM test");
+    assert_eq!(
+        rows,
+        vec![vec![Value::Integer(1)], vec![Value::Integer(20)]]
+    );
+}
+
+#[test]
+fn test_checkpoint_logical_log_on_close() {
+    let mut db = MvccTestDbNoConn::new_with_random_db();
+    let log_path = format!("{}-log", db.path.as_ref().unwrap());
+    {
+        let conn = db.connect();
+        conn.execute("CREATE TABLE test (x)").unwrap();
+        conn.execute("INSERT INTO test (x) VALUES (1), (2)")
+            .unwrap();
+        assert!(std::fs::metadata(&log_path).unwrap().len() > 0);
+        conn.close().unwrap();
+    }
+    assert_eq!(std::fs::metadata(&log_path).unwrap().len(), 0);
+    db.db.take();
+    db.restart();
+
+    let conn = db.connect();
+    let rows = get_rows(&conn, "SELECT x FROM test");
+    assert_eq!(rows, vec![vec![Value::Integer(1)], vec![Value::Integer(2)]]);
+}
+
 #[test]
 fn test_connection_sees_other_connection_changes() {
     let db = MvccTestDbNoConn::new_with_random_db();
@@ -1243,3 +1531,187 @@ fn get_rows(conn: &Arc<Connection>, query: &str) -> Vec<Vec<Value>> {
     }
     rows
 }
+
+fn index_key(info: &Arc<IndexInfo>, value: i64, rowid: i64) -> IndexKey {
+    let record = ImmutableRecord::from_values(&[Value::Integer(value), Value::Integer(rowid)], 2);
+    IndexKey::new(2, record, info.clone())
+}
+
+#[test]
+fn test_index_seek_snapshot_isolation() {
+    let db = MvccTestDbNoConn::new_with_random_db();
+    let conn = db.connect();
+    let mvcc_store = db.get_mvcc_store();
+    let key_info = KeyInfo {
+        sort_order: turso_parser::ast::SortOrder::Asc,
+        collation: crate::translate::collate::CollationSeq::Binary,
+    };
+    let info = Arc::new(IndexInfo {
+        key_info: vec![key_info.clone(), key_info],
+        has_rowid: true,
+        num_cols: 2,
+    });
+
+    let tx1 = mvcc_store.begin_tx(conn.pager.borrow().clone());
+    mvcc_store
+        .insert_index_entry(tx1, index_key(&info, 10, 1))
+        .unwrap();
+    commit_tx_no_conn(&db, tx1, &conn).unwrap();
+
+    let reader_conn = db.connect();
+    let reader = mvcc_store.begin_tx(reader_conn.pager.borrow().clone());
+    let tx2 = mvcc_store.begin_tx(conn.pager.borrow().clone());
+    mvcc_store
+        .insert_index_entry(tx2, index_key(&info, 5, 2))
+        .unwrap();
+    assert!(mvcc_store
+        .delete_index_entry(tx2, &index_key(&info, 10, 1), conn.pager.borrow().clone())
+        .unwrap());
+    commit_tx_no_conn(&db, tx2, &conn).unwrap();
+
+    // Seek for the first entry with a value >= 5, holding only the leading column.
+    let probe = IndexKey::new_seek_key(
+        2,
+        ImmutableRecord::from_values(&[Value::Integer(5)], 1),
+        info.clone(),
+        std::cmp::Ordering::Greater,
+    );
+    let seek = |tx_id| mvcc_store.seek_index_entry(Bound::Included(&probe), true, tx_id);
+    // The reader started before the second transaction committed.
+    assert_eq!(seek(reader), Some(index_key(&info, 10, 1)));
+    let tx3 = mvcc_store.begin_tx(conn.pager.borrow().clone());
+    assert_eq!(seek(tx3), Some(index_key(&info, 5, 2)));
+    assert_eq!(
+        mvcc_store.seek_index_entry(Bound::Excluded(&index_key(&info, 5, 2)), true, tx3),
+        None
+    );
+}
+
+#[test]
+fn test_index_scan_with_conn() {
+    let mut db = MvccTestDbNoConn::new_with_random_db();
+    {
+        let conn = db.connect();
+        conn.execute("CREATE TABLE test (id INTEGER PRIMARY KEY, x)")
+            .unwrap();
+        conn.execute("CREATE INDEX test_x ON test (x)").unwrap();
+        conn.execute("INSERT INTO test (id, x) VALUES (1, 30), (2, 10), (3, 20), (4, 40)")
+            .unwrap();
+        conn.execute("UPDATE test SET x = 50 WHERE x = 10").unwrap();
+        conn.execute("DELETE FROM test WHERE x = 40").unwrap();
+        let rows = get_rows(&conn, "SELECT id FROM test WHERE x >= 20 ORDER BY x");
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::Integer(3)],
+                vec![Value::Integer(1)],
+                vec![Value::Integer(2)]
+            ]
+        );
+        conn.close().unwrap();
+    }
+    db.db.take();
+    db.restart();
+
+    let conn = db.connect();
+    let rows = get_rows(&conn, "SELECT id, x FROM test WHERE x > 20 ORDER BY x DESC");
+    assert_eq!(
+        rows,
+        vec![
+            vec![Value::Integer(2), Value::Integer(50)],
+            vec![Value::Integer(1), Value::Integer(30)]
+        ]
+    );
+    let rows = get_rows(&conn, "SELECT id FROM test WHERE x = 10");
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn test_serializable_write_skew_with_conn() {
+    let db = MvccTestDbNoConn::new_with_random_db();
+    let conn1 = db.connect();
+    conn1
+        .execute("CREATE TABLE accounts (id INTEGER PRIMARY KEY, balance INTEGER)")
+        .unwrap();
+    conn1
+        .execute("INSERT INTO accounts VALUES (1, 100), (2, 100)")
+        .unwrap();
+    let conn2 = db.connect();
+    conn1
+        .execute("PRAGMA isolation_level = serializable")
+        .unwrap();
+    conn2
+        .execute("PRAGMA isolation_level = 'serializable'")
+        .unwrap();
+    for conn in [&conn1, &conn2] {
+        assert_eq!(
+            get_rows(conn, "PRAGMA isolation_level"),
+            vec![vec![Value::build_text("serializable")]]
+        );
+    }
+
+    // Both check the total balance and then withdraw from a different account.
+    for conn in [&conn1, &conn2] {
+        conn.execute("BEGIN").unwrap();
+        let rows = get_rows(conn, "SELECT sum(balance) FROM accounts");
+        assert_eq!(rows, vec![vec![Value::Integer(200)]]);
+    }
+    conn1
+        .execute("UPDATE accounts SET balance = balance - 150 WHERE id = 1")
+        .unwrap();
+    conn2
+        .execute("UPDATE accounts SET balance = balance - 150 WHERE id = 2")
+        .unwrap();
+    assert!(matches!(
+        conn1.execute("COMMIT"),
+        Err(LimboError::SerializationFailure)
+    ));
+    conn2.execute("COMMIT").unwrap();
+
+    // The retry sees the withdrawal of the other transaction.
+    conn1.execute("BEGIN").unwrap();
+    let rows = get_rows(&conn1, "SELECT sum(balance) FROM accounts");
+    assert_eq!(rows, vec![vec![Value::Integer(50)]]);
+    conn1.execute("COMMIT").unwrap();
+    let rows = get_rows(&conn1, "SELECT balance FROM accounts ORDER BY id");
+    assert_eq!(
+        rows,
+        vec![vec![Value::Integer(100)], vec![Value::Integer(-50)]]
+    );
+}
+
+#[test]
+fn test_mvcc_stats_pragma() {
+    let db = MvccTestDbNoConn::new_with_random_db();
+    let conn = db.connect();
+    conn.execute("CREATE TABLE test (x)").unwrap();
+    conn.execute("INSERT INTO test (x) VALUES (1), (2), (3)")
+        .unwrap();
+    let stats = |conn: &Arc<Connection>| {
+        get_rows(conn, "PRAGMA mvcc_stats")
+            .into_iter()
+            .find(|row| row[0] == Value::build_text("test"))
+            .unwrap()[1..]
+            .to_vec()
+    };
+
+    // Without garbage collection, every update leaves the previous version behind.
+    conn.execute("UPDATE test SET x = x + 1").unwrap();
+    assert_eq!(stats(&conn), [3, 6, 2, 0].map(Value::Integer).to_vec());
+
+    conn.execute("PRAGMA mvcc_gc_version_threshold = 1")
+        .unwrap();
+    assert_eq!(
+        get_rows(&conn, "PRAGMA mvcc_gc_version_threshold"),
+        vec![vec![Value::Integer(1)]]
+    );
+    conn.execute("UPDATE test SET x = x + 1").unwrap();
+    db.get_mvcc_store().wait_for_garbage_collection();
+    assert_eq!(stats(&conn), [3, 3, 1, 6].map(Value::Integer).to_vec());
+
+    // Without automatic collections, the versions are left behind again.
+    conn.execute("PRAGMA mvcc_gc = off").unwrap();
+    conn.execute("UPDATE test SET x = x + 1").unwrap();
+    db.get_mvcc_store().wait_for_garbage_collection();
+    assert_eq!(stats(&conn), [3, 6, 2, 6].map(Value::Integer).to_vec());
+}
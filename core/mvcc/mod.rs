@@ -24,12 +24,16 @@
 //!   prevents lost updates by detecting the write-write conflict and letting the
 //!   first-writer win by aborting the later transaction.
 //!
-//! TODO: phantom reads, cursor lost updates, read skew, write skew.
+//! * A *write skew* occurs when transactions T_m and T_n read overlapping values
+//!   and each updates a value that the other one read. Snapshot isolation allows
+//!   it. Serializable transactions detect the rw-antidependencies between them at
+//!   commit and abort one of them.
+//!
+//! TODO: phantom reads, cursor lost updates, read skew.
 //!
 //! ## TODO
 //!
 //! * Optimistic reads and writes
-//! * Garbage collection
 
 pub mod clock;
 pub mod cursor;
@@ -37,7 +41,7 @@ pub mod database;
 pub mod persistent_storage;
 
 pub use clock::LocalClock;
-pub use database::MvStore;
+pub use database::{GcPolicy, IsolationLevel, MvStore, VersionStats};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,377 @@
+//! The logical log of the MVCC store.
+//!
+//! Every committed transaction appends a [LogRecord] holding the row versions it created or
+//! ended. The log is the only durable copy of these rows until a checkpoint writes them to the
+//! B-tree and truncates the log. When the database is opened again, the records left in the log
+//! are replayed into the B-tree.
+//!
+//! Each record is stored as its payload length, the payload and an FNV-1a checksum of the
+//! payload. The payload holds the commit timestamp, the number of row versions and then for each
+//! version:
+//!
+//! | field        | size     |
+//! |--------------|----------|
+//! | table id     | 8        |
+//! | row id       | 8        |
+//! | begin        | 8        |
+//! | has end      | 1        |
+//! | end          | 8, if set|
+//! | column count | 4        |
+//! | data length  | 4        |
+//! | data         | variable |
+//!
+//! The row versions are followed by the number of index entry versions and then for each of
+//! them the comparison info of its index and the version, laid out like a row version with the
+//! index id as table id. The comparison info is the number of key columns, the sort order (1 for
+//! descending) and collation name of each key column, whether the index ends with a rowid and
+//! the number of columns of the index.
+//!
+//! All integers are little-endian. A record that is cut short or whose checksum does not match
+//! was being written when the process stopped, so it and everything after it is ignored.
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use turso_parser::ast::SortOrder;
+
+use crate::mvcc::database::{LogRecord, Row, RowID, RowVersion, TxTimestampOrID};
+use crate::translate::collate::CollationSeq;
+use crate::types::{IndexInfo, KeyInfo};
+use crate::{Buffer, Completion, File, OpenFlags, Result, SymbolTable, SyncMode, IO};
+
+const LENGTH_SIZE: usize = 4;
+const CHECKSUM_SIZE: usize = 8;
+
+pub struct LogicalLog {
+    io: Arc<dyn IO>,
+    file: Arc<dyn File>,
+    /// Where the next record is appended.
+    offset: u64,
+}
+
+impl std::fmt::Debug for LogicalLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogicalLog")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+impl LogicalLog {
+    pub fn open(io: Arc<dyn IO>, path: &str) -> Result<Self> {
+        let file = io.open_file(path, OpenFlags::Create, false)?;
+        let offset = file.size()?;
+        Ok(Self { io, file, offset })
+    }
+
+    /// The size of the log in bytes.
+    pub fn size(&self) -> u64 {
+        self.offset
+    }
+
+    /// Appends `record` to the log. With [SyncMode::Full], the record is durable on return.
+    pub fn append(&mut self, record: &LogRecord, sync_mode: SyncMode) -> Result<()> {
+        let mut payload = Vec::new();
+        serialize_record(record, &mut payload);
+        let mut data = Vec::with_capacity(LENGTH_SIZE + payload.len() + CHECKSUM_SIZE);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(&payload);
+        data.extend_from_slice(&checksum(&payload).to_le_bytes());
+        let len = data.len() as u64;
+
+        let c = Completion::new_write(|_| {});
+        let c = self
+            .file
+            .pwrite(self.offset, Arc::new(Buffer::new(data)), c)?;
+        self.io.wait_for_completion(c)?;
+        if sync_mode == SyncMode::Full {
+            let c = self.file.sync(Completion::new_sync(|_| {}))?;
+            self.io.wait_for_completion(c)?;
+        }
+        self.offset += len;
+        Ok(())
+    }
+
+    /// Reads the records of the log, up to the first one that was not written completely.
+    /// The collation sequences of the logged index keys are resolved in `syms`.
+    pub fn read_records(&self, syms: &SymbolTable) -> Result<Vec<LogRecord>> {
+        let size = self.file.size()? as usize;
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+        let bytes_read = Rc::new(Cell::new(0));
+        let c = {
+            let bytes_read = bytes_read.clone();
+            Completion::new_read(Arc::new(Buffer::new_temporary(size)), move |res| {
+                if let Ok((_, n)) = res {
+                    bytes_read.set(n.max(0) as usize);
+                }
+            })
+        };
+        let c = self.file.pread(0, c)?;
+        self.io.wait_for_completion(c.clone())?;
+        let buf = c.as_read().buf();
+        let mut data = &buf.as_slice()[..bytes_read.get().min(size)];
+
+        let mut records = Vec::new();
+        while let Some((record, len)) = parse_record(data, syms) {
+            records.push(record);
+            data = &data[len..];
+        }
+        if !data.is_empty() {
+            tracing::warn!(
+                "ignoring {} bytes at the end of the logical log that do not form a record",
+                data.len()
+            );
+        }
+        Ok(records)
+    }
+
+    /// Empties the log once its records were checkpointed.
+    pub fn truncate(&mut self) -> Result<()> {
+        if self.offset == 0 {
+            return Ok(());
+        }
+        let c = self.file.truncate(0, Completion::new_trunc(|_| {}))?;
+        self.io.wait_for_completion(c)?;
+        let c = self.file.sync(Completion::new_sync(|_| {}))?;
+        self.io.wait_for_completion(c)?;
+        self.offset = 0;
+        Ok(())
+    }
+}
+
+fn serialize_record(record: &LogRecord, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&record.tx_timestamp.to_le_bytes());
+    buf.extend_from_slice(&(record.row_versions.len() as u32).to_le_bytes());
+    for version in &record.row_versions {
+        serialize_version(version, buf);
+    }
+    buf.extend_from_slice(&(record.index_versions.len() as u32).to_le_bytes());
+    for (info, version) in &record.index_versions {
+        serialize_index_info(info, buf);
+        serialize_version(version, buf);
+    }
+}
+
+fn serialize_version(version: &RowVersion, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&version.row.id.table_id.to_le_bytes());
+    buf.extend_from_slice(&version.row.id.row_id.to_le_bytes());
+    buf.extend_from_slice(&timestamp(&version.begin).to_le_bytes());
+    match &version.end {
+        Some(end) => {
+            buf.push(1);
+            buf.extend_from_slice(&timestamp(end).to_le_bytes());
+        }
+        None => buf.push(0),
+    }
+    buf.extend_from_slice(&(version.row.column_count as u32).to_le_bytes());
+    buf.extend_from_slice(&(version.row.data.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&version.row.data);
+}
+
+fn serialize_index_info(info: &IndexInfo, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&(info.key_info.len() as u32).to_le_bytes());
+    for key_info in &info.key_info {
+        buf.push(matches!(key_info.sort_order, SortOrder::Desc) as u8);
+        let collation = key_info.collation.to_string();
+        buf.push(collation.len() as u8);
+        buf.extend_from_slice(collation.as_bytes());
+    }
+    buf.push(info.has_rowid as u8);
+    buf.extend_from_slice(&(info.num_cols as u32).to_le_bytes());
+}
+
+/// Only versions of committed transactions are logged, so they carry timestamps.
+fn timestamp(ts_or_id: &TxTimestampOrID) -> u64 {
+    match ts_or_id {
+        TxTimestampOrID::Timestamp(ts) => *ts,
+        TxTimestampOrID::TxID(id) => {
+            unreachable!("row version of uncommitted transaction {id} in the logical log")
+        }
+    }
+}
+
+/// Parses the record at the start of `data`, returning it with the number of bytes it takes up.
+fn parse_record(data: &[u8], syms: &SymbolTable) -> Option<(LogRecord, usize)> {
+    let mut reader = Reader { data };
+    let payload_len = reader.u32()? as usize;
+    let payload = reader.bytes(payload_len)?;
+    let expected_checksum = reader.u64()?;
+    if checksum(payload) != expected_checksum {
+        return None;
+    }
+    let record = deserialize_record(payload, syms)?;
+    Some((record, LENGTH_SIZE + payload_len + CHECKSUM_SIZE))
+}
+
+fn deserialize_record(payload: &[u8], syms: &SymbolTable) -> Option<LogRecord> {
+    let mut reader = Reader { data: payload };
+    let tx_timestamp = reader.u64()?;
+    let num_versions = reader.u32()?;
+    let mut row_versions = Vec::new();
+    for _ in 0..num_versions {
+        row_versions.push(deserialize_version(&mut reader)?);
+    }
+    let num_index_versions = reader.u32()?;
+    let mut index_versions = Vec::new();
+    for _ in 0..num_index_versions {
+        let info = Arc::new(deserialize_index_info(&mut reader, syms)?);
+        index_versions.push((info, deserialize_version(&mut reader)?));
+    }
+    Some(LogRecord {
+        tx_timestamp,
+        row_versions,
+        index_versions,
+    })
+}
+
+fn deserialize_version(reader: &mut Reader<'_>) -> Option<RowVersion> {
+    let table_id = reader.u64()?;
+    let row_id = reader.u64()? as i64;
+    let begin = reader.u64()?;
+    let end = match reader.u8()? {
+        0 => None,
+        _ => Some(TxTimestampOrID::Timestamp(reader.u64()?)),
+    };
+    let column_count = reader.u32()? as usize;
+    let data_len = reader.u32()? as usize;
+    let data = reader.bytes(data_len)?.to_vec();
+    Some(RowVersion {
+        begin: TxTimestampOrID::Timestamp(begin),
+        end,
+        row: Row::new(RowID::new(table_id, row_id), data, column_count),
+    })
+}
+
+fn deserialize_index_info(reader: &mut Reader<'_>, syms: &SymbolTable) -> Option<IndexInfo> {
+    let num_keys = reader.u32()?;
+    let mut key_info = Vec::new();
+    for _ in 0..num_keys {
+        let sort_order = match reader.u8()? {
+            0 => SortOrder::Asc,
+            _ => SortOrder::Desc,
+        };
+        let collation_len = reader.u8()? as usize;
+        let collation = std::str::from_utf8(reader.bytes(collation_len)?).ok()?;
+        key_info.push(KeyInfo {
+            sort_order,
+            collation: CollationSeq::new(collation, syms).ok()?,
+        });
+    }
+    let has_rowid = reader.u8()? != 0;
+    let num_cols = reader.u32()? as usize;
+    Some(IndexInfo {
+        key_info,
+        has_rowid,
+        num_cols,
+    })
+}
+
+/// 64-bit FNV-1a.
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.bytes(8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> LogRecord {
+        LogRecord {
+            tx_timestamp: 7,
+            row_versions: vec![
+                RowVersion {
+                    begin: TxTimestampOrID::Timestamp(3),
+                    end: Some(TxTimestampOrID::Timestamp(7)),
+                    row: Row::new(RowID::new(2, 1), vec![1, 2, 3], 1),
+                },
+                RowVersion {
+                    begin: TxTimestampOrID::Timestamp(7),
+                    end: None,
+                    row: Row::new(RowID::new(2, -1), vec![4, 5], 2),
+                },
+            ],
+            index_versions: vec![(
+                Arc::new(IndexInfo {
+                    key_info: vec![
+                        KeyInfo {
+                            sort_order: SortOrder::Desc,
+                            collation: CollationSeq::NoCase,
+                        },
+                        KeyInfo {
+                            sort_order: SortOrder::Asc,
+                            collation: CollationSeq::Binary,
+                        },
+                    ],
+                    has_rowid: true,
+                    num_cols: 2,
+                }),
+                RowVersion {
+                    begin: TxTimestampOrID::Timestamp(7),
+                    end: None,
+                    row: Row::new(RowID::new(3, 1), vec![3, 23, 9, 65], 2),
+                },
+            )],
+        }
+    }
+
+    fn encode(record: &LogRecord) -> Vec<u8> {
+        let mut payload = Vec::new();
+        serialize_record(record, &mut payload);
+        let mut data = (payload.len() as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(&payload);
+        data.extend_from_slice(&checksum(&payload).to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        let data = encode(&record());
+        let (parsed, len) = parse_record(&data, &SymbolTable::new()).unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(parsed.tx_timestamp, 7);
+        assert_eq!(parsed.row_versions, record().row_versions);
+        assert_eq!(parsed.index_versions, record().index_versions);
+    }
+
+    #[test]
+    fn test_torn_record_is_ignored() {
+        let data = encode(&record());
+        assert!(parse_record(&data[..data.len() - 1], &SymbolTable::new()).is_none());
+        let mut corrupted = data.clone();
+        corrupted[LENGTH_SIZE] ^= 1;
+        assert!(parse_record(&corrupted, &SymbolTable::new()).is_none());
+    }
+}
@@ -1,32 +1,78 @@
 use std::fmt::Debug;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
 
 use crate::mvcc::database::LogRecord;
-use crate::{LimboError, Result};
+use crate::{LimboError, Result, SymbolTable, SyncMode, IO};
+
+mod logical_log;
+
+pub use logical_log::LogicalLog;
+
+/// Size of the logical log after which a commit checkpoints it, like the WAL is checkpointed
+/// after 1000 pages of 4 KiB.
+const CHECKPOINT_THRESHOLD: u64 = 4096 * 1000;
 
 #[derive(Debug)]
 pub enum Storage {
     Noop,
+    LogicalLog(Mutex<LogicalLog>),
 }
 
 impl Storage {
     pub fn new_noop() -> Self {
         Self::Noop
     }
+
+    pub fn new_logical_log(io: Arc<dyn IO>, path: &str) -> Result<Self> {
+        Ok(Self::LogicalLog(Mutex::new(LogicalLog::open(io, path)?)))
+    }
 }
 
 impl Storage {
-    pub fn log_tx(&self, _m: LogRecord) -> Result<()> {
+    pub fn log_tx(&self, m: LogRecord, sync_mode: SyncMode) -> Result<()> {
         match self {
             Self::Noop => (),
+            Self::LogicalLog(log) => log.lock().append(&m, sync_mode)?,
         }
         Ok(())
     }
 
-    pub fn read_tx_log(&self) -> Result<Vec<LogRecord>> {
+    pub fn read_tx_log(&self, syms: &SymbolTable) -> Result<Vec<LogRecord>> {
         match self {
             Self::Noop => Err(LimboError::InternalError(
                 "cannot read from Noop storage".to_string(),
             )),
+            Self::LogicalLog(log) => log.lock().read_records(syms),
+        }
+    }
+
+    /// Whether committed rows are only written to the B-tree when the log is checkpointed.
+    pub fn is_logical_log(&self) -> bool {
+        matches!(self, Self::LogicalLog(_))
+    }
+
+    /// Whether the log grew large enough to be checkpointed.
+    pub fn should_checkpoint(&self) -> bool {
+        match self {
+            Self::Noop => false,
+            Self::LogicalLog(log) => log.lock().size() >= CHECKPOINT_THRESHOLD,
+        }
+    }
+
+    /// Runs `write_to_btree`, which writes the rows of the log to the B-tree, and truncates the
+    /// log if it returns true. No record is appended in the meantime, so none is lost.
+    pub fn checkpoint(&self, write_to_btree: impl FnOnce() -> Result<bool>) -> Result<()> {
+        match self {
+            Self::Noop => Ok(()),
+            Self::LogicalLog(log) => {
+                let mut log = log.lock();
+                if write_to_btree()? {
+                    log.truncate()?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -2,7 +2,9 @@ use crate::{Connection, LimboError, Statement, StepResult, Value};
 use bitflags::bitflags;
 use std::sync::Arc;
 use strum::IntoEnumIterator;
-use turso_ext::{ConstraintInfo, ConstraintOp, ConstraintUsage, IndexInfo, ResultCode};
+use turso_ext::{
+    ConstraintInfo, ConstraintOp, ConstraintUsage, IndexInfo, ResultCode, ROWID_COLUMN_INDEX,
+};
 use turso_parser::ast::PragmaName;
 
 bitflags! {
@@ -47,10 +49,18 @@ pub fn pragma_for(pragma: &PragmaName) -> Pragma {
             &["cache_size"],
         ),
         DatabaseList => Pragma::new(PragmaFlags::Result0, &["seq", "name", "file"]),
+        DeferForeignKeys => Pragma::new(
+            PragmaFlags::Result0 | PragmaFlags::NoColumns1,
+            &["defer_foreign_keys"],
+        ),
         Encoding => Pragma::new(
             PragmaFlags::Result0 | PragmaFlags::NoColumns1,
             &["encoding"],
         ),
+        IsolationLevel => Pragma::new(
+            PragmaFlags::NoColumns1 | PragmaFlags::Result0,
+            &["isolation_level"],
+        ),
         JournalMode => Pragma::new(
             PragmaFlags::NeedSchema | PragmaFlags::Result0 | PragmaFlags::SchemaReq,
             &["journal_mode"],
@@ -62,6 +72,19 @@ pub fn pragma_for(pragma: &PragmaName) -> Pragma {
             PragmaFlags::NeedSchema | PragmaFlags::Result0 | PragmaFlags::SchemaReq,
             &["module_list"],
         ),
+        MvccGc => Pragma::new(PragmaFlags::Result0 | PragmaFlags::NoColumns1, &["mvcc_gc"]),
+        MvccGcMemoryThreshold => Pragma::new(
+            PragmaFlags::Result0 | PragmaFlags::NoColumns1,
+            &["mvcc_gc_memory_threshold"],
+        ),
+        MvccGcVersionThreshold => Pragma::new(
+            PragmaFlags::Result0 | PragmaFlags::NoColumns1,
+            &["mvcc_gc_version_threshold"],
+        ),
+        MvccStats => Pragma::new(
+            PragmaFlags::NeedSchema | PragmaFlags::Result0,
+            &["name", "rows", "versions", "max_chain_length", "collected"],
+        ),
         PageCount => Pragma::new(
             PragmaFlags::NeedSchema | PragmaFlags::Result0 | PragmaFlags::SchemaReq,
             &["page_count"],
@@ -89,6 +112,10 @@ pub fn pragma_for(pragma: &PragmaName) -> Pragma {
             PragmaFlags::NeedSchema | PragmaFlags::Result1 | PragmaFlags::SchemaOpt,
             &["cid", "name", "type", "notnull", "dflt_value", "pk"],
         ),
+        TempStore => Pragma::new(
+            PragmaFlags::NoColumns1 | PragmaFlags::Result0,
+            &["temp_store"],
+        ),
         UserVersion => Pragma::new(
             PragmaFlags::NoColumns1 | PragmaFlags::Result0,
             &["user_version"],
@@ -111,6 +138,30 @@ pub fn pragma_for(pragma: &PragmaName) -> Pragma {
             &["query_only"],
         ),
         FreelistCount => Pragma::new(PragmaFlags::Result0, &["freelist_count"]),
+        ForeignKeyCheck => Pragma::new(
+            PragmaFlags::NeedSchema
+                | PragmaFlags::Result0
+                | PragmaFlags::Result1
+                | PragmaFlags::SchemaOpt,
+            &["table", "rowid", "parent", "fkid"],
+        ),
+        ForeignKeyList => Pragma::new(
+            PragmaFlags::NeedSchema | PragmaFlags::Result1 | PragmaFlags::SchemaOpt,
+            &[
+                "id",
+                "seq",
+                "table",
+                "from",
+                "to",
+                "on_update",
+                "on_delete",
+                "match",
+            ],
+        ),
+        ForeignKeys => Pragma::new(
+            PragmaFlags::Result0 | PragmaFlags::NoColumns1,
+            &["foreign_keys"],
+        ),
         EncryptionKey => Pragma::new(
             PragmaFlags::Result0 | PragmaFlags::SchemaReq | PragmaFlags::NoColumns1,
             &["hexkey"],
@@ -210,7 +261,7 @@ impl PragmaVirtualTable {
                 continue;
             }
             let visible_count = self.visible_column_count as u32;
-            if c.column_index < visible_count {
+            if c.column_index < visible_count || c.column_index == ROWID_COLUMN_INDEX {
                 continue;
             }
             if !c.usable {
@@ -11,17 +11,91 @@ pub struct View {
     pub sql: String,
     pub select_stmt: ast::Select,
     pub columns: Vec<Column>,
+    /// Whether the view was created with CREATE TEMP VIEW
+    pub temporary: bool,
 }
 
 /// Type alias for regular views collection
 pub type ViewsMap = HashMap<String, View>;
 
+/// A trigger created with CREATE TRIGGER.
+/// The body is kept as AST and compiled into a sub-program by every statement that fires it.
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    pub name: String,
+    /// The table (or view, for INSTEAD OF triggers) the trigger is attached to
+    pub table_name: String,
+    pub sql: String,
+    pub time: ast::TriggerTime,
+    pub event: ast::TriggerEvent,
+    pub for_each_row: bool,
+    pub when_clause: Option<Box<Expr>>,
+    pub commands: Vec<ast::TriggerCmd>,
+}
+
+impl Trigger {
+    pub fn from_sql(sql: &str) -> Result<Trigger> {
+        let mut parser = Parser::new(sql.as_bytes());
+        let cmd = parser.next_cmd()?;
+        match cmd {
+            Some(Cmd::Stmt(Stmt::CreateTrigger {
+                trigger_name,
+                time,
+                event,
+                tbl_name,
+                for_each_row,
+                when_clause,
+                commands,
+                ..
+            })) => Ok(Trigger {
+                name: normalize_ident(trigger_name.name.as_str()),
+                table_name: normalize_ident(tbl_name.name.as_str()),
+                sql: sql.to_string(),
+                time: time.unwrap_or(ast::TriggerTime::Before),
+                event,
+                for_each_row,
+                when_clause,
+                commands,
+            }),
+            _ => Err(LimboError::ParseError(format!(
+                "malformed trigger definition: {sql}"
+            ))),
+        }
+    }
+
+    /// Returns true if the trigger fires for the given event. For UPDATE, `updated_columns`
+    /// holds the names of the columns assigned by the statement, which is used to match
+    /// `UPDATE OF col, ...` triggers.
+    pub fn fires_on(&self, event: &ast::TriggerEvent, updated_columns: &[String]) -> bool {
+        match (&self.event, event) {
+            (ast::TriggerEvent::Delete, ast::TriggerEvent::Delete)
+            | (ast::TriggerEvent::Insert, ast::TriggerEvent::Insert)
+            | (ast::TriggerEvent::Update, ast::TriggerEvent::Update) => true,
+            (ast::TriggerEvent::UpdateOf(columns), ast::TriggerEvent::Update) => {
+                columns.iter().any(|column| {
+                    let column = normalize_ident(column.as_str());
+                    updated_columns
+                        .iter()
+                        .any(|c| c.eq_ignore_ascii_case(&column))
+                })
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Type alias for the triggers collection
+pub type TriggersMap = HashMap<String, Arc<Trigger>>;
+
 use crate::result::LimboResult;
 use crate::storage::btree::BTreeCursor;
 use crate::translate::collate::CollationSeq;
-use crate::translate::plan::SelectPlan;
+use crate::translate::expr::{walk_expr, WalkControl};
+use crate::translate::generated::validate_row_references;
+use crate::translate::plan::{RecursiveCte, SelectPlan, WindowPlan};
 use crate::util::{
     module_args_from_sql, module_name_from_sql, type_from_name, IOExt, UnparsedFromSqlIndex,
+    PRIMARY_KEY_AUTOMATIC_INDEX_NAME_PREFIX,
 };
 use crate::{return_if_io, LimboError, MvCursor, Pager, RefValue, SymbolTable, VirtualTable};
 use crate::{util::normalize_ident, Result};
@@ -42,12 +116,15 @@ use turso_parser::{
 
 const SCHEMA_TABLE_NAME: &str = "sqlite_schema";
 const SCHEMA_TABLE_NAME_ALT: &str = "sqlite_master";
+pub const TEMP_SCHEMA_TABLE_NAME: &str = "sqlite_temp_schema";
+const TEMP_SCHEMA_TABLE_NAME_ALT: &str = "sqlite_temp_master";
 
 #[derive(Debug)]
 pub struct Schema {
     pub tables: HashMap<String, Arc<Table>>,
     pub materialized_views: MaterializedViewsMap,
     pub views: ViewsMap,
+    pub triggers: TriggersMap,
 
     /// table_name to list of indexes for the table
     pub indexes: HashMap<String, Vec<Arc<Index>>>,
@@ -57,6 +134,10 @@ pub struct Schema {
 
     /// Mapping from table names to the materialized views that depend on them
     pub table_to_materialized_views: HashMap<String, Vec<String>>,
+
+    /// Tables left out of the schema because they or their indexes use a collation sequence
+    /// that is not registered, with the name of that collation sequence
+    pub unresolved_tables: HashMap<String, String>,
 }
 
 impl Schema {
@@ -69,6 +150,11 @@ impl Schema {
             SCHEMA_TABLE_NAME.to_string(),
             Arc::new(Table::BTree(sqlite_schema_table().into())),
         );
+        #[allow(clippy::arc_with_non_send_sync)]
+        tables.insert(
+            TEMP_SCHEMA_TABLE_NAME.to_string(),
+            Arc::new(Table::BTree(sqlite_temp_schema_table().into())),
+        );
         for function in VirtualTable::builtin_functions() {
             tables.insert(
                 function.name.to_owned(),
@@ -77,16 +163,19 @@ impl Schema {
         }
         let materialized_views: MaterializedViewsMap = HashMap::new();
         let views: ViewsMap = HashMap::new();
+        let triggers: TriggersMap = HashMap::new();
         let table_to_materialized_views: HashMap<String, Vec<String>> = HashMap::new();
         Self {
             tables,
             materialized_views,
             views,
+            triggers,
             indexes,
             has_indexes,
             indexes_enabled,
             schema_version: 0,
             table_to_materialized_views,
+            unresolved_tables: HashMap::new(),
         }
     }
 
@@ -195,6 +284,69 @@ impl Schema {
         self.views.get(&name)
     }
 
+    pub fn add_trigger(&mut self, trigger: Trigger) {
+        let name = normalize_ident(&trigger.name);
+        self.triggers.insert(name, Arc::new(trigger));
+    }
+
+    pub fn get_trigger(&self, name: &str) -> Option<&Arc<Trigger>> {
+        let name = normalize_ident(name);
+        self.triggers.get(&name)
+    }
+
+    pub fn remove_trigger(&mut self, name: &str) -> Result<()> {
+        let name = normalize_ident(name);
+        match self.triggers.remove(&name) {
+            Some(_) => Ok(()),
+            None => Err(LimboError::ParseError(format!("no such trigger: {name}"))),
+        }
+    }
+
+    /// Get all triggers attached to a table or view
+    pub fn get_triggers_for_table(&self, table_name: &str) -> Vec<Arc<Trigger>> {
+        let table_name = normalize_ident(table_name);
+        let mut triggers: Vec<Arc<Trigger>> = self
+            .triggers
+            .values()
+            .filter(|trigger| trigger.table_name == table_name)
+            .cloned()
+            .collect();
+        // HashMap iteration order is random, keep firing order stable
+        triggers.sort_by(|a, b| a.name.cmp(&b.name));
+        triggers
+    }
+
+    /// Get the foreign keys of all tables that refer to `table_name`, along with the
+    /// child table that declares them
+    pub fn get_referencing_foreign_keys(
+        &self,
+        table_name: &str,
+    ) -> Vec<(Arc<BTreeTable>, Arc<ForeignKey>)> {
+        let table_name = normalize_ident(table_name);
+        let mut foreign_keys: Vec<(Arc<BTreeTable>, Arc<ForeignKey>)> = self
+            .tables
+            .values()
+            .filter_map(|table| table.btree())
+            .flat_map(|table| {
+                table
+                    .foreign_keys
+                    .iter()
+                    .filter(|foreign_key| foreign_key.parent_table == table_name)
+                    .map(|foreign_key| (table.clone(), foreign_key.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        // HashMap iteration order is random, keep the order of checks and actions stable
+        foreign_keys.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+        foreign_keys
+    }
+
+    pub fn remove_triggers_for_table(&mut self, table_name: &str) {
+        let table_name = normalize_ident(table_name);
+        self.triggers
+            .retain(|_, trigger| trigger.table_name != table_name);
+    }
+
     pub fn add_btree_table(&mut self, table: Arc<BTreeTable>) {
         let name = normalize_ident(&table.name);
         self.tables.insert(name, Table::BTree(table).into());
@@ -209,17 +361,120 @@ impl Schema {
         let name = normalize_ident(name);
         let name = if name.eq_ignore_ascii_case(SCHEMA_TABLE_NAME_ALT) {
             SCHEMA_TABLE_NAME
+        } else if name.eq_ignore_ascii_case(TEMP_SCHEMA_TABLE_NAME_ALT) {
+            TEMP_SCHEMA_TABLE_NAME
         } else {
             &name
         };
         self.tables.get(name).cloned()
     }
 
+    /// Whether the schema contains objects of the connection's `temp` database, besides its
+    /// `sqlite_temp_schema` table.
+    pub fn has_temp_objects(&self) -> bool {
+        self.tables
+            .iter()
+            .any(|(name, table)| is_temp_object(name, table))
+            || self.views.values().any(|view| view.temporary)
+    }
+
+    /// Returns a copy of the schema where the objects of the connection's `temp` database,
+    /// described by `temp`, hide the objects of the same name. Like in SQLite, unqualified names
+    /// refer to the `temp` database first.
+    pub fn with_temp_objects(&self, temp: &Schema) -> Schema {
+        // The tables and indexes are shared rather than deep cloned, the copy is only read.
+        let mut schema = Schema {
+            tables: self.tables.clone(),
+            materialized_views: self.materialized_views.clone(),
+            views: self.views.clone(),
+            triggers: self.triggers.clone(),
+            indexes: self.indexes.clone(),
+            has_indexes: self.has_indexes.clone(),
+            indexes_enabled: self.indexes_enabled,
+            schema_version: self.schema_version,
+            table_to_materialized_views: self.table_to_materialized_views.clone(),
+            unresolved_tables: self.unresolved_tables.clone(),
+        };
+        for (name, table) in temp.tables.iter() {
+            if !is_temp_object(name, table) {
+                continue;
+            }
+            schema.views.remove(name);
+            schema.tables.insert(name.clone(), table.clone());
+            match temp.indexes.get(name) {
+                Some(indexes) => schema.indexes.insert(name.clone(), indexes.clone()),
+                None => schema.indexes.remove(name),
+            };
+            if temp.has_indexes.contains(name) {
+                schema.has_indexes.insert(name.clone());
+            } else {
+                schema.has_indexes.remove(name);
+            }
+        }
+        for (name, view) in temp.views.iter() {
+            schema.tables.remove(name);
+            schema.indexes.remove(name);
+            schema.has_indexes.remove(name);
+            schema.views.insert(name.clone(), view.clone());
+        }
+        schema
+    }
+
     pub fn remove_table(&mut self, table_name: &str) {
         let name = normalize_ident(table_name);
         self.tables.remove(&name);
     }
 
+    /// Leaves the table `table_name` and its indexes out of the schema because they use the
+    /// collation sequence `collation`, which is not registered. The table is loaded again once
+    /// it is, see [crate::Connection::create_collation].
+    pub fn add_unresolved_table(&mut self, table_name: &str, collation: &str) {
+        let name = normalize_ident(table_name);
+        self.tables.remove(&name);
+        self.indexes.remove(&name);
+        self.unresolved_tables.insert(name, collation.to_string());
+    }
+
+    pub fn is_table_unresolved(&self, table_name: &str) -> bool {
+        self.unresolved_tables
+            .contains_key(&normalize_ident(table_name))
+    }
+
+    /// Returns the tables that use the collation sequence `name` in a column or an index,
+    /// including the ones left out of the schema because it was not registered.
+    pub fn tables_using_collation(&self, name: &str) -> Vec<String> {
+        let uses_collation = |collation: &Option<CollationSeq>| {
+            collation
+                .as_ref()
+                .is_some_and(|collation| collation.is_custom_named(name))
+        };
+        let mut tables: Vec<String> = self
+            .unresolved_tables
+            .iter()
+            .filter(|(_, collation)| collation.eq_ignore_ascii_case(name))
+            .map(|(table_name, _)| table_name.clone())
+            .collect();
+        for (table_name, table) in &self.tables {
+            let Some(table) = table.btree() else {
+                continue;
+            };
+            let in_columns = table
+                .columns
+                .iter()
+                .any(|column| uses_collation(&column.collation));
+            let in_indexes = self.get_indices(table_name).iter().any(|index| {
+                index
+                    .columns
+                    .iter()
+                    .any(|column| uses_collation(&column.collation))
+            });
+            if in_columns || in_indexes {
+                tables.push(table_name.clone());
+            }
+        }
+        tables
+    }
+
     pub fn get_btree_table(&self, name: &str) -> Option<Arc<BTreeTable>> {
         let name = normalize_ident(name);
         if let Some(table) = self.tables.get(&name) {
@@ -350,8 +605,21 @@ impl Schema {
                         continue;
                     }
 
-                    let table = BTreeTable::from_sql(sql, root_page as usize)?;
-                    self.add_btree_table(Arc::new(table));
+                    match BTreeTable::from_sql(sql, root_page as usize, syms) {
+                        Ok(table) => self.add_btree_table(Arc::new(table)),
+                        Err(e) => {
+                            let Some(collation) = CollationSeq::missing(&e) else {
+                                return Err(e);
+                            };
+                            let table_name_value = record_cursor.get_value(&row, 2)?;
+                            let RefValue::Text(table_name_text) = table_name_value else {
+                                return Err(LimboError::ConversionError(
+                                    "Expected text value".into(),
+                                ));
+                            };
+                            self.add_unresolved_table(table_name_text.as_str(), collation);
+                        }
+                    }
                 }
                 "index" => {
                     let root_page_value = record_cursor.get_value(&row, 3)?;
@@ -457,6 +725,7 @@ impl Schema {
                                     sql: sql.to_string(),
                                     select_stmt: select,
                                     columns: final_columns,
+                                    temporary: false,
                                 };
                                 self.add_view(view);
                             }
@@ -464,6 +733,14 @@ impl Schema {
                         }
                     }
                 }
+                "trigger" => {
+                    let sql_value = record_cursor.get_value(&row, 4)?;
+                    let RefValue::Text(sql_text) = sql_value else {
+                        return Err(LimboError::ConversionError("Expected text value".into()));
+                    };
+                    let trigger = Trigger::from_sql(sql_text.as_str())?;
+                    self.add_trigger(trigger);
+                }
 
                 _ => {}
             };
@@ -484,22 +761,36 @@ impl Schema {
         }
 
         for unparsed_sql_from_index in from_sql_indexes {
+            if self.is_table_unresolved(&unparsed_sql_from_index.table_name) {
+                continue;
+            }
             if !self.indexes_enabled() {
                 self.table_set_has_index(&unparsed_sql_from_index.table_name);
             } else {
                 let table = self
                     .get_btree_table(&unparsed_sql_from_index.table_name)
                     .unwrap();
-                let index = Index::from_sql(
+                match Index::from_sql(
                     &unparsed_sql_from_index.sql,
                     unparsed_sql_from_index.root_page,
                     table.as_ref(),
-                )?;
-                self.add_index(Arc::new(index));
+                    syms,
+                ) {
+                    Ok(index) => self.add_index(Arc::new(index)),
+                    Err(e) => {
+                        let Some(collation) = CollationSeq::missing(&e) else {
+                            return Err(e);
+                        };
+                        self.add_unresolved_table(&unparsed_sql_from_index.table_name, collation);
+                    }
+                }
             }
         }
 
         for automatic_index in automatic_indices {
+            if self.is_table_unresolved(&automatic_index.0) {
+                continue;
+            }
             if !self.indexes_enabled() {
                 self.table_set_has_index(&automatic_index.0);
             } else {
@@ -565,15 +856,22 @@ impl Clone for Schema {
             .map(|(name, view)| (name.clone(), view.clone()))
             .collect();
         let views = self.views.clone();
+        let triggers = self
+            .triggers
+            .iter()
+            .map(|(name, trigger)| (name.clone(), Arc::new((**trigger).clone())))
+            .collect();
         Self {
             tables,
             materialized_views,
             views,
+            triggers,
             indexes,
             has_indexes: self.has_indexes.clone(),
             indexes_enabled: self.indexes_enabled,
             schema_version: self.schema_version,
             table_to_materialized_views: self.table_to_materialized_views.clone(),
+            unresolved_tables: self.unresolved_tables.clone(),
         }
     }
 }
@@ -638,6 +936,11 @@ impl Table {
         }
     }
 
+    /// Whether the table lives in the connection's `temp` database
+    pub fn is_temporary(&self) -> bool {
+        matches!(self, Self::BTree(table) if table.temporary)
+    }
+
     pub fn btree(&self) -> Option<Arc<BTreeTable>> {
         match self {
             Self::BTree(table) => Some(table.clone()),
@@ -673,6 +976,111 @@ pub struct BTreeTable {
     pub has_rowid: bool,
     pub is_strict: bool,
     pub unique_sets: Option<Vec<Vec<(String, SortOrder)>>>,
+    /// Foreign keys of the table, in declaration order
+    pub foreign_keys: Vec<Arc<ForeignKey>>,
+    /// CHECK constraints of the table, including the ones declared on columns
+    pub check_constraints: Vec<CheckConstraint>,
+    /// Whether the table lives in the connection's `temp` database
+    pub temporary: bool,
+}
+
+/// A CHECK constraint. A row violates it if the expression evaluates to false, NULL passes.
+#[derive(Debug, Clone)]
+pub struct CheckConstraint {
+    /// Name given with `CONSTRAINT name`, reported instead of the expression on failure
+    pub name: Option<String>,
+    pub expr: Box<Expr>,
+    /// The column the constraint is declared on, `None` for a table constraint. The constraint
+    /// is dropped together with the column.
+    pub column: Option<String>,
+}
+
+impl CheckConstraint {
+    fn to_sql(&self) -> String {
+        match &self.name {
+            Some(name) => format!("CONSTRAINT {name} CHECK ({})", self.expr),
+            None => format!("CHECK ({})", self.expr),
+        }
+    }
+
+    /// The text of the "CHECK constraint failed" error.
+    pub fn description(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.expr.to_string(),
+        }
+    }
+}
+
+/// A FOREIGN KEY constraint declared on a child table.
+#[derive(Debug, Clone)]
+pub struct ForeignKey {
+    /// Columns of the child table, normalized
+    pub child_columns: Vec<String>,
+    /// Name of the referenced table, normalized
+    pub parent_table: String,
+    /// Referenced columns of the parent table, normalized. Empty when the
+    /// constraint refers to the primary key of the parent table.
+    pub parent_columns: Vec<String>,
+    pub on_delete: ast::RefAct,
+    pub on_update: ast::RefAct,
+    /// Whether the constraint is DEFERRABLE INITIALLY DEFERRED, i.e. only checked at commit.
+    pub deferred: bool,
+}
+
+impl ForeignKey {
+    fn new(
+        child_columns: Vec<String>,
+        clause: &ast::ForeignKeyClause,
+        defer_clause: Option<&ast::DeferSubclause>,
+    ) -> Self {
+        let mut on_delete = ast::RefAct::NoAction;
+        let mut on_update = ast::RefAct::NoAction;
+        for arg in &clause.args {
+            match arg {
+                ast::RefArg::OnDelete(action) => on_delete = *action,
+                ast::RefArg::OnUpdate(action) => on_update = *action,
+                ast::RefArg::OnInsert(_) | ast::RefArg::Match(_) => {}
+            }
+        }
+        Self {
+            child_columns,
+            parent_table: normalize_ident(clause.tbl_name.as_str()),
+            parent_columns: clause
+                .columns
+                .iter()
+                .map(|column| normalize_ident(column.col_name.as_str()))
+                .collect(),
+            on_delete,
+            on_update,
+            deferred: defer_clause.is_some_and(|clause| {
+                clause.deferrable
+                    && clause.init_deferred == Some(ast::InitDeferredPred::InitiallyDeferred)
+            }),
+        }
+    }
+
+    /// Reconstructs the table constraint, see [BTreeTable::to_sql].
+    fn to_sql(&self) -> String {
+        let mut sql = format!(
+            "FOREIGN KEY ({}) REFERENCES {}",
+            self.child_columns.join(", "),
+            self.parent_table
+        );
+        if !self.parent_columns.is_empty() {
+            sql.push_str(&format!(" ({})", self.parent_columns.join(", ")));
+        }
+        if self.on_delete != ast::RefAct::NoAction {
+            sql.push_str(&format!(" ON DELETE {}", self.on_delete));
+        }
+        if self.on_update != ast::RefAct::NoAction {
+            sql.push_str(&format!(" ON UPDATE {}", self.on_update));
+        }
+        if self.deferred {
+            sql.push_str(" DEFERRABLE INITIALLY DEFERRED");
+        }
+        sql
+    }
 }
 
 impl BTreeTable {
@@ -703,12 +1111,162 @@ impl BTreeTable {
             .find(|(_, column)| column.name.as_ref() == Some(&name))
     }
 
-    pub fn from_sql(sql: &str, root_page: usize) -> Result<BTreeTable> {
+    /// Whether any column is a VIRTUAL generated column, which is not stored in the record.
+    pub fn has_virtual_columns(&self) -> bool {
+        self.columns.iter().any(|column| !column.is_stored())
+    }
+
+    /// Returns the position of column `idx` in the table record, which differs from the
+    /// position in the table if a VIRTUAL generated column precedes it or if the table is a
+    /// WITHOUT ROWID table. Returns `None` for VIRTUAL generated columns.
+    pub fn record_index(&self, idx: usize) -> Option<usize> {
+        self.record_columns()
+            .iter()
+            .position(|&column| column == idx)
+    }
+
+    /// Returns the positions of the columns stored in the table record, in record order. The
+    /// record of a WITHOUT ROWID table starts with the PRIMARY KEY columns, which are its key.
+    pub fn record_columns(&self) -> Vec<usize> {
+        let primary_key = if self.has_rowid {
+            vec![]
+        } else {
+            self.primary_key_columns
+                .iter()
+                .filter_map(|(name, _)| self.get_column(name).map(|(idx, _)| idx))
+                .collect()
+        };
+        let other_columns = self
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(idx, column)| column.is_stored() && !primary_key.contains(idx))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        [primary_key, other_columns].concat()
+    }
+
+    /// Returns the index a WITHOUT ROWID table is stored in, keyed by the PRIMARY KEY columns
+    /// and followed by the other columns of the table record. The index has no entry in
+    /// sqlite_schema, its B-tree is the one at the root page of the table.
+    pub fn clustered_index(&self) -> Option<Arc<Index>> {
+        if self.has_rowid {
+            return None;
+        }
+        let columns = self
+            .record_columns()
+            .into_iter()
+            .enumerate()
+            .map(|(i, pos_in_table)| {
+                let column = &self.columns[pos_in_table];
+                IndexColumn {
+                    name: column.name.clone().unwrap_or_default(),
+                    order: self
+                        .primary_key_columns
+                        .get(i)
+                        .map_or(SortOrder::Asc, |(_, order)| *order),
+                    pos_in_table,
+                    collation: column.collation.clone(),
+                    default: column.default.clone(),
+                    expr: None,
+                }
+            })
+            .collect::<Vec<_>>();
+        Some(Arc::new(Index {
+            name: format!("{PRIMARY_KEY_AUTOMATIC_INDEX_NAME_PREFIX}{}_pk", self.name),
+            table_name: self.name.clone(),
+            root_page: self.root_page,
+            non_key_columns: columns.len() - self.primary_key_columns.len(),
+            columns,
+            unique: true,
+            ephemeral: false,
+            has_rowid: false,
+            where_clause: None,
+            temporary: self.temporary,
+        }))
+    }
+
+    /// Returns the positions of the columns that `expr` refers to. `expr` must be an
+    /// expression over a single row of the table, like a CHECK constraint.
+    pub fn referenced_columns(&self, expr: &Expr) -> Vec<usize> {
+        let mut columns = vec![];
+        let _ = walk_expr(expr, &mut |expr: &Expr| -> Result<WalkControl> {
+            let name = match expr {
+                Expr::Id(name) | Expr::Name(name) | Expr::Qualified(_, name) => name,
+                _ => return Ok(WalkControl::Continue),
+            };
+            if let Some((idx, _)) = self.get_column(name.as_str()) {
+                columns.push(idx);
+            }
+            Ok(WalkControl::Continue)
+        });
+        columns
+    }
+
+    /// Whether column `idx` is referred to by a CHECK constraint or a generated column declared
+    /// on another column.
+    pub fn references_column(&self, idx: usize) -> bool {
+        let generated = self
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != idx)
+            .filter_map(|(_, column)| column.generated.as_ref().map(|g| &g.expr));
+        let column_name = self.columns[idx].name.as_ref();
+        let checks = self
+            .check_constraints
+            .iter()
+            .filter(|check| check.column.as_ref() != column_name)
+            .map(|check| &check.expr);
+        generated
+            .chain(checks)
+            .any(|expr| self.referenced_columns(expr).contains(&idx))
+    }
+
+    /// Returns the positions of the generated columns, ordered so that every generated column
+    /// comes after the generated columns it refers to.
+    pub fn generated_columns_in_order(&self) -> Result<Vec<usize>> {
+        fn visit(
+            table: &BTreeTable,
+            idx: usize,
+            visiting: &mut Vec<usize>,
+            order: &mut Vec<usize>,
+        ) -> Result<()> {
+            if order.contains(&idx) {
+                return Ok(());
+            }
+            let column = &table.columns[idx];
+            let Some(generated) = &column.generated else {
+                return Ok(());
+            };
+            visiting.push(idx);
+            for dependency in table.referenced_columns(&generated.expr) {
+                if visiting.contains(&dependency) {
+                    return Err(LimboError::ParseError(format!(
+                        "generated column loop on \"{}\"",
+                        column.name.as_deref().unwrap_or_default()
+                    )));
+                }
+                visit(table, dependency, visiting, order)?;
+            }
+            visiting.pop();
+            order.push(idx);
+            Ok(())
+        }
+
+        let mut order = vec![];
+        for idx in 0..self.columns.len() {
+            visit(self, idx, &mut vec![], &mut order)?;
+        }
+        Ok(order)
+    }
+
+    pub fn from_sql(sql: &str, root_page: usize, syms: &SymbolTable) -> Result<BTreeTable> {
         let mut parser = Parser::new(sql.as_bytes());
         let cmd = parser.next_cmd()?;
         match cmd {
             Some(Cmd::Stmt(Stmt::CreateTable { tbl_name, body, .. })) => {
-                create_table(tbl_name, body, root_page)
+                create_table(tbl_name, body, root_page, syms)
             }
             _ => unreachable!("Expected CREATE TABLE statement"),
         }
@@ -753,13 +1311,43 @@ impl BTreeTable {
                 sql.push_str(" DEFAULT ");
                 sql.push_str(&default.to_string());
             }
+
+            if let Some(generated) = &column.generated {
+                sql.push_str(&format!(
+                    " GENERATED ALWAYS AS ({}) {}",
+                    generated.expr,
+                    if generated.stored {
+                        "STORED"
+                    } else {
+                        "VIRTUAL"
+                    }
+                ));
+            }
+
+            for check in &self.check_constraints {
+                if check.column.as_ref() == Some(column_name) {
+                    sql.push(' ');
+                    sql.push_str(&check.to_sql());
+                }
+            }
+        }
+        for check in self.check_constraints.iter().filter(|c| c.column.is_none()) {
+            sql.push_str(", ");
+            sql.push_str(&check.to_sql());
+        }
+        for foreign_key in &self.foreign_keys {
+            sql.push_str(", ");
+            sql.push_str(&foreign_key.to_sql());
         }
         sql.push(')');
         sql
     }
 
     pub fn column_collations(&self) -> Vec<Option<CollationSeq>> {
-        self.columns.iter().map(|column| column.collation).collect()
+        self.columns
+            .iter()
+            .map(|column| column.collation.clone())
+            .collect()
     }
 }
 
@@ -796,6 +1384,17 @@ pub struct FromClauseSubquery {
     /// The start register for the result columns of the derived table;
     /// must be set before data is read from it.
     pub result_columns_start_reg: Option<usize>,
+    /// Set if the derived table is a recursive CTE. `plan` is then only used to describe
+    /// the columns, the rows are produced by the SELECTs of the [RecursiveCte].
+    pub recursive: Option<Box<RecursiveCte>>,
+    /// Whether this is the reference a recursive CTE makes to itself from one of its recursive
+    /// SELECTs. Such a reference produces exactly one row, the one being expanded, which the
+    /// recursive CTE stores in `result_columns_start_reg` before running the SELECT.
+    pub is_recursive_reference: bool,
+    /// Set if the derived table computes window functions over the rows of `plan`. The results
+    /// of the window functions are the first columns of the table, followed by the result
+    /// columns of `plan`.
+    pub window: Option<Box<WindowPlan>>,
 }
 
 #[derive(Debug, Eq)]
@@ -826,6 +1425,7 @@ fn create_table(
     tbl_name: QualifiedName,
     body: CreateTableBody,
     root_page: usize,
+    syms: &SymbolTable,
 ) -> Result<BTreeTable> {
     let table_name = normalize_ident(tbl_name.name.as_str());
     trace!("Creating table {}", table_name);
@@ -835,6 +1435,10 @@ fn create_table(
     let is_strict: bool;
     // BtreeSet here to preserve order of inserted keys
     let mut unique_sets: Vec<BTreeSet<UniqueColumnProps>> = vec![];
+    let mut foreign_keys = vec![];
+    let mut table_foreign_keys = vec![];
+    let mut check_constraints = vec![];
+    let mut table_check_constraints = vec![];
     match body {
         CreateTableBody::ColumnsAndConstraints {
             columns,
@@ -851,7 +1455,9 @@ fn create_table(
                                 value.trim_matches('\'').to_owned()
                             }
                             _ => {
-                                todo!("Unsupported primary key expression");
+                                crate::bail_parse_error!(
+                                    "expressions prohibited in PRIMARY KEY and UNIQUE constraints"
+                                );
                             }
                         };
                         primary_key_columns
@@ -871,16 +1477,39 @@ fn create_table(
                             let column_name = match column.expr.as_ref() {
                                 Expr::Id(id) => normalize_ident(id.as_str()),
                                 _ => {
-                                    todo!("Unsupported unique expression");
+                                    crate::bail_parse_error!(
+                                        "expressions prohibited in PRIMARY KEY and UNIQUE constraints"
+                                    );
                                 }
                             };
-                            UniqueColumnProps {
+                            Ok(UniqueColumnProps {
                                 column_name,
                                 order: column.order.unwrap_or(SortOrder::Asc),
-                            }
+                            })
                         })
-                        .collect();
+                        .collect::<Result<_>>()?;
                     unique_sets.push(unique_set);
+                } else if let ast::TableConstraint::ForeignKey {
+                    columns,
+                    clause,
+                    deref_clause,
+                } = c.constraint
+                {
+                    let child_columns = columns
+                        .iter()
+                        .map(|column| normalize_ident(column.col_name.as_str()))
+                        .collect();
+                    table_foreign_keys.push(Arc::new(ForeignKey::new(
+                        child_columns,
+                        &clause,
+                        deref_clause.as_ref(),
+                    )));
+                } else if let ast::TableConstraint::Check(expr) = c.constraint {
+                    table_check_constraints.push(CheckConstraint {
+                        name: c.name.map(|name| normalize_ident(name.as_str())),
+                        expr,
+                        column: None,
+                    });
                 }
             }
             for ast::ColumnDefinition {
@@ -920,6 +1549,7 @@ fn create_table(
                 let mut order = SortOrder::Asc;
                 let mut unique = false;
                 let mut collation = None;
+                let mut generated = None;
                 for c_def in constraints {
                     match c_def.constraint {
                         ast::ColumnConstraint::PrimaryKey { order: o, .. } => {
@@ -931,7 +1561,14 @@ fn create_table(
                         ast::ColumnConstraint::NotNull { nullable, .. } => {
                             notnull = !nullable;
                         }
-                        ast::ColumnConstraint::Default(ref expr) => default = Some(expr),
+                        ast::ColumnConstraint::Default(ref expr) => {
+                            if generated.is_some() {
+                                crate::bail_parse_error!(
+                                    "cannot use DEFAULT on a generated column"
+                                );
+                            }
+                            default = Some(expr);
+                        }
                         // TODO: for now we don't check Resolve type of unique
                         ast::ColumnConstraint::Unique(on_conflict) => {
                             if on_conflict.is_some() {
@@ -940,9 +1577,34 @@ fn create_table(
                             unique = true;
                         }
                         ast::ColumnConstraint::Collate { ref collation_name } => {
-                            collation = Some(CollationSeq::new(collation_name.as_str())?);
+                            collation = Some(CollationSeq::new(collation_name.as_str(), syms)?);
+                        }
+                        ast::ColumnConstraint::ForeignKey {
+                            ref clause,
+                            ref deref_clause,
+                        } => {
+                            foreign_keys.push(Arc::new(ForeignKey::new(
+                                vec![normalize_ident(&name)],
+                                clause,
+                                deref_clause.as_ref(),
+                            )));
+                        }
+                        ast::ColumnConstraint::Check(ref expr) => {
+                            check_constraints.push(CheckConstraint {
+                                name: c_def
+                                    .name
+                                    .as_ref()
+                                    .map(|name| normalize_ident(name.as_str())),
+                                expr: expr.clone(),
+                                column: Some(normalize_ident(&name)),
+                            });
+                        }
+                        ast::ColumnConstraint::Generated { ref expr, ref typ } => {
+                            if default.is_some() {
+                                crate::bail_parse_error!("error in generated column \"{}\"", name);
+                            }
+                            generated = Some(GeneratedColumn::new(expr, typ.as_ref())?);
                         }
-                        _ => {}
                     }
                 }
 
@@ -966,6 +1628,7 @@ fn create_table(
                     unique,
                     collation,
                     hidden: false,
+                    generated,
                 });
             }
             if options.contains(TableOptions::WITHOUT_ROWID) {
@@ -974,6 +1637,21 @@ fn create_table(
         }
         CreateTableBody::AsSelect(_) => todo!(),
     };
+    // SQLite numbers column constraints before table constraints
+    foreign_keys.extend(table_foreign_keys);
+    check_constraints.extend(table_check_constraints);
+    if cols.iter().all(|col| col.generated.is_some()) {
+        crate::bail_parse_error!("must have at least one non-generated column");
+    }
+    for col in &cols {
+        if col.generated.is_some()
+            && primary_key_columns
+                .iter()
+                .any(|(name, _)| col.name.as_deref() == Some(&normalize_ident(name)))
+        {
+            crate::bail_parse_error!("generated columns cannot be part of the PRIMARY KEY");
+        }
+    }
     // flip is_rowid_alias back to false if the table has multiple primary keys
     // or if the table has no rowid
     if !has_rowid || primary_key_columns.len() > 1 {
@@ -981,13 +1659,21 @@ fn create_table(
             col.is_rowid_alias = false;
         }
     }
-    Ok(BTreeTable {
+    // the PRIMARY KEY of a WITHOUT ROWID table can't be NULL, since it is the key of the table
+    if !has_rowid {
+        for col in cols.iter_mut().filter(|col| col.primary_key) {
+            col.notnull = true;
+        }
+    }
+    let table = BTreeTable {
         root_page,
         name: table_name,
         has_rowid,
         primary_key_columns,
         columns: cols,
         is_strict,
+        foreign_keys,
+        check_constraints,
         unique_sets: if unique_sets.is_empty() {
             None
         } else {
@@ -1004,7 +1690,19 @@ fn create_table(
                     .collect(),
             )
         },
-    })
+        temporary: false,
+    };
+    // reject generated columns that depend on themselves
+    table.generated_columns_in_order()?;
+    let generated_exprs = table
+        .columns
+        .iter()
+        .filter_map(|column| column.generated.as_ref().map(|generated| &generated.expr));
+    let check_exprs = table.check_constraints.iter().map(|check| &check.expr);
+    for expr in generated_exprs.chain(check_exprs) {
+        validate_row_references(expr, &table)?;
+    }
+    Ok(table)
 }
 
 pub fn _build_pseudo_table(columns: &[ResultColumn]) -> PseudoCursorType {
@@ -1038,17 +1736,52 @@ pub struct Column {
     pub unique: bool,
     pub collation: Option<CollationSeq>,
     pub hidden: bool,
+    /// The expression of a `GENERATED ALWAYS AS` column
+    pub generated: Option<GeneratedColumn>,
+}
+
+/// The definition of a generated column.
+#[derive(Debug, Clone)]
+pub struct GeneratedColumn {
+    pub expr: Box<Expr>,
+    /// STORED columns are computed on write and stored in the record, VIRTUAL columns are
+    /// computed on read.
+    pub stored: bool,
+}
+
+impl GeneratedColumn {
+    fn new(expr: &Expr, typ: Option<&ast::Name>) -> Result<Self> {
+        let stored = match typ {
+            None => false,
+            Some(typ) if typ.as_str().eq_ignore_ascii_case("virtual") => false,
+            Some(typ) if typ.as_str().eq_ignore_ascii_case("stored") => true,
+            Some(typ) => crate::bail_parse_error!("unknown generated column type: {}", typ),
+        };
+        Ok(Self {
+            expr: Box::new(expr.clone()),
+            stored,
+        })
+    }
 }
 
 impl Column {
     pub fn affinity(&self) -> Affinity {
         affinity(&self.ty_str)
     }
+
+    /// Whether the column is stored in the table record, i.e. it is not a VIRTUAL generated
+    /// column.
+    pub fn is_stored(&self) -> bool {
+        self.generated
+            .as_ref()
+            .is_none_or(|generated| generated.stored)
+    }
 }
 
 // TODO: This might replace some of util::columns_from_create_table_body
-impl From<&ColumnDefinition> for Column {
-    fn from(value: &ColumnDefinition) -> Self {
+impl Column {
+    /// Builds the column of a column definition, resolving its collation sequence in `syms`.
+    pub fn from_definition(value: &ColumnDefinition, syms: &SymbolTable) -> Result<Self> {
         let name = value.col_name.as_str();
 
         let mut default = None;
@@ -1056,6 +1789,7 @@ impl From<&ColumnDefinition> for Column {
         let mut primary_key = false;
         let mut unique = false;
         let mut collation = None;
+        let mut generated = None;
 
         for ast::NamedColumnConstraint { constraint, .. } in &value.constraints {
             match constraint {
@@ -1066,10 +1800,10 @@ impl From<&ColumnDefinition> for Column {
                     default.replace(expr.clone());
                 }
                 ast::ColumnConstraint::Collate { collation_name } => {
-                    collation.replace(
-                        CollationSeq::new(collation_name.as_str())
-                            .expect("collation should have been set correctly in create table"),
-                    );
+                    collation.replace(CollationSeq::new(collation_name.as_str(), syms)?);
+                }
+                ast::ColumnConstraint::Generated { expr, typ } => {
+                    generated = GeneratedColumn::new(expr, typ.as_ref()).ok();
                 }
                 _ => {}
             };
@@ -1088,7 +1822,7 @@ impl From<&ColumnDefinition> for Column {
 
         let hidden = ty_str.contains("HIDDEN");
 
-        Column {
+        Ok(Column {
             name: Some(normalize_ident(name)),
             ty,
             default,
@@ -1099,7 +1833,8 @@ impl From<&ColumnDefinition> for Column {
             unique,
             collation,
             hidden,
-        }
+            generated,
+        })
     }
 }
 
@@ -1296,6 +2031,20 @@ impl fmt::Display for Type {
     }
 }
 
+/// The table of the `temp` database that describes its objects, like `sqlite_schema` does for
+/// the main database. Like in SQLite, its root page is page 1 of the `temp` database.
+pub fn sqlite_temp_schema_table() -> BTreeTable {
+    BTreeTable {
+        name: TEMP_SCHEMA_TABLE_NAME.to_string(),
+        temporary: true,
+        ..sqlite_schema_table()
+    }
+}
+
+fn is_temp_object(name: &str, table: &Table) -> bool {
+    table.is_temporary() && name != TEMP_SCHEMA_TABLE_NAME
+}
+
 pub fn sqlite_schema_table() -> BTreeTable {
     BTreeTable {
         root_page: 1,
@@ -1315,6 +2064,7 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 unique: false,
                 collation: None,
                 hidden: false,
+                generated: None,
             },
             Column {
                 name: Some("name".to_string()),
@@ -1327,6 +2077,7 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 unique: false,
                 collation: None,
                 hidden: false,
+                generated: None,
             },
             Column {
                 name: Some("tbl_name".to_string()),
@@ -1339,6 +2090,7 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 unique: false,
                 collation: None,
                 hidden: false,
+                generated: None,
             },
             Column {
                 name: Some("rootpage".to_string()),
@@ -1351,6 +2103,7 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 unique: false,
                 collation: None,
                 hidden: false,
+                generated: None,
             },
             Column {
                 name: Some("sql".to_string()),
@@ -1363,9 +2116,13 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 unique: false,
                 collation: None,
                 hidden: false,
+                generated: None,
             },
         ],
         unique_sets: None,
+        foreign_keys: vec![],
+        check_constraints: vec![],
+        temporary: false,
     }
 }
 
@@ -1381,9 +2138,19 @@ pub struct Index {
     /// Does the index have a rowid as the last column?
     /// This is the case for btree indexes (persistent or ephemeral) that
     /// have been created based on a table with a rowid.
-    /// For example, WITHOUT ROWID tables (not supported in Limbo yet),
+    /// For example, indexes of WITHOUT ROWID tables,
     /// and  SELECT DISTINCT ephemeral indexes will not have a rowid.
     pub has_rowid: bool,
+    /// The WHERE clause of a partial index. Only the rows for which it is true are in the index.
+    pub where_clause: Option<Box<Expr>>,
+    /// The number of trailing `columns` that are not part of the key the index is declared on.
+    /// An index of a WITHOUT ROWID table ends with the PRIMARY KEY columns it does not already
+    /// contain, which locate the table row like the rowid does for other tables. For the
+    /// clustered index of a WITHOUT ROWID table these are the columns outside the PRIMARY KEY,
+    /// see [BTreeTable::clustered_index].
+    pub non_key_columns: usize,
+    /// Whether the index lives in the connection's `temp` database, like its table
+    pub temporary: bool,
 }
 
 #[allow(dead_code)]
@@ -1399,10 +2166,18 @@ pub struct IndexColumn {
     pub pos_in_table: usize,
     pub collation: Option<CollationSeq>,
     pub default: Option<Box<Expr>>,
+    /// The indexed expression, if this is a column of an index on an expression, e.g. `lower(b)`
+    /// in `CREATE INDEX idx ON t(lower(b))`. `pos_in_table` is meaningless in that case.
+    pub expr: Option<Box<Expr>>,
 }
 
 impl Index {
-    pub fn from_sql(sql: &str, root_page: usize, table: &BTreeTable) -> Result<Index> {
+    pub fn from_sql(
+        sql: &str,
+        root_page: usize,
+        table: &BTreeTable,
+        syms: &SymbolTable,
+    ) -> Result<Index> {
         let mut parser = Parser::new(sql.as_bytes());
         let cmd = parser.next_cmd()?;
         match cmd {
@@ -1411,28 +2186,25 @@ impl Index {
                 tbl_name,
                 columns,
                 unique,
+                where_clause,
                 ..
             })) => {
                 let index_name = normalize_ident(idx_name.name.as_str());
                 let mut index_columns = Vec::with_capacity(columns.len());
-                for col in columns.into_iter() {
-                    let name = normalize_ident(&col.expr.to_string());
-                    let Some((pos_in_table, _)) = table.get_column(&name) else {
-                        return Err(crate::LimboError::InternalError(format!(
-                            "Column {} is in index {} but not found in table {}",
-                            name, index_name, table.name
-                        )));
+                for col in columns.iter() {
+                    let index_column = match IndexColumn::from_sorted_column(col, table, syms) {
+                        Ok(index_column) => index_column,
+                        Err(e) if CollationSeq::missing(&e).is_some() => return Err(e),
+                        Err(_) => {
+                            return Err(crate::LimboError::InternalError(format!(
+                                "Column {} is in index {} but not found in table {}",
+                                col.expr, index_name, table.name
+                            )))
+                        }
                     };
-                    let (_, column) = table.get_column(&name).unwrap();
-                    index_columns.push(IndexColumn {
-                        name,
-                        order: col.order.unwrap_or(SortOrder::Asc),
-                        pos_in_table,
-                        collation: column.collation,
-                        default: column.default.clone(),
-                    });
+                    index_columns.push(index_column);
                 }
-                Ok(Index {
+                let mut index = Index {
                     name: index_name,
                     table_name: normalize_ident(tbl_name.as_str()),
                     root_page,
@@ -1440,7 +2212,12 @@ impl Index {
                     unique,
                     ephemeral: false,
                     has_rowid: table.has_rowid,
-                })
+                    where_clause,
+                    non_key_columns: 0,
+                    temporary: table.temporary,
+                };
+                index.append_primary_key_columns(table);
+                Ok(index)
             }
             _ => todo!("Expected create index statement"),
         }
@@ -1466,8 +2243,11 @@ impl Index {
         // I wanted to just chain the iterator above but Rust type system get's messy with Iterators.
         // It would not allow me chain them even by using a core::iter::empty()
         // To circumvent this, I'm having to allocate a second Vec, and extend the other from it.
-        let has_primary_key_index =
-            table.get_rowid_alias_column().is_none() && !table.primary_key_columns.is_empty();
+        let has_primary_key_index = table.has_rowid
+            && table.get_rowid_alias_column().is_none()
+            && !table.primary_key_columns.is_empty();
+        // the table of a WITHOUT ROWID table is the index of its primary key
+        let primary_key_is_indexed = has_primary_key_index || !table.has_rowid;
         if has_primary_key_index {
             let (index_name, root_page) = auto_indices.next().expect(
                 "number of auto_indices in schema should be same number of indices calculated",
@@ -1492,8 +2272,9 @@ impl Index {
                         name: normalize_ident(col_name),
                         order: *order,
                         pos_in_table,
-                        collation: column.collation,
+                        collation: column.collation.clone(),
                         default: column.default.clone(),
+                        expr: None,
                     }
                 })
                 .collect::<Vec<_>>();
@@ -1506,6 +2287,9 @@ impl Index {
                 unique: true,
                 ephemeral: false,
                 has_rowid: table.has_rowid,
+                where_clause: None,
+                non_key_columns: 0,
+                temporary: table.temporary,
             });
         }
 
@@ -1518,7 +2302,7 @@ impl Index {
                 if col.unique {
                     // Unique columns in Table should always be named
                     let col_name = col.name.as_ref().unwrap();
-                    if has_primary_key_index
+                    if primary_key_is_indexed
                         && table.primary_key_columns.len() == 1
                         && &table.primary_key_columns.first().as_ref().unwrap().0 == col_name {
                             // skip unique columns that are satisfied with pk constraint
@@ -1534,12 +2318,16 @@ impl Index {
                             name: normalize_ident(col_name),
                             order: SortOrder::Asc, // Default Sort Order
                             pos_in_table,
-                            collation: column.collation,
+                            collation: column.collation.clone(),
                             default: column.default.clone(),
+                            expr: None,
                         }],
                         unique: true,
                         ephemeral: false,
                         has_rowid: table.has_rowid,
+                        where_clause: None,
+                        non_key_columns: 0,
+                        temporary: table.temporary,
                     })
                 } else {
                     None
@@ -1571,7 +2359,7 @@ impl Index {
             let unique_set_indices = unique_sets
                 .iter()
                 .filter(|set| {
-                    if has_primary_key_index
+                    if primary_key_is_indexed
                         && table.primary_key_columns.len() == set.len()
                         && table
                             .primary_key_columns
@@ -1602,8 +2390,9 @@ impl Index {
                             name: normalize_ident(col_name),
                             order: *order,
                             pos_in_table,
-                            collation: column.collation,
+                            collation: column.collation.clone(),
                             default: column.default.clone(),
+                            expr: None,
                         }
                     });
                     Index {
@@ -1614,6 +2403,9 @@ impl Index {
                         unique: true,
                         ephemeral: false,
                         has_rowid: table.has_rowid,
+                        where_clause: None,
+                        non_key_columns: 0,
+                        temporary: table.temporary,
                     }
                 });
             indices.extend(unique_set_indices);
@@ -1623,9 +2415,47 @@ impl Index {
             panic!("number of auto_indices in schema should be same number of indices calculated");
         }
 
+        for index in indices.iter_mut() {
+            index.append_primary_key_columns(table);
+        }
         Ok(indices)
     }
 
+    /// Appends the PRIMARY KEY columns of a WITHOUT ROWID `table` that the index does not
+    /// contain yet. An index of such a table stores them instead of the rowid.
+    pub fn append_primary_key_columns(&mut self, table: &BTreeTable) {
+        if table.has_rowid {
+            return;
+        }
+        for (name, order) in &table.primary_key_columns {
+            let (pos_in_table, column) = table
+                .get_column(name)
+                .expect("primary key column must exist in the table");
+            if self
+                .columns
+                .iter()
+                .any(|index_column| index_column.stores_column(pos_in_table, column))
+            {
+                continue;
+            }
+            self.columns.push(IndexColumn {
+                name: normalize_ident(name),
+                order: *order,
+                pos_in_table,
+                collation: column.collation.clone(),
+                default: column.default.clone(),
+                expr: None,
+            });
+            self.non_key_columns += 1;
+        }
+    }
+
+    /// Returns the columns of the key the index is declared on, without the columns a WITHOUT
+    /// ROWID table stores to locate the row. These are the columns a UNIQUE index constrains.
+    pub fn key_columns(&self) -> &[IndexColumn] {
+        &self.columns[..self.columns.len() - self.non_key_columns]
+    }
+
     /// Given a column position in the table, return the position in the index.
     /// Returns None if the column is not found in the index.
     /// For example, given:
@@ -1635,7 +2465,84 @@ impl Index {
     pub fn column_table_pos_to_index_pos(&self, table_pos: usize) -> Option<usize> {
         self.columns
             .iter()
-            .position(|c| c.pos_in_table == table_pos)
+            .position(|c| c.expr.is_none() && c.pos_in_table == table_pos)
+    }
+
+    /// Whether the index is an index on expressions or a partial index, whose keys are not plain
+    /// copies of table columns or which does not contain every row.
+    pub fn has_expressions(&self) -> bool {
+        self.where_clause.is_some() || self.columns.iter().any(|c| c.expr.is_some())
+    }
+
+    /// Returns the positions of the table columns the keys of the index are computed from,
+    /// including the columns the WHERE clause of a partial index refers to.
+    pub fn referenced_columns(&self, table: &BTreeTable) -> Vec<usize> {
+        let mut columns = vec![];
+        for column in &self.columns {
+            match &column.expr {
+                Some(expr) => columns.extend(table.referenced_columns(expr)),
+                None => columns.push(column.pos_in_table),
+            }
+        }
+        if let Some(where_clause) = &self.where_clause {
+            columns.extend(table.referenced_columns(where_clause));
+        }
+        columns.sort_unstable();
+        columns.dedup();
+        columns
+    }
+}
+
+impl IndexColumn {
+    /// Whether the index column holds the values of the table column at `pos_in_table`, which is
+    /// `table_column`, and orders them like the table column compares them, so that the index
+    /// can be used for comparisons with the column and for ordering by it.
+    pub fn stores_column(&self, pos_in_table: usize, table_column: &Column) -> bool {
+        self.expr.is_none()
+            && self.pos_in_table == pos_in_table
+            && self.collation.clone().unwrap_or_default()
+                == table_column.collation.clone().unwrap_or_default()
+    }
+
+    /// Resolves a column of a CREATE INDEX statement against the table being indexed. A column
+    /// reference, optionally with a COLLATE clause, indexes that column, anything else is an
+    /// index on an expression.
+    pub fn from_sorted_column(
+        column: &ast::SortedColumn,
+        table: &BTreeTable,
+        syms: &SymbolTable,
+    ) -> Result<Self> {
+        let order = column.order.unwrap_or(SortOrder::Asc);
+        let (expr, collation) = match column.expr.as_ref() {
+            Expr::Collate(expr, collation) => (
+                expr.as_ref(),
+                Some(CollationSeq::new(collation.as_str(), syms)?),
+            ),
+            expr => (expr, None),
+        };
+        if let Expr::Id(name) | Expr::Name(name) = expr {
+            let name = normalize_ident(name.as_str());
+            let Some((pos_in_table, table_column)) = table.get_column(&name) else {
+                crate::bail_parse_error!("no such column: {name}");
+            };
+            return Ok(IndexColumn {
+                name,
+                order,
+                pos_in_table,
+                collation: collation.or_else(|| table_column.collation.clone()),
+                default: table_column.default.clone(),
+                expr: None,
+            });
+        }
+        validate_row_references(expr, table)?;
+        Ok(IndexColumn {
+            name: expr.to_string(),
+            order,
+            pos_in_table: 0,
+            collation,
+            default: None,
+            expr: Some(Box::new(expr.clone())),
+        })
     }
 }
 
@@ -1647,7 +2554,7 @@ mod tests {
     #[test]
     pub fn test_has_rowid_true() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY, b TEXT);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         assert!(table.has_rowid, "has_rowid should be set to true");
         Ok(())
     }
@@ -1655,7 +2562,7 @@ mod tests {
     #[test]
     pub fn test_has_rowid_false() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY, b TEXT) WITHOUT ROWID;"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         assert!(!table.has_rowid, "has_rowid should be set to false");
         Ok(())
     }
@@ -1663,7 +2570,7 @@ mod tests {
     #[test]
     pub fn test_column_is_rowid_alias_single_text() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a TEXT PRIMARY KEY, b TEXT);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(
             !table.column_is_rowid_alias(column),
@@ -1675,7 +2582,7 @@ mod tests {
     #[test]
     pub fn test_column_is_rowid_alias_single_integer() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY, b TEXT);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(
             table.column_is_rowid_alias(column),
@@ -1688,7 +2595,7 @@ mod tests {
     pub fn test_column_is_rowid_alias_single_integer_separate_primary_key_definition() -> Result<()>
     {
         let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT, PRIMARY KEY(a));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(
             table.column_is_rowid_alias(column),
@@ -1701,7 +2608,7 @@ mod tests {
     pub fn test_column_is_rowid_alias_single_integer_separate_primary_key_definition_without_rowid(
     ) -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT, PRIMARY KEY(a)) WITHOUT ROWID;"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(
             !table.column_is_rowid_alias(column),
@@ -1713,7 +2620,7 @@ mod tests {
     #[test]
     pub fn test_column_is_rowid_alias_single_integer_without_rowid() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY, b TEXT) WITHOUT ROWID;"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(
             !table.column_is_rowid_alias(column),
@@ -1725,7 +2632,7 @@ mod tests {
     #[test]
     pub fn test_column_is_rowid_alias_inline_composite_primary_key() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY, b TEXT PRIMARY KEY);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(
             !table.column_is_rowid_alias(column),
@@ -1737,7 +2644,7 @@ mod tests {
     #[test]
     pub fn test_column_is_rowid_alias_separate_composite_primary_key_definition() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT, PRIMARY KEY(a, b));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(
             !table.column_is_rowid_alias(column),
@@ -1749,7 +2656,7 @@ mod tests {
     #[test]
     pub fn test_primary_key_inline_single() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY, b TEXT, c REAL);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(column.primary_key, "column 'a' should be a primary key");
         let column = table.get_column("b").unwrap().1;
@@ -1767,7 +2674,7 @@ mod tests {
     #[test]
     pub fn test_primary_key_inline_multiple() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY, b TEXT PRIMARY KEY, c REAL);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(column.primary_key, "column 'a' should be a primary key");
         let column = table.get_column("b").unwrap().1;
@@ -1788,7 +2695,7 @@ mod tests {
     #[test]
     pub fn test_primary_key_separate_single() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT, c REAL, PRIMARY KEY(a desc));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(column.primary_key, "column 'a' should be a primary key");
         let column = table.get_column("b").unwrap().1;
@@ -1806,7 +2713,7 @@ mod tests {
     #[test]
     pub fn test_primary_key_separate_multiple() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT, c REAL, PRIMARY KEY(a, b desc));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(column.primary_key, "column 'a' should be a primary key");
         let column = table.get_column("b").unwrap().1;
@@ -1827,7 +2734,7 @@ mod tests {
     #[test]
     pub fn test_primary_key_separate_single_quoted() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT, c REAL, PRIMARY KEY('a'));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(column.primary_key, "column 'a' should be a primary key");
         let column = table.get_column("b").unwrap().1;
@@ -1844,7 +2751,7 @@ mod tests {
     #[test]
     pub fn test_primary_key_separate_single_doubly_quoted() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT, c REAL, PRIMARY KEY("a"));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(column.primary_key, "column 'a' should be a primary key");
         let column = table.get_column("b").unwrap().1;
@@ -1862,7 +2769,7 @@ mod tests {
     #[test]
     pub fn test_default_value() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER DEFAULT 23);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         let default = column.default.clone().unwrap();
         assert_eq!(default.to_string(), "23");
@@ -1872,7 +2779,7 @@ mod tests {
     #[test]
     pub fn test_col_notnull() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER NOT NULL);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(column.notnull);
         Ok(())
@@ -1881,7 +2788,7 @@ mod tests {
     #[test]
     pub fn test_col_notnull_negative() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert!(!column.notnull);
         Ok(())
@@ -1890,7 +2797,7 @@ mod tests {
     #[test]
     pub fn test_col_type_string_integer() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a InTeGeR);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let column = table.get_column("a").unwrap().1;
         assert_eq!(column.ty_str, "InTeGeR");
         Ok(())
@@ -1915,7 +2822,7 @@ mod tests {
 
         for (input_column_name, expected_sql) in tests {
             let sql = format!("CREATE TABLE t ([{input_column_name}] TEXT)");
-            let actual = BTreeTable::from_sql(&sql, 0)?.to_sql();
+            let actual = BTreeTable::from_sql(&sql, 0, &SymbolTable::new())?.to_sql();
             assert_eq!(expected_sql, actual);
         }
 
@@ -1927,7 +2834,7 @@ mod tests {
     fn test_automatic_index_single_column() {
         // Without composite primary keys, we should not have an automatic index on a primary key that is a rowid alias
         let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY, b TEXT);"#;
-        let table = BTreeTable::from_sql(sql, 0).unwrap();
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new()).unwrap();
         let _index = Index::automatic_from_primary_key_and_unique(
             &table,
             vec![("sqlite_autoindex_t1_1".to_string(), 2)],
@@ -1938,7 +2845,7 @@ mod tests {
     #[test]
     fn test_automatic_index_composite_key() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT, PRIMARY KEY(a, b));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let mut index = Index::automatic_from_primary_key_and_unique(
             &table,
             vec![("sqlite_autoindex_t1_1".to_string(), 2)],
@@ -1961,7 +2868,7 @@ mod tests {
     #[test]
     fn test_automatic_index_no_primary_key() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let result = Index::automatic_from_primary_key_and_unique(
             &table,
             vec![("sqlite_autoindex_t1_1".to_string(), 2)],
@@ -1996,8 +2903,12 @@ mod tests {
                 unique: false,
                 collation: None,
                 hidden: false,
+                generated: None,
             }],
             unique_sets: None,
+            foreign_keys: vec![],
+            check_constraints: vec![],
+            temporary: false,
         };
 
         let _result = Index::automatic_from_primary_key_and_unique(
@@ -2009,7 +2920,7 @@ mod tests {
     #[test]
     fn test_automatic_index_unique_column() -> Result<()> {
         let sql = r#"CREATE table t1 (x INTEGER, y INTEGER UNIQUE);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let mut index = Index::automatic_from_primary_key_and_unique(
             &table,
             vec![("sqlite_autoindex_t1_1".to_string(), 2)],
@@ -2031,7 +2942,7 @@ mod tests {
     #[test]
     fn test_automatic_index_pkey_unique_column() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (x PRIMARY KEY, y UNIQUE);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let auto_indices = vec![
             ("sqlite_autoindex_t1_1".to_string(), 2),
             ("sqlite_autoindex_t1_2".to_string(), 3),
@@ -2062,7 +2973,7 @@ mod tests {
     #[test]
     fn test_automatic_index_pkey_many_unique_columns() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a PRIMARY KEY, b UNIQUE, c, d, UNIQUE(c, d));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let auto_indices = vec![
             ("sqlite_autoindex_t1_1".to_string(), 2),
             ("sqlite_autoindex_t1_2".to_string(), 3),
@@ -2100,7 +3011,7 @@ mod tests {
     #[test]
     fn test_automatic_index_unique_set_dedup() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a, b, UNIQUE(a, b), UNIQUE(a, b));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let mut index = Index::automatic_from_primary_key_and_unique(
             &table,
             vec![("sqlite_autoindex_t1_1".to_string(), 2)],
@@ -2125,7 +3036,7 @@ mod tests {
     #[test]
     fn test_automatic_index_primary_key_is_unique() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a primary key unique);"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let mut index = Index::automatic_from_primary_key_and_unique(
             &table,
             vec![("sqlite_autoindex_t1_1".to_string(), 2)],
@@ -2148,7 +3059,7 @@ mod tests {
     #[test]
     fn test_automatic_index_primary_key_is_unique_and_composite() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a, b, PRIMARY KEY(a, b), UNIQUE(a, b));"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let mut index = Index::automatic_from_primary_key_and_unique(
             &table,
             vec![("sqlite_autoindex_t1_1".to_string(), 2)],
@@ -2172,7 +3083,7 @@ mod tests {
     #[test]
     fn test_automatic_index_unique_and_a_pk() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a NUMERIC UNIQUE UNIQUE,  b TEXT PRIMARY KEY)"#;
-        let table = BTreeTable::from_sql(sql, 0)?;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
         let mut indexes = Index::automatic_from_primary_key_and_unique(
             &table,
             vec![
@@ -2202,4 +3113,127 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_foreign_keys() -> Result<()> {
+        let sql = r#"CREATE TABLE c (a REFERENCES p ON DELETE CASCADE, b, c, FOREIGN KEY (b, c) REFERENCES "P"(x, y) ON UPDATE SET NULL DEFERRABLE INITIALLY DEFERRED);"#;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
+        assert_eq!(table.foreign_keys.len(), 2);
+
+        let fk = &table.foreign_keys[0];
+        assert_eq!(fk.child_columns, vec!["a"]);
+        assert_eq!(fk.parent_table, "p");
+        assert!(fk.parent_columns.is_empty());
+        assert_eq!(fk.on_delete, ast::RefAct::Cascade);
+        assert_eq!(fk.on_update, ast::RefAct::NoAction);
+        assert!(!fk.deferred);
+
+        let fk = &table.foreign_keys[1];
+        assert_eq!(fk.child_columns, vec!["b", "c"]);
+        assert_eq!(fk.parent_table, "p");
+        assert_eq!(fk.parent_columns, vec!["x", "y"]);
+        assert_eq!(fk.on_delete, ast::RefAct::NoAction);
+        assert_eq!(fk.on_update, ast::RefAct::SetNull);
+        assert!(fk.deferred);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_constraints_and_generated_columns() -> Result<()> {
+        let sql = r#"CREATE TABLE t (a INTEGER CHECK (a > 0), b AS (a * 2), c TEXT GENERATED ALWAYS AS (b || 'x') STORED, CONSTRAINT pos CHECK (b > a));"#;
+        let table = BTreeTable::from_sql(sql, 0, &SymbolTable::new())?;
+
+        assert!(table.columns[0].generated.is_none());
+        assert!(!table.columns[1].generated.as_ref().unwrap().stored);
+        assert!(table.columns[2].generated.as_ref().unwrap().stored);
+        assert!(table.has_virtual_columns());
+        assert_eq!(table.record_index(0), Some(0));
+        assert_eq!(table.record_index(1), None);
+        assert_eq!(table.record_index(2), Some(1));
+        assert_eq!(table.generated_columns_in_order()?, vec![1, 2]);
+
+        assert_eq!(table.check_constraints.len(), 2);
+        assert_eq!(table.check_constraints[0].column.as_deref(), Some("a"));
+        assert_eq!(table.check_constraints[0].description(), "a > 0");
+        assert_eq!(table.check_constraints[1].column, None);
+        assert_eq!(table.check_constraints[1].description(), "pos");
+        assert!(table.references_column(0));
+        assert!(table.references_column(1));
+        assert!(!table.references_column(2));
+
+        let sql = "CREATE TABLE t (a AS (b), b AS (a), c);";
+        assert!(BTreeTable::from_sql(sql, 0, &SymbolTable::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expression_and_partial_index() -> Result<()> {
+        let table = BTreeTable::from_sql(
+            "CREATE TABLE t (a, b TEXT COLLATE NOCASE, c);",
+            0,
+            &SymbolTable::new(),
+        )?;
+        let sql = "CREATE INDEX i ON t (lower(c), b, a COLLATE NOCASE DESC) WHERE c IS NOT NULL";
+        let index = Index::from_sql(sql, 3, &table, &SymbolTable::new())?;
+
+        assert!(index.has_expressions());
+        assert!(index.where_clause.is_some());
+        assert_eq!(index.columns.len(), 3);
+        assert!(index.columns[0].expr.is_some());
+        assert_eq!(index.columns[0].name, "lower(c)");
+        assert!(index.columns[1].stores_column(1, &table.columns[1]));
+        assert!(index.columns[2].expr.is_none());
+        assert_eq!(index.columns[2].pos_in_table, 0);
+        assert!(matches!(index.columns[2].order, SortOrder::Desc));
+        assert!(!index.columns[2].stores_column(0, &table.columns[0]));
+        assert_eq!(index.column_table_pos_to_index_pos(2), None);
+        assert_eq!(index.column_table_pos_to_index_pos(1), Some(1));
+        assert_eq!(index.referenced_columns(&table), vec![0, 1, 2]);
+
+        let sql = "CREATE INDEX i ON t (lower(z))";
+        assert!(Index::from_sql(sql, 3, &table, &SymbolTable::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_without_rowid_clustered_index() -> Result<()> {
+        let sql = "CREATE TABLE t (a TEXT, b TEXT, c, PRIMARY KEY (b, a DESC)) WITHOUT ROWID;";
+        let table = BTreeTable::from_sql(sql, 2, &SymbolTable::new())?;
+        assert_eq!(table.record_columns(), vec![1, 0, 2]);
+        assert_eq!(table.record_index(2), Some(2));
+        assert_eq!(table.record_index(0), Some(1));
+        assert!(table.columns.iter().take(2).all(|column| column.notnull));
+
+        let clustered = table.clustered_index().unwrap();
+        assert_eq!(clustered.root_page, 2);
+        assert!(clustered.unique && !clustered.has_rowid);
+        assert_eq!(clustered.key_columns().len(), 2);
+        assert!(matches!(clustered.columns[1].order, SortOrder::Desc));
+
+        let index = Index::from_sql("CREATE INDEX i ON t (c, a)", 3, &table, &SymbolTable::new())?;
+        let columns = index
+            .columns
+            .iter()
+            .map(|c| c.pos_in_table)
+            .collect::<Vec<_>>();
+        assert_eq!(columns, vec![2, 0, 1]);
+        assert_eq!(index.key_columns().len(), 2);
+        let unique = Index::from_sql(
+            "CREATE UNIQUE INDEX u ON t (c)",
+            4,
+            &table,
+            &SymbolTable::new(),
+        )?;
+        assert_eq!(unique.columns.len(), 3);
+        assert_eq!(unique.key_columns().len(), 1);
+
+        let table = BTreeTable::from_sql(
+            "CREATE TABLE r (a TEXT PRIMARY KEY, b);",
+            2,
+            &SymbolTable::new(),
+        )?;
+        assert!(table.clustered_index().is_none());
+        assert_eq!(table.record_columns(), vec![0, 1]);
+        Ok(())
+    }
 }
@@ -633,16 +633,29 @@ impl BTreeCursor {
         root_page: usize,
         index: &Index,
         num_columns: usize,
+    ) -> Self {
+        let index_info = IndexInfo::new_from_index(index);
+        Self::new_index_with_info(mv_cursor, pager, root_page, index_info, num_columns)
+    }
+
+    /// Like [Self::new_index], for callers that have the comparison info of the index but not
+    /// its schema.
+    pub fn new_index_with_info(
+        mv_cursor: Option<Rc<RefCell<MvCursor>>>,
+        pager: Rc<Pager>,
+        root_page: usize,
+        index_info: IndexInfo,
+        num_columns: usize,
     ) -> Self {
         let mut cursor = Self::new(mv_cursor, pager, root_page, num_columns);
-        cursor.index_info = Some(IndexInfo::new_from_index(index));
+        cursor.index_info = Some(index_info);
         cursor
     }
 
     pub fn has_rowid(&self) -> bool {
         match &self.index_info {
             Some(index_key_info) => index_key_info.has_rowid,
-            None => true, // currently we don't support WITHOUT ROWID tables
+            None => true, // table B-trees are keyed by the rowid
         }
     }
 
@@ -697,6 +710,10 @@ impl BTreeCursor {
     /// Used in backwards iteration.
     #[instrument(skip(self), level = Level::DEBUG, name = "prev")]
     fn get_prev_record(&mut self) -> Result<IOResult<bool>> {
+        if let Some(mv_cursor) = &self.mv_cursor {
+            let mut mv_cursor = mv_cursor.borrow_mut();
+            return Ok(IOResult::Done(mv_cursor.backward()));
+        }
         loop {
             let page = self.stack.top();
             let contents = page.get_contents();
@@ -4079,7 +4096,10 @@ impl BTreeCursor {
     }
 
     pub fn seek_end(&mut self) -> Result<IOResult<()>> {
-        assert!(self.mv_cursor.is_none()); // unsure about this -_-
+        if self.mv_cursor.is_some() {
+            // Versioned inserts do not depend on the position of the cursor.
+            return Ok(IOResult::Done(()));
+        }
         loop {
             match self.seek_end_state {
                 SeekEndState::Start => {
@@ -4180,7 +4200,14 @@ impl BTreeCursor {
 
     #[instrument(skip_all, level = Level::DEBUG)]
     pub fn last(&mut self) -> Result<IOResult<()>> {
-        assert!(self.mv_cursor.is_none());
+        if let Some(mv_cursor) = &self.mv_cursor {
+            let mut mv_cursor = mv_cursor.borrow_mut();
+            mv_cursor.last();
+            self.has_record.replace(!mv_cursor.is_empty());
+            drop(mv_cursor);
+            self.invalidate_record();
+            return Ok(IOResult::Done(()));
+        }
         let cursor_has_record = return_if_io!(self.move_to_rightmost());
         self.has_record.replace(cursor_has_record);
         self.invalidate_record();
@@ -4220,7 +4247,6 @@ impl BTreeCursor {
 
     #[instrument(skip_all, level = Level::DEBUG)]
     pub fn prev(&mut self) -> Result<IOResult<bool>> {
-        assert!(self.mv_cursor.is_none());
         loop {
             match self.advance_state {
                 AdvanceState::Start => {
@@ -4272,9 +4298,13 @@ impl BTreeCursor {
 
     #[instrument(skip(self), level = Level::DEBUG)]
     pub fn seek(&mut self, key: SeekKey<'_>, op: SeekOp) -> Result<IOResult<SeekResult>> {
-        if let Some(mv_cursor) = &self.mv_cursor {
-            let mut mv_cursor = mv_cursor.borrow_mut();
-            return mv_cursor.seek(key, op);
+        if let Some(mv_cursor) = self.mv_cursor.clone() {
+            self.set_null_flag(false);
+            let seek_result = return_if_io!(mv_cursor.borrow_mut().seek(key, op));
+            self.invalidate_record();
+            self.has_record
+                .replace(matches!(seek_result, SeekResult::Found));
+            return Ok(IOResult::Done(seek_result));
         }
         // Empty trace to capture the span information
         tracing::trace!("");
@@ -4385,9 +4415,14 @@ impl BTreeCursor {
                         }
                     };
                     let row = crate::mvcc::database::Row::new(row_id, record_buf, num_columns);
-                    mv_cursor.borrow_mut().insert(row).unwrap();
+                    mv_cursor.borrow_mut().insert(row, self.pager.clone())?;
+                }
+                None => {
+                    let BTreeKey::IndexKey(record) = key else {
+                        unreachable!("table keys always have a rowid");
+                    };
+                    mv_cursor.borrow_mut().insert_index_entry(record)?;
                 }
-                None => todo!("Support mvcc inserts with index btrees"),
             },
             None => {
                 return_if_io!(self.insert_into_page(key));
@@ -4413,6 +4448,12 @@ impl BTreeCursor {
     #[instrument(skip(self), level = Level::DEBUG)]
     pub fn delete(&mut self) -> Result<IOResult<()>> {
         if let Some(mv_cursor) = &self.mv_cursor {
+            if self.index_info.is_some() {
+                mv_cursor
+                    .borrow_mut()
+                    .delete_index_entry(self.pager.clone())?;
+                return Ok(IOResult::Done(()));
+            }
             let rowid = mv_cursor.borrow_mut().current_row_id().unwrap();
             mv_cursor.borrow_mut().delete(rowid, self.pager.clone())?;
             return Ok(IOResult::Done(()));
@@ -4766,7 +4807,6 @@ impl BTreeCursor {
 
     #[instrument(skip_all, level = Level::DEBUG)]
     pub fn exists(&mut self, key: &Value) -> Result<IOResult<bool>> {
-        assert!(self.mv_cursor.is_none());
         let int_key = match key {
             Value::Integer(i) => i,
             _ => unreachable!("btree tables are indexed by integers!"),
@@ -5339,8 +5379,9 @@ impl BTreeCursor {
             .do_allocate_page(page_type, offset, BtreePageAllocMode::Any)
     }
 
-    pub fn get_mvcc_cursor(&self) -> Rc<RefCell<MvCursor>> {
-        self.mv_cursor.as_ref().unwrap().clone()
+    /// The MVCC cursor, unless the B-tree is not versioned, like ephemeral tables.
+    pub fn get_mvcc_cursor(&self) -> Option<Rc<RefCell<MvCursor>>> {
+        self.mv_cursor.clone()
     }
 }
 
@@ -8099,6 +8140,7 @@ mod tests {
                         collation: None,
                         pos_in_table: i,
                         default: None,
+                        expr: None,
                     })
                     .collect(),
                 table_name: "test".to_string(),
@@ -8106,6 +8148,9 @@ mod tests {
                 unique: false,
                 ephemeral: false,
                 has_rowid: false,
+                where_clause: None,
+                non_key_columns: 0,
+                temporary: false,
             };
             let num_columns = index_def.columns.len();
             let mut cursor = BTreeCursor::new_index(
@@ -8259,12 +8304,16 @@ mod tests {
                     collation: None,
                     pos_in_table: 0,
                     default: None,
+                    expr: None,
                 }],
                 table_name: "test".to_string(),
                 root_page: index_root_page,
                 unique: false,
                 ephemeral: false,
                 has_rowid: false,
+                where_clause: None,
+                non_key_columns: 0,
+                temporary: false,
             };
             let mut cursor =
                 BTreeCursor::new_index(None, pager.clone(), index_root_page, &index_def, 1);
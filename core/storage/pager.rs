@@ -17,7 +17,7 @@ use crate::{
 };
 use parking_lot::RwLock;
 use std::cell::{Cell, OnceCell, RefCell, UnsafeCell};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::hash;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
@@ -470,6 +470,8 @@ pub struct Pager {
     /// I/O interface for input/output operations.
     pub io: Arc<dyn crate::io::IO>,
     dirty_pages: Rc<RefCell<HashSet<usize, hash::BuildHasherDefault<hash::DefaultHasher>>>>,
+    /// Open savepoints of the current write transaction, oldest first.
+    savepoints: RefCell<Vec<PagerSavepoint>>,
 
     commit_info: CommitInfo,
     checkpoint_state: RefCell<CheckpointState>,
@@ -505,6 +507,16 @@ pub struct Pager {
     pub(crate) io_ctx: RefCell<IOContext>,
 }
 
+/// Page images captured when a savepoint is opened.
+///
+/// Only the pages that were already dirty are recorded. A page that was clean at that point
+/// still has its savepoint-time contents in the WAL or the database file, so rolling back to
+/// the savepoint only needs to evict it from the page cache.
+#[derive(Debug, Default)]
+struct PagerSavepoint {
+    dirty_pages: HashMap<usize, Vec<u8>>,
+}
+
 #[derive(Debug, Clone)]
 /// The status of the current cache flush.
 pub enum PagerCommitResult {
@@ -583,6 +595,7 @@ impl Pager {
             dirty_pages: Rc::new(RefCell::new(HashSet::with_hasher(
                 hash::BuildHasherDefault::new(),
             ))),
+            savepoints: RefCell::new(Vec::new()),
             commit_info: CommitInfo {
                 state: CommitState::Start.into(),
                 time: now.into(),
@@ -1060,11 +1073,12 @@ impl Pager {
             return Ok(IOResult::Done(PagerCommitResult::Rollback));
         }
         let commit_status = return_if_io!(self.commit_dirty_pages(
-            connection.wal_auto_checkpoint_disabled.get(),
+            connection.is_wal_auto_checkpoint_disabled(),
             connection.get_sync_mode()
         ));
         wal.borrow().end_write_tx();
         wal.borrow().end_read_tx();
+        self.savepoints.borrow_mut().clear();
 
         if schema_did_change {
             let schema = connection.schema.borrow().clone();
@@ -1073,6 +1087,70 @@ impl Pager {
         Ok(IOResult::Done(commit_status))
     }
 
+    /// Ends a transaction that the transaction state of the connection does not track, like the
+    /// transaction of an attached database or of an MVCC checkpoint. Unlike [Self::end_tx], the
+    /// transaction state and the schema of the connection are left alone.
+    #[instrument(skip_all, level = Level::DEBUG)]
+    pub fn end_untracked_tx(
+        &self,
+        rollback: bool,
+        is_write: bool,
+        connection: &Connection,
+    ) -> Result<IOResult<()>> {
+        tracing::trace!("end_untracked_tx(rollback={rollback}, is_write={is_write})");
+        let Some(wal) = self.wal.as_ref() else {
+            return Ok(IOResult::Done(()));
+        };
+        if rollback || !is_write {
+            if is_write {
+                wal.borrow().end_write_tx();
+            }
+            wal.borrow().end_read_tx();
+            if rollback {
+                self.rollback(false, connection, is_write)?;
+            }
+            return Ok(IOResult::Done(()));
+        }
+        return_if_io!(self.commit_dirty_pages(
+            connection.is_wal_auto_checkpoint_disabled(),
+            connection.get_sync_mode()
+        ));
+        wal.borrow().end_write_tx();
+        wal.borrow().end_read_tx();
+        self.savepoints.borrow_mut().clear();
+        Ok(IOResult::Done(()))
+    }
+
+    /// Size of the WAL up to the last frame of the current transaction snapshot, i.e. the size
+    /// the WAL is cut back to in order to undo the commit in progress.
+    pub(crate) fn wal_size_before_commit(&self) -> Option<u64> {
+        let wal = self.wal.as_ref()?;
+        let max_frame = wal.borrow().get_max_frame();
+        if max_frame == 0 {
+            return Some(0);
+        }
+        let page_size = self.page_size.get().unwrap_or_default().get() as u64;
+        Some(
+            sqlite3_ondisk::WAL_HEADER_SIZE as u64
+                + max_frame * (sqlite3_ondisk::WAL_FRAME_HEADER_SIZE as u64 + page_size),
+        )
+    }
+
+    /// Undoes the transactions committed since the WAL held `wal_size` bytes, as returned by
+    /// [Self::wal_size_before_commit] before them.
+    pub(crate) fn undo_commits(&self, wal_size: u64) -> Result<()> {
+        let Some(wal) = self.wal.as_ref() else {
+            return Ok(());
+        };
+        let page_size = self.page_size.get().unwrap_or_default().get() as u64;
+        let max_frame = wal_size.saturating_sub(sqlite3_ondisk::WAL_HEADER_SIZE as u64)
+            / (sqlite3_ondisk::WAL_FRAME_HEADER_SIZE as u64 + page_size);
+        wal.borrow_mut().undo_commits(max_frame)?;
+        // The cache may hold the pages as the undone transactions left them.
+        self.clear_page_cache();
+        Ok(())
+    }
+
     #[instrument(skip_all, level = Level::DEBUG)]
     pub fn end_read_tx(&self) -> Result<()> {
         let Some(wal) = self.wal.as_ref() else {
@@ -1219,6 +1297,148 @@ impl Pager {
         page.set_dirty();
     }
 
+    /// Opens a savepoint by capturing the contents of every page dirtied so far in the current
+    /// write transaction.
+    pub fn open_savepoint(&self) -> Result<()> {
+        let mut savepoint = PagerSavepoint::default();
+        let mut cache = self.page_cache.write();
+        for page_id in self.dirty_pages.borrow().iter() {
+            let page = cache
+                .peek(&PageCacheKey::new(*page_id), false)
+                .ok_or_else(|| {
+                    LimboError::InternalError(format!("dirty page {page_id} is not in the cache"))
+                })?;
+            savepoint
+                .dirty_pages
+                .insert(*page_id, page.get_contents().as_ptr().to_vec());
+        }
+        self.savepoints.borrow_mut().push(savepoint);
+        Ok(())
+    }
+
+    /// Discards the savepoint at `depth` together with every savepoint opened after it.
+    /// The changes made since then become part of the enclosing savepoint or transaction.
+    pub fn release_savepoint(&self, depth: usize) {
+        self.savepoints.borrow_mut().truncate(depth);
+    }
+
+    /// Reverts every page to its state when the savepoint at `depth` was opened. The savepoint
+    /// stays open, the savepoints opened after it are discarded.
+    pub fn rollback_to_savepoint(&self, depth: usize) -> Result<()> {
+        let mut savepoints = self.savepoints.borrow_mut();
+        savepoints.truncate(depth + 1);
+        let Some(savepoint) = savepoints.last() else {
+            return Err(LimboError::InternalError(format!(
+                "no pager savepoint at depth {depth}"
+            )));
+        };
+        let mut cache = self.page_cache.write();
+        let mut dirty_pages = self.dirty_pages.borrow_mut();
+        let dirty_page_ids: Vec<usize> = dirty_pages.iter().copied().collect();
+        for page_id in dirty_page_ids {
+            let key = PageCacheKey::new(page_id);
+            if let Some(contents) = savepoint.dirty_pages.get(&page_id) {
+                let page = cache.peek(&key, false).ok_or_else(|| {
+                    LimboError::InternalError(format!("dirty page {page_id} is not in the cache"))
+                })?;
+                page.get_contents().as_ptr().copy_from_slice(contents);
+                continue;
+            }
+            if let Some(page) = cache.peek(&key, false) {
+                page.clear_dirty();
+            }
+            dirty_pages.remove(&page_id);
+            cache.delete(key).map_err(|e| {
+                LimboError::InternalError(format!(
+                    "Failed to evict page {page_id} on savepoint rollback: {e:?}"
+                ))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the contents of the database with the pages of `source` as part of the current
+    /// write transaction. This is the last step of VACUUM: `source` holds a compacted copy of
+    /// this database, so it is expected to use the same page size and reserved space.
+    ///
+    /// Header fields that describe this database rather than its contents (schema cookie,
+    /// user version, ...) are preserved, only the page count and the freelist are taken from
+    /// `source`.
+    pub fn copy_from(&self, source: &Pager) -> Result<()> {
+        let result = self.copy_pages_from(source);
+        if result.is_err() {
+            // Don't leave half-copied pages behind: they are read again from the database.
+            self.clear_page_cache();
+        }
+        result
+    }
+
+    fn copy_pages_from(&self, source: &Pager) -> Result<()> {
+        let source_header = source.io.block(|| source.with_header(|header| *header))?;
+        let header = self.io.block(|| self.with_header(|header| *header))?;
+        if source_header.page_size.get() != header.page_size.get()
+            || source_header.reserved_space != header.reserved_space
+        {
+            return Err(LimboError::InternalError(
+                "cannot copy pages between databases with different page layouts".to_string(),
+            ));
+        }
+        let current_page_count = header.database_size.get() as usize;
+        let page_count = source_header.database_size.get() as usize;
+
+        for page_id in 1..=page_count {
+            let (source_page, c) = source.read_page(page_id)?;
+            if let Some(c) = c {
+                source.io.wait_for_completion(c)?;
+            }
+            let page = if page_id <= current_page_count {
+                let (page, c) = self.read_page(page_id)?;
+                if let Some(c) = c {
+                    self.io.wait_for_completion(c)?;
+                }
+                page
+            } else {
+                let offset = if page_id == DatabaseHeader::PAGE_ID {
+                    DatabaseHeader::SIZE
+                } else {
+                    0
+                };
+                let page = allocate_new_page(page_id, &self.buffer_pool, offset);
+                self.page_cache
+                    .write()
+                    .insert(PageCacheKey::new(page_id), page.clone())?;
+                page
+            };
+            page.get_contents()
+                .as_ptr()
+                .copy_from_slice(source_page.get_contents().as_ptr());
+            self.add_dirty(&page);
+        }
+
+        self.io.block(|| {
+            self.with_header_mut(|h| {
+                *h = header;
+                h.change_counter = (header.change_counter.get() + 1).into();
+                h.database_size = source_header.database_size;
+                h.freelist_trunk_page = source_header.freelist_trunk_page;
+                h.freelist_pages = source_header.freelist_pages;
+                h.vacuum_mode_largest_root_page = source_header.vacuum_mode_largest_root_page;
+            })
+        })?;
+
+        // Pages past the end of the compacted database are no longer reachable.
+        let mut cache = self.page_cache.write();
+        for page_id in page_count + 1..=current_page_count {
+            let key = PageCacheKey::new(page_id);
+            if let Some(page) = cache.peek(&key, false) {
+                page.clear_dirty();
+            }
+            self.dirty_pages.borrow_mut().remove(&page_id);
+            let _ = cache.delete(key);
+        }
+        Ok(())
+    }
+
     pub fn wal_state(&self) -> Result<WalState> {
         let Some(wal) = self.wal.as_ref() else {
             return Err(LimboError::InternalError(
@@ -1231,6 +1451,29 @@ impl Pager {
         })
     }
 
+    /// Writes the dirty pages straight to the database file, so that they can be evicted from
+    /// the page cache. Only for databases without a WAL, like the `temp` database of a
+    /// connection, which are never committed.
+    pub fn flush_dirty_pages_to_db_file(&self) -> Result<Vec<Completion>> {
+        turso_assert!(
+            self.wal.is_none(),
+            "flush_dirty_pages_to_db_file() called on database with WAL"
+        );
+        let dirty_pages = std::mem::take(&mut *self.dirty_pages.borrow_mut());
+        let mut completions = Vec::with_capacity(dirty_pages.len());
+        for page_id in dirty_pages {
+            let page = self
+                .page_cache
+                .write()
+                .get(&PageCacheKey::new(page_id))?
+                .ok_or_else(|| {
+                    LimboError::InternalError(format!("dirty page {page_id} is not in the cache"))
+                })?;
+            completions.push(begin_write_btree_page(self, &page)?);
+        }
+        Ok(completions)
+    }
+
     /// Flush all dirty pages to disk.
     /// Unlike commit_dirty_pages, this function does not commit, checkpoint now sync the WAL/Database.
     #[instrument(skip_all, level = Level::INFO)]
@@ -1553,6 +1796,7 @@ impl Pager {
     /// right after new writes happened which would invalidate current page cache.
     pub fn clear_page_cache(&self) {
         self.dirty_pages.borrow_mut().clear();
+        self.savepoints.borrow_mut().clear();
         self.page_cache.write().unset_dirty_all_pages();
         self.page_cache
             .write()
@@ -1656,8 +1900,7 @@ impl Pager {
 
     pub fn freepage_list(&self) -> u32 {
         self.io
-            .block(|| HeaderRefMut::from_pager(self))
-            .map(|header_ref| header_ref.borrow_mut().freelist_pages.into())
+            .block(|| self.with_header(|header| header.freelist_pages.get()))
             .unwrap_or(0)
     }
     // Providing a page is optional, if provided it will be used to avoid reading the page from disk.
@@ -2109,6 +2352,7 @@ impl Pager {
         is_write: bool,
     ) -> Result<(), LimboError> {
         tracing::debug!(schema_did_change);
+        self.savepoints.borrow_mut().clear();
         if is_write {
             self.dirty_pages.borrow_mut().clear();
         } else {
@@ -0,0 +1,312 @@
+//! Super-journal for transactions that write to several databases.
+//!
+//! A transaction that wrote to the main database and to attached databases commits each of them
+//! separately, so a crash in between would leave only some of them with its changes. Like the
+//! super-journal of SQLite, the commit first writes a file that names every database of the
+//! transaction, and a record next to the WAL of each database holding the path of that file and
+//! the size of the WAL before the commit. Deleting the super-journal once every database has
+//! committed is the commit point: when a database is opened while its record still points to an
+//! existing super-journal, the transaction did not finish and the frames it appended to the WAL
+//! are cut off again.
+//!
+//! Checkpoints are held back while a super-journal exists, otherwise the frames to cut off could
+//! already have been copied to the database file.
+//!
+//! The files are accessed through the [IO] of the main database, like the databases themselves.
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::error::CompletionError;
+use crate::{Buffer, Completion, LimboError, OpenFlags, Result, IO};
+
+/// Suffix of the record written next to the WAL of each database of the transaction.
+const RECORD_SUFFIX: &str = "-mj";
+
+/// The super-journal of a commit in progress.
+pub(crate) struct SuperJournal {
+    io: Arc<dyn IO>,
+    path: String,
+    records: Vec<String>,
+}
+
+impl std::fmt::Debug for SuperJournal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SuperJournal")
+            .field("path", &self.path)
+            .field("records", &self.records)
+            .finish()
+    }
+}
+
+impl SuperJournal {
+    /// Writes the super-journal of a commit that spans the databases whose WAL files are given
+    /// with their size before the commit. Nothing is committed before all files are synced.
+    pub(crate) fn create(io: Arc<dyn IO>, main_path: &str, wals: &[(String, u64)]) -> Result<Self> {
+        // The files are named by absolute paths, so that recovery finds them from anywhere.
+        let path = absolute(&format!(
+            "{main_path}-mj{:08X}",
+            io.generate_random_number() as u32
+        ))?;
+        let records = wals
+            .iter()
+            .map(|(wal_path, _)| absolute(&record_path(wal_path)))
+            .collect::<Result<Vec<_>>>()?;
+        let journal = Self { io, path, records };
+
+        let mut contents = String::new();
+        for record in &journal.records {
+            contents.push_str(record);
+            contents.push('\n');
+        }
+        write_synced(&*journal.io, &journal.path, &contents)?;
+        for (record, (_, wal_size)) in journal.records.iter().zip(wals) {
+            let contents = format!("{}\n{wal_size}\n", journal.path);
+            if let Err(e) = write_synced(&*journal.io, record, &contents) {
+                // Nothing was committed yet, so the transaction can still fail as a whole.
+                journal.finish()?;
+                return Err(e);
+            }
+        }
+        Ok(journal)
+    }
+
+    /// Commits the transaction in every database by deleting the super-journal, then removes the
+    /// records that point to it.
+    pub(crate) fn finish(self) -> Result<()> {
+        remove_if_exists(&*self.io, &self.path)?;
+        for record in &self.records {
+            remove_if_exists(&*self.io, record)?;
+        }
+        Ok(())
+    }
+}
+
+/// Undoes the changes that a transaction spanning several databases left in the WAL at
+/// `wal_path` if it did not commit in all of them, see the module documentation.
+pub(crate) fn recover(io: &dyn IO, wal_path: &str) -> Result<()> {
+    let record = record_path(wal_path);
+    let Some(contents) = read_to_string(io, &record)? else {
+        return Ok(());
+    };
+    let mut lines = contents.lines();
+    // A torn record was being written when the process stopped, before anything was committed.
+    if let (Some(journal), Some(Ok(wal_size))) = (lines.next(), lines.next().map(str::parse)) {
+        if exists(io, journal)? {
+            tracing::info!("rolling back interrupted multi-database commit in {wal_path}");
+            truncate_wal(io, wal_path, wal_size)?;
+        }
+        remove_if_exists(io, &record)?;
+        remove_journal_if_unused(io, journal)?;
+    } else {
+        remove_if_exists(io, &record)?;
+    }
+    Ok(())
+}
+
+fn record_path(wal_path: &str) -> String {
+    format!("{wal_path}{RECORD_SUFFIX}")
+}
+
+fn absolute(path: &str) -> Result<String> {
+    Ok(std::path::absolute(path)?.to_string_lossy().into_owned())
+}
+
+fn truncate_wal(io: &dyn IO, wal_path: &str, wal_size: u64) -> Result<()> {
+    if wal_size == 0 {
+        // The WAL held no frames before the commit.
+        return remove_if_exists(io, wal_path);
+    }
+    let file = io.open_file(wal_path, OpenFlags::None, false)?;
+    let c = file.truncate(wal_size, Completion::new_trunc(|_| {}))?;
+    io.wait_for_completion(c)?;
+    let c = file.sync(Completion::new_sync(|_| {}))?;
+    io.wait_for_completion(c)?;
+    Ok(())
+}
+
+/// Deletes the super-journal once no database of its transaction still has to be rolled back.
+fn remove_journal_if_unused(io: &dyn IO, journal: &str) -> Result<()> {
+    let Some(contents) = read_to_string(io, journal)? else {
+        return Ok(());
+    };
+    for record in contents.lines() {
+        if exists(io, record)? {
+            return Ok(());
+        }
+    }
+    remove_if_exists(io, journal)
+}
+
+fn is_not_found(err: &LimboError) -> bool {
+    matches!(
+        err,
+        LimboError::CompletionError(CompletionError::IOError(std::io::ErrorKind::NotFound))
+    )
+}
+
+fn exists(io: &dyn IO, path: &str) -> Result<bool> {
+    match io.open_file(path, OpenFlags::ReadOnly, false) {
+        Ok(_) => Ok(true),
+        Err(e) if is_not_found(&e) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads the file at `path`, `None` if there is none.
+fn read_to_string(io: &dyn IO, path: &str) -> Result<Option<String>> {
+    let file = match io.open_file(path, OpenFlags::ReadOnly, false) {
+        Ok(file) => file,
+        Err(e) if is_not_found(&e) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let size = file.size()? as usize;
+    if size == 0 {
+        return Ok(Some(String::new()));
+    }
+    let bytes_read = Rc::new(Cell::new(0));
+    let c = {
+        let bytes_read = bytes_read.clone();
+        Completion::new_read(Arc::new(Buffer::new_temporary(size)), move |res| {
+            if let Ok((_, n)) = res {
+                bytes_read.set(n.max(0) as usize);
+            }
+        })
+    };
+    let c = file.pread(0, c)?;
+    io.wait_for_completion(c.clone())?;
+    let buf = c.as_read().buf();
+    let contents = &buf.as_slice()[..bytes_read.get().min(size)];
+    Ok(Some(String::from_utf8_lossy(contents).into_owned()))
+}
+
+fn write_synced(io: &dyn IO, path: &str, contents: &str) -> Result<()> {
+    let file = io.open_file(path, OpenFlags::Create, false)?;
+    let buffer = Arc::new(Buffer::new(contents.as_bytes().to_vec()));
+    let c = file.pwrite(0, buffer, Completion::new_write(|_| {}))?;
+    io.wait_for_completion(c)?;
+    // Drop whatever a longer file at the same path held.
+    let c = file.truncate(contents.len() as u64, Completion::new_trunc(|_| {}))?;
+    io.wait_for_completion(c)?;
+    let c = file.sync(Completion::new_sync(|_| {}))?;
+    io.wait_for_completion(c)?;
+    Ok(())
+}
+
+fn remove_if_exists(io: &dyn IO, path: &str) -> Result<()> {
+    match io.remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if is_not_found(&e) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::Value, Connection, Database, PlatformIO};
+
+    fn open(io: &Arc<dyn IO>, path: &str) -> (Arc<Database>, Arc<Connection>) {
+        let db = Database::open_file(io.clone(), path, false, false).unwrap();
+        let conn = db.connect().unwrap();
+        (db, conn)
+    }
+
+    fn values(conn: &Arc<Connection>) -> Vec<i64> {
+        let rows = conn
+            .prepare("SELECT x FROM t ORDER BY x")
+            .unwrap()
+            .run_collect_rows()
+            .unwrap();
+        rows.iter()
+            .map(|row| match row[0] {
+                Value::Integer(x) => x,
+                _ => panic!("unexpected row: {row:?}"),
+            })
+            .collect()
+    }
+
+    fn wal_size(path: &str) -> u64 {
+        std::fs::metadata(format!("{path}-wal")).unwrap().len()
+    }
+
+    /// Opens two databases holding a row each, then starts a commit spanning both of them that
+    /// appends a row to each database. Everything is dropped without closing, as in a crash.
+    fn interrupted_commit(
+        dir: &std::path::Path,
+    ) -> (Arc<dyn IO>, [String; 2], [u64; 2], SuperJournal) {
+        let io: Arc<dyn IO> = Arc::new(PlatformIO::new().unwrap());
+        let a = dir.join("a.db").to_str().unwrap().to_string();
+        let b = dir.join("b.db").to_str().unwrap().to_string();
+        let (db_a, conn_a) = open(&io, &a);
+        let (db_b, conn_b) = open(&io, &b);
+        for conn in [&conn_a, &conn_b] {
+            conn.execute("CREATE TABLE t(x)").unwrap();
+            conn.execute("INSERT INTO t VALUES (1)").unwrap();
+        }
+        let wals = [
+            (format!("{a}-wal"), wal_size(&a)),
+            (format!("{b}-wal"), wal_size(&b)),
+        ];
+        let journal = SuperJournal::create(io.clone(), &a, &wals).unwrap();
+        for conn in [&conn_a, &conn_b] {
+            conn.execute("INSERT INTO t VALUES (2)").unwrap();
+        }
+        assert!(wal_size(&a) > wals[0].1 && wal_size(&b) > wals[1].1);
+        drop((conn_a, conn_b, db_a, db_b));
+        (io, [a, b], [wals[0].1, wals[1].1], journal)
+    }
+
+    #[test]
+    fn test_recover_interrupted_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let (io, [a, b], wal_sizes, journal) = interrupted_commit(dir.path());
+        let journal_path = journal.path.clone();
+        // The process stops before the commit point.
+        std::mem::forget(journal);
+        assert!(exists(&*io, &format!("{a}-wal-mj")).unwrap());
+        assert!(exists(&*io, &journal_path).unwrap());
+
+        let (_db_a, conn_a) = open(&io, &a);
+        assert_eq!(wal_size(&a), wal_sizes[0]);
+        assert_eq!(values(&conn_a), vec![1]);
+        assert!(!exists(&*io, &format!("{a}-wal-mj")).unwrap());
+        // The other database still has to be rolled back.
+        assert!(exists(&*io, &journal_path).unwrap());
+
+        let (_db_b, conn_b) = open(&io, &b);
+        assert_eq!(wal_size(&b), wal_sizes[1]);
+        assert_eq!(values(&conn_b), vec![1]);
+        assert!(!exists(&*io, &format!("{b}-wal-mj")).unwrap());
+        assert!(!exists(&*io, &journal_path).unwrap());
+    }
+
+    #[test]
+    fn test_recover_finished_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let (io, [a, b], _, journal) = interrupted_commit(dir.path());
+        let journal_path = journal.path.clone();
+        journal.finish().unwrap();
+        assert!(!exists(&*io, &journal_path).unwrap());
+        assert!(!exists(&*io, &format!("{a}-wal-mj")).unwrap());
+
+        for path in [&a, &b] {
+            let (_db, conn) = open(&io, path);
+            assert_eq!(values(&conn), vec![1, 2]);
+        }
+    }
+
+    #[test]
+    fn test_recover_ignores_torn_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let (io, [a, _], _, journal) = interrupted_commit(dir.path());
+        let journal_path = journal.path.clone();
+        std::mem::forget(journal);
+        // The record lost its WAL size, so the commit had not started yet.
+        write_synced(&*io, &format!("{a}-wal-mj"), &format!("{journal_path}\n")).unwrap();
+
+        let (_db, conn) = open(&io, &a);
+        assert_eq!(values(&conn), vec![1, 2]);
+        assert!(!exists(&*io, &format!("{a}-wal-mj")).unwrap());
+    }
+}
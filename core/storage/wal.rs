@@ -300,6 +300,11 @@ pub trait Wal: Debug {
     fn get_min_frame(&self) -> u64;
     fn rollback(&mut self) -> Result<()>;
 
+    /// Drops the frames that transactions committed after frame `max_frame` and cuts the WAL
+    /// file back to them. Used when a transaction spanning several databases fails to commit
+    /// after this database did.
+    fn undo_commits(&mut self, max_frame: u64) -> Result<()>;
+
     /// Return unique set of pages changed **after** frame_watermark position and until current WAL session max_frame_no
     fn changed_pages_after(&self, frame_watermark: u64) -> Result<Vec<u32>>;
 
@@ -1390,6 +1395,35 @@ impl Wal for WalFile {
         Ok(())
     }
 
+    #[instrument(err, skip_all, level = Level::DEBUG)]
+    fn undo_commits(&mut self, max_frame: u64) -> Result<()> {
+        turso_assert!(
+            max_frame <= self.get_max_frame_in_wal(),
+            "cannot undo commits past the end of the WAL"
+        );
+        // The checksums are cumulative, so appending goes on from the one of the last frame kept.
+        let last_checksum = if max_frame == 0 {
+            let header = self.get_shared().wal_header.lock();
+            (header.checksum_1, header.checksum_2)
+        } else {
+            let mut frame = vec![0u8; self.page_size() as usize + WAL_FRAME_HEADER_SIZE];
+            let c = self.read_frame_raw(max_frame, &mut frame)?;
+            self.io.wait_for_completion(c)?;
+            let (header, _) = sqlite3_ondisk::parse_wal_frame_header(&frame);
+            (header.checksum_1, header.checksum_2)
+        };
+        let shared = self.get_shared();
+        shared.max_frame.store(max_frame, Ordering::Release);
+        shared.last_checksum = last_checksum;
+        // The header stays, as it belongs to the frames kept.
+        let c = shared.file.truncate(
+            self.frame_offset(max_frame + 1),
+            Completion::new_trunc(|_| {}),
+        )?;
+        self.io.wait_for_completion(c)?;
+        self.rollback()
+    }
+
     #[instrument(skip_all, level = Level::DEBUG)]
     fn finish_append_frames_commit(&mut self) -> Result<()> {
         let shared = self.get_shared();
@@ -2275,7 +2309,7 @@ pub mod test {
             sqlite3_ondisk::{self, WAL_HEADER_SIZE},
             wal::READMARK_NOT_USED,
         },
-        types::IOResult,
+        types::{IOResult, Value},
         util::IOExt,
         CheckpointMode, CheckpointResult, Completion, Connection, Database, LimboError, PlatformIO,
         StepResult, Wal, WalFile, WalFileShared, IO,
@@ -2371,6 +2405,50 @@ pub mod test {
         std::fs::remove_dir_all(path).unwrap();
     }
 
+    #[test]
+    fn test_wal_undo_commits() {
+        let (db, path) = get_database();
+        let dbpath = path.join("test.db");
+        let conn = db.connect().unwrap();
+        conn.execute("create table test (id integer primary key, value text)")
+            .unwrap();
+        bulk_inserts(&conn, 1, 10);
+        let wal_size = conn.pager.borrow().wal_size_before_commit().unwrap();
+        bulk_inserts(&conn, 2, 10);
+        assert_eq!(count_rows(&conn), 30);
+
+        conn.pager.borrow().undo_commits(wal_size).unwrap();
+        assert_eq!(
+            std::fs::metadata(path.join("test.db-wal")).unwrap().len(),
+            wal_size
+        );
+        assert_eq!(count_rows(&conn), 10);
+        // Frames appended afterwards continue the checksums of the frames kept.
+        bulk_inserts(&conn, 1, 5);
+        assert_eq!(count_rows(&conn), 15);
+        drop(conn);
+        drop(db);
+
+        let io: Arc<dyn IO> = Arc::new(PlatformIO::new().unwrap());
+        let db = Database::open_file(io, dbpath.to_str().unwrap(), false, false).unwrap();
+        let conn = db.connect().unwrap();
+        assert_eq!(count_rows(&conn), 15);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    /// Counts the rows of `test`, ending the read transaction of the statement.
+    fn count_rows(conn: &Arc<Connection>) -> i64 {
+        let rows = conn
+            .prepare("select count(*) from test")
+            .unwrap()
+            .run_collect_rows()
+            .unwrap();
+        let Value::Integer(count) = rows[0][0] else {
+            panic!("unexpected rows: {rows:?}");
+        };
+        count
+    }
+
     fn bulk_inserts(conn: &Arc<Connection>, n_txns: usize, rows_per_txn: usize) {
         for _ in 0..n_txns {
             conn.execute("begin transaction").unwrap();
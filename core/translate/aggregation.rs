@@ -64,11 +64,12 @@ pub fn emit_ungrouped_aggregation<'a>(
 fn emit_collseq_if_needed(
     program: &mut ProgramBuilder,
     referenced_tables: &TableReferences,
+    resolver: &Resolver,
     expr: &ast::Expr,
 ) {
     // Check if this is a column expression with explicit COLLATE clause
     if let ast::Expr::Collate(_, collation_name) = expr {
-        if let Ok(collation) = CollationSeq::new(collation_name.as_str()) {
+        if let Ok(collation) = CollationSeq::new(collation_name.as_str(), resolver.symbol_table) {
             program.emit_insn(Insn::CollSeq {
                 reg: None,
                 collation,
@@ -84,7 +85,7 @@ fn emit_collseq_if_needed(
                 if let Some(collation) = &table_column.collation {
                     program.emit_insn(Insn::CollSeq {
                         reg: None,
-                        collation: *collation,
+                        collation: collation.clone(),
                     });
                 }
             }
@@ -317,7 +318,7 @@ pub fn translate_aggregation_step(
 
             if num_args == 2 {
                 match &agg_arg_source.args()[1] {
-                    arg @ ast::Expr::Column { .. } => {
+                    arg @ (ast::Expr::Column { .. } | ast::Expr::Register(_)) => {
                         delimiter_expr = arg.clone();
                     }
                     ast::Expr::Literal(ast::Literal::String(s)) => {
@@ -355,7 +356,7 @@ pub fn translate_aggregation_step(
             let expr_reg = agg_arg_source.translate(program, referenced_tables, resolver, 0)?;
             handle_distinct(program, agg_arg_source.aggregate(), expr_reg);
             let expr = &agg_arg_source.args()[0];
-            emit_collseq_if_needed(program, referenced_tables, expr);
+            emit_collseq_if_needed(program, referenced_tables, resolver, expr);
             program.emit_insn(Insn::AggStep {
                 acc_reg: target_register,
                 col: expr_reg,
@@ -371,7 +372,7 @@ pub fn translate_aggregation_step(
             let expr_reg = agg_arg_source.translate(program, referenced_tables, resolver, 0)?;
             handle_distinct(program, agg_arg_source.aggregate(), expr_reg);
             let expr = &agg_arg_source.args()[0];
-            emit_collseq_if_needed(program, referenced_tables, expr);
+            emit_collseq_if_needed(program, referenced_tables, resolver, expr);
             program.emit_insn(Insn::AggStep {
                 acc_reg: target_register,
                 col: expr_reg,
@@ -420,7 +421,7 @@ pub fn translate_aggregation_step(
             let delimiter_reg = program.alloc_register();
 
             let delimiter_expr = match &agg_arg_source.args()[1] {
-                arg @ ast::Expr::Column { .. } => arg.clone(),
+                arg @ (ast::Expr::Column { .. } | ast::Expr::Register(_)) => arg.clone(),
                 ast::Expr::Literal(ast::Literal::String(s)) => {
                     ast::Expr::Literal(ast::Literal::String(s.to_string()))
                 }
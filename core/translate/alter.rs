@@ -3,7 +3,7 @@ use turso_parser::{ast, parser::Parser};
 
 use crate::{
     function::{AlterTableFunc, Func},
-    schema::{Column, Schema},
+    schema::{CheckConstraint, Column, Schema},
     util::normalize_ident,
     vdbe::{
         builder::{CursorType, ProgramBuilder},
@@ -12,7 +12,10 @@ use crate::{
     LimboError, Result, SymbolTable,
 };
 
-use super::{schema::SQLITE_TABLEID, update::translate_update_for_schema_change};
+use super::{
+    schema::{resolve_schema_target, SQLITE_TABLEID},
+    update::translate_update_for_schema_change,
+};
 
 pub fn translate_alter_table(
     alter: ast::AlterTable,
@@ -27,6 +30,13 @@ pub fn translate_alter_table(
         name: table_name,
         body: alter_table,
     } = alter;
+    // A table of an attached database is altered with the schema of that database.
+    let (db, attached_schema) = resolve_schema_target(connection, &table_name)?;
+    let schema = attached_schema.as_deref().unwrap_or(schema);
+    let schema_table = match &table_name.db_name {
+        Some(db_name) if db > 1 => format!("{}.{SQLITE_TABLEID}", db_name.as_str()),
+        _ => SQLITE_TABLEID.to_string(),
+    };
     let table_name = table_name.name.as_str();
     if schema.table_has_indexes(table_name) && !schema.indexes_enabled() {
         // Let's disable altering a table with indices altogether instead of checking column by
@@ -36,12 +46,34 @@ pub fn translate_alter_table(
         );
     }
 
+    if let ast::AlterTableBody::RenameTo(new_name) = &alter_table {
+        let vtab = schema
+            .get_table(table_name)
+            .and_then(|table| table.virtual_table())
+            .filter(|vtab| syms.vtabs.contains_key(&vtab.name));
+        if let Some(vtab) = vtab {
+            // The module is told first, so that it can refuse the rename.
+            program.emit_insn(Insn::VRename {
+                table_name: vtab.name.clone(),
+                new_name: new_name.as_str().to_string(),
+            });
+            emit_rename_table(&mut program, schema, db, table_name, new_name.as_str())?;
+            return Ok(program);
+        }
+    }
+
     let Some(original_btree) = schema.get_table(table_name).and_then(|table| table.btree()) else {
         return Err(LimboError::ParseError(format!(
             "no such table: {table_name}"
         )));
     };
 
+    if original_btree.temporary {
+        return Err(LimboError::ParseError(
+            "ALTER TABLE on TEMPORARY tables is not supported yet".to_string(),
+        ));
+    }
+
     let mut btree = (*original_btree).clone();
 
     Ok(match alter_table {
@@ -80,13 +112,44 @@ pub fn translate_alter_table(
                 )));
             }
 
-            btree.columns.remove(dropped_index);
+            if btree.foreign_keys.iter().any(|fk| {
+                fk.child_columns
+                    .iter()
+                    .any(|name| name == &normalize_ident(column_name))
+            }) {
+                return Err(LimboError::ParseError(format!(
+                    "error in table {table_name} after drop column: unknown column \"{column_name}\" in foreign key definition"
+                )));
+            }
+
+            if btree.references_column(dropped_index) {
+                return Err(LimboError::ParseError(format!(
+                    "error in table {table_name} after drop column: no such column: {column_name}"
+                )));
+            }
+
+            if let Some(index) = schema.get_indices(table_name).iter().find(|index| {
+                index.has_expressions()
+                    && index
+                        .referenced_columns(&original_btree)
+                        .contains(&dropped_index)
+            }) {
+                return Err(LimboError::ParseError(format!(
+                    "error in index {} after drop column: no such column: {column_name}",
+                    index.name
+                )));
+            }
+
+            let dropped_column = btree.columns.remove(dropped_index);
+            btree
+                .check_constraints
+                .retain(|check| check.column != dropped_column.name);
 
             let sql = btree.to_sql().replace('\'', "''");
 
             let stmt = format!(
                 r#"
-                    UPDATE {SQLITE_TABLEID}
+                    UPDATE {schema_table}
                     SET sql = '{sql}'
                     WHERE name = '{table_name}' COLLATE NOCASE AND type = 'table'
                 "#,
@@ -106,7 +169,11 @@ pub fn translate_alter_table(
                 connection,
                 input,
                 |program| {
-                    let column_count = btree.columns.len();
+                    // VIRTUAL generated columns are not part of the record
+                    let stored_columns = (0..original_btree.columns.len())
+                        .filter(|&i| i != dropped_index && original_btree.columns[i].is_stored())
+                        .collect::<Vec<_>>();
+                    let column_count = stored_columns.len();
                     let root_page = btree.root_page;
                     let table_name = btree.name.clone();
 
@@ -115,22 +182,14 @@ pub fn translate_alter_table(
                     program.emit_insn(Insn::OpenWrite {
                         cursor_id,
                         root_page: RegisterOrLiteral::Literal(root_page),
-                        db: 0,
+                        db,
                     });
 
                     program.cursor_loop(cursor_id, |program, rowid| {
                         let first_column = program.alloc_registers(column_count);
 
-                        let mut iter = first_column;
-
-                        for i in 0..(column_count + 1) {
-                            if i == dropped_index {
-                                continue;
-                            }
-
-                            program.emit_column_or_rowid(cursor_id, i, iter);
-
-                            iter += 1;
+                        for (offset, &i) in stored_columns.iter().enumerate() {
+                            program.emit_column_or_rowid(cursor_id, i, first_column + offset);
                         }
 
                         let record = program.alloc_register();
@@ -152,13 +211,14 @@ pub fn translate_alter_table(
                     });
 
                     program.emit_insn(Insn::SetCookie {
-                        db: 0,
+                        db,
                         cookie: Cookie::SchemaVersion,
                         value: schema.schema_version as i32 + 1,
                         p5: 0,
                     });
 
                     program.emit_insn(Insn::DropColumn {
+                        db,
                         table: table_name,
                         column_index: dropped_index,
                     })
@@ -166,7 +226,30 @@ pub fn translate_alter_table(
             )?
         }
         ast::AlterTableBody::AddColumn(col_def) => {
-            let column = Column::from(&col_def);
+            let column = Column::from_definition(&col_def, syms)?;
+
+            if column
+                .generated
+                .as_ref()
+                .is_some_and(|generated| generated.stored)
+            {
+                return Err(LimboError::ParseError(
+                    "cannot add a STORED column".to_string(),
+                ));
+            }
+
+            let check_constraints = col_def
+                .constraints
+                .iter()
+                .filter_map(|c| match &c.constraint {
+                    ast::ColumnConstraint::Check(expr) => Some(CheckConstraint {
+                        name: c.name.as_ref().map(|name| normalize_ident(name.as_str())),
+                        expr: expr.clone(),
+                        column: column.name.clone(),
+                    }),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
 
             if let Some(default) = &column.default {
                 if !matches!(
@@ -187,6 +270,9 @@ pub fn translate_alter_table(
             }
 
             btree.columns.push(column.clone());
+            btree
+                .check_constraints
+                .extend(check_constraints.iter().cloned());
 
             let sql = btree.to_sql();
             let mut escaped = String::with_capacity(sql.len());
@@ -200,7 +286,7 @@ pub fn translate_alter_table(
 
             let stmt = format!(
                 r#"
-                    UPDATE {SQLITE_TABLEID}
+                    UPDATE {schema_table}
                     SET sql = '{escaped}'
                     WHERE name = '{table_name}' COLLATE NOCASE AND type = 'table'
                 "#,
@@ -221,103 +307,22 @@ pub fn translate_alter_table(
                 input,
                 |program| {
                     program.emit_insn(Insn::SetCookie {
-                        db: 0,
+                        db,
                         cookie: Cookie::SchemaVersion,
                         value: schema.schema_version as i32 + 1,
                         p5: 0,
                     });
                     program.emit_insn(Insn::AddColumn {
+                        db,
                         table: table_name.to_owned(),
                         column,
+                        check_constraints,
                     });
                 },
             )?
         }
         ast::AlterTableBody::RenameTo(new_name) => {
-            let new_name = new_name.as_str();
-
-            if schema.get_table(new_name).is_some()
-                || schema
-                    .indexes
-                    .values()
-                    .flatten()
-                    .any(|index| index.name == normalize_ident(new_name))
-            {
-                return Err(LimboError::ParseError(format!(
-                    "there is already another table or index with this name: {new_name}"
-                )));
-            };
-
-            let sqlite_schema = schema
-                .get_btree_table(SQLITE_TABLEID)
-                .expect("sqlite_schema should be on schema");
-
-            let cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(sqlite_schema.clone()));
-
-            program.emit_insn(Insn::OpenWrite {
-                cursor_id,
-                root_page: RegisterOrLiteral::Literal(sqlite_schema.root_page),
-                db: 0,
-            });
-
-            program.cursor_loop(cursor_id, |program, rowid| {
-                let sqlite_schema_column_len = sqlite_schema.columns.len();
-                assert_eq!(sqlite_schema_column_len, 5);
-
-                let first_column = program.alloc_registers(sqlite_schema_column_len);
-
-                for i in 0..sqlite_schema_column_len {
-                    program.emit_column_or_rowid(cursor_id, i, first_column + i);
-                }
-
-                program.emit_string8_new_reg(table_name.to_string());
-                program.mark_last_insn_constant();
-
-                program.emit_string8_new_reg(new_name.to_string());
-                program.mark_last_insn_constant();
-
-                let out = program.alloc_registers(5);
-
-                program.emit_insn(Insn::Function {
-                    constant_mask: 0,
-                    start_reg: first_column,
-                    dest: out,
-                    func: crate::function::FuncCtx {
-                        func: Func::AlterTable(AlterTableFunc::RenameTable),
-                        arg_count: 7,
-                    },
-                });
-
-                let record = program.alloc_register();
-
-                program.emit_insn(Insn::MakeRecord {
-                    start_reg: out,
-                    count: sqlite_schema_column_len,
-                    dest_reg: record,
-                    index_name: None,
-                });
-
-                program.emit_insn(Insn::Insert {
-                    cursor: cursor_id,
-                    key_reg: rowid,
-                    record_reg: record,
-                    flag: crate::vdbe::insn::InsertFlags(0),
-                    table_name: table_name.to_string(),
-                });
-            });
-
-            program.emit_insn(Insn::SetCookie {
-                db: 0,
-                cookie: Cookie::SchemaVersion,
-                value: schema.schema_version as i32 + 1,
-                p5: 0,
-            });
-
-            program.emit_insn(Insn::RenameTable {
-                from: table_name.to_owned(),
-                to: new_name.to_owned(),
-            });
-
+            emit_rename_table(&mut program, schema, db, table_name, new_name.as_str())?;
             program
         }
         body @ (ast::AlterTableBody::AlterColumn { .. }
@@ -356,6 +361,24 @@ pub fn translate_alter_table(
                 )));
             };
 
+            if btree.references_column(column_index) {
+                return Err(LimboError::ParseError(format!(
+                    "cannot alter column \"{from}\": used by a CHECK constraint or generated column"
+                )));
+            }
+
+            if let Some(index) = schema.get_indices(table_name).iter().find(|index| {
+                index.has_expressions()
+                    && index
+                        .referenced_columns(&original_btree)
+                        .contains(&column_index)
+            }) {
+                return Err(LimboError::ParseError(format!(
+                    "cannot alter column \"{from}\": used by index {}",
+                    index.name
+                )));
+            }
+
             if btree.get_column(col_name).is_some() {
                 return Err(LimboError::ParseError(format!(
                     "duplicate column name: \"{col_name}\""
@@ -391,7 +414,7 @@ pub fn translate_alter_table(
             program.emit_insn(Insn::OpenWrite {
                 cursor_id,
                 root_page: RegisterOrLiteral::Literal(sqlite_schema.root_page),
-                db: 0,
+                db,
             });
 
             program.cursor_loop(cursor_id, |program, rowid| {
@@ -448,12 +471,13 @@ pub fn translate_alter_table(
             });
 
             program.emit_insn(Insn::SetCookie {
-                db: 0,
+                db,
                 cookie: Cookie::SchemaVersion,
                 value: schema.schema_version as i32 + 1,
                 p5: 0,
             });
             program.emit_insn(Insn::AlterColumn {
+                db,
                 table: table_name.to_owned(),
                 column_index,
                 definition,
@@ -464,3 +488,98 @@ pub fn translate_alter_table(
         }
     })
 }
+
+/// Emits the instructions renaming `table_name` to `new_name` in the schema table of database
+/// `db` and in its in-memory schema.
+fn emit_rename_table(
+    program: &mut ProgramBuilder,
+    schema: &Schema,
+    db: usize,
+    table_name: &str,
+    new_name: &str,
+) -> Result<()> {
+    if schema.get_table(new_name).is_some()
+        || schema
+            .indexes
+            .values()
+            .flatten()
+            .any(|index| index.name == normalize_ident(new_name))
+    {
+        return Err(LimboError::ParseError(format!(
+            "there is already another table or index with this name: {new_name}"
+        )));
+    };
+
+    let sqlite_schema = schema
+        .get_btree_table(SQLITE_TABLEID)
+        .expect("sqlite_schema should be on schema");
+
+    let cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(sqlite_schema.clone()));
+
+    program.emit_insn(Insn::OpenWrite {
+        cursor_id,
+        root_page: RegisterOrLiteral::Literal(sqlite_schema.root_page),
+        db,
+    });
+
+    program.cursor_loop(cursor_id, |program, rowid| {
+        let sqlite_schema_column_len = sqlite_schema.columns.len();
+        assert_eq!(sqlite_schema_column_len, 5);
+
+        let first_column = program.alloc_registers(sqlite_schema_column_len);
+
+        for i in 0..sqlite_schema_column_len {
+            program.emit_column_or_rowid(cursor_id, i, first_column + i);
+        }
+
+        program.emit_string8_new_reg(table_name.to_string());
+        program.mark_last_insn_constant();
+
+        program.emit_string8_new_reg(new_name.to_string());
+        program.mark_last_insn_constant();
+
+        let out = program.alloc_registers(5);
+
+        program.emit_insn(Insn::Function {
+            constant_mask: 0,
+            start_reg: first_column,
+            dest: out,
+            func: crate::function::FuncCtx {
+                func: Func::AlterTable(AlterTableFunc::RenameTable),
+                arg_count: 7,
+            },
+        });
+
+        let record = program.alloc_register();
+
+        program.emit_insn(Insn::MakeRecord {
+            start_reg: out,
+            count: sqlite_schema_column_len,
+            dest_reg: record,
+            index_name: None,
+        });
+
+        program.emit_insn(Insn::Insert {
+            cursor: cursor_id,
+            key_reg: rowid,
+            record_reg: record,
+            flag: crate::vdbe::insn::InsertFlags(0),
+            table_name: table_name.to_string(),
+        });
+    });
+
+    program.emit_insn(Insn::SetCookie {
+        db,
+        cookie: Cookie::SchemaVersion,
+        value: schema.schema_version as i32 + 1,
+        p5: 0,
+    });
+
+    program.emit_insn(Insn::RenameTable {
+        db,
+        from: table_name.to_owned(),
+        to: new_name.to_owned(),
+    });
+
+    Ok(())
+}
@@ -128,7 +128,7 @@ pub fn translate_analyze(
         let sql = "CREATE TABLE sqlite_stat1(tbl,idx,stat)";
         // The root_page==0 is false, but we don't rely on it, and there's no
         // way to initialize it with a correct value.
-        sqlite_stat1_btreetable = Arc::new(BTreeTable::from_sql(sql, 0)?);
+        sqlite_stat1_btreetable = Arc::new(BTreeTable::from_sql(sql, 0, syms)?);
         sqlite_stat1_source = RegisterOrLiteral::Register(table_root_reg);
 
         let table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
@@ -157,7 +157,7 @@ pub fn translate_analyze(
         let parse_schema_where_clause =
             "tbl_name = 'sqlite_stat1' AND type != 'trigger'".to_string();
         program.emit_insn(Insn::ParseSchema {
-            db: sqlite_schema_cursor_id,
+            db: 0,
             where_clause: Some(parse_schema_where_clause),
         });
     };
@@ -0,0 +1,47 @@
+//! CHECK constraints.
+
+use crate::error::SQLITE_CONSTRAINT_CHECK;
+use crate::schema::BTreeTable;
+use crate::translate::emitter::Resolver;
+use crate::translate::expr::{translate_expr_no_constant_opt, NoConstantOptReason};
+use crate::translate::generated::rewrite_row_references;
+use crate::translate::trigger::TriggerRow;
+use crate::vdbe::builder::ProgramBuilder;
+use crate::vdbe::insn::Insn;
+use crate::Result;
+
+/// Evaluates the CHECK constraints of `table` against the row in the registers of `row`, and
+/// halts the statement if one of them is false. Like in SQLite a constraint that evaluates to
+/// NULL is satisfied.
+pub fn emit_check_constraints(
+    program: &mut ProgramBuilder,
+    resolver: &Resolver,
+    table: &BTreeTable,
+    row: TriggerRow,
+) -> Result<()> {
+    for check in &table.check_constraints {
+        let mut expr = check.expr.as_ref().clone();
+        rewrite_row_references(&mut expr, table, row)?;
+        let reg = program.alloc_register();
+        translate_expr_no_constant_opt(
+            program,
+            None,
+            &expr,
+            reg,
+            resolver,
+            NoConstantOptReason::RegisterReuse,
+        )?;
+        let check_passed = program.allocate_label();
+        program.emit_insn(Insn::If {
+            reg,
+            target_pc: check_passed,
+            jump_if_null: true,
+        });
+        program.emit_insn(Insn::Halt {
+            err_code: SQLITE_CONSTRAINT_CHECK,
+            description: check.description(),
+        });
+        program.preassign_label_to_next_insn(check_passed);
+    }
+    Ok(())
+}
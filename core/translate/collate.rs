@@ -1,13 +1,44 @@
-use std::{cmp::Ordering, str::FromStr as _};
+use std::{cmp::Ordering, fmt, sync::Arc};
 
 use tracing::Level;
 
-// TODO: in the future allow user to define collation sequences
-// Will have to meddle with ffi for this
-#[derive(
-    Debug, Clone, Copy, Eq, PartialEq, strum_macros::Display, strum_macros::EnumString, Default,
-)]
-#[strum(ascii_case_insensitive)]
+use crate::SymbolTable;
+
+/// Comparison function of a user defined collation sequence.
+pub type CollationFn = dyn Fn(&str, &str) -> Ordering + Send + Sync;
+
+/// A collation sequence registered on a connection, see [crate::Connection::create_collation].
+pub struct CustomCollation {
+    name: String,
+    compare: Box<CollationFn>,
+}
+
+impl CustomCollation {
+    pub fn new(name: &str, compare: Box<CollationFn>) -> Self {
+        Self {
+            name: name.to_string(),
+            compare,
+        }
+    }
+}
+
+impl fmt::Debug for CustomCollation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomCollation").field(&self.name).finish()
+    }
+}
+
+/// A registered collation sequence is only equal to itself: registering its name again can
+/// change the order of the text it compares.
+impl PartialEq for CustomCollation {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for CustomCollation {}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
 /// **Pre defined collation sequences**\
 /// Collating functions only matter when comparing string values.
 /// Numeric values are always compared numerically, and BLOBs are always compared byte-by-byte using memcmp().
@@ -19,13 +50,52 @@ pub enum CollationSeq {
     NoCase,
     /// Same as Binary but with trimmed whitespace
     Rtrim,
+    /// Collation sequence registered on the connection
+    Custom(Arc<CustomCollation>),
 }
 
+const NO_SUCH_COLLATION: &str = "no such collation sequence: ";
+
 impl CollationSeq {
-    pub fn new(collation: &str) -> crate::Result<Self> {
-        CollationSeq::from_str(collation).map_err(|_| {
-            crate::LimboError::ParseError(format!("no such collation sequence: {collation}"))
-        })
+    /// Resolves a collation sequence by name, looking up the collation sequences registered on
+    /// the connection in `syms` if it isn't a built-in one.
+    pub fn new(collation: &str, syms: &SymbolTable) -> crate::Result<Self> {
+        Self::builtin(collation)
+            .or_else(|| {
+                syms.collations
+                    .get(&collation.to_ascii_lowercase())
+                    .cloned()
+                    .map(CollationSeq::Custom)
+            })
+            .ok_or_else(|| crate::LimboError::ParseError(format!("{NO_SUCH_COLLATION}{collation}")))
+    }
+
+    /// Returns the name of the collation sequence that `err` reports as not registered, if it
+    /// is the error of [CollationSeq::new] for an unknown name.
+    pub fn missing(err: &crate::LimboError) -> Option<&str> {
+        match err {
+            crate::LimboError::ParseError(msg) => msg.strip_prefix(NO_SUCH_COLLATION),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this is the collation sequence registered under `name`.
+    pub fn is_custom_named(&self, name: &str) -> bool {
+        matches!(self, CollationSeq::Custom(collation) if collation.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn is_builtin(collation: &str) -> bool {
+        Self::builtin(collation).is_some()
+    }
+
+    fn builtin(collation: &str) -> Option<Self> {
+        [
+            CollationSeq::Binary,
+            CollationSeq::NoCase,
+            CollationSeq::Rtrim,
+        ]
+        .into_iter()
+        .find(|seq| seq.to_string().eq_ignore_ascii_case(collation))
     }
 
     pub fn compare_strings(&self, lhs: &str, rhs: &str) -> Ordering {
@@ -34,6 +104,7 @@ impl CollationSeq {
             CollationSeq::Binary => Self::binary_cmp(lhs, rhs),
             CollationSeq::NoCase => Self::nocase_cmp(lhs, rhs),
             CollationSeq::Rtrim => Self::rtrim_cmp(lhs, rhs),
+            CollationSeq::Custom(collation) => (collation.compare)(lhs, rhs),
         }
     }
 
@@ -51,3 +122,58 @@ impl CollationSeq {
         lhs.trim_end().cmp(rhs.trim_end())
     }
 }
+
+impl fmt::Display for CollationSeq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollationSeq::Binary => f.write_str("Binary"),
+            CollationSeq::NoCase => f.write_str("NoCase"),
+            CollationSeq::Rtrim => f.write_str("Rtrim"),
+            CollationSeq::Custom(collation) => f.write_str(&collation.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_collation() {
+        let mut syms = SymbolTable::new();
+        syms.register_collation(
+            "test_by_len",
+            Box::new(|lhs: &str, rhs: &str| lhs.len().cmp(&rhs.len())),
+        )
+        .unwrap();
+        let seq = CollationSeq::new("TEST_BY_LEN", &syms).unwrap();
+        assert_eq!(seq.to_string(), "test_by_len");
+        assert_eq!(seq.compare_strings("zz", "aaa"), Ordering::Less);
+
+        syms.register_collation("test_by_len", Box::new(|lhs: &str, rhs: &str| rhs.cmp(lhs)))
+            .unwrap();
+        let reversed = CollationSeq::new("test_by_len", &syms).unwrap();
+        assert_ne!(reversed, seq);
+        assert!(reversed.is_custom_named("TEST_BY_LEN"));
+        assert_eq!(reversed.compare_strings("zz", "aaa"), Ordering::Less);
+        assert_eq!(reversed.compare_strings("aaa", "zz"), Ordering::Greater);
+
+        // Collation sequences are registered per connection.
+        assert!(CollationSeq::new("test_by_len", &SymbolTable::new()).is_err());
+    }
+
+    #[test]
+    fn test_builtin_collations() {
+        let syms = SymbolTable::new();
+        assert_eq!(
+            CollationSeq::new("nocase", &syms).unwrap(),
+            CollationSeq::NoCase
+        );
+        let err = CollationSeq::new("no_such_collation", &syms).unwrap_err();
+        assert_eq!(CollationSeq::missing(&err), Some("no_such_collation"));
+        let binary = Box::new(|lhs: &str, rhs: &str| lhs.cmp(rhs));
+        assert!(SymbolTable::new()
+            .register_collation("BINARY", binary)
+            .is_err());
+    }
+}
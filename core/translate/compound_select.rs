@@ -358,6 +358,7 @@ fn create_dedupe_index(
                 pos_in_table: 0,
                 default: None,
                 collation: None, // FIXME: this should be inferred
+                expr: None,
             })
             .collect(),
         name: "compound_dedupe".to_string(),
@@ -366,6 +367,9 @@ fn create_dedupe_index(
         table_name: String::new(),
         unique: false,
         has_rowid: false,
+        where_clause: None,
+        non_key_columns: 0,
+        temporary: false,
     });
     let cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(dedupe_index.clone()));
     program.emit_insn(Insn::OpenEphemeral {
@@ -1,19 +1,20 @@
 use crate::schema::Table;
 use crate::translate::emitter::emit_program;
+use crate::translate::foreign_key::{prepare_foreign_keys, ForeignKeyPlan};
 use crate::translate::optimizer::optimize_plan;
 use crate::translate::plan::{DeletePlan, Operation, Plan};
-use crate::translate::planner::{parse_limit, parse_where};
+use crate::translate::planner::{parse_limit, parse_where, resolve_write_target};
+use crate::translate::trigger::{compile_triggers, translate_instead_of_delete, TriggerPrograms};
 use crate::util::normalize_ident;
 use crate::vdbe::builder::{ProgramBuilder, ProgramBuilderOpts, TableRefIdCounter};
 use crate::{schema::Schema, Result, SymbolTable};
 use std::sync::Arc;
-use turso_parser::ast::{Expr, Limit, QualifiedName, ResultColumn};
+use turso_parser::ast::{self, Expr, Limit, QualifiedName, ResultColumn};
 
 use super::plan::{ColumnUsedMask, JoinedTable, TableReferences};
 
 #[allow(clippy::too_many_arguments)]
 pub fn translate_delete(
-    schema: &Schema,
     tbl_name: &QualifiedName,
     where_clause: Option<Box<Expr>>,
     limit: Option<Limit>,
@@ -22,7 +23,22 @@ pub fn translate_delete(
     mut program: ProgramBuilder,
     connection: &Arc<crate::Connection>,
 ) -> Result<ProgramBuilder> {
+    let (database_id, target_schema) = resolve_write_target(connection, tbl_name)?;
+    let schema = target_schema.as_ref();
     let tbl_name = normalize_ident(tbl_name.name.as_str());
+    if schema.get_view(&tbl_name).is_some() {
+        if !returning.is_empty() || limit.is_some() {
+            crate::bail_parse_error!("RETURNING and LIMIT are not supported for views");
+        }
+        return translate_instead_of_delete(
+            schema,
+            &tbl_name,
+            where_clause,
+            syms,
+            program,
+            connection,
+        );
+    }
     if schema.table_has_indexes(&tbl_name) && !schema.indexes_enabled() {
         // Let's disable altering a table with indices altogether instead of checking column by
         // column to be extra safe.
@@ -43,12 +59,38 @@ pub fn translate_delete(
     let mut delete_plan = prepare_delete_plan(
         schema,
         tbl_name,
+        database_id,
         where_clause,
         limit,
         result_columns,
         &mut program.table_reference_counter,
         connection,
     )?;
+    if let Plan::Delete(delete) = &mut delete_plan {
+        let table = &delete.table_references.joined_tables()[0].table;
+        if let Some(btree) = table.btree() {
+            let triggers = compile_triggers(
+                schema,
+                syms,
+                connection,
+                &program,
+                table.get_name(),
+                table.columns(),
+                ast::TriggerEvent::Delete,
+                &[],
+            )?;
+            delete.triggers = triggers;
+            delete.foreign_keys = prepare_foreign_keys(
+                schema,
+                syms,
+                connection,
+                &program,
+                &btree,
+                ast::TriggerEvent::Delete,
+                &[],
+            )?;
+        }
+    }
     optimize_plan(&mut delete_plan, schema)?;
     let Plan::Delete(ref delete) = delete_plan else {
         panic!("delete_plan is not a DeletePlan");
@@ -63,9 +105,11 @@ pub fn translate_delete(
     Ok(program)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn prepare_delete_plan(
     schema: &Schema,
     tbl_name: String,
+    database_id: usize,
     where_clause: Option<Box<Expr>>,
     limit: Option<Limit>,
     result_columns: Vec<super::plan::ResultSetColumn>,
@@ -91,7 +135,7 @@ pub fn prepare_delete_plan(
         internal_id: table_ref_counter.next(),
         join_info: None,
         col_used_mask: ColumnUsedMask::default(),
-        database_id: 0,
+        database_id,
     }];
     let mut table_references = TableReferences::new(joined_tables, vec![]);
 
@@ -118,6 +162,8 @@ pub fn prepare_delete_plan(
         offset: resolved_offset,
         contains_constant_false_condition: false,
         indexes,
+        triggers: TriggerPrograms::default(),
+        foreign_keys: ForeignKeyPlan::default(),
     };
 
     Ok(Plan::Delete(plan))
@@ -13,6 +13,7 @@ use super::group_by::{
 };
 use super::main_loop::{
     close_loop, emit_loop, init_distinct, init_loop, open_loop, LeftJoinMetadata, LoopLabels,
+    RightJoinMetadata,
 };
 use super::order_by::{emit_order_by, init_order_by, SortMetadata};
 use super::plan::{
@@ -23,9 +24,22 @@ use super::subquery::emit_subqueries;
 use crate::error::SQLITE_CONSTRAINT_PRIMARYKEY;
 use crate::function::Func;
 use crate::schema::{BTreeTable, Column, Schema, Table};
+use crate::translate::check::emit_check_constraints;
 use crate::translate::compound_select::emit_program_for_compound_select;
 use crate::translate::expr::{emit_returning_results, ReturningValueRegisters};
+use crate::translate::foreign_key::{
+    emit_child_key_checks, emit_foreign_key_actions, emit_parent_key_checks, ForeignKeyPlan,
+};
+use crate::translate::generated::{
+    emit_generated_columns, emit_record_registers, emit_table_column,
+};
+use crate::translate::index::{
+    emit_index_column_values, emit_index_primary_key, emit_index_row, emit_partial_index_check,
+    emit_primary_key, emit_primary_key_change_check, emit_primary_key_seek,
+    unique_index_description,
+};
 use crate::translate::plan::{DeletePlan, Plan, QueryDestination, Search};
+use crate::translate::trigger::{emit_old_row, emit_trigger_programs, TriggerPrograms, TriggerRow};
 use crate::translate::values::emit_values;
 use crate::util::exprs_are_equivalent;
 use crate::vdbe::builder::{CursorKey, CursorType, ProgramBuilder};
@@ -132,6 +146,9 @@ pub struct TranslateCtx<'a> {
     /// mapping between table loop index and associated metadata (for left joins only)
     /// this metadata exists for the right table in a given left join
     pub meta_left_joins: Vec<Option<LeftJoinMetadata>>,
+    /// mapping between table loop index and associated metadata (for right joins only)
+    /// this metadata exists for the right table in a given right join
+    pub meta_right_joins: Vec<Option<RightJoinMetadata>>,
     pub resolver: Resolver<'a>,
     /// A list of expressions that are not aggregates, along with a flag indicating
     /// whether the expression should be included in the output for each group.
@@ -166,6 +183,7 @@ impl<'a> TranslateCtx<'a> {
             reg_result_cols_start: None,
             meta_group_by: None,
             meta_left_joins: (0..table_count).map(|_| None).collect(),
+            meta_right_joins: (0..table_count).map(|_| None).collect(),
             meta_sort: None,
             resolver: Resolver::new(schema, syms),
             non_aggregate_expressions: Vec::new(),
@@ -205,7 +223,7 @@ pub fn emit_program(
     match plan {
         Plan::Select(plan) => emit_program_for_select(program, plan, schema, syms),
         Plan::Delete(plan) => emit_program_for_delete(program, plan, schema, syms),
-        Plan::Update(plan) => emit_program_for_update(program, plan, schema, syms, after),
+        Plan::Update(plan) => emit_program_for_update(program, *plan, schema, syms, after),
         Plan::CompoundSelect { .. } => {
             emit_program_for_compound_select(program, plan, schema, syms)
         }
@@ -234,6 +252,17 @@ fn emit_program_for_select(
             return Ok(());
         }
     }
+    // When the query yields its rows to a coroutine (e.g. within an INSERT clause), the parent
+    // reads them from the registers that follow the yield register, so allocate them before the
+    // subqueries of the FROM clause allocate theirs.
+    if plan.values.is_empty()
+        && matches!(
+            plan.query_destination,
+            QueryDestination::CoroutineYield { .. }
+        )
+    {
+        t_ctx.reg_result_cols_start = Some(program.alloc_registers(plan.result_columns.len()));
+    }
     // Emit main parts of query
     emit_query(program, &mut plan, &mut t_ctx)?;
 
@@ -447,6 +476,8 @@ fn emit_program_for_delete(
         &mut t_ctx,
         &plan.table_references,
         &plan.result_columns,
+        &plan.triggers,
+        &plan.foreign_keys,
     )?;
 
     // Clean up and close the main execution loop
@@ -470,6 +501,8 @@ fn emit_delete_insns(
     t_ctx: &mut TranslateCtx,
     table_references: &TableReferences,
     result_columns: &[super::plan::ResultSetColumn],
+    triggers: &TriggerPrograms,
+    foreign_keys: &ForeignKeyPlan,
 ) -> Result<()> {
     let table_reference = table_references.joined_tables().first().unwrap();
     if table_reference
@@ -514,6 +547,7 @@ fn emit_delete_insns(
             dest: new_rowid_reg,
             dest_end: None,
         });
+        program.emit_insn(Insn::VBegin { cursor_id });
         program.emit_insn(Insn::VUpdate {
             cursor_id,
             arg_count: 2,
@@ -521,6 +555,57 @@ fn emit_delete_insns(
             conflict_action,
         });
     } else {
+        let next_row_label = t_ctx.labels_main_loop.first().unwrap().next;
+        let btree_table = table_reference
+            .btree()
+            .expect("DELETE target must be a btree table");
+        let old_row = if triggers.is_empty() && foreign_keys.is_empty() {
+            None
+        } else {
+            let old_row = emit_old_row(
+                program,
+                main_table_cursor_id,
+                table_reference.columns(),
+                key_reg,
+            );
+            if btree_table.has_virtual_columns() {
+                emit_generated_columns(program, &t_ctx.resolver, &btree_table, old_row)?;
+            }
+            Some(old_row)
+        };
+        if !triggers.before.is_empty() {
+            emit_trigger_programs(
+                program,
+                &triggers.before,
+                table_reference.columns(),
+                None,
+                old_row,
+                next_row_label,
+            );
+            // A BEFORE trigger may have deleted the row or moved the cursor, so seek it again.
+            if btree_table.has_rowid {
+                program.emit_insn(Insn::NotExists {
+                    cursor: main_table_cursor_id,
+                    rowid_reg: key_reg,
+                    target_pc: next_row_label,
+                });
+            } else {
+                let old_row = old_row.expect("old row is read when there are triggers");
+                let key_start = emit_primary_key(program, &btree_table, old_row);
+                emit_primary_key_seek(
+                    program,
+                    &btree_table,
+                    main_table_cursor_id,
+                    key_start,
+                    next_row_label,
+                );
+            }
+        }
+        if let Some(old_row) = old_row.filter(|_| !foreign_keys.is_empty()) {
+            emit_parent_key_checks(program, foreign_keys, old_row, 1);
+            emit_child_key_checks(program, foreign_keys, old_row, -1);
+        }
+
         // Delete from all indexes before deleting from the main table.
         let indexes = t_ctx
             .resolver
@@ -544,30 +629,48 @@ fn emit_delete_insns(
 
         if let Some(index_refs) = index_refs_opt {
             for (index, index_cursor_id) in index_refs {
-                let num_regs = index.columns.len() + 1;
+                // the index record ends with the rowid, if the table has one
+                let num_regs = index.columns.len() + btree_table.has_rowid as usize;
+                let row = emit_index_row(
+                    program,
+                    &t_ctx.resolver,
+                    main_table_cursor_id,
+                    &btree_table,
+                    &index,
+                )?;
+                // A row that is not part of a partial index has no entry to delete.
+                let skip_index_label = program.allocate_label();
+                emit_partial_index_check(
+                    program,
+                    &t_ctx.resolver,
+                    &btree_table,
+                    &index,
+                    row,
+                    skip_index_label,
+                )?;
                 let start_reg = program.alloc_registers(num_regs);
                 // Emit columns that are part of the index
-                index
-                    .columns
-                    .iter()
-                    .enumerate()
-                    .for_each(|(reg_offset, column_index)| {
-                        program.emit_column_or_rowid(
-                            main_table_cursor_id,
-                            column_index.pos_in_table,
-                            start_reg + reg_offset,
-                        );
+                emit_index_column_values(
+                    program,
+                    &t_ctx.resolver,
+                    &btree_table,
+                    &index,
+                    row,
+                    start_reg,
+                )?;
+                if btree_table.has_rowid {
+                    program.emit_insn(Insn::RowId {
+                        cursor_id: main_table_cursor_id,
+                        dest: start_reg + num_regs - 1,
                     });
-                program.emit_insn(Insn::RowId {
-                    cursor_id: main_table_cursor_id,
-                    dest: start_reg + num_regs - 1,
-                });
+                }
                 program.emit_insn(Insn::IdxDelete {
                     start_reg,
                     num_regs,
                     cursor_id: index_cursor_id,
                     raise_error_if_no_matching_entry: true,
                 });
+                program.preassign_label_to_next_insn(skip_index_label);
             }
         }
 
@@ -615,8 +718,15 @@ fn emit_delete_insns(
             let columns_start_reg = program.alloc_registers(table_reference.columns().len());
 
             // Read all column values from the row to be deleted
-            for (i, _column) in table_reference.columns().iter().enumerate() {
-                program.emit_column_or_rowid(main_table_cursor_id, i, columns_start_reg + i);
+            for i in 0..table_reference.columns().len() {
+                emit_table_column(
+                    program,
+                    &t_ctx.resolver,
+                    main_table_cursor_id,
+                    &btree_table,
+                    i,
+                    columns_start_reg + i,
+                )?;
             }
 
             // Emit RETURNING results using the values we just read
@@ -633,6 +743,23 @@ fn emit_delete_insns(
             cursor_id: main_table_cursor_id,
             table_name: table_reference.table.get_name().to_string(),
         });
+
+        emit_foreign_key_actions(
+            program,
+            foreign_keys,
+            table_reference.columns(),
+            None,
+            old_row,
+            next_row_label,
+        );
+        emit_trigger_programs(
+            program,
+            &triggers.after,
+            table_reference.columns(),
+            None,
+            old_row,
+            next_row_label,
+        );
     }
     if let Some(limit_ctx) = t_ctx.limit_ctx {
         program.emit_insn(Insn::DecrJumpZero {
@@ -676,21 +803,24 @@ fn emit_program_for_update(
     }
 
     let ephemeral_plan = plan.ephemeral_plan.take();
-    let temp_cursor_id = ephemeral_plan.as_ref().map(|plan| {
-        let QueryDestination::EphemeralTable { cursor_id, .. } = &plan.query_destination else {
-            unreachable!()
+    let temp_cursor_id = if let Some(ephemeral_plan) = ephemeral_plan {
+        let (cursor_id, is_table) = match &ephemeral_plan.query_destination {
+            QueryDestination::EphemeralTable { cursor_id, .. } => (*cursor_id, true),
+            // The PRIMARY KEYs of the rows of a WITHOUT ROWID table
+            QueryDestination::EphemeralIndex { cursor_id, .. } => (*cursor_id, false),
+            _ => unreachable!(),
         };
-        *cursor_id
-    });
-    if let Some(ephemeral_plan) = ephemeral_plan {
         program.emit_insn(Insn::OpenEphemeral {
-            cursor_id: temp_cursor_id.unwrap(),
-            is_table: true,
+            cursor_id,
+            is_table,
         });
         program.incr_nesting();
         emit_program_for_select(program, ephemeral_plan, schema, syms)?;
         program.decr_nesting();
-    }
+        Some(cursor_id)
+    } else {
+        None
+    };
 
     // Initialize the main loop
     init_loop(
@@ -704,23 +834,19 @@ fn emit_program_for_update(
     )?;
 
     // Prepare index cursors
+    let target_table = plan.table_references.joined_tables().first().unwrap();
     let mut index_cursors = Vec::with_capacity(plan.indexes_to_update.len());
     for index in &plan.indexes_to_update {
-        let index_cursor = if let Some(cursor) = program.resolve_cursor_id_safe(&CursorKey::index(
-            plan.table_references
-                .joined_tables()
-                .first()
-                .unwrap()
-                .internal_id,
-            index.clone(),
-        )) {
+        let index_cursor = if let Some(cursor) = program
+            .resolve_cursor_id_safe(&CursorKey::index(target_table.internal_id, index.clone()))
+        {
             cursor
         } else {
             let cursor = program.alloc_cursor_id(CursorType::BTreeIndex(index.clone()));
             program.emit_insn(Insn::OpenWrite {
                 cursor_id: cursor,
                 root_page: RegisterOrLiteral::Literal(index.root_page),
-                db: 0,
+                db: target_table.database_id,
             });
             cursor
         };
@@ -805,10 +931,29 @@ fn emit_update_insns(
                 1 // rowid reg
             },
     );
-    program.emit_insn(Insn::RowId {
-        cursor_id: temp_cursor_id.unwrap_or(cursor_id),
-        dest: beg,
-    });
+    let without_rowid = table_ref.btree().is_some_and(|btree| !btree.has_rowid);
+    if let Some(btree) = table_ref.btree().filter(|_| without_rowid) {
+        // A WITHOUT ROWID table has no rowid. If its PRIMARY KEY is updated, the keys of the
+        // rows to update were collected first, so the row of each key is sought.
+        program.emit_null(beg, None);
+        if let Some(temp_cursor_id) = temp_cursor_id {
+            let key_start = program.alloc_registers(btree.primary_key_columns.len());
+            for i in 0..btree.primary_key_columns.len() {
+                program.emit_insn(Insn::Column {
+                    cursor_id: temp_cursor_id,
+                    column: i,
+                    dest: key_start + i,
+                    default: None,
+                });
+            }
+            emit_primary_key_seek(program, &btree, cursor_id, key_start, loop_labels.next);
+        }
+    } else {
+        program.emit_insn(Insn::RowId {
+            cursor_id: temp_cursor_id.unwrap_or(cursor_id),
+            dest: beg,
+        });
+    }
 
     // Check if rowid was provided (through INTEGER PRIMARY KEY as a rowid alias)
     let rowid_alias_index = table_ref.columns().iter().position(|c| c.is_rowid_alias);
@@ -838,7 +983,7 @@ fn emit_update_insns(
             rowid_reg: beg,
             target_pc: check_rowid_not_exists_label.unwrap(),
         });
-    } else {
+    } else if !without_rowid {
         // if no rowid, we're done
         program.emit_insn(Insn::IsNull {
             reg: beg,
@@ -862,6 +1007,23 @@ fn emit_update_insns(
         });
     }
 
+    let old_row = if (plan.triggers.is_empty() && plan.foreign_keys.is_empty()) || is_virtual {
+        None
+    } else {
+        let old_row = emit_old_row(program, cursor_id, table_ref.columns(), beg);
+        if let Some(btree) = table_ref
+            .btree()
+            .filter(|btree| btree.has_virtual_columns())
+        {
+            emit_generated_columns(program, &t_ctx.resolver, &btree, old_row)?;
+        }
+        Some(old_row)
+    };
+    let new_row = TriggerRow {
+        rowid_reg: rowid_set_clause_reg.unwrap_or(beg),
+        columns_start: if is_virtual { beg + 2 } else { beg + 1 },
+    };
+
     // we scan a column at a time, loading either the column's values, or the new value
     // from the Set expression, into registers so we can emit a MakeRecord and update the row.
 
@@ -986,10 +1148,50 @@ fn emit_update_insns(
         }
     }
 
+    let btree_table = table_ref.btree().filter(|_| !is_virtual);
+    if let Some(btree_table) = &btree_table {
+        emit_generated_columns(program, &t_ctx.resolver, btree_table, new_row)?;
+    }
+
+    if !plan.triggers.before.is_empty() && !is_virtual {
+        emit_trigger_programs(
+            program,
+            &plan.triggers.before,
+            table_ref.columns(),
+            Some(new_row),
+            old_row,
+            loop_labels.next,
+        );
+        // A BEFORE trigger may have deleted the row or moved the cursor, so seek it again.
+        match btree_table.as_ref().filter(|_| without_rowid) {
+            Some(btree_table) => {
+                let old_row = old_row.expect("old row is read when there are triggers");
+                let key_start = emit_primary_key(program, btree_table, old_row);
+                emit_primary_key_seek(program, btree_table, cursor_id, key_start, loop_labels.next);
+            }
+            None => program.emit_insn(Insn::NotExists {
+                cursor: cursor_id,
+                rowid_reg: beg,
+                target_pc: loop_labels.next,
+            }),
+        }
+    }
+    if let Some(btree_table) = &btree_table {
+        emit_check_constraints(program, &t_ctx.resolver, btree_table, new_row)?;
+    }
+    if let Some(old_row) = old_row.filter(|_| !plan.foreign_keys.is_empty()) {
+        emit_child_key_checks(program, &plan.foreign_keys, old_row, -1);
+        emit_parent_key_checks(program, &plan.foreign_keys, old_row, 1);
+        emit_child_key_checks(program, &plan.foreign_keys, new_row, 1);
+        emit_parent_key_checks(program, &plan.foreign_keys, new_row, -1);
+    }
+
     for (index, (idx_cursor_id, record_reg)) in plan.indexes_to_update.iter().zip(&index_cursors) {
         let num_cols = index.columns.len();
+        // the index record ends with the rowid, if the table has one
+        let num_regs = num_cols + !without_rowid as usize;
         // allocate scratch registers for the index columns plus rowid
-        let idx_start_reg = program.alloc_registers(num_cols + 1);
+        let idx_start_reg = program.alloc_registers(num_regs);
 
         // Use the new rowid value (if the UPDATE statement sets the rowid alias),
         // otherwise keep using the original rowid. This guarantees that any
@@ -1001,41 +1203,49 @@ fn emit_update_insns(
         } else {
             beg
         };
-        let idx_cols_start_reg = beg + 1;
-
-        // copy each index column from the table's column registers into these scratch regs
-        for (i, col) in index.columns.iter().enumerate() {
-            let col_in_table = table_ref
-                .columns()
-                .get(col.pos_in_table)
-                .expect("column index out of bounds");
-            // copy from the table's column register over to the index's scratch register
+        let btree_table = btree_table
+            .as_ref()
+            .expect("indexes are only updated for btree tables");
+
+        // If the new row is not part of a partial index, it gets no index entry.
+        let skip_index_label = program.allocate_label();
+        emit_partial_index_check(
+            program,
+            &t_ctx.resolver,
+            btree_table,
+            index,
+            new_row,
+            skip_index_label,
+        )?;
+
+        // compute each index column from the table's column registers into these scratch regs
+        emit_index_column_values(
+            program,
+            &t_ctx.resolver,
+            btree_table,
+            index,
+            new_row,
+            idx_start_reg,
+        )?;
+        // last register is the rowid
+        if !without_rowid {
             program.emit_insn(Insn::Copy {
-                src_reg: if col_in_table.is_rowid_alias {
-                    rowid_reg
-                } else {
-                    idx_cols_start_reg + col.pos_in_table
-                },
-                dst_reg: idx_start_reg + i,
+                src_reg: rowid_reg,
+                dst_reg: idx_start_reg + num_cols,
                 extra_amount: 0,
             });
         }
-        // last register is the rowid
-        program.emit_insn(Insn::Copy {
-            src_reg: rowid_reg,
-            dst_reg: idx_start_reg + num_cols,
-            extra_amount: 0,
-        });
 
         // this record will be inserted into the index later
         program.emit_insn(Insn::MakeRecord {
             start_reg: idx_start_reg,
-            count: num_cols + 1,
+            count: num_regs,
             dest_reg: *record_reg,
             index_name: Some(index.name.clone()),
         });
 
         if !index.unique {
+            program.preassign_label_to_next_insn(skip_index_label);
             continue;
         }
 
@@ -1045,38 +1255,51 @@ fn emit_update_insns(
             cursor_id: *idx_cursor_id,
             target_pc: constraint_check,
             record_reg: idx_start_reg,
-            num_regs: num_cols,
+            num_regs: index.key_columns().len(),
         });
 
-        let column_names = index.columns.iter().enumerate().fold(
-            String::with_capacity(50),
-            |mut accum, (idx, col)| {
-                if idx > 0 {
-                    accum.push_str(", ");
-                }
-                accum.push_str(table_ref.table.get_name());
-                accum.push('.');
-                accum.push_str(&col.name);
-
-                accum
-            },
-        );
-
-        let idx_rowid_reg = program.alloc_register();
-        program.emit_insn(Insn::IdxRowId {
-            cursor_id: *idx_cursor_id,
-            dest: idx_rowid_reg,
-        });
+        let column_names = unique_index_description(index);
+
+        if without_rowid {
+            // Skip over the UNIQUE constraint failure if the existing row is the one that we are
+            // currently changing, i.e. the index entry has the PRIMARY KEY of the current row
+            let conflict_label = program.allocate_label();
+            let conflict_key = emit_index_primary_key(program, btree_table, index, *idx_cursor_id);
+            for (i, (name, _)) in btree_table.primary_key_columns.iter().enumerate() {
+                let (pos_in_table, column) = btree_table
+                    .get_column(name)
+                    .expect("primary key column must exist in the table");
+                let current_reg = program.alloc_register();
+                program.emit_column_or_rowid(cursor_id, pos_in_table, current_reg);
+                program.emit_insn(Insn::Ne {
+                    lhs: current_reg,
+                    rhs: conflict_key + i,
+                    target_pc: conflict_label,
+                    flags: CmpInsFlags::default(),
+                    collation: column.collation.clone(),
+                });
+            }
+            program.emit_insn(Insn::Goto {
+                target_pc: constraint_check,
+            });
+            program.preassign_label_to_next_insn(conflict_label);
+        } else {
+            let idx_rowid_reg = program.alloc_register();
+            program.emit_insn(Insn::IdxRowId {
+                cursor_id: *idx_cursor_id,
+                dest: idx_rowid_reg,
+            });
 
-        // Skip over the UNIQUE constraint failure if the existing row is the one that we are currently changing
-        let original_rowid_reg = beg;
-        program.emit_insn(Insn::Eq {
-            lhs: original_rowid_reg,
-            rhs: idx_rowid_reg,
-            target_pc: constraint_check,
-            flags: CmpInsFlags::default(), // TODO: not sure what type of comparison flag is needed
-            collation: program.curr_collation(),
-        });
+            // Skip over the UNIQUE constraint failure if the existing row is the one that we are currently changing
+            let original_rowid_reg = beg;
+            program.emit_insn(Insn::Eq {
+                lhs: original_rowid_reg,
+                rhs: idx_rowid_reg,
+                target_pc: constraint_check,
+                flags: CmpInsFlags::default(), // TODO: not sure what type of comparison flag is needed
+                collation: program.curr_collation(),
+            });
+        }
 
         program.emit_insn(Insn::Halt {
             err_code: SQLITE_CONSTRAINT_PRIMARYKEY, // TODO: distinct between primary key and unique index for error code
@@ -1084,6 +1307,7 @@ fn emit_update_insns(
         });
 
         program.preassign_label_to_next_insn(constraint_check);
+        program.preassign_label_to_next_insn(skip_index_label);
     }
 
     if let Some(btree_table) = table_ref.btree() {
@@ -1132,10 +1356,35 @@ fn emit_update_insns(
             program.preassign_label_to_next_insn(record_label);
         }
 
+        // The PRIMARY KEY of a WITHOUT ROWID table may be updated to the key of another row.
+        let primary_key_updated = plan
+            .set_clauses
+            .iter()
+            .any(|(idx, _)| btree_table.columns[*idx].primary_key);
+        if without_rowid && primary_key_updated {
+            let old_key = program.alloc_registers(btree_table.primary_key_columns.len());
+            for (i, (name, _)) in btree_table.primary_key_columns.iter().enumerate() {
+                let (pos_in_table, _) = btree_table
+                    .get_column(name)
+                    .expect("primary key column must exist in the table");
+                program.emit_column_or_rowid(cursor_id, pos_in_table, old_key + i);
+            }
+            let new_key = emit_primary_key(program, &btree_table, new_row);
+            emit_primary_key_change_check(
+                program,
+                &btree_table,
+                cursor_id,
+                old_key,
+                new_key,
+                loop_labels.next,
+            );
+        }
+
         let record_reg = program.alloc_register();
+        let (record_start_reg, record_count) = emit_record_registers(program, &btree_table, start);
         program.emit_insn(Insn::MakeRecord {
-            start_reg: start,
-            count: table_ref.columns().len(),
+            start_reg: record_start_reg,
+            count: record_count,
             dest_reg: record_reg,
             index_name: None,
         });
@@ -1151,26 +1400,37 @@ fn emit_update_insns(
         // For each index -> insert
         for (index, (idx_cursor_id, record_reg)) in plan.indexes_to_update.iter().zip(index_cursors)
         {
-            let num_regs = index.columns.len() + 1;
+            // the index record ends with the rowid, if the table has one
+            let num_regs = index.columns.len() + btree_table.has_rowid as usize;
+
+            // Delete existing index key, if the old row is part of the index
+            let old_index_row =
+                emit_index_row(program, &t_ctx.resolver, cursor_id, &btree_table, index)?;
+            let skip_delete_label = program.allocate_label();
+            emit_partial_index_check(
+                program,
+                &t_ctx.resolver,
+                &btree_table,
+                index,
+                old_index_row,
+                skip_delete_label,
+            )?;
             let start_reg = program.alloc_registers(num_regs);
+            emit_index_column_values(
+                program,
+                &t_ctx.resolver,
+                &btree_table,
+                index,
+                old_index_row,
+                start_reg,
+            )?;
 
-            // Delete existing index key
-            index
-                .columns
-                .iter()
-                .enumerate()
-                .for_each(|(reg_offset, column_index)| {
-                    program.emit_column_or_rowid(
-                        cursor_id,
-                        column_index.pos_in_table,
-                        start_reg + reg_offset,
-                    );
+            if btree_table.has_rowid {
+                program.emit_insn(Insn::RowId {
+                    cursor_id,
+                    dest: start_reg + num_regs - 1,
                 });
-
-            program.emit_insn(Insn::RowId {
-                cursor_id,
-                dest: start_reg + num_regs - 1,
-            });
+            }
 
             program.emit_insn(Insn::IdxDelete {
                 start_reg,
@@ -1178,15 +1438,27 @@ fn emit_update_insns(
                 cursor_id: idx_cursor_id,
                 raise_error_if_no_matching_entry: true,
             });
+            program.preassign_label_to_next_insn(skip_delete_label);
 
-            // Insert new index key (filled further above with values from set_clauses)
+            // Insert new index key (filled further above with values from set_clauses), if the
+            // new row is part of the index
+            let skip_insert_label = program.allocate_label();
+            emit_partial_index_check(
+                program,
+                &t_ctx.resolver,
+                &btree_table,
+                index,
+                new_row,
+                skip_insert_label,
+            )?;
             program.emit_insn(Insn::IdxInsert {
                 cursor_id: idx_cursor_id,
                 record_reg,
                 unpacked_start: Some(start),
-                unpacked_count: Some((index.columns.len() + 1) as u16),
+                unpacked_count: Some(num_regs as u16),
                 flags: IdxInsertFlags::new().nchange(true),
             });
+            program.preassign_label_to_next_insn(skip_insert_label);
         }
 
         // create alias for CDC rowid after the change (will differ from cdc_rowid_before_reg only in case of UPDATE with change in rowid alias)
@@ -1230,19 +1502,35 @@ fn emit_update_insns(
             });
         }
 
-        program.emit_insn(Insn::Insert {
-            cursor: cursor_id,
-            key_reg: rowid_set_clause_reg.unwrap_or(beg),
-            record_reg,
-            flag: if has_user_provided_rowid {
-                // The previous Insn::NotExists and Insn::Delete seek to the old rowid,
-                // so to insert a new user-provided rowid, we need to seek to the correct place.
-                InsertFlags::new().require_seek().update_rowid_change()
-            } else {
-                InsertFlags::new()
-            },
-            table_name: table_ref.identifier.clone(),
-        });
+        if without_rowid {
+            // The PRIMARY KEY of a WITHOUT ROWID table is the key of its B-tree, so the old row
+            // is replaced rather than overwritten.
+            program.emit_insn(Insn::Delete {
+                cursor_id,
+                table_name: table_ref.table.get_name().to_string(),
+            });
+            program.emit_insn(Insn::IdxInsert {
+                cursor_id,
+                record_reg,
+                unpacked_start: Some(record_start_reg),
+                unpacked_count: Some(record_count as u16),
+                flags: IdxInsertFlags::new(),
+            });
+        } else {
+            program.emit_insn(Insn::Insert {
+                cursor: cursor_id,
+                key_reg: rowid_set_clause_reg.unwrap_or(beg),
+                record_reg,
+                flag: if has_user_provided_rowid {
+                    // The previous Insn::NotExists and Insn::Delete seek to the old rowid,
+                    // so to insert a new user-provided rowid, we need to seek to the correct place.
+                    InsertFlags::new().require_seek().update_rowid_change()
+                } else {
+                    InsertFlags::new()
+                },
+                table_name: table_ref.identifier.clone(),
+            });
+        }
 
         // Emit RETURNING results if specified
         if let Some(returning_columns) = &plan.returning {
@@ -1324,8 +1612,26 @@ fn emit_update_insns(
                 )?;
             }
         }
+
+        emit_foreign_key_actions(
+            program,
+            &plan.foreign_keys,
+            table_ref.columns(),
+            Some(new_row),
+            old_row,
+            loop_labels.next,
+        );
+        emit_trigger_programs(
+            program,
+            &plan.triggers.after,
+            table_ref.columns(),
+            Some(new_row),
+            old_row,
+            loop_labels.next,
+        );
     } else if table_ref.virtual_table().is_some() {
         let arg_count = table_ref.columns().len() + 2;
+        program.emit_insn(Insn::VBegin { cursor_id });
         program.emit_insn(Insn::VUpdate {
             cursor_id,
             arg_count,
@@ -2,8 +2,10 @@ use tracing::{instrument, Level};
 use turso_parser::ast::{self, As, Expr, UnaryOperator};
 
 use super::emitter::Resolver;
+use super::generated::emit_table_column;
 use super::optimizer::Optimizable;
 use super::plan::TableReferences;
+use crate::error::SQLITE_CONSTRAINT_TRIGGER;
 #[cfg(feature = "json")]
 use crate::function::JsonFunc;
 use crate::function::{Func, FuncCtx, MathFuncArity, ScalarFunc, VectorFunc};
@@ -149,7 +151,12 @@ macro_rules! expect_arguments_even {
 ///
 /// This is extracted from the original conditional implementation to be reusable.
 /// The logic exactly matches the original conditional InList implementation.
+///
+/// A condition treats a NULL result as false. When the value of the expression is needed,
+/// `dest_if_null` is where to jump when the result is NULL: when nothing matches and either the
+/// left hand side or one of the values is NULL.
 #[instrument(skip(program, referenced_tables, resolver), level = Level::DEBUG)]
+#[allow(clippy::too_many_arguments)]
 fn translate_in_list(
     program: &mut ProgramBuilder,
     referenced_tables: Option<&TableReferences>,
@@ -157,6 +164,7 @@ fn translate_in_list(
     rhs: &[Box<ast::Expr>],
     not: bool,
     condition_metadata: ConditionMetadata,
+    dest_if_null: Option<BranchOffset>,
     resolver: &Resolver,
 ) -> Result<()> {
     // lhs is e.g. a column reference
@@ -212,7 +220,46 @@ fn translate_in_list(
         program.allocate_label()
     };
 
-    if !not {
+    if let Some(dest_if_null) = dest_if_null {
+        program.emit_insn(Insn::IsNull {
+            reg: lhs_reg,
+            target_pc: dest_if_null,
+        });
+        let target_on_match = if not {
+            condition_metadata.jump_target_when_false
+        } else {
+            jump_target_when_true
+        };
+        let mut rhs_regs = Vec::with_capacity(rhs.len());
+        for expr in rhs.iter() {
+            let rhs_reg = program.alloc_register();
+            let _ = translate_expr(program, referenced_tables, expr, rhs_reg, resolver)?;
+            program.emit_insn(Insn::Eq {
+                lhs: lhs_reg,
+                rhs: rhs_reg,
+                target_pc: target_on_match,
+                flags: CmpInsFlags::default(),
+                collation: program.curr_collation(),
+            });
+            rhs_regs.push(rhs_reg);
+        }
+        // Nothing matched, the result is NULL if any of the values is NULL.
+        for rhs_reg in rhs_regs {
+            program.emit_insn(Insn::IsNull {
+                reg: rhs_reg,
+                target_pc: dest_if_null,
+            });
+        }
+        if !not {
+            program.emit_insn(Insn::Goto {
+                target_pc: condition_metadata.jump_target_when_false,
+            });
+        } else if condition_metadata.jump_if_condition_is_true {
+            program.emit_insn(Insn::Goto {
+                target_pc: condition_metadata.jump_target_when_true,
+            });
+        }
+    } else if !not {
         // If it's an IN expression, we need to jump to the 'jump_target_when_true' label if any of the conditions are true.
         for (i, expr) in rhs.iter().enumerate() {
             let rhs_reg = program.alloc_register();
@@ -359,6 +406,7 @@ pub fn translate_condition_expr(
                 rhs,
                 *not,
                 condition_metadata,
+                None,
                 resolver,
             )?;
         }
@@ -690,7 +738,7 @@ pub fn translate_expr(
             // First translate inner expr, then set the curr collation. If we set curr collation before,
             // it may be overwritten later by inner translate.
             translate_expr(program, referenced_tables, expr, target_register, resolver)?;
-            let collation = CollationSeq::new(collation.as_str())?;
+            let collation = CollationSeq::new(collation.as_str(), resolver.symbol_table)?;
             program.set_collation(Some((collation, true)));
             Ok(target_register)
         }
@@ -1841,8 +1889,14 @@ pub fn translate_expr(
                     .unwrap()
                     .find_joined_table_by_internal_id(*table_ref_id)
                 {
+                    // The rows of the right table of a RIGHT JOIN that match no row are read
+                    // from the table, without positioning the index cursor.
+                    let is_right_join = table_reference
+                        .join_info
+                        .as_ref()
+                        .is_some_and(|join_info| join_info.right);
                     (
-                        table_reference.op.index(),
+                        table_reference.op.index().filter(|_| !is_right_join),
                         table_reference.utilizes_covering_index(),
                     )
                 } else {
@@ -1859,13 +1913,16 @@ pub fn translate_expr(
                 crate::bail_parse_error!("column index out of bounds");
             };
             // Counter intuitive but a column always needs to have a collation
-            program.set_collation(Some((table_column.collation.unwrap_or_default(), false)));
+            program.set_collation(Some((
+                table_column.collation.clone().unwrap_or_default(),
+                false,
+            )));
 
             // If we are reading a column from a table, we find the cursor that corresponds to
             // the table and read the column from the cursor.
             // If we have a covering index, we don't have an open table cursor so we read from the index cursor.
             match &table {
-                Table::BTree(_) => {
+                Table::BTree(btree) => {
                     let table_cursor_id = if use_covering_index {
                         None
                     } else {
@@ -1909,7 +1966,19 @@ pub fn translate_expr(
                             *column
                         };
 
-                        program.emit_column_or_rowid(read_cursor, column, target_register);
+                        if use_covering_index {
+                            program.emit_column_or_rowid(read_cursor, column, target_register);
+                        } else {
+                            // VIRTUAL generated columns are computed from the table row
+                            emit_table_column(
+                                program,
+                                resolver,
+                                read_cursor,
+                                btree,
+                                column,
+                                target_register,
+                            )?;
+                        }
                     }
                     let Some(column) = table.get_column_at(*column) else {
                         crate::bail_parse_error!("column index out of bounds");
@@ -1989,6 +2058,7 @@ pub fn translate_expr(
             });
 
             let dest_if_false = program.allocate_label();
+            let dest_if_null = program.allocate_label();
             let label_integer_conversion = program.allocate_label();
 
             // Call the core InList logic with expression-appropriate condition metadata
@@ -2003,6 +2073,7 @@ pub fn translate_expr(
                     jump_target_when_true: label_integer_conversion, // will be resolved below
                     jump_target_when_false: dest_if_false,
                 },
+                Some(dest_if_null),
                 resolver,
             )?;
 
@@ -2030,6 +2101,9 @@ pub fn translate_expr(
                 value: 0,
             });
 
+            // NULL path: keep the NULL the result was set to
+            program.preassign_label_to_next_insn(dest_if_null);
+
             Ok(result_reg)
         }
         ast::Expr::InSelect { .. } => todo!(),
@@ -2111,7 +2185,36 @@ pub fn translate_expr(
         ast::Expr::Qualified(_, _) => {
             unreachable!("Qualified should be resolved to a Column before translation")
         }
-        ast::Expr::Raise(_, _) => todo!(),
+        ast::Expr::Raise(resolve_type, message) => {
+            if !program.is_trigger_program() {
+                crate::bail_parse_error!("RAISE() may only be used within a trigger-program");
+            }
+            match resolve_type {
+                ast::ResolveType::Ignore => {
+                    program.emit_insn(Insn::RaiseIgnore);
+                }
+                ast::ResolveType::Rollback | ast::ResolveType::Abort | ast::ResolveType::Fail => {
+                    let description = match message.as_deref() {
+                        Some(ast::Expr::Literal(ast::Literal::String(s))) => sanitize_string(s),
+                        _ => crate::bail_parse_error!(
+                            "RAISE() error message must be a string literal"
+                        ),
+                    };
+                    program.emit_insn(Insn::Halt {
+                        err_code: SQLITE_CONSTRAINT_TRIGGER,
+                        description,
+                    });
+                }
+                ast::ResolveType::Replace => {
+                    crate::bail_parse_error!("RAISE(REPLACE) is not a valid raise type")
+                }
+            }
+            program.emit_insn(Insn::Null {
+                dest: target_register,
+                dest_end: None,
+            });
+            Ok(target_register)
+        }
         ast::Expr::Subquery(_) => todo!(),
         ast::Expr::Unary(op, expr) => match (op, expr.as_ref()) {
             (UnaryOperator::Positive, expr) => {
@@ -0,0 +1,764 @@
+//! Foreign keys.
+//!
+//! Foreign key constraints are enforced like SQLite does it, by counting violations instead of
+//! checking every constraint on the spot. Writing a row to a child table adds one violation if
+//! the parent row does not exist, and removing a row from the child table resolves one if the
+//! parent row did not exist. Removing a row from a parent table adds one violation for every
+//! child row that refers to it, and writing a parent row resolves one for every child row that
+//! refers to the new key. Immediate constraints are counted per statement and checked when the
+//! statement halts, deferred constraints are counted per connection and checked at commit.
+//!
+//! ON DELETE and ON UPDATE actions are compiled into AFTER triggers on the parent table, see
+//! [compile_action].
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use turso_parser::ast;
+
+use crate::error::SQLITE_CONSTRAINT_FOREIGNKEY;
+use crate::schema::{BTreeTable, Column, ForeignKey, Index, Schema, Trigger};
+use crate::translate::delete::prepare_delete_plan;
+use crate::translate::emitter::emit_program;
+use crate::translate::optimizer::optimize_plan;
+use crate::translate::plan::Plan;
+use crate::translate::trigger::{
+    compile_trigger, emit_trigger_programs, CompiledTrigger, TriggerRow,
+};
+use crate::vdbe::builder::{CursorType, ProgramBuilder};
+use crate::vdbe::insn::{CmpInsFlags, Insn};
+use crate::vdbe::BranchOffset;
+use crate::{bail_parse_error, Connection, Result, SymbolTable};
+
+/// A foreign key with its columns resolved against the child and the parent table.
+#[derive(Debug, Clone)]
+pub struct ResolvedForeignKey {
+    pub foreign_key: Arc<ForeignKey>,
+    /// Position of the foreign key among the foreign keys of the child table
+    pub position: usize,
+    pub child_table: Arc<BTreeTable>,
+    /// The parent table, `None` if it does not exist. Every child row with a non-NULL key
+    /// violates the constraint then.
+    pub parent_table: Option<Arc<BTreeTable>>,
+    /// Positions of the child key columns in the child table
+    pub child_columns: Vec<usize>,
+    /// Positions of the parent key columns in the parent table, matching `child_columns`
+    pub parent_columns: Vec<usize>,
+    /// The unique index on the parent key, `None` if the parent key is the rowid
+    pub parent_index: Option<Arc<Index>>,
+    /// An index on the child table that starts with the child key, used to find the child
+    /// rows of a parent key without a full scan
+    pub child_index: Option<Arc<Index>>,
+}
+
+impl ResolvedForeignKey {
+    fn deferred(&self) -> bool {
+        self.foreign_key.deferred
+    }
+
+    fn is_self_referencing(&self) -> bool {
+        self.parent_table
+            .as_ref()
+            .is_some_and(|parent| parent.name == self.child_table.name)
+    }
+}
+
+/// Resolves the columns of `foreign_key`, declared on `child_table`.
+/// Fails with a "foreign key mismatch" error if the parent key is not the primary key of the
+/// parent table or covered by a unique index, like SQLite does.
+pub fn resolve_foreign_key(
+    schema: &Schema,
+    child_table: &Arc<BTreeTable>,
+    position: usize,
+) -> Result<ResolvedForeignKey> {
+    let foreign_key = child_table.foreign_keys[position].clone();
+    let mismatch = || {
+        crate::LimboError::ParseError(format!(
+            "foreign key mismatch - \"{}\" referencing \"{}\"",
+            child_table.name, foreign_key.parent_table
+        ))
+    };
+
+    let mut child_columns = Vec::with_capacity(foreign_key.child_columns.len());
+    for name in &foreign_key.child_columns {
+        let Some((idx, _)) = child_table.get_column(name) else {
+            bail_parse_error!("unknown column \"{name}\" in foreign key definition");
+        };
+        child_columns.push(idx);
+    }
+
+    let Some(parent_table) = schema.get_btree_table(&foreign_key.parent_table) else {
+        return Ok(ResolvedForeignKey {
+            foreign_key,
+            position,
+            child_table: child_table.clone(),
+            parent_table: None,
+            child_columns,
+            parent_columns: vec![],
+            parent_index: None,
+            child_index: None,
+        });
+    };
+
+    let parent_key = if foreign_key.parent_columns.is_empty() {
+        parent_table
+            .primary_key_columns
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    } else {
+        foreign_key.parent_columns.clone()
+    };
+    if parent_key.is_empty() || parent_key.len() != child_columns.len() {
+        return Err(mismatch());
+    }
+    let mut parent_columns = Vec::with_capacity(parent_key.len());
+    for name in &parent_key {
+        let (idx, _) = parent_table.get_column(name).ok_or_else(mismatch)?;
+        parent_columns.push(idx);
+    }
+
+    let parent_index =
+        if parent_columns.len() == 1 && parent_table.columns[parent_columns[0]].is_rowid_alias {
+            None
+        } else {
+            // the primary key of a WITHOUT ROWID table is the index the table is stored in
+            let index = schema
+                .get_indices(&parent_table.name)
+                .iter()
+                .cloned()
+                .chain(parent_table.clustered_index())
+                .find(|index| {
+                    index.unique
+                        && !index.has_expressions()
+                        && index.key_columns().len() == parent_columns.len()
+                        && index
+                            .key_columns()
+                            .iter()
+                            .all(|column| parent_columns.contains(&column.pos_in_table))
+                })
+                .ok_or_else(mismatch)?;
+            Some(index)
+        };
+
+    // The index must compare the child key like the parent key does
+    let collation = |column: &Column| column.collation.clone().unwrap_or_default();
+    let child_index = schema
+        .get_indices(&child_table.name)
+        .iter()
+        .find(|index| {
+            !index.has_expressions()
+                && index.columns.len() >= child_columns.len()
+                && child_columns
+                    .iter()
+                    .zip(&parent_columns)
+                    .zip(&index.columns)
+                    .all(|((&child_idx, &parent_idx), index_column)| {
+                        index_column.pos_in_table == child_idx
+                            && index_column
+                                .collation
+                                .clone()
+                                .unwrap_or_else(|| collation(&child_table.columns[child_idx]))
+                                == collation(&parent_table.columns[parent_idx])
+                    })
+        })
+        .cloned();
+
+    Ok(ResolvedForeignKey {
+        foreign_key,
+        position,
+        child_table: child_table.clone(),
+        parent_table: Some(parent_table),
+        child_columns,
+        parent_columns,
+        parent_index,
+        child_index,
+    })
+}
+
+/// The foreign key checks and actions a statement performs for every row it writes.
+#[derive(Debug, Clone, Default)]
+pub struct ForeignKeyPlan {
+    /// Foreign keys of the written table, whose parent rows must exist
+    pub child_keys: Vec<ResolvedForeignKey>,
+    /// Foreign keys that refer to the written table
+    pub parent_keys: Vec<ResolvedForeignKey>,
+    /// ON DELETE or ON UPDATE actions of `parent_keys`
+    pub actions: Vec<CompiledTrigger>,
+}
+
+impl ForeignKeyPlan {
+    pub fn is_empty(&self) -> bool {
+        self.child_keys.is_empty() && self.parent_keys.is_empty()
+    }
+}
+
+/// Collects the foreign keys that `event` on `table` must check, and compiles their actions.
+/// For UPDATE, only the foreign keys whose columns are among `updated_columns` are checked.
+/// Returns an empty plan if foreign keys are not enforced by the connection.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_foreign_keys(
+    schema: &Schema,
+    syms: &SymbolTable,
+    connection: &Arc<Connection>,
+    parent: &ProgramBuilder,
+    table: &Arc<BTreeTable>,
+    event: ast::TriggerEvent,
+    updated_columns: &[usize],
+) -> Result<ForeignKeyPlan> {
+    let mut plan = ForeignKeyPlan::default();
+    if !connection.foreign_keys_enabled() {
+        return Ok(plan);
+    }
+    let is_update = matches!(event, ast::TriggerEvent::Update);
+    let is_modified =
+        |columns: &[usize]| !is_update || columns.iter().any(|c| updated_columns.contains(c));
+
+    for position in 0..table.foreign_keys.len() {
+        let foreign_key = resolve_foreign_key(schema, table, position)?;
+        if is_modified(&foreign_key.child_columns) {
+            plan.child_keys.push(foreign_key);
+        }
+    }
+
+    for (child_table, foreign_key) in schema.get_referencing_foreign_keys(&table.name) {
+        let position = child_table
+            .foreign_keys
+            .iter()
+            .position(|fk| Arc::ptr_eq(fk, &foreign_key))
+            .expect("foreign key must belong to its child table");
+        let foreign_key = resolve_foreign_key(schema, &child_table, position)?;
+        if !is_modified(&foreign_key.parent_columns) {
+            continue;
+        }
+        let action = match event {
+            ast::TriggerEvent::Delete => foreign_key.foreign_key.on_delete,
+            ast::TriggerEvent::Update => foreign_key.foreign_key.on_update,
+            _ => ast::RefAct::NoAction,
+        };
+        // RESTRICT fails on the spot, which PRAGMA defer_foreign_keys turns off
+        let has_action = match action {
+            ast::RefAct::NoAction => false,
+            ast::RefAct::Restrict => !connection.get_defer_foreign_keys(),
+            _ => true,
+        };
+        if has_action {
+            plan.actions.push(compile_action(
+                schema,
+                syms,
+                connection,
+                parent,
+                &foreign_key,
+                &event,
+                action,
+            )?);
+        }
+        plan.parent_keys.push(foreign_key);
+    }
+    Ok(plan)
+}
+
+/// Returns the register of column `idx` of `table` in `row`.
+fn column_register(table: &BTreeTable, row: TriggerRow, idx: usize) -> usize {
+    if table.columns[idx].is_rowid_alias {
+        row.rowid_reg
+    } else {
+        row.columns_start + idx
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Emits the checks of the foreign keys for which the table is the child, for the row held in
+/// `row`. `increment` is 1 when the row is written and -1 when it is removed.
+pub fn emit_child_key_checks(
+    program: &mut ProgramBuilder,
+    plan: &ForeignKeyPlan,
+    row: TriggerRow,
+    increment: i64,
+) {
+    for foreign_key in &plan.child_keys {
+        let ok_label = program.allocate_label();
+        if increment < 0 {
+            // Removing a row can only resolve violations, skip the lookup if there are none.
+            program.emit_insn(Insn::FkIfZero {
+                deferred: foreign_key.deferred(),
+                target_pc: ok_label,
+            });
+        }
+        let key_regs = foreign_key
+            .child_columns
+            .iter()
+            .map(|&idx| column_register(&foreign_key.child_table, row, idx))
+            .collect::<Vec<_>>();
+        // A key with a NULL value does not refer to any parent row
+        for &reg in &key_regs {
+            program.emit_insn(Insn::IsNull {
+                reg,
+                target_pc: ok_label,
+            });
+        }
+        if increment > 0 && foreign_key.is_self_referencing() {
+            emit_self_reference_check(program, foreign_key, row, &key_regs, ok_label);
+        }
+        emit_parent_lookup(program, foreign_key, &key_regs, ok_label);
+        program.emit_insn(Insn::FkCounter {
+            increment_value: increment,
+            deferred: foreign_key.deferred(),
+        });
+        program.preassign_label_to_next_insn(ok_label);
+    }
+}
+
+/// Jumps to `ok_label` if the row being inserted into a self-referencing table is its own
+/// parent, since the row cannot be found in the table yet.
+fn emit_self_reference_check(
+    program: &mut ProgramBuilder,
+    foreign_key: &ResolvedForeignKey,
+    row: TriggerRow,
+    key_regs: &[usize],
+    ok_label: BranchOffset,
+) {
+    let not_self_label = program.allocate_label();
+    for (&key_reg, &parent_column) in key_regs.iter().zip(&foreign_key.parent_columns) {
+        let column = &foreign_key.child_table.columns[parent_column];
+        program.emit_insn(Insn::Ne {
+            lhs: key_reg,
+            rhs: column_register(&foreign_key.child_table, row, parent_column),
+            target_pc: not_self_label,
+            flags: CmpInsFlags::default(),
+            collation: column.collation.clone(),
+        });
+    }
+    program.emit_insn(Insn::Goto {
+        target_pc: ok_label,
+    });
+    program.preassign_label_to_next_insn(not_self_label);
+}
+
+/// Emits a lookup of the parent row that the child key in `key_regs` refers to, jumping to
+/// `found_label` if it exists. Falls through if it does not, or if there is no parent table.
+pub fn emit_parent_lookup(
+    program: &mut ProgramBuilder,
+    foreign_key: &ResolvedForeignKey,
+    key_regs: &[usize],
+    found_label: BranchOffset,
+) {
+    let Some(parent_table) = &foreign_key.parent_table else {
+        return;
+    };
+    match &foreign_key.parent_index {
+        None => {
+            let cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(parent_table.clone()));
+            program.emit_insn(Insn::OpenRead {
+                cursor_id,
+                root_page: parent_table.root_page,
+                db: 0,
+            });
+            let not_found_label = program.allocate_label();
+            program.emit_insn(Insn::SeekRowid {
+                cursor_id,
+                src_reg: key_regs[0],
+                target_pc: not_found_label,
+            });
+            program.emit_insn(Insn::Goto {
+                target_pc: found_label,
+            });
+            program.preassign_label_to_next_insn(not_found_label);
+        }
+        Some(index) => {
+            // Build the key in the column order of the index, with the affinities of the
+            // parent columns
+            let num_regs = index.key_columns().len();
+            let key_start = program.alloc_registers(num_regs);
+            let mut affinities = String::with_capacity(num_regs);
+            for (i, index_column) in index.key_columns().iter().enumerate() {
+                let key_idx = foreign_key
+                    .parent_columns
+                    .iter()
+                    .position(|&c| c == index_column.pos_in_table)
+                    .expect("parent index must cover the parent key");
+                program.emit_insn(Insn::Copy {
+                    src_reg: key_regs[key_idx],
+                    dst_reg: key_start + i,
+                    extra_amount: 0,
+                });
+                affinities.push(
+                    parent_table.columns[index_column.pos_in_table]
+                        .affinity()
+                        .aff_mask(),
+                );
+            }
+            program.emit_insn(Insn::Affinity {
+                start_reg: key_start,
+                count: NonZeroUsize::new(num_regs).expect("index must have columns"),
+                affinities,
+            });
+            let cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(index.clone()));
+            program.emit_insn(Insn::OpenRead {
+                cursor_id,
+                root_page: index.root_page,
+                db: 0,
+            });
+            program.emit_insn(Insn::Found {
+                cursor_id,
+                target_pc: found_label,
+                record_reg: key_start,
+                num_regs,
+            });
+        }
+    }
+}
+
+/// Emits the checks of the foreign keys that refer to the table, for the row held in `row`.
+/// Every child row that refers to the key of the row counts as one violation when `increment`
+/// is 1 (the row is removed), or resolves one when it is -1 (the row is written).
+pub fn emit_parent_key_checks(
+    program: &mut ProgramBuilder,
+    plan: &ForeignKeyPlan,
+    row: TriggerRow,
+    increment: i64,
+) {
+    for foreign_key in &plan.parent_keys {
+        let parent_table = foreign_key
+            .parent_table
+            .as_ref()
+            .expect("referencing foreign key must have a parent table");
+        let child_table = &foreign_key.child_table;
+        let done_label = program.allocate_label();
+        if increment < 0 {
+            program.emit_insn(Insn::FkIfZero {
+                deferred: foreign_key.deferred(),
+                target_pc: done_label,
+            });
+        }
+        // No child row can refer to a key with a NULL value
+        let num_regs = foreign_key.parent_columns.len();
+        for &idx in &foreign_key.parent_columns {
+            program.emit_insn(Insn::IsNull {
+                reg: column_register(parent_table, row, idx),
+                target_pc: done_label,
+            });
+        }
+        // Convert the key like the child columns store it
+        let key_start = program.alloc_registers(num_regs);
+        let mut affinities = String::with_capacity(num_regs);
+        for (i, (&parent_idx, &child_idx)) in foreign_key
+            .parent_columns
+            .iter()
+            .zip(&foreign_key.child_columns)
+            .enumerate()
+        {
+            program.emit_insn(Insn::Copy {
+                src_reg: column_register(parent_table, row, parent_idx),
+                dst_reg: key_start + i,
+                extra_amount: 0,
+            });
+            affinities.push(child_table.columns[child_idx].affinity().aff_mask());
+        }
+        program.emit_insn(Insn::Affinity {
+            start_reg: key_start,
+            count: NonZeroUsize::new(num_regs).expect("foreign key must have columns"),
+            affinities,
+        });
+
+        // The row itself does not count when it refers to its own key
+        let exclude_rowid =
+            (increment > 0 && foreign_key.is_self_referencing()).then_some(row.rowid_reg);
+        let loop_start = program.allocate_label();
+        let next_label = program.allocate_label();
+        if let Some(index) = &foreign_key.child_index {
+            let cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(index.clone()));
+            program.emit_insn(Insn::OpenRead {
+                cursor_id,
+                root_page: index.root_page,
+                db: 0,
+            });
+            program.emit_insn(Insn::SeekGE {
+                is_index: true,
+                cursor_id,
+                start_reg: key_start,
+                num_regs,
+                target_pc: done_label,
+                eq_only: true,
+            });
+            program.preassign_label_to_next_insn(loop_start);
+            program.emit_insn(Insn::IdxGT {
+                cursor_id,
+                start_reg: key_start,
+                num_regs,
+                target_pc: done_label,
+            });
+            if let Some(rowid_reg) = exclude_rowid {
+                let child_rowid_reg = program.alloc_register();
+                program.emit_insn(Insn::IdxRowId {
+                    cursor_id,
+                    dest: child_rowid_reg,
+                });
+                program.emit_insn(Insn::Eq {
+                    lhs: child_rowid_reg,
+                    rhs: rowid_reg,
+                    target_pc: next_label,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                });
+            }
+            program.emit_insn(Insn::FkCounter {
+                increment_value: increment,
+                deferred: foreign_key.deferred(),
+            });
+            program.preassign_label_to_next_insn(next_label);
+            program.emit_insn(Insn::Next {
+                cursor_id,
+                pc_if_next: loop_start,
+            });
+        } else {
+            let cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(child_table.clone()));
+            program.emit_insn(Insn::OpenRead {
+                cursor_id,
+                root_page: child_table.root_page,
+                db: 0,
+            });
+            program.emit_insn(Insn::Rewind {
+                cursor_id,
+                pc_if_empty: done_label,
+            });
+            program.preassign_label_to_next_insn(loop_start);
+            let column_reg = program.alloc_register();
+            for (i, (&child_idx, &parent_idx)) in foreign_key
+                .child_columns
+                .iter()
+                .zip(&foreign_key.parent_columns)
+                .enumerate()
+            {
+                program.emit_column_or_rowid(cursor_id, child_idx, column_reg);
+                program.emit_insn(Insn::Ne {
+                    lhs: column_reg,
+                    rhs: key_start + i,
+                    target_pc: next_label,
+                    flags: CmpInsFlags::default().jump_if_null(),
+                    collation: parent_table.columns[parent_idx].collation.clone(),
+                });
+            }
+            if let Some(rowid_reg) = exclude_rowid {
+                let child_rowid_reg = program.alloc_register();
+                program.emit_insn(Insn::RowId {
+                    cursor_id,
+                    dest: child_rowid_reg,
+                });
+                program.emit_insn(Insn::Eq {
+                    lhs: child_rowid_reg,
+                    rhs: rowid_reg,
+                    target_pc: next_label,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                });
+            }
+            program.emit_insn(Insn::FkCounter {
+                increment_value: increment,
+                deferred: foreign_key.deferred(),
+            });
+            program.preassign_label_to_next_insn(next_label);
+            program.emit_insn(Insn::Next {
+                cursor_id,
+                pc_if_next: loop_start,
+            });
+        }
+        program.preassign_label_to_next_insn(done_label);
+    }
+}
+
+/// Emits the ON DELETE or ON UPDATE actions of the plan, after the row has been written.
+pub fn emit_foreign_key_actions(
+    program: &mut ProgramBuilder,
+    plan: &ForeignKeyPlan,
+    columns: &[Column],
+    new_row: Option<TriggerRow>,
+    old_row: Option<TriggerRow>,
+    ignore_target: BranchOffset,
+) {
+    emit_trigger_programs(
+        program,
+        &plan.actions,
+        columns,
+        new_row,
+        old_row,
+        ignore_target,
+    );
+}
+
+/// Emits the implicit `DELETE FROM table` that DROP TABLE runs first when other tables refer
+/// to the dropped table, so that the actions and checks of their foreign keys run as for any
+/// DELETE. Triggers on the table do not fire. Immediate violations halt the statement before
+/// the schema is changed.
+pub fn emit_drop_table_delete(
+    program: &mut ProgramBuilder,
+    schema: &Schema,
+    syms: &SymbolTable,
+    connection: &Arc<Connection>,
+    table: &Arc<BTreeTable>,
+    database_id: usize,
+) -> Result<()> {
+    if !connection.foreign_keys_enabled()
+        || schema.get_referencing_foreign_keys(&table.name).is_empty()
+    {
+        return Ok(());
+    }
+    let mut plan = prepare_delete_plan(
+        schema,
+        table.name.clone(),
+        database_id,
+        None,
+        None,
+        vec![],
+        &mut program.table_reference_counter,
+        connection,
+    )?;
+    if let Plan::Delete(delete) = &mut plan {
+        delete.foreign_keys = prepare_foreign_keys(
+            schema,
+            syms,
+            connection,
+            program,
+            table,
+            ast::TriggerEvent::Delete,
+            &[],
+        )?;
+    }
+    optimize_plan(&mut plan, schema)?;
+    emit_program(program, plan, schema, syms, |_| {})?;
+
+    if !connection.get_defer_foreign_keys() {
+        let ok_label = program.allocate_label();
+        program.emit_insn(Insn::FkIfZero {
+            deferred: false,
+            target_pc: ok_label,
+        });
+        program.emit_insn(Insn::Halt {
+            err_code: SQLITE_CONSTRAINT_FOREIGNKEY,
+            description: String::new(),
+        });
+        program.preassign_label_to_next_insn(ok_label);
+    }
+    Ok(())
+}
+
+/// Compiles the action of a foreign key that refers to the written table into an AFTER
+/// trigger on the parent table, e.g. `ON DELETE CASCADE` into
+/// `DELETE FROM child WHERE child.c = OLD.p`. Unlike other triggers, actions always recurse:
+/// an action that is already running further up the trigger stack invokes that program again,
+/// e.g. to cascade a delete through a self-referencing table.
+fn compile_action(
+    schema: &Schema,
+    syms: &SymbolTable,
+    connection: &Arc<Connection>,
+    parent: &ProgramBuilder,
+    foreign_key: &ResolvedForeignKey,
+    event: &ast::TriggerEvent,
+    action: ast::RefAct,
+) -> Result<CompiledTrigger> {
+    let parent_table = foreign_key
+        .parent_table
+        .as_ref()
+        .expect("referencing foreign key must have a parent table");
+    let child_table = &foreign_key.child_table;
+    let is_update = matches!(event, ast::TriggerEvent::Update);
+    let name = format!(
+        "#fk_{}_{}_{}",
+        if is_update { "update" } else { "delete" },
+        child_table.name,
+        foreign_key.position
+    );
+    if let Some(frame) = parent
+        .trigger_stack()
+        .iter()
+        .find(|frame| frame.name == name)
+    {
+        return Ok(frame.recursive_trigger());
+    }
+
+    let column_name = |table: &BTreeTable, idx: usize| {
+        quote_ident(table.columns[idx].name.as_deref().unwrap_or_default())
+    };
+    let key_columns = foreign_key
+        .child_columns
+        .iter()
+        .zip(&foreign_key.parent_columns)
+        .map(|(&child_idx, &parent_idx)| {
+            (
+                column_name(child_table, child_idx),
+                column_name(parent_table, parent_idx),
+                &child_table.columns[child_idx],
+            )
+        })
+        .collect::<Vec<_>>();
+    let where_clause = key_columns
+        .iter()
+        .map(|(child, parent, _)| format!("{child} = old.{parent}"))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let child_name = quote_ident(&child_table.name);
+    let update_child = |values: Vec<String>| {
+        let sets = key_columns
+            .iter()
+            .zip(values)
+            .map(|((child, _, _), value)| format!("{child} = {value}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("UPDATE {child_name} SET {sets} WHERE {where_clause}")
+    };
+    let body = match action {
+        ast::RefAct::Cascade if is_update => update_child(
+            key_columns
+                .iter()
+                .map(|(_, parent, _)| format!("new.{parent}"))
+                .collect(),
+        ),
+        ast::RefAct::Cascade => format!("DELETE FROM {child_name} WHERE {where_clause}"),
+        ast::RefAct::SetNull => update_child(vec!["NULL".to_string(); key_columns.len()]),
+        ast::RefAct::SetDefault => update_child(
+            key_columns
+                .iter()
+                .map(|(_, _, column)| {
+                    column
+                        .default
+                        .as_ref()
+                        .map_or("NULL".to_string(), |default| default.to_string())
+                })
+                .collect(),
+        ),
+        ast::RefAct::Restrict => format!(
+            "SELECT RAISE(ABORT, 'FOREIGN KEY constraint failed') FROM {child_name} WHERE {where_clause}"
+        ),
+        ast::RefAct::NoAction => unreachable!("NO ACTION has no action trigger"),
+    };
+    // An update that keeps the parent key does not affect the child rows
+    let when_clause = if is_update {
+        let unchanged = key_columns
+            .iter()
+            .map(|(_, parent, _)| format!("old.{parent} IS new.{parent}"))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        format!(" WHEN NOT ({unchanged})")
+    } else {
+        String::new()
+    };
+    let sql = format!(
+        "CREATE TRIGGER {} AFTER {} ON {} FOR EACH ROW{when_clause} BEGIN {body}; END",
+        quote_ident(&name),
+        if is_update { "UPDATE" } else { "DELETE" },
+        quote_ident(&parent_table.name),
+    );
+    let trigger = Trigger::from_sql(&sql)?;
+    compile_trigger(
+        schema,
+        syms,
+        connection,
+        parent.trigger_stack(),
+        &trigger,
+        &parent_table.columns,
+        event,
+    )
+}
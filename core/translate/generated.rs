@@ -0,0 +1,177 @@
+//! Generated columns.
+//!
+//! STORED generated columns are computed whenever a row is written and stored in the record like
+//! any other column. VIRTUAL generated columns are not stored at all: they are computed from the
+//! other columns of the row whenever they are read, see [emit_table_column]. Since the record
+//! lacks the VIRTUAL columns, the position of a column in the record can differ from its position
+//! in the table, see [BTreeTable::record_index].
+
+use std::num::NonZeroUsize;
+
+use turso_parser::ast::{self, Expr};
+
+use crate::schema::BTreeTable;
+use crate::translate::emitter::Resolver;
+use crate::translate::expr::{translate_expr_no_constant_opt, walk_expr_mut, NoConstantOptReason};
+use crate::translate::trigger::TriggerRow;
+use crate::util::normalize_ident;
+use crate::vdbe::builder::ProgramBuilder;
+use crate::vdbe::insn::Insn;
+use crate::{bail_parse_error, Result};
+
+/// Rewrites the column references of `expr`, an expression over a single row of `table` like a
+/// generated column or a CHECK constraint, to the registers of `row`.
+pub fn rewrite_row_references(expr: &mut Expr, table: &BTreeTable, row: TriggerRow) -> Result<()> {
+    walk_expr_mut(expr, &mut |expr: &mut Expr| -> Result<()> {
+        let name = match expr {
+            Expr::Id(name) | Expr::Name(name) => name.clone(),
+            Expr::Qualified(tbl_name, col_name) => {
+                if normalize_ident(tbl_name.as_str()) != table.name {
+                    bail_parse_error!("no such column: {tbl_name}.{col_name}");
+                }
+                col_name.clone()
+            }
+            _ => return Ok(()),
+        };
+        let reg = match table.get_column(name.as_str()) {
+            Some((_, column)) if column.is_rowid_alias => row.rowid_reg,
+            Some((idx, _)) => row.columns_start + idx,
+            None if table.has_rowid
+                && matches!(
+                    normalize_ident(name.as_str()).as_str(),
+                    "rowid" | "oid" | "_rowid_"
+                ) =>
+            {
+                row.rowid_reg
+            }
+            // a double-quoted identifier that is not a column is a string literal
+            None if name.is_double_quoted() => {
+                *expr = Expr::Literal(ast::Literal::String(name.to_string()));
+                return Ok(());
+            }
+            None => bail_parse_error!("no such column: {name}"),
+        };
+        *expr = Expr::Register(reg);
+        Ok(())
+    })
+}
+
+/// Checks that `expr`, an expression over a single row of `table`, only refers to columns of
+/// `table`.
+pub fn validate_row_references(expr: &Expr, table: &BTreeTable) -> Result<()> {
+    // the registers do not matter, only whether every reference resolves
+    let row = TriggerRow {
+        rowid_reg: 0,
+        columns_start: 0,
+    };
+    rewrite_row_references(&mut expr.clone(), table, row)
+}
+
+/// Computes the generated columns of `table` into the registers of `row`, which must already
+/// hold the other columns.
+pub fn emit_generated_columns(
+    program: &mut ProgramBuilder,
+    resolver: &Resolver,
+    table: &BTreeTable,
+    row: TriggerRow,
+) -> Result<()> {
+    for idx in table.generated_columns_in_order()? {
+        emit_generated_column(program, resolver, table, idx, row, row.columns_start + idx)?;
+    }
+    Ok(())
+}
+
+/// Reads column `column` of the row `cursor_id` points to into `dest`, computing it if it is a
+/// VIRTUAL generated column.
+pub fn emit_table_column(
+    program: &mut ProgramBuilder,
+    resolver: &Resolver,
+    cursor_id: usize,
+    table: &BTreeTable,
+    column: usize,
+    dest: usize,
+) -> Result<()> {
+    let Some(generated) = table.columns[column]
+        .generated
+        .as_ref()
+        .filter(|generated| !generated.stored)
+    else {
+        program.emit_column_or_rowid(cursor_id, column, dest);
+        return Ok(());
+    };
+    let row = TriggerRow {
+        rowid_reg: program.alloc_register(),
+        columns_start: program.alloc_registers(table.columns.len()),
+    };
+    if table.has_rowid {
+        program.emit_insn(Insn::RowId {
+            cursor_id,
+            dest: row.rowid_reg,
+        });
+    }
+    for idx in table.referenced_columns(&generated.expr) {
+        emit_table_column(
+            program,
+            resolver,
+            cursor_id,
+            table,
+            idx,
+            row.columns_start + idx,
+        )?;
+    }
+    emit_generated_column(program, resolver, table, column, row, dest)
+}
+
+/// Returns the registers to build the record of a row of `table` from, given the registers of
+/// the row in table order. The stored columns are copied into contiguous registers in record
+/// order if the table has VIRTUAL generated columns or is a WITHOUT ROWID table.
+pub fn emit_record_registers(
+    program: &mut ProgramBuilder,
+    table: &BTreeTable,
+    columns_start: usize,
+) -> (usize, usize) {
+    if table.has_rowid && !table.has_virtual_columns() {
+        return (columns_start, table.columns.len());
+    }
+    let stored_columns = table.record_columns();
+    let start_reg = program.alloc_registers(stored_columns.len());
+    for (i, idx) in stored_columns.iter().enumerate() {
+        program.emit_insn(Insn::Copy {
+            src_reg: columns_start + idx,
+            dst_reg: start_reg + i,
+            extra_amount: 0,
+        });
+    }
+    (start_reg, stored_columns.len())
+}
+
+fn emit_generated_column(
+    program: &mut ProgramBuilder,
+    resolver: &Resolver,
+    table: &BTreeTable,
+    column: usize,
+    row: TriggerRow,
+    dest: usize,
+) -> Result<()> {
+    let generated = table.columns[column]
+        .generated
+        .as_ref()
+        .expect("column must be generated");
+    let mut expr = generated.expr.as_ref().clone();
+    rewrite_row_references(&mut expr, table, row)?;
+    translate_expr_no_constant_opt(
+        program,
+        None,
+        &expr,
+        dest,
+        resolver,
+        NoConstantOptReason::RegisterReuse,
+    )?;
+    // the value of a generated column gets the affinity of the column, like a stored value
+    program.emit_insn(Insn::Affinity {
+        start_reg: dest,
+        count: NonZeroUsize::new(1).unwrap(),
+        affinities: table.columns[column].affinity().aff_mask().to_string(),
+    });
+    Ok(())
+}
@@ -139,7 +139,8 @@ pub fn init_group_by<'a>(
             .iter()
             .map(|expr| match expr {
                 ast::Expr::Collate(_, collation_name) => {
-                    CollationSeq::new(collation_name.as_str()).map(Some)
+                    CollationSeq::new(collation_name.as_str(), t_ctx.resolver.symbol_table)
+                        .map(Some)
                 }
                 ast::Expr::Column { table, column, .. } => {
                     let table_reference = plan
@@ -151,7 +152,7 @@ pub fn init_group_by<'a>(
                         crate::bail_parse_error!("column index out of bounds");
                     };
 
-                    Ok(table_column.collation)
+                    Ok(table_column.collation.clone())
                 }
                 _ => Ok(Some(CollationSeq::default())),
             })
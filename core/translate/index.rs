@@ -1,30 +1,39 @@
 use std::sync::Arc;
 
+use crate::error::SQLITE_CONSTRAINT_PRIMARYKEY;
 use crate::translate::emitter::{
     emit_cdc_full_record, emit_cdc_insns, prepare_cdc_if_necessary, OperationMode, Resolver,
 };
 use crate::vdbe::insn::{CmpInsFlags, Cookie};
 use crate::SymbolTable;
 use crate::{
-    schema::{BTreeTable, Column, Index, IndexColumn, PseudoCursorType, Schema},
+    function::Func,
+    schema::{BTreeTable, Index, IndexColumn, PseudoCursorType, Schema, TEMP_SCHEMA_TABLE_NAME},
     storage::pager::CreateBTreeFlags,
     util::normalize_ident,
     vdbe::{
         builder::{CursorType, ProgramBuilder},
         insn::{IdxInsertFlags, Insn, RegisterOrLiteral},
+        BranchOffset,
     },
 };
-use turso_parser::ast::{self, Expr, SortOrder, SortedColumn};
+use turso_parser::ast::{Expr, QualifiedName, SortOrder, SortedColumn};
 
-use super::schema::{emit_schema_entry, SchemaEntryType, SQLITE_TABLEID};
+use super::expr::{translate_expr_no_constant_opt, walk_expr, NoConstantOptReason, WalkControl};
+use super::generated::{emit_table_column, rewrite_row_references, validate_row_references};
+use super::schema::{emit_schema_entry, resolve_schema_target, SchemaEntryType, SQLITE_TABLEID};
+use super::trigger::TriggerRow;
 
+#[allow(clippy::too_many_arguments)]
 pub fn translate_create_index(
     unique_if_not_exists: (bool, bool),
-    idx_name: &str,
+    idx_name: &QualifiedName,
     tbl_name: &str,
     columns: &[SortedColumn],
+    where_clause: Option<Box<Expr>>,
     schema: &Schema,
     syms: &SymbolTable,
+    connection: &Arc<crate::Connection>,
     mut program: ProgramBuilder,
 ) -> crate::Result<ProgramBuilder> {
     if !schema.indexes_enabled() {
@@ -32,7 +41,10 @@ pub fn translate_create_index(
             "CREATE INDEX is disabled by default. Run with `--experimental-indexes` to enable this feature."
         );
     }
-    let idx_name = normalize_ident(idx_name);
+    // `CREATE INDEX aux.i ON t` creates the index on the table `t` of the database `aux`.
+    let (attached_db, attached_schema) = resolve_schema_target(connection, idx_name)?;
+    let schema = attached_schema.as_deref().unwrap_or(schema);
+    let idx_name = normalize_ident(idx_name.name.as_str());
     let tbl_name = normalize_ident(tbl_name);
     let opts = crate::vdbe::builder::ProgramBuilderOpts {
         num_cursors: 5,
@@ -56,26 +68,28 @@ pub fn translate_create_index(
     let Some(tbl) = tbl.btree() else {
         crate::bail_parse_error!("Error: table '{tbl_name}' is not a b-tree table.");
     };
-    let columns = resolve_sorted_columns(&tbl, columns)?;
+    let resolver = Resolver::new(schema, syms);
+    let index_columns = resolve_sorted_columns(&tbl, columns, &resolver)?;
+    if let Some(where_clause) = &where_clause {
+        validate_index_expr(where_clause, &tbl, &resolver, "partial index WHERE clauses")?;
+    }
 
-    let idx = Arc::new(Index {
+    let mut idx = Index {
         name: idx_name.clone(),
         table_name: tbl.name.clone(),
         root_page: 0, //  we dont have access till its created, after we parse the schema table
-        columns: columns
-            .iter()
-            .map(|((pos_in_table, col), order)| IndexColumn {
-                name: col.name.as_ref().unwrap().clone(),
-                order: *order,
-                pos_in_table: *pos_in_table,
-                collation: col.collation,
-                default: col.default.clone(),
-            })
-            .collect(),
+        columns: index_columns,
         unique: unique_if_not_exists.0,
         ephemeral: false,
         has_rowid: tbl.has_rowid,
-    });
+        where_clause,
+        non_key_columns: 0,
+        temporary: tbl.temporary,
+    };
+    idx.append_primary_key_columns(&tbl);
+    let idx = Arc::new(idx);
+    // the number of values in an index record, including the rowid
+    let num_values = idx.columns.len() + tbl.has_rowid as usize;
 
     // Allocate the necessary cursors:
     //
@@ -84,7 +98,14 @@ pub fn translate_create_index(
     // 3. table_cursor_id         - table we are creating the index on
     // 4. sorter_cursor_id        - sorter
     // 5. pseudo_cursor_id        - pseudo table to store the sorted index values
-    let sqlite_table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
+    // Indexes on TEMP tables live in the temp database, along with their schema entry.
+    let db = if tbl.temporary { 1 } else { attached_db };
+    let schema_table_name = if tbl.temporary {
+        TEMP_SCHEMA_TABLE_NAME
+    } else {
+        SQLITE_TABLEID
+    };
+    let sqlite_table = schema.get_btree_table(schema_table_name).unwrap();
     let sqlite_schema_cursor_id =
         program.alloc_cursor_id(CursorType::BTreeTable(sqlite_table.clone()));
     let btree_cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(idx.clone()));
@@ -97,7 +118,7 @@ pub fn translate_create_index(
     // Create a new B-Tree and store the root page index in a register
     let root_page_reg = program.alloc_register();
     program.emit_insn(Insn::CreateBtree {
-        db: 0,
+        db,
         root: root_page_reg,
         flags: CreateBTreeFlags::new_index(),
     });
@@ -106,11 +127,14 @@ pub fn translate_create_index(
     program.emit_insn(Insn::OpenWrite {
         cursor_id: sqlite_schema_cursor_id,
         root_page: RegisterOrLiteral::Literal(sqlite_table.root_page),
-        db: 0,
+        db,
     });
-    let sql = create_idx_stmt_to_sql(&tbl_name, &idx_name, unique_if_not_exists, &columns);
-    let resolver = Resolver::new(schema, syms);
-    let cdc_table = prepare_cdc_if_necessary(&mut program, schema, SQLITE_TABLEID)?;
+    let sql = create_idx_stmt_to_sql(&tbl_name, &idx_name, unique_if_not_exists, columns, &idx);
+    let cdc_table = if db != 0 {
+        None
+    } else {
+        prepare_cdc_if_necessary(&mut program, schema, SQLITE_TABLEID)?
+    };
     emit_schema_entry(
         &mut program,
         &resolver,
@@ -128,25 +152,26 @@ pub fn translate_create_index(
     // open the sorter and the pseudo table
     program.emit_insn(Insn::SorterOpen {
         cursor_id: sorter_cursor_id,
-        columns: columns.len(),
+        columns: idx.columns.len(),
         order,
-        collations: idx.columns.iter().map(|c| c.collation).collect(),
+        collations: idx.columns.iter().map(|c| c.collation.clone()).collect(),
     });
     let content_reg = program.alloc_register();
     program.emit_insn(Insn::OpenPseudo {
         cursor_id: pseudo_cursor_id,
         content_reg,
-        num_fields: columns.len() + 1,
+        num_fields: num_values,
     });
 
     // open the table we are creating the index on for reading
     program.emit_insn(Insn::OpenRead {
         cursor_id: table_cursor_id,
         root_page: tbl.root_page,
-        db: 0,
+        db,
     });
 
     let loop_start_label = program.allocate_label();
+    let loop_next_label = program.allocate_label();
     let loop_end_label = program.allocate_label();
     program.emit_insn(Insn::Rewind {
         cursor_id: table_cursor_id,
@@ -159,19 +184,21 @@ pub fn translate_create_index(
     // emit MakeRecord (index key + rowid) into record_reg.
     //
     // Then insert the record into the sorter
-    let start_reg = program.alloc_registers(columns.len() + 1);
-    for (i, (col, _)) in columns.iter().enumerate() {
-        program.emit_column_or_rowid(table_cursor_id, col.0, start_reg + i);
+    let row = emit_index_row(&mut program, &resolver, table_cursor_id, &tbl, &idx)?;
+    // rows that are not part of a partial index are skipped
+    emit_partial_index_check(&mut program, &resolver, &tbl, &idx, row, loop_next_label)?;
+    let start_reg = program.alloc_registers(num_values);
+    emit_index_column_values(&mut program, &resolver, &tbl, &idx, row, start_reg)?;
+    if tbl.has_rowid {
+        program.emit_insn(Insn::RowId {
+            cursor_id: table_cursor_id,
+            dest: start_reg + idx.columns.len(),
+        });
     }
-    let rowid_reg = start_reg + columns.len();
-    program.emit_insn(Insn::RowId {
-        cursor_id: table_cursor_id,
-        dest: rowid_reg,
-    });
     let record_reg = program.alloc_register();
     program.emit_insn(Insn::MakeRecord {
         start_reg,
-        count: columns.len() + 1,
+        count: num_values,
         dest_reg: record_reg,
         index_name: Some(idx_name.clone()),
     });
@@ -180,6 +207,7 @@ pub fn translate_create_index(
         record_reg,
     });
 
+    program.preassign_label_to_next_insn(loop_next_label);
     program.emit_insn(Insn::Next {
         cursor_id: table_cursor_id,
         pc_if_next: loop_start_label,
@@ -191,7 +219,7 @@ pub fn translate_create_index(
     program.emit_insn(Insn::OpenWrite {
         cursor_id: btree_cursor_id,
         root_page: RegisterOrLiteral::Register(root_page_reg),
-        db: 0,
+        db,
     });
 
     let sorted_loop_start = program.allocate_label();
@@ -233,16 +261,18 @@ pub fn translate_create_index(
     // Keep schema table open to emit ParseSchema, close the other cursors.
     program.close_cursors(&[sorter_cursor_id, table_cursor_id, btree_cursor_id]);
 
-    program.emit_insn(Insn::SetCookie {
-        db: 0,
-        cookie: Cookie::SchemaVersion,
-        value: schema.schema_version as i32 + 1,
-        p5: 0,
-    });
+    if !tbl.temporary {
+        program.emit_insn(Insn::SetCookie {
+            db,
+            cookie: Cookie::SchemaVersion,
+            value: schema.schema_version as i32 + 1,
+            p5: 0,
+        });
+    }
     // Parse the schema table to get the index root page and add new index to Schema
     let parse_schema_where_clause = format!("name = '{idx_name}' AND type = 'index'");
     program.emit_insn(Insn::ParseSchema {
-        db: sqlite_schema_cursor_id,
+        db,
         where_clause: Some(parse_schema_where_clause),
     });
     // Close the final sqlite_schema cursor
@@ -253,37 +283,67 @@ pub fn translate_create_index(
     Ok(program)
 }
 
-fn resolve_sorted_columns<'a>(
-    table: &'a BTreeTable,
+fn resolve_sorted_columns(
+    table: &BTreeTable,
     cols: &[SortedColumn],
-) -> crate::Result<Vec<((usize, &'a Column), SortOrder)>> {
+    resolver: &Resolver,
+) -> crate::Result<Vec<IndexColumn>> {
     let mut resolved = Vec::with_capacity(cols.len());
     for sc in cols {
-        let ident = normalize_ident(match sc.expr.as_ref() {
-            // SQLite supports indexes on arbitrary expressions, but we don't (yet).
-            // See "How to use indexes on expressions" in https://www.sqlite.org/expridx.html
-            Expr::Id(ast::Name::Ident(col_name))
-            | Expr::Id(ast::Name::Quoted(col_name))
-            | Expr::Name(ast::Name::Ident(col_name))
-            | Expr::Name(ast::Name::Quoted(col_name)) => col_name,
-            _ => crate::bail_parse_error!("Error: cannot use expressions in CREATE INDEX"),
-        });
-        let Some(col) = table.get_column(&ident) else {
-            crate::bail_parse_error!(
-                "Error: column '{ident}' does not exist in table '{}'",
-                table.name
-            );
-        };
-        resolved.push((col, sc.order.unwrap_or(SortOrder::Asc)));
+        let index_column = IndexColumn::from_sorted_column(sc, table, resolver.symbol_table)?;
+        if let Some(expr) = &index_column.expr {
+            // See "Indexes On Expressions" in https://www.sqlite.org/expridx.html
+            validate_index_expr(expr, table, resolver, "index expressions")?;
+        }
+        resolved.push(index_column);
     }
     Ok(resolved)
 }
 
+/// Checks that `expr`, an indexed expression or the WHERE clause of a partial index, only refers
+/// to columns of `table` and only uses deterministic functions, so that the index can be
+/// maintained from the row alone. `context` names the kind of expression for error messages.
+fn validate_index_expr(
+    expr: &Expr,
+    table: &BTreeTable,
+    resolver: &Resolver,
+    context: &str,
+) -> crate::Result<()> {
+    walk_expr(expr, &mut |expr: &Expr| -> crate::Result<WalkControl> {
+        match expr {
+            Expr::Subquery(_) | Expr::Exists(_) | Expr::InSelect { .. } => {
+                crate::bail_parse_error!("subqueries prohibited in {context}");
+            }
+            Expr::Variable(_) => {
+                crate::bail_parse_error!("parameters prohibited in {context}");
+            }
+            Expr::FunctionCall { name, args, .. } => {
+                let Some(func) = resolver.resolve_function(name.as_str(), args.len()) else {
+                    crate::bail_parse_error!("no such function: {name}");
+                };
+                if matches!(func, Func::Agg(_)) {
+                    crate::bail_parse_error!("misuse of aggregate function {name}()");
+                }
+                if !func.is_deterministic() {
+                    crate::bail_parse_error!("non-deterministic functions prohibited in {context}");
+                }
+            }
+            Expr::FunctionCallStar { name, .. } => {
+                crate::bail_parse_error!("misuse of aggregate function {name}()");
+            }
+            _ => {}
+        }
+        Ok(WalkControl::Continue)
+    })?;
+    validate_row_references(expr, table)
+}
+
 fn create_idx_stmt_to_sql(
     tbl_name: &str,
     idx_name: &str,
     unique_if_not_exists: (bool, bool),
-    cols: &[((usize, &Column), SortOrder)],
+    cols: &[SortedColumn],
+    index: &Index,
 ) -> String {
     let mut sql = String::with_capacity(128);
     sql.push_str("CREATE ");
@@ -298,24 +358,257 @@ fn create_idx_stmt_to_sql(
     sql.push_str(" ON ");
     sql.push_str(tbl_name);
     sql.push_str(" (");
-    for (i, (col, order)) in cols.iter().enumerate() {
+    for (i, (col, index_col)) in cols.iter().zip(&index.columns).enumerate() {
         if i > 0 {
             sql.push_str(", ");
         }
-        sql.push_str(col.1.name.as_ref().unwrap());
-        if *order == SortOrder::Desc {
+        match col.expr.as_ref() {
+            Expr::Id(_) | Expr::Name(_) => sql.push_str(&index_col.name),
+            expr => sql.push_str(&expr.to_string()),
+        }
+        if index_col.order == SortOrder::Desc {
             sql.push_str(" DESC");
         }
     }
     sql.push(')');
+    if let Some(where_clause) = &index.where_clause {
+        sql.push_str(" WHERE ");
+        sql.push_str(&where_clause.to_string());
+    }
     sql
 }
 
+/// Describes the columns of the UNIQUE index `index` for a UNIQUE constraint error, like SQLite:
+/// `t.a, t.b`, or `index 'idx'` if the index is on expressions.
+pub fn unique_index_description(index: &Index) -> String {
+    if index.columns.iter().any(|column| column.expr.is_some()) {
+        return format!("index '{}'", index.name);
+    }
+    index
+        .key_columns()
+        .iter()
+        .map(|column| format!("{}.{}", index.table_name, column.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Reads the columns of the row `cursor_id` points to that the keys of `index` are computed from
+/// into registers, for [emit_index_column_values] and [emit_partial_index_check].
+pub fn emit_index_row(
+    program: &mut ProgramBuilder,
+    resolver: &Resolver,
+    cursor_id: usize,
+    table: &BTreeTable,
+    index: &Index,
+) -> crate::Result<TriggerRow> {
+    let row = TriggerRow {
+        rowid_reg: program.alloc_register(),
+        columns_start: program.alloc_registers(table.columns.len()),
+    };
+    if table.has_rowid {
+        program.emit_insn(Insn::RowId {
+            cursor_id,
+            dest: row.rowid_reg,
+        });
+    }
+    for idx in index.referenced_columns(table) {
+        emit_table_column(
+            program,
+            resolver,
+            cursor_id,
+            table,
+            idx,
+            row.columns_start + idx,
+        )?;
+    }
+    Ok(row)
+}
+
+/// Computes the key of `index` for the row in the registers of `row` into the registers starting
+/// at `dest`. The rowid that completes the index record of a table with a rowid is not included.
+pub fn emit_index_column_values(
+    program: &mut ProgramBuilder,
+    resolver: &Resolver,
+    table: &BTreeTable,
+    index: &Index,
+    row: TriggerRow,
+    dest: usize,
+) -> crate::Result<()> {
+    for (i, index_column) in index.columns.iter().enumerate() {
+        let Some(expr) = &index_column.expr else {
+            program.emit_insn(Insn::Copy {
+                src_reg: if table.columns[index_column.pos_in_table].is_rowid_alias {
+                    row.rowid_reg
+                } else {
+                    row.columns_start + index_column.pos_in_table
+                },
+                dst_reg: dest + i,
+                extra_amount: 0,
+            });
+            continue;
+        };
+        let mut expr = expr.as_ref().clone();
+        rewrite_row_references(&mut expr, table, row)?;
+        translate_expr_no_constant_opt(
+            program,
+            None,
+            &expr,
+            dest + i,
+            resolver,
+            NoConstantOptReason::RegisterReuse,
+        )?;
+    }
+    Ok(())
+}
+
+/// Jumps to `skip_label` if `index` is a partial index and the row in the registers of `row` is
+/// not part of it, i.e. the WHERE clause of the index is false or NULL for the row.
+pub fn emit_partial_index_check(
+    program: &mut ProgramBuilder,
+    resolver: &Resolver,
+    table: &BTreeTable,
+    index: &Index,
+    row: TriggerRow,
+    skip_label: BranchOffset,
+) -> crate::Result<()> {
+    let Some(where_clause) = &index.where_clause else {
+        return Ok(());
+    };
+    let mut expr = where_clause.as_ref().clone();
+    rewrite_row_references(&mut expr, table, row)?;
+    let reg = program.alloc_register();
+    translate_expr_no_constant_opt(
+        program,
+        None,
+        &expr,
+        reg,
+        resolver,
+        NoConstantOptReason::RegisterReuse,
+    )?;
+    program.emit_insn(Insn::IfNot {
+        reg,
+        target_pc: skip_label,
+        jump_if_null: true,
+    });
+    Ok(())
+}
+
+/// Copies the PRIMARY KEY of the row in the registers of `row` into contiguous registers and
+/// returns the first one. For a WITHOUT ROWID `table`, this is the key of the row in its B-tree.
+pub fn emit_primary_key(
+    program: &mut ProgramBuilder,
+    table: &BTreeTable,
+    row: TriggerRow,
+) -> usize {
+    let key_start = program.alloc_registers(table.primary_key_columns.len());
+    for (i, (name, _)) in table.primary_key_columns.iter().enumerate() {
+        let (pos_in_table, _) = table
+            .get_column(name)
+            .expect("primary key column must exist in the table");
+        program.emit_insn(Insn::Copy {
+            src_reg: row.columns_start + pos_in_table,
+            dst_reg: key_start + i,
+            extra_amount: 0,
+        });
+    }
+    key_start
+}
+
+/// Reads the PRIMARY KEY that the entry `cursor_id` points to in `index`, an index of a WITHOUT
+/// ROWID `table`, stores instead of a rowid into contiguous registers and returns the first one.
+pub fn emit_index_primary_key(
+    program: &mut ProgramBuilder,
+    table: &BTreeTable,
+    index: &Index,
+    cursor_id: usize,
+) -> usize {
+    let key_start = program.alloc_registers(table.primary_key_columns.len());
+    for (i, (name, _)) in table.primary_key_columns.iter().enumerate() {
+        let (pos_in_table, _) = table
+            .get_column(name)
+            .expect("primary key column must exist in the table");
+        let column = index
+            .column_table_pos_to_index_pos(pos_in_table)
+            .expect("an index of a WITHOUT ROWID table must contain its primary key");
+        program.emit_insn(Insn::Column {
+            cursor_id,
+            column,
+            dest: key_start + i,
+            default: None,
+        });
+    }
+    key_start
+}
+
+/// Positions `cursor_id` on the row of the WITHOUT ROWID `table` whose PRIMARY KEY is in the
+/// registers starting at `key_start`, or jumps to `not_found_label` if there is no such row.
+pub fn emit_primary_key_seek(
+    program: &mut ProgramBuilder,
+    table: &BTreeTable,
+    cursor_id: usize,
+    key_start: usize,
+    not_found_label: BranchOffset,
+) {
+    program.emit_insn(Insn::NotFound {
+        cursor_id,
+        target_pc: not_found_label,
+        record_reg: key_start,
+        num_regs: table.primary_key_columns.len(),
+    });
+}
+
+/// Raises a PRIMARY KEY constraint error if the PRIMARY KEY of the row `cursor_id` points to in
+/// the WITHOUT ROWID `table` changes from `old_key` to `new_key` and another row already has
+/// `new_key`. The cursor points to the row again afterwards, see [emit_primary_key_seek].
+pub fn emit_primary_key_change_check(
+    program: &mut ProgramBuilder,
+    table: &BTreeTable,
+    cursor_id: usize,
+    old_key: usize,
+    new_key: usize,
+    not_found_label: BranchOffset,
+) {
+    let check_label = program.allocate_label();
+    let done_label = program.allocate_label();
+    for (i, (name, _)) in table.primary_key_columns.iter().enumerate() {
+        let (_, column) = table
+            .get_column(name)
+            .expect("primary key column must exist in the table");
+        program.emit_insn(Insn::Ne {
+            lhs: new_key + i,
+            rhs: old_key + i,
+            target_pc: check_label,
+            flags: CmpInsFlags::default(),
+            collation: column.collation.clone(),
+        });
+    }
+    program.emit_insn(Insn::Goto {
+        target_pc: done_label,
+    });
+    program.preassign_label_to_next_insn(check_label);
+    program.emit_insn(Insn::NoConflict {
+        cursor_id,
+        target_pc: done_label,
+        record_reg: new_key,
+        num_regs: table.primary_key_columns.len(),
+    });
+    let clustered_index = table
+        .clustered_index()
+        .expect("a WITHOUT ROWID table has a clustered index");
+    program.emit_insn(Insn::Halt {
+        err_code: SQLITE_CONSTRAINT_PRIMARYKEY,
+        description: unique_index_description(&clustered_index),
+    });
+    program.preassign_label_to_next_insn(done_label);
+    emit_primary_key_seek(program, table, cursor_id, old_key, not_found_label);
+}
+
 pub fn translate_drop_index(
-    idx_name: &str,
+    idx_name: &QualifiedName,
     if_exists: bool,
     schema: &Schema,
     syms: &SymbolTable,
+    connection: &Arc<crate::Connection>,
     mut program: ProgramBuilder,
 ) -> crate::Result<ProgramBuilder> {
     if !schema.indexes_enabled() {
@@ -323,7 +616,9 @@ pub fn translate_drop_index(
             "DROP INDEX is disabled by default. Run with `--experimental-indexes` to enable this feature."
         );
     }
-    let idx_name = normalize_ident(idx_name);
+    let (attached_db, attached_schema) = resolve_schema_target(connection, idx_name)?;
+    let schema = attached_schema.as_deref().unwrap_or(schema);
+    let idx_name = normalize_ident(idx_name.name.as_str());
     let opts = crate::vdbe::builder::ProgramBuilderOpts {
         num_cursors: 5,
         approx_num_insns: 40,
@@ -367,7 +662,20 @@ pub fn translate_drop_index(
         }
     }
 
-    let cdc_table = prepare_cdc_if_necessary(&mut program, schema, SQLITE_TABLEID)?;
+    // Indexes of TEMP tables are dropped from the temp database.
+    let temporary = maybe_index.is_some_and(|idx| idx.temporary);
+    let db = if temporary { 1 } else { attached_db };
+    let schema_table_name = if temporary {
+        TEMP_SCHEMA_TABLE_NAME
+    } else {
+        SQLITE_TABLEID
+    };
+
+    let cdc_table = if db != 0 {
+        None
+    } else {
+        prepare_cdc_if_necessary(&mut program, schema, SQLITE_TABLEID)?
+    };
 
     // According to sqlite should emit Null instruction
     // but why?
@@ -383,7 +691,7 @@ pub fn translate_drop_index(
     let row_id_reg = program.alloc_register();
 
     // We're going to use this cursor to search through sqlite_schema
-    let sqlite_table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
+    let sqlite_table = schema.get_btree_table(schema_table_name).unwrap();
     let sqlite_schema_cursor_id =
         program.alloc_cursor_id(CursorType::BTreeTable(sqlite_table.clone()));
 
@@ -391,7 +699,7 @@ pub fn translate_drop_index(
     program.emit_insn(Insn::OpenWrite {
         cursor_id: sqlite_schema_cursor_id,
         root_page: RegisterOrLiteral::Literal(sqlite_table.root_page),
-        db: 0,
+        db,
     });
 
     let loop_start_label = program.allocate_label();
@@ -467,7 +775,7 @@ pub fn translate_drop_index(
 
     program.emit_insn(Insn::Delete {
         cursor_id: sqlite_schema_cursor_id,
-        table_name: "sqlite_schema".to_string(),
+        table_name: schema_table_name.to_string(),
     });
 
     program.resolve_label(next_label, program.offset());
@@ -478,25 +786,27 @@ pub fn translate_drop_index(
 
     program.resolve_label(loop_end_label, program.offset());
 
-    program.emit_insn(Insn::SetCookie {
-        db: 0,
-        cookie: Cookie::SchemaVersion,
-        value: schema.schema_version as i32 + 1,
-        p5: 0,
-    });
+    if !temporary {
+        program.emit_insn(Insn::SetCookie {
+            db,
+            cookie: Cookie::SchemaVersion,
+            value: schema.schema_version as i32 + 1,
+            p5: 0,
+        });
+    }
 
     // Destroy index btree
     program.emit_insn(Insn::Destroy {
         root: maybe_index.unwrap().root_page,
         former_root_reg: 0,
-        is_temp: 0,
+        is_temp: db,
     });
 
     // Remove from the Schema any mention of the index
     if let Some(idx) = maybe_index {
         program.emit_insn(Insn::DropIndex {
             index: idx.clone(),
-            db: 0,
+            db,
         });
     }
 
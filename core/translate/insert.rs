@@ -6,13 +6,25 @@ use turso_parser::ast::{
 
 use crate::error::{SQLITE_CONSTRAINT_NOTNULL, SQLITE_CONSTRAINT_PRIMARYKEY};
 use crate::schema::{self, Table};
+use crate::translate::check::emit_check_constraints;
 use crate::translate::emitter::{
     emit_cdc_insns, emit_cdc_patch_record, prepare_cdc_if_necessary, OperationMode,
 };
 use crate::translate::expr::{
     emit_returning_results, process_returning_clause, ReturningValueRegisters,
 };
-use crate::translate::planner::ROWID;
+use crate::translate::foreign_key::{
+    emit_child_key_checks, emit_parent_key_checks, prepare_foreign_keys,
+};
+use crate::translate::generated::{emit_generated_columns, emit_record_registers};
+use crate::translate::index::{
+    emit_index_column_values, emit_index_primary_key, emit_partial_index_check, emit_primary_key,
+    emit_primary_key_seek, unique_index_description,
+};
+use crate::translate::planner::{resolve_write_target, ROWID};
+use crate::translate::trigger::{
+    compile_triggers, emit_trigger_programs, translate_instead_of_insert, TriggerRow,
+};
 use crate::translate::upsert::{
     collect_set_clauses_for_upsert, emit_upsert, upsert_matches_index, upsert_matches_pk,
 };
@@ -21,7 +33,7 @@ use crate::vdbe::builder::ProgramBuilderOpts;
 use crate::vdbe::insn::{IdxInsertFlags, InsertFlags, RegisterOrLiteral};
 use crate::vdbe::BranchOffset;
 use crate::{
-    schema::{Column, Schema},
+    schema::Column,
     vdbe::{
         builder::{CursorType, ProgramBuilder},
         insn::Insn,
@@ -43,7 +55,6 @@ struct TempTableCtx {
 
 #[allow(clippy::too_many_arguments)]
 pub fn translate_insert(
-    schema: &Schema,
     with: Option<With>,
     on_conflict: Option<ResolveType>,
     tbl_name: QualifiedName,
@@ -66,6 +77,8 @@ pub fn translate_insert(
     if on_conflict.is_some() {
         crate::bail_parse_error!("ON CONFLICT clause is not supported");
     }
+    let (database_id, target_schema) = resolve_write_target(connection, &tbl_name)?;
+    let schema = target_schema.as_ref();
 
     if schema.table_has_indexes(&tbl_name.name.to_string()) && !schema.indexes_enabled() {
         // Let's disable altering a table with indices altogether instead of checking column by
@@ -75,6 +88,20 @@ pub fn translate_insert(
         );
     }
     let table_name = &tbl_name.name;
+    if schema.get_view(table_name.as_str()).is_some() {
+        if !returning.is_empty() {
+            crate::bail_parse_error!("RETURNING is not supported for views");
+        }
+        return translate_instead_of_insert(
+            schema,
+            table_name.as_str(),
+            columns,
+            body,
+            syms,
+            program,
+            connection,
+        );
+    }
     let table = match schema.get_table(table_name.as_str()) {
         Some(table) => table,
         None => crate::bail_parse_error!("no such table: {}", table_name),
@@ -97,10 +124,6 @@ pub fn translate_insert(
     let Some(btree_table) = table.btree() else {
         crate::bail_parse_error!("no such table: {}", table_name);
     };
-    if !btree_table.has_rowid {
-        crate::bail_parse_error!("INSERT into WITHOUT ROWID table is not supported");
-    }
-
     let root_page = btree_table.root_page;
 
     let mut values: Option<Vec<Box<Expr>>> = None;
@@ -150,7 +173,32 @@ pub fn translate_insert(
     let loop_start_label = program.allocate_label();
     let row_done_label = program.allocate_label();
 
-    let cdc_table = prepare_cdc_if_necessary(&mut program, schema, table.get_name())?;
+    // Changes to attached databases are not captured.
+    let cdc_table = if database_id > 1 {
+        None
+    } else {
+        prepare_cdc_if_necessary(&mut program, schema, table.get_name())?
+    };
+
+    let triggers = compile_triggers(
+        schema,
+        syms,
+        connection,
+        &program,
+        table.get_name(),
+        table.columns(),
+        ast::TriggerEvent::Insert,
+        &[],
+    )?;
+    let foreign_keys = prepare_foreign_keys(
+        schema,
+        syms,
+        connection,
+        &program,
+        &btree_table,
+        ast::TriggerEvent::Insert,
+        &[],
+    )?;
 
     // Process RETURNING clause using shared module
     let (mut result_columns, _) = process_returning_clause(
@@ -215,7 +263,7 @@ pub fn translate_insert(
                  ** of the tables being read by the SELECT statement.  Also use a
                  ** temp table in the case of row triggers.
                  */
-                if program.is_table_open(&table) {
+                if program.is_table_open(&table) || !triggers.is_empty() {
                     let temp_cursor_id =
                         program.alloc_cursor_id(CursorType::BTreeTable(btree_table.clone()));
                     temp_table_ctx = Some(TempTableCtx {
@@ -274,13 +322,13 @@ pub fn translate_insert(
                     program.emit_insn(Insn::OpenWrite {
                         cursor_id,
                         root_page: RegisterOrLiteral::Literal(root_page),
-                        db: 0,
+                        db: database_id,
                     });
                 } else {
                     program.emit_insn(Insn::OpenWrite {
                         cursor_id,
                         root_page: RegisterOrLiteral::Literal(root_page),
-                        db: 0,
+                        db: database_id,
                     });
 
                     // Main loop
@@ -332,7 +380,7 @@ pub fn translate_insert(
         program.emit_insn(Insn::OpenWrite {
             cursor_id,
             root_page: RegisterOrLiteral::Literal(root_page),
-            db: 0,
+            db: database_id,
         });
 
         translate_rows_single(&mut program, &values.unwrap(), &insertion, &resolver)?;
@@ -343,10 +391,28 @@ pub fn translate_insert(
         program.emit_insn(Insn::OpenWrite {
             cursor_id: idx_cursor.2,
             root_page: idx_cursor.1.into(),
-            db: 0,
+            db: database_id,
         });
     }
 
+    if !triggers.before.is_empty() {
+        if !insertion.key.is_provided_by_user() {
+            // The rowid is not allocated until after the BEFORE triggers have run
+            program.emit_null(insertion.key_register(), None);
+        }
+        emit_trigger_programs(
+            &mut program,
+            &triggers.before,
+            table.columns(),
+            Some(TriggerRow {
+                rowid_reg: insertion.key_register(),
+                columns_start: insertion.first_col_register(),
+            }),
+            None,
+            row_done_label,
+        );
+    }
+
     // Common record insertion logic for both single and multiple rows
     let has_user_provided_rowid = insertion.key.is_provided_by_user();
     let check_rowid_is_integer_label = if has_user_provided_rowid {
@@ -361,12 +427,17 @@ pub fn translate_insert(
         });
     }
 
-    // Create new rowid if a) not provided by user or b) provided by user but is NULL
-    program.emit_insn(Insn::NewRowid {
-        cursor: cursor_id,
-        rowid_reg: insertion.key_register(),
-        prev_largest_reg: 0,
-    });
+    if btree_table.has_rowid {
+        // Create new rowid if a) not provided by user or b) provided by user but is NULL
+        program.emit_insn(Insn::NewRowid {
+            cursor: cursor_id,
+            rowid_reg: insertion.key_register(),
+            prev_largest_reg: 0,
+        });
+    } else {
+        // A WITHOUT ROWID table has no rowid, it is keyed by its PRIMARY KEY.
+        program.emit_null(insertion.key_register(), None);
+    }
 
     if let Some(must_be_int_label) = check_rowid_is_integer_label {
         program.resolve_label(must_be_int_label, program.offset());
@@ -376,6 +447,12 @@ pub fn translate_insert(
         });
     }
 
+    let new_row = TriggerRow {
+        rowid_reg: insertion.key_register(),
+        columns_start: insertion.first_col_register(),
+    };
+    emit_generated_columns(&mut program, &resolver, &btree_table, new_row)?;
+
     let emit_halt_with_constraint = |program: &mut ProgramBuilder, col_name: &str| {
         let mut description = String::with_capacity(table_name.as_str().len() + col_name.len() + 2);
         description.push_str(table_name.as_str());
@@ -440,6 +517,60 @@ pub fn translate_insert(
         program.preassign_label_to_next_insn(make_record_label);
     }
 
+    // Check uniqueness of the PRIMARY KEY of a WITHOUT ROWID table, the key of its B-tree.
+    if let Some(clustered_index) = btree_table.clustered_index() {
+        let make_record_label = program.allocate_label();
+        let key_start = emit_primary_key(&mut program, &btree_table, new_row);
+        program.emit_insn(Insn::NoConflict {
+            cursor_id,
+            target_pc: make_record_label,
+            record_reg: key_start,
+            num_regs: btree_table.primary_key_columns.len(),
+        });
+        // emit Halt for every case *except* when upsert handles the conflict
+        'emit_halt: {
+            if let Some(ref mut upsert) = upsert_opt.as_mut() {
+                if upsert_matches_index(upsert, &clustered_index, &table) {
+                    match upsert.do_clause {
+                        UpsertDo::Nothing => {
+                            program.emit_insn(Insn::Goto {
+                                target_pc: row_done_label,
+                            });
+                        }
+                        UpsertDo::Set {
+                            ref mut sets,
+                            ref mut where_clause,
+                        } => {
+                            let mut rewritten_sets = collect_set_clauses_for_upsert(&table, sets)?;
+                            // NoConflict left the cursor on the conflicting row
+                            emit_upsert(
+                                &mut program,
+                                schema,
+                                &table,
+                                &insertion,
+                                cursor_id,
+                                insertion.key_register(),
+                                &mut rewritten_sets,
+                                where_clause,
+                                &resolver,
+                                &idx_cursors,
+                                &mut result_columns,
+                                cdc_table.as_ref().map(|c| c.0),
+                                row_done_label,
+                            )?;
+                        }
+                    }
+                    break 'emit_halt;
+                }
+            }
+            program.emit_insn(Insn::Halt {
+                err_code: SQLITE_CONSTRAINT_PRIMARYKEY,
+                description: unique_index_description(&clustered_index),
+            });
+        }
+        program.preassign_label_to_next_insn(make_record_label);
+    }
+
     match table.btree() {
         Some(t) if t.is_strict => {
             program.emit_insn(Insn::TypeCheck {
@@ -452,11 +583,14 @@ pub fn translate_insert(
         _ => (),
     }
 
+    emit_check_constraints(&mut program, &resolver, &btree_table, new_row)?;
+
+    if !foreign_keys.is_empty() {
+        emit_child_key_checks(&mut program, &foreign_keys, new_row, 1);
+        emit_parent_key_checks(&mut program, &foreign_keys, new_row, -1);
+    }
+
     for index in schema.get_indices(table_name.as_str()) {
-        let column_mappings = index
-            .columns
-            .iter()
-            .map(|idx_col| insertion.get_col_mapping_by_name(&idx_col.name));
         // find which cursor we opened earlier for this index
         let idx_cursor_id = idx_cursors
             .iter()
@@ -464,35 +598,45 @@ pub fn translate_insert(
             .map(|(_, _, c_id)| *c_id)
             .expect("no cursor found for index");
 
+        // rows that are not part of a partial index are not inserted into it
+        let skip_index_label = program.allocate_label();
+        emit_partial_index_check(
+            &mut program,
+            &resolver,
+            &btree_table,
+            index,
+            new_row,
+            skip_index_label,
+        )?;
+
         let num_cols = index.columns.len();
+        // the index record ends with the rowid, if the table has one
+        let num_regs = num_cols + btree_table.has_rowid as usize;
         // allocate scratch registers for the index columns plus rowid
-        let idx_start_reg = program.alloc_registers(num_cols + 1);
-
-        // copy each index column from the table's column registers into these scratch regs
-        for (i, column_mapping) in column_mappings.clone().enumerate() {
-            // copy from the table's column register over to the index's scratch register
-            let Some(col_mapping) = column_mapping else {
-                return Err(crate::LimboError::PlanningError(
-                    "Column not found in INSERT".to_string(),
-                ));
-            };
+        let idx_start_reg = program.alloc_registers(num_regs);
+
+        // compute each index column from the table's column registers into these scratch regs
+        emit_index_column_values(
+            &mut program,
+            &resolver,
+            &btree_table,
+            index,
+            new_row,
+            idx_start_reg,
+        )?;
+        // last register is the rowid
+        if btree_table.has_rowid {
             program.emit_insn(Insn::Copy {
-                src_reg: col_mapping.register,
-                dst_reg: idx_start_reg + i,
+                src_reg: insertion.key_register(),
+                dst_reg: idx_start_reg + num_cols,
                 extra_amount: 0,
             });
         }
-        // last register is the rowid
-        program.emit_insn(Insn::Copy {
-            src_reg: insertion.key_register(),
-            dst_reg: idx_start_reg + num_cols,
-            extra_amount: 0,
-        });
 
         let record_reg = program.alloc_register();
         program.emit_insn(Insn::MakeRecord {
             start_reg: idx_start_reg,
-            count: num_cols + 1,
+            count: num_regs,
             dest_reg: record_reg,
             index_name: Some(index.name.clone()),
         });
@@ -503,20 +647,9 @@ pub fn translate_insert(
                 cursor_id: idx_cursor_id,
                 target_pc: label_idx_insert,
                 record_reg: idx_start_reg,
-                num_regs: num_cols,
+                num_regs: index.key_columns().len(),
             });
-            let column_names = index.columns.iter().enumerate().fold(
-                String::with_capacity(50),
-                |mut accum, (idx, column)| {
-                    if idx > 0 {
-                        accum.push_str(", ");
-                    }
-                    accum.push_str(&index.name);
-                    accum.push('.');
-                    accum.push_str(&column.name);
-                    accum
-                },
-            );
+            let column_names = unique_index_description(index);
 
             // again, emit halt for every case *except* when upsert handles the conflict
             'emit_halt: {
@@ -535,10 +668,28 @@ pub fn translate_insert(
                                 let mut rewritten_sets =
                                     collect_set_clauses_for_upsert(&table, sets)?;
                                 let conflict_rowid_reg = program.alloc_register();
-                                program.emit_insn(Insn::IdxRowId {
-                                    cursor_id: idx_cursor_id,
-                                    dest: conflict_rowid_reg,
-                                });
+                                if btree_table.has_rowid {
+                                    program.emit_insn(Insn::IdxRowId {
+                                        cursor_id: idx_cursor_id,
+                                        dest: conflict_rowid_reg,
+                                    });
+                                } else {
+                                    // the index entry locates the row by its PRIMARY KEY
+                                    program.emit_null(conflict_rowid_reg, None);
+                                    let key_start = emit_index_primary_key(
+                                        &mut program,
+                                        &btree_table,
+                                        index,
+                                        idx_cursor_id,
+                                    );
+                                    emit_primary_key_seek(
+                                        &mut program,
+                                        &btree_table,
+                                        cursor_id,
+                                        key_start,
+                                        row_done_label,
+                                    );
+                                }
                                 emit_upsert(
                                     &mut program,
                                     schema,
@@ -572,10 +723,11 @@ pub fn translate_insert(
             cursor_id: idx_cursor_id,
             record_reg,
             unpacked_start: Some(idx_start_reg), // TODO: enable optimization
-            unpacked_count: Some((num_cols + 1) as u16),
+            unpacked_count: Some(num_regs as u16),
             // TODO: figure out how to determine whether or not we need to seek prior to insert.
             flags: IdxInsertFlags::new().nchange(true),
         });
+        program.preassign_label_to_next_insn(skip_index_label);
     }
 
     for column_mapping in insertion
@@ -615,19 +767,32 @@ pub fn translate_insert(
         });
     }
     // Create and insert the record
+    let (record_start_reg, record_count) =
+        emit_record_registers(&mut program, &btree_table, insertion.first_col_register());
     program.emit_insn(Insn::MakeRecord {
-        start_reg: insertion.first_col_register(),
-        count: insertion.col_mappings.len(),
+        start_reg: record_start_reg,
+        count: record_count,
         dest_reg: insertion.record_register(),
         index_name: None,
     });
-    program.emit_insn(Insn::Insert {
-        cursor: cursor_id,
-        key_reg: insertion.key_register(),
-        record_reg: insertion.record_register(),
-        flag: InsertFlags::new(),
-        table_name: table_name.to_string(),
-    });
+    if btree_table.has_rowid {
+        program.emit_insn(Insn::Insert {
+            cursor: cursor_id,
+            key_reg: insertion.key_register(),
+            record_reg: insertion.record_register(),
+            flag: InsertFlags::new(),
+            table_name: table_name.to_string(),
+        });
+    } else {
+        // the record of a WITHOUT ROWID table is the key of its clustered index
+        program.emit_insn(Insn::IdxInsert {
+            cursor_id,
+            record_reg: insertion.record_register(),
+            unpacked_start: Some(record_start_reg),
+            unpacked_count: Some(record_count as u16),
+            flags: IdxInsertFlags::new().nchange(true),
+        });
+    }
 
     // Emit update in the CDC table if necessary (after the INSERT updated the table)
     if let Some((cdc_cursor_id, _)) = &cdc_table {
@@ -656,6 +821,18 @@ pub fn translate_insert(
         )?;
     }
 
+    emit_trigger_programs(
+        &mut program,
+        &triggers.after,
+        table.columns(),
+        Some(TriggerRow {
+            rowid_reg: insertion.key_register(),
+            columns_start: insertion.first_col_register(),
+        }),
+        None,
+        row_done_label,
+    );
+
     // Emit RETURNING results if specified
     if !result_columns.is_empty() {
         let value_registers = ReturningValueRegisters {
@@ -707,6 +884,7 @@ pub const ROWID_COLUMN: &Column = &Column {
     unique: false,
     collation: None,
     hidden: false,
+    generated: None,
 };
 
 /// Represents how a table should be populated during an INSERT.
@@ -848,18 +1026,22 @@ fn build_insertion<'a>(
 
     if columns.is_empty() {
         // Case 1: No columns specified - map values to columns in order
-        if num_values != table_columns.iter().filter(|c| !c.hidden).count() {
+        let num_columns = table_columns
+            .iter()
+            .filter(|c| !c.hidden && c.generated.is_none())
+            .count();
+        if num_values != num_columns {
             crate::bail_parse_error!(
                 "table {} has {} columns but {} values were supplied",
                 &table.get_name(),
-                table_columns.len(),
+                num_columns,
                 num_values
             );
         }
         let mut value_idx = 0;
         for (i, col) in table_columns.iter().enumerate() {
-            if col.hidden {
-                // Hidden columns are not taken into account.
+            if col.hidden || col.generated.is_some() {
+                // Hidden and generated columns are not taken into account.
                 continue;
             }
             if col.is_rowid_alias {
@@ -880,6 +1062,12 @@ fn build_insertion<'a>(
             let column_name = normalize_ident(column_name.as_str());
             if let Some((idx_in_table, col_in_table)) = table.get_column_by_name(&column_name) {
                 // Named column
+                if col_in_table.generated.is_some() {
+                    crate::bail_parse_error!(
+                        "cannot INSERT into generated column \"{}\"",
+                        column_name
+                    );
+                }
                 if col_in_table.is_rowid_alias {
                     insertion_key = InsertionKey::RowidAlias(ColMapping {
                         column: col_in_table,
@@ -889,7 +1077,7 @@ fn build_insertion<'a>(
                 } else {
                     column_mappings[idx_in_table].value_index = Some(value_index);
                 }
-            } else if column_name == ROWID {
+            } else if column_name == ROWID && table.btree().is_none_or(|t| t.has_rowid) {
                 // Explicit use of the 'rowid' keyword
                 if let Some(col_in_table) = table.columns().iter().find(|c| c.is_rowid_alias) {
                     insertion_key = InsertionKey::RowidAlias(ColMapping {
@@ -941,7 +1129,13 @@ fn translate_rows_multiple<'short, 'long: 'short>(
     let translate_value_fn =
         |prg: &mut ProgramBuilder, value_index: usize, column_register: usize| {
             if let Some(temp_table_ctx) = temp_table_ctx {
-                prg.emit_column_or_rowid(temp_table_ctx.cursor_id, value_index, column_register);
+                // the records of the temp table hold the values of the rows to insert, in order
+                prg.emit_insn(Insn::Column {
+                    cursor_id: temp_table_ctx.cursor_id,
+                    column: value_index,
+                    dest: column_register,
+                    default: None,
+                });
             } else {
                 prg.emit_insn(Insn::Copy {
                     src_reg: yield_reg + value_index,
@@ -1051,8 +1245,9 @@ fn translate_column(
         program.emit_insn(Insn::SoftNull {
             reg: column_register,
         });
-    } else if column.hidden {
+    } else if column.hidden || column.generated.is_some() {
         // Emit NULL for not-explicitly-mentioned hidden columns, even ignoring DEFAULT.
+        // Generated columns are computed once the rest of the row is known.
         program.emit_insn(Insn::Null {
             dest: column_register,
             dest_end: None,
@@ -1120,6 +1315,7 @@ fn translate_virtual_table_insert(
 
     let cursor_id = program.alloc_cursor_id(CursorType::VirtualTable(virtual_table.clone()));
 
+    program.emit_insn(Insn::VBegin { cursor_id });
     program.emit_insn(Insn::VUpdate {
         cursor_id,
         arg_count: insertion.col_mappings.len() + 2, // +1 for NULL, +1 for rowid
@@ -13,9 +13,12 @@ pub fn translate_integrity_check(
 ) -> crate::Result<()> {
     let mut root_pages = Vec::with_capacity(schema.tables.len() + schema.indexes.len());
     // Collect root pages to run integrity check on
+    // TEMP tables live in the temp database, not in the file being checked.
     for table in schema.tables.values() {
         if let crate::schema::Table::BTree(table) = table.as_ref() {
-            root_pages.push(table.root_page);
+            if !table.temporary {
+                root_pages.push(table.root_page);
+            }
         };
     }
     let message_register = program.alloc_register();
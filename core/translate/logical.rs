@@ -9,6 +9,7 @@
 //! from SQL AST nodes.
 use crate::function::AggFunc;
 use crate::schema::{Schema, Type};
+use crate::translate::expr::sanitize_string;
 use crate::types::Value;
 use crate::{LimboError, Result};
 use std::collections::HashMap;
@@ -66,8 +67,8 @@ pub enum LogicalPlan {
     Filter(Filter),
     /// Aggregate - GROUP BY with aggregate functions
     Aggregate(Aggregate),
-    // TODO: Join - combining two relations (not yet implemented)
-    // Join(Join),
+    /// Join - combining two relations
+    Join(Join),
     /// Sort - ORDER BY clause
     Sort(Sort),
     /// Limit - LIMIT/OFFSET clause
@@ -95,7 +96,7 @@ impl LogicalPlan {
             LogicalPlan::Projection(p) => &p.schema,
             LogicalPlan::Filter(f) => f.input.schema(),
             LogicalPlan::Aggregate(a) => &a.schema,
-            // LogicalPlan::Join(j) => &j.schema,
+            LogicalPlan::Join(j) => &j.schema,
             LogicalPlan::Sort(s) => s.input.schema(),
             LogicalPlan::Limit(l) => l.input.schema(),
             LogicalPlan::TableScan(t) => &t.schema,
@@ -133,26 +134,29 @@ pub struct Aggregate {
     pub schema: SchemaRef,
 }
 
-// TODO: Join operator (not yet implemented)
-// #[derive(Debug, Clone, PartialEq)]
-// pub struct Join {
-//     pub left: Arc<LogicalPlan>,
-//     pub right: Arc<LogicalPlan>,
-//     pub join_type: JoinType,
-//     pub on: Vec<(LogicalExpr, LogicalExpr)>, // Equijoin conditions
-//     pub filter: Option<LogicalExpr>,         // Additional filter conditions
-//     pub schema: SchemaRef,
-// }
-
-// TODO: Types of joins (not yet implemented)
-// #[derive(Debug, Clone, Copy, PartialEq)]
-// pub enum JoinType {
-//     Inner,
-//     Left,
-//     Right,
-//     Full,
-//     Cross,
-// }
+/// Join operator - combines two relations
+///
+/// The output schema is the left schema followed by the right schema. Columns coming from a table
+/// are qualified with the table name or alias (e.g. `o.id`), so both sides can be told apart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub left: Arc<LogicalPlan>,
+    pub right: Arc<LogicalPlan>,
+    pub join_type: JoinType,
+    pub on: Vec<(LogicalExpr, LogicalExpr)>, // Equijoin conditions
+    pub filter: Option<LogicalExpr>,         // Additional filter conditions
+    pub schema: SchemaRef,
+}
+
+/// Types of joins
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinType {
+    Inner,
+    Left,
+    Right,
+    Full,
+    Cross,
+}
 
 /// Sort operator - ORDER BY
 #[derive(Debug, Clone, PartialEq)]
@@ -295,9 +299,10 @@ pub enum LogicalExpr {
         high: Box<LogicalExpr>,
         negated: bool,
     },
-    /// LIKE pattern matching
+    /// LIKE, GLOB, MATCH and REGEXP pattern matching
     Like {
         expr: Box<LogicalExpr>,
+        op: LikeOperator,
         pattern: Box<LogicalExpr>,
         escape: Option<char>,
         negated: bool,
@@ -342,6 +347,9 @@ pub type BinaryOperator = ast::Operator;
 /// Type alias for unary operators
 pub type UnaryOperator = ast::UnaryOperator;
 
+/// Type alias for pattern matching operators
+pub type LikeOperator = ast::LikeOperator;
+
 /// Type alias for aggregate functions
 pub type AggregateFunction = AggFunc;
 
@@ -521,8 +529,10 @@ impl<'a> LogicalPlanBuilder<'a> {
 
         // Handle JOINs
         if !from.joins.is_empty() {
+            plan = Self::qualify_columns(plan, Self::table_qualifier(&from.select));
             for join in &from.joins {
                 let right = self.build_select_table(&join.table)?;
+                let right = Self::qualify_columns(right, Self::table_qualifier(&join.table));
                 plan = self.build_join(plan, right, &join.operator, &join.constraint)?;
             }
         }
@@ -561,17 +571,197 @@ impl<'a> LogicalPlanBuilder<'a> {
         }
     }
 
+    // Name used to qualify the columns of a table in a join: the alias if present, otherwise the
+    // table name
+    fn table_qualifier(table: &ast::SelectTable) -> Option<String> {
+        match table {
+            ast::SelectTable::Table(name, alias, _) => Some(match alias {
+                Some(ast::As::As(alias) | ast::As::Elided(alias)) => Self::name_to_string(alias),
+                None => Self::name_to_string(&name.name),
+            }),
+            ast::SelectTable::Select(_, Some(ast::As::As(alias) | ast::As::Elided(alias)))
+            | ast::SelectTable::Sub(_, Some(ast::As::As(alias) | ast::As::Elided(alias))) => {
+                Some(Self::name_to_string(alias))
+            }
+            _ => None,
+        }
+    }
+
+    // Rename the output columns of a join input to `qualifier.column`
+    fn qualify_columns(plan: LogicalPlan, qualifier: Option<String>) -> LogicalPlan {
+        let Some(qualifier) = qualifier else {
+            return plan;
+        };
+        let qualified = Arc::new(LogicalSchema::new(
+            plan.schema()
+                .columns
+                .iter()
+                .map(|(name, typ)| (format!("{qualifier}.{name}"), *typ))
+                .collect(),
+        ));
+        match plan {
+            LogicalPlan::TableScan(scan) => LogicalPlan::TableScan(TableScan {
+                schema: qualified,
+                ..scan
+            }),
+            LogicalPlan::CTERef(cte) => LogicalPlan::CTERef(CTERef {
+                schema: qualified,
+                ..cte
+            }),
+            other => {
+                let exprs = other
+                    .schema()
+                    .columns
+                    .iter()
+                    .map(|(name, _)| LogicalExpr::Column(Column::new(name.clone())))
+                    .collect();
+                LogicalPlan::Projection(Projection {
+                    input: Arc::new(other),
+                    exprs,
+                    schema: qualified,
+                })
+            }
+        }
+    }
+
     // Build JOIN
     fn build_join(
         &mut self,
-        _left: LogicalPlan,
-        _right: LogicalPlan,
-        _op: &ast::JoinOperator,
-        _constraint: &Option<ast::JoinConstraint>,
+        left: LogicalPlan,
+        right: LogicalPlan,
+        op: &ast::JoinOperator,
+        constraint: &Option<ast::JoinConstraint>,
     ) -> Result<LogicalPlan> {
-        Err(LimboError::ParseError(
-            "JOINs are not yet supported in logical plans".to_string(),
-        ))
+        let mut join_type = match op {
+            ast::JoinOperator::Comma => JoinType::Cross,
+            ast::JoinOperator::TypedJoin(None) => JoinType::Inner,
+            ast::JoinOperator::TypedJoin(Some(jt)) => {
+                if jt.contains(ast::JoinType::NATURAL) {
+                    return Err(LimboError::ParseError(
+                        "NATURAL JOINs are not yet supported in logical plans".to_string(),
+                    ));
+                }
+                if jt.contains(ast::JoinType::LEFT | ast::JoinType::RIGHT) {
+                    JoinType::Full
+                } else if jt.contains(ast::JoinType::LEFT) {
+                    JoinType::Left
+                } else if jt.contains(ast::JoinType::RIGHT) {
+                    JoinType::Right
+                } else if jt.contains(ast::JoinType::CROSS) {
+                    JoinType::Cross
+                } else {
+                    JoinType::Inner
+                }
+            }
+        };
+
+        let mut columns = left.schema().columns.clone();
+        columns.extend(right.schema().columns.iter().cloned());
+        let schema = Arc::new(LogicalSchema::new(columns));
+
+        let (on, filter) = match constraint {
+            None => (Vec::new(), None),
+            Some(ast::JoinConstraint::On(expr)) => {
+                let condition = self.build_expr(expr, &schema)?;
+                Self::split_join_condition(condition, left.schema(), right.schema())
+            }
+            Some(ast::JoinConstraint::Using(names)) => {
+                let mut on = Vec::new();
+                for name in names {
+                    let name = Self::name_to_string(name);
+                    let left_col = Self::resolve_column(name.clone(), None, left.schema())?;
+                    let right_col = Self::resolve_column(name.clone(), None, right.schema())?;
+                    if left.schema().find_column(&left_col.name).is_none()
+                        || right.schema().find_column(&right_col.name).is_none()
+                    {
+                        return Err(LimboError::ParseError(format!(
+                            "cannot join using column {name} - column not present in both tables"
+                        )));
+                    }
+                    on.push((
+                        LogicalExpr::Column(left_col),
+                        LogicalExpr::Column(right_col),
+                    ));
+                }
+                (on, None)
+            }
+        };
+
+        // A CROSS JOIN with a join condition is just an inner join
+        if join_type == JoinType::Cross && (!on.is_empty() || filter.is_some()) {
+            join_type = JoinType::Inner;
+        }
+
+        Ok(LogicalPlan::Join(Join {
+            left: Arc::new(left),
+            right: Arc::new(right),
+            join_type,
+            on,
+            filter,
+            schema,
+        }))
+    }
+
+    // Split a join condition into equijoin pairs (left column = right column) and the remaining
+    // conjuncts, which are AND-ed together into a filter
+    fn split_join_condition(
+        condition: LogicalExpr,
+        left_schema: &SchemaRef,
+        right_schema: &SchemaRef,
+    ) -> (Vec<(LogicalExpr, LogicalExpr)>, Option<LogicalExpr>) {
+        let mut conjuncts = Vec::new();
+        let mut pending = vec![condition];
+        while let Some(expr) = pending.pop() {
+            match expr {
+                LogicalExpr::BinaryExpr {
+                    left,
+                    op: BinaryOperator::And,
+                    right,
+                } => {
+                    pending.push(*right);
+                    pending.push(*left);
+                }
+                other => conjuncts.push(other),
+            }
+        }
+
+        let mut on = Vec::new();
+        let mut filter: Option<LogicalExpr> = None;
+        for conjunct in conjuncts {
+            if let LogicalExpr::BinaryExpr {
+                left,
+                op: BinaryOperator::Equals,
+                right,
+            } = &conjunct
+            {
+                if let (LogicalExpr::Column(a), LogicalExpr::Column(b)) =
+                    (left.as_ref(), right.as_ref())
+                {
+                    let a_left = left_schema.find_column(&a.name).is_some();
+                    let b_right = right_schema.find_column(&b.name).is_some();
+                    let a_right = right_schema.find_column(&a.name).is_some();
+                    let b_left = left_schema.find_column(&b.name).is_some();
+                    if a_left && b_right {
+                        on.push((*left.clone(), *right.clone()));
+                        continue;
+                    }
+                    if a_right && b_left {
+                        on.push((*right.clone(), *left.clone()));
+                        continue;
+                    }
+                }
+            }
+            filter = Some(match filter {
+                Some(existing) => LogicalExpr::BinaryExpr {
+                    left: Box::new(existing),
+                    op: BinaryOperator::And,
+                    right: Box::new(conjunct),
+                },
+                None => conjunct,
+            });
+        }
+
+        (on, filter)
     }
 
     // Build projection
@@ -620,7 +810,20 @@ impl<'a> LogicalPlanBuilder<'a> {
                 ast::ResultColumn::TableStar(table) => {
                     // Expand table.* to all columns from that table
                     let table_name = Self::name_to_string(table);
+                    // Join inputs qualify their columns, so we can pick the ones of this table
+                    let prefix = format!("{table_name}.");
+                    let is_qualified = input_schema
+                        .columns
+                        .iter()
+                        .any(|(name, _)| name.starts_with(&prefix));
                     for (name, typ) in &input_schema.columns {
+                        if is_qualified {
+                            if name.starts_with(&prefix) {
+                                proj_exprs.push(LogicalExpr::Column(Column::new(name.clone())));
+                                schema_columns.push((name.clone(), *typ));
+                            }
+                            continue;
+                        }
                         // Simple check - would need proper table tracking in real implementation
                         proj_exprs.push(LogicalExpr::Column(Column::with_table(
                             name.clone(),
@@ -1114,7 +1317,11 @@ impl<'a> LogicalPlanBuilder<'a> {
     // Build expression from AST
     fn build_expr(&mut self, expr: &ast::Expr, _schema: &SchemaRef) -> Result<LogicalExpr> {
         match expr {
-            ast::Expr::Id(name) => Ok(LogicalExpr::Column(Column::new(Self::name_to_string(name)))),
+            ast::Expr::Id(name) => Ok(LogicalExpr::Column(Self::resolve_column(
+                Self::name_to_string(name),
+                None,
+                _schema,
+            )?)),
 
             ast::Expr::DoublyQualified(db, table, col) => {
                 Ok(LogicalExpr::Column(Column::with_table(
@@ -1127,10 +1334,11 @@ impl<'a> LogicalPlanBuilder<'a> {
                 )))
             }
 
-            ast::Expr::Qualified(table, col) => Ok(LogicalExpr::Column(Column::with_table(
+            ast::Expr::Qualified(table, col) => Ok(LogicalExpr::Column(Self::resolve_column(
                 Self::name_to_string(col),
-                Self::name_to_string(table),
-            ))),
+                Some(Self::name_to_string(table)),
+                _schema,
+            )?)),
 
             ast::Expr::Literal(lit) => Ok(LogicalExpr::Literal(Self::build_literal(lit)?)),
 
@@ -1328,7 +1536,7 @@ impl<'a> LogicalPlanBuilder<'a> {
             ast::Expr::Like {
                 lhs,
                 not,
-                op: _,
+                op,
                 rhs,
                 escape,
             } => {
@@ -1336,13 +1544,14 @@ impl<'a> LogicalPlanBuilder<'a> {
                 let pattern = Box::new(self.build_expr(rhs, _schema)?);
                 let escape_char = escape.as_ref().and_then(|e| {
                     if let ast::Expr::Literal(ast::Literal::String(s)) = e.as_ref() {
-                        s.chars().next()
+                        sanitize_string(s).chars().next()
                     } else {
                         None
                     }
                 });
                 Ok(LogicalExpr::Like {
                     expr,
+                    op: *op,
                     pattern,
                     escape: escape_char,
                     negated: *not,
@@ -1715,6 +1924,36 @@ impl<'a> LogicalPlanBuilder<'a> {
         }
     }
 
+    // Resolve a column reference against the input schema. Join inputs expose their columns
+    // qualified (`o.id`), so a reference is rewritten to the qualified name when that is the
+    // column it refers to.
+    fn resolve_column(name: String, table: Option<String>, schema: &SchemaRef) -> Result<Column> {
+        if let Some(table) = table {
+            let qualified = format!("{table}.{name}");
+            if schema.find_column(&qualified).is_some() {
+                return Ok(Column::new(qualified));
+            }
+            return Ok(Column::with_table(name, table));
+        }
+
+        if schema.find_column(&name).is_some() {
+            return Ok(Column::new(name));
+        }
+
+        let suffix = format!(".{name}");
+        let mut candidates = schema
+            .columns
+            .iter()
+            .filter(|(col_name, _)| col_name.ends_with(&suffix));
+        match (candidates.next(), candidates.next()) {
+            (Some((col_name, _)), None) => Ok(Column::new(col_name.clone())),
+            (Some(_), Some(_)) => Err(LimboError::ParseError(format!(
+                "ambiguous column name: {name}"
+            ))),
+            _ => Ok(Column::new(name)),
+        }
+    }
+
     // Get column name from expression
     fn expr_to_column_name(expr: &ast::Expr) -> String {
         match expr {
@@ -1863,6 +2102,7 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
                 SchemaColumn {
                     name: Some("name".to_string()),
@@ -1875,6 +2115,7 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
                 SchemaColumn {
                     name: Some("age".to_string()),
@@ -1887,6 +2128,7 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
                 SchemaColumn {
                     name: Some("email".to_string()),
@@ -1899,11 +2141,15 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
             ],
             has_rowid: true,
             is_strict: false,
             unique_sets: None,
+            foreign_keys: vec![],
+            check_constraints: vec![],
+            temporary: false,
         };
         schema.add_btree_table(Arc::new(users_table));
 
@@ -1924,6 +2170,7 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
                 SchemaColumn {
                     name: Some("user_id".to_string()),
@@ -1936,6 +2183,7 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
                 SchemaColumn {
                     name: Some("product".to_string()),
@@ -1948,6 +2196,7 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
                 SchemaColumn {
                     name: Some("amount".to_string()),
@@ -1960,11 +2209,15 @@ mod tests {
                     unique: false,
                     collation: None,
                     hidden: false,
+                    generated: None,
                 },
             ],
             has_rowid: true,
             is_strict: false,
             unique_sets: None,
+            foreign_keys: vec![],
+            check_constraints: vec![],
+            temporary: false,
         };
         schema.add_btree_table(Arc::new(orders_table));
 
@@ -2375,8 +2628,12 @@ mod tests {
             LogicalPlan::Projection(proj) => match &*proj.input {
                 LogicalPlan::Filter(filter) => match &filter.predicate {
                     LogicalExpr::Like {
-                        negated, escape, ..
+                        op,
+                        negated,
+                        escape,
+                        ..
                     } => {
+                        assert_eq!(*op, LikeOperator::Like);
                         assert!(!negated);
                         assert!(escape.is_none());
                     }
@@ -3080,4 +3337,74 @@ mod tests {
             _ => panic!("Expected Projection as top-level operator, got: {plan:?}"),
         }
     }
+
+    #[test]
+    fn test_join() {
+        let schema = create_test_schema();
+        let sql = "SELECT u.name, amount, o.id FROM users u JOIN orders o ON u.id = o.user_id";
+        let plan = parse_and_build(sql, &schema).unwrap();
+
+        let LogicalPlan::Projection(proj) = plan else {
+            panic!("Expected Projection, got: {plan:?}");
+        };
+        let columns: Vec<String> = proj
+            .exprs
+            .iter()
+            .map(|expr| match expr {
+                LogicalExpr::Column(col) => col.name.clone(),
+                _ => panic!("Expected Column, got: {expr:?}"),
+            })
+            .collect();
+        assert_eq!(columns, vec!["u.name", "o.amount", "o.id"]);
+
+        let LogicalPlan::Join(join) = &*proj.input else {
+            panic!("Expected Join, got: {:?}", proj.input);
+        };
+        assert_eq!(join.join_type, JoinType::Inner);
+        assert!(join.filter.is_none());
+        assert_eq!(
+            join.on,
+            vec![(
+                LogicalExpr::Column(Column::new("u.id")),
+                LogicalExpr::Column(Column::new("o.user_id"))
+            )]
+        );
+        assert!(matches!(&*join.left, LogicalPlan::TableScan(scan) if scan.table_name == "users"));
+        assert!(
+            matches!(&*join.right, LogicalPlan::TableScan(scan) if scan.table_name == "orders")
+        );
+        assert_eq!(join.schema.columns[0].0, "u.id");
+    }
+
+    #[test]
+    fn test_left_join_condition_split() {
+        let schema = create_test_schema();
+        let sql = "SELECT * FROM orders LEFT JOIN users ON users.id = orders.user_id AND age > 18";
+        let plan = parse_and_build(sql, &schema).unwrap();
+
+        let LogicalPlan::Projection(proj) = plan else {
+            panic!("Expected Projection, got: {plan:?}");
+        };
+        let LogicalPlan::Join(join) = &*proj.input else {
+            panic!("Expected Join, got: {:?}", proj.input);
+        };
+        assert_eq!(join.join_type, JoinType::Left);
+        // The equality is normalized to (left column, right column)
+        assert_eq!(
+            join.on,
+            vec![(
+                LogicalExpr::Column(Column::new("orders.user_id")),
+                LogicalExpr::Column(Column::new("users.id"))
+            )]
+        );
+        assert!(join.filter.is_some());
+    }
+
+    #[test]
+    fn test_join_ambiguous_column() {
+        let schema = create_test_schema();
+        let sql = "SELECT id FROM users JOIN orders ON users.id = orders.user_id";
+        let result = parse_and_build(sql, &schema);
+        assert!(result.is_err());
+    }
 }
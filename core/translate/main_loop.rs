@@ -6,6 +6,7 @@ use crate::{
     schema::{Affinity, Index, IndexColumn, Table},
     translate::{
         emitter::prepare_cdc_if_necessary,
+        index::{emit_index_primary_key, emit_primary_key_seek},
         plan::{DistinctCtx, Distinctness, Scan},
         result_row::emit_select_result,
     },
@@ -30,8 +31,8 @@ use super::{
     optimizer::Optimizable,
     order_by::{order_by_sorter_insert, sorter_insert},
     plan::{
-        Aggregate, GroupBy, IterationDirection, JoinOrderMember, Operation, QueryDestination,
-        Search, SeekDef, SelectPlan, TableReferences, WhereTerm,
+        Aggregate, GroupBy, IterationDirection, JoinOrderMember, JoinedTable, Operation,
+        QueryDestination, Search, SeekDef, SelectPlan, TableReferences, WhereTerm,
     },
 };
 
@@ -46,6 +47,19 @@ pub struct LeftJoinMetadata {
     pub label_match_flag_check_value: BranchOffset,
 }
 
+// Metadata for handling RIGHT and FULL OUTER JOIN operations
+#[derive(Debug)]
+pub struct RightJoinMetadata {
+    // ephemeral index of the keys of the rows of the right table that matched some row
+    pub matched_cursor_id: CursorID,
+    // integer register that is set to true while the rows of the right table that matched no row are emitted
+    pub reg_unmatched_pass: usize,
+    // label for the instruction that resumes the loop body for a row that matched no row
+    pub label_unmatched_row: BranchOffset,
+    // label for the instruction that moves to the next row that may have matched no row
+    pub label_next_unmatched_row: BranchOffset,
+}
+
 /// Jump labels for each loop in the query's main execution loop
 #[derive(Debug, Clone, Copy)]
 pub struct LoopLabels {
@@ -88,11 +102,15 @@ pub fn init_distinct(program: &mut ProgramBuilder, plan: &SelectPlan) -> Distinc
                     pos_in_table: i,
                     collation: None, // FIXME: this should be determined based on the result column expression!
                     default: None, // FIXME: this should be determined based on the result column expression!
+                    expr: None,
                 }
             })
             .collect(),
         unique: false,
         has_rowid: false,
+        where_clause: None,
+        non_key_columns: 0,
+        temporary: false,
     });
     let cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(index.clone()));
     let ctx = DistinctCtx {
@@ -129,11 +147,17 @@ pub fn init_loop(
         OperationMode::INSERT | OperationMode::UPDATE | OperationMode::DELETE
     ) {
         assert!(tables.joined_tables().len() == 1);
-        let changed_table = &tables.joined_tables()[0].table;
-        let prepared =
-            prepare_cdc_if_necessary(program, t_ctx.resolver.schema, changed_table.get_name())?;
-        if let Some((cdc_cursor_id, _)) = prepared {
-            t_ctx.cdc_cursor_id = Some(cdc_cursor_id);
+        let changed_table = &tables.joined_tables()[0];
+        // Changes to attached databases are not captured.
+        if changed_table.database_id < 2 {
+            let prepared = prepare_cdc_if_necessary(
+                program,
+                t_ctx.resolver.schema,
+                changed_table.table.get_name(),
+            )?;
+            if let Some((cdc_cursor_id, _)) = prepared {
+                t_ctx.cdc_cursor_id = Some(cdc_cursor_id);
+            }
         }
     }
 
@@ -163,9 +187,13 @@ pub fn init_loop(
                 pos_in_table: 0,
                 collation: None, // FIXME: this should be inferred from the expression
                 default: None,   // FIXME: this should be inferred from the expression
+                expr: None,
             }],
             has_rowid: false,
             unique: false,
+            where_clause: None,
+            non_key_columns: 0,
+            temporary: false,
         });
         let cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(index.clone()));
         if group_by.is_none() {
@@ -195,6 +223,10 @@ pub fn init_loop(
                 };
                 t_ctx.meta_left_joins[table_index] = Some(lj_metadata);
             }
+            if join_info.right {
+                let rj_metadata = init_right_join(program, table);
+                t_ctx.meta_right_joins[table_index] = Some(rj_metadata);
+            }
         }
         let (table_cursor_id, index_cursor_id) = table.open_cursors(program, mode)?;
         match &table.op {
@@ -501,6 +533,13 @@ pub fn open_loop(
                         });
                         program.preassign_label_to_next_insn(loop_start);
                     }
+                    (Scan::Subquery, Table::FromClauseSubquery(from_clause_subquery))
+                        if from_clause_subquery.is_recursive_reference =>
+                    {
+                        // The reference to a recursive CTE from its own recursive SELECT reads
+                        // the single row being expanded, which is already in the result registers.
+                        program.preassign_label_to_next_insn(loop_start);
+                    }
                     (Scan::Subquery, Table::FromClauseSubquery(from_clause_subquery)) => {
                         let (yield_reg, coroutine_implementation_start) =
                             match &from_clause_subquery.plan.query_destination {
@@ -534,10 +573,7 @@ pub fn open_loop(
 
                 if let Some(table_cursor_id) = table_cursor_id {
                     if let Some(index_cursor_id) = index_cursor_id {
-                        program.emit_insn(Insn::DeferredSeek {
-                            index_cursor_id,
-                            table_cursor_id,
-                        });
+                        emit_table_seek(program, table, index_cursor_id, table_cursor_id, next);
                     }
                 }
             }
@@ -591,7 +627,9 @@ pub fn open_loop(
                         index_cursor_id
                     };
 
-                    let is_index = index_cursor_id.is_some();
+                    // A WITHOUT ROWID table is sought like an index, by its PRIMARY KEY.
+                    let is_index = index_cursor_id.is_some()
+                        || table.btree().is_some_and(|btree| !btree.has_rowid);
                     let seek_cursor_id = temp_cursor_id.unwrap_or_else(|| {
                         index_cursor_id.unwrap_or_else(|| {
                             table_cursor_id
@@ -627,11 +665,7 @@ pub fn open_loop(
 
                     if let Some(index_cursor_id) = index_cursor_id {
                         if let Some(table_cursor_id) = table_cursor_id {
-                            // Don't do a btree table seek until it's actually necessary to read from the table.
-                            program.emit_insn(Insn::DeferredSeek {
-                                index_cursor_id,
-                                table_cursor_id,
-                            });
+                            emit_table_seek(program, table, index_cursor_id, table_cursor_id, next);
                         }
                     }
                 }
@@ -650,6 +684,28 @@ pub fn open_loop(
             true,
         )?;
 
+        // Remember that the row of the right table of a RIGHT JOIN matched, so that it is not
+        // emitted again with NULLs for the left tables once the loops are done.
+        if let Some(rj_meta) = t_ctx.meta_right_joins[joined_table_index].as_ref() {
+            let table_cursor_id =
+                table_cursor_id.expect("the right table of a RIGHT JOIN is read from the table");
+            let (key_start, num_keys) = emit_right_join_key(program, table, table_cursor_id);
+            let record_reg = program.alloc_register();
+            program.emit_insn(Insn::MakeRecord {
+                start_reg: key_start,
+                count: num_keys,
+                dest_reg: record_reg,
+                index_name: None,
+            });
+            program.emit_insn(Insn::IdxInsert {
+                cursor_id: rj_meta.matched_cursor_id,
+                record_reg,
+                unpacked_start: Some(key_start),
+                unpacked_count: Some(num_keys as u16),
+                flags: IdxInsertFlags::new().no_op_duplicate(),
+            });
+        }
+
         // Set the match flag to true if this is a LEFT JOIN.
         // At this point of execution we are going to emit columns for the left table,
         // and either emit columns or NULLs for the right table, depending on whether the null_flag is set
@@ -665,6 +721,12 @@ pub fn open_loop(
             }
         }
 
+        // The rows of the right table of a RIGHT JOIN that matched no row join the loop here,
+        // with NULLs for the left tables.
+        if let Some(rj_meta) = t_ctx.meta_right_joins[joined_table_index].as_ref() {
+            program.preassign_label_to_next_insn(rj_meta.label_unmatched_row);
+        }
+
         // Now we can emit conditions from the WHERE clause.
         // If the right table produces a NULL row, control jumps to the point where the match flag is set.
         // The WHERE clause conditions may reference columns from that row, so they cannot be emitted
@@ -990,9 +1052,19 @@ pub fn close_loop(
 
         let (table_cursor_id, index_cursor_id) = table.resolve_cursors(program)?;
 
+        program.resolve_label(loop_labels.next, program.offset());
+        // While the rows of the right table of a RIGHT JOIN that matched no row are emitted,
+        // the right table is scanned in full instead of being iterated as planned.
+        if let Some(rj_meta) = t_ctx.meta_right_joins[table_index].as_ref() {
+            program.emit_insn(Insn::IfPos {
+                reg: rj_meta.reg_unmatched_pass,
+                target_pc: rj_meta.label_next_unmatched_row,
+                decrement_by: 0,
+            });
+        }
+
         match &table.op {
             Operation::Scan(scan) => {
-                program.resolve_label(loop_labels.next, program.offset());
                 match scan {
                     Scan::BTreeTable { iter_dir, .. } => {
                         let iteration_cursor_id = temp_cursor_id.unwrap_or_else(|| {
@@ -1021,6 +1093,15 @@ pub fn close_loop(
                             pc_if_next: loop_labels.loop_start,
                         });
                     }
+                    Scan::Subquery
+                        if matches!(
+                            &table.table,
+                            Table::FromClauseSubquery(from_clause_subquery)
+                                if from_clause_subquery.is_recursive_reference
+                        ) =>
+                    {
+                        // The row being expanded by a recursive CTE is read only once.
+                    }
                     Scan::Subquery => {
                         // A subquery has no cursor to call Next on, so it just emits a Goto
                         // to the Yield instruction, which in turn jumps back to the main loop of the subquery,
@@ -1037,7 +1118,6 @@ pub fn close_loop(
                     !matches!(table.table, Table::FromClauseSubquery(_)),
                     "Subqueries do not support index seeks"
                 );
-                program.resolve_label(loop_labels.next, program.offset());
                 let iteration_cursor_id = temp_cursor_id.unwrap_or_else(|| {
                     index_cursor_id.unwrap_or_else(|| {
                         table_cursor_id
@@ -1110,6 +1190,16 @@ pub fn close_loop(
             }
         }
     }
+
+    // Once all the loops are done, the rows of the right table of each RIGHT JOIN that matched
+    // no row are emitted with NULLs for the tables to its left. This is done in join order,
+    // since the rows emitted for one RIGHT JOIN may match the right table of a later one.
+    for (join_index, join) in join_order.iter().enumerate() {
+        let Some(rj_meta) = t_ctx.meta_right_joins[join.original_idx].as_ref() else {
+            continue;
+        };
+        emit_right_join_unmatched_rows(program, tables, &join_order[..join_index], join, rj_meta)?;
+    }
     Ok(())
 }
 
@@ -1382,6 +1472,173 @@ fn emit_seek_termination(
     Ok(())
 }
 
+/// Open the ephemeral index that records which rows of the right table of a RIGHT JOIN matched.
+fn init_right_join(program: &mut ProgramBuilder, table: &JoinedTable) -> RightJoinMetadata {
+    let btree = table
+        .btree()
+        .expect("the right table of a RIGHT JOIN is a btree table");
+    // The rows are keyed by their rowid, or by their PRIMARY KEY in a WITHOUT ROWID table.
+    let columns = match btree.clustered_index() {
+        Some(clustered_index) => {
+            clustered_index.columns[..btree.primary_key_columns.len()].to_vec()
+        }
+        None => vec![IndexColumn {
+            name: "rowid".to_string(),
+            order: SortOrder::Asc,
+            pos_in_table: 0,
+            collation: None,
+            default: None,
+            expr: None,
+        }],
+    };
+    let index = Arc::new(Index {
+        name: format!("right_join_matched_{}", table.identifier),
+        table_name: String::new(),
+        ephemeral: true,
+        root_page: 0,
+        columns,
+        has_rowid: false,
+        unique: false,
+        where_clause: None,
+        non_key_columns: 0,
+        temporary: false,
+    });
+    let matched_cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(index));
+    program.emit_insn(Insn::OpenEphemeral {
+        cursor_id: matched_cursor_id,
+        is_table: false,
+    });
+    let reg_unmatched_pass = program.alloc_register();
+    program.emit_insn(Insn::Integer {
+        value: 0,
+        dest: reg_unmatched_pass,
+    });
+    RightJoinMetadata {
+        matched_cursor_id,
+        reg_unmatched_pass,
+        label_unmatched_row: program.allocate_label(),
+        label_next_unmatched_row: program.allocate_label(),
+    }
+}
+
+/// Read the key of the row the table cursor of the right table of a RIGHT JOIN points to
+/// into contiguous registers, and return the first one and their count.
+fn emit_right_join_key(
+    program: &mut ProgramBuilder,
+    table: &JoinedTable,
+    table_cursor_id: CursorID,
+) -> (usize, usize) {
+    let Some(btree) = table.btree().filter(|btree| !btree.has_rowid) else {
+        let key_reg = program.alloc_register();
+        program.emit_insn(Insn::RowId {
+            cursor_id: table_cursor_id,
+            dest: key_reg,
+        });
+        return (key_reg, 1);
+    };
+    // The record of a WITHOUT ROWID table starts with its PRIMARY KEY.
+    let num_keys = btree.primary_key_columns.len();
+    let key_start = program.alloc_registers(num_keys);
+    for i in 0..num_keys {
+        program.emit_insn(Insn::Column {
+            cursor_id: table_cursor_id,
+            column: i,
+            dest: key_start + i,
+            default: None,
+        });
+    }
+    (key_start, num_keys)
+}
+
+/// Emit the rows of the right table of a RIGHT JOIN that matched no row, with NULLs for the
+/// tables to its left. The right table is scanned in full and each row that is not in the
+/// matched index resumes the loop body right after the ON conditions of the right table.
+fn emit_right_join_unmatched_rows(
+    program: &mut ProgramBuilder,
+    tables: &TableReferences,
+    left_tables: &[JoinOrderMember],
+    join: &JoinOrderMember,
+    rj_meta: &RightJoinMetadata,
+) -> Result<()> {
+    let table = &tables.joined_tables()[join.original_idx];
+    let (table_cursor_id, _) = table.resolve_cursors(program)?;
+    let table_cursor_id =
+        table_cursor_id.expect("the right table of a RIGHT JOIN is read from the table");
+    program.emit_insn(Insn::Integer {
+        value: 1,
+        dest: rj_meta.reg_unmatched_pass,
+    });
+    for left_table in left_tables {
+        let left_table = &tables.joined_tables()[left_table.original_idx];
+        if let Table::FromClauseSubquery(from_clause_subquery) = &left_table.table {
+            if let Some(start_reg) = from_clause_subquery.result_columns_start_reg {
+                program.emit_insn(Insn::Null {
+                    dest: start_reg,
+                    dest_end: Some(start_reg + from_clause_subquery.columns.len() - 1),
+                });
+            }
+            continue;
+        }
+        let (table_cursor_id, index_cursor_id) = left_table.resolve_cursors(program)?;
+        for cursor_id in [table_cursor_id, index_cursor_id].into_iter().flatten() {
+            program.emit_insn(Insn::NullRow { cursor_id });
+        }
+    }
+    let label_loop_start = program.allocate_label();
+    let label_loop_end = program.allocate_label();
+    program.emit_insn(Insn::Rewind {
+        cursor_id: table_cursor_id,
+        pc_if_empty: label_loop_end,
+    });
+    program.preassign_label_to_next_insn(label_loop_start);
+    let (key_start, num_keys) = emit_right_join_key(program, table, table_cursor_id);
+    program.emit_insn(Insn::Found {
+        cursor_id: rj_meta.matched_cursor_id,
+        target_pc: rj_meta.label_next_unmatched_row,
+        record_reg: key_start,
+        num_regs: num_keys,
+    });
+    program.emit_insn(Insn::Goto {
+        target_pc: rj_meta.label_unmatched_row,
+    });
+    program.resolve_label(rj_meta.label_next_unmatched_row, program.offset());
+    program.emit_insn(Insn::Next {
+        cursor_id: table_cursor_id,
+        pc_if_next: label_loop_start,
+    });
+    program.preassign_label_to_next_insn(label_loop_end);
+    program.emit_insn(Insn::Integer {
+        value: 0,
+        dest: rj_meta.reg_unmatched_pass,
+    });
+    Ok(())
+}
+
+/// Position the table cursor on the row that the index cursor points to.
+fn emit_table_seek(
+    program: &mut ProgramBuilder,
+    table: &JoinedTable,
+    index_cursor_id: CursorID,
+    table_cursor_id: CursorID,
+    not_found_label: BranchOffset,
+) {
+    let Some(btree) = table.btree().filter(|btree| !btree.has_rowid) else {
+        // Don't do a btree table seek until it's actually necessary to read from the table.
+        program.emit_insn(Insn::DeferredSeek {
+            index_cursor_id,
+            table_cursor_id,
+        });
+        return;
+    };
+    let index = table.op.index().expect("index cursor without an index");
+    // An automatic index on a WITHOUT ROWID table holds every column the query uses, so the
+    // table is never read. Other indexes lead to their row through its PRIMARY KEY.
+    if !index.ephemeral {
+        let key_start = emit_index_primary_key(program, &btree, index, index_cursor_id);
+        emit_primary_key_seek(program, &btree, table_cursor_id, key_start, not_found_label);
+    }
+}
+
 /// Open an ephemeral index cursor and build an automatic index on a table.
 /// This is used as a last-resort to avoid a nested full table scan
 /// Returns the cursor id of the ephemeral index cursor.
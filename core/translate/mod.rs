@@ -11,12 +11,15 @@ pub(crate) mod aggregation;
 pub(crate) mod alter;
 pub(crate) mod analyze;
 pub(crate) mod attach;
+pub(crate) mod check;
 pub(crate) mod collate;
 mod compound_select;
 pub(crate) mod delete;
 pub(crate) mod display;
 pub(crate) mod emitter;
 pub(crate) mod expr;
+pub(crate) mod foreign_key;
+pub(crate) mod generated;
 pub(crate) mod group_by;
 pub(crate) mod index;
 pub(crate) mod insert;
@@ -34,10 +37,13 @@ pub(crate) mod schema;
 pub(crate) mod select;
 pub(crate) mod subquery;
 pub(crate) mod transaction;
+pub(crate) mod trigger;
 pub(crate) mod update;
 pub(crate) mod upsert;
+pub(crate) mod vacuum;
 mod values;
 pub(crate) mod view;
+pub(crate) mod window;
 
 use crate::schema::Schema;
 use crate::storage::pager::Pager;
@@ -55,7 +61,9 @@ use select::translate_select;
 use std::rc::Rc;
 use std::sync::Arc;
 use tracing::{instrument, Level};
-use transaction::{translate_tx_begin, translate_tx_commit};
+use transaction::{
+    translate_release, translate_savepoint, translate_tx_begin, translate_tx_commit,
+};
 use turso_parser::ast::{self, Indexed};
 use update::translate_update;
 
@@ -106,6 +114,9 @@ pub fn translate(
         stmt => translate_inner(schema, stmt, syms, program, &connection, input)?,
     };
 
+    program.begin_attached_operations(|database_id| {
+        connection.with_schema(database_id, |schema| schema.schema_version)
+    });
     program.epilogue(schema);
 
     Ok(program.build(connection, change_cnt_on, input))
@@ -134,10 +145,12 @@ pub fn translate_inner(
             | ast::Stmt::Delete { .. }
             | ast::Stmt::DropIndex { .. }
             | ast::Stmt::DropTable { .. }
+            | ast::Stmt::DropTrigger { .. }
             | ast::Stmt::DropView { .. }
             | ast::Stmt::Reindex { .. }
             | ast::Stmt::Update { .. }
             | ast::Stmt::Insert { .. }
+            | ast::Stmt::Vacuum { into: None, .. }
     );
 
     if is_write && connection.get_query_only() {
@@ -163,20 +176,17 @@ pub fn translate_inner(
             tbl_name,
             columns,
             where_clause,
-        } => {
-            if where_clause.is_some() {
-                bail_parse_error!("Partial indexes are not supported");
-            }
-            translate_create_index(
-                (unique, if_not_exists),
-                idx_name.name.as_str(),
-                tbl_name.as_str(),
-                &columns,
-                schema,
-                syms,
-                program,
-            )?
-        }
+        } => translate_create_index(
+            (unique, if_not_exists),
+            &idx_name,
+            tbl_name.as_str(),
+            &columns,
+            where_clause,
+            schema,
+            syms,
+            connection,
+            program,
+        )?,
         ast::Stmt::CreateTable {
             temporary,
             if_not_exists,
@@ -187,20 +197,23 @@ pub fn translate_inner(
             temporary,
             body,
             if_not_exists,
-            schema,
             syms,
             connection,
             program,
         )?,
-        ast::Stmt::CreateTrigger { .. } => bail_parse_error!("CREATE TRIGGER not supported yet"),
+        stmt @ ast::Stmt::CreateTrigger { .. } => {
+            trigger::translate_create_trigger(schema, stmt, syms, connection, program)?
+        }
         ast::Stmt::CreateView {
+            temporary,
             view_name,
             select,
             columns,
             ..
         } => view::translate_create_view(
             schema,
-            view_name.name.as_str(),
+            &view_name,
+            temporary,
             &select,
             &columns,
             connection.clone(),
@@ -239,7 +252,6 @@ pub fn translate_inner(
                 bail_parse_error!("ORDER BY clause is not supported in DELETE");
             }
             translate_delete(
-                schema,
                 &tbl_name,
                 where_clause,
                 limit,
@@ -253,12 +265,17 @@ pub fn translate_inner(
         ast::Stmt::DropIndex {
             if_exists,
             idx_name,
-        } => translate_drop_index(idx_name.name.as_str(), if_exists, schema, syms, program)?,
+        } => translate_drop_index(&idx_name, if_exists, schema, syms, connection, program)?,
         ast::Stmt::DropTable {
             if_exists,
             tbl_name,
-        } => translate_drop_table(tbl_name, if_exists, schema, syms, program)?,
-        ast::Stmt::DropTrigger { .. } => bail_parse_error!("DROP TRIGGER not supported yet"),
+        } => translate_drop_table(tbl_name, if_exists, schema, syms, connection, program)?,
+        ast::Stmt::DropTrigger {
+            if_exists,
+            trigger_name,
+        } => {
+            trigger::translate_drop_trigger(schema, &trigger_name, if_exists, connection, program)?
+        }
         ast::Stmt::DropView {
             if_exists,
             view_name,
@@ -267,12 +284,12 @@ pub fn translate_inner(
             bail_parse_error!("PRAGMA statement cannot be evaluated in a nested context")
         }
         ast::Stmt::Reindex { .. } => bail_parse_error!("REINDEX not supported yet"),
-        ast::Stmt::Release { .. } => bail_parse_error!("RELEASE not supported yet"),
+        ast::Stmt::Release { name } => translate_release(name, program)?,
         ast::Stmt::Rollback {
             tx_name,
             savepoint_name,
         } => translate_rollback(schema, syms, program, tx_name, savepoint_name)?,
-        ast::Stmt::Savepoint { .. } => bail_parse_error!("SAVEPOINT not supported yet"),
+        ast::Stmt::Savepoint { name } => translate_savepoint(name, program)?,
         ast::Stmt::Select(select) => {
             translate_select(
                 schema,
@@ -284,10 +301,10 @@ pub fn translate_inner(
             )?
             .program
         }
-        ast::Stmt::Update(mut update) => {
-            translate_update(schema, &mut update, syms, program, connection)?
+        ast::Stmt::Update(mut update) => translate_update(&mut update, syms, program, connection)?,
+        ast::Stmt::Vacuum { name, into } => {
+            vacuum::translate_vacuum(schema, syms, name, into, program)?
         }
-        ast::Stmt::Vacuum { .. } => bail_parse_error!("VACUUM not supported yet"),
         ast::Stmt::Insert {
             with,
             or_conflict,
@@ -296,7 +313,6 @@ pub fn translate_inner(
             body,
             returning,
         } => translate_insert(
-            schema,
             with,
             or_conflict,
             tbl_name,
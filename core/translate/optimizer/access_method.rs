@@ -144,7 +144,12 @@ fn find_best_access_method_for_btree<'a>(
                         }
                     }
                 };
-                if !correct_table || !correct_column {
+                // A column of an index on an expression can't deliver rows ordered by a column.
+                let is_expression = candidate
+                    .index
+                    .as_ref()
+                    .is_some_and(|index| index.columns[i].expr.is_some());
+                if !correct_table || !correct_column || is_expression {
                     all_same_direction = false;
                     all_opposite_direction = false;
                     break;
@@ -1,18 +1,19 @@
 use std::{cmp::Ordering, collections::HashMap, sync::Arc};
 
 use crate::{
-    schema::{Column, Index},
+    schema::{Column, Index, Table},
     translate::{
-        expr::as_binary_components,
-        plan::{JoinOrderMember, TableReferences, WhereTerm},
+        expr::{as_binary_components, walk_expr_mut},
+        plan::{JoinOrderMember, JoinedTable, TableReferences, WhereTerm},
         planner::{table_mask_from_expr, TableMask},
     },
+    util::{exprs_are_equivalent, normalize_ident},
     Result,
 };
-use turso_ext::{ConstraintInfo, ConstraintOp};
+use turso_ext::{ConstraintInfo, ConstraintOp, ROWID_COLUMN_INDEX};
 use turso_parser::ast::{self, SortOrder, TableInternalId};
 
-use super::cost::ESTIMATED_HARDCODED_ROWS_PER_TABLE;
+use super::{cost::ESTIMATED_HARDCODED_ROWS_PER_TABLE, rewrite_expr};
 
 /// Represents a single condition derived from a `WHERE` clause term
 /// that constrains a specific column of a table.
@@ -39,8 +40,11 @@ pub struct Constraint {
     pub where_clause_pos: (usize, BinaryExprSide),
     /// The comparison operator (e.g., `=`, `>`, `<`) used in the constraint.
     pub operator: ast::Operator,
-    /// The zero-based index of the constrained column within the table's schema.
-    pub table_col_pos: usize,
+    /// The zero-based index of the constrained column within the table's schema, or None if the
+    /// constrained side is an expression over the table that an index on expressions contains,
+    /// e.g. `lower(t.x)` in SELECT * FROM t WHERE lower(t.x) = 'a', or the rowid of a virtual
+    /// table.
+    pub table_col_pos: Option<usize>,
     /// A bitmask representing the set of tables that appear on the *constraining* side
     /// of the comparison expression. For example, in SELECT * FROM t1,t2,t3 WHERE t1.x = t2.x + t3.x,
     /// the lhs_mask contains t2 and t3. Thus, this constraint can only be used if t2 and t3
@@ -71,6 +75,20 @@ impl Constraint {
             rhs.clone()
         }
     }
+
+    /// Get the constrained expression, e.g. 't.x' from 't.x = 2+3'
+    pub fn get_constrained_expr<'a>(&self, where_clause: &'a [WhereTerm]) -> &'a ast::Expr {
+        let (idx, side) = self.where_clause_pos;
+        let where_term = &where_clause[idx];
+        let Ok(Some((lhs, _, rhs))) = as_binary_components(&where_term.expr) else {
+            panic!("Expected a valid binary expression");
+        };
+        if side == BinaryExprSide::Lhs {
+            rhs
+        } else {
+            lhs
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -152,10 +170,11 @@ const SELECTIVITY_OTHER: f64 = 0.9;
 const SELECTIVITY_UNIQUE_EQUALITY: f64 = 1.0 / ESTIMATED_HARDCODED_ROWS_PER_TABLE as f64;
 
 /// Estimate the selectivity of a constraint based on the operator and the column type.
-fn estimate_selectivity(column: &Column, op: ast::Operator) -> f64 {
+/// `column` is None for a constraint on an expression.
+fn estimate_selectivity(column: Option<&Column>, op: ast::Operator) -> f64 {
     match op {
         ast::Operator::Equals => {
-            if column.is_rowid_alias || column.primary_key {
+            if column.is_some_and(|column| column.is_rowid_alias || column.primary_key) {
                 SELECTIVITY_UNIQUE_EQUALITY
             } else {
                 SELECTIVITY_EQ
@@ -178,27 +197,85 @@ pub fn constraints_from_where_clause(
 ) -> Result<Vec<TableConstraints>> {
     let mut constraints = Vec::new();
 
+    // The tables to the left of the last RIGHT JOIN, whose rows the WHERE clause can only filter
+    // once the rows of its right table have been matched with them.
+    let last_right_join = table_references
+        .joined_tables()
+        .iter()
+        .rposition(|t| t.join_info.as_ref().is_some_and(|j| j.right));
+    let left_of_right_join =
+        TableMask::from_table_number_iter(0..last_right_join.unwrap_or_default());
+
     // For each table, collect all the Constraints and all potential index candidates that may use them.
-    for table_reference in table_references.joined_tables() {
+    for (table_idx, table_reference) in table_references.joined_tables().iter().enumerate() {
         let rowid_alias_column = table_reference
             .columns()
             .iter()
             .position(|c| c.is_rowid_alias);
+        let has_rowid_constraints =
+            rowid_alias_column.is_some() || table_reference.virtual_table().is_some();
+
+        // A WITHOUT ROWID table can be sought by its PRIMARY KEY like an index.
+        let clustered_index = table_reference
+            .btree()
+            .and_then(|btree| btree.clustered_index());
+        // A partial index can only be used if the query only needs rows that are in the index.
+        let usable_indexes = clustered_index
+            .into_iter()
+            .chain(
+                available_indexes
+                    .get(table_reference.table.get_name())
+                    .into_iter()
+                    .flatten()
+                    .filter(|index| {
+                        partial_index_is_usable(
+                            index,
+                            table_reference,
+                            where_clause,
+                            left_of_right_join.contains_table(table_idx),
+                        )
+                    })
+                    .cloned(),
+            )
+            .collect::<Vec<_>>();
+        // The expressions of the indexes on expressions, bound to the table like the expressions
+        // of the WHERE clause are, so that the two can be compared.
+        let index_exprs = usable_indexes
+            .iter()
+            .map(|index| {
+                index
+                    .columns
+                    .iter()
+                    .map(|column| {
+                        column.expr.as_ref().map(|expr| {
+                            bind_index_expr(
+                                expr,
+                                &table_reference.table,
+                                table_reference.internal_id,
+                            )
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let is_indexed_expr = |expr: &ast::Expr| {
+            index_exprs
+                .iter()
+                .flatten()
+                .flatten()
+                .any(|index_expr| exprs_are_equivalent(index_expr, expr))
+        };
 
         let mut cs = TableConstraints {
             table_id: table_reference.internal_id,
             constraints: Vec::new(),
-            candidates: available_indexes
-                .get(table_reference.table.get_name())
-                .map_or(Vec::new(), |indexes| {
-                    indexes
-                        .iter()
-                        .map(|index| ConstraintUseCandidate {
-                            index: Some(index.clone()),
-                            refs: Vec::new(),
-                        })
-                        .collect()
-                }),
+            candidates: usable_indexes
+                .iter()
+                .map(|index| ConstraintUseCandidate {
+                    index: Some(index.clone()),
+                    refs: Vec::new(),
+                })
+                .collect(),
         };
         // Add a candidate for the rowid index, which is always available when the table has a rowid alias.
         cs.candidates.push(ConstraintUseCandidate {
@@ -217,6 +294,10 @@ pub fn constraints_from_where_clause(
                 if outer_join_tbl != table_reference.internal_id {
                     continue;
                 }
+            } else if last_right_join.is_some_and(|idx| table_idx <= idx) {
+                // The WHERE clause filters the rows joined by a RIGHT JOIN, including the rows of its
+                // right table that match no row, so it can't be used to look up the joined rows.
+                continue;
             }
 
             // If either the LHS or RHS of the constraint is a column from the table, add the constraint.
@@ -227,28 +308,38 @@ pub fn constraints_from_where_clause(
                         cs.constraints.push(Constraint {
                             where_clause_pos: (i, BinaryExprSide::Rhs),
                             operator,
-                            table_col_pos: *column,
+                            table_col_pos: Some(*column),
                             lhs_mask: table_mask_from_expr(rhs, table_references)?,
-                            selectivity: estimate_selectivity(table_column, operator),
+                            selectivity: estimate_selectivity(Some(table_column), operator),
                         });
                     }
                 }
                 ast::Expr::RowId { table, .. } => {
                     // A rowid alias column must exist for the 'rowid' keyword to be considered a valid reference.
                     // This should be a parse error at an earlier stage of the query compilation, but nevertheless,
-                    // we check it here.
-                    if *table == table_reference.internal_id && rowid_alias_column.is_some() {
+                    // we check it here. Virtual tables are the exception: their rowid is a constraint with no
+                    // column, which is passed to best_index as ROWID_COLUMN_INDEX.
+                    if *table == table_reference.internal_id && has_rowid_constraints {
                         let table_column =
-                            &table_reference.table.columns()[rowid_alias_column.unwrap()];
+                            rowid_alias_column.map(|pos| &table_reference.table.columns()[pos]);
                         cs.constraints.push(Constraint {
                             where_clause_pos: (i, BinaryExprSide::Rhs),
                             operator,
-                            table_col_pos: rowid_alias_column.unwrap(),
+                            table_col_pos: rowid_alias_column,
                             lhs_mask: table_mask_from_expr(rhs, table_references)?,
                             selectivity: estimate_selectivity(table_column, operator),
                         });
                     }
                 }
+                lhs if is_indexed_expr(lhs) => {
+                    cs.constraints.push(Constraint {
+                        where_clause_pos: (i, BinaryExprSide::Rhs),
+                        operator,
+                        table_col_pos: None,
+                        lhs_mask: table_mask_from_expr(rhs, table_references)?,
+                        selectivity: estimate_selectivity(None, operator),
+                    });
+                }
                 _ => {}
             };
             match rhs {
@@ -258,25 +349,34 @@ pub fn constraints_from_where_clause(
                         cs.constraints.push(Constraint {
                             where_clause_pos: (i, BinaryExprSide::Lhs),
                             operator: opposite_cmp_op(operator),
-                            table_col_pos: *column,
+                            table_col_pos: Some(*column),
                             lhs_mask: table_mask_from_expr(lhs, table_references)?,
-                            selectivity: estimate_selectivity(table_column, operator),
+                            selectivity: estimate_selectivity(Some(table_column), operator),
                         });
                     }
                 }
                 ast::Expr::RowId { table, .. } => {
-                    if *table == table_reference.internal_id && rowid_alias_column.is_some() {
+                    if *table == table_reference.internal_id && has_rowid_constraints {
                         let table_column =
-                            &table_reference.table.columns()[rowid_alias_column.unwrap()];
+                            rowid_alias_column.map(|pos| &table_reference.table.columns()[pos]);
                         cs.constraints.push(Constraint {
                             where_clause_pos: (i, BinaryExprSide::Lhs),
                             operator: opposite_cmp_op(operator),
-                            table_col_pos: rowid_alias_column.unwrap(),
+                            table_col_pos: rowid_alias_column,
                             lhs_mask: table_mask_from_expr(lhs, table_references)?,
                             selectivity: estimate_selectivity(table_column, operator),
                         });
                     }
                 }
+                rhs if is_indexed_expr(rhs) => {
+                    cs.constraints.push(Constraint {
+                        where_clause_pos: (i, BinaryExprSide::Lhs),
+                        operator: opposite_cmp_op(operator),
+                        table_col_pos: None,
+                        lhs_mask: table_mask_from_expr(lhs, table_references)?,
+                        selectivity: estimate_selectivity(None, operator),
+                    });
+                }
                 _ => {}
             };
         }
@@ -294,7 +394,8 @@ pub fn constraints_from_where_clause(
 
         // For each constraint we found, add a reference to it for each index that may be able to use it.
         for (i, constraint) in cs.constraints.iter().enumerate() {
-            if rowid_alias_column == Some(constraint.table_col_pos) {
+            if constraint.table_col_pos.is_some() && rowid_alias_column == constraint.table_col_pos
+            {
                 let rowid_candidate = cs
                     .candidates
                     .iter_mut()
@@ -312,13 +413,25 @@ pub fn constraints_from_where_clause(
                     sort_order: SortOrder::Asc,
                 });
             }
-            for index in available_indexes
-                .get(table_reference.table.get_name())
-                .unwrap_or(&Vec::new())
-            {
-                if let Some(position_in_index) =
-                    index.column_table_pos_to_index_pos(constraint.table_col_pos)
-                {
+            for (index, index_exprs) in usable_indexes.iter().zip(&index_exprs) {
+                let position_in_index = match constraint.table_col_pos {
+                    Some(table_col_pos) => {
+                        let table_column = &table_reference.columns()[table_col_pos];
+                        index
+                            .columns
+                            .iter()
+                            .position(|c| c.stores_column(table_col_pos, table_column))
+                    }
+                    None => {
+                        let constrained_expr = constraint.get_constrained_expr(where_clause);
+                        index_exprs.iter().position(|index_expr| {
+                            index_expr
+                                .as_ref()
+                                .is_some_and(|e| exprs_are_equivalent(e, constrained_expr))
+                        })
+                    }
+                };
+                if let Some(position_in_index) = position_in_index {
                     let index_candidate = cs
                         .candidates
                         .iter_mut()
@@ -423,8 +536,13 @@ pub fn convert_to_vtab_constraint(
                 return None;
             }
             let all_required_tables_are_on_left_side = lhs_mask.contains_all(&constraint.lhs_mask);
+            // A virtual table has no indexes on expressions, so a constraint without a column
+            // constrains its rowid.
+            let column_index = constraint
+                .table_col_pos
+                .map_or(ROWID_COLUMN_INDEX, |pos| pos as u32);
             to_ext_constraint_op(&constraint.operator).map(|op| ConstraintInfo {
-                column_index: constraint.table_col_pos as u32,
+                column_index,
                 op,
                 usable: all_required_tables_are_on_left_side,
                 index: i,
@@ -445,6 +563,124 @@ fn to_ext_constraint_op(op: &ast::Operator) -> Option<ConstraintOp> {
     }
 }
 
+/// Binds the column references of `expr`, an indexed expression or the WHERE clause of a partial
+/// index on `table`, to the table reference `table_id`, like the column references of a query are.
+fn bind_index_expr(expr: &ast::Expr, table: &Table, table_id: TableInternalId) -> ast::Expr {
+    let mut expr = expr.clone();
+    let _ = walk_expr_mut(&mut expr, &mut |expr: &mut ast::Expr| -> Result<()> {
+        let name = match expr {
+            ast::Expr::Id(name) | ast::Expr::Name(name) | ast::Expr::Qualified(_, name) => {
+                normalize_ident(name.as_str())
+            }
+            _ => return Ok(()),
+        };
+        if let Some((column, table_column)) = table.get_column_by_name(&name) {
+            *expr = ast::Expr::Column {
+                database: None,
+                table: table_id,
+                column,
+                is_rowid_alias: table_column.is_rowid_alias,
+            };
+        } else if matches!(name.as_str(), "rowid" | "oid" | "_rowid_") {
+            *expr = ast::Expr::RowId {
+                database: None,
+                table: table_id,
+            };
+        }
+        Ok(())
+    });
+    // Apply the same rewrites as the optimizer applies to the query, e.g. of BETWEEN.
+    let _ = rewrite_expr(&mut expr, &mut 1);
+    expr
+}
+
+/// Returns the conjuncts of `expr`, e.g. `a`, `b` and `c` for `a AND (b AND c)`.
+fn split_conjuncts(expr: ast::Expr, conjuncts: &mut Vec<ast::Expr>) {
+    match expr {
+        ast::Expr::Binary(lhs, ast::Operator::And, rhs) => {
+            split_conjuncts(*lhs, conjuncts);
+            split_conjuncts(*rhs, conjuncts);
+        }
+        ast::Expr::Parenthesized(mut exprs) if exprs.len() == 1 => {
+            split_conjuncts(*exprs.pop().unwrap(), conjuncts);
+        }
+        expr => conjuncts.push(expr),
+    }
+}
+
+/// Whether `term` being true implies that `conjunct` is true. Besides identical expressions,
+/// a comparison implies that its operands are not NULL, e.g. `x > 5` implies `x IS NOT NULL`.
+fn term_implies(term: &ast::Expr, conjunct: &ast::Expr) -> bool {
+    if exprs_are_equivalent(term, conjunct) {
+        return true;
+    }
+    let not_null_operand = match conjunct {
+        ast::Expr::NotNull(operand) => operand.as_ref(),
+        ast::Expr::Binary(operand, ast::Operator::IsNot, null)
+            if matches!(null.as_ref(), ast::Expr::Literal(ast::Literal::Null)) =>
+        {
+            operand.as_ref()
+        }
+        _ => return false,
+    };
+    match term {
+        ast::Expr::Binary(
+            lhs,
+            ast::Operator::Equals
+            | ast::Operator::NotEquals
+            | ast::Operator::Less
+            | ast::Operator::LessEquals
+            | ast::Operator::Greater
+            | ast::Operator::GreaterEquals,
+            rhs,
+        ) => {
+            exprs_are_equivalent(lhs, not_null_operand)
+                || exprs_are_equivalent(rhs, not_null_operand)
+        }
+        ast::Expr::InList {
+            lhs, not: false, ..
+        } => exprs_are_equivalent(lhs, not_null_operand),
+        _ => false,
+    }
+}
+
+/// Whether `index` can be used to access `table_reference`. A partial index can only be used if
+/// every term of its WHERE clause is implied by a term of the query's WHERE clause, so that every
+/// row the query needs is in the index. For the right-hand side table of an OUTER JOIN, only the
+/// terms of the join condition count, since the WHERE clause also applies to the NULL row the
+/// join produces when no row of the table matches.
+fn partial_index_is_usable(
+    index: &Index,
+    table_reference: &JoinedTable,
+    where_clause: &[WhereTerm],
+    left_of_right_join: bool,
+) -> bool {
+    let Some(index_where) = &index.where_clause else {
+        return true;
+    };
+    let outer_join_id = (left_of_right_join
+        || table_reference
+            .join_info
+            .as_ref()
+            .is_some_and(|join_info| join_info.outer))
+    .then_some(table_reference.internal_id);
+    let mut conjuncts = vec![];
+    split_conjuncts(
+        bind_index_expr(
+            index_where,
+            &table_reference.table,
+            table_reference.internal_id,
+        ),
+        &mut conjuncts,
+    );
+    conjuncts.iter().all(|conjunct| {
+        where_clause
+            .iter()
+            .filter(|term| term.from_outer_join == outer_join_id)
+            .any(|term| term_implies(&term.expr, conjunct))
+    })
+}
+
 fn opposite_cmp_op(op: ast::Operator) -> ast::Operator {
     match op {
         ast::Operator::Equals => ast::Operator::Equals,
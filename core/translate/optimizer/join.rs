@@ -173,6 +173,7 @@ pub fn compute_best_join_order<'a>(
         table_id: TableInternalId::default(),
         original_idx: 0,
         is_outer: false,
+        is_right: false,
     });
 
     // Keep track of the current best cost so we can short-circuit planning for subplans
@@ -197,6 +198,7 @@ pub fn compute_best_join_order<'a>(
             table_id: table_ref.internal_id,
             original_idx: i,
             is_outer: false,
+            is_right: false,
         };
         assert!(join_order.len() == 1);
         let rel = join_lhs_and_rhs(
@@ -218,20 +220,28 @@ pub fn compute_best_join_order<'a>(
     // Example:
     // "a LEFT JOIN b" can NOT be reordered as "b LEFT JOIN a".
     // If there are outer joins in the plan, ensure correct ordering.
+    // The right table of a RIGHT JOIN must also come after all the tables to its left, and before
+    // all the tables to its right, since its rows without a match are joined with NULLs for
+    // exactly the tables that come before it.
     let left_join_illegal_map = {
-        let left_join_count = joined_tables
+        let outer_join_count = joined_tables
             .iter()
-            .filter(|t| t.join_info.as_ref().is_some_and(|j| j.outer))
+            .filter(|t| t.join_info.as_ref().is_some_and(|j| j.outer || j.right))
             .count();
-        if left_join_count == 0 {
+        if outer_join_count == 0 {
             None
         } else {
             // map from rhs table index to lhs table index
             let mut left_join_illegal_map: HashMap<usize, TableMask> =
-                HashMap::with_capacity(left_join_count);
-            for (i, _) in joined_tables.iter().enumerate() {
+                HashMap::with_capacity(outer_join_count);
+            for (i, lhs_table) in joined_tables.iter().enumerate() {
+                let lhs_is_right = lhs_table.join_info.as_ref().is_some_and(|j| j.right);
                 for (j, joined_table) in joined_tables.iter().enumerate().skip(i + 1) {
-                    if joined_table.join_info.as_ref().is_some_and(|j| j.outer) {
+                    let is_outer = joined_table
+                        .join_info
+                        .as_ref()
+                        .is_some_and(|j| j.outer || j.right);
+                    if is_outer || lhs_is_right {
                         // bitwise OR the masks
                         if let Some(illegal_lhs) = left_join_illegal_map.get_mut(&i) {
                             illegal_lhs.add_table(j);
@@ -301,6 +311,10 @@ pub fn compute_best_join_order<'a>(
                             .join_info
                             .as_ref()
                             .is_some_and(|j| j.outer),
+                        is_right: joined_tables[table_no]
+                            .join_info
+                            .as_ref()
+                            .is_some_and(|j| j.right),
                     });
                 }
                 join_order.push(JoinOrderMember {
@@ -310,6 +324,10 @@ pub fn compute_best_join_order<'a>(
                         .join_info
                         .as_ref()
                         .is_some_and(|j| j.outer),
+                    is_right: joined_tables[rhs_idx]
+                        .join_info
+                        .as_ref()
+                        .is_some_and(|j| j.right),
                 });
                 assert!(join_order.len() == subset_size);
 
@@ -416,6 +434,7 @@ pub fn compute_naive_left_deep_plan<'a>(
             table_id: t.internal_id,
             original_idx: i,
             is_outer: t.join_info.as_ref().is_some_and(|j| j.outer),
+            is_right: t.join_info.as_ref().is_some_and(|j| j.right),
         })
         .collect::<Vec<_>>();
 
@@ -670,11 +689,15 @@ mod tests {
                 pos_in_table: 0,
                 collation: None,
                 default: None,
+                expr: None,
             }],
             unique: true,
             ephemeral: false,
             root_page: 1,
             has_rowid: true,
+            where_clause: None,
+            non_key_columns: 0,
+            temporary: false,
         });
         available_indexes.insert("test_table".to_string(), vec![index]);
 
@@ -719,6 +742,7 @@ mod tests {
                 t2.clone(),
                 Some(JoinInfo {
                     outer: false,
+                    right: false,
                     using: vec![],
                 }),
                 table_id_counter.next(),
@@ -739,11 +763,15 @@ mod tests {
                 pos_in_table: 0,
                 collation: None,
                 default: None,
+                expr: None,
             }],
             unique: true,
             ephemeral: false,
             root_page: 1,
             has_rowid: true,
+            where_clause: None,
+            non_key_columns: 0,
+            temporary: false,
         });
         available_indexes.insert("table1".to_string(), vec![index1]);
 
@@ -823,6 +851,7 @@ mod tests {
                 table_customers.clone(),
                 Some(JoinInfo {
                     outer: false,
+                    right: false,
                     using: vec![],
                 }),
                 table_id_counter.next(),
@@ -831,6 +860,7 @@ mod tests {
                 table_order_items.clone(),
                 Some(JoinInfo {
                     outer: false,
+                    right: false,
                     using: vec![],
                 }),
                 table_id_counter.next(),
@@ -856,11 +886,15 @@ mod tests {
                         pos_in_table: 0,
                         collation: None,
                         default: None,
+                        expr: None,
                     }],
                     unique: true,
                     ephemeral: false,
                     root_page: 1,
                     has_rowid: true,
+                    where_clause: None,
+                    non_key_columns: 0,
+                    temporary: false,
                 });
                 available_indexes.insert(table_name.to_string(), vec![index]);
             });
@@ -873,11 +907,15 @@ mod tests {
                 pos_in_table: 1,
                 collation: None,
                 default: None,
+                expr: None,
             }],
             unique: false,
             ephemeral: false,
             root_page: 1,
             has_rowid: true,
+            where_clause: None,
+            non_key_columns: 0,
+            temporary: false,
         });
         let order_id_idx = Arc::new(Index {
             name: "order_items_order_id_idx".to_string(),
@@ -888,11 +926,15 @@ mod tests {
                 pos_in_table: 1,
                 collation: None,
                 default: None,
+                expr: None,
             }],
             unique: false,
             ephemeral: false,
             root_page: 1,
             has_rowid: true,
+            where_clause: None,
+            non_key_columns: 0,
+            temporary: false,
         });
 
         available_indexes
@@ -1007,6 +1049,7 @@ mod tests {
                 t2.clone(),
                 Some(JoinInfo {
                     outer: false,
+                    right: false,
                     using: vec![],
                 }),
                 table_id_counter.next(),
@@ -1015,6 +1058,7 @@ mod tests {
                 t3.clone(),
                 Some(JoinInfo {
                     outer: false,
+                    right: false,
                     using: vec![],
                 }),
                 table_id_counter.next(),
@@ -1113,6 +1157,7 @@ mod tests {
                     t.clone(),
                     Some(JoinInfo {
                         outer: false,
+                        right: false,
                         using: vec![],
                     }),
                     table_id_counter.next(),
@@ -1122,6 +1167,7 @@ mod tests {
                 fact_table.clone(),
                 Some(JoinInfo {
                     outer: false,
+                    right: false,
                     using: vec![],
                 }),
                 table_id_counter.next(),
@@ -1302,6 +1348,7 @@ mod tests {
                     pos_in_table: 0,
                     collation: None,
                     default: None,
+                    expr: None,
                 },
                 IndexColumn {
                     name: "y".to_string(),
@@ -1309,12 +1356,16 @@ mod tests {
                     pos_in_table: 1,
                     collation: None,
                     default: None,
+                    expr: None,
                 },
             ],
             unique: false,
             root_page: 2,
             ephemeral: false,
             has_rowid: true,
+            where_clause: None,
+            non_key_columns: 0,
+            temporary: false,
         });
 
         let mut available_indexes = HashMap::new();
@@ -1388,6 +1439,7 @@ mod tests {
                     pos_in_table: 0,
                     collation: None,
                     default: None,
+                    expr: None,
                 },
                 IndexColumn {
                     name: "c2".to_string(),
@@ -1395,6 +1447,7 @@ mod tests {
                     pos_in_table: 1,
                     collation: None,
                     default: None,
+                    expr: None,
                 },
                 IndexColumn {
                     name: "c3".to_string(),
@@ -1402,12 +1455,16 @@ mod tests {
                     pos_in_table: 2,
                     collation: None,
                     default: None,
+                    expr: None,
                 },
             ],
             unique: false,
             root_page: 2,
             ephemeral: false,
             has_rowid: true,
+            where_clause: None,
+            non_key_columns: 0,
+            temporary: false,
         });
         available_indexes.insert("t1".to_string(), vec![index]);
 
@@ -1476,7 +1533,7 @@ mod tests {
         assert!(constraint_refs.len() == 1);
         let constraint = &table_constraints[0].constraints[constraint_refs[0].constraint_vec_pos];
         assert!(constraint.operator == ast::Operator::Equals);
-        assert!(constraint.table_col_pos == 0); // c1
+        assert!(constraint.table_col_pos == Some(0)); // c1
     }
 
     #[test]
@@ -1499,6 +1556,7 @@ mod tests {
                     pos_in_table: 0,
                     collation: None,
                     default: None,
+                    expr: None,
                 },
                 IndexColumn {
                     name: "c2".to_string(),
@@ -1506,6 +1564,7 @@ mod tests {
                     pos_in_table: 1,
                     collation: None,
                     default: None,
+                    expr: None,
                 },
                 IndexColumn {
                     name: "c3".to_string(),
@@ -1513,12 +1572,16 @@ mod tests {
                     pos_in_table: 2,
                     collation: None,
                     default: None,
+                    expr: None,
                 },
             ],
             root_page: 2,
             ephemeral: false,
             has_rowid: true,
             unique: false,
+            where_clause: None,
+            non_key_columns: 0,
+            temporary: false,
         });
         available_indexes.insert("t1".to_string(), vec![index]);
 
@@ -1602,10 +1665,10 @@ mod tests {
         assert!(constraint_refs.len() == 2);
         let constraint = &table_constraints[0].constraints[constraint_refs[0].constraint_vec_pos];
         assert!(constraint.operator == ast::Operator::Equals);
-        assert!(constraint.table_col_pos == 0); // c1
+        assert!(constraint.table_col_pos == Some(0)); // c1
         let constraint = &table_constraints[0].constraints[constraint_refs[1].constraint_vec_pos];
         assert!(constraint.operator == ast::Operator::Greater);
-        assert!(constraint.table_col_pos == 1); // c2
+        assert!(constraint.table_col_pos == Some(1)); // c2
     }
 
     fn _create_column(c: &TestColumn) -> Column {
@@ -1620,6 +1683,7 @@ mod tests {
             unique: false,
             collation: None,
             hidden: false,
+            generated: None,
         }
     }
     fn _create_column_of_type(name: &str, ty: Type) -> Column {
@@ -1655,6 +1719,9 @@ mod tests {
             has_rowid: true,
             is_strict: false,
             unique_sets: None,
+            foreign_keys: vec![],
+            check_constraints: vec![],
+            temporary: false,
         })
     }
 
@@ -160,7 +160,20 @@ fn optimize_update_plan(plan: &mut UpdatePlan, schema: &Schema) -> Result<()> {
 fn optimize_subqueries(plan: &mut SelectPlan, schema: &Schema) -> Result<()> {
     for table in plan.table_references.joined_tables_mut() {
         if let Table::FromClauseSubquery(from_clause_subquery) = &mut table.table {
-            optimize_select_plan(&mut from_clause_subquery.plan, schema)?;
+            if from_clause_subquery.is_recursive_reference {
+                continue;
+            }
+            if let Some(recursive) = from_clause_subquery.recursive.as_mut() {
+                for plan in recursive
+                    .initial
+                    .iter_mut()
+                    .chain(recursive.recursive.iter_mut())
+                {
+                    optimize_select_plan(plan, schema)?;
+                }
+            } else {
+                optimize_select_plan(&mut from_clause_subquery.plan, schema)?;
+            }
         }
     }
 
@@ -262,6 +275,10 @@ fn optimize_table_access(
                 .join_info
                 .as_ref()
                 .is_some_and(|join_info| join_info.outer),
+            is_right: joined_tables[table_number]
+                .join_info
+                .as_ref()
+                .is_some_and(|join_info| join_info.right),
         })
         .collect();
 
@@ -280,7 +297,13 @@ fn optimize_table_access(
                     let try_to_build_ephemeral_index = if schema.indexes_enabled() {
                         let is_leftmost_table = i == 0;
                         let uses_index = index.is_some();
-                        !is_leftmost_table && !uses_index
+                        // An automatic index on a WITHOUT ROWID table is never led back to the
+                        // table, where the rows of the right table of a RIGHT JOIN are read.
+                        let is_right_without_rowid = join_order_member.is_right
+                            && joined_tables[table_idx]
+                                .btree()
+                                .is_some_and(|btree| !btree.has_rowid);
+                        !is_leftmost_table && !uses_index && !is_right_without_rowid
                     } else {
                         false
                     };
@@ -304,11 +327,18 @@ fn optimize_table_access(
                         });
                         continue;
                     };
-                    let temp_constraint_refs = (0..table_constraints.constraints.len())
-                        .map(|i| ConstraintRef {
-                            constraint_vec_pos: i,
-                            index_col_pos: table_constraints.constraints[i].table_col_pos,
-                            sort_order: SortOrder::Asc,
+                    // Constraints on expressions can't be used, since the ephemeral index only
+                    // contains columns.
+                    let temp_constraint_refs = table_constraints
+                        .constraints
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, constraint)| {
+                            Some(ConstraintRef {
+                                constraint_vec_pos: i,
+                                index_col_pos: constraint.table_col_pos?,
+                                sort_order: SortOrder::Asc,
+                            })
                         })
                         .collect::<Vec<_>>();
                     let usable_constraint_refs = usable_constraints_for_join_order(
@@ -404,6 +434,28 @@ fn optimize_table_access(
         }
     }
 
+    // A WITHOUT ROWID table is stored in the B-tree of its clustered index, so using that index
+    // means iterating over the table itself.
+    for table in joined_tables.iter_mut() {
+        let Table::BTree(btree) = &table.table else {
+            continue;
+        };
+        if btree.has_rowid {
+            continue;
+        }
+        let root_page = btree.root_page;
+        if let Operation::Scan(Scan::BTreeTable { index, .. })
+        | Operation::Search(Search::Seek { index, .. }) = &mut table.op
+        {
+            if index
+                .as_ref()
+                .is_some_and(|index| !index.ephemeral && index.root_page == root_page)
+            {
+                *index = None;
+            }
+        }
+    }
+
     Ok(Some(best_join_order))
 }
 
@@ -744,7 +796,8 @@ impl Optimizable for ast::Expr {
             Expr::Qualified(_, _) => {
                 panic!("Qualified should have been rewritten as Column")
             }
-            Expr::Raise(_, expr) => expr.as_ref().is_none_or(|expr| expr.is_constant(resolver)),
+            // RAISE() halts the program, so it must never be hoisted out of its branch
+            Expr::Raise(..) => false,
             Expr::Subquery(_) => false,
             Expr::Unary(_, expr) => expr.is_constant(resolver),
             Expr::Variable(_) => false,
@@ -875,8 +928,9 @@ fn ephemeral_index_build(
             name: c.name.clone().unwrap(),
             order: SortOrder::Asc,
             pos_in_table: i,
-            collation: c.collation,
+            collation: c.collation.clone(),
             default: c.default.clone(),
+            expr: None,
         })
         // only include columns that are used in the query
         .filter(|c| table_reference.column_is_used(c.pos_in_table))
@@ -886,11 +940,11 @@ fn ephemeral_index_build(
         let a_constraint = constraint_refs
             .iter()
             .enumerate()
-            .find(|(_, c)| constraints[c.constraint_vec_pos].table_col_pos == a.pos_in_table);
+            .find(|(_, c)| constraints[c.constraint_vec_pos].table_col_pos == Some(a.pos_in_table));
         let b_constraint = constraint_refs
             .iter()
             .enumerate()
-            .find(|(_, c)| constraints[c.constraint_vec_pos].table_col_pos == b.pos_in_table);
+            .find(|(_, c)| constraints[c.constraint_vec_pos].table_col_pos == Some(b.pos_in_table));
         match (a_constraint, b_constraint) {
             (Some(_), None) => Ordering::Less,
             (None, Some(_)) => Ordering::Greater,
@@ -913,6 +967,9 @@ fn ephemeral_index_build(
             .table
             .btree()
             .is_some_and(|btree| btree.has_rowid),
+        where_clause: None,
+        non_key_columns: 0,
+        temporary: false,
     };
 
     ephemeral_index
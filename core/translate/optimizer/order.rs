@@ -161,6 +161,14 @@ pub fn plan_satisfies_order_target(
     joined_tables: &[JoinedTable],
     order_target: &OrderTarget,
 ) -> bool {
+    // The rows of the right table of a RIGHT JOIN that match no row are emitted after all the
+    // other rows, whatever the order of the loops.
+    if joined_tables
+        .iter()
+        .any(|t| t.join_info.as_ref().is_some_and(|j| j.right))
+    {
+        return false;
+    }
     let mut target_col_idx = 0;
     let num_cols_in_order_target = order_target.0.len();
     for (table_index, access_method_index) in plan.data.iter() {
@@ -212,7 +220,8 @@ pub fn plan_satisfies_order_target(
                         // All of the index columns must match the next required columns in the order target.
                         for index_col in index.columns.iter() {
                             let target_col = &order_target.0[target_col_idx];
-                            let correct_column = target_col.column_no == index_col.pos_in_table;
+                            let correct_column = index_col.expr.is_none()
+                                && target_col.column_no == index_col.pos_in_table;
                             if !correct_column {
                                 return false;
                             }
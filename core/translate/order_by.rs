@@ -57,7 +57,7 @@ pub fn init_order_by(
         .iter()
         .map(|(expr, _)| match expr.as_ref() {
             ast::Expr::Collate(_, collation_name) => {
-                CollationSeq::new(collation_name.as_str()).map(Some)
+                CollationSeq::new(collation_name.as_str(), t_ctx.resolver.symbol_table).map(Some)
             }
             ast::Expr::Column { table, column, .. } => {
                 let table = referenced_tables.find_table_by_internal_id(*table).unwrap();
@@ -66,7 +66,7 @@ pub fn init_order_by(
                     crate::bail_parse_error!("column index out of bounds");
                 };
 
-                Ok(table_column.collation)
+                Ok(table_column.collation.clone())
             }
             _ => Ok(Some(CollationSeq::default())),
         })
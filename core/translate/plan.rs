@@ -2,7 +2,7 @@ use std::{cmp::Ordering, sync::Arc};
 use turso_parser::ast::{self, SortOrder};
 
 use crate::{
-    function::AggFunc,
+    function::{AggFunc, WindowFunc},
     schema::{BTreeTable, Column, FromClauseSubquery, Index, Table},
     vdbe::{
         builder::{CursorKey, CursorType, ProgramBuilder},
@@ -11,11 +11,17 @@ use crate::{
     },
     Result, VirtualTable,
 };
-use crate::{schema::Type, types::SeekOp};
+use crate::{
+    schema::Type,
+    types::{SeekOp, Value},
+};
 
 use turso_parser::ast::TableInternalId;
 
-use super::{emitter::OperationMode, planner::determine_where_to_eval_term};
+use super::{
+    emitter::OperationMode, foreign_key::ForeignKeyPlan, planner::determine_where_to_eval_term,
+    trigger::TriggerPrograms,
+};
 
 #[derive(Debug, Clone)]
 pub struct ResultSetColumn {
@@ -159,7 +165,7 @@ pub enum Plan {
         order_by: Option<Vec<(ast::Expr, SortOrder)>>,
     },
     Delete(DeletePlan),
-    Update(UpdatePlan),
+    Update(Box<UpdatePlan>),
 }
 
 /// The destination of the results of a query.
@@ -198,6 +204,109 @@ pub enum QueryDestination {
     },
 }
 
+/// The SELECTs of a recursive CTE, evaluated with a queue:
+/// - the rows of the `initial` SELECTs are added to the queue,
+/// - then, until the queue is empty, a row is taken from it, returned as a row of the CTE and
+///   the `recursive` SELECTs are run with their reference to the CTE producing that single row.
+///   Their rows are added to the queue.
+#[derive(Debug, Clone)]
+pub struct RecursiveCte {
+    /// The SELECTs that do not reference the CTE.
+    pub initial: Vec<SelectPlan>,
+    /// The SELECTs that reference the CTE in their FROM clause.
+    pub recursive: Vec<SelectPlan>,
+    /// Whether the SELECTs are combined with UNION rather than UNION ALL. If so, rows that were
+    /// already added to the queue once are discarded, which is what stops the recursion on
+    /// cyclic data.
+    pub distinct: bool,
+    /// Maximum number of rows produced by the CTE; the recursion stops when it is reached.
+    pub limit: Option<isize>,
+    /// Number of rows produced by the CTE that are skipped. They are still expanded.
+    pub offset: Option<isize>,
+}
+
+/// The window functions computed over the rows of a FROM clause subquery.
+///
+/// All arguments, PARTITION BY and ORDER BY terms of the window functions are result columns
+/// of the subquery, referenced by their index. The rows are sorted once per distinct
+/// [WindowPartitioning], and each partition is buffered before the results of the window
+/// functions using that partitioning are computed for its rows.
+#[derive(Debug, Clone)]
+pub struct WindowPlan {
+    pub partitionings: Vec<WindowPartitioning>,
+    pub functions: Vec<WindowFunction>,
+}
+
+/// The PARTITION BY and ORDER BY terms of a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowPartitioning {
+    pub partition_by: Vec<usize>,
+    pub order_by: Vec<(usize, SortOrder)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WindowFunction {
+    pub func: WindowFunctionKind,
+    /// Indexes of the subquery columns holding the arguments.
+    pub args: Vec<usize>,
+    /// Index of the subquery column holding the FILTER condition, if any.
+    pub filter: Option<usize>,
+    /// Index of the partitioning in [WindowPlan::partitionings].
+    pub partitioning: usize,
+    pub frame: WindowFrame,
+    /// The window function call, used to name the result column.
+    pub original_expr: ast::Expr,
+}
+
+#[derive(Debug, Clone)]
+pub enum WindowFunctionKind {
+    Builtin(WindowFunc),
+    Aggregate(AggFunc),
+}
+
+/// The frame of a window, i.e. the rows of the partition an aggregate or a value function
+/// like `first_value()` is computed over for a given row.
+#[derive(Debug, Clone)]
+pub struct WindowFrame {
+    pub mode: ast::FrameMode,
+    pub start: WindowFrameBound,
+    pub end: WindowFrameBound,
+    pub exclude: ast::FrameExclude,
+}
+
+impl Default for WindowFrame {
+    /// `RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW`, the frame of a window without a
+    /// frame specification.
+    fn default() -> Self {
+        Self {
+            mode: ast::FrameMode::Range,
+            start: WindowFrameBound::UnboundedPreceding,
+            end: WindowFrameBound::CurrentRow,
+            exclude: ast::FrameExclude::NoOthers,
+        }
+    }
+}
+
+impl WindowFrame {
+    /// Whether the frame of each row includes the frame of the previous row, so that the
+    /// frame can be computed incrementally as the rows of the partition are visited in order.
+    pub fn is_growing(&self) -> bool {
+        matches!(self.start, WindowFrameBound::UnboundedPreceding)
+            && matches!(self.exclude, ast::FrameExclude::NoOthers)
+    }
+}
+
+/// A frame boundary. Offsets are non-negative integers for ROWS and GROUPS frames and
+/// non-negative numbers for RANGE frames.
+#[derive(Debug, Clone)]
+pub enum WindowFrameBound {
+    UnboundedPreceding,
+    Preceding(Value),
+    CurrentRow,
+    Following(Value),
+    UnboundedFollowing,
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct JoinOrderMember {
     /// The internal ID of the[TableReference]
@@ -207,6 +316,8 @@ pub struct JoinOrderMember {
     pub original_idx: usize,
     /// Whether this member is the right side of an OUTER JOIN
     pub is_outer: bool,
+    /// Whether this member is the right side of a RIGHT or FULL OUTER JOIN
+    pub is_right: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -385,6 +496,10 @@ pub struct DeletePlan {
     pub contains_constant_false_condition: bool,
     /// Indexes that must be updated by the delete operation.
     pub indexes: Vec<Arc<Index>>,
+    /// Triggers fired for every deleted row.
+    pub triggers: TriggerPrograms,
+    /// Foreign key checks and actions performed for every deleted row.
+    pub foreign_keys: ForeignKeyPlan,
 }
 
 #[derive(Debug, Clone)]
@@ -406,6 +521,10 @@ pub struct UpdatePlan {
     // For ALTER TABLE turso-db emits appropriate DDL statement in the "updates" cell of CDC table
     // This field is present only for update plan created for ALTER TABLE when CDC mode has "updates" values
     pub cdc_update_alter_statement: Option<String>,
+    // Triggers fired for every updated row
+    pub triggers: TriggerPrograms,
+    // Foreign key checks and actions performed for every updated row
+    pub foreign_keys: ForeignKeyPlan,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -415,7 +534,7 @@ pub enum IterationDirection {
 }
 
 pub fn select_star(tables: &[JoinedTable], out_columns: &mut Vec<ResultSetColumn>) {
-    for table in tables.iter() {
+    for (table_idx, table) in tables.iter().enumerate() {
         out_columns.extend(
             table
                 .columns()
@@ -436,24 +555,82 @@ pub fn select_star(tables: &[JoinedTable], out_columns: &mut Vec<ResultSetColumn
                     }
                 })
                 .map(|(i, col)| ResultSetColumn {
-                    alias: None,
-                    expr: ast::Expr::Column {
-                        database: None,
-                        table: table.internal_id,
-                        column: i,
-                        is_rowid_alias: col.is_rowid_alias,
-                    },
+                    // A column joined with USING in a RIGHT JOIN keeps its name
+                    alias: right_join_using_column(&tables[table_idx + 1..], col)
+                        .and(col.name.clone()),
+                    expr: joined_column_expr(tables, table_idx, i),
                     contains_aggregates: false,
                 }),
         );
     }
 }
 
+/// Returns the expression that reads column `column` of `tables[table_idx]`. A column joined
+/// with USING in a later RIGHT JOIN is NULL for the rows of the right table that match no row,
+/// so it is taken from the right table for those rows.
+pub fn joined_column_expr(tables: &[JoinedTable], table_idx: usize, column: usize) -> ast::Expr {
+    let table = &tables[table_idx];
+    let col = &table.columns()[column];
+    let expr = ast::Expr::Column {
+        database: None,
+        table: table.internal_id,
+        column,
+        is_rowid_alias: col.is_rowid_alias,
+    };
+    let Some(right_expr) = right_join_using_column(&tables[table_idx + 1..], col) else {
+        return expr;
+    };
+    ast::Expr::FunctionCall {
+        name: ast::Name::new("coalesce"),
+        distinctness: None,
+        args: vec![Box::new(expr), Box::new(right_expr)],
+        order_by: vec![],
+        filter_over: ast::FunctionTail {
+            filter_clause: None,
+            over_clause: None,
+        },
+    }
+}
+
+/// Returns the column named like `column` of the first of `tables` that is the right table of a
+/// RIGHT JOIN with that name in its USING clause.
+fn right_join_using_column(tables: &[JoinedTable], column: &Column) -> Option<ast::Expr> {
+    let name = column.name.as_deref()?;
+    tables.iter().find_map(|table| {
+        let join_info = table
+            .join_info
+            .as_ref()
+            .filter(|join_info| join_info.right)?;
+        if !join_info
+            .using
+            .iter()
+            .any(|using_col| using_col.as_str().eq_ignore_ascii_case(name))
+        {
+            return None;
+        }
+        let (column, col) = table.columns().iter().enumerate().find(|(_, col)| {
+            col.name
+                .as_ref()
+                .is_some_and(|col_name| col_name.eq_ignore_ascii_case(name))
+        })?;
+        Some(ast::Expr::Column {
+            database: None,
+            table: table.internal_id,
+            column,
+            is_rowid_alias: col.is_rowid_alias,
+        })
+    })
+}
+
 /// Join information for a table reference.
 #[derive(Debug, Clone)]
 pub struct JoinInfo {
-    /// Whether this is an OUTER JOIN.
+    /// Whether this is a LEFT (or FULL) OUTER JOIN, i.e. whether the rows of the tables to the left
+    /// that match no row of this table are returned with NULLs for this table.
     pub outer: bool,
+    /// Whether this is a RIGHT (or FULL) OUTER JOIN, i.e. whether the rows of this table that match
+    /// no row of the tables to the left are returned with NULLs for those tables.
+    pub right: bool,
     /// The USING clause for the join, if any. NATURAL JOIN is transformed into USING (col1, col2, ...).
     pub using: Vec<ast::Name>,
 }
@@ -467,7 +644,7 @@ pub struct JoinInfo {
 /// - all have [Operation::Scan]
 /// - identifiers are `t`, `p`, `sub`
 /// - `t` and `p` are [Table::BTree] while `sub` is [Table::FromClauseSubquery]
-/// - join_info is None for the first table reference, and Some(JoinInfo { outer: false, using: None }) for the second and third table references
+/// - join_info is None for the first table reference, and Some(JoinInfo { outer: false, right: false, using: None }) for the second and third table references
 #[derive(Debug, Clone)]
 pub struct JoinedTable {
     /// The operation that this table reference performs.
@@ -815,6 +992,7 @@ impl JoinedTable {
                 unique: false,
                 collation: None, // FIXME: infer collation from subquery
                 hidden: false,
+                generated: None,
             })
             .collect();
 
@@ -823,6 +1001,9 @@ impl JoinedTable {
             plan: Box::new(plan),
             columns,
             result_columns_start_reg: None,
+            recursive: None,
+            is_recursive_reference: false,
+            window: None,
         });
         Self {
             op: Operation::default_scan_for(&table),
@@ -909,8 +1090,18 @@ impl JoinedTable {
         if self.col_used_mask.is_empty() {
             return false;
         }
+        // The rows of the right table of a RIGHT JOIN that match no row are read from the table.
+        if self
+            .join_info
+            .as_ref()
+            .is_some_and(|join_info| join_info.right)
+        {
+            return false;
+        }
         let mut index_cols_mask = ColumnUsedMask::default();
-        for col in index.columns.iter() {
+        // the columns of an index on expressions hold the values of the expressions, not of any
+        // table column
+        for col in index.columns.iter().filter(|col| col.expr.is_none()) {
             index_cols_mask.set(col.pos_in_table);
         }
 
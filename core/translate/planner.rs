@@ -3,9 +3,9 @@ use std::sync::Arc;
 use super::{
     expr::walk_expr,
     plan::{
-        Aggregate, ColumnUsedMask, Distinctness, EvalAt, JoinInfo, JoinOrderMember, JoinedTable,
-        Operation, OuterQueryReference, Plan, QueryDestination, ResultSetColumn, Scan,
-        TableReferences, WhereTerm,
+        joined_column_expr, Aggregate, ColumnUsedMask, Distinctness, EvalAt, JoinInfo,
+        JoinOrderMember, JoinedTable, Operation, OuterQueryReference, Plan, QueryDestination,
+        RecursiveCte, ResultSetColumn, Scan, SelectPlan, TableReferences, WhereTerm,
     },
     select::prepare_select_plan,
     SymbolTable,
@@ -13,7 +13,7 @@ use super::{
 use crate::translate::expr::WalkControl;
 use crate::{
     function::Func,
-    schema::{Schema, Table},
+    schema::{Column, Schema, Table},
     translate::expr::walk_expr_mut,
     util::{exprs_are_equivalent, normalize_ident},
     vdbe::{builder::TableRefIdCounter, BranchOffset},
@@ -22,8 +22,8 @@ use crate::{
 use turso_macros::match_ignore_ascii_case;
 use turso_parser::ast::Literal::Null;
 use turso_parser::ast::{
-    self, As, Expr, FromClause, JoinType, Limit, Literal, Materialized, QualifiedName,
-    TableInternalId, UnaryOperator, With,
+    self, As, Expr, FromClause, JoinType, Limit, Literal, QualifiedName, TableInternalId,
+    UnaryOperator, With,
 };
 
 pub const ROWID: &str = "rowid";
@@ -50,7 +50,10 @@ pub fn resolve_aggregates(
                 filter_over,
                 order_by,
             } => {
-                if filter_over.filter_clause.is_some() || filter_over.over_clause.is_some() {
+                if filter_over.over_clause.is_some() {
+                    crate::bail_parse_error!("misuse of window function {}()", name.as_str());
+                }
+                if filter_over.filter_clause.is_some() {
                     crate::bail_parse_error!(
                         "FILTER clause is not supported yet in aggregate functions"
                     );
@@ -85,7 +88,10 @@ pub fn resolve_aggregates(
                 }
             }
             Expr::FunctionCallStar { name, filter_over } => {
-                if filter_over.filter_clause.is_some() || filter_over.over_clause.is_some() {
+                if filter_over.over_clause.is_some() {
+                    crate::bail_parse_error!("misuse of window function {}()", name.as_str());
+                }
+                if filter_over.filter_clause.is_some() {
                     crate::bail_parse_error!(
                         "FILTER clause is not supported yet in aggregate functions"
                     );
@@ -124,7 +130,12 @@ pub fn bind_column_references(
                 });
                 let normalized_id = normalize_ident(id.as_str());
 
-                if !referenced_tables.joined_tables().is_empty() {
+                // A WITHOUT ROWID table has no rowid
+                let has_rowid = referenced_tables
+                    .joined_tables()
+                    .first()
+                    .is_some_and(|t| t.btree().is_none_or(|btree| btree.has_rowid));
+                if has_rowid {
                     if let Some(row_id_expr) = parse_row_id(
                         &normalized_id,
                         referenced_tables.joined_tables()[0].internal_id,
@@ -137,24 +148,40 @@ pub fn bind_column_references(
                 }
                 let mut match_result = None;
 
-                // First check joined tables
-                for joined_table in referenced_tables.joined_tables().iter() {
+                // First check joined tables. A column joined with USING is found in the
+                // table to the left of the join.
+                let mut joined_match = None;
+                for (table_idx, joined_table) in
+                    referenced_tables.joined_tables().iter().enumerate()
+                {
                     let col_idx = joined_table.table.columns().iter().position(|c| {
                         c.name
                             .as_ref()
                             .is_some_and(|name| name.eq_ignore_ascii_case(&normalized_id))
                     });
-                    if col_idx.is_some() {
-                        if match_result.is_some() {
-                            crate::bail_parse_error!("Column {} is ambiguous", id.as_str());
+                    let Some(col_idx) = col_idx else {
+                        continue;
+                    };
+                    if joined_match.is_some() {
+                        let is_using_column = joined_table.join_info.as_ref().is_some_and(|j| {
+                            j.using
+                                .iter()
+                                .any(|c| c.as_str().eq_ignore_ascii_case(&normalized_id))
+                        });
+                        if is_using_column {
+                            continue;
                         }
-                        let col = joined_table.table.columns().get(col_idx.unwrap()).unwrap();
-                        match_result = Some((
-                            joined_table.internal_id,
-                            col_idx.unwrap(),
-                            col.is_rowid_alias,
-                        ));
+                        crate::bail_parse_error!("Column {} is ambiguous", id.as_str());
                     }
+                    joined_match = Some((table_idx, col_idx));
+                }
+                if let Some((table_idx, col_idx)) = joined_match {
+                    let joined_table = &referenced_tables.joined_tables()[table_idx];
+                    let table_id = joined_table.internal_id;
+                    *expr =
+                        joined_column_expr(referenced_tables.joined_tables(), table_idx, col_idx);
+                    referenced_tables.mark_column_used(table_id, col_idx);
+                    return Ok(());
                 }
 
                 // Then check outer query references, if we still didn't find something.
@@ -226,7 +253,10 @@ pub fn bind_column_references(
                 let (tbl_id, tbl) = matching_tbl.unwrap();
                 let normalized_id = normalize_ident(id.as_str());
 
-                if let Some(row_id_expr) = parse_row_id(&normalized_id, tbl_id, || false)? {
+                let has_rowid = tbl.btree().is_none_or(|btree| btree.has_rowid);
+                if let Some(row_id_expr) =
+                    parse_row_id(&normalized_id, tbl_id, || false)?.filter(|_| has_rowid)
+                {
                     *expr = row_id_expr;
 
                     return Ok(());
@@ -262,7 +292,7 @@ pub fn bind_column_references(
 
                 // Get the table from the specified database
                 let table = connection
-                    .with_schema(database_id, |schema| schema.get_table(tbl_name.as_str()))
+                    .get_table_in_database(database_id, tbl_name.as_str())
                     .ok_or_else(|| {
                         crate::LimboError::ParseError(format!(
                             "no such table: {}.{}",
@@ -395,6 +425,35 @@ fn parse_from_clause_table(
     }
 }
 
+/// Resolves the database holding `tbl_name`, the table written by an INSERT, UPDATE or DELETE.
+/// The statement is translated with the schema of that database, which is returned along with
+/// its index. For the main database, this is the schema without the TEMP objects hiding its own.
+pub fn resolve_write_target(
+    connection: &Arc<crate::Connection>,
+    tbl_name: &QualifiedName,
+) -> Result<(usize, Arc<Schema>)> {
+    let database_id = connection.resolve_database_id(tbl_name)?;
+    if database_id < 2 {
+        return Ok((database_id, connection.database_schema(database_id)));
+    }
+    if connection._db.mv_store.is_some() {
+        crate::bail_parse_error!("writes to attached databases are not supported with MVCC");
+    }
+    let schema = connection.database_schema(database_id);
+    let table_name = normalize_ident(tbl_name.name.as_str());
+    if !schema.get_triggers_for_table(&table_name).is_empty() {
+        crate::bail_parse_error!("triggers on attached databases are not supported yet");
+    }
+    let has_foreign_keys = schema.get_btree_table(&table_name).is_some_and(|table| {
+        !table.foreign_keys.is_empty()
+            || !schema.get_referencing_foreign_keys(&table.name).is_empty()
+    });
+    if has_foreign_keys && connection.foreign_keys_enabled() {
+        crate::bail_parse_error!("foreign keys on attached databases are not supported yet");
+    }
+    Ok((database_id, schema))
+}
+
 #[allow(clippy::too_many_arguments)]
 fn parse_table(
     schema: &Schema,
@@ -423,8 +482,7 @@ fn parse_table(
         return Ok(());
     };
 
-    // Resolve table using connection's with_schema method
-    let table = connection.with_schema(database_id, |schema| schema.get_table(table_name.as_str()));
+    let table = connection.get_table_in_database(database_id, table_name.as_str());
 
     if let Some(table) = table {
         let alias = maybe_alias
@@ -456,9 +514,7 @@ fn parse_table(
         return Ok(());
     };
 
-    let regular_view = connection.with_schema(database_id, |schema| {
-        schema.get_view(table_name.as_str()).cloned()
-    });
+    let regular_view = connection.get_view_in_database(database_id, table_name.as_str());
     if let Some(view) = regular_view {
         // Views are essentially query aliases, so just Expand the view as a subquery
         let view_select = view.select_stmt.clone();
@@ -530,7 +586,13 @@ fn parse_table(
             table_references.add_joined_table(JoinedTable {
                 op: Operation::default_scan_for(&outer_ref.table),
                 table: outer_ref.table.clone(),
-                identifier: outer_ref.identifier.clone(),
+                identifier: maybe_alias
+                    .map(|a| match a {
+                        ast::As::As(id) => id,
+                        ast::As::Elided(id) => id,
+                    })
+                    .map(|a| normalize_ident(a.as_str()))
+                    .unwrap_or_else(|| outer_ref.identifier.clone()),
                 internal_id: table_ref_counter.next(),
                 join_info: None,
                 col_used_mask: ColumnUsedMask::default(),
@@ -607,17 +669,10 @@ pub fn parse_from(
     let mut ctes_as_subqueries = vec![];
 
     if let Some(with) = with {
-        if with.recursive {
-            crate::bail_parse_error!("Recursive CTEs are not yet supported");
-        }
+        let recursive = with.recursive;
         for cte in with.ctes {
-            if cte.materialized == Materialized::Yes {
-                crate::bail_parse_error!("Materialized CTEs are not yet supported");
-            }
-            if !cte.columns.is_empty() {
-                crate::bail_parse_error!("CTE columns are not yet supported");
-            }
-
+            // MATERIALIZED and NOT MATERIALIZED are only hints: a CTE is always evaluated where it
+            // is used, which gives the same results.
             // Check if normalized name conflicts with catalog tables or other CTEs
             // TODO: sqlite actually allows overriding a catalog table with a CTE.
             // We should carry over the 'Scope' struct to all of our identifier resolution.
@@ -649,29 +704,45 @@ pub fn parse_from(
                     col_used_mask: ColumnUsedMask::default(),
                 }
             }));
+            let column_names: Vec<String> = cte
+                .columns
+                .iter()
+                .map(|c| normalize_ident(c.col_name.as_str()))
+                .collect();
 
             // CTE can refer to other CTEs that came before it, plus any schema tables or tables in the outer scope.
-            let cte_plan = prepare_select_plan(
-                schema,
-                cte.select,
-                syms,
-                &outer_query_refs_for_cte,
-                table_ref_counter,
-                QueryDestination::CoroutineYield {
-                    yield_reg: usize::MAX, // will be set later in bytecode emission
-                    coroutine_implementation_start: BranchOffset::Placeholder, // will be set later in bytecode emission
-                },
-                connection,
-            )?;
-            let Plan::Select(cte_plan) = cte_plan else {
-                crate::bail_parse_error!("Only SELECT queries are currently supported in CTEs");
+            let cte_table = if recursive && is_recursive_cte(&cte.select, &cte_name_normalized) {
+                plan_recursive_cte(
+                    schema,
+                    cte.select,
+                    cte_name_normalized,
+                    &column_names,
+                    syms,
+                    &outer_query_refs_for_cte,
+                    table_ref_counter,
+                    connection,
+                )?
+            } else {
+                let cte_plan = plan_cte_select(
+                    schema,
+                    cte.select,
+                    syms,
+                    &outer_query_refs_for_cte,
+                    table_ref_counter,
+                    connection,
+                )?;
+                let mut cte_table = JoinedTable::new_subquery(
+                    cte_name_normalized,
+                    cte_plan,
+                    None,
+                    table_ref_counter.next(),
+                );
+                if let Table::FromClauseSubquery(subquery) = &mut cte_table.table {
+                    apply_cte_column_names(&mut subquery.columns, &subquery.name, &column_names)?;
+                }
+                cte_table
             };
-            ctes_as_subqueries.push(JoinedTable::new_subquery(
-                cte_name_normalized,
-                cte_plan,
-                None,
-                table_ref_counter.next(),
-            ));
+            ctes_as_subqueries.push(cte_table);
         }
     }
 
@@ -689,7 +760,10 @@ pub fn parse_from(
         connection,
     )?;
 
+    // The position in the WHERE clause of the ON conditions of each join
+    let mut join_conditions_start = Vec::with_capacity(joins_owned.len());
     for join in joins_owned.into_iter() {
+        join_conditions_start.push(out_where_clause.len());
         parse_join(
             schema,
             join,
@@ -703,9 +777,258 @@ pub fn parse_from(
         )?;
     }
 
+    // The rows of the tables to the left of a RIGHT JOIN are joined before the rows of its right
+    // table are matched with them, so the ON conditions of their inner joins are evaluated at the
+    // loop of their table, like the ON conditions of an OUTER JOIN, and not with the WHERE clause.
+    let Some(last_right_join) = table_references
+        .joined_tables()
+        .iter()
+        .rposition(|t| t.join_info.as_ref().is_some_and(|j| j.right))
+    else {
+        return Ok(());
+    };
+    for (table_idx, table) in table_references.joined_tables()[..=last_right_join]
+        .iter()
+        .enumerate()
+    {
+        let is_right = table.join_info.as_ref().is_some_and(|j| j.right);
+        if is_right && !matches!(table.table, Table::BTree(_)) {
+            crate::bail_parse_error!("RIGHT JOIN of a subquery or virtual table is not supported");
+        }
+        if table.table.virtual_table().is_some() {
+            crate::bail_parse_error!("RIGHT JOIN after a virtual table is not supported");
+        }
+        if table_idx == 0 {
+            continue;
+        }
+        let start = join_conditions_start[table_idx - 1];
+        let end = join_conditions_start
+            .get(table_idx)
+            .copied()
+            .unwrap_or(out_where_clause.len());
+        for term in &mut out_where_clause[start..end] {
+            term.from_outer_join.get_or_insert(table.internal_id);
+        }
+    }
+
     Ok(())
 }
 
+/// Plans one SELECT of a CTE; its rows are yielded to the query using the CTE.
+fn plan_cte_select(
+    schema: &Schema,
+    select: ast::Select,
+    syms: &SymbolTable,
+    outer_query_refs: &[OuterQueryReference],
+    table_ref_counter: &mut TableRefIdCounter,
+    connection: &Arc<crate::Connection>,
+) -> Result<SelectPlan> {
+    let plan = prepare_select_plan(
+        schema,
+        select,
+        syms,
+        outer_query_refs,
+        table_ref_counter,
+        QueryDestination::CoroutineYield {
+            yield_reg: usize::MAX, // will be set later in bytecode emission
+            coroutine_implementation_start: BranchOffset::Placeholder, // will be set later in bytecode emission
+        },
+        connection,
+    )?;
+    let Plan::Select(plan) = plan else {
+        crate::bail_parse_error!("Only SELECT queries are currently supported in CTEs");
+    };
+    Ok(plan)
+}
+
+/// Renames the columns of a CTE to the names listed after its name, as in `WITH t(a, b) AS (...)`.
+fn apply_cte_column_names(columns: &mut [Column], cte_name: &str, names: &[String]) -> Result<()> {
+    if names.is_empty() {
+        return Ok(());
+    }
+    if names.len() != columns.len() {
+        crate::bail_parse_error!(
+            "table {} has {} values for {} columns",
+            cte_name,
+            columns.len(),
+            names.len()
+        );
+    }
+    for (column, name) in columns.iter_mut().zip(names) {
+        column.name = Some(name.clone());
+    }
+    Ok(())
+}
+
+/// Whether `select` reads from the table `name` in its FROM clause.
+fn one_select_references_table(select: &ast::OneSelect, name: &str) -> bool {
+    let ast::OneSelect::Select {
+        from: Some(from), ..
+    } = select
+    else {
+        return false;
+    };
+    std::iter::once(from.select.as_ref())
+        .chain(from.joins.iter().map(|join| join.table.as_ref()))
+        .any(|table| {
+            matches!(table, ast::SelectTable::Table(qualified_name, ..)
+                if qualified_name.db_name.is_none()
+                    && normalize_ident(qualified_name.name.as_str()) == name)
+        })
+}
+
+/// Whether the CTE `name` defined by `select` refers to itself, making it a recursive CTE.
+fn is_recursive_cte(select: &ast::Select, name: &str) -> bool {
+    std::iter::once(&select.body.select)
+        .chain(select.body.compounds.iter().map(|c| &c.select))
+        .any(|select| one_select_references_table(select, name))
+}
+
+/// Plans a recursive CTE: a compound SELECT whose first SELECTs do not refer to the CTE and
+/// provide the initial rows, and whose remaining SELECTs refer to the CTE in their FROM clause
+/// and compute new rows from every row produced so far. See [RecursiveCte].
+#[allow(clippy::too_many_arguments)]
+fn plan_recursive_cte(
+    schema: &Schema,
+    select: ast::Select,
+    cte_name: String,
+    column_names: &[String],
+    syms: &SymbolTable,
+    outer_query_refs: &[OuterQueryReference],
+    table_ref_counter: &mut TableRefIdCounter,
+    connection: &Arc<crate::Connection>,
+) -> Result<JoinedTable> {
+    let ast::Select {
+        with,
+        body,
+        order_by,
+        limit,
+    } = select;
+    if with.is_some() {
+        crate::bail_parse_error!("WITH is not supported in recursive CTEs yet");
+    }
+    if !order_by.is_empty() {
+        crate::bail_parse_error!("ORDER BY is not supported in recursive CTEs yet");
+    }
+    let (limit, offset) = limit.as_ref().map_or(Ok((None, None)), parse_limit)?;
+
+    let mut initial: Vec<SelectPlan> = vec![];
+    let mut recursive: Vec<SelectPlan> = vec![];
+    let mut distinct = false;
+    let mut self_reference: Option<OuterQueryReference> = None;
+    let selects = std::iter::once((None, body.select)).chain(
+        body.compounds
+            .into_iter()
+            .map(|compound| (Some(compound.operator), compound.select)),
+    );
+    for (operator, one_select) in selects {
+        if let Some(operator) = operator {
+            if !matches!(
+                operator,
+                ast::CompoundOperator::Union | ast::CompoundOperator::UnionAll
+            ) {
+                crate::bail_parse_error!(
+                    "recursive CTE {} may only combine SELECTs with UNION or UNION ALL",
+                    cte_name
+                );
+            }
+        }
+        let is_recursive = one_select_references_table(&one_select, &cte_name);
+        let select = ast::Select {
+            with: None,
+            body: ast::SelectBody {
+                select: one_select,
+                compounds: vec![],
+            },
+            order_by: vec![],
+            limit: None,
+        };
+        if !is_recursive {
+            if !recursive.is_empty() {
+                crate::bail_parse_error!(
+                    "recursive CTE {}: the non-recursive SELECTs must come before the recursive ones",
+                    cte_name
+                );
+            }
+            initial.push(plan_cte_select(
+                schema,
+                select,
+                syms,
+                outer_query_refs,
+                table_ref_counter,
+                connection,
+            )?);
+            continue;
+        }
+
+        if recursive.is_empty() {
+            // The operator between the initial and the recursive SELECTs decides whether
+            // duplicate rows are discarded.
+            distinct = operator == Some(ast::CompoundOperator::Union);
+        }
+        if self_reference.is_none() {
+            let Some(first) = initial.first() else {
+                crate::bail_parse_error!("circular reference: {}", cte_name);
+            };
+            let mut table = JoinedTable::new_subquery(
+                cte_name.clone(),
+                first.clone(),
+                None,
+                table_ref_counter.next(),
+            );
+            if let Table::FromClauseSubquery(subquery) = &mut table.table {
+                subquery.is_recursive_reference = true;
+                apply_cte_column_names(&mut subquery.columns, &cte_name, column_names)?;
+            }
+            self_reference = Some(OuterQueryReference {
+                identifier: table.identifier,
+                internal_id: table.internal_id,
+                table: table.table,
+                col_used_mask: ColumnUsedMask::default(),
+            });
+        }
+        let mut outer_query_refs = outer_query_refs.to_vec();
+        outer_query_refs.extend(self_reference.clone());
+        let plan = plan_cte_select(
+            schema,
+            select,
+            syms,
+            &outer_query_refs,
+            table_ref_counter,
+            connection,
+        )?;
+        if !plan.aggregates.is_empty() || plan.group_by.is_some() {
+            crate::bail_parse_error!("recursive aggregate queries not supported");
+        }
+        recursive.push(plan);
+    }
+
+    let num_columns = initial[0].result_columns.len();
+    if initial
+        .iter()
+        .chain(recursive.iter())
+        .any(|plan| plan.result_columns.len() != num_columns)
+    {
+        crate::bail_parse_error!(
+            "SELECTs to the left and right of UNION do not have the same number of result columns"
+        );
+    }
+
+    let mut table =
+        JoinedTable::new_subquery(cte_name, initial[0].clone(), None, table_ref_counter.next());
+    if let Table::FromClauseSubquery(subquery) = &mut table.table {
+        apply_cte_column_names(&mut subquery.columns, &subquery.name, column_names)?;
+        subquery.recursive = Some(Box::new(RecursiveCte {
+            initial,
+            recursive,
+            distinct,
+            limit: limit.filter(|limit| *limit >= 0),
+            offset,
+        }));
+    }
+    Ok(table)
+}
+
 pub fn parse_where(
     where_clause: Option<&Expr>,
     table_references: &mut TableReferences,
@@ -752,7 +1075,15 @@ pub fn determine_where_to_eval_term(
         ));
     }
 
-    determine_where_to_eval_expr(&term.expr, join_order)
+    let eval_at = determine_where_to_eval_expr(&term.expr, join_order)?;
+    // A WHERE term that references the tables to the left of a RIGHT JOIN filters the joined rows,
+    // so it is evaluated only after the rows of the right table have been matched.
+    if let Some(right_join_idx) = join_order.iter().rposition(|t| t.is_right) {
+        if matches!(eval_at, EvalAt::Loop(idx) if idx < right_join_idx) {
+            return Ok(EvalAt::Loop(right_join_idx));
+        }
+    }
+    Ok(eval_at)
 }
 
 /// A bitmask representing a set of tables in a query plan.
@@ -935,13 +1266,14 @@ fn parse_join(
         connection,
     )?;
 
-    let (outer, natural) = match join_operator {
+    let (outer, right, natural) = match join_operator {
         ast::JoinOperator::TypedJoin(Some(join_type)) => {
-            let is_outer = join_type.contains(JoinType::OUTER);
+            let is_outer = join_type.contains(JoinType::LEFT);
+            let is_right = join_type.contains(JoinType::RIGHT);
             let is_natural = join_type.contains(JoinType::NATURAL);
-            (is_outer, is_natural)
+            (is_outer, is_right, is_natural)
         }
-        _ => (false, false),
+        _ => (false, false, false),
     };
 
     if natural && constraint.is_some() {
@@ -998,7 +1330,7 @@ fn parse_join(
                 for pred in preds {
                     out_where_clause.push(WhereTerm {
                         expr: pred,
-                        from_outer_join: if outer {
+                        from_outer_join: if outer || right {
                             Some(table_references.joined_tables().last().unwrap().internal_id)
                         } else {
                             None
@@ -1079,7 +1411,7 @@ fn parse_join(
                     right_table.mark_column_used(right_col_idx);
                     out_where_clause.push(WhereTerm {
                         expr,
-                        from_outer_join: if outer {
+                        from_outer_join: if outer || right {
                             Some(right_table.internal_id)
                         } else {
                             None
@@ -1098,7 +1430,11 @@ fn parse_join(
         .joined_tables_mut()
         .get_mut(last_idx)
         .unwrap();
-    rightmost_table.join_info = Some(JoinInfo { outer, using });
+    rightmost_table.join_info = Some(JoinInfo {
+        outer,
+        right,
+        using,
+    });
 
     Ok(())
 }
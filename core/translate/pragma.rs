@@ -2,24 +2,28 @@
 //! More info: https://www.sqlite.org/pragma.html.
 
 use chrono::Datelike;
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::Arc;
 use turso_macros::match_ignore_ascii_case;
-use turso_parser::ast::{self, ColumnDefinition, Expr, Literal, Name};
+use turso_parser::ast::{self, ColumnDefinition, Expr, Literal};
 use turso_parser::ast::{PragmaName, QualifiedName};
 
 use super::integrity_check::translate_integrity_check;
 use crate::pragma::pragma_for;
-use crate::schema::Schema;
+use crate::schema::{BTreeTable, Schema};
 use crate::storage::encryption::{CipherMode, EncryptionKey};
 use crate::storage::pager::AutoVacuumMode;
 use crate::storage::pager::Pager;
 use crate::storage::sqlite3_ondisk::CacheSize;
 use crate::storage::wal::CheckpointMode;
 use crate::translate::emitter::TransactionMode;
+use crate::translate::foreign_key::{emit_parent_lookup, resolve_foreign_key};
 use crate::translate::schema::translate_create_table;
-use crate::util::{normalize_ident, parse_signed_number, parse_string, IOExt as _};
-use crate::vdbe::builder::{ProgramBuilder, ProgramBuilderOpts};
+use crate::util::{
+    normalize_ident, parse_pragma_bool, parse_signed_number, parse_string, IOExt as _,
+};
+use crate::vdbe::builder::{CursorType, ProgramBuilder, ProgramBuilderOpts};
 use crate::vdbe::insn::{Cookie, Insn};
 use crate::{bail_parse_error, CaptureDataChangesMode, LimboError, SymbolTable, Value};
 use std::str::FromStr;
@@ -63,7 +67,7 @@ pub fn translate_pragma(
     let (mut program, mode) = match body {
         None => query_pragma(pragma, schema, None, pager, connection, program)?,
         Some(ast::PragmaBody::Equals(value) | ast::PragmaBody::Call(value)) => match pragma {
-            PragmaName::TableInfo => {
+            PragmaName::TableInfo | PragmaName::ForeignKeyList | PragmaName::ForeignKeyCheck => {
                 query_pragma(pragma, schema, Some(*value), pager, connection, program)?
             }
             _ => update_pragma(pragma, schema, syms, *value, pager, connection, program)?,
@@ -148,6 +152,12 @@ fn update_pragma(
             program,
         ),
         PragmaName::ModuleList => Ok((program, TransactionMode::None)),
+        PragmaName::MvccGc
+        | PragmaName::MvccGcMemoryThreshold
+        | PragmaName::MvccGcVersionThreshold => {
+            query_pragma(pragma, schema, Some(value), pager, connection, program)
+        }
+        PragmaName::MvccStats => unreachable!("mvcc_stats cannot be set"),
         PragmaName::PageCount => query_pragma(
             PragmaName::PageCount,
             schema,
@@ -196,7 +206,7 @@ fn update_pragma(
             program.emit_insn(Insn::Noop {});
             Ok((program, TransactionMode::None))
         }
-        PragmaName::TableInfo => {
+        PragmaName::TableInfo | PragmaName::ForeignKeyList | PragmaName::ForeignKeyCheck => {
             // because we need control over the write parameter for the transaction,
             // this should be unreachable. We have to force-call query_pragma before
             // getting here
@@ -288,7 +298,6 @@ fn update_pragma(
                         options: ast::TableOptions::NONE,
                     },
                     true,
-                    schema,
                     syms,
                     &connection,
                     program,
@@ -298,14 +307,9 @@ fn update_pragma(
             Ok((program, TransactionMode::Write))
         }
         PragmaName::DatabaseList => unreachable!("database_list cannot be set"),
-        PragmaName::QueryOnly => query_pragma(
-            PragmaName::QueryOnly,
-            schema,
-            Some(value),
-            pager,
-            connection,
-            program,
-        ),
+        PragmaName::QueryOnly | PragmaName::ForeignKeys | PragmaName::DeferForeignKeys => {
+            query_pragma(pragma, schema, Some(value), pager, connection, program)
+        }
         PragmaName::FreelistCount => query_pragma(
             PragmaName::FreelistCount,
             schema,
@@ -347,6 +351,41 @@ fn update_pragma(
             connection.set_sync_mode(mode);
             Ok((program, TransactionMode::None))
         }
+        PragmaName::TempStore => {
+            use crate::TempStore;
+
+            let value = match value {
+                Expr::Name(name) => name.as_str().to_string(),
+                Expr::Literal(Literal::Numeric(n)) => n,
+                _ => bail_parse_error!("Invalid value for temp_store pragma"),
+            };
+            let temp_store = match_ignore_ascii_case!(match value.as_bytes() {
+                b"1" | b"FILE" => TempStore::File,
+                b"2" | b"MEMORY" => TempStore::Memory,
+                _ => TempStore::Default,
+            });
+            connection.set_temp_store(temp_store)?;
+            Ok((program, TransactionMode::None))
+        }
+        PragmaName::IsolationLevel => {
+            use crate::mvcc::IsolationLevel;
+
+            // A quoted value is a string, a bare one a name.
+            let value = match parse_string(&value) {
+                Ok(value) => value,
+                Err(_) => match value {
+                    Expr::Name(name) => name.as_str().to_string(),
+                    _ => bail_parse_error!("Invalid value for isolation_level pragma"),
+                },
+            };
+            let isolation_level = match_ignore_ascii_case!(match value.as_bytes() {
+                b"SNAPSHOT" => IsolationLevel::Snapshot,
+                b"SERIALIZABLE" => IsolationLevel::Serializable,
+                _ => bail_parse_error!("Invalid value for isolation_level pragma: {}", value),
+            });
+            connection.set_isolation_level(isolation_level);
+            Ok((program, TransactionMode::None))
+        }
     }
 }
 
@@ -458,6 +497,74 @@ fn query_pragma(
             program.add_pragma_result_column(pragma.to_string());
             Ok((program, TransactionMode::None))
         }
+        PragmaName::MvccGc
+        | PragmaName::MvccGcMemoryThreshold
+        | PragmaName::MvccGcVersionThreshold => {
+            // The garbage collection policy is the one of the database, not of the connection.
+            let Some(mv_store) = connection._db.get_mv_store() else {
+                return Ok((program, TransactionMode::None));
+            };
+            let mut policy = mv_store.gc_policy();
+            if let Some(value_expr) = value {
+                if pragma == PragmaName::MvccGc {
+                    policy.enabled = parse_pragma_bool(&value_expr)?;
+                } else {
+                    let threshold = match parse_signed_number(&value_expr)? {
+                        Value::Integer(threshold) if threshold > 0 => threshold as u64,
+                        _ => bail_parse_error!("{pragma} must be a positive integer"),
+                    };
+                    if pragma == PragmaName::MvccGcMemoryThreshold {
+                        policy.memory_threshold = threshold;
+                    } else {
+                        policy.version_threshold = threshold;
+                    }
+                }
+                mv_store.set_gc_policy(policy);
+                return Ok((program, TransactionMode::None));
+            }
+            let current = match pragma {
+                PragmaName::MvccGc => policy.enabled as u64,
+                PragmaName::MvccGcMemoryThreshold => policy.memory_threshold,
+                _ => policy.version_threshold,
+            };
+            program.emit_int(current.min(i64::MAX as u64) as i64, register);
+            program.emit_result_row(register, 1);
+            program.add_pragma_result_column(pragma.to_string());
+            Ok((program, TransactionMode::None))
+        }
+        PragmaName::MvccStats => {
+            let base_reg = register;
+            program.alloc_registers(4);
+            if let Some(mv_store) = connection._db.get_mv_store() {
+                // Stats are reported by the name of the table or index with the root page.
+                let mut names = HashMap::new();
+                for table in schema.tables.values() {
+                    if let Some(table) = table.btree() {
+                        names.insert(table.root_page as u64, table.name.clone());
+                    }
+                }
+                for index in schema.indexes.values().flatten() {
+                    names.insert(index.root_page as u64, index.name.clone());
+                }
+                for (id, stats) in mv_store.version_stats() {
+                    let Some(name) = names.remove(&id) else {
+                        continue;
+                    };
+                    program.emit_string8(name, base_reg);
+                    program.emit_int(stats.rows as i64, base_reg + 1);
+                    program.emit_int(stats.versions as i64, base_reg + 2);
+                    program.emit_int(stats.max_chain_length as i64, base_reg + 3);
+                    program.emit_int(stats.collected as i64, base_reg + 4);
+                    program.emit_result_row(base_reg, 5);
+                }
+            }
+
+            let pragma = pragma_for(&pragma);
+            for col_name in pragma.columns.iter() {
+                program.add_pragma_result_column(col_name.to_string());
+            }
+            Ok((program, TransactionMode::None))
+        }
         PragmaName::PageCount => {
             program.emit_insn(Insn::PageCount {
                 db: 0,
@@ -497,7 +604,7 @@ fn query_pragma(
             }
             let col_names = ["cid", "name", "type", "notnull", "dflt_value", "pk"];
             for name in col_names {
-                program.add_pragma_result_column(name.into());
+                program.add_pragma_result_column(name.to_string());
             }
             Ok((program, TransactionMode::None))
         }
@@ -571,22 +678,7 @@ fn query_pragma(
         }
         PragmaName::QueryOnly => {
             if let Some(value_expr) = value {
-                let is_query_only = match value_expr {
-                    ast::Expr::Literal(Literal::Numeric(i)) => i.parse::<i64>().unwrap() != 0,
-                    ast::Expr::Literal(Literal::String(ref s))
-                    | ast::Expr::Name(Name::Ident(ref s)) => {
-                        let s = s.as_bytes();
-                        match_ignore_ascii_case!(match s {
-                            b"1" | b"on" | b"true" => true,
-                            _ => false,
-                        })
-                    }
-                    _ => {
-                        return Err(LimboError::ParseError(format!(
-                            "Invalid value for PRAGMA query_only: {value_expr:?}"
-                        )));
-                    }
-                };
+                let is_query_only = parse_pragma_bool(&value_expr)?;
                 connection.set_query_only(is_query_only);
                 return Ok((program, TransactionMode::None));
             };
@@ -638,9 +730,179 @@ fn query_pragma(
             program.add_pragma_result_column(pragma.to_string());
             Ok((program, TransactionMode::None))
         }
+        PragmaName::IsolationLevel => {
+            use crate::mvcc::IsolationLevel;
+
+            let isolation_level = match connection.get_isolation_level() {
+                IsolationLevel::Snapshot => "snapshot",
+                IsolationLevel::Serializable => "serializable",
+            };
+            program.emit_string8(isolation_level.to_string(), register);
+            program.emit_result_row(register, 1);
+            program.add_pragma_result_column(pragma.to_string());
+            Ok((program, TransactionMode::None))
+        }
+        PragmaName::TempStore => {
+            program.emit_int(connection.get_temp_store() as i64, register);
+            program.emit_result_row(register, 1);
+            program.add_pragma_result_column(pragma.to_string());
+            Ok((program, TransactionMode::None))
+        }
+        PragmaName::ForeignKeys => {
+            if let Some(value_expr) = value {
+                let enabled = parse_pragma_bool(&value_expr)?;
+                // Like SQLite, changing foreign key enforcement within a transaction is a no-op
+                if connection.get_auto_commit() {
+                    connection.set_foreign_keys_enabled(enabled);
+                }
+                return Ok((program, TransactionMode::None));
+            }
+            program.emit_int(connection.foreign_keys_enabled() as i64, register);
+            program.emit_result_row(register, 1);
+            program.add_pragma_result_column(pragma.to_string());
+            Ok((program, TransactionMode::None))
+        }
+        PragmaName::DeferForeignKeys => {
+            if let Some(value_expr) = value {
+                let defer = parse_pragma_bool(&value_expr)?;
+                connection.set_defer_foreign_keys(defer);
+                return Ok((program, TransactionMode::None));
+            }
+            program.emit_int(connection.get_defer_foreign_keys() as i64, register);
+            program.emit_result_row(register, 1);
+            program.add_pragma_result_column(pragma.to_string());
+            Ok((program, TransactionMode::None))
+        }
+        PragmaName::ForeignKeyList => {
+            let name = match value {
+                Some(ast::Expr::Name(name)) => Some(normalize_ident(name.as_str())),
+                _ => None,
+            };
+            if let Some(table) = name.and_then(|name| schema.get_btree_table(&name)) {
+                emit_foreign_key_list(&mut program, &table);
+            }
+            let pragma = pragma_for(&pragma);
+            for name in pragma.columns {
+                program.add_pragma_result_column(name.to_string());
+            }
+            Ok((program, TransactionMode::None))
+        }
+        PragmaName::ForeignKeyCheck => {
+            let tables = match value {
+                Some(ast::Expr::Name(name)) => {
+                    let name = normalize_ident(name.as_str());
+                    let Some(table) = schema.get_btree_table(&name) else {
+                        bail_parse_error!("no such table: {name}");
+                    };
+                    vec![table]
+                }
+                _ => {
+                    let mut tables = schema
+                        .tables
+                        .values()
+                        .filter_map(|table| table.btree())
+                        .filter(|table| !table.foreign_keys.is_empty())
+                        .collect::<Vec<_>>();
+                    tables.sort_by(|a, b| a.name.cmp(&b.name));
+                    tables
+                }
+            };
+            for table in tables {
+                emit_foreign_key_check(&mut program, schema, &table)?;
+            }
+            let pragma = pragma_for(&pragma);
+            for name in pragma.columns {
+                program.add_pragma_result_column(name.to_string());
+            }
+            Ok((program, TransactionMode::Read))
+        }
     }
 }
 
+/// Emits a row for every column of every foreign key of `table`, for PRAGMA foreign_key_list.
+/// Like SQLite, the last declared foreign key gets id 0.
+fn emit_foreign_key_list(program: &mut ProgramBuilder, table: &BTreeTable) {
+    let base_reg = program.alloc_registers(8);
+    for (id, foreign_key) in table.foreign_keys.iter().rev().enumerate() {
+        for (seq, from) in foreign_key.child_columns.iter().enumerate() {
+            program.emit_int(id as i64, base_reg);
+            program.emit_int(seq as i64, base_reg + 1);
+            program.emit_string8(foreign_key.parent_table.clone(), base_reg + 2);
+            program.emit_string8(from.clone(), base_reg + 3);
+            match foreign_key.parent_columns.get(seq) {
+                Some(to) => program.emit_string8(to.clone(), base_reg + 4),
+                None => program.emit_null(base_reg + 4, None),
+            }
+            program.emit_string8(foreign_key.on_update.to_string(), base_reg + 5);
+            program.emit_string8(foreign_key.on_delete.to_string(), base_reg + 6);
+            program.emit_string8("NONE".to_string(), base_reg + 7);
+            program.emit_result_row(base_reg, 8);
+        }
+    }
+}
+
+/// Emits a row for every row of `table` whose parent row does not exist, for
+/// PRAGMA foreign_key_check.
+fn emit_foreign_key_check(
+    program: &mut ProgramBuilder,
+    schema: &Schema,
+    table: &Arc<BTreeTable>,
+) -> crate::Result<()> {
+    let foreign_keys = (0..table.foreign_keys.len())
+        .map(|position| resolve_foreign_key(schema, table, position))
+        .collect::<crate::Result<Vec<_>>>()?;
+    let cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
+    program.emit_insn(Insn::OpenRead {
+        cursor_id,
+        root_page: table.root_page,
+        db: 0,
+    });
+    let loop_start = program.allocate_label();
+    let loop_end = program.allocate_label();
+    program.emit_insn(Insn::Rewind {
+        cursor_id,
+        pc_if_empty: loop_end,
+    });
+    program.preassign_label_to_next_insn(loop_start);
+    let result_reg = program.alloc_registers(4);
+    // Foreign keys are numbered like PRAGMA foreign_key_list does it, last declared first
+    for foreign_key in foreign_keys.iter().rev() {
+        let ok_label = program.allocate_label();
+        let key_regs = foreign_key
+            .child_columns
+            .iter()
+            .map(|&idx| {
+                let reg = program.alloc_register();
+                program.emit_column_or_rowid(cursor_id, idx, reg);
+                program.emit_insn(Insn::IsNull {
+                    reg,
+                    target_pc: ok_label,
+                });
+                reg
+            })
+            .collect::<Vec<_>>();
+        emit_parent_lookup(program, foreign_key, &key_regs, ok_label);
+        program.emit_string8(table.name.clone(), result_reg);
+        program.emit_insn(Insn::RowId {
+            cursor_id,
+            dest: result_reg + 1,
+        });
+        program.emit_string8(foreign_key.foreign_key.parent_table.clone(), result_reg + 2);
+        program.emit_int(
+            (foreign_keys.len() - 1 - foreign_key.position) as i64,
+            result_reg + 3,
+        );
+        program.emit_result_row(result_reg, 4);
+        program.preassign_label_to_next_insn(ok_label);
+    }
+    program.emit_insn(Insn::Next {
+        cursor_id,
+        pc_if_next: loop_start,
+    });
+    program.preassign_label_to_next_insn(loop_end);
+    Ok(())
+}
+
 /// Helper function to emit column information for PRAGMA table_info
 /// Used by both tables and views since they now have the same column emission logic
 fn emit_columns_for_table_info(
@@ -650,8 +912,13 @@ fn emit_columns_for_table_info(
 ) {
     // According to the SQLite documentation: "The 'cid' column should not be taken to
     // mean more than 'rank within the current result set'."
-    // Therefore, we enumerate only after filtering out hidden columns.
-    for (i, column) in columns.iter().filter(|col| !col.hidden).enumerate() {
+    // Therefore, we enumerate only after filtering out hidden columns. Like in SQLite,
+    // generated columns are hidden from table_info too.
+    for (i, column) in columns
+        .iter()
+        .filter(|col| !col.hidden && col.generated.is_none())
+        .enumerate()
+    {
         // cid
         program.emit_int(i as i64, base_reg);
         // name
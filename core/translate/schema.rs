@@ -5,17 +5,20 @@ use std::sync::Arc;
 
 use crate::ast;
 use crate::ext::VTabImpl;
+use crate::incremental::persistence::DBSP_STATE_TABLE_PREFIX;
 use crate::schema::BTreeTable;
 use crate::schema::Column;
 use crate::schema::Schema;
 use crate::schema::Table;
 use crate::schema::Type;
+use crate::schema::TEMP_SCHEMA_TABLE_NAME;
 use crate::storage::pager::CreateBTreeFlags;
 use crate::translate::emitter::emit_cdc_full_record;
 use crate::translate::emitter::emit_cdc_insns;
 use crate::translate::emitter::prepare_cdc_if_necessary;
 use crate::translate::emitter::OperationMode;
 use crate::translate::emitter::Resolver;
+use crate::translate::foreign_key::emit_drop_table_delete;
 use crate::translate::ProgramBuilder;
 use crate::translate::ProgramBuilderOpts;
 use crate::util::normalize_ident;
@@ -35,14 +38,22 @@ pub fn translate_create_table(
     temporary: bool,
     body: ast::CreateTableBody,
     if_not_exists: bool,
-    schema: &Schema,
     syms: &SymbolTable,
     connection: &Arc<crate::Connection>,
     mut program: ProgramBuilder,
 ) -> Result<ProgramBuilder> {
-    if temporary {
-        bail_parse_error!("TEMPORARY table not supported yet");
+    let temporary = is_temp_database_object(temporary, &tbl_name, "table")?;
+    if temporary && connection._db.mv_store.is_some() {
+        bail_parse_error!("TEMPORARY tables are not supported with MVCC");
     }
+    let db = if temporary {
+        1
+    } else {
+        resolve_schema_target(connection, &tbl_name)?.0
+    };
+    // The name only has to be unique in the database the table goes into: a TEMP table may
+    // have the name of a table of the main database.
+    let schema = &connection.database_schema(db);
 
     // Check for STRICT mode without experimental flag
     if let ast::CreateTableBody::ColumnsAndConstraints { options, .. } = &body {
@@ -60,7 +71,9 @@ pub fn translate_create_table(
     };
     program.extend(&opts);
     let normalized_tbl_name = normalize_ident(tbl_name.name.as_str());
-    if schema.get_table(&normalized_tbl_name).is_some() {
+    if schema.get_table(&normalized_tbl_name).is_some()
+        || schema.is_table_unresolved(&normalized_tbl_name)
+    {
         if if_not_exists {
             return Ok(program);
         }
@@ -68,6 +81,8 @@ pub fn translate_create_table(
     }
 
     let sql = create_table_body_to_str(&tbl_name, &body);
+    // Reject invalid column definitions, like generated column loops, before anything is written.
+    BTreeTable::from_sql(&sql, 0, syms)?;
 
     let parse_schema_label = program.allocate_label();
     // TODO: ReadCookie
@@ -75,12 +90,24 @@ pub fn translate_create_table(
     // TODO: SetCookie
     // TODO: SetCookie
 
-    // Create the table B-tree
+    // Create the table B-tree. A WITHOUT ROWID table is stored in an index B-tree.
     let table_root_reg = program.alloc_register();
+    let without_rowid = matches!(
+        &body,
+        ast::CreateTableBody::ColumnsAndConstraints { options, .. }
+            if options.contains(ast::TableOptions::WITHOUT_ROWID)
+    );
+    if without_rowid && connection._db.mv_store.is_some() {
+        bail_parse_error!("WITHOUT ROWID tables are not supported with MVCC");
+    }
     program.emit_insn(Insn::CreateBtree {
-        db: 0,
+        db,
         root: table_root_reg,
-        flags: CreateBTreeFlags::new_table(),
+        flags: if without_rowid {
+            CreateBTreeFlags::new_index()
+        } else {
+            CreateBTreeFlags::new_table()
+        },
     });
 
     // Create an automatic index B-tree if needed
@@ -115,22 +142,20 @@ pub fn translate_create_table(
         }
         for index_reg in index_regs.clone() {
             program.emit_insn(Insn::CreateBtree {
-                db: 0,
+                db,
                 root: index_reg,
                 flags: CreateBTreeFlags::new_index(),
             });
         }
     }
 
-    let table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
-    let sqlite_schema_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
-    program.emit_insn(Insn::OpenWrite {
-        cursor_id: sqlite_schema_cursor_id,
-        root_page: 1usize.into(),
-        db: 0,
-    });
+    let sqlite_schema_cursor_id = open_schema_table(&mut program, schema, db);
 
-    let cdc_table = prepare_cdc_if_necessary(&mut program, schema, SQLITE_TABLEID)?;
+    let cdc_table = if db != 0 {
+        None
+    } else {
+        prepare_cdc_if_necessary(&mut program, schema, SQLITE_TABLEID)?
+    };
     let resolver = Resolver::new(schema, syms);
     // Add the table entry to sqlite_schema
     emit_schema_entry(
@@ -147,12 +172,18 @@ pub fn translate_create_table(
 
     // If we need an automatic index, add its entry to sqlite_schema
     if let Some(index_regs) = index_regs {
+        // The primary key of a WITHOUT ROWID table has no entry, but still takes up a number.
+        let primary_key_number = without_rowid_primary_key_number(&body);
         for (idx, index_reg) in index_regs.into_iter().enumerate() {
+            let number = match primary_key_number {
+                Some(n) if idx + 1 >= n => idx + 2,
+                _ => idx + 1,
+            };
             let index_name = format!(
                 "{}{}_{}",
                 PRIMARY_KEY_AUTOMATIC_INDEX_NAME_PREFIX,
                 tbl_name.name.as_str(),
-                idx + 1
+                number
             );
             emit_schema_entry(
                 &mut program,
@@ -169,18 +200,20 @@ pub fn translate_create_table(
     }
 
     program.resolve_label(parse_schema_label, program.offset());
-    // TODO: SetCookie
-    program.emit_insn(Insn::SetCookie {
-        db: 0,
-        cookie: Cookie::SchemaVersion,
-        value: schema.schema_version as i32 + 1,
-        p5: 0,
-    });
+    // The schema cookie of the main database does not cover the temp database.
+    if !temporary {
+        program.emit_insn(Insn::SetCookie {
+            db,
+            cookie: Cookie::SchemaVersion,
+            value: schema.schema_version as i32 + 1,
+            p5: 0,
+        });
+    }
     // TODO: remove format, it sucks for performance but is convenient
     let parse_schema_where_clause =
         format!("tbl_name = '{normalized_tbl_name}' AND type != 'trigger'");
     program.emit_insn(Insn::ParseSchema {
-        db: sqlite_schema_cursor_id,
+        db,
         where_clause: Some(parse_schema_where_clause),
     });
 
@@ -194,6 +227,7 @@ pub enum SchemaEntryType {
     Table,
     Index,
     View,
+    Trigger,
 }
 
 impl SchemaEntryType {
@@ -202,11 +236,69 @@ impl SchemaEntryType {
             SchemaEntryType::Table => "table",
             SchemaEntryType::Index => "index",
             SchemaEntryType::View => "view",
+            SchemaEntryType::Trigger => "trigger",
         }
     }
 }
 pub const SQLITE_TABLEID: &str = "sqlite_schema";
 
+/// Returns whether an object created with `CREATE [TEMP] <kind> name` goes into the `temp`
+/// database. Like in SQLite, `CREATE TABLE temp.t` creates a TEMP table too.
+pub(crate) fn is_temp_database_object(
+    temporary: bool,
+    name: &ast::QualifiedName,
+    kind: &str,
+) -> Result<bool> {
+    let Some(db_name) = &name.db_name else {
+        return Ok(temporary);
+    };
+    if normalize_ident(db_name.as_str()) == "temp" {
+        return Ok(true);
+    }
+    if temporary {
+        bail_parse_error!("temporary {kind} name must be unqualified");
+    }
+    Ok(false)
+}
+
+/// Returns the database that a schema object named `name` lives in, with the schema of that
+/// database if the name is qualified. Unqualified names are looked up in the schema of the
+/// statement, where TEMP objects hide the main objects of the same name.
+pub(crate) fn resolve_schema_target(
+    connection: &Arc<crate::Connection>,
+    name: &ast::QualifiedName,
+) -> Result<(usize, Option<Arc<Schema>>)> {
+    if name.db_name.is_none() {
+        return Ok((0, None));
+    }
+    let db = connection.resolve_database_id(name)?;
+    if db < 2 {
+        return Ok((db, Some(connection.database_schema(db))));
+    }
+    if connection._db.mv_store.is_some() {
+        bail_parse_error!("schema changes in attached databases are not supported with MVCC");
+    }
+    Ok((db, Some(connection.database_schema(db))))
+}
+
+/// Opens a write cursor on the schema table of database `db`, which is `sqlite_temp_schema` for
+/// the `temp` database.
+pub(crate) fn open_schema_table(program: &mut ProgramBuilder, schema: &Schema, db: usize) -> usize {
+    let table_name = if db == 1 {
+        TEMP_SCHEMA_TABLE_NAME
+    } else {
+        SQLITE_TABLEID
+    };
+    let table = schema.get_btree_table(table_name).unwrap();
+    let cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table));
+    program.emit_insn(Insn::OpenWrite {
+        cursor_id,
+        root_page: 1usize.into(),
+        db,
+    });
+    cursor_id
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn emit_schema_entry(
     program: &mut ProgramBuilder,
@@ -301,6 +393,7 @@ struct PrimaryKeyColumnInfo<'a> {
 /// - The table has no PRIMARY KEY
 /// - The table has a single-column PRIMARY KEY whose typename is _exactly_ "INTEGER" e.g. not "INT".
 ///   In this case, the PRIMARY KEY column becomes an alias for the rowid.
+/// - The table is a WITHOUT ROWID table, which is itself stored in the index of its PRIMARY KEY.
 ///
 /// Otherwise, an automatic PRIMARY KEY index is required.
 fn check_automatic_pk_index_required(
@@ -444,9 +537,15 @@ fn check_automatic_pk_index_required(
                 }
             }
 
-            // Check if table has rowid
-            if options.contains(ast::TableOptions::WITHOUT_ROWID) {
-                bail_parse_error!("WITHOUT ROWID tables are not supported yet");
+            // A WITHOUT ROWID table is stored in the index of its PRIMARY KEY
+            let without_rowid = options.contains(ast::TableOptions::WITHOUT_ROWID);
+            if without_rowid {
+                if primary_key_definition.is_none() {
+                    bail_parse_error!("PRIMARY KEY missing on table {}", tbl_name);
+                }
+                if has_autoincrement(columns, constraints) {
+                    bail_parse_error!("AUTOINCREMENT not allowed on WITHOUT ROWID tables");
+                }
             }
 
             unique_sets.dedup();
@@ -476,8 +575,12 @@ fn check_automatic_pk_index_required(
                 false
             };
             let mut total_indices = unique_sets.len();
-            // if pk needs and index, but we already found out we primary key is unique, we only need a single index since constraint pk == unique
-            if auto_index_pk && !pk_is_unique {
+            if without_rowid {
+                // the table itself is the index of the primary key, which makes a UNIQUE
+                // constraint on the same columns redundant
+                total_indices -= pk_is_unique as usize;
+            } else if auto_index_pk && !pk_is_unique {
+                // if pk needs and index, but we already found out we primary key is unique, we only need a single index since constraint pk == unique
                 total_indices += 1;
             }
 
@@ -494,6 +597,96 @@ fn check_automatic_pk_index_required(
     }
 }
 
+/// Returns the number SQLite gives the automatic index of the PRIMARY KEY of a WITHOUT ROWID
+/// table, which is counted with the UNIQUE constraints in the order the constraints are declared
+/// in. A PRIMARY KEY on a single INTEGER column is only numbered after all the other constraints.
+fn without_rowid_primary_key_number(body: &ast::CreateTableBody) -> Option<usize> {
+    let ast::CreateTableBody::ColumnsAndConstraints {
+        columns,
+        constraints,
+        options,
+    } = body
+    else {
+        return None;
+    };
+    if !options.contains(ast::TableOptions::WITHOUT_ROWID) {
+        return None;
+    }
+    let is_integer = |name: &str| {
+        columns.iter().any(|column| {
+            column.col_name.as_str() == name
+                && column
+                    .col_type
+                    .as_ref()
+                    .is_some_and(|t| t.name.eq_ignore_ascii_case("INTEGER"))
+        })
+    };
+    let mut number = 0;
+    for column in columns {
+        for constraint in &column.constraints {
+            match &constraint.constraint {
+                ast::ColumnConstraint::PrimaryKey { order, .. } => {
+                    if !is_integer(column.col_name.as_str()) || *order == Some(ast::SortOrder::Desc)
+                    {
+                        return Some(number + 1);
+                    }
+                }
+                ast::ColumnConstraint::Unique(..) => number += 1,
+                _ => {}
+            }
+        }
+    }
+    for constraint in constraints {
+        match &constraint.constraint {
+            ast::TableConstraint::PrimaryKey { columns: pk, .. } => {
+                let integer_key = match pk.as_slice() {
+                    [column] => match column.expr.as_ref() {
+                        ast::Expr::Id(name) => {
+                            is_integer(name.as_str()) && column.order != Some(ast::SortOrder::Desc)
+                        }
+                        _ => false,
+                    },
+                    _ => false,
+                };
+                if !integer_key {
+                    return Some(number + 1);
+                }
+            }
+            ast::TableConstraint::Unique { .. } => number += 1,
+            _ => {}
+        }
+    }
+    Some(number + 1)
+}
+
+fn has_autoincrement(
+    columns: &[ast::ColumnDefinition],
+    constraints: &[ast::NamedTableConstraint],
+) -> bool {
+    let column_autoincrement = columns
+        .iter()
+        .flat_map(|column| &column.constraints)
+        .any(|c| {
+            matches!(
+                c.constraint,
+                ast::ColumnConstraint::PrimaryKey {
+                    auto_increment: true,
+                    ..
+                }
+            )
+        });
+    column_autoincrement
+        || constraints.iter().any(|c| {
+            matches!(
+                c.constraint,
+                ast::TableConstraint::PrimaryKey {
+                    auto_increment: true,
+                    ..
+                }
+            )
+        })
+}
+
 #[derive(Debug)]
 enum PrimaryKeyDefinitionType<'a> {
     Simple {
@@ -584,7 +777,7 @@ pub fn translate_create_virtual_table(
     if !vtab_module.module_kind.eq(&VTabKind::VirtualTable) {
         bail_parse_error!("module {} is not a virtual table", module_name_str);
     };
-    if schema.get_table(&table_name).is_some() {
+    if schema.get_table(&table_name).is_some() || schema.is_table_unresolved(&table_name) {
         if *if_not_exists {
             return Ok(program);
         }
@@ -656,7 +849,7 @@ pub fn translate_create_virtual_table(
     });
     let parse_schema_where_clause = format!("tbl_name = '{table_name}' AND type != 'trigger'");
     program.emit_insn(Insn::ParseSchema {
-        db: sqlite_schema_cursor_id,
+        db: 0,
         where_clause: Some(parse_schema_where_clause),
     });
 
@@ -668,8 +861,11 @@ pub fn translate_drop_table(
     if_exists: bool,
     schema: &Schema,
     syms: &SymbolTable,
+    connection: &Arc<crate::Connection>,
     mut program: ProgramBuilder,
 ) -> Result<ProgramBuilder> {
+    let (attached_db, attached_schema) = resolve_schema_target(connection, &tbl_name)?;
+    let schema = attached_schema.as_deref().unwrap_or(schema);
     if !schema.indexes_enabled() && schema.table_has_indexes(&tbl_name.name.to_string()) {
         bail_parse_error!(
             "DROP TABLE with indexes on the table is disabled by default. Omit the `--experimental-indexes=false` flag to enable this feature."
@@ -690,18 +886,40 @@ pub fn translate_drop_table(
     }
 
     let table = table.unwrap(); // safe since we just checked for None
-    let cdc_table = prepare_cdc_if_necessary(&mut program, schema, SQLITE_TABLEID)?;
+
+    // The state table of a materialized view goes away with the view
+    let normalized_table_name = normalize_ident(tbl_name.name.as_str());
+    if let Some(view_name) = normalized_table_name.strip_prefix(DBSP_STATE_TABLE_PREFIX) {
+        if attached_db == 0 && schema.get_materialized_view(view_name).is_some() {
+            bail_parse_error!("table {normalized_table_name} may not be dropped");
+        }
+    }
+
+    // TEMP tables are dropped from the temp database and its schema table.
+    let temporary = table.is_temporary();
+    let db = if temporary { 1 } else { attached_db };
+    let schema_table_name = if temporary {
+        TEMP_SCHEMA_TABLE_NAME
+    } else {
+        SQLITE_TABLEID
+    };
+    if let Table::BTree(btree) = table.as_ref() {
+        emit_drop_table_delete(&mut program, schema, syms, connection, btree, db)?;
+    }
+    let cdc_table = if db != 0 {
+        None
+    } else {
+        prepare_cdc_if_necessary(&mut program, schema, SQLITE_TABLEID)?
+    };
 
     let null_reg = program.alloc_register(); //  r1
     program.emit_null(null_reg, None);
     let table_name_and_root_page_register = program.alloc_register(); //  r2, this register is special because it's first used to track table name and then moved root page
     let table_reg = program.emit_string8_new_reg(tbl_name.name.as_str().to_string()); //  r3
     program.mark_last_insn_constant();
-    let table_type = program.emit_string8_new_reg("trigger".to_string()); //  r4
-    program.mark_last_insn_constant();
-    let row_id_reg = program.alloc_register(); //  r5
+    let row_id_reg = program.alloc_register(); //  r4
 
-    let schema_table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
+    let schema_table = schema.get_btree_table(schema_table_name).unwrap();
     let sqlite_schema_cursor_id_0 = program.alloc_cursor_id(
         //  cursor 0
         CursorType::BTreeTable(schema_table.clone()),
@@ -709,10 +927,10 @@ pub fn translate_drop_table(
     program.emit_insn(Insn::OpenWrite {
         cursor_id: sqlite_schema_cursor_id_0,
         root_page: 1usize.into(),
-        db: 0,
+        db,
     });
 
-    //  1. Remove all entries from the schema table related to the table we are dropping, except for triggers
+    //  1. Remove all entries from the schema table related to the table we are dropping, including its triggers
     //  loop to beginning of schema table
     let end_metadata_label = program.allocate_label();
     let metadata_loop = program.allocate_label();
@@ -736,18 +954,6 @@ pub fn translate_drop_table(
         flags: CmpInsFlags::default(),
         collation: program.curr_collation(),
     });
-    program.emit_column_or_rowid(
-        sqlite_schema_cursor_id_0,
-        0,
-        table_name_and_root_page_register,
-    );
-    program.emit_insn(Insn::Eq {
-        lhs: table_name_and_root_page_register,
-        rhs: table_type,
-        target_pc: next_label,
-        flags: CmpInsFlags::default(),
-        collation: program.curr_collation(),
-    });
     program.emit_insn(Insn::RowId {
         cursor_id: sqlite_schema_cursor_id_0,
         dest: row_id_reg,
@@ -793,7 +999,7 @@ pub fn translate_drop_table(
     }
     program.emit_insn(Insn::Delete {
         cursor_id: sqlite_schema_cursor_id_0,
-        table_name: SQLITE_TABLEID.to_string(),
+        table_name: schema_table_name.to_string(),
     });
 
     program.resolve_label(next_label, program.offset());
@@ -810,14 +1016,8 @@ pub fn translate_drop_table(
         program.emit_insn(Insn::Destroy {
             root: index.root_page,
             former_root_reg: 0, //  no autovacuum (https://www.sqlite.org/opcode.html#Destroy)
-            is_temp: 0,
+            is_temp: db,
         });
-
-        //  3. TODO: Open an ephemeral table, and read over triggers from schema table into ephemeral table
-        //  Requires support via https://github.com/tursodatabase/turso/pull/768
-
-        //  4. TODO: Open a write cursor to the schema table and re-insert all triggers into the sqlite schema table from the ephemeral table and delete old trigger
-        //  Requires support via https://github.com/tursodatabase/turso/pull/768
     }
 
     //  3. Destroy the table structure
@@ -826,7 +1026,7 @@ pub fn translate_drop_table(
             program.emit_insn(Insn::Destroy {
                 root: table.root_page,
                 former_root_reg: table_name_and_root_page_register,
-                is_temp: 0,
+                is_temp: db,
             });
         }
         Table::Virtual(vtab) => {
@@ -839,6 +1039,9 @@ pub fn translate_drop_table(
                     vtab.name
                 )));
             }
+            if db > 1 {
+                bail_parse_error!("virtual tables in attached databases are not supported yet");
+            }
             program.emit_insn(Insn::VDestroy {
                 table_name: vtab.name.clone(),
                 db: 0, // TODO change this for multiple databases
@@ -874,9 +1077,13 @@ pub fn translate_drop_table(
                 unique: false,
                 collation: None,
                 hidden: false,
+                generated: None,
             }],
             is_strict: false,
             unique_sets: None,
+            foreign_keys: vec![],
+            check_constraints: vec![],
+            temporary: false,
         });
         //  cursor id 2
         let ephemeral_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(simple_table_rc));
@@ -893,7 +1100,7 @@ pub fn translate_drop_table(
         program.emit_insn(Insn::OpenRead {
             cursor_id: sqlite_schema_cursor_id_1,
             root_page: 1usize,
-            db: 0,
+            db,
         });
 
         let schema_column_0_register = program.alloc_register();
@@ -950,7 +1157,7 @@ pub fn translate_drop_table(
         program.emit_insn(Insn::OpenWrite {
             cursor_id: sqlite_schema_cursor_id_1,
             root_page: 1usize.into(),
-            db: 0,
+            db,
         });
 
         //  Loop to copy over row id's from the ephemeral table and then re-insert into the schema table with the correct root page
@@ -993,14 +1200,14 @@ pub fn translate_drop_table(
         });
         program.emit_insn(Insn::Delete {
             cursor_id: sqlite_schema_cursor_id_1,
-            table_name: SQLITE_TABLEID.to_string(),
+            table_name: schema_table_name.to_string(),
         });
         program.emit_insn(Insn::Insert {
             cursor: sqlite_schema_cursor_id_1,
             key_reg: schema_row_id_register,
             record_reg: new_record_register,
             flag: InsertFlags::new(),
-            table_name: SQLITE_TABLEID.to_string(),
+            table_name: schema_table_name.to_string(),
         });
 
         program.resolve_label(next_label, program.offset());
@@ -1014,18 +1221,20 @@ pub fn translate_drop_table(
 
     //  Drop the in-memory structures for the table
     program.emit_insn(Insn::DropTable {
-        db: 0,
+        db,
         _p2: 0,
         _p3: 0,
         table_name: tbl_name.name.as_str().to_string(),
     });
 
-    program.emit_insn(Insn::SetCookie {
-        db: 0,
-        cookie: Cookie::SchemaVersion,
-        value: schema.schema_version as i32 + 1,
-        p5: 0,
-    });
+    if !temporary {
+        program.emit_insn(Insn::SetCookie {
+            db,
+            cookie: Cookie::SchemaVersion,
+            value: schema.schema_version as i32 + 1,
+            p5: 0,
+        });
+    }
 
     Ok(program)
 }
@@ -3,7 +3,7 @@ use super::plan::{
     select_star, Distinctness, JoinOrderMember, Operation, OuterQueryReference, QueryDestination,
     Search, TableReferences, WhereTerm,
 };
-use crate::function::{AggFunc, ExtFunc, Func};
+use crate::function::{AggFunc, Func};
 use crate::schema::Table;
 use crate::translate::optimizer::optimize_plan;
 use crate::translate::plan::{Aggregate, GroupBy, Plan, ResultSetColumn, SelectPlan};
@@ -11,6 +11,7 @@ use crate::translate::planner::{
     bind_column_references, break_predicate_at_and_boundaries, parse_from, parse_limit,
     parse_where, resolve_aggregates,
 };
+use crate::translate::window::{prepare_window_select_plan, select_has_window_functions};
 use crate::util::normalize_ident;
 use crate::vdbe::builder::{ProgramBuilderOpts, TableRefIdCounter};
 use crate::vdbe::insn::Insn;
@@ -186,6 +187,22 @@ fn prepare_one_select_plan(
     query_destination: QueryDestination,
     connection: &Arc<crate::Connection>,
 ) -> Result<SelectPlan> {
+    if let ast::OneSelect::Select { columns, .. } = &select {
+        if select_has_window_functions(columns, &order_by)? {
+            return prepare_window_select_plan(
+                schema,
+                select,
+                limit,
+                order_by,
+                with,
+                syms,
+                outer_query_refs,
+                table_ref_counter,
+                query_destination,
+                connection,
+            );
+        }
+    }
     match select {
         ast::OneSelect::Select {
             mut columns,
@@ -193,7 +210,6 @@ fn prepare_one_select_plan(
             where_clause,
             group_by,
             distinctness,
-            window_clause,
             ..
         } => {
             if !schema.indexes_enabled() && distinctness.is_some() {
@@ -201,9 +217,6 @@ fn prepare_one_select_plan(
                     "SELECT with DISTINCT is not allowed without indexes enabled"
                 );
             }
-            if !window_clause.is_empty() {
-                crate::bail_parse_error!("WINDOW clause is not supported yet");
-            }
             let col_count = columns.len();
             if col_count == 0 {
                 crate::bail_parse_error!("SELECT without columns is not allowed");
@@ -268,6 +281,7 @@ fn prepare_one_select_plan(
                         table_id: t.internal_id,
                         original_idx: i,
                         is_outer: t.join_info.as_ref().is_some_and(|j| j.outer),
+                        is_right: t.join_info.as_ref().is_some_and(|j| j.right),
                     })
                     .collect(),
                 table_references,
@@ -405,7 +419,7 @@ fn prepare_one_select_plan(
                                         if let Some(f) =
                                             syms.resolve_function(name.as_str(), args_count)
                                         {
-                                            if let ExtFunc::Scalar(_) = f.as_ref().func {
+                                            if f.func.is_scalar() {
                                                 let contains_aggregates = resolve_aggregates(
                                                     schema,
                                                     expr,
@@ -659,7 +673,7 @@ fn add_vtab_predicates_to_where_clause(
 /// Replaces a column number in an ORDER BY or GROUP BY expression with a copy of the column expression.
 /// For example, in SELECT u.first_name, count(1) FROM users u GROUP BY 1 ORDER BY 2,
 /// the column number 1 is replaced with u.first_name and the column number 2 is replaced with count(1).
-fn replace_column_number_with_copy_of_column_expr(
+pub fn replace_column_number_with_copy_of_column_expr(
     order_by_or_group_by_expr: &mut ast::Expr,
     columns: &[ResultSetColumn],
 ) -> Result<()> {
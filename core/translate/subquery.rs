@@ -1,13 +1,22 @@
+use std::sync::Arc;
+
+use turso_parser::ast::SortOrder;
+
 use crate::{
-    schema::Table,
-    vdbe::{builder::ProgramBuilder, insn::Insn},
+    schema::{BTreeTable, FromClauseSubquery, Index, IndexColumn, Table},
+    vdbe::{
+        builder::{CursorType, ProgramBuilder},
+        insn::{InsertFlags, Insn},
+        BranchOffset, CursorID,
+    },
     Result,
 };
 
 use super::{
     emitter::{emit_query, Resolver, TranslateCtx},
     main_loop::LoopLabels,
-    plan::{QueryDestination, SelectPlan, TableReferences},
+    plan::{DistinctCtx, QueryDestination, SelectPlan, TableReferences},
+    window::emit_window_subquery,
 };
 
 /// Emit the subqueries contained in the FROM clause.
@@ -19,9 +28,18 @@ pub fn emit_subqueries(
 ) -> Result<()> {
     for table_reference in tables.joined_tables_mut() {
         if let Table::FromClauseSubquery(from_clause_subquery) = &mut table_reference.table {
+            if from_clause_subquery.is_recursive_reference {
+                // The recursive CTE has already stored the row being expanded in its registers.
+                continue;
+            }
             // Emit the subquery and get the start register of the result columns.
-            let result_columns_start =
-                emit_subquery(program, &mut from_clause_subquery.plan, t_ctx)?;
+            let result_columns_start = if from_clause_subquery.recursive.is_some() {
+                emit_recursive_cte(program, from_clause_subquery, t_ctx)?
+            } else if from_clause_subquery.window.is_some() {
+                emit_window_subquery(program, from_clause_subquery, t_ctx)?
+            } else {
+                emit_subquery(program, &mut from_clause_subquery.plan, t_ctx)?
+            };
             // Set the start register of the subquery's result columns.
             // This is done so that translate_expr() can read the result columns of the subquery,
             // as if it were reading from a regular table.
@@ -71,6 +89,7 @@ pub fn emit_subquery(
         label_main_loop_end: None,
         meta_group_by: None,
         meta_left_joins: (0..plan.joined_tables().len()).map(|_| None).collect(),
+        meta_right_joins: (0..plan.joined_tables().len()).map(|_| None).collect(),
         meta_sort: None,
         reg_agg_start: None,
         reg_nonagg_emit_once_flag: None,
@@ -95,3 +114,249 @@ pub fn emit_subquery(
     program.preassign_label_to_next_insn(subquery_body_end_label);
     Ok(result_column_start_reg)
 }
+
+/// Emit a recursive CTE as a coroutine and return the start register of its result columns.
+///
+/// The rows are produced with a queue held in an ephemeral table, see [super::plan::RecursiveCte]:
+/// the initial SELECTs fill the queue, then every row taken from the queue is yielded to the
+/// parent query and expanded by running the recursive SELECTs, whose rows are added to the queue.
+/// The reference to the CTE in the recursive SELECTs reads the row being expanded, which stays
+/// in the result registers while they run.
+fn emit_recursive_cte(
+    program: &mut ProgramBuilder,
+    from_clause_subquery: &mut FromClauseSubquery,
+    t_ctx: &mut TranslateCtx,
+) -> Result<usize> {
+    let num_columns = from_clause_subquery.columns.len();
+    let recursive = from_clause_subquery
+        .recursive
+        .as_mut()
+        .expect("recursive CTE must have a recursive plan");
+
+    let yield_reg = program.alloc_register();
+    let coroutine_implementation_start = program.allocate_label();
+    let coroutine_body_end = program.allocate_label();
+    let coroutine_end = program.allocate_label();
+    program.emit_insn(Insn::InitCoroutine {
+        yield_reg,
+        jump_on_definition: coroutine_body_end,
+        start_offset: coroutine_implementation_start,
+    });
+    program.preassign_label_to_next_insn(coroutine_implementation_start);
+
+    let queue_table = Arc::new(BTreeTable {
+        root_page: 0, // Not relevant for ephemeral table definition
+        name: format!("{}_queue", from_clause_subquery.name),
+        has_rowid: true,
+        primary_key_columns: vec![],
+        columns: from_clause_subquery.columns.clone(),
+        is_strict: false,
+        unique_sets: None,
+        foreign_keys: vec![],
+        check_constraints: vec![],
+        temporary: false,
+    });
+    let queue_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(queue_table));
+    program.emit_insn(Insn::OpenEphemeral {
+        cursor_id: queue_cursor_id,
+        is_table: true,
+    });
+    // With UNION, every row ever added to the queue is also recorded in an ephemeral index,
+    // so that it is not added again.
+    let distinct_index = if recursive.distinct {
+        let index = Arc::new(Index {
+            name: format!("{}_distinct", from_clause_subquery.name),
+            table_name: String::new(),
+            ephemeral: true,
+            root_page: 0,
+            columns: from_clause_subquery
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| IndexColumn {
+                    name: column.name.clone().unwrap_or_default(),
+                    order: SortOrder::Asc,
+                    pos_in_table: i,
+                    collation: None,
+                    default: None,
+                    expr: None,
+                })
+                .collect(),
+            unique: false,
+            has_rowid: false,
+            where_clause: None,
+            non_key_columns: 0,
+            temporary: false,
+        });
+        let cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(index.clone()));
+        program.emit_insn(Insn::OpenEphemeral {
+            cursor_id,
+            is_table: false,
+        });
+        Some((cursor_id, index.name.clone()))
+    } else {
+        None
+    };
+
+    let reg_limit = recursive.limit.map(|limit| {
+        let reg = program.alloc_register();
+        program.emit_int(limit as i64, reg);
+        program.emit_insn(Insn::IfNot {
+            reg,
+            target_pc: coroutine_end,
+            jump_if_null: false,
+        });
+        reg
+    });
+    let reg_offset = recursive.offset.filter(|offset| *offset > 0).map(|offset| {
+        let reg = program.alloc_register();
+        program.emit_int(offset as i64, reg);
+        reg
+    });
+
+    for plan in recursive.initial.iter_mut() {
+        emit_rows_into_queue(
+            program,
+            plan,
+            t_ctx,
+            num_columns,
+            queue_cursor_id,
+            distinct_index.as_ref(),
+        )?;
+    }
+
+    // Take the next row from the queue and yield it to the parent query.
+    let result_columns_start = program.alloc_registers(num_columns);
+    let queue_loop_start = program.allocate_label();
+    program.preassign_label_to_next_insn(queue_loop_start);
+    program.emit_insn(Insn::Rewind {
+        cursor_id: queue_cursor_id,
+        pc_if_empty: coroutine_end,
+    });
+    for i in 0..num_columns {
+        program.emit_column_or_rowid(queue_cursor_id, i, result_columns_start + i);
+    }
+    program.emit_insn(Insn::Delete {
+        cursor_id: queue_cursor_id,
+        table_name: String::new(),
+    });
+    let expand_row = program.allocate_label();
+    if let Some(reg_offset) = reg_offset {
+        program.emit_insn(Insn::IfPos {
+            reg: reg_offset,
+            target_pc: expand_row,
+            decrement_by: 1,
+        });
+    }
+    program.emit_insn(Insn::Yield {
+        yield_reg,
+        end_offset: BranchOffset::Offset(0),
+    });
+    if let Some(reg_limit) = reg_limit {
+        program.emit_insn(Insn::DecrJumpZero {
+            reg: reg_limit,
+            target_pc: coroutine_end,
+        });
+    }
+
+    // Expand the row by running the recursive SELECTs.
+    program.preassign_label_to_next_insn(expand_row);
+    for plan in recursive.recursive.iter_mut() {
+        set_recursive_reference_registers(&mut plan.table_references, result_columns_start);
+        emit_rows_into_queue(
+            program,
+            plan,
+            t_ctx,
+            num_columns,
+            queue_cursor_id,
+            distinct_index.as_ref(),
+        )?;
+    }
+    program.emit_insn(Insn::Goto {
+        target_pc: queue_loop_start,
+    });
+
+    program.preassign_label_to_next_insn(coroutine_end);
+    program.emit_insn(Insn::EndCoroutine { yield_reg });
+    program.preassign_label_to_next_insn(coroutine_body_end);
+
+    // The parent query reads the CTE like any other FROM clause subquery.
+    from_clause_subquery.plan.query_destination = QueryDestination::CoroutineYield {
+        yield_reg,
+        coroutine_implementation_start,
+    };
+    Ok(result_columns_start)
+}
+
+/// Runs `plan` as a coroutine and adds the rows it yields to the queue of a recursive CTE,
+/// skipping the rows already recorded in `distinct_index`, if any.
+fn emit_rows_into_queue(
+    program: &mut ProgramBuilder,
+    plan: &mut SelectPlan,
+    t_ctx: &mut TranslateCtx,
+    num_columns: usize,
+    queue_cursor_id: CursorID,
+    distinct_index: Option<&(CursorID, String)>,
+) -> Result<()> {
+    let start_reg = emit_subquery(program, plan, t_ctx)?;
+    let QueryDestination::CoroutineYield { yield_reg, .. } = plan.query_destination else {
+        unreachable!("CTE SELECT with non-subquery query type");
+    };
+    let loop_start = program.allocate_label();
+    let loop_end = program.allocate_label();
+    program.preassign_label_to_next_insn(loop_start);
+    program.emit_insn(Insn::Yield {
+        yield_reg,
+        end_offset: loop_end,
+    });
+    if let Some((cursor_id, index_name)) = distinct_index {
+        let distinct_ctx = DistinctCtx {
+            cursor_id: *cursor_id,
+            ephemeral_index_name: index_name.clone(),
+            label_on_conflict: loop_start,
+        };
+        distinct_ctx.emit_deduplication_insns(program, num_columns, start_reg);
+    }
+    let record_reg = program.alloc_register();
+    program.emit_insn(Insn::MakeRecord {
+        start_reg,
+        count: num_columns,
+        dest_reg: record_reg,
+        index_name: None,
+    });
+    let rowid_reg = program.alloc_register();
+    program.emit_insn(Insn::NewRowid {
+        cursor: queue_cursor_id,
+        rowid_reg,
+        prev_largest_reg: 0,
+    });
+    program.emit_insn(Insn::Insert {
+        cursor: queue_cursor_id,
+        key_reg: rowid_reg,
+        record_reg,
+        flag: InsertFlags::new().require_seek(),
+        table_name: String::new(),
+    });
+    program.emit_insn(Insn::Goto {
+        target_pc: loop_start,
+    });
+    program.preassign_label_to_next_insn(loop_end);
+    Ok(())
+}
+
+/// Points the references a recursive SELECT makes to its CTE at the registers holding the row
+/// being expanded.
+fn set_recursive_reference_registers(tables: &mut TableReferences, start_reg: usize) {
+    for table_reference in tables.joined_tables_mut() {
+        if let Table::FromClauseSubquery(from_clause_subquery) = &mut table_reference.table {
+            if from_clause_subquery.is_recursive_reference {
+                from_clause_subquery.result_columns_start_reg = Some(start_reg);
+            } else {
+                set_recursive_reference_registers(
+                    &mut from_clause_subquery.plan.table_references,
+                    start_reg,
+                );
+            }
+        }
+    }
+}
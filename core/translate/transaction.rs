@@ -1,6 +1,7 @@
 use crate::schema::Schema;
 use crate::translate::{ProgramBuilder, ProgramBuilderOpts};
-use crate::vdbe::insn::Insn;
+use crate::util::normalize_ident;
+use crate::vdbe::insn::{Insn, SavepointOp};
 use crate::Result;
 use turso_parser::ast::{Name, TransactionType};
 
@@ -54,3 +55,29 @@ pub fn translate_tx_commit(
     });
     Ok(program)
 }
+
+pub fn translate_savepoint(name: Name, mut program: ProgramBuilder) -> Result<ProgramBuilder> {
+    program.extend(&ProgramBuilderOpts {
+        num_cursors: 0,
+        approx_num_insns: 0,
+        approx_num_labels: 0,
+    });
+    program.emit_insn(Insn::Savepoint {
+        op: SavepointOp::Begin,
+        name: normalize_ident(name.as_str()),
+    });
+    Ok(program)
+}
+
+pub fn translate_release(name: Name, mut program: ProgramBuilder) -> Result<ProgramBuilder> {
+    program.extend(&ProgramBuilderOpts {
+        num_cursors: 0,
+        approx_num_insns: 0,
+        approx_num_labels: 0,
+    });
+    program.emit_insn(Insn::Savepoint {
+        op: SavepointOp::Release,
+        name: normalize_ident(name.as_str()),
+    });
+    Ok(program)
+}
@@ -0,0 +1,1250 @@
+//! Triggers.
+//!
+//! CREATE TRIGGER stores the trigger in sqlite_schema; the body is kept as AST in the
+//! in-memory [Schema]. Every INSERT, UPDATE or DELETE that fires a trigger compiles the
+//! trigger body into a separate [Program], which is invoked once per row with
+//! [Insn::Program]. The NEW and OLD rows are passed to the sub-program as parameters:
+//! before compilation, every `NEW.col` / `OLD.col` reference in the body is rewritten into
+//! a named variable, and the statement binds the registers holding the row to them.
+
+use std::cell::{OnceCell, RefCell};
+use std::num::NonZero;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+
+use turso_parser::ast::{self, Expr, InsertBody};
+
+use crate::schema::{BTreeTable, Column, Schema, Trigger, Type};
+use crate::translate::emitter::Resolver;
+use crate::translate::expr::{translate_expr, walk_expr_mut};
+use crate::translate::plan::QueryDestination;
+use crate::translate::schema::{
+    emit_schema_entry, resolve_schema_target, SchemaEntryType, SQLITE_TABLEID,
+};
+use crate::translate::select::translate_select;
+use crate::translate::translate_inner;
+use crate::util::normalize_ident;
+use crate::vdbe::builder::{CursorType, ProgramBuilder, ProgramBuilderOpts, QueryMode};
+use crate::vdbe::insn::{CmpInsFlags, Cookie, InsertFlags, Insn};
+use crate::vdbe::{BranchOffset, Program};
+use crate::{bail_parse_error, Connection, Result, SymbolTable};
+
+/// Prefix of the variables that carry the NEW row into a trigger program.
+const NEW_PARAM_PREFIX: &str = "#new_";
+/// Prefix of the variables that carry the OLD row into a trigger program.
+const OLD_PARAM_PREFIX: &str = "#old_";
+/// Suffix used instead of a column index when the trigger refers to the rowid.
+const ROWID_PARAM_SUFFIX: &str = "rowid";
+
+/// A value of the NEW or OLD row that a trigger program reads through a parameter.
+/// The column index is `None` when the trigger refers to the rowid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TriggerParam {
+    New(Option<usize>),
+    Old(Option<usize>),
+}
+
+impl TriggerParam {
+    fn name(&self) -> String {
+        let (prefix, column) = match self {
+            TriggerParam::New(column) => (NEW_PARAM_PREFIX, column),
+            TriggerParam::Old(column) => (OLD_PARAM_PREFIX, column),
+        };
+        match column {
+            Some(idx) => format!("{prefix}{idx}"),
+            None => format!("{prefix}{ROWID_PARAM_SUFFIX}"),
+        }
+    }
+}
+
+/// The program run by an [Insn::Program].
+#[derive(Debug, Clone)]
+pub enum TriggerProgram {
+    Compiled(Rc<Program>),
+    /// A trigger that fires itself again while it is still being compiled further up the
+    /// trigger stack, like the ON DELETE CASCADE action of a self-referencing foreign key.
+    /// The program is set once it is built, and is held weakly since it contains this reference.
+    Recursive(Rc<OnceCell<Weak<Program>>>),
+}
+
+impl TriggerProgram {
+    pub fn get(&self) -> Rc<Program> {
+        match self {
+            TriggerProgram::Compiled(program) => program.clone(),
+            TriggerProgram::Recursive(program) => program
+                .get()
+                .and_then(Weak::upgrade)
+                .expect("recursive trigger program must outlive its invocations"),
+        }
+    }
+}
+
+/// A trigger whose body is being compiled, see [ProgramBuilder::trigger_stack].
+#[derive(Debug, Clone)]
+pub struct TriggerFrame {
+    pub name: String,
+    /// Set to the trigger program once it is built
+    program: Rc<OnceCell<Weak<Program>>>,
+    params: Vec<(NonZero<usize>, TriggerParam)>,
+}
+
+impl TriggerFrame {
+    /// The trigger running again from inside its own body.
+    pub fn recursive_trigger(&self) -> CompiledTrigger {
+        CompiledTrigger {
+            name: self.name.clone(),
+            program: TriggerProgram::Recursive(self.program.clone()),
+            params: self.params.clone(),
+        }
+    }
+}
+
+/// A trigger body compiled for a specific statement.
+#[derive(Debug, Clone)]
+pub struct CompiledTrigger {
+    pub name: String,
+    pub program: TriggerProgram,
+    /// The parameters of the program and the row values bound to them
+    pub params: Vec<(NonZero<usize>, TriggerParam)>,
+}
+
+/// The triggers fired by a statement, grouped by when they run.
+#[derive(Debug, Clone, Default)]
+pub struct TriggerPrograms {
+    pub before: Vec<CompiledTrigger>,
+    pub after: Vec<CompiledTrigger>,
+    pub instead_of: Vec<CompiledTrigger>,
+}
+
+impl TriggerPrograms {
+    pub fn is_empty(&self) -> bool {
+        self.before.is_empty() && self.after.is_empty() && self.instead_of.is_empty()
+    }
+}
+
+/// Registers holding a row passed to trigger programs as NEW or OLD.
+#[derive(Debug, Clone, Copy)]
+pub struct TriggerRow {
+    /// Register holding the rowid of the row
+    pub rowid_reg: usize,
+    /// First of the registers holding the column values, in table order
+    pub columns_start: usize,
+}
+
+/// Compiles the triggers attached to `table_name` that fire on `event`.
+/// `updated_columns` are the columns assigned by an UPDATE, used to match `UPDATE OF` triggers.
+/// Triggers that are already running further up the trigger stack are skipped, since
+/// recursive triggers are not supported.
+#[allow(clippy::too_many_arguments)]
+pub fn compile_triggers(
+    schema: &Schema,
+    syms: &SymbolTable,
+    connection: &Arc<Connection>,
+    parent: &ProgramBuilder,
+    table_name: &str,
+    columns: &[Column],
+    event: ast::TriggerEvent,
+    updated_columns: &[String],
+) -> Result<TriggerPrograms> {
+    let mut triggers = TriggerPrograms::default();
+    for trigger in schema.get_triggers_for_table(table_name) {
+        if !trigger.fires_on(&event, updated_columns)
+            || parent
+                .trigger_stack()
+                .iter()
+                .any(|frame| frame.name == trigger.name)
+        {
+            continue;
+        }
+        let compiled = compile_trigger(
+            schema,
+            syms,
+            connection,
+            parent.trigger_stack(),
+            &trigger,
+            columns,
+            &event,
+        )?;
+        match trigger.time {
+            ast::TriggerTime::Before => triggers.before.push(compiled),
+            ast::TriggerTime::After => triggers.after.push(compiled),
+            ast::TriggerTime::InsteadOf => triggers.instead_of.push(compiled),
+        }
+    }
+    Ok(triggers)
+}
+
+pub(crate) fn compile_trigger(
+    schema: &Schema,
+    syms: &SymbolTable,
+    connection: &Arc<Connection>,
+    trigger_stack: &[TriggerFrame],
+    trigger: &Trigger,
+    columns: &[Column],
+    event: &ast::TriggerEvent,
+) -> Result<CompiledTrigger> {
+    let rewriter = RowRefRewriter {
+        columns,
+        has_new: !matches!(event, ast::TriggerEvent::Delete),
+        has_old: !matches!(event, ast::TriggerEvent::Insert),
+        params: RefCell::new(Vec::new()),
+    };
+    let when_clause = match &trigger.when_clause {
+        Some(when_clause) => {
+            let mut when_clause = when_clause.clone();
+            rewriter.rewrite_expr(&mut when_clause)?;
+            Some(when_clause)
+        }
+        None => None,
+    };
+    let stmts = trigger
+        .commands
+        .iter()
+        .map(|command| rewriter.rewrite_command(command.clone()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut program = ProgramBuilder::new(
+        QueryMode::Normal,
+        connection.get_capture_data_changes().clone(),
+        ProgramBuilderOpts {
+            num_cursors: 1,
+            approx_num_insns: 32,
+            approx_num_labels: 2,
+        },
+    );
+    // Declare the parameters before compiling the body, so that a recursive invocation of the
+    // trigger from inside the body knows how to bind them
+    let mut row_params = rewriter.params.into_inner();
+    row_params.sort();
+    row_params.dedup();
+    let params = row_params
+        .into_iter()
+        .map(|param| (program.parameters.push(param.name()), param))
+        .collect::<Vec<_>>();
+    let frame = TriggerFrame {
+        name: trigger.name.clone(),
+        program: Rc::new(OnceCell::new()),
+        params: params.clone(),
+    };
+    let mut stack = trigger_stack.to_vec();
+    stack.push(frame.clone());
+    program.set_trigger_stack(stack);
+    program.prologue();
+
+    let end_label = program.allocate_label();
+    if let Some(when_clause) = &when_clause {
+        let resolver = Resolver::new(schema, syms);
+        let when_reg = program.alloc_register();
+        translate_expr(&mut program, None, when_clause, when_reg, &resolver)?;
+        program.emit_insn(Insn::IfNot {
+            reg: when_reg,
+            target_pc: end_label,
+            jump_if_null: true,
+        });
+    }
+
+    for stmt in stmts {
+        program = translate_inner(schema, stmt, syms, program, connection, &trigger.sql)?;
+    }
+
+    program.preassign_label_to_next_insn(end_label);
+    program.epilogue(schema);
+    let program = Rc::new(program.build(connection.clone(), false, &trigger.sql));
+    frame
+        .program
+        .set(Rc::downgrade(&program))
+        .expect("trigger program is built once");
+
+    Ok(CompiledTrigger {
+        name: trigger.name.clone(),
+        program: TriggerProgram::Compiled(program),
+        params,
+    })
+}
+
+/// Rewrites `NEW.col` and `OLD.col` references in a trigger body into the named variables
+/// that the firing statement binds.
+struct RowRefRewriter<'a> {
+    columns: &'a [Column],
+    has_new: bool,
+    has_old: bool,
+    /// The row values referenced so far
+    params: RefCell<Vec<TriggerParam>>,
+}
+
+impl RowRefRewriter<'_> {
+    fn rewrite_command(&self, command: ast::TriggerCmd) -> Result<ast::Stmt> {
+        let stmt = match command {
+            ast::TriggerCmd::Update {
+                or_conflict,
+                tbl_name,
+                mut sets,
+                mut from,
+                mut where_clause,
+            } => {
+                for set in sets.iter_mut() {
+                    self.rewrite_expr(&mut set.expr)?;
+                }
+                if let Some(from) = from.as_mut() {
+                    self.rewrite_from(from)?;
+                }
+                self.rewrite_opt_expr(&mut where_clause)?;
+                ast::Stmt::Update(ast::Update {
+                    with: None,
+                    or_conflict,
+                    tbl_name: ast::QualifiedName::single(tbl_name),
+                    indexed: None,
+                    sets,
+                    from,
+                    where_clause,
+                    returning: vec![],
+                    order_by: vec![],
+                    limit: None,
+                })
+            }
+            ast::TriggerCmd::Insert {
+                or_conflict,
+                tbl_name,
+                col_names,
+                mut select,
+                mut upsert,
+                returning,
+            } => {
+                self.rewrite_select(&mut select)?;
+                let mut next = upsert.as_deref_mut();
+                while let Some(upsert) = next {
+                    if let Some(index) = upsert.index.as_mut() {
+                        self.rewrite_opt_expr(&mut index.where_clause)?;
+                    }
+                    if let ast::UpsertDo::Set { sets, where_clause } = &mut upsert.do_clause {
+                        for set in sets.iter_mut() {
+                            self.rewrite_expr(&mut set.expr)?;
+                        }
+                        self.rewrite_opt_expr(where_clause)?;
+                    }
+                    next = upsert.next.as_deref_mut();
+                }
+                ast::Stmt::Insert {
+                    with: None,
+                    or_conflict,
+                    tbl_name: ast::QualifiedName::single(tbl_name),
+                    columns: col_names,
+                    body: InsertBody::Select(select, upsert),
+                    returning,
+                }
+            }
+            ast::TriggerCmd::Delete {
+                tbl_name,
+                mut where_clause,
+            } => {
+                self.rewrite_opt_expr(&mut where_clause)?;
+                ast::Stmt::Delete {
+                    with: None,
+                    tbl_name: ast::QualifiedName::single(tbl_name),
+                    indexed: None,
+                    where_clause,
+                    returning: vec![],
+                    order_by: vec![],
+                    limit: None,
+                }
+            }
+            ast::TriggerCmd::Select(mut select) => {
+                self.rewrite_select(&mut select)?;
+                ast::Stmt::Select(select)
+            }
+        };
+        Ok(stmt)
+    }
+
+    fn rewrite_select(&self, select: &mut ast::Select) -> Result<()> {
+        if let Some(with) = select.with.as_mut() {
+            for cte in with.ctes.iter_mut() {
+                self.rewrite_select(&mut cte.select)?;
+            }
+        }
+        self.rewrite_one_select(&mut select.body.select)?;
+        for compound in select.body.compounds.iter_mut() {
+            self.rewrite_one_select(&mut compound.select)?;
+        }
+        for sorted_column in select.order_by.iter_mut() {
+            self.rewrite_expr(&mut sorted_column.expr)?;
+        }
+        if let Some(limit) = select.limit.as_mut() {
+            self.rewrite_expr(&mut limit.expr)?;
+            self.rewrite_opt_expr(&mut limit.offset)?;
+        }
+        Ok(())
+    }
+
+    fn rewrite_one_select(&self, select: &mut ast::OneSelect) -> Result<()> {
+        match select {
+            ast::OneSelect::Select {
+                columns,
+                from,
+                where_clause,
+                group_by,
+                ..
+            } => {
+                for column in columns.iter_mut() {
+                    if let ast::ResultColumn::Expr(expr, _) = column {
+                        self.rewrite_expr(expr)?;
+                    }
+                }
+                if let Some(from) = from.as_mut() {
+                    self.rewrite_from(from)?;
+                }
+                self.rewrite_opt_expr(where_clause)?;
+                if let Some(group_by) = group_by.as_mut() {
+                    for expr in group_by.exprs.iter_mut() {
+                        self.rewrite_expr(expr)?;
+                    }
+                    self.rewrite_opt_expr(&mut group_by.having)?;
+                }
+            }
+            ast::OneSelect::Values(rows) => {
+                for expr in rows.iter_mut().flat_map(|row| row.iter_mut()) {
+                    self.rewrite_expr(expr)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn rewrite_from(&self, from: &mut ast::FromClause) -> Result<()> {
+        self.rewrite_select_table(&mut from.select)?;
+        for join in from.joins.iter_mut() {
+            self.rewrite_select_table(&mut join.table)?;
+            if let Some(ast::JoinConstraint::On(expr)) = join.constraint.as_mut() {
+                self.rewrite_expr(expr)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn rewrite_select_table(&self, table: &mut ast::SelectTable) -> Result<()> {
+        match table {
+            ast::SelectTable::Table(..) => {}
+            ast::SelectTable::TableCall(_, args, _) => {
+                for arg in args.iter_mut() {
+                    self.rewrite_expr(arg)?;
+                }
+            }
+            ast::SelectTable::Select(select, _) => self.rewrite_select(select)?,
+            ast::SelectTable::Sub(from, _) => self.rewrite_from(from)?,
+        }
+        Ok(())
+    }
+
+    fn rewrite_opt_expr(&self, expr: &mut Option<Box<Expr>>) -> Result<()> {
+        if let Some(expr) = expr.as_mut() {
+            self.rewrite_expr(expr)?;
+        }
+        Ok(())
+    }
+
+    fn rewrite_expr(&self, expr: &mut Expr) -> Result<()> {
+        walk_expr_mut(expr, &mut |expr: &mut Expr| -> Result<()> {
+            match expr {
+                Expr::Qualified(tbl_name, col_name) => {
+                    let tbl_name = normalize_ident(tbl_name.as_str());
+                    let prefix = match tbl_name.as_str() {
+                        "new" => NEW_PARAM_PREFIX,
+                        "old" => OLD_PARAM_PREFIX,
+                        _ => return Ok(()),
+                    };
+                    let col_name = normalize_ident(col_name.as_str());
+                    let row_exists = if prefix == NEW_PARAM_PREFIX {
+                        self.has_new
+                    } else {
+                        self.has_old
+                    };
+                    if !row_exists {
+                        bail_parse_error!("no such column: {tbl_name}.{col_name}");
+                    }
+                    let position = self.columns.iter().position(|column| {
+                        column
+                            .name
+                            .as_ref()
+                            .is_some_and(|name| name.eq_ignore_ascii_case(&col_name))
+                    });
+                    let column = match position {
+                        Some(idx) => Some(idx),
+                        None if matches!(col_name.as_str(), "rowid" | "oid" | "_rowid_") => None,
+                        None => bail_parse_error!("no such column: {tbl_name}.{col_name}"),
+                    };
+                    let param = if prefix == NEW_PARAM_PREFIX {
+                        TriggerParam::New(column)
+                    } else {
+                        TriggerParam::Old(column)
+                    };
+                    *expr = Expr::Variable(param.name());
+                    self.params.borrow_mut().push(param);
+                }
+                Expr::Variable(_) => bail_parse_error!("trigger cannot use variables"),
+                Expr::Exists(select) | Expr::Subquery(select) => self.rewrite_select(select)?,
+                Expr::InSelect { rhs, .. } => self.rewrite_select(rhs)?,
+                _ => {}
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Emits an [Insn::Program] for every trigger in `triggers`, binding the NEW and OLD rows
+/// from the given registers. A RAISE(IGNORE) in any of them jumps to `ignore_target`.
+pub fn emit_trigger_programs(
+    program: &mut ProgramBuilder,
+    triggers: &[CompiledTrigger],
+    columns: &[Column],
+    new_row: Option<TriggerRow>,
+    old_row: Option<TriggerRow>,
+    ignore_target: BranchOffset,
+) {
+    for trigger in triggers {
+        let params = trigger
+            .params
+            .iter()
+            .map(|(index, param)| {
+                let (row, column) = match param {
+                    TriggerParam::New(column) => (new_row, column),
+                    TriggerParam::Old(column) => (old_row, column),
+                };
+                let row = row.expect("trigger program refers to a row the statement does not have");
+                let reg = match column {
+                    Some(idx) if !columns[*idx].is_rowid_alias => row.columns_start + idx,
+                    _ => row.rowid_reg,
+                };
+                (*index, reg)
+            })
+            .collect();
+        program.emit_insn(Insn::Program {
+            program: trigger.program.clone(),
+            params,
+            ignore_jump_target: ignore_target,
+            trigger_name: trigger.name.clone(),
+        });
+    }
+}
+
+/// Reads the current row of `cursor_id` into a new block of registers, so that it can be
+/// passed to trigger programs as the OLD row.
+pub fn emit_old_row(
+    program: &mut ProgramBuilder,
+    cursor_id: usize,
+    columns: &[Column],
+    rowid_reg: usize,
+) -> TriggerRow {
+    let columns_start = program.alloc_registers(columns.len());
+    for idx in 0..columns.len() {
+        program.emit_column_or_rowid(cursor_id, idx, columns_start + idx);
+    }
+    TriggerRow {
+        rowid_reg,
+        columns_start,
+    }
+}
+
+pub fn translate_create_trigger(
+    schema: &Schema,
+    stmt: ast::Stmt,
+    syms: &SymbolTable,
+    connection: &Arc<Connection>,
+    mut program: ProgramBuilder,
+) -> Result<ProgramBuilder> {
+    let ast::Stmt::CreateTrigger {
+        temporary,
+        if_not_exists,
+        trigger_name,
+        time,
+        event,
+        tbl_name,
+        for_each_row,
+        when_clause,
+        commands,
+    } = stmt
+    else {
+        unreachable!("translate_create_trigger called with a non CREATE TRIGGER statement");
+    };
+    if temporary {
+        bail_parse_error!("TEMPORARY triggers are not supported yet");
+    }
+    let (db, attached_schema) = resolve_schema_target(connection, &trigger_name)?;
+    if db == 1 {
+        bail_parse_error!("TEMPORARY triggers are not supported yet");
+    }
+    let schema = attached_schema.as_deref().unwrap_or(schema);
+    let normalized_trigger_name = normalize_ident(trigger_name.name.as_str());
+    let normalized_tbl_name = normalize_ident(tbl_name.name.as_str());
+    // Like in SQLite, a trigger lives in the database of its table.
+    if let Some(db_name) = &tbl_name.db_name {
+        if connection.resolve_database_id(&tbl_name)? != db {
+            bail_parse_error!(
+                "trigger {normalized_trigger_name} cannot reference objects in database {}",
+                normalize_ident(db_name.as_str())
+            );
+        }
+    }
+
+    if schema.get_trigger(&normalized_trigger_name).is_some() {
+        if if_not_exists {
+            return Ok(program);
+        }
+        bail_parse_error!("trigger {} already exists", normalized_trigger_name);
+    }
+
+    let time = time.unwrap_or(ast::TriggerTime::Before);
+    if normalized_tbl_name.starts_with("sqlite_") {
+        bail_parse_error!("cannot create trigger on system table");
+    }
+    if schema.get_view(&normalized_tbl_name).is_some() {
+        match time {
+            ast::TriggerTime::InsteadOf => {}
+            ast::TriggerTime::Before => {
+                bail_parse_error!("cannot create BEFORE trigger on view: {normalized_tbl_name}")
+            }
+            ast::TriggerTime::After => {
+                bail_parse_error!("cannot create AFTER trigger on view: {normalized_tbl_name}")
+            }
+        }
+    } else {
+        let Some(table) = schema.get_table(&normalized_tbl_name) else {
+            bail_parse_error!("no such table: {}", normalized_tbl_name);
+        };
+        if table.virtual_table().is_some() {
+            bail_parse_error!("cannot create triggers on virtual tables");
+        }
+        if table.is_temporary() {
+            bail_parse_error!("triggers on TEMPORARY tables are not supported yet");
+        }
+        if time == ast::TriggerTime::InsteadOf {
+            bail_parse_error!("cannot create INSTEAD OF trigger on table: {normalized_tbl_name}");
+        }
+    }
+
+    let sql = ast::Stmt::CreateTrigger {
+        temporary,
+        if_not_exists: false,
+        trigger_name,
+        time: Some(time),
+        event,
+        tbl_name,
+        for_each_row,
+        when_clause,
+        commands,
+    }
+    .to_string();
+
+    let opts = ProgramBuilderOpts {
+        num_cursors: 1,
+        approx_num_insns: 20,
+        approx_num_labels: 1,
+    };
+    program.extend(&opts);
+
+    let table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
+    let sqlite_schema_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
+    program.emit_insn(Insn::OpenWrite {
+        cursor_id: sqlite_schema_cursor_id,
+        root_page: 1usize.into(),
+        db,
+    });
+
+    let resolver = Resolver::new(schema, syms);
+    emit_schema_entry(
+        &mut program,
+        &resolver,
+        sqlite_schema_cursor_id,
+        None, // cdc_table_cursor_id, no cdc for triggers
+        SchemaEntryType::Trigger,
+        &normalized_trigger_name,
+        &normalized_tbl_name,
+        0, // triggers don't have a root page
+        Some(sql),
+    )?;
+
+    program.emit_insn(Insn::ParseSchema {
+        db,
+        where_clause: Some(format!(
+            "type = 'trigger' AND name = '{normalized_trigger_name}'"
+        )),
+    });
+
+    program.emit_insn(Insn::SetCookie {
+        db,
+        cookie: Cookie::SchemaVersion,
+        value: (schema.schema_version + 1) as i32,
+        p5: 0,
+    });
+
+    Ok(program)
+}
+
+pub fn translate_drop_trigger(
+    schema: &Schema,
+    trigger_name: &ast::QualifiedName,
+    if_exists: bool,
+    connection: &Arc<Connection>,
+    mut program: ProgramBuilder,
+) -> Result<ProgramBuilder> {
+    let (db, attached_schema) = resolve_schema_target(connection, trigger_name)?;
+    let schema = attached_schema.as_deref().unwrap_or(schema);
+    let normalized_trigger_name = normalize_ident(trigger_name.name.as_str());
+
+    if schema.get_trigger(&normalized_trigger_name).is_none() {
+        if if_exists {
+            return Ok(program);
+        }
+        bail_parse_error!("no such trigger: {}", normalized_trigger_name);
+    }
+
+    let opts = ProgramBuilderOpts {
+        num_cursors: 1,
+        approx_num_insns: 20,
+        approx_num_labels: 3,
+    };
+    program.extend(&opts);
+
+    let schema_table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
+    let sqlite_schema_cursor_id =
+        program.alloc_cursor_id(CursorType::BTreeTable(schema_table.clone()));
+    program.emit_insn(Insn::OpenWrite {
+        cursor_id: sqlite_schema_cursor_id,
+        root_page: 1usize.into(),
+        db,
+    });
+
+    let trigger_name_reg = program.emit_string8_new_reg(normalized_trigger_name.clone());
+    let type_reg = program.emit_string8_new_reg("trigger".to_string());
+
+    // Scan sqlite_schema and delete the row of the trigger
+    let end_loop_label = program.allocate_label();
+    let loop_start_label = program.allocate_label();
+    program.emit_insn(Insn::Rewind {
+        cursor_id: sqlite_schema_cursor_id,
+        pc_if_empty: end_loop_label,
+    });
+    program.preassign_label_to_next_insn(loop_start_label);
+
+    // Column 0 is type, Column 1 is name
+    let col0_reg = program.alloc_register();
+    let col1_reg = program.alloc_register();
+    program.emit_column_or_rowid(sqlite_schema_cursor_id, 0, col0_reg);
+    program.emit_column_or_rowid(sqlite_schema_cursor_id, 1, col1_reg);
+
+    let skip_delete_label = program.allocate_label();
+    program.emit_insn(Insn::Ne {
+        lhs: col0_reg,
+        rhs: type_reg,
+        target_pc: skip_delete_label,
+        flags: CmpInsFlags::default(),
+        collation: program.curr_collation(),
+    });
+    program.emit_insn(Insn::Ne {
+        lhs: col1_reg,
+        rhs: trigger_name_reg,
+        target_pc: skip_delete_label,
+        flags: CmpInsFlags::default(),
+        collation: program.curr_collation(),
+    });
+    program.emit_insn(Insn::Delete {
+        cursor_id: sqlite_schema_cursor_id,
+        table_name: SQLITE_TABLEID.to_string(),
+    });
+    program.preassign_label_to_next_insn(skip_delete_label);
+
+    program.emit_insn(Insn::Next {
+        cursor_id: sqlite_schema_cursor_id,
+        pc_if_next: loop_start_label,
+    });
+    program.preassign_label_to_next_insn(end_loop_label);
+
+    // Remove the trigger from the in-memory schema
+    program.emit_insn(Insn::DropTrigger {
+        db,
+        trigger_name: normalized_trigger_name,
+    });
+
+    program.emit_insn(Insn::SetCookie {
+        db,
+        cookie: Cookie::SchemaVersion,
+        value: (schema.schema_version + 1) as i32,
+        p5: 1, // update version
+    });
+
+    Ok(program)
+}
+
+/// Returns the columns of a view as seen by its INSTEAD OF triggers, or an error if the view
+/// has no INSTEAD OF trigger for `event`.
+fn view_columns_for_instead_of(
+    schema: &Schema,
+    view_name: &str,
+    event: &ast::TriggerEvent,
+) -> Result<Vec<Column>> {
+    let view = schema.get_view(view_name).expect("view must exist");
+    let has_trigger = schema.get_triggers_for_table(&view.name).iter().any(|t| {
+        t.time == ast::TriggerTime::InsteadOf
+            && match (&t.event, event) {
+                // `UPDATE OF` triggers are matched against the assigned columns later on
+                (ast::TriggerEvent::UpdateOf(_), ast::TriggerEvent::Update) => true,
+                _ => t.fires_on(event, &[]),
+            }
+    });
+    if !has_trigger {
+        bail_parse_error!("cannot modify {} because it is a view", view.name);
+    }
+    // A view has no rowid, so none of its columns can alias it.
+    Ok(view
+        .columns
+        .iter()
+        .map(|column| Column {
+            is_rowid_alias: false,
+            primary_key: false,
+            default: None,
+            ..column.clone()
+        })
+        .collect())
+}
+
+/// Builds `SELECT <columns> FROM view WHERE <where_clause>`.
+fn select_from_view(
+    view_name: &str,
+    columns: Vec<ast::ResultColumn>,
+    where_clause: Option<Box<Expr>>,
+) -> ast::Select {
+    ast::Select {
+        with: None,
+        body: ast::SelectBody {
+            select: ast::OneSelect::Select {
+                distinctness: None,
+                columns,
+                from: Some(ast::FromClause {
+                    select: Box::new(ast::SelectTable::Table(
+                        ast::QualifiedName::single(ast::Name::new(view_name)),
+                        None,
+                        None,
+                    )),
+                    joins: vec![],
+                }),
+                where_clause,
+                group_by: None,
+                window_clause: vec![],
+            },
+            compounds: vec![],
+        },
+        order_by: vec![],
+        limit: None,
+    }
+}
+
+fn column_expr(column: &Column) -> Box<Expr> {
+    Box::new(Expr::Id(ast::Name::Ident(
+        column.name.clone().unwrap_or_default(),
+    )))
+}
+
+/// Materializes the rows produced by `select` into an ephemeral table and then calls
+/// `emit_row` once for every row, with the first of the registers holding it, the number
+/// of columns and the label of the next row. The rows are materialized first so that the
+/// triggers fired by `emit_row` cannot affect the rows that are visited.
+fn emit_instead_of_loop(
+    mut program: ProgramBuilder,
+    schema: &Schema,
+    syms: &SymbolTable,
+    connection: &Arc<Connection>,
+    select: ast::Select,
+    mut emit_row: impl FnMut(&mut ProgramBuilder, usize, usize, BranchOffset) -> Result<()>,
+) -> Result<ProgramBuilder> {
+    let yield_reg = program.alloc_register();
+    let jump_on_definition_label = program.allocate_label();
+    let start_offset_label = program.allocate_label();
+    let materialized_label = program.allocate_label();
+    program.emit_insn(Insn::InitCoroutine {
+        yield_reg,
+        jump_on_definition: jump_on_definition_label,
+        start_offset: start_offset_label,
+    });
+    program.preassign_label_to_next_insn(start_offset_label);
+
+    let query_destination = QueryDestination::CoroutineYield {
+        yield_reg,
+        coroutine_implementation_start: materialized_label,
+    };
+    program.incr_nesting();
+    let result = translate_select(schema, select, syms, program, query_destination, connection)?;
+    program = result.program;
+    program.decr_nesting();
+    let num_cols = result.num_result_cols;
+
+    program.emit_insn(Insn::EndCoroutine { yield_reg });
+    program.preassign_label_to_next_insn(jump_on_definition_label);
+
+    let scratch_table = Arc::new(BTreeTable {
+        root_page: 0, // Not relevant for ephemeral table definition
+        name: "ephemeral_scratch".to_string(),
+        has_rowid: true,
+        primary_key_columns: vec![],
+        columns: (0..num_cols)
+            .map(|_| Column {
+                name: None,
+                ty: Type::Null,
+                ty_str: String::new(),
+                primary_key: false,
+                is_rowid_alias: false,
+                notnull: false,
+                default: None,
+                unique: false,
+                collation: None,
+                hidden: false,
+                generated: None,
+            })
+            .collect(),
+        is_strict: false,
+        unique_sets: None,
+        foreign_keys: vec![],
+        check_constraints: vec![],
+        temporary: false,
+    });
+    let temp_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(scratch_table));
+    program.emit_insn(Insn::OpenEphemeral {
+        cursor_id: temp_cursor_id,
+        is_table: true,
+    });
+
+    // Copy every row produced by the coroutine into the ephemeral table
+    let yield_loop_label = program.allocate_label();
+    program.preassign_label_to_next_insn(yield_loop_label);
+    program.emit_insn(Insn::Yield {
+        yield_reg,
+        end_offset: materialized_label,
+    });
+    let record_reg = program.alloc_register();
+    program.emit_insn(Insn::MakeRecord {
+        start_reg: yield_reg + 1,
+        count: num_cols,
+        dest_reg: record_reg,
+        index_name: None,
+    });
+    let rowid_reg = program.alloc_register();
+    program.emit_insn(Insn::NewRowid {
+        cursor: temp_cursor_id,
+        rowid_reg,
+        prev_largest_reg: 0,
+    });
+    program.emit_insn(Insn::Insert {
+        cursor: temp_cursor_id,
+        key_reg: rowid_reg,
+        record_reg,
+        flag: InsertFlags::new().require_seek(),
+        table_name: "".to_string(),
+    });
+    program.emit_insn(Insn::Goto {
+        target_pc: yield_loop_label,
+    });
+    program.preassign_label_to_next_insn(materialized_label);
+
+    // Visit the materialized rows
+    let loop_end_label = program.allocate_label();
+    let loop_start_label = program.allocate_label();
+    let next_label = program.allocate_label();
+    program.emit_insn(Insn::Rewind {
+        cursor_id: temp_cursor_id,
+        pc_if_empty: loop_end_label,
+    });
+    program.preassign_label_to_next_insn(loop_start_label);
+    let row_start = program.alloc_registers(num_cols.max(1));
+    for idx in 0..num_cols {
+        program.emit_column_or_rowid(temp_cursor_id, idx, row_start + idx);
+    }
+    emit_row(&mut program, row_start, num_cols, next_label)?;
+    program.preassign_label_to_next_insn(next_label);
+    program.emit_insn(Insn::Next {
+        cursor_id: temp_cursor_id,
+        pc_if_next: loop_start_label,
+    });
+    program.preassign_label_to_next_insn(loop_end_label);
+    program.emit_insn(Insn::Close {
+        cursor_id: temp_cursor_id,
+    });
+
+    Ok(program)
+}
+
+/// Translates an INSERT into a view, which runs the INSTEAD OF INSERT triggers of the view
+/// once for every row instead of inserting it.
+pub fn translate_instead_of_insert(
+    schema: &Schema,
+    view_name: &str,
+    columns: Vec<ast::Name>,
+    body: InsertBody,
+    syms: &SymbolTable,
+    mut program: ProgramBuilder,
+    connection: &Arc<Connection>,
+) -> Result<ProgramBuilder> {
+    let event = ast::TriggerEvent::Insert;
+    let view_columns = view_columns_for_instead_of(schema, view_name, &event)?;
+    let triggers = compile_triggers(
+        schema,
+        syms,
+        connection,
+        &program,
+        view_name,
+        &view_columns,
+        event,
+        &[],
+    )?;
+
+    // For every view column, the position of the inserted value that populates it
+    let value_positions: Vec<Option<usize>> = if columns.is_empty() {
+        (0..view_columns.len()).map(Some).collect()
+    } else {
+        let mut positions = vec![None; view_columns.len()];
+        for (value_idx, name) in columns.iter().enumerate() {
+            let name = normalize_ident(name.as_str());
+            let Some(col_idx) = view_columns.iter().position(|c| {
+                c.name
+                    .as_ref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(&name))
+            }) else {
+                bail_parse_error!("table {} has no column named {}", view_name, name);
+            };
+            positions[col_idx] = Some(value_idx);
+        }
+        positions
+    };
+    let expected_values = if columns.is_empty() {
+        view_columns.len()
+    } else {
+        columns.len()
+    };
+
+    let null_rowid_reg = program.alloc_register();
+    program.emit_null(null_rowid_reg, None);
+
+    let emit_new_row =
+        |program: &mut ProgramBuilder, row_start: Option<usize>, next_label: BranchOffset| {
+            let new_start = program.alloc_registers(view_columns.len());
+            for (col_idx, position) in value_positions.iter().enumerate() {
+                match (row_start, position) {
+                    (Some(row_start), Some(value_idx)) => program.emit_insn(Insn::Copy {
+                        src_reg: row_start + value_idx,
+                        dst_reg: new_start + col_idx,
+                        extra_amount: 0,
+                    }),
+                    _ => program.emit_null(new_start + col_idx, None),
+                }
+            }
+            let new_row = TriggerRow {
+                rowid_reg: null_rowid_reg,
+                columns_start: new_start,
+            };
+            emit_trigger_programs(
+                program,
+                &triggers.instead_of,
+                &view_columns,
+                Some(new_row),
+                None,
+                next_label,
+            );
+        };
+
+    match body {
+        InsertBody::Select(select, upsert) => {
+            if upsert.is_some() {
+                bail_parse_error!("UPSERT is not supported for views");
+            }
+            program = emit_instead_of_loop(
+                program,
+                schema,
+                syms,
+                connection,
+                select,
+                |program, row_start, num_cols, next_label| {
+                    if num_cols != expected_values {
+                        if columns.is_empty() {
+                            bail_parse_error!(
+                                "table {} has {} columns but {} values were supplied",
+                                view_name,
+                                view_columns.len(),
+                                num_cols
+                            );
+                        }
+                        bail_parse_error!("{} values for {} columns", num_cols, expected_values);
+                    }
+                    emit_new_row(program, Some(row_start), next_label);
+                    Ok(())
+                },
+            )?;
+        }
+        InsertBody::DefaultValues => {
+            let done_label = program.allocate_label();
+            emit_new_row(&mut program, None, done_label);
+            program.preassign_label_to_next_insn(done_label);
+        }
+    }
+
+    Ok(program)
+}
+
+/// Translates an UPDATE of a view, which runs the INSTEAD OF UPDATE triggers of the view
+/// once for every row matched by the WHERE clause.
+pub fn translate_instead_of_update(
+    schema: &Schema,
+    body: &mut ast::Update,
+    syms: &SymbolTable,
+    mut program: ProgramBuilder,
+    connection: &Arc<Connection>,
+) -> Result<ProgramBuilder> {
+    if body.with.is_some() {
+        bail_parse_error!("WITH clause is not supported in UPDATE");
+    }
+    if body.from.is_some() {
+        bail_parse_error!("FROM clause is not supported in UPDATE of a view");
+    }
+    if !body.returning.is_empty() {
+        bail_parse_error!("RETURNING is not supported in UPDATE of a view");
+    }
+    let view_name = normalize_ident(body.tbl_name.name.as_str());
+    let event = ast::TriggerEvent::Update;
+    let view_columns = view_columns_for_instead_of(schema, &view_name, &event)?;
+
+    // The new value of every view column: the assigned expression, or the column itself
+    let mut new_values: Vec<Box<Expr>> = view_columns.iter().map(column_expr).collect();
+    let mut updated_columns = Vec::with_capacity(body.sets.len());
+    for set in body.sets.iter() {
+        let values = match set.expr.as_ref() {
+            Expr::Parenthesized(values) if set.col_names.len() > 1 => values.clone(),
+            expr => vec![Box::new(expr.clone())],
+        };
+        if set.col_names.len() != values.len() {
+            bail_parse_error!(
+                "{} columns assigned {} values",
+                set.col_names.len(),
+                values.len()
+            );
+        }
+        for (col_name, value) in set.col_names.iter().zip(values) {
+            let col_name = normalize_ident(col_name.as_str());
+            let Some(col_idx) = view_columns.iter().position(|c| {
+                c.name
+                    .as_ref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(&col_name))
+            }) else {
+                bail_parse_error!("no such column: {}", col_name);
+            };
+            new_values[col_idx] = value;
+            updated_columns.push(col_name);
+        }
+    }
+
+    let triggers = compile_triggers(
+        schema,
+        syms,
+        connection,
+        &program,
+        &view_name,
+        &view_columns,
+        event,
+        &updated_columns,
+    )?;
+
+    // SELECT <old columns>, <new values> FROM view WHERE ...
+    let num_columns = view_columns.len();
+    let result_columns = view_columns
+        .iter()
+        .map(column_expr)
+        .chain(new_values)
+        .map(|expr| ast::ResultColumn::Expr(expr, None))
+        .collect();
+    let select = select_from_view(&view_name, result_columns, body.where_clause.take());
+
+    let null_rowid_reg = program.alloc_register();
+    program.emit_null(null_rowid_reg, None);
+
+    program = emit_instead_of_loop(
+        program,
+        schema,
+        syms,
+        connection,
+        select,
+        |program, row_start, _, next_label| {
+            let old_row = TriggerRow {
+                rowid_reg: null_rowid_reg,
+                columns_start: row_start,
+            };
+            let new_row = TriggerRow {
+                rowid_reg: null_rowid_reg,
+                columns_start: row_start + num_columns,
+            };
+            emit_trigger_programs(
+                program,
+                &triggers.instead_of,
+                &view_columns,
+                Some(new_row),
+                Some(old_row),
+                next_label,
+            );
+            Ok(())
+        },
+    )?;
+
+    Ok(program)
+}
+
+/// Translates a DELETE from a view, which runs the INSTEAD OF DELETE triggers of the view
+/// once for every row matched by the WHERE clause.
+pub fn translate_instead_of_delete(
+    schema: &Schema,
+    view_name: &str,
+    where_clause: Option<Box<Expr>>,
+    syms: &SymbolTable,
+    mut program: ProgramBuilder,
+    connection: &Arc<Connection>,
+) -> Result<ProgramBuilder> {
+    let event = ast::TriggerEvent::Delete;
+    let view_columns = view_columns_for_instead_of(schema, view_name, &event)?;
+    let triggers = compile_triggers(
+        schema,
+        syms,
+        connection,
+        &program,
+        view_name,
+        &view_columns,
+        event,
+        &[],
+    )?;
+
+    let result_columns = view_columns
+        .iter()
+        .map(|column| ast::ResultColumn::Expr(column_expr(column), None))
+        .collect();
+    let select = select_from_view(view_name, result_columns, where_clause);
+
+    let null_rowid_reg = program.alloc_register();
+    program.emit_null(null_rowid_reg, None);
+
+    program = emit_instead_of_loop(
+        program,
+        schema,
+        syms,
+        connection,
+        select,
+        |program, row_start, _, next_label| {
+            let old_row = TriggerRow {
+                rowid_reg: null_rowid_reg,
+                columns_start: row_start,
+            };
+            emit_trigger_programs(
+                program,
+                &triggers.instead_of,
+                &view_columns,
+                None,
+                Some(old_row),
+                next_label,
+            );
+            Ok(())
+        },
+    )?;
+
+    Ok(program)
+}
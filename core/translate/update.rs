@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::schema::{BTreeTable, Column, Type};
+use crate::schema::{BTreeTable, Column, Index, Type};
 use crate::translate::optimizer::optimize_select_plan;
 use crate::translate::plan::{Operation, QueryDestination, Scan, Search, SelectPlan};
 use crate::vdbe::builder::CursorType;
@@ -16,13 +16,15 @@ use turso_parser::ast::{self, Expr, Indexed, SortOrder};
 
 use super::emitter::emit_program;
 use super::expr::process_returning_clause;
+use super::foreign_key::{prepare_foreign_keys, ForeignKeyPlan};
 use super::optimizer::optimize_plan;
 use super::plan::{
     ColumnUsedMask, IterationDirection, JoinedTable, Plan, ResultSetColumn, TableReferences,
     UpdatePlan,
 };
 use super::planner::bind_column_references;
-use super::planner::{parse_limit, parse_where};
+use super::planner::{parse_limit, parse_where, resolve_write_target};
+use super::trigger::{compile_triggers, translate_instead_of_update, TriggerPrograms};
 /*
 * Update is simple. By default we scan the table, and for each row, we check the WHERE
 * clause. If it evaluates to true, we build the new record with the updated value and insert.
@@ -53,13 +55,52 @@ addr  opcode         p1    p2    p3    p4             p5  comment
 18    Goto           0     1     0                    0
 */
 pub fn translate_update(
-    schema: &Schema,
     body: &mut ast::Update,
     syms: &SymbolTable,
     mut program: ProgramBuilder,
     connection: &Arc<crate::Connection>,
 ) -> crate::Result<ProgramBuilder> {
+    let (_, target_schema) = resolve_write_target(connection, &body.tbl_name)?;
+    let schema = target_schema.as_ref();
+    if schema.get_view(body.tbl_name.name.as_str()).is_some() {
+        return translate_instead_of_update(schema, body, syms, program, connection);
+    }
     let mut plan = prepare_update_plan(&mut program, schema, body, connection)?;
+    if let Plan::Update(update_plan) = &mut plan {
+        let table = &update_plan.table_references.joined_tables()[0].table;
+        if let Some(btree) = table.btree() {
+            let updated_columns = update_plan
+                .set_clauses
+                .iter()
+                .filter_map(|(idx, _)| table.columns()[*idx].name.clone())
+                .collect::<Vec<_>>();
+            let triggers = compile_triggers(
+                schema,
+                syms,
+                connection,
+                &program,
+                table.get_name(),
+                table.columns(),
+                ast::TriggerEvent::Update,
+                &updated_columns,
+            )?;
+            update_plan.triggers = triggers;
+            let updated_column_indices = update_plan
+                .set_clauses
+                .iter()
+                .map(|(idx, _)| *idx)
+                .collect::<Vec<_>>();
+            update_plan.foreign_keys = prepare_foreign_keys(
+                schema,
+                syms,
+                connection,
+                &program,
+                &btree,
+                ast::TriggerEvent::Update,
+                &updated_column_indices,
+            )?;
+        }
+    }
     optimize_plan(&mut plan, schema)?;
     // TODO: freestyling these numbers
     let opts = ProgramBuilderOpts {
@@ -133,6 +174,7 @@ pub fn prepare_update_plan(
         None => bail_parse_error!("Parse error: no such table: {}", table_name),
     };
     let table_name = table.get_name();
+    let database_id = connection.resolve_database_id(&body.tbl_name)?;
     let iter_dir = body
         .order_by
         .first()
@@ -155,7 +197,7 @@ pub fn prepare_update_plan(
         op: build_scan_op(&table, iter_dir),
         join_info: None,
         col_used_mask: ColumnUsedMask::default(),
-        database_id: 0,
+        database_id,
     }];
     let mut table_references = TableReferences::new(joined_tables, vec![]);
 
@@ -193,6 +235,9 @@ pub fn prepare_update_plan(
                 Some(idx) => idx,
                 None => bail_parse_error!("no such column: {}", ident),
             };
+            if table.columns()[*col_index].generated.is_some() {
+                bail_parse_error!("cannot UPDATE generated column \"{}\"", ident);
+            }
 
             // Update existing entry or add new one
             match set_clauses.iter_mut().find(|(idx, _)| idx == col_index) {
@@ -225,8 +270,12 @@ pub fn prepare_update_plan(
     let rowid_alias_used = set_clauses.iter().fold(false, |accum, (idx, _)| {
         accum || columns[*idx].is_rowid_alias
     });
+    // The rows of a WITHOUT ROWID table are ordered by their PRIMARY KEY, so updating it moves
+    // them, like updating the rowid does.
+    let without_rowid_key_used = table.btree().is_some_and(|btree| !btree.has_rowid)
+        && set_clauses.iter().any(|(idx, _)| columns[*idx].primary_key);
 
-    let (ephemeral_plan, mut where_clause) = if rowid_alias_used {
+    let (ephemeral_plan, mut where_clause) = if rowid_alias_used || without_rowid_key_used {
         let mut where_clause = vec![];
         let internal_id = program.table_reference_counter.next();
 
@@ -241,7 +290,7 @@ pub fn prepare_update_plan(
             op: build_scan_op(&table, iter_dir),
             join_info: None,
             col_used_mask: ColumnUsedMask::default(),
-            database_id: 0,
+            database_id,
         }];
         let mut table_references = TableReferences::new(joined_tables, vec![]);
 
@@ -254,48 +303,109 @@ pub fn prepare_update_plan(
             connection,
         )?;
 
-        let table = Arc::new(BTreeTable {
-            root_page: 0, // Not relevant for ephemeral table definition
-            name: "ephemeral_scratch".to_string(),
-            has_rowid: true,
-            primary_key_columns: vec![],
-            columns: vec![Column {
-                name: Some("rowid".to_string()),
-                ty: Type::Integer,
-                ty_str: "INTEGER".to_string(),
-                primary_key: true,
-                is_rowid_alias: false,
-                notnull: true,
-                default: None,
-                unique: false,
-                collation: None,
-                hidden: false,
-            }],
-            is_strict: false,
-            unique_sets: None,
-        });
+        // The keys of the rows to update are collected first: the PRIMARY KEYs of a WITHOUT ROWID
+        // table into an ephemeral index, the rowids of other tables into an ephemeral table.
+        let (result_columns, query_destination) =
+            match table.btree().filter(|_| without_rowid_key_used) {
+                Some(btree) => {
+                    let mut primary_key = btree
+                        .clustered_index()
+                        .expect("WITHOUT ROWID table must have a PRIMARY KEY")
+                        .columns
+                        .clone();
+                    primary_key.truncate(btree.primary_key_columns.len());
+                    let result_columns = primary_key
+                        .iter()
+                        .map(|column| ResultSetColumn {
+                            expr: Expr::Column {
+                                database: None,
+                                table: internal_id,
+                                column: column.pos_in_table,
+                                is_rowid_alias: false,
+                            },
+                            alias: None,
+                            contains_aggregates: false,
+                        })
+                        .collect();
+                    for column in &primary_key {
+                        table_references.mark_column_used(internal_id, column.pos_in_table);
+                    }
+                    let index = Arc::new(Index {
+                        name: "ephemeral_scratch".to_string(),
+                        table_name: String::new(),
+                        root_page: 0,
+                        columns: primary_key,
+                        unique: false,
+                        ephemeral: true,
+                        has_rowid: false,
+                        where_clause: None,
+                        non_key_columns: 0,
+                        temporary: false,
+                    });
+                    let cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(index.clone()));
+                    (
+                        result_columns,
+                        QueryDestination::EphemeralIndex {
+                            cursor_id,
+                            index,
+                            is_delete: false,
+                        },
+                    )
+                }
+                None => {
+                    let table = Arc::new(BTreeTable {
+                        root_page: 0, // Not relevant for ephemeral table definition
+                        name: "ephemeral_scratch".to_string(),
+                        has_rowid: true,
+                        primary_key_columns: vec![],
+                        columns: vec![Column {
+                            name: Some("rowid".to_string()),
+                            ty: Type::Integer,
+                            ty_str: "INTEGER".to_string(),
+                            primary_key: true,
+                            is_rowid_alias: false,
+                            notnull: true,
+                            default: None,
+                            unique: false,
+                            collation: None,
+                            hidden: false,
+                            generated: None,
+                        }],
+                        is_strict: false,
+                        unique_sets: None,
+                        foreign_keys: vec![],
+                        check_constraints: vec![],
+                        temporary: false,
+                    });
 
-        let temp_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
+                    let temp_cursor_id =
+                        program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
+                    (
+                        vec![ResultSetColumn {
+                            expr: Expr::RowId {
+                                database: None,
+                                table: internal_id,
+                            },
+                            alias: None,
+                            contains_aggregates: false,
+                        }],
+                        QueryDestination::EphemeralTable {
+                            cursor_id: temp_cursor_id,
+                            table,
+                        },
+                    )
+                }
+            };
 
         let mut ephemeral_plan = SelectPlan {
             table_references,
-            result_columns: vec![ResultSetColumn {
-                expr: Expr::RowId {
-                    database: None,
-                    table: internal_id,
-                },
-                alias: None,
-                contains_aggregates: false,
-            }],
+            result_columns,
             where_clause,       // original WHERE terms from the UPDATE clause
             group_by: None,     // N/A
             order_by: vec![],   // N/A
             aggregates: vec![], // N/A
             limit: None,        // N/A
-            query_destination: QueryDestination::EphemeralTable {
-                cursor_id: temp_cursor_id,
-                table,
-            },
+            query_destination,
             join_order: vec![],
             offset: None,
             contains_constant_false_condition: false,
@@ -343,21 +453,26 @@ pub fn prepare_update_plan(
         // If the rowid alias is used in the SET clause, we need to update all indexes
         indexes.to_vec()
     } else {
-        // otherwise we need to update the indexes whose columns are set in the SET clause
+        // otherwise we need to update the indexes whose keys depend on the columns that are set
+        // in the SET clause, including through expressions and the WHERE clause of a partial
+        // index, and the indexes on generated columns, which may depend on them as well
         indexes
             .iter()
             .filter(|index| {
-                index.columns.iter().any(|index_column| {
-                    set_clauses
-                        .iter()
-                        .any(|(set_index_column, _)| index_column.pos_in_table == *set_index_column)
+                table.btree().is_some_and(|btree| {
+                    index.referenced_columns(&btree).into_iter().any(|column| {
+                        columns[column].generated.is_some()
+                            || set_clauses
+                                .iter()
+                                .any(|(set_index_column, _)| column == *set_index_column)
+                    })
                 })
             })
             .cloned()
             .collect()
     };
 
-    Ok(Plan::Update(UpdatePlan {
+    Ok(Plan::Update(Box::new(UpdatePlan {
         table_references,
         set_clauses,
         where_clause,
@@ -373,7 +488,9 @@ pub fn prepare_update_plan(
         indexes_to_update,
         ephemeral_plan,
         cdc_update_alter_statement: None,
-    }))
+        triggers: TriggerPrograms::default(),
+        foreign_keys: ForeignKeyPlan::default(),
+    })))
 }
 
 fn build_scan_op(table: &Table, iter_dir: IterationDirection) -> Operation {
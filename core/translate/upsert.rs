@@ -7,6 +7,7 @@ use crate::{
     error::SQLITE_CONSTRAINT_NOTNULL,
     schema::{Index, IndexColumn, Schema, Table},
     translate::{
+        check::emit_check_constraints,
         emitter::{
             emit_cdc_full_record, emit_cdc_insns, emit_cdc_patch_record, OperationMode, Resolver,
         },
@@ -14,10 +15,16 @@ use crate::{
             emit_returning_results, translate_expr, translate_expr_no_constant_opt,
             NoConstantOptReason, ReturningValueRegisters,
         },
+        generated::{emit_generated_columns, emit_record_registers, emit_table_column},
+        index::{
+            emit_index_column_values, emit_partial_index_check, emit_primary_key,
+            emit_primary_key_change_check,
+        },
         insert::{Insertion, ROWID_COLUMN},
         plan::ResultSetColumn,
+        trigger::TriggerRow,
     },
-    util::normalize_ident,
+    util::{exprs_are_equivalent, normalize_ident},
     vdbe::{
         builder::ProgramBuilder,
         insn::{IdxInsertFlags, InsertFlags, Insn},
@@ -81,6 +88,7 @@ fn effective_collation_for_index_col(idx_col: &IndexColumn, table: &Table) -> St
         .get_column_by_name(&idx_col.name)
         .map(|s| {
             s.1.collation
+                .as_ref()
                 .map(|c| c.to_string().to_ascii_lowercase())
                 .unwrap_or_else(|| "binary".to_string())
         })
@@ -128,12 +136,22 @@ pub fn upsert_matches_index(upsert: &Upsert, index: &Index, table: &Table) -> bo
         return true;
     };
     // if not unique or column count differs, no match
-    if !index.unique || target.targets.len() != index.columns.len() {
+    if !index.unique || target.targets.len() != index.key_columns().len() {
         return false;
     }
+    // a partial index only matches a target with the same WHERE clause
+    if let Some(index_where) = index.where_clause.as_ref() {
+        if !target
+            .where_clause
+            .as_ref()
+            .is_some_and(|target_where| exprs_are_equivalent(target_where, index_where))
+        {
+            return false;
+        }
+    }
 
     let mut need: HashMap<KeySig, usize> = HashMap::new();
-    for ic in &index.columns {
+    for ic in index.key_columns() {
         let sig = KeySig {
             name: normalize_ident(&ic.name).to_string(),
             coll: effective_collation_for_index_col(ic, table),
@@ -200,7 +218,8 @@ pub fn upsert_matches_index(upsert: &Upsert, index: &Index, table: &Table) -> bo
 /// 3. Copy CURRENT into NEW, then evaluate SET expressions into NEW,
 ///    with all references to the target table columns rewritten to read from
 ///    the CURRENT registers (per SQLite semantics).
-/// 4. Enforce NOT NULL constraints and (if STRICT) type checks on NEW.
+/// 4. Compute the generated columns of NEW, then enforce NOT NULL, CHECK and
+///    (if STRICT) type constraints on it.
 /// 5. Rebuild indexes (delete keys using BEFORE, insert keys using NEW).
 /// 6. Rewrite the table row payload at the same rowid with NEW.
 /// 7. Emit CDC rows and RETURNING output if requested.
@@ -224,28 +243,27 @@ pub fn emit_upsert(
     cdc_cursor_id: Option<usize>,
     row_done_label: BranchOffset,
 ) -> crate::Result<()> {
-    // Seek and snapshot current row
-    program.emit_insn(Insn::SeekRowid {
-        cursor_id: tbl_cursor_id,
-        src_reg: conflict_rowid_reg,
-        target_pc: row_done_label,
-    });
+    let btree = table.btree().expect("UPSERT target must be a btree table");
+    // Seek and snapshot current row. The cursor on a WITHOUT ROWID table was already positioned
+    // on the conflicting row by its PRIMARY KEY.
+    if btree.has_rowid {
+        program.emit_insn(Insn::SeekRowid {
+            cursor_id: tbl_cursor_id,
+            src_reg: conflict_rowid_reg,
+            target_pc: row_done_label,
+        });
+    }
     let num_cols = table.columns().len();
     let current_start = program.alloc_registers(num_cols);
-    for (i, col) in table.columns().iter().enumerate() {
-        if col.is_rowid_alias {
-            program.emit_insn(Insn::RowId {
-                cursor_id: tbl_cursor_id,
-                dest: current_start + i,
-            });
-        } else {
-            program.emit_insn(Insn::Column {
-                cursor_id: tbl_cursor_id,
-                column: i,
-                dest: current_start + i,
-                default: None,
-            });
-        }
+    for i in 0..num_cols {
+        emit_table_column(
+            program,
+            resolver,
+            tbl_cursor_id,
+            &btree,
+            i,
+            current_start + i,
+        )?;
     }
 
     // Keep BEFORE snapshot if needed
@@ -317,16 +335,40 @@ pub fn emit_upsert(
         }
     }
 
+    let new_row = TriggerRow {
+        rowid_reg: conflict_rowid_reg,
+        columns_start: new_start,
+    };
+    emit_generated_columns(program, resolver, &btree, new_row)?;
+
     // If STRICT, perform type checks on the NEW image
-    if let Some(bt) = table.btree() {
-        if bt.is_strict {
-            program.emit_insn(Insn::TypeCheck {
-                start_reg: new_start,
-                count: num_cols,
-                check_generated: true,
-                table_reference: Arc::clone(&bt),
-            });
-        }
+    if btree.is_strict {
+        program.emit_insn(Insn::TypeCheck {
+            start_reg: new_start,
+            count: num_cols,
+            check_generated: true,
+            table_reference: Arc::clone(&btree),
+        });
+    }
+
+    emit_check_constraints(program, resolver, &btree, new_row)?;
+
+    // The SET clause may change the PRIMARY KEY of a WITHOUT ROWID table.
+    if !btree.has_rowid {
+        let current_row = TriggerRow {
+            rowid_reg: conflict_rowid_reg,
+            columns_start: current_start,
+        };
+        let old_key = emit_primary_key(program, &btree, current_row);
+        let new_key = emit_primary_key(program, &btree, new_row);
+        emit_primary_key_change_check(
+            program,
+            &btree,
+            tbl_cursor_id,
+            old_key,
+            new_key,
+            row_done_label,
+        );
     }
 
     // Rebuild indexes: remove keys corresponding to BEFORE and insert keys for NEW.
@@ -336,47 +378,63 @@ pub fn emit_upsert(
                 .get_index(table.get_name(), idx_name)
                 .expect("index exists");
             let k = idx_meta.columns.len();
+            // the index record ends with the rowid, if the table has one
+            let num_regs = k + btree.has_rowid as usize;
+            let before_row = TriggerRow {
+                rowid_reg: conflict_rowid_reg,
+                columns_start: before,
+            };
 
-            let del = program.alloc_registers(k + 1);
-            for (i, ic) in idx_meta.columns.iter().enumerate() {
-                let (ci, _) = table.get_column_by_name(&ic.name).unwrap();
+            // partial indexes only contain the rows their WHERE clause is true for
+            let skip_delete_label = program.allocate_label();
+            emit_partial_index_check(
+                program,
+                resolver,
+                &btree,
+                idx_meta,
+                before_row,
+                skip_delete_label,
+            )?;
+            let del = program.alloc_registers(num_regs);
+            emit_index_column_values(program, resolver, &btree, idx_meta, before_row, del)?;
+            if btree.has_rowid {
                 program.emit_insn(Insn::Copy {
-                    src_reg: before + ci,
-                    dst_reg: del + i,
+                    src_reg: conflict_rowid_reg,
+                    dst_reg: del + k,
                     extra_amount: 0,
                 });
             }
-            program.emit_insn(Insn::Copy {
-                src_reg: conflict_rowid_reg,
-                dst_reg: del + k,
-                extra_amount: 0,
-            });
             program.emit_insn(Insn::IdxDelete {
                 start_reg: del,
-                num_regs: k + 1,
+                num_regs,
                 cursor_id: *idx_cid,
                 raise_error_if_no_matching_entry: false,
             });
+            program.preassign_label_to_next_insn(skip_delete_label);
 
-            let ins = program.alloc_registers(k + 1);
-            for (i, ic) in idx_meta.columns.iter().enumerate() {
-                let (ci, _) = table.get_column_by_name(&ic.name).unwrap();
+            let skip_insert_label = program.allocate_label();
+            emit_partial_index_check(
+                program,
+                resolver,
+                &btree,
+                idx_meta,
+                new_row,
+                skip_insert_label,
+            )?;
+            let ins = program.alloc_registers(num_regs);
+            emit_index_column_values(program, resolver, &btree, idx_meta, new_row, ins)?;
+            if btree.has_rowid {
                 program.emit_insn(Insn::Copy {
-                    src_reg: new_start + ci,
-                    dst_reg: ins + i,
+                    src_reg: conflict_rowid_reg,
+                    dst_reg: ins + k,
                     extra_amount: 0,
                 });
             }
-            program.emit_insn(Insn::Copy {
-                src_reg: conflict_rowid_reg,
-                dst_reg: ins + k,
-                extra_amount: 0,
-            });
 
             let rec = program.alloc_register();
             program.emit_insn(Insn::MakeRecord {
                 start_reg: ins,
-                count: k + 1,
+                count: num_regs,
                 dest_reg: rec,
                 index_name: Some((*idx_name).clone()),
             });
@@ -384,27 +442,45 @@ pub fn emit_upsert(
                 cursor_id: *idx_cid,
                 record_reg: rec,
                 unpacked_start: Some(ins),
-                unpacked_count: Some((k + 1) as u16),
+                unpacked_count: Some(num_regs as u16),
                 flags: IdxInsertFlags::new().nchange(true),
             });
+            program.preassign_label_to_next_insn(skip_insert_label);
         }
     }
 
     // Write table row (same rowid, new payload)
     let rec = program.alloc_register();
+    let (record_start_reg, record_count) = emit_record_registers(program, &btree, new_start);
     program.emit_insn(Insn::MakeRecord {
-        start_reg: new_start,
-        count: num_cols,
+        start_reg: record_start_reg,
+        count: record_count,
         dest_reg: rec,
         index_name: None,
     });
-    program.emit_insn(Insn::Insert {
-        cursor: tbl_cursor_id,
-        key_reg: conflict_rowid_reg,
-        record_reg: rec,
-        flag: InsertFlags::new(),
-        table_name: table.get_name().to_string(),
-    });
+    if btree.has_rowid {
+        program.emit_insn(Insn::Insert {
+            cursor: tbl_cursor_id,
+            key_reg: conflict_rowid_reg,
+            record_reg: rec,
+            flag: InsertFlags::new(),
+            table_name: table.get_name().to_string(),
+        });
+    } else {
+        // the PRIMARY KEY of a WITHOUT ROWID table is the key of its B-tree, so the old row is
+        // replaced rather than overwritten
+        program.emit_insn(Insn::Delete {
+            cursor_id: tbl_cursor_id,
+            table_name: table.get_name().to_string(),
+        });
+        program.emit_insn(Insn::IdxInsert {
+            cursor_id: tbl_cursor_id,
+            record_reg: rec,
+            unpacked_start: Some(record_start_reg),
+            unpacked_count: Some(record_count as u16),
+            flags: IdxInsertFlags::new(),
+        });
+    }
 
     if let Some(cdc_id) = cdc_cursor_id {
         let after_rec = if program.capture_data_changes_mode().has_after() {
@@ -492,6 +568,12 @@ pub fn collect_set_clauses_for_upsert(
             let Some(idx) = lookup.get(&normalize_ident(cn.as_str())) else {
                 bail_parse_error!("no such column: {}", cn);
             };
+            if table.columns()[*idx].generated.is_some() {
+                bail_parse_error!(
+                    "cannot UPDATE generated column \"{}\"",
+                    normalize_ident(cn.as_str())
+                );
+            }
             if let Some(existing) = out.iter_mut().find(|(i, _)| *i == *idx) {
                 existing.1 = e;
             } else {
@@ -0,0 +1,63 @@
+use crate::schema::Schema;
+use crate::translate::emitter::Resolver;
+use crate::translate::expr::translate_expr;
+use crate::translate::{ProgramBuilder, ProgramBuilderOpts};
+use crate::util::normalize_ident;
+use crate::vdbe::insn::{Cookie, Insn};
+use crate::{bail_parse_error, Result, SymbolTable};
+use turso_parser::ast::{Expr, Name};
+
+/// Translate VACUUM and VACUUM INTO statements.
+///
+/// VACUUM rewrites the whole database, so it takes a write transaction and reloads the schema
+/// afterwards. VACUUM INTO only reads the database and writes the copy to another file, so a
+/// read transaction is enough and writers are not blocked while the copy is made.
+pub fn translate_vacuum(
+    schema: &Schema,
+    syms: &SymbolTable,
+    name: Option<Name>,
+    into: Option<Box<Expr>>,
+    mut program: ProgramBuilder,
+) -> Result<ProgramBuilder> {
+    if let Some(name) = name {
+        let name = normalize_ident(name.as_str());
+        if name != "main" {
+            bail_parse_error!("VACUUM is only supported for the main database, not {name}");
+        }
+    }
+
+    program.extend(&ProgramBuilderOpts {
+        num_cursors: 0,
+        approx_num_insns: 5,
+        approx_num_labels: 0,
+    });
+
+    let Some(into) = into else {
+        program.emit_insn(Insn::Vacuum {
+            db: 0,
+            dest_reg: None,
+        });
+        program.emit_insn(Insn::ParseSchema {
+            db: 0,
+            where_clause: None,
+        });
+        program.emit_insn(Insn::SetCookie {
+            db: 0,
+            cookie: Cookie::SchemaVersion,
+            value: (schema.schema_version + 1) as i32,
+            p5: 0,
+        });
+        program.begin_write_operation();
+        return Ok(program);
+    };
+
+    let dest_reg = program.alloc_register();
+    let resolver = Resolver::new(schema, syms);
+    translate_expr(&mut program, None, &into, dest_reg, &resolver)?;
+    program.emit_insn(Insn::Vacuum {
+        db: 0,
+        dest_reg: Some(dest_reg),
+    });
+    program.begin_read_operation();
+    Ok(program)
+}
@@ -1,14 +1,21 @@
+use crate::incremental::persistence::{state_table_name, state_table_sql};
 use crate::schema::Schema;
+use crate::schema::TEMP_SCHEMA_TABLE_NAME;
+use crate::storage::pager::CreateBTreeFlags;
 use crate::translate::emitter::Resolver;
-use crate::translate::schema::{emit_schema_entry, SchemaEntryType, SQLITE_TABLEID};
+use crate::translate::schema::{
+    emit_schema_entry, is_temp_database_object, open_schema_table, resolve_schema_target,
+    SchemaEntryType, SQLITE_TABLEID,
+};
 use crate::util::normalize_ident;
-use crate::vdbe::builder::{CursorType, ProgramBuilder};
+use crate::vdbe::builder::ProgramBuilder;
 use crate::vdbe::insn::{CmpInsFlags, Cookie, Insn};
 use crate::{Connection, Result, SymbolTable};
 use std::sync::Arc;
 use turso_parser::ast;
 
 /// Common logic for creating views (both regular and materialized)
+#[allow(clippy::too_many_arguments)]
 fn emit_create_view_program(
     schema: &Schema,
     view_name: &str,
@@ -16,20 +23,38 @@ fn emit_create_view_program(
     syms: &SymbolTable,
     program: &mut ProgramBuilder,
     populate_materialized: bool,
+    create_state_table: bool,
+    temporary: bool,
 ) -> Result<()> {
     let normalized_view_name = normalize_ident(view_name);
 
-    // Open cursor to sqlite_schema table
-    let table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
-    let sqlite_schema_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
-    program.emit_insn(Insn::OpenWrite {
-        cursor_id: sqlite_schema_cursor_id,
-        root_page: 1usize.into(),
-        db: 0,
-    });
+    // Open cursor to sqlite_schema table, or to sqlite_temp_schema for TEMP views
+    let sqlite_schema_cursor_id = open_schema_table(program, schema, temporary as usize);
+    let resolver = Resolver::new(schema, syms);
+
+    // Create the table holding the state of a materialized view
+    let state_table = state_table_name(&normalized_view_name);
+    if create_state_table {
+        let root_reg = program.alloc_register();
+        program.emit_insn(Insn::CreateBtree {
+            db: 0,
+            root: root_reg,
+            flags: CreateBTreeFlags::new_table(),
+        });
+        emit_schema_entry(
+            program,
+            &resolver,
+            sqlite_schema_cursor_id,
+            None,
+            SchemaEntryType::Table,
+            &state_table,
+            &state_table,
+            root_reg,
+            Some(state_table_sql(&normalized_view_name)),
+        )?;
+    }
 
     // Add the view entry to sqlite_schema
-    let resolver = Resolver::new(schema, syms);
     emit_schema_entry(
         program,
         &resolver,
@@ -42,18 +67,25 @@ fn emit_create_view_program(
         Some(sql),
     )?;
 
-    // Parse schema to load the new view
+    // Parse schema to load the new view, and its state table
+    let where_clause = if create_state_table {
+        format!("name = '{normalized_view_name}' OR name = '{state_table}'")
+    } else {
+        format!("name = '{normalized_view_name}'")
+    };
     program.emit_insn(Insn::ParseSchema {
-        db: sqlite_schema_cursor_id,
-        where_clause: Some(format!("name = '{normalized_view_name}'")),
+        db: temporary as usize,
+        where_clause: Some(where_clause),
     });
 
-    program.emit_insn(Insn::SetCookie {
-        db: 0,
-        cookie: Cookie::SchemaVersion,
-        value: (schema.schema_version + 1) as i32,
-        p5: 0,
-    });
+    if !temporary {
+        program.emit_insn(Insn::SetCookie {
+            db: 0,
+            cookie: Cookie::SchemaVersion,
+            value: (schema.schema_version + 1) as i32,
+            p5: 0,
+        });
+    }
 
     // Populate materialized views if needed
     // Note: This must come after SetCookie since it may do I/O operations
@@ -98,11 +130,29 @@ pub fn translate_create_materialized_view(
     use crate::incremental::view::IncrementalView;
     IncrementalView::can_create_view(select_stmt)?;
 
+    // In MVCC mode the view is kept in memory only, so it has no state table
+    let create_state_table = connection._db.mv_store.is_none();
+    let state_table = state_table_name(&normalized_view_name);
+    if create_state_table && schema.get_table(&state_table).is_some() {
+        return Err(crate::LimboError::ParseError(format!(
+            "table {state_table} already exists"
+        )));
+    }
+
     // Reconstruct the SQL string
     let sql = create_materialized_view_to_str(view_name, select_stmt);
 
     // Use common logic to emit the view creation program
-    emit_create_view_program(schema, view_name, sql, syms, &mut program, true)?;
+    emit_create_view_program(
+        schema,
+        view_name,
+        sql,
+        syms,
+        &mut program,
+        true,
+        create_state_table,
+        false,
+    )?;
 
     program.epilogue(schema);
     Ok(program)
@@ -112,20 +162,36 @@ fn create_materialized_view_to_str(view_name: &str, select_stmt: &ast::Select) -
     format!("CREATE MATERIALIZED VIEW {view_name} AS {select_stmt}")
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn translate_create_view(
     schema: &Schema,
-    view_name: &str,
+    view_name: &ast::QualifiedName,
+    temporary: bool,
     select_stmt: &ast::Select,
     _columns: &[ast::IndexedColumn],
-    _connection: Arc<Connection>,
+    connection: Arc<Connection>,
     syms: &SymbolTable,
     mut program: ProgramBuilder,
 ) -> Result<ProgramBuilder> {
+    let temporary = is_temp_database_object(temporary, view_name, "view")?;
+    if temporary && connection._db.mv_store.is_some() {
+        return Err(crate::LimboError::ParseError(
+            "TEMPORARY views are not supported with MVCC".to_string(),
+        ));
+    }
+    if !temporary && resolve_schema_target(&connection, view_name)?.0 > 1 {
+        return Err(crate::LimboError::ParseError(
+            "views on attached databases are not supported yet".to_string(),
+        ));
+    }
+    let view_name = view_name.name.as_str();
     let normalized_view_name = normalize_ident(view_name);
 
-    // Check if view already exists
-    if schema.get_view(&normalized_view_name).is_some()
-        || schema
+    // Check if view already exists in the database it goes into, a TEMP view may have the name
+    // of a view of the main database
+    let target_schema = connection.database_schema(temporary as usize);
+    if target_schema.get_view(&normalized_view_name).is_some()
+        || target_schema
             .get_materialized_view(&normalized_view_name)
             .is_some()
     {
@@ -138,7 +204,16 @@ pub fn translate_create_view(
     let sql = create_view_to_str(view_name, select_stmt);
 
     // Use common logic to emit the view creation program
-    emit_create_view_program(schema, view_name, sql, syms, &mut program, false)?;
+    emit_create_view_program(
+        schema,
+        view_name,
+        sql,
+        syms,
+        &mut program,
+        false,
+        false,
+        temporary,
+    )?;
 
     Ok(program)
 }
@@ -172,15 +247,17 @@ pub fn translate_drop_view(
         return Ok(program);
     }
 
-    // Open cursor to sqlite_schema table
-    let schema_table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
-    let sqlite_schema_cursor_id =
-        program.alloc_cursor_id(CursorType::BTreeTable(schema_table.clone()));
-    program.emit_insn(Insn::OpenWrite {
-        cursor_id: sqlite_schema_cursor_id,
-        root_page: 1usize.into(),
-        db: 0,
-    });
+    let temporary = schema
+        .get_view(&normalized_view_name)
+        .is_some_and(|view| view.temporary);
+
+    // The state table of a materialized view is dropped along with it
+    let state_table = schema
+        .get_materialized_view(&normalized_view_name)
+        .and_then(|_| schema.get_btree_table(&state_table_name(&normalized_view_name)));
+
+    // Open cursor to sqlite_schema table, or to sqlite_temp_schema for TEMP views
+    let sqlite_schema_cursor_id = open_schema_table(&mut program, schema, temporary as usize);
 
     // Allocate registers for searching
     let view_name_reg = program.alloc_register();
@@ -196,6 +273,11 @@ pub fn translate_drop_view(
         dest: type_reg,
         value: "view".to_string(),
     });
+    let state_table_regs = state_table.as_ref().map(|table| {
+        let table_type_reg = program.emit_string8_new_reg("table".to_string());
+        let table_name_reg = program.emit_string8_new_reg(table.name.clone());
+        (table_type_reg, table_name_reg)
+    });
 
     // Start scanning from the beginning
     let end_loop_label = program.allocate_label();
@@ -217,21 +299,50 @@ pub fn translate_drop_view(
 
     // Check if type == 'view' and name == view_name
     let skip_delete_label = program.allocate_label();
+    let not_view_label = if state_table_regs.is_some() {
+        program.allocate_label()
+    } else {
+        skip_delete_label
+    };
     program.emit_insn(Insn::Ne {
         lhs: col0_reg,
         rhs: type_reg,
-        target_pc: skip_delete_label,
+        target_pc: not_view_label,
         flags: CmpInsFlags::default(),
         collation: program.curr_collation(),
     });
     program.emit_insn(Insn::Ne {
         lhs: col1_reg,
         rhs: view_name_reg,
-        target_pc: skip_delete_label,
+        target_pc: not_view_label,
         flags: CmpInsFlags::default(),
         collation: program.curr_collation(),
     });
 
+    // Otherwise check if type == 'table' and name is the view's state table
+    if let Some((table_type_reg, table_name_reg)) = state_table_regs {
+        let delete_label = program.allocate_label();
+        program.emit_insn(Insn::Goto {
+            target_pc: delete_label,
+        });
+        program.preassign_label_to_next_insn(not_view_label);
+        program.emit_insn(Insn::Ne {
+            lhs: col0_reg,
+            rhs: table_type_reg,
+            target_pc: skip_delete_label,
+            flags: CmpInsFlags::default(),
+            collation: program.curr_collation(),
+        });
+        program.emit_insn(Insn::Ne {
+            lhs: col1_reg,
+            rhs: table_name_reg,
+            target_pc: skip_delete_label,
+            flags: CmpInsFlags::default(),
+            collation: program.curr_collation(),
+        });
+        program.preassign_label_to_next_insn(delete_label);
+    }
+
     // Get the rowid and delete this row
     program.emit_insn(Insn::RowId {
         cursor_id: sqlite_schema_cursor_id,
@@ -239,7 +350,12 @@ pub fn translate_drop_view(
     });
     program.emit_insn(Insn::Delete {
         cursor_id: sqlite_schema_cursor_id,
-        table_name: "sqlite_schema".to_string(),
+        table_name: if temporary {
+            TEMP_SCHEMA_TABLE_NAME
+        } else {
+            SQLITE_TABLEID
+        }
+        .to_string(),
     });
 
     program.resolve_label(skip_delete_label, program.offset());
@@ -252,24 +368,40 @@ pub fn translate_drop_view(
 
     program.preassign_label_to_next_insn(end_loop_label);
 
+    if let Some(table) = &state_table {
+        program.emit_insn(Insn::Destroy {
+            root: table.root_page,
+            former_root_reg: 0, //  no autovacuum (https://www.sqlite.org/opcode.html#Destroy)
+            is_temp: 0,
+        });
+        program.emit_insn(Insn::DropTable {
+            db: 0,
+            _p2: 0,
+            _p3: 0,
+            table_name: table.name.clone(),
+        });
+    }
+
     // Remove the view from the in-memory schema
     program.emit_insn(Insn::DropView {
-        db: 0,
+        db: temporary as usize,
         view_name: normalized_view_name.clone(),
     });
 
     // Update schema version (increment schema cookie)
-    let schema_version_reg = program.alloc_register();
-    program.emit_insn(Insn::Integer {
-        dest: schema_version_reg,
-        value: (schema.schema_version + 1) as i64,
-    });
-    program.emit_insn(Insn::SetCookie {
-        db: 0,
-        cookie: Cookie::SchemaVersion,
-        value: (schema.schema_version + 1) as i32,
-        p5: 1, // update version
-    });
+    if !temporary {
+        let schema_version_reg = program.alloc_register();
+        program.emit_insn(Insn::Integer {
+            dest: schema_version_reg,
+            value: (schema.schema_version + 1) as i64,
+        });
+        program.emit_insn(Insn::SetCookie {
+            db: 0,
+            cookie: Cookie::SchemaVersion,
+            value: (schema.schema_version + 1) as i32,
+            p5: 1, // update version
+        });
+    }
 
     program.epilogue(schema);
     Ok(program)
@@ -0,0 +1,2167 @@
+//! Window functions.
+//!
+//! A SELECT with window functions is planned as a query over a derived table, the "window
+//! table". The window table reads the rows of the SELECT without its window functions (the
+//! input subquery) and computes the window functions for each of them. The outer query then
+//! computes the result columns, ORDER BY, DISTINCT and LIMIT from the window function results
+//! and the columns of the input subquery. For example,
+//!
+//! `SELECT a, b - lag(b) OVER (ORDER BY a) FROM t`
+//!
+//! runs roughly as
+//!
+//! `SELECT c1, c2 - c0 FROM (SELECT lag(b) OVER (ORDER BY a) AS c0, a AS c1, b AS c2 FROM t)`.
+//!
+//! The window table sorts its rows once per distinct PARTITION BY and ORDER BY combination.
+//! The rows of each partition are stored in ephemeral tables keyed by their position in the
+//! sort order, so that the frame of a row can be visited with rowid seeks.
+
+use std::sync::Arc;
+
+use turso_parser::ast::{self, Expr, ResultColumn, SortOrder, TableInternalId};
+
+use crate::{
+    error::SQLITE_ERROR,
+    function::{AggFunc, Func, WindowFunc},
+    schema::{BTreeTable, Column, FromClauseSubquery, PseudoCursorType, Schema, Table, Type},
+    translate::{
+        aggregation::{translate_aggregation_step, AggArgumentSource},
+        collate::CollationSeq,
+        emitter::TranslateCtx,
+        expr::{walk_expr, walk_expr_mut, WalkControl},
+        plan::{
+            Aggregate, Distinctness, JoinOrderMember, JoinedTable, OuterQueryReference, Plan,
+            QueryDestination, ResultSetColumn, SelectPlan, TableReferences, WindowFrame,
+            WindowFrameBound, WindowFunction, WindowFunctionKind, WindowPartitioning, WindowPlan,
+        },
+        planner::parse_limit,
+        select::{prepare_select_plan, replace_column_number_with_copy_of_column_expr},
+        subquery::emit_subquery,
+    },
+    types::Value,
+    util::{exprs_are_equivalent, normalize_ident},
+    vdbe::{
+        builder::{CursorType, ProgramBuilder, TableRefIdCounter},
+        insn::{CmpInsFlags, InsertFlags, Insn},
+        BranchOffset, CursorID,
+    },
+    Connection, Result, SymbolTable,
+};
+
+/// Returns the OVER clause of `expr` if it is a window function call.
+fn over_clause(expr: &Expr) -> Option<&ast::Over> {
+    match expr {
+        Expr::FunctionCall { filter_over, .. } | Expr::FunctionCallStar { filter_over, .. } => {
+            filter_over.over_clause.as_ref()
+        }
+        _ => None,
+    }
+}
+
+fn contains_window_function(expr: &Expr) -> Result<bool> {
+    let mut found = false;
+    walk_expr(expr, &mut |expr: &Expr| -> Result<WalkControl> {
+        if over_clause(expr).is_some() {
+            found = true;
+            return Ok(WalkControl::SkipChildren);
+        }
+        Ok(WalkControl::Continue)
+    })?;
+    Ok(found)
+}
+
+/// Whether the result columns or the ORDER BY clause of a SELECT call window functions.
+pub fn select_has_window_functions(
+    columns: &[ResultColumn],
+    order_by: &[ast::SortedColumn],
+) -> Result<bool> {
+    for column in columns {
+        if let ResultColumn::Expr(expr, _) = column {
+            if contains_window_function(expr)? {
+                return Ok(true);
+            }
+        }
+    }
+    for column in order_by {
+        if contains_window_function(&column.expr)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Collects the window functions of a SELECT and the columns the input subquery must produce
+/// for them and for the outer query.
+struct WindowPlanner<'a> {
+    window_clause: &'a [ast::WindowDef],
+    syms: &'a SymbolTable,
+    window_table_id: TableInternalId,
+    /// The window function calls, in the order of [WindowPlan::functions].
+    calls: Vec<Expr>,
+    functions: Vec<WindowFunction>,
+    partitionings: Vec<WindowPartitioning>,
+    /// The result columns of the input subquery.
+    input_columns: Vec<Expr>,
+}
+
+impl WindowPlanner<'_> {
+    fn add_input_column(&mut self, expr: &Expr) -> usize {
+        if let Some(idx) = self
+            .input_columns
+            .iter()
+            .position(|column| exprs_are_equivalent(column, expr))
+        {
+            return idx;
+        }
+        self.input_columns.push(expr.clone());
+        self.input_columns.len() - 1
+    }
+
+    /// Registers the window function calls in `expr`.
+    fn add_window_functions(&mut self, expr: &Expr) -> Result<()> {
+        walk_expr(expr, &mut |expr: &Expr| -> Result<WalkControl> {
+            if over_clause(expr).is_none() {
+                return Ok(WalkControl::Continue);
+            }
+            if !self
+                .calls
+                .iter()
+                .any(|call| exprs_are_equivalent(call, expr))
+            {
+                let function = self.resolve_window_function(expr)?;
+                self.functions.push(function);
+                self.calls.push(expr.clone());
+            }
+            Ok(WalkControl::SkipChildren)
+        })?;
+        Ok(())
+    }
+
+    fn resolve_window_function(&mut self, expr: &Expr) -> Result<WindowFunction> {
+        let (name, args, distinctness, order_by, filter_over) = match expr {
+            Expr::FunctionCall {
+                name,
+                args,
+                distinctness,
+                order_by,
+                filter_over,
+            } => (
+                name,
+                args.as_slice(),
+                distinctness.as_ref(),
+                order_by.as_slice(),
+                filter_over,
+            ),
+            Expr::FunctionCallStar { name, filter_over } => {
+                (name, &[][..], None, &[][..], filter_over)
+            }
+            _ => unreachable!("window function call must be a function call"),
+        };
+        if distinctness.is_some() {
+            crate::bail_parse_error!("DISTINCT is not supported for window functions");
+        }
+        if !order_by.is_empty() {
+            crate::bail_parse_error!("ORDER BY clause is not supported yet in aggregate functions");
+        }
+        let func = match WindowFunc::resolve(name.as_str(), args.len())? {
+            Some(func) => {
+                if filter_over.filter_clause.is_some() {
+                    crate::bail_parse_error!(
+                        "FILTER clause may only be used with aggregate window functions"
+                    );
+                }
+                WindowFunctionKind::Builtin(func)
+            }
+            None => match Func::resolve_function(name.as_str(), args.len()) {
+                Ok(Func::Agg(func)) => WindowFunctionKind::Aggregate(func),
+                _ => match self.syms.resolve_function(name.as_str(), args.len()) {
+                    Some(f) if !f.func.is_scalar() => {
+                        WindowFunctionKind::Aggregate(AggFunc::External(f.func.clone().into()))
+                    }
+                    _ => crate::bail_parse_error!(
+                        "{}() may not be used as a window function",
+                        name.as_str()
+                    ),
+                },
+            },
+        };
+        let args = args.iter().map(|arg| self.add_input_column(arg)).collect();
+        let filter = filter_over
+            .filter_clause
+            .as_ref()
+            .map(|filter| self.add_input_column(filter));
+        let over = filter_over
+            .over_clause
+            .as_ref()
+            .expect("window function call must have an OVER clause");
+        let window = match over {
+            ast::Over::Window(window) => self.resolve_base_window(window, 0)?,
+            ast::Over::Name(name) => {
+                let window = &self.find_window(name)?.window;
+                self.resolve_base_window(window, 0)?
+            }
+        };
+        let frame = resolve_frame(&window)?;
+
+        let partitioning = WindowPartitioning {
+            partition_by: window
+                .partition_by
+                .iter()
+                .map(|expr| self.add_input_column(expr))
+                .collect(),
+            order_by: window
+                .order_by
+                .iter()
+                .map(|column| {
+                    (
+                        self.add_input_column(&column.expr),
+                        column.order.unwrap_or(SortOrder::Asc),
+                    )
+                })
+                .collect(),
+        };
+        let partitioning = match self.partitionings.iter().position(|p| *p == partitioning) {
+            Some(idx) => idx,
+            None => {
+                self.partitionings.push(partitioning);
+                self.partitionings.len() - 1
+            }
+        };
+
+        Ok(WindowFunction {
+            func,
+            args,
+            filter,
+            partitioning,
+            frame,
+            original_expr: expr.clone(),
+        })
+    }
+
+    fn find_window(&self, name: &ast::Name) -> Result<&ast::WindowDef> {
+        let normalized_name = normalize_ident(name.as_str());
+        let Some(window_def) = self
+            .window_clause
+            .iter()
+            .find(|window_def| normalize_ident(window_def.name.as_str()) == normalized_name)
+        else {
+            crate::bail_parse_error!("no such window: {}", name.as_str());
+        };
+        Ok(window_def)
+    }
+
+    /// Merges a window with the named window it is based on, if any.
+    fn resolve_base_window(&self, window: &ast::Window, depth: usize) -> Result<ast::Window> {
+        let Some(base_name) = &window.base else {
+            return Ok(window.clone());
+        };
+        if depth > self.window_clause.len() {
+            crate::bail_parse_error!("no such window: {}", base_name.as_str());
+        }
+        let base = self.resolve_base_window(&self.find_window(base_name)?.window, depth + 1)?;
+        if !window.partition_by.is_empty() {
+            crate::bail_parse_error!(
+                "cannot override PARTITION clause of window: {}",
+                base_name.as_str()
+            );
+        }
+        if !window.order_by.is_empty() && !base.order_by.is_empty() {
+            crate::bail_parse_error!(
+                "cannot override ORDER BY clause of window: {}",
+                base_name.as_str()
+            );
+        }
+        if base.frame_clause.is_some() {
+            crate::bail_parse_error!(
+                "cannot override frame specification of window: {}",
+                base_name.as_str()
+            );
+        }
+        Ok(ast::Window {
+            base: None,
+            partition_by: base.partition_by,
+            order_by: if window.order_by.is_empty() {
+                base.order_by
+            } else {
+                window.order_by.clone()
+            },
+            frame_clause: window.frame_clause.clone(),
+        })
+    }
+
+    fn window_column(&self, idx: usize) -> Expr {
+        Expr::Column {
+            database: None,
+            table: self.window_table_id,
+            column: idx,
+            is_rowid_alias: false,
+        }
+    }
+
+    /// Rewrites an expression of the outer query so that it reads from the window table:
+    /// window function calls become references to their results, and the largest
+    /// subexpressions without window functions become references to columns of the input
+    /// subquery.
+    fn rewrite(&mut self, expr: &mut Expr) -> Result<()> {
+        walk_expr_mut(expr, &mut |expr: &mut Expr| -> Result<()> {
+            if over_clause(expr).is_some() {
+                let idx = self
+                    .calls
+                    .iter()
+                    .position(|call| exprs_are_equivalent(call, expr))
+                    .expect("window function call must have been registered");
+                *expr = self.window_column(idx);
+            } else if !matches!(expr, Expr::Literal(_) | Expr::Column { .. })
+                && !contains_window_function(expr)?
+            {
+                let idx = self.calls.len() + self.add_input_column(expr);
+                *expr = self.window_column(idx);
+            }
+            Ok(())
+        })
+    }
+}
+
+fn resolve_frame(window: &ast::Window) -> Result<WindowFrame> {
+    let Some(frame_clause) = &window.frame_clause else {
+        return Ok(WindowFrame::default());
+    };
+    let mode = frame_clause.mode;
+    let bound = |bound: &ast::FrameBound, is_start: bool| -> Result<WindowFrameBound> {
+        Ok(match bound {
+            ast::FrameBound::UnboundedPreceding => WindowFrameBound::UnboundedPreceding,
+            ast::FrameBound::Preceding(offset) => {
+                WindowFrameBound::Preceding(frame_offset(offset, mode, is_start)?)
+            }
+            ast::FrameBound::CurrentRow => WindowFrameBound::CurrentRow,
+            ast::FrameBound::Following(offset) => {
+                WindowFrameBound::Following(frame_offset(offset, mode, is_start)?)
+            }
+            ast::FrameBound::UnboundedFollowing => WindowFrameBound::UnboundedFollowing,
+        })
+    };
+    let start = bound(&frame_clause.start, true)?;
+    let end = match &frame_clause.end {
+        Some(end) => bound(end, false)?,
+        None => WindowFrameBound::CurrentRow,
+    };
+    let invalid = matches!(
+        (&start, &end),
+        (WindowFrameBound::UnboundedFollowing, _)
+            | (_, WindowFrameBound::UnboundedPreceding)
+            | (WindowFrameBound::CurrentRow, WindowFrameBound::Preceding(_))
+            | (
+                WindowFrameBound::Following(_),
+                WindowFrameBound::Preceding(_) | WindowFrameBound::CurrentRow,
+            )
+    );
+    if invalid {
+        crate::bail_parse_error!("unsupported frame specification");
+    }
+    let has_offset = |bound: &WindowFrameBound| {
+        matches!(
+            bound,
+            WindowFrameBound::Preceding(_) | WindowFrameBound::Following(_)
+        )
+    };
+    if mode == ast::FrameMode::Range
+        && (has_offset(&start) || has_offset(&end))
+        && window.order_by.len() != 1
+    {
+        crate::bail_parse_error!(
+            "RANGE with offset PRECEDING/FOLLOWING requires one ORDER BY expression"
+        );
+    }
+    Ok(WindowFrame {
+        mode,
+        start,
+        end,
+        exclude: frame_clause
+            .exclude
+            .clone()
+            .unwrap_or(ast::FrameExclude::NoOthers),
+    })
+}
+
+fn frame_offset(expr: &Expr, mode: ast::FrameMode, is_start: bool) -> Result<Value> {
+    let which = if is_start { "starting" } else { "ending" };
+    let value = match expr {
+        Expr::Literal(ast::Literal::Numeric(n)) => match n.parse::<i64>() {
+            Ok(i) => Some(Value::Integer(i)),
+            Err(_) => n.parse::<f64>().ok().map(Value::Float),
+        },
+        _ => None,
+    };
+    match (mode, value) {
+        (_, Some(Value::Integer(i))) if i >= 0 => Ok(Value::Integer(i)),
+        (ast::FrameMode::Range, Some(Value::Float(f))) if f >= 0.0 => Ok(Value::Float(f)),
+        (ast::FrameMode::Range, _) => {
+            crate::bail_parse_error!("frame {which} offset must be a non-negative number")
+        }
+        _ => crate::bail_parse_error!("frame {which} offset must be a non-negative integer"),
+    }
+}
+
+fn unnamed_column() -> Column {
+    Column {
+        name: None,
+        ty: Type::Blob,
+        ty_str: "BLOB".to_string(),
+        is_rowid_alias: false,
+        primary_key: false,
+        notnull: false,
+        default: None,
+        unique: false,
+        collation: None,
+        hidden: false,
+        generated: None,
+    }
+}
+
+/// How an ORDER BY term of a SELECT with window functions is resolved once the result columns
+/// of the outer query are known.
+enum OrderByTerm {
+    /// A reference to a result column alias.
+    Alias(String),
+    /// A result column number, or an expression over the window table.
+    Expr(Box<Expr>),
+}
+
+/// Plans a SELECT whose result columns or ORDER BY clause call window functions.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_window_select_plan(
+    schema: &Schema,
+    select: ast::OneSelect,
+    limit: Option<ast::Limit>,
+    order_by: Vec<ast::SortedColumn>,
+    with: Option<ast::With>,
+    syms: &SymbolTable,
+    outer_query_refs: &[OuterQueryReference],
+    table_ref_counter: &mut TableRefIdCounter,
+    query_destination: QueryDestination,
+    connection: &Arc<Connection>,
+) -> Result<SelectPlan> {
+    let ast::OneSelect::Select {
+        distinctness,
+        columns,
+        from,
+        where_clause,
+        group_by,
+        window_clause,
+    } = select
+    else {
+        unreachable!("VALUES cannot contain window functions");
+    };
+    if !schema.indexes_enabled() && distinctness.is_some() {
+        crate::bail_parse_error!("SELECT with DISTINCT is not allowed without indexes enabled");
+    }
+
+    let mut planner = WindowPlanner {
+        window_clause: &window_clause,
+        syms,
+        window_table_id: table_ref_counter.next(),
+        calls: vec![],
+        functions: vec![],
+        partitionings: vec![],
+        input_columns: vec![],
+    };
+
+    // Result column aliases; ORDER BY terms naming them refer to the result columns.
+    let aliases: Vec<String> = columns
+        .iter()
+        .filter_map(|column| match column {
+            ResultColumn::Expr(_, Some(ast::As::As(alias) | ast::As::Elided(alias))) => {
+                Some(normalize_ident(alias.as_str()))
+            }
+            _ => None,
+        })
+        .collect();
+    let is_alias = |expr: &Expr| match expr {
+        Expr::Id(id) => aliases.contains(&normalize_ident(id.as_str())),
+        _ => false,
+    };
+
+    for column in columns.iter() {
+        if let ResultColumn::Expr(expr, _) = column {
+            planner.add_window_functions(expr)?;
+        }
+    }
+    for column in order_by.iter() {
+        if !is_alias(&column.expr) {
+            planner.add_window_functions(&column.expr)?;
+        }
+    }
+
+    // Rewrite the result columns and the ORDER BY terms to read from the window table. The
+    // columns of the tables selected with `*` are the last columns of the input subquery,
+    // they are only known once it is planned.
+    let mut rewritten_columns = Vec::with_capacity(columns.len());
+    let mut stars = vec![];
+    for column in columns {
+        match column {
+            ResultColumn::Expr(expr, alias) => {
+                let alias = match alias {
+                    Some(ast::As::As(alias) | ast::As::Elided(alias)) => {
+                        Some(alias.as_str().to_string())
+                    }
+                    None => match expr.as_ref() {
+                        Expr::Id(_) | Expr::Qualified(..) | Expr::DoublyQualified(..) => None,
+                        expr => Some(expr.to_string()),
+                    },
+                };
+                let mut expr = *expr;
+                planner.rewrite(&mut expr)?;
+                rewritten_columns.push(Some((expr, alias)));
+            }
+            star => {
+                rewritten_columns.push(None);
+                stars.push(star);
+            }
+        }
+    }
+    let mut order_by_terms = Vec::with_capacity(order_by.len());
+    for column in order_by {
+        let order = column.order.unwrap_or(SortOrder::Asc);
+        let term = match *column.expr {
+            Expr::Id(id) if aliases.contains(&normalize_ident(id.as_str())) => {
+                OrderByTerm::Alias(id.as_str().to_string())
+            }
+            mut expr => {
+                planner.rewrite(&mut expr)?;
+                OrderByTerm::Expr(Box::new(expr))
+            }
+        };
+        order_by_terms.push((term, order));
+    }
+
+    let num_functions = planner.calls.len();
+    let num_input_expressions = planner.input_columns.len();
+    let mut input_columns: Vec<ResultColumn> = planner
+        .input_columns
+        .drain(..)
+        .map(|expr| ResultColumn::Expr(Box::new(expr), None))
+        .collect();
+    if input_columns.is_empty() && stars.is_empty() {
+        input_columns.push(ResultColumn::Expr(
+            Box::new(Expr::Literal(ast::Literal::Numeric("1".to_string()))),
+            None,
+        ));
+    }
+    input_columns.extend(stars.iter().cloned());
+
+    let input_select = ast::Select {
+        with,
+        body: ast::SelectBody {
+            select: ast::OneSelect::Select {
+                distinctness: None,
+                columns: input_columns,
+                from,
+                where_clause,
+                group_by,
+                window_clause: vec![],
+            },
+            compounds: vec![],
+        },
+        order_by: vec![],
+        limit: None,
+    };
+    let Plan::Select(input_plan) = prepare_select_plan(
+        schema,
+        input_select,
+        syms,
+        outer_query_refs,
+        table_ref_counter,
+        QueryDestination::CoroutineYield {
+            yield_reg: usize::MAX, // will be set later in bytecode emission
+            coroutine_implementation_start: BranchOffset::Placeholder, // will be set later in bytecode emission
+        },
+        connection,
+    )?
+    else {
+        unreachable!("the input of a window must be a simple SELECT");
+    };
+
+    // Expand the stars into the columns they selected in the input subquery.
+    let mut star_columns = vec![];
+    let mut star_start = num_functions + num_input_expressions;
+    for star in stars.iter() {
+        let num_columns = match star {
+            ResultColumn::Star => input_plan
+                .table_references
+                .joined_tables()
+                .iter()
+                .map(|t| t.columns().iter().filter(|col| !col.hidden).count())
+                .sum(),
+            ResultColumn::TableStar(name) => {
+                let name_normalized = normalize_ident(name.as_str());
+                input_plan
+                    .table_references
+                    .joined_tables()
+                    .iter()
+                    .find(|t| t.identifier == name_normalized)
+                    .map(|t| t.columns().iter().filter(|col| !col.hidden).count())
+                    .unwrap_or(0)
+            }
+            ResultColumn::Expr(..) => unreachable!(),
+        };
+        star_columns.push(star_start..star_start + num_columns);
+        star_start += num_columns;
+    }
+
+    let mut window_table = JoinedTable::new_subquery(
+        "window".to_string(),
+        input_plan,
+        None,
+        planner.window_table_id,
+    );
+    let Table::FromClauseSubquery(FromClauseSubquery {
+        columns: window_columns,
+        window,
+        ..
+    }) = &mut window_table.table
+    else {
+        unreachable!("the window table must be a subquery");
+    };
+    let mut columns: Vec<Column> = planner.calls.iter().map(|_| unnamed_column()).collect();
+    columns.append(window_columns);
+    *window_columns = columns;
+    *window = Some(Box::new(WindowPlan {
+        partitionings: planner.partitionings,
+        functions: planner.functions,
+    }));
+
+    let mut result_columns = Vec::with_capacity(rewritten_columns.len());
+    let mut star_columns = star_columns.into_iter();
+    for column in rewritten_columns {
+        match column {
+            Some((expr, alias)) => result_columns.push(ResultSetColumn {
+                expr,
+                alias,
+                contains_aggregates: false,
+            }),
+            None => {
+                for idx in star_columns.next().expect("star must have been expanded") {
+                    result_columns.push(ResultSetColumn {
+                        expr: Expr::Column {
+                            database: None,
+                            table: planner.window_table_id,
+                            column: idx,
+                            is_rowid_alias: false,
+                        },
+                        alias: None,
+                        contains_aggregates: false,
+                    });
+                }
+            }
+        }
+    }
+
+    let mut order_by = Vec::with_capacity(order_by_terms.len());
+    for (term, order) in order_by_terms {
+        let expr = match term {
+            OrderByTerm::Alias(alias) => result_columns
+                .iter()
+                .find(|column| {
+                    column
+                        .alias
+                        .as_ref()
+                        .is_some_and(|a| normalize_ident(a) == normalize_ident(&alias))
+                })
+                .map(|column| column.expr.clone())
+                .expect("alias must name a result column"),
+            OrderByTerm::Expr(mut expr) => {
+                replace_column_number_with_copy_of_column_expr(&mut expr, &result_columns)?;
+                *expr
+            }
+        };
+        order_by.push((Box::new(expr), order));
+    }
+
+    let (limit, offset) = limit.as_ref().map_or(Ok((None, None)), parse_limit)?;
+    Ok(SelectPlan {
+        join_order: vec![JoinOrderMember {
+            table_id: window_table.internal_id,
+            original_idx: 0,
+            is_outer: false,
+            is_right: false,
+        }],
+        table_references: TableReferences::new(vec![window_table], outer_query_refs.to_vec()),
+        result_columns,
+        where_clause: vec![],
+        group_by: None,
+        order_by,
+        aggregates: vec![],
+        limit,
+        offset,
+        contains_constant_false_condition: false,
+        query_destination,
+        distinctness: Distinctness::from_ast(distinctness.as_ref()),
+        values: vec![],
+    })
+}
+
+/// Emits the coroutine of a window table and returns the start register of its columns.
+///
+/// The coroutine runs the input subquery and sorts its rows for the first partitioning.
+/// Then, for each partitioning, the sorted rows are grouped into partitions and peer groups
+/// (rows with equal ORDER BY terms) and stored in two ephemeral tables keyed by their
+/// position. Once a partition is complete, its rows are visited in order and the window
+/// functions of the partitioning are computed for each of them, reading the other rows of
+/// the partition through the second table. The rows are then sorted for the next
+/// partitioning, or yielded after the last one.
+pub fn emit_window_subquery(
+    program: &mut ProgramBuilder,
+    from_clause_subquery: &mut FromClauseSubquery,
+    t_ctx: &mut TranslateCtx,
+) -> Result<usize> {
+    let window = from_clause_subquery
+        .window
+        .as_deref()
+        .expect("window table must have a window plan");
+    let num_functions = window.functions.len();
+    let num_columns = from_clause_subquery.columns.len();
+
+    let yield_reg = program.alloc_register();
+    let coroutine_implementation_start = program.allocate_label();
+    let coroutine_body_end = program.allocate_label();
+    program.emit_insn(Insn::InitCoroutine {
+        yield_reg,
+        jump_on_definition: coroutine_body_end,
+        start_offset: coroutine_implementation_start,
+    });
+    program.preassign_label_to_next_insn(coroutine_implementation_start);
+
+    // The current row: the results of the window functions followed by the input columns.
+    let row_reg = program.alloc_registers(num_columns);
+    let passes: Vec<WindowPass> = window
+        .partitionings
+        .iter()
+        .enumerate()
+        .map(|(i, partitioning)| {
+            WindowPass::new(
+                program,
+                i,
+                partitioning,
+                &from_clause_subquery.name,
+                num_functions,
+                num_columns,
+                row_reg,
+            )
+        })
+        .collect();
+    for pass in passes.iter() {
+        pass.emit_open_sorter(program);
+    }
+
+    // Sort the rows of the input subquery for the first partitioning.
+    let input_start_reg = emit_subquery(program, &mut from_clause_subquery.plan, t_ctx)?;
+    let QueryDestination::CoroutineYield {
+        yield_reg: input_yield_reg,
+        ..
+    } = from_clause_subquery.plan.query_destination
+    else {
+        unreachable!("window input SELECT with non-subquery query type");
+    };
+    let input_loop_start = program.allocate_label();
+    let input_loop_end = program.allocate_label();
+    program.preassign_label_to_next_insn(input_loop_start);
+    program.emit_insn(Insn::Yield {
+        yield_reg: input_yield_reg,
+        end_offset: input_loop_end,
+    });
+    program.emit_insn(Insn::Copy {
+        src_reg: input_start_reg,
+        dst_reg: row_reg + num_functions,
+        extra_amount: num_columns - num_functions - 1,
+    });
+    passes[0].emit_sorter_insert(program);
+    program.emit_insn(Insn::Goto {
+        target_pc: input_loop_start,
+    });
+    program.preassign_label_to_next_insn(input_loop_end);
+
+    for (i, pass) in passes.iter().enumerate() {
+        let functions: Vec<usize> = window
+            .functions
+            .iter()
+            .enumerate()
+            .filter(|(_, function)| function.partitioning == i)
+            .map(|(idx, _)| idx)
+            .collect();
+        pass.emit_partitions(
+            program,
+            t_ctx,
+            &window.functions,
+            &functions,
+            passes.get(i + 1),
+            yield_reg,
+        )?;
+    }
+
+    program.emit_insn(Insn::EndCoroutine { yield_reg });
+    program.preassign_label_to_next_insn(coroutine_body_end);
+
+    // The parent query reads the window table like any other FROM clause subquery.
+    from_clause_subquery.plan.query_destination = QueryDestination::CoroutineYield {
+        yield_reg,
+        coroutine_implementation_start,
+    };
+    Ok(row_reg)
+}
+
+/// Where the frame functions of a partitioning keep their state.
+enum FrameState {
+    Aggregate {
+        /// The accumulator, which is the result register unless the frame is computed
+        /// incrementally.
+        acc_reg: usize,
+        args_reg: usize,
+        aggregate: Box<Aggregate>,
+    },
+    /// `first_value()`, `last_value()` and `nth_value()`.
+    Value {
+        value_reg: usize,
+        /// The number of rows of the frame visited so far.
+        count_reg: usize,
+    },
+}
+
+/// The state of a frame function whose frame only grows from one row to the next. The rows
+/// before `frontier_reg` have already been added to the state.
+struct RunningFrame {
+    state: FrameState,
+    frontier_reg: usize,
+}
+
+/// The cursors and registers used to compute the window functions of one partitioning.
+struct WindowPass<'a> {
+    partitioning: &'a WindowPartitioning,
+    num_functions: usize,
+    num_columns: usize,
+    /// The current row, see [emit_window_subquery].
+    row_reg: usize,
+    sorter_cursor_id: CursorID,
+    pseudo_cursor_id: CursorID,
+    /// The rows of the partitions, keyed by their position. Used to visit the rows.
+    rows_cursor_id: CursorID,
+    /// The same rows as `rows_cursor_id`, used to visit the frames and peers of the rows.
+    frame_cursor_id: CursorID,
+    reg_sorter_data: usize,
+    reg_sorter_record: usize,
+    reg_keys: usize,
+    reg_prev_keys: usize,
+    reg_record: usize,
+    /// The position of the last row stored in the ephemeral tables.
+    reg_position: usize,
+    reg_partition_start: usize,
+    reg_partition_size: usize,
+    reg_peer_group: usize,
+    reg_flush_return: usize,
+    /// The position and the peer group number of the row being computed.
+    reg_rowid: usize,
+    reg_row_peer_group: usize,
+    /// The positions of the first and the last peer of the row being computed.
+    reg_peer_start: usize,
+    reg_peer_end: usize,
+    reg_known_peer_group: usize,
+}
+
+impl<'a> WindowPass<'a> {
+    fn new(
+        program: &mut ProgramBuilder,
+        idx: usize,
+        partitioning: &'a WindowPartitioning,
+        name: &str,
+        num_functions: usize,
+        num_columns: usize,
+        row_reg: usize,
+    ) -> Self {
+        // The sorter needs at least one key, a window without PARTITION BY and ORDER BY terms
+        // sorts its rows by a constant.
+        let num_sorter_keys =
+            (partitioning.partition_by.len() + partitioning.order_by.len()).max(1);
+        let partition_table = |suffix: &str| {
+            Arc::new(BTreeTable {
+                root_page: 0, // Not relevant for ephemeral table definition
+                name: format!("{name}_{suffix}{idx}"),
+                has_rowid: true,
+                primary_key_columns: vec![],
+                columns: (0..num_columns + 1).map(|_| unnamed_column()).collect(),
+                is_strict: false,
+                unique_sets: None,
+                foreign_keys: vec![],
+                check_constraints: vec![],
+                temporary: false,
+            })
+        };
+        let rows_table = partition_table("rows");
+        let frame_table = partition_table("frame");
+        Self {
+            partitioning,
+            num_functions,
+            num_columns,
+            row_reg,
+            sorter_cursor_id: program.alloc_cursor_id(CursorType::Sorter),
+            pseudo_cursor_id: program.alloc_cursor_id(CursorType::Pseudo(PseudoCursorType {
+                column_count: num_sorter_keys + num_columns,
+            })),
+            rows_cursor_id: program.alloc_cursor_id(CursorType::BTreeTable(rows_table)),
+            frame_cursor_id: program.alloc_cursor_id(CursorType::BTreeTable(frame_table)),
+            reg_sorter_data: program.alloc_register(),
+            reg_sorter_record: program.alloc_registers(num_sorter_keys + num_columns),
+            reg_keys: program.alloc_registers(num_sorter_keys),
+            reg_prev_keys: program.alloc_registers(num_sorter_keys),
+            reg_record: program.alloc_registers(num_columns + 1),
+            reg_position: program.alloc_register(),
+            reg_partition_start: program.alloc_register(),
+            reg_partition_size: program.alloc_register(),
+            reg_peer_group: program.alloc_register(),
+            reg_flush_return: program.alloc_register(),
+            reg_rowid: program.alloc_register(),
+            reg_row_peer_group: program.alloc_register(),
+            reg_peer_start: program.alloc_register(),
+            reg_peer_end: program.alloc_register(),
+            reg_known_peer_group: program.alloc_register(),
+        }
+    }
+
+    fn num_partition_keys(&self) -> usize {
+        self.partitioning.partition_by.len()
+    }
+
+    fn num_keys(&self) -> usize {
+        self.partitioning.partition_by.len() + self.partitioning.order_by.len()
+    }
+
+    fn num_sorter_keys(&self) -> usize {
+        self.num_keys().max(1)
+    }
+
+    /// The column of the partition tables holding the peer group number of a row.
+    fn peer_group_column(&self) -> usize {
+        self.num_columns
+    }
+
+    fn emit_open_sorter(&self, program: &mut ProgramBuilder) {
+        let mut order: Vec<SortOrder> = self
+            .partitioning
+            .partition_by
+            .iter()
+            .map(|_| SortOrder::Asc)
+            .chain(self.partitioning.order_by.iter().map(|(_, order)| *order))
+            .collect();
+        if order.is_empty() {
+            order.push(SortOrder::Asc);
+        }
+        program.emit_insn(Insn::SorterOpen {
+            cursor_id: self.sorter_cursor_id,
+            columns: order.len(),
+            collations: vec![Some(CollationSeq::default()); order.len()],
+            order,
+        });
+    }
+
+    /// Adds the current row to the sorter, preceded by its PARTITION BY and ORDER BY terms.
+    fn emit_sorter_insert(&self, program: &mut ProgramBuilder) {
+        let input_reg = self.row_reg + self.num_functions;
+        let keys = self
+            .partitioning
+            .partition_by
+            .iter()
+            .chain(self.partitioning.order_by.iter().map(|(idx, _)| idx));
+        for (i, idx) in keys.enumerate() {
+            program.emit_insn(Insn::Copy {
+                src_reg: input_reg + idx,
+                dst_reg: self.reg_sorter_record + i,
+                extra_amount: 0,
+            });
+        }
+        if self.num_keys() == 0 {
+            program.emit_int(0, self.reg_sorter_record);
+        }
+        program.emit_insn(Insn::Copy {
+            src_reg: self.row_reg,
+            dst_reg: self.reg_sorter_record + self.num_sorter_keys(),
+            extra_amount: self.num_columns - 1,
+        });
+        program.emit_insn(Insn::MakeRecord {
+            start_reg: self.reg_sorter_record,
+            count: self.num_sorter_keys() + self.num_columns,
+            dest_reg: self.reg_sorter_data,
+            index_name: None,
+        });
+        program.emit_insn(Insn::SorterInsert {
+            cursor_id: self.sorter_cursor_id,
+            record_reg: self.reg_sorter_data,
+        });
+    }
+
+    /// Reads the sorted rows, stores the rows of each partition in the partition tables and
+    /// computes the window functions of the partition once it is complete.
+    fn emit_partitions(
+        &self,
+        program: &mut ProgramBuilder,
+        t_ctx: &TranslateCtx,
+        functions: &[WindowFunction],
+        pass_functions: &[usize],
+        next_pass: Option<&WindowPass>,
+        yield_reg: usize,
+    ) -> Result<()> {
+        let num_keys = self.num_keys();
+        let num_partition_keys = self.num_partition_keys();
+        let num_order_keys = num_keys - num_partition_keys;
+
+        program.emit_insn(Insn::OpenPseudo {
+            cursor_id: self.pseudo_cursor_id,
+            content_reg: self.reg_sorter_data,
+            num_fields: self.num_sorter_keys() + self.num_columns,
+        });
+        program.emit_insn(Insn::OpenEphemeral {
+            cursor_id: self.rows_cursor_id,
+            is_table: true,
+        });
+        program.emit_insn(Insn::OpenEphemeral {
+            cursor_id: self.frame_cursor_id,
+            is_table: true,
+        });
+        program.emit_int(0, self.reg_position);
+        program.emit_int(0, self.reg_partition_size);
+
+        let pass_end = program.allocate_label();
+        let sort_loop = program.allocate_label();
+        let flush = program.allocate_label();
+        program.emit_insn(Insn::SorterSort {
+            cursor_id: self.sorter_cursor_id,
+            pc_if_empty: pass_end,
+        });
+        program.preassign_label_to_next_insn(sort_loop);
+        program.emit_insn(Insn::SorterData {
+            cursor_id: self.sorter_cursor_id,
+            dest_reg: self.reg_sorter_data,
+            pseudo_cursor: self.pseudo_cursor_id,
+        });
+        for i in 0..num_keys {
+            program.emit_column_or_rowid(self.pseudo_cursor_id, i, self.reg_keys + i);
+        }
+
+        // A row starts a new partition if it is the first one or if its PARTITION BY terms
+        // differ from the previous row's.
+        let new_partition = program.allocate_label();
+        let same_partition = program.allocate_label();
+        let new_peer_group = program.allocate_label();
+        let store_row = program.allocate_label();
+        program.emit_insn(Insn::IfNot {
+            reg: self.reg_partition_size,
+            target_pc: new_partition,
+            jump_if_null: false,
+        });
+        if num_partition_keys > 0 {
+            program.emit_insn(Insn::Compare {
+                start_reg_a: self.reg_keys,
+                start_reg_b: self.reg_prev_keys,
+                count: num_partition_keys,
+                collation: program.curr_collation(),
+            });
+            program.emit_insn(Insn::Jump {
+                target_pc_lt: new_partition,
+                target_pc_eq: same_partition,
+                target_pc_gt: new_partition,
+            });
+        } else {
+            program.emit_insn(Insn::Goto {
+                target_pc: same_partition,
+            });
+        }
+        program.preassign_label_to_next_insn(new_partition);
+        let start_partition = program.allocate_label();
+        program.emit_insn(Insn::IfNot {
+            reg: self.reg_partition_size,
+            target_pc: start_partition,
+            jump_if_null: false,
+        });
+        program.emit_insn(Insn::Gosub {
+            target_pc: flush,
+            return_reg: self.reg_flush_return,
+        });
+        program.preassign_label_to_next_insn(start_partition);
+        program.emit_int(0, self.reg_peer_group);
+        program.emit_int(0, self.reg_partition_size);
+        program.emit_insn(Insn::Copy {
+            src_reg: self.reg_position,
+            dst_reg: self.reg_partition_start,
+            extra_amount: 0,
+        });
+        program.emit_insn(Insn::AddImm {
+            register: self.reg_partition_start,
+            value: 1,
+        });
+        program.emit_insn(Insn::Goto {
+            target_pc: new_peer_group,
+        });
+
+        // Within a partition, a row starts a new peer group if its ORDER BY terms differ from
+        // the previous row's.
+        program.preassign_label_to_next_insn(same_partition);
+        if num_order_keys > 0 {
+            program.emit_insn(Insn::Compare {
+                start_reg_a: self.reg_keys + num_partition_keys,
+                start_reg_b: self.reg_prev_keys + num_partition_keys,
+                count: num_order_keys,
+                collation: program.curr_collation(),
+            });
+            program.emit_insn(Insn::Jump {
+                target_pc_lt: new_peer_group,
+                target_pc_eq: store_row,
+                target_pc_gt: new_peer_group,
+            });
+        } else {
+            program.emit_insn(Insn::Goto {
+                target_pc: store_row,
+            });
+        }
+        program.preassign_label_to_next_insn(new_peer_group);
+        program.emit_insn(Insn::AddImm {
+            register: self.reg_peer_group,
+            value: 1,
+        });
+
+        program.preassign_label_to_next_insn(store_row);
+        if num_keys > 0 {
+            program.emit_insn(Insn::Copy {
+                src_reg: self.reg_keys,
+                dst_reg: self.reg_prev_keys,
+                extra_amount: num_keys - 1,
+            });
+        }
+        for i in 0..self.num_columns {
+            program.emit_column_or_rowid(
+                self.pseudo_cursor_id,
+                self.num_sorter_keys() + i,
+                self.reg_record + i,
+            );
+        }
+        program.emit_insn(Insn::Copy {
+            src_reg: self.reg_peer_group,
+            dst_reg: self.reg_record + self.num_columns,
+            extra_amount: 0,
+        });
+        let record_reg = program.alloc_register();
+        program.emit_insn(Insn::MakeRecord {
+            start_reg: self.reg_record,
+            count: self.num_columns + 1,
+            dest_reg: record_reg,
+            index_name: None,
+        });
+        program.emit_insn(Insn::AddImm {
+            register: self.reg_position,
+            value: 1,
+        });
+        program.emit_insn(Insn::AddImm {
+            register: self.reg_partition_size,
+            value: 1,
+        });
+        for cursor in [self.rows_cursor_id, self.frame_cursor_id] {
+            program.emit_insn(Insn::Insert {
+                cursor,
+                key_reg: self.reg_position,
+                record_reg,
+                flag: InsertFlags::new().require_seek(),
+                table_name: String::new(),
+            });
+        }
+        program.emit_insn(Insn::SorterNext {
+            cursor_id: self.sorter_cursor_id,
+            pc_if_next: sort_loop,
+        });
+        program.emit_insn(Insn::Gosub {
+            target_pc: flush,
+            return_reg: self.reg_flush_return,
+        });
+        program.emit_insn(Insn::Goto {
+            target_pc: pass_end,
+        });
+
+        program.preassign_label_to_next_insn(flush);
+        self.emit_flush(
+            program,
+            t_ctx,
+            functions,
+            pass_functions,
+            next_pass,
+            yield_reg,
+        )?;
+        program.emit_insn(Insn::Return {
+            return_reg: self.reg_flush_return,
+            can_fallthrough: false,
+        });
+
+        program.preassign_label_to_next_insn(pass_end);
+        Ok(())
+    }
+
+    /// The subroutine computing the window functions for each row of the last partition.
+    fn emit_flush(
+        &self,
+        program: &mut ProgramBuilder,
+        t_ctx: &TranslateCtx,
+        functions: &[WindowFunction],
+        pass_functions: &[usize],
+        next_pass: Option<&WindowPass>,
+        yield_reg: usize,
+    ) -> Result<()> {
+        let flush_end = program.allocate_label();
+        let row_loop = program.allocate_label();
+
+        // Frames that only grow are computed incrementally: their state is kept across the
+        // rows of the partition.
+        let mut running_frames = Vec::with_capacity(pass_functions.len());
+        for &idx in pass_functions {
+            let function = &functions[idx];
+            let running = match &function.func {
+                WindowFunctionKind::Aggregate(AggFunc::External(_)) => false,
+                WindowFunctionKind::Aggregate(_) => function.frame.is_growing(),
+                WindowFunctionKind::Builtin(func) => {
+                    func.uses_frame() && function.frame.is_growing()
+                }
+            };
+            let running_frame = if running {
+                let state = self.alloc_frame_state(program, function, None);
+                let frontier_reg = program.alloc_register();
+                program.emit_insn(Insn::Copy {
+                    src_reg: self.reg_partition_start,
+                    dst_reg: frontier_reg,
+                    extra_amount: 0,
+                });
+                Some(RunningFrame {
+                    state,
+                    frontier_reg,
+                })
+            } else {
+                None
+            };
+            running_frames.push(running_frame);
+        }
+
+        program.emit_int(0, self.reg_known_peer_group);
+        program.emit_insn(Insn::SeekRowid {
+            cursor_id: self.rows_cursor_id,
+            src_reg: self.reg_partition_start,
+            target_pc: flush_end,
+        });
+        program.preassign_label_to_next_insn(row_loop);
+        program.emit_insn(Insn::RowId {
+            cursor_id: self.rows_cursor_id,
+            dest: self.reg_rowid,
+        });
+        for i in 0..self.num_columns {
+            program.emit_column_or_rowid(self.rows_cursor_id, i, self.row_reg + i);
+        }
+        program.emit_column_or_rowid(
+            self.rows_cursor_id,
+            self.peer_group_column(),
+            self.reg_row_peer_group,
+        );
+
+        // Find the peers of the row when it starts a new peer group.
+        let peers_known = program.allocate_label();
+        program.emit_insn(Insn::Eq {
+            lhs: self.reg_row_peer_group,
+            rhs: self.reg_known_peer_group,
+            target_pc: peers_known,
+            flags: CmpInsFlags::default(),
+            collation: None,
+        });
+        program.emit_insn(Insn::Copy {
+            src_reg: self.reg_row_peer_group,
+            dst_reg: self.reg_known_peer_group,
+            extra_amount: 0,
+        });
+        program.emit_insn(Insn::Copy {
+            src_reg: self.reg_rowid,
+            dst_reg: self.reg_peer_start,
+            extra_amount: 0,
+        });
+        if pass_functions.iter().any(|&idx| {
+            matches!(
+                functions[idx].func,
+                WindowFunctionKind::Builtin(WindowFunc::CumeDist)
+            )
+        }) {
+            self.emit_find_peer_end(program);
+        }
+        program.preassign_label_to_next_insn(peers_known);
+
+        for (&idx, running_frame) in pass_functions.iter().zip(running_frames.iter()) {
+            let function = &functions[idx];
+            let dest = self.row_reg + idx;
+            match &function.func {
+                WindowFunctionKind::Builtin(func) if !func.uses_frame() => {
+                    self.emit_builtin(program, *func, &function.args, dest)
+                }
+                _ => match running_frame {
+                    Some(running_frame) => {
+                        self.emit_running_frame(program, t_ctx, function, running_frame, dest)?
+                    }
+                    None => self.emit_frame(program, t_ctx, function, dest)?,
+                },
+            }
+        }
+
+        match next_pass {
+            Some(next_pass) => next_pass.emit_sorter_insert(program),
+            None => program.emit_insn(Insn::Yield {
+                yield_reg,
+                end_offset: BranchOffset::Offset(0),
+            }),
+        }
+        program.emit_insn(Insn::Next {
+            cursor_id: self.rows_cursor_id,
+            pc_if_next: row_loop,
+        });
+        program.preassign_label_to_next_insn(flush_end);
+        Ok(())
+    }
+
+    /// Sets `reg_peer_end` to the position of the last peer of the current row.
+    fn emit_find_peer_end(&self, program: &mut ProgramBuilder) {
+        let done = program.allocate_label();
+        let next = program.allocate_label();
+        let check = program.allocate_label();
+        let peer_group_reg = program.alloc_register();
+        program.emit_insn(Insn::Copy {
+            src_reg: self.reg_rowid,
+            dst_reg: self.reg_peer_end,
+            extra_amount: 0,
+        });
+        program.emit_insn(Insn::SeekRowid {
+            cursor_id: self.frame_cursor_id,
+            src_reg: self.reg_rowid,
+            target_pc: done,
+        });
+        program.preassign_label_to_next_insn(next);
+        program.emit_insn(Insn::Next {
+            cursor_id: self.frame_cursor_id,
+            pc_if_next: check,
+        });
+        program.emit_insn(Insn::Goto { target_pc: done });
+        program.preassign_label_to_next_insn(check);
+        program.emit_column_or_rowid(
+            self.frame_cursor_id,
+            self.peer_group_column(),
+            peer_group_reg,
+        );
+        program.emit_insn(Insn::Ne {
+            lhs: peer_group_reg,
+            rhs: self.reg_row_peer_group,
+            target_pc: done,
+            flags: CmpInsFlags::default(),
+            collation: None,
+        });
+        program.emit_insn(Insn::RowId {
+            cursor_id: self.frame_cursor_id,
+            dest: self.reg_peer_end,
+        });
+        program.emit_insn(Insn::Goto { target_pc: next });
+        program.preassign_label_to_next_insn(done);
+    }
+
+    fn arg_reg(&self, args: &[usize], i: usize) -> usize {
+        self.row_reg + self.num_functions + args[i]
+    }
+
+    /// Emits a check that `reg` holds a positive number, failing the statement otherwise.
+    fn emit_positive_check(&self, program: &mut ProgramBuilder, reg: usize, message: &str) {
+        let zero_reg = program.alloc_register();
+        let ok = program.allocate_label();
+        program.emit_int(0, zero_reg);
+        program.emit_insn(Insn::Gt {
+            lhs: reg,
+            rhs: zero_reg,
+            target_pc: ok,
+            flags: CmpInsFlags::default(),
+            collation: None,
+        });
+        program.emit_insn(Insn::Halt {
+            err_code: SQLITE_ERROR,
+            description: message.to_string(),
+        });
+        program.preassign_label_to_next_insn(ok);
+    }
+
+    /// Computes a ranking function or `lag()`/`lead()` for the current row. Positions in the
+    /// partition tables are relative to the first row of the partition.
+    fn emit_builtin(
+        &self,
+        program: &mut ProgramBuilder,
+        func: WindowFunc,
+        args: &[usize],
+        dest: usize,
+    ) {
+        match func {
+            WindowFunc::RowNumber => {
+                program.emit_insn(Insn::Subtract {
+                    lhs: self.reg_rowid,
+                    rhs: self.reg_partition_start,
+                    dest,
+                });
+                program.emit_insn(Insn::AddImm {
+                    register: dest,
+                    value: 1,
+                });
+            }
+            WindowFunc::Rank => {
+                program.emit_insn(Insn::Subtract {
+                    lhs: self.reg_peer_start,
+                    rhs: self.reg_partition_start,
+                    dest,
+                });
+                program.emit_insn(Insn::AddImm {
+                    register: dest,
+                    value: 1,
+                });
+            }
+            WindowFunc::DenseRank => {
+                program.emit_insn(Insn::Copy {
+                    src_reg: self.reg_row_peer_group,
+                    dst_reg: dest,
+                    extra_amount: 0,
+                });
+            }
+            WindowFunc::PercentRank => {
+                // (rank - 1) / (partition size - 1), or 0.0 for a single row partition.
+                let done = program.allocate_label();
+                let divisor_reg = program.alloc_register();
+                let rank_reg = program.alloc_register();
+                let one_reg = program.alloc_register();
+                program.emit_insn(Insn::Real { value: 0.0, dest });
+                program.emit_insn(Insn::Copy {
+                    src_reg: self.reg_partition_size,
+                    dst_reg: divisor_reg,
+                    extra_amount: 0,
+                });
+                program.emit_insn(Insn::AddImm {
+                    register: divisor_reg,
+                    value: -1,
+                });
+                program.emit_insn(Insn::IfNot {
+                    reg: divisor_reg,
+                    target_pc: done,
+                    jump_if_null: false,
+                });
+                program.emit_insn(Insn::Subtract {
+                    lhs: self.reg_peer_start,
+                    rhs: self.reg_partition_start,
+                    dest: rank_reg,
+                });
+                program.emit_insn(Insn::Real {
+                    value: 1.0,
+                    dest: one_reg,
+                });
+                program.emit_insn(Insn::Multiply {
+                    lhs: rank_reg,
+                    rhs: one_reg,
+                    dest: rank_reg,
+                });
+                program.emit_insn(Insn::Divide {
+                    lhs: rank_reg,
+                    rhs: divisor_reg,
+                    dest,
+                });
+                program.preassign_label_to_next_insn(done);
+            }
+            WindowFunc::CumeDist => {
+                // (number of rows up to the last peer of the row) / (partition size)
+                let rows_reg = program.alloc_register();
+                let one_reg = program.alloc_register();
+                program.emit_insn(Insn::Subtract {
+                    lhs: self.reg_peer_end,
+                    rhs: self.reg_partition_start,
+                    dest: rows_reg,
+                });
+                program.emit_insn(Insn::AddImm {
+                    register: rows_reg,
+                    value: 1,
+                });
+                program.emit_insn(Insn::Real {
+                    value: 1.0,
+                    dest: one_reg,
+                });
+                program.emit_insn(Insn::Multiply {
+                    lhs: rows_reg,
+                    rhs: one_reg,
+                    dest: rows_reg,
+                });
+                program.emit_insn(Insn::Divide {
+                    lhs: rows_reg,
+                    rhs: self.reg_partition_size,
+                    dest,
+                });
+            }
+            WindowFunc::Ntile => {
+                // The first (size % n) buckets have one more row than the others.
+                let n_reg = self.arg_reg(args, 0);
+                self.emit_positive_check(
+                    program,
+                    n_reg,
+                    "argument of ntile must be a positive integer",
+                );
+                let idx_reg = program.alloc_register();
+                let size_reg = program.alloc_register();
+                let large_reg = program.alloc_register();
+                let large_size_reg = program.alloc_register();
+                let large_rows_reg = program.alloc_register();
+                let tmp_reg = program.alloc_register();
+                let small_partition = program.allocate_label();
+                let in_small_bucket = program.allocate_label();
+                let done = program.allocate_label();
+                program.emit_insn(Insn::Subtract {
+                    lhs: self.reg_rowid,
+                    rhs: self.reg_partition_start,
+                    dest: idx_reg,
+                });
+                program.emit_insn(Insn::Divide {
+                    lhs: self.reg_partition_size,
+                    rhs: n_reg,
+                    dest: size_reg,
+                });
+                program.emit_insn(Insn::IfNot {
+                    reg: size_reg,
+                    target_pc: small_partition,
+                    jump_if_null: false,
+                });
+                program.emit_insn(Insn::Multiply {
+                    lhs: n_reg,
+                    rhs: size_reg,
+                    dest: tmp_reg,
+                });
+                program.emit_insn(Insn::Subtract {
+                    lhs: self.reg_partition_size,
+                    rhs: tmp_reg,
+                    dest: large_reg,
+                });
+                program.emit_insn(Insn::Copy {
+                    src_reg: size_reg,
+                    dst_reg: large_size_reg,
+                    extra_amount: 0,
+                });
+                program.emit_insn(Insn::AddImm {
+                    register: large_size_reg,
+                    value: 1,
+                });
+                program.emit_insn(Insn::Multiply {
+                    lhs: large_reg,
+                    rhs: large_size_reg,
+                    dest: large_rows_reg,
+                });
+                program.emit_insn(Insn::Ge {
+                    lhs: idx_reg,
+                    rhs: large_rows_reg,
+                    target_pc: in_small_bucket,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                });
+                program.emit_insn(Insn::Divide {
+                    lhs: idx_reg,
+                    rhs: large_size_reg,
+                    dest,
+                });
+                program.emit_insn(Insn::AddImm {
+                    register: dest,
+                    value: 1,
+                });
+                program.emit_insn(Insn::Goto { target_pc: done });
+                program.preassign_label_to_next_insn(in_small_bucket);
+                program.emit_insn(Insn::Subtract {
+                    lhs: idx_reg,
+                    rhs: large_rows_reg,
+                    dest: tmp_reg,
+                });
+                program.emit_insn(Insn::Divide {
+                    lhs: tmp_reg,
+                    rhs: size_reg,
+                    dest: tmp_reg,
+                });
+                program.emit_insn(Insn::Add {
+                    lhs: tmp_reg,
+                    rhs: large_reg,
+                    dest,
+                });
+                program.emit_insn(Insn::AddImm {
+                    register: dest,
+                    value: 1,
+                });
+                program.emit_insn(Insn::Goto { target_pc: done });
+                // With fewer rows than buckets, each row has its own bucket.
+                program.preassign_label_to_next_insn(small_partition);
+                program.emit_insn(Insn::Copy {
+                    src_reg: idx_reg,
+                    dst_reg: dest,
+                    extra_amount: 0,
+                });
+                program.emit_insn(Insn::AddImm {
+                    register: dest,
+                    value: 1,
+                });
+                program.preassign_label_to_next_insn(done);
+            }
+            WindowFunc::Lag | WindowFunc::Lead => {
+                let done = program.allocate_label();
+                let target_reg = program.alloc_register();
+                match args.get(2) {
+                    Some(_) => program.emit_insn(Insn::Copy {
+                        src_reg: self.arg_reg(args, 2),
+                        dst_reg: dest,
+                        extra_amount: 0,
+                    }),
+                    None => program.emit_insn(Insn::Null {
+                        dest,
+                        dest_end: None,
+                    }),
+                }
+                if args.len() > 1 {
+                    let offset_reg = self.arg_reg(args, 1);
+                    if func == WindowFunc::Lag {
+                        program.emit_insn(Insn::Subtract {
+                            lhs: self.reg_rowid,
+                            rhs: offset_reg,
+                            dest: target_reg,
+                        });
+                    } else {
+                        program.emit_insn(Insn::Add {
+                            lhs: self.reg_rowid,
+                            rhs: offset_reg,
+                            dest: target_reg,
+                        });
+                    }
+                } else {
+                    program.emit_insn(Insn::Copy {
+                        src_reg: self.reg_rowid,
+                        dst_reg: target_reg,
+                        extra_amount: 0,
+                    });
+                    program.emit_insn(Insn::AddImm {
+                        register: target_reg,
+                        value: if func == WindowFunc::Lag { -1 } else { 1 },
+                    });
+                }
+                // Rows after the partition are not stored yet, so only the start of the
+                // partition needs to be checked.
+                program.emit_insn(Insn::Lt {
+                    lhs: target_reg,
+                    rhs: self.reg_partition_start,
+                    target_pc: done,
+                    flags: CmpInsFlags::default().jump_if_null(),
+                    collation: None,
+                });
+                program.emit_insn(Insn::SeekRowid {
+                    cursor_id: self.frame_cursor_id,
+                    src_reg: target_reg,
+                    target_pc: done,
+                });
+                program.emit_column_or_rowid(
+                    self.frame_cursor_id,
+                    self.num_functions + args[0],
+                    dest,
+                );
+                program.preassign_label_to_next_insn(done);
+            }
+            WindowFunc::FirstValue | WindowFunc::LastValue | WindowFunc::NthValue => {
+                unreachable!("{func}() is computed over its frame")
+            }
+        }
+    }
+
+    /// Allocates the state of a frame function. The result register is used as the state
+    /// when given.
+    fn alloc_frame_state(
+        &self,
+        program: &mut ProgramBuilder,
+        function: &WindowFunction,
+        dest: Option<usize>,
+    ) -> FrameState {
+        let reg = dest.unwrap_or_else(|| program.alloc_register());
+        program.emit_insn(Insn::Null {
+            dest: reg,
+            dest_end: None,
+        });
+        match &function.func {
+            WindowFunctionKind::Aggregate(func) => {
+                let num_args = function.args.len().max(1);
+                let args_reg = program.alloc_registers(num_args);
+                if function.args.is_empty() {
+                    program.emit_int(1, args_reg);
+                }
+                FrameState::Aggregate {
+                    acc_reg: reg,
+                    args_reg,
+                    aggregate: Box::new(Aggregate {
+                        func: func.clone(),
+                        args: (0..num_args)
+                            .map(|i| Expr::Register(args_reg + i))
+                            .collect(),
+                        original_expr: function.original_expr.clone(),
+                        distinctness: Distinctness::NonDistinct,
+                    }),
+                }
+            }
+            WindowFunctionKind::Builtin(_) => {
+                let count_reg = program.alloc_register();
+                program.emit_int(0, count_reg);
+                FrameState::Value {
+                    value_reg: reg,
+                    count_reg,
+                }
+            }
+        }
+    }
+
+    /// Adds the row the frame cursor points to to the state of a frame function. Jumps to
+    /// `skip` if the row is filtered out, and to `done`, if given, when the value of a value
+    /// function is known.
+    fn emit_frame_step(
+        &self,
+        program: &mut ProgramBuilder,
+        t_ctx: &TranslateCtx,
+        function: &WindowFunction,
+        state: &FrameState,
+        skip: BranchOffset,
+        done: Option<BranchOffset>,
+    ) -> Result<()> {
+        match state {
+            FrameState::Aggregate {
+                acc_reg,
+                args_reg,
+                aggregate,
+            } => {
+                if let Some(filter) = function.filter {
+                    let filter_reg = program.alloc_register();
+                    program.emit_column_or_rowid(
+                        self.frame_cursor_id,
+                        self.num_functions + filter,
+                        filter_reg,
+                    );
+                    program.emit_insn(Insn::IfNot {
+                        reg: filter_reg,
+                        target_pc: skip,
+                        jump_if_null: true,
+                    });
+                }
+                for (i, arg) in function.args.iter().enumerate() {
+                    program.emit_column_or_rowid(
+                        self.frame_cursor_id,
+                        self.num_functions + arg,
+                        args_reg + i,
+                    );
+                }
+                translate_aggregation_step(
+                    program,
+                    &TableReferences::new(vec![], vec![]),
+                    AggArgumentSource::new_from_registers(*args_reg, aggregate),
+                    *acc_reg,
+                    &t_ctx.resolver,
+                )?;
+            }
+            FrameState::Value {
+                value_reg,
+                count_reg,
+            } => {
+                let WindowFunctionKind::Builtin(func) = function.func else {
+                    unreachable!("value state for an aggregate window function");
+                };
+                let column = self.num_functions + function.args[0];
+                match func {
+                    WindowFunc::FirstValue => {
+                        program.emit_insn(Insn::If {
+                            reg: *count_reg,
+                            target_pc: skip,
+                            jump_if_null: false,
+                        });
+                        program.emit_column_or_rowid(self.frame_cursor_id, column, *value_reg);
+                        program.emit_insn(Insn::AddImm {
+                            register: *count_reg,
+                            value: 1,
+                        });
+                    }
+                    WindowFunc::LastValue => {
+                        program.emit_column_or_rowid(self.frame_cursor_id, column, *value_reg);
+                        return Ok(());
+                    }
+                    WindowFunc::NthValue => {
+                        program.emit_insn(Insn::AddImm {
+                            register: *count_reg,
+                            value: 1,
+                        });
+                        program.emit_insn(Insn::Ne {
+                            lhs: *count_reg,
+                            rhs: self.arg_reg(&function.args, 1),
+                            target_pc: skip,
+                            flags: CmpInsFlags::default(),
+                            collation: None,
+                        });
+                        program.emit_column_or_rowid(self.frame_cursor_id, column, *value_reg);
+                    }
+                    _ => unreachable!("{func}() is not computed over a frame"),
+                }
+                if let Some(done) = done {
+                    program.emit_insn(Insn::Goto { target_pc: done });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_value(&self, program: &mut ProgramBuilder, value: &Value) -> usize {
+        let reg = program.alloc_register();
+        match value {
+            Value::Integer(i) => program.emit_int(*i, reg),
+            Value::Float(f) => program.emit_insn(Insn::Real {
+                value: *f,
+                dest: reg,
+            }),
+            _ => unreachable!("frame offsets are numbers"),
+        }
+        reg
+    }
+
+    /// The register holding the ORDER BY term of the current row, for RANGE frames.
+    fn order_value_reg(&self) -> (usize, SortOrder) {
+        let (idx, order) = self.partitioning.order_by[0];
+        (self.row_reg + self.num_functions + idx, order)
+    }
+
+    /// Computes the bound of the frame of the current row, in the unit of the frame: a
+    /// position for ROWS, a peer group number for GROUPS and RANGE with CURRENT ROW, and a
+    /// value of the ORDER BY term for RANGE with an offset.
+    fn emit_frame_bound(
+        &self,
+        program: &mut ProgramBuilder,
+        mode: ast::FrameMode,
+        bound: &WindowFrameBound,
+    ) -> Option<usize> {
+        let (offset, preceding) = match bound {
+            WindowFrameBound::UnboundedPreceding | WindowFrameBound::UnboundedFollowing => {
+                return None
+            }
+            WindowFrameBound::CurrentRow => {
+                return Some(match mode {
+                    ast::FrameMode::Rows => self.reg_rowid,
+                    ast::FrameMode::Groups | ast::FrameMode::Range => self.reg_row_peer_group,
+                })
+            }
+            WindowFrameBound::Preceding(offset) => (offset, true),
+            WindowFrameBound::Following(offset) => (offset, false),
+        };
+        let (base_reg, subtract) = match mode {
+            ast::FrameMode::Rows => (self.reg_rowid, preceding),
+            ast::FrameMode::Groups => (self.reg_row_peer_group, preceding),
+            ast::FrameMode::Range => {
+                let (reg, order) = self.order_value_reg();
+                (reg, preceding == (order == SortOrder::Asc))
+            }
+        };
+        let offset_reg = self.emit_value(program, offset);
+        let reg = program.alloc_register();
+        if subtract {
+            program.emit_insn(Insn::Subtract {
+                lhs: base_reg,
+                rhs: offset_reg,
+                dest: reg,
+            });
+        } else {
+            program.emit_insn(Insn::Add {
+                lhs: base_reg,
+                rhs: offset_reg,
+                dest: reg,
+            });
+        }
+        Some(reg)
+    }
+
+    /// Jumps to `outside` if the row the frame cursor points to is after the end (`is_end`)
+    /// or before the start of the frame of the current row.
+    fn emit_frame_bound_test(
+        &self,
+        program: &mut ProgramBuilder,
+        mode: ast::FrameMode,
+        bound: &WindowFrameBound,
+        bound_reg: Option<usize>,
+        is_end: bool,
+        outside: BranchOffset,
+    ) {
+        let Some(bound_reg) = bound_reg else {
+            return;
+        };
+        let emit_compare = |program: &mut ProgramBuilder, lhs: usize, rhs: usize, after: bool| {
+            let insn = if after {
+                Insn::Gt {
+                    lhs,
+                    rhs,
+                    target_pc: outside,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                }
+            } else {
+                Insn::Lt {
+                    lhs,
+                    rhs,
+                    target_pc: outside,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                }
+            };
+            program.emit_insn(insn);
+        };
+        let reg = program.alloc_register();
+        match (mode, bound) {
+            (ast::FrameMode::Rows, _) => {
+                program.emit_insn(Insn::RowId {
+                    cursor_id: self.frame_cursor_id,
+                    dest: reg,
+                });
+                emit_compare(program, reg, bound_reg, is_end);
+            }
+            (ast::FrameMode::Groups, _) | (ast::FrameMode::Range, WindowFrameBound::CurrentRow) => {
+                program.emit_column_or_rowid(self.frame_cursor_id, self.peer_group_column(), reg);
+                emit_compare(program, reg, bound_reg, is_end);
+            }
+            (ast::FrameMode::Range, _) => {
+                // NULLs sort first in ascending order and last in descending order. The frame
+                // of a row with a NULL ORDER BY term is its peer group.
+                let (value_reg, order) = self.order_value_reg();
+                let descending = order == SortOrder::Desc;
+                let peer_test = program.allocate_label();
+                let inside = program.allocate_label();
+                program.emit_insn(Insn::IsNull {
+                    reg: value_reg,
+                    target_pc: peer_test,
+                });
+                program.emit_column_or_rowid(
+                    self.frame_cursor_id,
+                    self.num_functions + self.partitioning.order_by[0].0,
+                    reg,
+                );
+                program.emit_insn(Insn::IsNull {
+                    reg,
+                    target_pc: if is_end == descending {
+                        outside
+                    } else {
+                        inside
+                    },
+                });
+                emit_compare(program, reg, bound_reg, is_end != descending);
+                program.emit_insn(Insn::Goto { target_pc: inside });
+                program.preassign_label_to_next_insn(peer_test);
+                program.emit_column_or_rowid(self.frame_cursor_id, self.peer_group_column(), reg);
+                emit_compare(program, reg, self.reg_row_peer_group, is_end);
+                program.preassign_label_to_next_insn(inside);
+            }
+        }
+    }
+
+    /// Jumps to `excluded` if the row the frame cursor points to is excluded from the frame
+    /// of the current row by its EXCLUDE clause.
+    fn emit_frame_exclude_test(
+        &self,
+        program: &mut ProgramBuilder,
+        exclude: ast::FrameExclude,
+        excluded: BranchOffset,
+    ) {
+        let reg = program.alloc_register();
+        match exclude {
+            ast::FrameExclude::NoOthers => {}
+            ast::FrameExclude::CurrentRow => {
+                program.emit_insn(Insn::RowId {
+                    cursor_id: self.frame_cursor_id,
+                    dest: reg,
+                });
+                program.emit_insn(Insn::Eq {
+                    lhs: reg,
+                    rhs: self.reg_rowid,
+                    target_pc: excluded,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                });
+            }
+            ast::FrameExclude::Group => {
+                program.emit_column_or_rowid(self.frame_cursor_id, self.peer_group_column(), reg);
+                program.emit_insn(Insn::Eq {
+                    lhs: reg,
+                    rhs: self.reg_row_peer_group,
+                    target_pc: excluded,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                });
+            }
+            ast::FrameExclude::Ties => {
+                let included = program.allocate_label();
+                program.emit_column_or_rowid(self.frame_cursor_id, self.peer_group_column(), reg);
+                program.emit_insn(Insn::Ne {
+                    lhs: reg,
+                    rhs: self.reg_row_peer_group,
+                    target_pc: included,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                });
+                program.emit_insn(Insn::RowId {
+                    cursor_id: self.frame_cursor_id,
+                    dest: reg,
+                });
+                program.emit_insn(Insn::Ne {
+                    lhs: reg,
+                    rhs: self.reg_rowid,
+                    target_pc: excluded,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                });
+                program.preassign_label_to_next_insn(included);
+            }
+        }
+    }
+
+    fn emit_nth_value_check(&self, program: &mut ProgramBuilder, function: &WindowFunction) {
+        if matches!(
+            function.func,
+            WindowFunctionKind::Builtin(WindowFunc::NthValue)
+        ) {
+            self.emit_positive_check(
+                program,
+                self.arg_reg(&function.args, 1),
+                "second argument to nth_value must be a positive integer",
+            );
+        }
+    }
+
+    /// Computes a frame function whose frame only grows: the rows between the end of the
+    /// frame of the previous row and the end of the frame of the current row are added to
+    /// the state kept across the rows of the partition.
+    fn emit_running_frame(
+        &self,
+        program: &mut ProgramBuilder,
+        t_ctx: &TranslateCtx,
+        function: &WindowFunction,
+        running_frame: &RunningFrame,
+        dest: usize,
+    ) -> Result<()> {
+        self.emit_nth_value_check(program, function);
+        let frame = &function.frame;
+        let end_reg = self.emit_frame_bound(program, frame.mode, &frame.end);
+        let frame_loop = program.allocate_label();
+        let advance = program.allocate_label();
+        let done = program.allocate_label();
+        program.preassign_label_to_next_insn(frame_loop);
+        program.emit_insn(Insn::SeekRowid {
+            cursor_id: self.frame_cursor_id,
+            src_reg: running_frame.frontier_reg,
+            target_pc: done,
+        });
+        self.emit_frame_bound_test(program, frame.mode, &frame.end, end_reg, true, done);
+        self.emit_frame_step(
+            program,
+            t_ctx,
+            function,
+            &running_frame.state,
+            advance,
+            None,
+        )?;
+        program.preassign_label_to_next_insn(advance);
+        program.emit_insn(Insn::AddImm {
+            register: running_frame.frontier_reg,
+            value: 1,
+        });
+        program.emit_insn(Insn::Goto {
+            target_pc: frame_loop,
+        });
+        program.preassign_label_to_next_insn(done);
+        match &running_frame.state {
+            FrameState::Aggregate { acc_reg, .. } => {
+                let WindowFunctionKind::Aggregate(func) = &function.func else {
+                    unreachable!("aggregate state for a builtin window function");
+                };
+                program.emit_insn(Insn::AggValue {
+                    acc_reg: *acc_reg,
+                    dest_reg: dest,
+                    func: func.clone(),
+                });
+            }
+            FrameState::Value { value_reg, .. } => {
+                program.emit_insn(Insn::Copy {
+                    src_reg: *value_reg,
+                    dst_reg: dest,
+                    extra_amount: 0,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes a frame function by visiting the whole frame of the current row.
+    fn emit_frame(
+        &self,
+        program: &mut ProgramBuilder,
+        t_ctx: &TranslateCtx,
+        function: &WindowFunction,
+        dest: usize,
+    ) -> Result<()> {
+        self.emit_nth_value_check(program, function);
+        let frame = &function.frame;
+        let state = self.alloc_frame_state(program, function, Some(dest));
+        let start_reg = self.emit_frame_bound(program, frame.mode, &frame.start);
+        let end_reg = self.emit_frame_bound(program, frame.mode, &frame.end);
+
+        // Position the frame cursor on the first row that may be part of the frame.
+        let seek_reg = match (frame.mode, &frame.start) {
+            (ast::FrameMode::Rows, WindowFrameBound::Preceding(_)) => {
+                let start_reg = start_reg.expect("frame start must have a bound");
+                let in_partition = program.allocate_label();
+                program.emit_insn(Insn::Ge {
+                    lhs: start_reg,
+                    rhs: self.reg_partition_start,
+                    target_pc: in_partition,
+                    flags: CmpInsFlags::default(),
+                    collation: None,
+                });
+                program.emit_insn(Insn::Copy {
+                    src_reg: self.reg_partition_start,
+                    dst_reg: start_reg,
+                    extra_amount: 0,
+                });
+                program.preassign_label_to_next_insn(in_partition);
+                start_reg
+            }
+            (
+                ast::FrameMode::Rows,
+                WindowFrameBound::CurrentRow | WindowFrameBound::Following(_),
+            ) => start_reg.expect("frame start must have a bound"),
+            (_, WindowFrameBound::CurrentRow) => self.reg_peer_start,
+            _ => self.reg_partition_start,
+        };
+        let frame_loop = program.allocate_label();
+        let next = program.allocate_label();
+        let done = program.allocate_label();
+        program.emit_insn(Insn::SeekRowid {
+            cursor_id: self.frame_cursor_id,
+            src_reg: seek_reg,
+            target_pc: done,
+        });
+        program.preassign_label_to_next_insn(frame_loop);
+        self.emit_frame_bound_test(program, frame.mode, &frame.end, end_reg, true, done);
+        if frame.mode != ast::FrameMode::Rows {
+            self.emit_frame_bound_test(program, frame.mode, &frame.start, start_reg, false, next);
+        }
+        self.emit_frame_exclude_test(program, frame.exclude.clone(), next);
+        self.emit_frame_step(program, t_ctx, function, &state, next, Some(done))?;
+        program.preassign_label_to_next_insn(next);
+        program.emit_insn(Insn::Next {
+            cursor_id: self.frame_cursor_id,
+            pc_if_next: frame_loop,
+        });
+        program.preassign_label_to_next_insn(done);
+        if let WindowFunctionKind::Aggregate(func) = &function.func {
+            program.emit_insn(Insn::AggFinal {
+                register: dest,
+                func: func.clone(),
+            });
+        }
+        Ok(())
+    }
+}
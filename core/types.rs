@@ -5,6 +5,7 @@ use turso_parser::ast::SortOrder;
 
 use crate::error::LimboError;
 use crate::ext::{ExtValue, ExtValueType};
+use crate::function::AggregateState;
 use crate::pseudo::PseudoCursor;
 use crate::schema::Index;
 use crate::storage::btree::BTreeCursor;
@@ -15,7 +16,9 @@ use crate::vdbe::sorter::Sorter;
 use crate::vdbe::Register;
 use crate::vtab::VirtualTableCursor;
 use crate::{turso_assert, Completion, CompletionError, Result, IO};
+use std::cell::RefCell;
 use std::fmt::{Debug, Display};
+use std::rc::Rc;
 
 const MAX_REAL_SIZE: u8 = 15;
 
@@ -408,6 +411,40 @@ impl ExternalAggState {
     }
 }
 
+/// State of an aggregate function defined by the application. Like [ExternalAggState], copies
+/// share the accumulator.
+#[derive(Clone)]
+pub struct NativeAggState {
+    pub state: Rc<RefCell<Box<dyn AggregateState>>>,
+    pub argc: usize,
+    pub finalized_value: Option<Value>,
+}
+
+impl NativeAggState {
+    pub fn new(state: Box<dyn AggregateState>, argc: usize) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(state)),
+            argc,
+            finalized_value: None,
+        }
+    }
+}
+
+impl Debug for NativeAggState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeAggState")
+            .field("argc", &self.argc)
+            .field("finalized_value", &self.finalized_value)
+            .finish()
+    }
+}
+
+impl PartialEq for NativeAggState {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state) && self.finalized_value == other.finalized_value
+    }
+}
+
 /// Please use Display trait for all limbo output so we have single origin of truth
 /// When you need value as string:
 /// ---GOOD---
@@ -729,17 +766,23 @@ pub enum AggContext {
     Min(Option<Value>),
     GroupConcat(Value),
     External(ExternalAggState),
+    Native(NativeAggState),
 }
 
 const NULL: Value = Value::Null;
 
 impl AggContext {
     pub fn compute_external(&mut self) -> Result<()> {
-        if let Self::External(ext_state) = self {
-            if ext_state.finalized_value.is_none() {
+        match self {
+            Self::External(ext_state) if ext_state.finalized_value.is_none() => {
                 let final_value = unsafe { (ext_state.finalize_fn)(ext_state.state) };
                 ext_state.cache_final_value(Value::from_ffi(final_value)?);
             }
+            Self::Native(native_state) if native_state.finalized_value.is_none() => {
+                let final_value = native_state.state.borrow_mut().finalize()?;
+                native_state.finalized_value = Some(final_value);
+            }
+            _ => {}
         }
         Ok(())
     }
@@ -753,6 +796,7 @@ impl AggContext {
             Self::Min(min) => min.as_ref().unwrap_or(&NULL),
             Self::GroupConcat(s) => s,
             Self::External(ext_state) => ext_state.finalized_value.as_ref().unwrap_or(&NULL),
+            Self::Native(native_state) => native_state.finalized_value.as_ref().unwrap_or(&NULL),
         }
     }
 }
@@ -1694,7 +1738,7 @@ fn sqlite_int_float_compare(int_val: i64, float_val: f64) -> std::cmp::Ordering
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KeyInfo {
     pub sort_order: SortOrder,
     pub collation: CollationSeq,
@@ -1734,7 +1778,7 @@ impl IndexInfo {
                     .iter()
                     .map(|c| KeyInfo {
                         sort_order: c.order,
-                        collation: c.collation.unwrap_or_default(),
+                        collation: c.collation.clone().unwrap_or_default(),
                     })
                     .collect();
                 if index.has_rowid {
@@ -1760,7 +1804,7 @@ pub fn compare_immutable(
     turso_assert!(column_info.len() >= l.len(), "column_info.len() < l.len()");
     for (i, (l, r)) in l.iter().zip(r).enumerate() {
         let column_order = column_info[i].sort_order;
-        let collation = column_info[i].collation;
+        let collation = &column_info[i].collation;
         let cmp = match (l, r) {
             (RefValue::Text(left), RefValue::Text(right)) => {
                 collation.compare_strings(left.as_str(), right.as_str())
@@ -2017,7 +2061,7 @@ fn compare_records_string(
         return compare_records_generic(serialized, unpacked, index_info, 0, tie_breaker);
     };
 
-    let collation = index_info.key_info[0].collation;
+    let collation = &index_info.key_info[0].collation;
     let comparison = collation.compare_strings(lhs_text.as_str(), rhs_text.as_str());
 
     let final_comparison = match index_info.key_info[0].sort_order {
@@ -2703,7 +2747,7 @@ mod tests {
 
         for i in 0..min_len {
             let column_order = index_key_info[i].sort_order;
-            let collation = index_key_info[i].collation;
+            let collation = &index_key_info[i].collation;
 
             let cmp = match (&l[i], &r[i]) {
                 (RefValue::Text(left), RefValue::Text(right)) => {
@@ -142,6 +142,8 @@ pub struct UnparsedFromSqlIndex {
     pub sql: String,
 }
 
+/// Adds the objects described by the `sqlite_schema` rows of `rows` to `schema`. The objects
+/// are marked as living in the `temp` database when `temporary` is set.
 #[instrument(skip_all, level = Level::INFO)]
 pub fn parse_schema_rows(
     mut rows: Statement,
@@ -149,6 +151,7 @@ pub fn parse_schema_rows(
     syms: &SymbolTable,
     mv_tx_id: Option<u64>,
     mut existing_views: MaterializedViewsMap,
+    temporary: bool,
 ) -> Result<()> {
     rows.set_mv_tx_id(mv_tx_id);
     // TODO: if we IO, this unparsed indexes is lost. Will probably need some state between
@@ -188,8 +191,18 @@ pub fn parse_schema_rows(
                             };
                             schema.add_virtual_table(vtab);
                         } else {
-                            let table = schema::BTreeTable::from_sql(sql, root_page as usize)?;
-                            schema.add_btree_table(Arc::new(table));
+                            match schema::BTreeTable::from_sql(sql, root_page as usize, syms) {
+                                Ok(mut table) => {
+                                    table.temporary = temporary;
+                                    schema.add_btree_table(Arc::new(table));
+                                }
+                                Err(e) => {
+                                    let Some(collation) = CollationSeq::missing(&e) else {
+                                        return Err(e);
+                                    };
+                                    schema.add_unresolved_table(row.get::<&str>(2)?, collation);
+                                }
+                            }
                         }
                     }
                     "index" => {
@@ -309,6 +322,7 @@ pub fn parse_schema_rows(
                                         sql: sql.to_string(),
                                         select_stmt: select,
                                         columns: final_columns,
+                                        temporary,
                                     };
                                     schema.add_view(view);
                                 }
@@ -316,6 +330,11 @@ pub fn parse_schema_rows(
                             }
                         }
                     }
+                    "trigger" => {
+                        let sql: &str = row.get::<&str>(4)?;
+                        let trigger = schema::Trigger::from_sql(sql)?;
+                        schema.add_trigger(trigger);
+                    }
                     _ => continue,
                 }
             }
@@ -330,21 +349,35 @@ pub fn parse_schema_rows(
         }
     }
     for unparsed_sql_from_index in from_sql_indexes {
+        if schema.is_table_unresolved(&unparsed_sql_from_index.table_name) {
+            continue;
+        }
         if !schema.indexes_enabled() {
             schema.table_set_has_index(&unparsed_sql_from_index.table_name);
         } else {
             let table = schema
                 .get_btree_table(&unparsed_sql_from_index.table_name)
                 .unwrap();
-            let index = schema::Index::from_sql(
+            match schema::Index::from_sql(
                 &unparsed_sql_from_index.sql,
                 unparsed_sql_from_index.root_page,
                 table.as_ref(),
-            )?;
-            schema.add_index(Arc::new(index));
+                syms,
+            ) {
+                Ok(index) => schema.add_index(Arc::new(index)),
+                Err(e) => {
+                    let Some(collation) = CollationSeq::missing(&e) else {
+                        return Err(e);
+                    };
+                    schema.add_unresolved_table(&unparsed_sql_from_index.table_name, collation);
+                }
+            }
         }
     }
     for automatic_index in automatic_indices {
+        if schema.is_table_unresolved(&automatic_index.0) {
+            continue;
+        }
         if !schema.indexes_enabled() {
             schema.table_set_has_index(&automatic_index.0);
         } else {
@@ -699,6 +732,7 @@ pub(crate) fn type_from_name(type_name: &str) -> (Type, bool) {
 
 pub fn columns_from_create_table_body(
     body: &turso_parser::ast::CreateTableBody,
+    syms: &SymbolTable,
 ) -> crate::Result<Vec<Column>> {
     let CreateTableBody::ColumnsAndConstraints { columns, .. } = body else {
         return Err(crate::LimboError::ParseError(
@@ -706,9 +740,10 @@ pub fn columns_from_create_table_body(
         ));
     };
 
-    use turso_parser::ast;
-
-    Ok(columns.iter().map(Into::into).collect())
+    columns
+        .iter()
+        .map(|column| Column::from_definition(column, syms))
+        .collect()
 }
 
 /// This function checks if a given expression is a constant value that can be pushed down to the database engine.
@@ -1202,8 +1237,13 @@ pub fn parse_pragma_bool(expr: &Expr) -> Result<bool> {
         if let Value::Integer(x @ (0 | 1)) = number {
             return Ok(x != 0);
         }
-    } else if let Expr::Name(name) = expr {
-        let ident = normalize_ident(name.as_str());
+    } else {
+        // `ON` is a keyword, the other values are names
+        let ident = match expr {
+            Expr::Name(name) => normalize_ident(name.as_str()),
+            Expr::Literal(Literal::Keyword(keyword)) => keyword.to_lowercase(),
+            _ => String::new(),
+        };
         if TRUE_VALUES.contains(&ident.as_str()) {
             return Ok(true);
         }
@@ -1236,18 +1276,25 @@ pub fn extract_view_columns(select_stmt: &ast::Select, schema: &Schema) -> Vec<C
         ..
     } = &select_stmt.body.select
     {
-        // First, we need to figure out which table(s) are being selected from
-        let table_name = if let Some(from) = from {
-            if let ast::SelectTable::Table(qualified_name, _, _) = from.select.as_ref() {
-                Some(normalize_ident(qualified_name.name.as_str()))
-            } else {
-                None
+        // First, we need to figure out which table(s) are being selected from, along with the
+        // name they are referred to by (the alias if there is one)
+        let mut from_tables = Vec::new();
+        if let Some(from) = from {
+            let selected = std::iter::once(from.select.as_ref())
+                .chain(from.joins.iter().map(|join| join.table.as_ref()));
+            for select_table in selected {
+                if let ast::SelectTable::Table(qualified_name, alias, _) = select_table {
+                    let table_name = normalize_ident(qualified_name.name.as_str());
+                    let reference = match alias {
+                        Some(ast::As::As(name) | ast::As::Elided(name)) => {
+                            normalize_ident(name.as_str())
+                        }
+                        None => table_name.clone(),
+                    };
+                    from_tables.push((reference, table_name));
+                }
             }
-        } else {
-            None
-        };
-        // Get the table for column resolution
-        let _table = table_name.as_ref().and_then(|name| schema.get_table(name));
+        }
         // Process each column in the SELECT list
         for (i, result_col) in select_columns.iter().enumerate() {
             match result_col {
@@ -1274,14 +1321,19 @@ pub fn extract_view_columns(select_stmt: &ast::Select, schema: &Schema) -> Vec<C
                         unique: false,
                         collation: None,
                         hidden: false,
+                        generated: None,
                     });
                 }
                 ast::ResultColumn::Star => {
                     // For SELECT *, expand to all columns from the table
-                    if let Some(ref table_name) = table_name {
-                        if let Some(table) = schema.get_table(table_name) {
-                            // Copy all columns from the table, but adjust for view constraints
-                            for table_column in table.columns() {
+                    if !from_tables.is_empty() {
+                        let tables = from_tables
+                            .iter()
+                            .map(|(_, table_name)| schema.get_table(table_name))
+                            .collect::<Option<Vec<_>>>();
+                        if let Some(tables) = tables {
+                            // Copy all columns from the tables, but adjust for view constraints
+                            for table_column in tables.iter().flat_map(|table| table.columns()) {
                                 columns.push(Column {
                                     name: table_column.name.clone(),
                                     ty: table_column.ty,
@@ -1291,8 +1343,9 @@ pub fn extract_view_columns(select_stmt: &ast::Select, schema: &Schema) -> Vec<C
                                     notnull: false, // Views typically don't enforce NOT NULL
                                     default: None,  // Views don't have default values
                                     unique: false,
-                                    collation: table_column.collation,
+                                    collation: table_column.collation.clone(),
                                     hidden: false,
+                                    generated: None,
                                 });
                             }
                         } else {
@@ -1308,6 +1361,7 @@ pub fn extract_view_columns(select_stmt: &ast::Select, schema: &Schema) -> Vec<C
                                 unique: false,
                                 collation: None,
                                 hidden: false,
+                                generated: None,
                             });
                         }
                     } else {
@@ -1323,13 +1377,20 @@ pub fn extract_view_columns(select_stmt: &ast::Select, schema: &Schema) -> Vec<C
                             unique: false,
                             collation: None,
                             hidden: false,
+                            generated: None,
                         });
                     }
                 }
                 ast::ResultColumn::TableStar(table_name) => {
                     // For table.*, expand to all columns from the specified table
                     let table_name_str = normalize_ident(table_name.as_str());
-                    if let Some(table) = schema.get_table(&table_name_str) {
+                    let resolved_name = from_tables
+                        .iter()
+                        .find(|(reference, _)| *reference == table_name_str)
+                        .map_or(table_name_str.as_str(), |(_, table_name)| {
+                            table_name.as_str()
+                        });
+                    if let Some(table) = schema.get_table(resolved_name) {
                         // Copy all columns from the table, but adjust for view constraints
                         for table_column in table.columns() {
                             columns.push(Column {
@@ -1341,8 +1402,9 @@ pub fn extract_view_columns(select_stmt: &ast::Select, schema: &Schema) -> Vec<C
                                 notnull: false,
                                 default: None,
                                 unique: false,
-                                collation: table_column.collation,
+                                collation: table_column.collation.clone(),
                                 hidden: false,
+                                generated: None,
                             });
                         }
                     } else {
@@ -1358,6 +1420,7 @@ pub fn extract_view_columns(select_stmt: &ast::Select, schema: &Schema) -> Vec<C
                             unique: false,
                             collation: None,
                             hidden: false,
+                            generated: None,
                         });
                     }
                 }
@@ -2348,6 +2411,7 @@ pub mod tests {
         assert!(parse_pragma_bool(&Expr::Literal(Literal::Numeric("1".into()))).unwrap(),);
         assert!(parse_pragma_bool(&Expr::Name(Name::Ident("true".into()))).unwrap(),);
         assert!(parse_pragma_bool(&Expr::Name(Name::Ident("on".into()))).unwrap(),);
+        assert!(parse_pragma_bool(&Expr::Literal(Literal::Keyword("ON".into()))).unwrap(),);
         assert!(parse_pragma_bool(&Expr::Name(Name::Ident("yes".into()))).unwrap(),);
 
         assert!(!parse_pragma_bool(&Expr::Literal(Literal::Numeric("0".into()))).unwrap(),);
@@ -0,0 +1,245 @@
+//! VACUUM support.
+//!
+//! Both forms of VACUUM rebuild the database by copying its schema and contents into a fresh
+//! database, which leaves no free pages behind and stores every b-tree contiguously:
+//!
+//! - `VACUUM INTO 'file'` writes the copy to a new database file. The copy is made inside the
+//!   read transaction of the statement, so it is a consistent snapshot and writers on other
+//!   connections are not blocked.
+//! - `VACUUM` builds the copy in a temporary database file and then writes its pages back over
+//!   the original database inside the write transaction of the statement.
+//!
+//! Both functions are called with the connection in nested statement mode, so the statements
+//! reading from it run inside the transaction of the VACUUM statement.
+
+use crate::storage::pager::Pager;
+use crate::{Connection, LimboError, Result};
+use std::sync::Arc;
+
+#[cfg(feature = "fs")]
+use crate::incremental::persistence::DBSP_STATE_TABLE_PREFIX;
+#[cfg(feature = "fs")]
+use crate::{
+    types::Value, util::IOExt as _, CheckpointMode, Database, DatabaseOpts, OpenFlags, StepResult,
+};
+#[cfg(feature = "fs")]
+use std::num::NonZero;
+
+/// Copies the main database of `conn` into a new database file at `path`.
+#[cfg(feature = "fs")]
+pub(crate) fn vacuum_into(conn: &Arc<Connection>, path: &str) -> Result<()> {
+    if std::fs::metadata(path).is_ok_and(|metadata| metadata.len() > 0) {
+        return Err(LimboError::InvalidArgument(
+            "output file already exists".to_string(),
+        ));
+    }
+    let (_io, db) = Database::open_new(
+        path,
+        None::<&str>,
+        OpenFlags::default(),
+        database_opts(conn)?,
+    )?;
+    let dest = db.connect()?;
+    let result = write_copy(conn, &dest);
+    let closed = dest.close();
+    if result.is_err() || closed.is_err() {
+        // Don't leave an incomplete copy behind.
+        drop(db);
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(format!("{path}-wal"));
+    }
+    result.and(closed)
+}
+
+/// Copies the main database of `conn` into the empty database of `dest`, header fields
+/// included, and checkpoints the copy into its database file.
+#[cfg(feature = "fs")]
+fn write_copy(conn: &Arc<Connection>, dest: &Arc<Connection>) -> Result<()> {
+    copy_database(conn, dest)?;
+    let pager = conn.get_pager();
+    let (user_version, application_id) = pager.io.block(|| {
+        pager.with_header(|header| (header.user_version.get(), header.application_id.get()))
+    })?;
+    dest.execute(format!("PRAGMA user_version = {user_version}"))?;
+    dest.execute(format!("PRAGMA application_id = {application_id}"))?;
+    dest.checkpoint(CheckpointMode::Truncate {
+        upper_bound_inclusive: None,
+    })?;
+    Ok(())
+}
+
+#[cfg(not(feature = "fs"))]
+pub(crate) fn vacuum_into(_conn: &Arc<Connection>, _path: &str) -> Result<()> {
+    Err(LimboError::InvalidArgument(
+        "VACUUM INTO not available in this build (no-fs)".to_string(),
+    ))
+}
+
+/// Rebuilds the main database of `conn` in place. Must be called inside a write transaction
+/// on `pager`.
+#[cfg(feature = "fs")]
+pub(crate) fn vacuum(conn: &Arc<Connection>, pager: &Pager) -> Result<()> {
+    // The copy can be as large as the database itself, so it goes to a file rather than to
+    // memory. The directory is removed once the copy has been written back.
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("vacuum.db");
+    let Some(path) = path.to_str() else {
+        return Err(LimboError::InternalError(
+            "Failed to convert path to string".to_string(),
+        ));
+    };
+    let db = Database::open_file_with_flags(
+        pager.io.clone(),
+        path,
+        OpenFlags::default(),
+        database_opts(conn)?,
+    )?;
+    let temp = db.connect()?;
+    copy_database(conn, &temp)?;
+
+    let temp_pager = temp.get_pager();
+    temp_pager.begin_read_tx()?;
+    let result = pager.copy_from(&temp_pager);
+    temp_pager.end_read_tx()?;
+    result.and(temp.close())
+}
+
+#[cfg(not(feature = "fs"))]
+pub(crate) fn vacuum(_conn: &Arc<Connection>, _pager: &Pager) -> Result<()> {
+    Err(LimboError::InvalidArgument(
+        "VACUUM not available in this build (no-fs)".to_string(),
+    ))
+}
+
+/// Options for the database receiving the copy, matching the ones of the source database.
+#[cfg(feature = "fs")]
+fn database_opts(conn: &Arc<Connection>) -> Result<DatabaseOpts> {
+    let use_indexes = conn
+        ._db
+        .schema
+        .lock()
+        .map_err(|_| LimboError::SchemaLocked)?
+        .indexes_enabled();
+    Ok(DatabaseOpts::new()
+        .with_indexes(use_indexes)
+        .with_views(conn._db.experimental_views_enabled())
+        .with_strict(conn._db.experimental_strict_enabled()))
+}
+
+/// Copies the schema and the rows of the main database of `source` into the empty database
+/// behind `dest`. Internal tables, whose names start with `sqlite_`, are not copied.
+#[cfg(feature = "fs")]
+fn copy_database(source: &Arc<Connection>, dest: &Arc<Connection>) -> Result<()> {
+    let page_size = {
+        let pager = source.get_pager();
+        pager
+            .io
+            .block(|| pager.with_header(|header| header.page_size))?
+            .get()
+    };
+    dest.reset_page_size(page_size)?;
+    dest.syms.borrow_mut().extend(&source.syms.borrow());
+
+    let entries = source
+        .prepare(
+            "SELECT type, name, sql FROM sqlite_schema \
+             WHERE sql IS NOT NULL AND substr(name, 1, 7) <> 'sqlite_' ORDER BY rowid",
+        )?
+        .run_collect_rows()?;
+    let entries: Vec<(String, String, String)> = entries
+        .into_iter()
+        .filter_map(|row| match row.as_slice() {
+            [Value::Text(ty), Value::Text(name), Value::Text(sql)] => Some((
+                ty.as_str().to_string(),
+                name.as_str().to_string(),
+                sql.as_str().to_string(),
+            )),
+            _ => None,
+        })
+        .collect();
+
+    dest.execute("BEGIN")?;
+    match copy_entries(source, dest, &entries) {
+        Ok(()) => dest.execute("COMMIT"),
+        Err(err) => {
+            dest.execute("ROLLBACK")?;
+            Err(err)
+        }
+    }
+}
+
+/// Creates the schema `entries` (`(type, name, sql)` tuples) in `dest`. Tables come first,
+/// together with their rows, then everything built on top of them, so that indexes are built
+/// in one pass over the copied data.
+#[cfg(feature = "fs")]
+fn copy_entries(
+    source: &Arc<Connection>,
+    dest: &Arc<Connection>,
+    entries: &[(String, String, String)],
+) -> Result<()> {
+    // The state tables of materialized views are created by the views themselves
+    let is_state_table = |name: &str| name.starts_with(DBSP_STATE_TABLE_PREFIX);
+    for (_, name, sql) in entries.iter().filter(|(ty, ..)| ty == "table") {
+        if !is_state_table(name) {
+            dest.execute(sql)?;
+            copy_rows(source, dest, name)?;
+        }
+    }
+    for (_, _, sql) in entries.iter().filter(|(ty, ..)| ty != "table") {
+        dest.execute(sql)?;
+    }
+    // The views of `source` keep track of the rows of their state tables, so those rows are
+    // copied as they are rather than rebuilt
+    for (_, name, _) in entries.iter().filter(|(_, name, _)| is_state_table(name)) {
+        dest.execute(format!("DELETE FROM {}", quote_identifier(name)))?;
+        copy_rows(source, dest, name)?;
+    }
+    Ok(())
+}
+
+/// Copies every row of table `name`, preserving rowids.
+#[cfg(feature = "fs")]
+fn copy_rows(source: &Arc<Connection>, dest: &Arc<Connection>, name: &str) -> Result<()> {
+    let Some(table) = source.schema.borrow().get_btree_table(name) else {
+        // Virtual tables have no rows of their own.
+        return Ok(());
+    };
+    // Generated columns are computed again when the rows are inserted.
+    let mut columns: Vec<String> = table
+        .columns
+        .iter()
+        .filter(|column| column.generated.is_none())
+        .map(|column| quote_identifier(column.name.as_deref().unwrap_or_default()))
+        .collect();
+    if table.has_rowid && table.get_rowid_alias_column().is_none() {
+        columns.insert(0, "rowid".to_string());
+    }
+    let columns_list = columns.join(", ");
+    let placeholders = vec!["?"; columns.len()].join(", ");
+    let table_name = quote_identifier(name);
+
+    let mut rows = source.prepare(format!("SELECT {columns_list} FROM {table_name}"))?;
+    let mut insert = dest.prepare(format!(
+        "INSERT INTO {table_name} ({columns_list}) VALUES ({placeholders})"
+    ))?;
+    loop {
+        match rows.step()? {
+            StepResult::Row => {
+                let row = rows.row().unwrap();
+                insert.reset();
+                for (i, value) in row.get_values().enumerate() {
+                    insert.bind_at(NonZero::new(i + 1).unwrap(), value.clone());
+                }
+                insert.run_ignore_rows()?;
+            }
+            StepResult::IO => rows.run_once()?,
+            StepResult::Done => return Ok(()),
+            StepResult::Interrupt | StepResult::Busy => return Err(LimboError::Busy),
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
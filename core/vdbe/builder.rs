@@ -11,6 +11,7 @@ use crate::{
         collate::CollationSeq,
         emitter::TransactionMode,
         plan::{ResultSetColumn, TableReferences},
+        trigger::TriggerFrame,
     },
     CaptureDataChangesMode, Connection, Value, VirtualTable,
 };
@@ -113,7 +114,16 @@ pub struct ProgramBuilder {
     capture_data_changes_mode: CaptureDataChangesMode,
     // TODO: when we support multiple dbs, this should be a write mask to track which DBs need to be written
     txn_mode: TransactionMode,
+    /// Transactions on attached databases: the database index, whether the program writes to
+    /// it and the schema version the program was translated with.
+    attached_transactions: Vec<(usize, bool, u32)>,
+    /// Whether the program only writes to attached databases, in which case it only reads the
+    /// main database.
+    attached_writes_only: bool,
     rollback: bool,
+    /// Names of the triggers whose bodies are being compiled into this program, innermost last.
+    /// Used to stop recursive trigger firing and to reject RAISE() outside of triggers.
+    trigger_stack: Vec<TriggerFrame>,
 }
 
 #[derive(Debug, Clone)]
@@ -182,7 +192,10 @@ impl ProgramBuilder {
             start_offset: BranchOffset::Placeholder,
             capture_data_changes_mode,
             txn_mode: TransactionMode::None,
+            attached_transactions: Vec::new(),
+            attached_writes_only: false,
             rollback: false,
+            trigger_stack: Vec::new(),
         }
     }
 
@@ -190,6 +203,19 @@ impl ProgramBuilder {
         &self.capture_data_changes_mode
     }
 
+    pub fn trigger_stack(&self) -> &[TriggerFrame] {
+        &self.trigger_stack
+    }
+
+    pub fn set_trigger_stack(&mut self, trigger_stack: Vec<TriggerFrame>) {
+        self.trigger_stack = trigger_stack;
+    }
+
+    /// Whether this program is the body of a trigger
+    pub fn is_trigger_program(&self) -> bool {
+        !self.trigger_stack.is_empty()
+    }
+
     pub fn extend(&mut self, opts: &ProgramBuilderOpts) {
         self.insns.reserve(opts.approx_num_insns);
         self.cursor_ref.reserve(opts.num_cursors);
@@ -699,6 +725,14 @@ impl ProgramBuilder {
                 Insn::NotFound { target_pc, .. } => {
                     resolve(target_pc, "NotFound");
                 }
+                Insn::Program {
+                    ignore_jump_target, ..
+                } => {
+                    resolve(ignore_jump_target, "Program");
+                }
+                Insn::FkIfZero { target_pc, .. } => {
+                    resolve(target_pc, "FkIfZero");
+                }
                 _ => {}
             }
         }
@@ -722,11 +756,11 @@ impl ProgramBuilder {
     }
 
     pub fn curr_collation_ctx(&self) -> Option<(CollationSeq, bool)> {
-        self.collation
+        self.collation.clone()
     }
 
     pub fn curr_collation(&self) -> Option<CollationSeq> {
-        self.collation.map(|c| c.0)
+        self.collation.as_ref().map(|c| c.0.clone())
     }
 
     pub fn reset_collation(&mut self) {
@@ -770,6 +804,34 @@ impl ProgramBuilder {
         }
     }
 
+    /// Registers the transactions the program needs on attached databases, i.e. on the ones its
+    /// instructions read or write. `schema_cookie` returns the schema version of a database the
+    /// program was translated with.
+    pub fn begin_attached_operations(&mut self, schema_cookie: impl Fn(usize) -> u32) {
+        let mut databases = std::collections::BTreeMap::<usize, bool>::new();
+        let mut writes_main_database = false;
+        for (insn, ..) in &self.insns {
+            let (db, write) = match insn {
+                Insn::OpenRead { db, .. } | Insn::ReadCookie { db, .. } => (*db, false),
+                Insn::OpenWrite { db, .. }
+                | Insn::CreateBtree { db, .. }
+                | Insn::SetCookie { db, .. }
+                | Insn::Destroy { is_temp: db, .. } => (*db, true),
+                _ => continue,
+            };
+            if db > 1 {
+                *databases.entry(db).or_default() |= write;
+            } else {
+                writes_main_database |= write;
+            }
+        }
+        self.attached_writes_only = !writes_main_database && databases.values().any(|&write| write);
+        self.attached_transactions = databases
+            .into_iter()
+            .map(|(db, write)| (db, write, schema_cookie(db)))
+            .collect();
+    }
+
     /// Indicates the rollback behvaiour for the halt instruction in epilogue
     pub fn rollback(&mut self) {
         self.rollback = true;
@@ -784,13 +846,23 @@ impl ProgramBuilder {
             self.emit_halt(self.rollback);
             self.preassign_label_to_next_insn(self.init_label);
 
-            if !matches!(self.txn_mode, TransactionMode::None) {
+            if !matches!(self.txn_mode, TransactionMode::None)
+                || !self.attached_transactions.is_empty()
+            {
                 self.emit_insn(Insn::Transaction {
                     db: 0,
-                    write: matches!(self.txn_mode, TransactionMode::Write),
+                    write: matches!(self.txn_mode, TransactionMode::Write)
+                        && !self.attached_writes_only,
                     schema_cookie: schema.schema_version,
                 });
             }
+            for (db, write, schema_cookie) in std::mem::take(&mut self.attached_transactions) {
+                self.emit_insn(Insn::Transaction {
+                    db,
+                    write,
+                    schema_cookie,
+                });
+            }
 
             self.emit_constant_insns();
             self.emit_insn(Insn::Goto {
@@ -896,6 +968,23 @@ impl ProgramBuilder {
             })
         };
 
+        // VIRTUAL generated columns are not stored in the record and the record of a WITHOUT
+        // ROWID table starts with its PRIMARY KEY, so the position of a column in the record can
+        // differ from its position in the table.
+        let column = match cursor_type {
+            CursorType::BTreeTable(btree) => match btree.record_index(column) {
+                Some(record_idx) => record_idx,
+                None => {
+                    self.emit_insn(Insn::Null {
+                        dest: out,
+                        dest_end: None,
+                    });
+                    return;
+                }
+            },
+            _ => column,
+        };
+
         self.emit_insn(Insn::Column {
             cursor_id,
             column,
@@ -12,7 +12,7 @@ use crate::storage::sqlite3_ondisk::read_varint;
 use crate::translate::collate::CollationSeq;
 use crate::types::{
     compare_immutable, compare_records_generic, Extendable, IOCompletions, ImmutableRecord,
-    SeekResult, Text,
+    IndexInfo, SeekResult, Text,
 };
 use crate::util::{normalize_ident, IOExt as _};
 use crate::vdbe::insn::InsertFlags;
@@ -21,7 +21,9 @@ use crate::vector::{vector_concat, vector_slice};
 use crate::MvCursor;
 use crate::{
     error::{
-        LimboError, SQLITE_CONSTRAINT, SQLITE_CONSTRAINT_NOTNULL, SQLITE_CONSTRAINT_PRIMARYKEY,
+        LimboError, SQLITE_CONSTRAINT, SQLITE_CONSTRAINT_CHECK, SQLITE_CONSTRAINT_FOREIGNKEY,
+        SQLITE_CONSTRAINT_NOTNULL, SQLITE_CONSTRAINT_PRIMARYKEY, SQLITE_CONSTRAINT_TRIGGER,
+        SQLITE_ERROR,
     },
     ext::ExtValue,
     function::{AggFunc, ExtFunc, MathFunc, MathFuncArity, ScalarFunc, VectorFunc},
@@ -51,8 +53,8 @@ use crate::{
 use crate::{
     storage::wal::CheckpointResult,
     types::{
-        AggContext, Cursor, ExternalAggState, IOResult, SeekKey, SeekOp, SumAggState, Value,
-        ValueType,
+        AggContext, Cursor, ExternalAggState, IOResult, NativeAggState, SeekKey, SeekOp,
+        SumAggState, Value, ValueType,
     },
     util::{
         cast_real_to_integer, cast_text_to_integer, cast_text_to_numeric, cast_text_to_real,
@@ -68,7 +70,7 @@ use crate::{
 use crate::{info, turso_assert, OpenFlags, RefValue, Row, TransactionState};
 
 use super::{
-    insn::{Cookie, RegisterOrLiteral},
+    insn::{Cookie, RegisterOrLiteral, SavepointOp},
     CommitState,
 };
 use parking_lot::RwLock;
@@ -137,7 +139,7 @@ pub type InsnFunction = fn(
 fn compare_with_collation(
     lhs: &Value,
     rhs: &Value,
-    collation: Option<CollationSeq>,
+    collation: Option<&CollationSeq>,
 ) -> std::cmp::Ordering {
     match (lhs, rhs) {
         (Value::Text(lhs_text), Value::Text(rhs_text)) => {
@@ -257,10 +259,10 @@ pub fn op_drop_index(
     pager: &Rc<Pager>,
     mv_store: Option<&Arc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
-    load_insn!(DropIndex { index, db: _ }, insn);
+    load_insn!(DropIndex { index, db }, insn);
     program
         .connection
-        .with_schema_mut(|schema| schema.remove_index(index));
+        .with_database_schema_mut(*db, |schema| schema.remove_index(index));
     state.pc += 1;
     Ok(InsnFunctionStepResult::Step)
 }
@@ -409,6 +411,8 @@ pub fn op_null_row(
         let cursor = cursor.as_btree_mut();
         cursor.set_null_flag(true);
     }
+    // A pending DeferredSeek would move the cursor off the NULL row.
+    state.deferred_seeks[*cursor_id] = None;
     state.pc += 1;
     Ok(InsnFunctionStepResult::Step)
 }
@@ -432,7 +436,7 @@ pub fn op_compare(
     let start_reg_a = *start_reg_a;
     let start_reg_b = *start_reg_b;
     let count = *count;
-    let collation = collation.unwrap_or_default();
+    let collation = collation.clone().unwrap_or_default();
 
     if start_reg_a + count > start_reg_b {
         return Err(LimboError::InternalError(
@@ -659,7 +663,7 @@ pub fn op_comparison(
             *rhs,
             *target_pc,
             *flags,
-            collation.unwrap_or_default(),
+            collation.clone().unwrap_or_default(),
             ComparisonOp::Eq,
         ),
         Insn::Ne {
@@ -673,7 +677,7 @@ pub fn op_comparison(
             *rhs,
             *target_pc,
             *flags,
-            collation.unwrap_or_default(),
+            collation.clone().unwrap_or_default(),
             ComparisonOp::Ne,
         ),
         Insn::Lt {
@@ -687,7 +691,7 @@ pub fn op_comparison(
             *rhs,
             *target_pc,
             *flags,
-            collation.unwrap_or_default(),
+            collation.clone().unwrap_or_default(),
             ComparisonOp::Lt,
         ),
         Insn::Le {
@@ -701,7 +705,7 @@ pub fn op_comparison(
             *rhs,
             *target_pc,
             *flags,
-            collation.unwrap_or_default(),
+            collation.clone().unwrap_or_default(),
             ComparisonOp::Le,
         ),
         Insn::Gt {
@@ -715,7 +719,7 @@ pub fn op_comparison(
             *rhs,
             *target_pc,
             *flags,
-            collation.unwrap_or_default(),
+            collation.clone().unwrap_or_default(),
             ComparisonOp::Gt,
         ),
         Insn::Ge {
@@ -729,7 +733,7 @@ pub fn op_comparison(
             *rhs,
             *target_pc,
             *flags,
-            collation.unwrap_or_default(),
+            collation.clone().unwrap_or_default(),
             ComparisonOp::Ge,
         ),
         _ => unreachable!("unexpected Insn {:?}", insn),
@@ -919,6 +923,17 @@ pub fn op_if_not(
     Ok(InsnFunctionStepResult::Step)
 }
 
+/// The database to open a cursor on. TEMP tables and indexes are found in the schema of the
+/// connection like the ones of the main database, so some statements name the main database
+/// for them.
+fn cursor_database(cursor_type: &CursorType, db: usize) -> usize {
+    match cursor_type {
+        CursorType::BTreeTable(table) if table.temporary => 1,
+        CursorType::BTreeIndex(index) if index.temporary => 1,
+        _ => db,
+    }
+}
+
 pub fn op_open_read(
     program: &Program,
     state: &mut ProgramState,
@@ -935,20 +950,9 @@ pub fn op_open_read(
         insn
     );
 
-    let pager = program.get_pager_from_database_index(db);
-
     let (_, cursor_type) = program.cursor_ref.get(*cursor_id).unwrap();
-    let mv_cursor = match program.connection.mv_tx_id.get() {
-        Some(tx_id) => {
-            let table_id = *root_page as u64;
-            let mv_store = mv_store.unwrap().clone();
-            let mv_cursor = Rc::new(RefCell::new(
-                MvCursor::new(mv_store, tx_id, table_id, pager.clone()).unwrap(),
-            ));
-            Some(mv_cursor)
-        }
-        None => None,
-    };
+    let pager = program.get_pager_from_database_index(&cursor_database(cursor_type, *db))?;
+    let mv_cursor = new_mv_cursor(program, mv_store, cursor_type, *root_page as u64, &pager)?;
     let mut cursors = state.cursors.borrow_mut();
     let num_columns = match cursor_type {
         CursorType::BTreeTable(table_rc) => table_rc.columns.len(),
@@ -957,6 +961,23 @@ pub fn op_open_read(
     };
 
     match cursor_type {
+        CursorType::BTreeTable(table) if !table.has_rowid => {
+            // WITHOUT ROWID tables are stored as a clustered index keyed by the primary key.
+            let index = table
+                .clustered_index()
+                .expect("WITHOUT ROWID table without PK");
+            let cursor = BTreeCursor::new_index(
+                mv_cursor,
+                pager.clone(),
+                *root_page,
+                index.as_ref(),
+                num_columns,
+            );
+            cursors
+                .get_mut(*cursor_id)
+                .unwrap()
+                .replace(Cursor::new_btree(cursor));
+        }
         CursorType::BTreeTable(_) => {
             let cursor = BTreeCursor::new_table(mv_cursor, pager.clone(), *root_page, num_columns);
             cursors
@@ -991,6 +1012,34 @@ pub fn op_open_read(
     Ok(InsnFunctionStepResult::Step)
 }
 
+/// Creates the MVCC cursor of a B-tree cursor opened in an MVCC transaction. Indexes, including
+/// the clustered index of a WITHOUT ROWID table, get a cursor over versioned index entries.
+fn new_mv_cursor(
+    program: &Program,
+    mv_store: Option<&Arc<MvStore>>,
+    cursor_type: &CursorType,
+    root_page: u64,
+    pager: &Rc<Pager>,
+) -> Result<Option<Rc<RefCell<MvCursor>>>> {
+    let Some(tx_id) = program.connection.mv_tx_id.get() else {
+        return Ok(None);
+    };
+    let mv_store = mv_store.unwrap().clone();
+    let index = match cursor_type {
+        CursorType::BTreeIndex(index) => Some(index.clone()),
+        CursorType::BTreeTable(table) if !table.has_rowid => table.clustered_index(),
+        _ => None,
+    };
+    let mv_cursor = match index {
+        Some(index) => {
+            let index_info = Arc::new(IndexInfo::new_from_index(&index));
+            MvCursor::new_index(mv_store, tx_id, root_page, index_info, pager.clone())?
+        }
+        None => MvCursor::new(mv_store, tx_id, root_page, pager.clone())?,
+    };
+    Ok(Some(Rc::new(RefCell::new(mv_cursor))))
+}
+
 pub fn op_vopen(
     program: &Program,
     state: &mut ProgramState,
@@ -1188,6 +1237,23 @@ pub fn op_vupdate(
     Ok(InsnFunctionStepResult::Step)
 }
 
+pub fn op_vbegin(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    load_insn!(VBegin { cursor_id }, insn);
+    let (_, cursor_type) = program.cursor_ref.get(*cursor_id).unwrap();
+    let CursorType::VirtualTable(virtual_table) = cursor_type else {
+        panic!("VBegin on non-virtual table cursor");
+    };
+    program.connection.begin_vtab_transaction(virtual_table)?;
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_vnext(
     program: &Program,
     state: &mut ProgramState,
@@ -1232,6 +1298,7 @@ pub fn op_vdestroy(
                 "Could not find Virtual Table to Destroy".to_string(),
             ));
         };
+        conn.forget_vtab_transaction(&vtab);
         vtab.destroy()?;
     }
 
@@ -1239,6 +1306,31 @@ pub fn op_vdestroy(
     Ok(InsnFunctionStepResult::Step)
 }
 
+pub fn op_vrename(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    load_insn!(
+        VRename {
+            table_name,
+            new_name
+        },
+        insn
+    );
+    let conn = program.connection.clone();
+    let Some(vtab) = conn.syms.borrow().vtabs.get(table_name).cloned() else {
+        return Err(crate::LimboError::InternalError(
+            "Could not find Virtual Table to Rename".to_string(),
+        ));
+    };
+    vtab.rename(new_name)?;
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_open_pseudo(
     program: &Program,
     state: &mut ProgramState,
@@ -1284,9 +1376,12 @@ pub fn op_rewind(
     let is_empty = {
         let mut cursor = must_be_btree_cursor!(*cursor_id, program.cursor_ref, state, "Rewind");
         let cursor = cursor.as_btree_mut();
+        cursor.set_null_flag(false);
         return_if_io!(cursor.rewind());
         cursor.is_empty()
     };
+    // The cursor no longer points to the row of a pending DeferredSeek.
+    state.deferred_seeks[*cursor_id] = None;
     if is_empty {
         state.pc = pc_if_empty.as_offset_int();
     } else {
@@ -1315,9 +1410,12 @@ pub fn op_last(
     let is_empty = {
         let mut cursor = must_be_btree_cursor!(*cursor_id, program.cursor_ref, state, "Last");
         let cursor = cursor.as_btree_mut();
+        cursor.set_null_flag(false);
         return_if_io!(cursor.last());
         cursor.is_empty()
     };
+    // The cursor no longer points to the row of a pending DeferredSeek.
+    state.deferred_seeks[*cursor_id] = None;
     if is_empty {
         state.pc = pc_if_empty.as_offset_int();
     } else {
@@ -1845,10 +1943,16 @@ pub fn op_next(
     let is_empty = {
         let mut cursor = must_be_btree_cursor!(*cursor_id, program.cursor_ref, state, "Next");
         let cursor = cursor.as_btree_mut();
-        cursor.set_null_flag(false);
-        return_if_io!(cursor.next());
-
-        cursor.is_empty()
+        if cursor.get_null_flag() {
+            // Like in SQLite, a cursor set to a NULL row stands on no row and has no next row.
+            // It may not have been positioned at all, e.g. the inner cursor of a LEFT JOIN whose
+            // seek was skipped for a NULL key.
+            cursor.set_null_flag(false);
+            true
+        } else {
+            return_if_io!(cursor.next());
+            cursor.is_empty()
+        }
     };
     if !is_empty {
         // Increment metrics for row read
@@ -1887,10 +1991,16 @@ pub fn op_prev(
     let is_empty = {
         let mut cursor = must_be_btree_cursor!(*cursor_id, program.cursor_ref, state, "Prev");
         let cursor = cursor.as_btree_mut();
-        cursor.set_null_flag(false);
-        return_if_io!(cursor.prev());
-
-        cursor.is_empty()
+        if cursor.get_null_flag() {
+            // Like in SQLite, a cursor set to a NULL row stands on no row and has no prev row.
+            // It may not have been positioned at all, e.g. the inner cursor of a LEFT JOIN whose
+            // seek was skipped for a NULL key.
+            cursor.set_null_flag(false);
+            true
+        } else {
+            return_if_io!(cursor.prev());
+            cursor.is_empty()
+        }
     };
     if !is_empty {
         // Increment metrics for row read
@@ -1925,6 +2035,9 @@ pub fn halt(
     }
     match err_code {
         0 => {}
+        SQLITE_ERROR | SQLITE_CONSTRAINT_TRIGGER => {
+            return Err(LimboError::Constraint(description.to_string()));
+        }
         SQLITE_CONSTRAINT_PRIMARYKEY => {
             return Err(LimboError::Constraint(format!(
                 "UNIQUE constraint failed: {description} (19)"
@@ -1935,6 +2048,16 @@ pub fn halt(
                 "NOT NULL constraint failed: {description} (19)"
             )));
         }
+        SQLITE_CONSTRAINT_CHECK => {
+            return Err(LimboError::Constraint(format!(
+                "CHECK constraint failed: {description} (19)"
+            )));
+        }
+        SQLITE_CONSTRAINT_FOREIGNKEY => {
+            return Err(LimboError::Constraint(
+                "FOREIGN KEY constraint failed".to_string(),
+            ));
+        }
         _ => {
             return Err(LimboError::Constraint(format!(
                 "undocumented halt error code {description}"
@@ -1943,6 +2066,17 @@ pub fn halt(
     }
 
     let auto_commit = program.connection.auto_commit.get();
+    if !program.connection.is_nested_stmt.get() {
+        // Foreign key violations are only reported once the whole statement has run, since
+        // a later row may resolve a violation caused by an earlier one.
+        if state.fk_immediate_violations > 0
+            || (auto_commit && program.connection.deferred_fk_violations() > 0)
+        {
+            return Err(LimboError::Constraint(
+                "FOREIGN KEY constraint failed".to_string(),
+            ));
+        }
+    }
     tracing::trace!("halt(auto_commit={})", auto_commit);
     if auto_commit {
         program
@@ -1970,6 +2104,19 @@ pub fn op_halt(
     halt(program, state, pager, mv_store, *err_code, description)
 }
 
+pub fn op_raise_ignore(
+    _program: &Program,
+    state: &mut ProgramState,
+    _insn: &Insn,
+    _pager: &Rc<Pager>,
+    _mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    // Stop the sub-program without an error and let the calling Program instruction skip the
+    // rest of the current row.
+    state.raise_ignore = true;
+    Ok(InsnFunctionStepResult::Done)
+}
+
 pub fn op_halt_if_null(
     program: &Program,
     state: &mut ProgramState,
@@ -2009,11 +2156,14 @@ pub fn op_transaction(
         insn
     );
     let conn = program.connection.clone();
+    if *db > 1 {
+        return op_transaction_attached(program, state, *db, *write, *schema_cookie, mv_store);
+    }
     if *write && conn._db.open_flags.contains(OpenFlags::ReadOnly) {
         return Err(LimboError::ReadOnly);
     }
 
-    let pager = program.get_pager_from_database_index(db);
+    let pager = program.get_pager_from_database_index(db)?;
 
     // 1. We try to upgrade current version
     let current_state = conn.transaction_state.get();
@@ -2073,7 +2223,7 @@ pub fn op_transaction(
             // if header_schema_cookie != *schema_cookie {
             //     return Err(LimboError::SchemaUpdated);
             // }
-            let tx_id = mv_store.begin_tx(pager.clone());
+            let tx_id = mv_store.begin_tx_with_isolation(pager.clone(), conn.get_isolation_level());
             conn.mv_transactions.borrow_mut().push(tx_id);
             program.connection.mv_tx_id.set(Some(tx_id));
         }
@@ -2149,6 +2299,48 @@ pub fn op_transaction(
     Ok(InsnFunctionStepResult::Step)
 }
 
+/// Transaction on an attached database. It is tracked by the connection apart from the
+/// transaction of the main database, see [Connection::begin_attached_transaction].
+fn op_transaction_attached(
+    program: &Program,
+    state: &mut ProgramState,
+    db: usize,
+    write: bool,
+    schema_cookie: u32,
+    mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    let conn = program.connection.clone();
+    // Attached databases are read outside of MVCC transactions, and writes to them are
+    // rejected when the statement is translated.
+    if mv_store.is_none() {
+        match conn.begin_attached_transaction(db, write)? {
+            IOResult::Done(LimboResult::Busy) => return Ok(InsnFunctionStepResult::Busy),
+            IOResult::Done(LimboResult::Ok) => {}
+            IOResult::IO(io) => return Ok(InsnFunctionStepResult::IO(io)),
+        }
+    }
+    let pager = program.get_pager_from_database_index(&db)?;
+    match pager
+        .io
+        .block(|| pager.with_header(|header| header.schema_cookie.get()))
+    {
+        Ok(header_schema_cookie) if header_schema_cookie != schema_cookie => {
+            tracing::info!(
+                "schema of attached database {db} changed, force reprepare: {} != {}",
+                header_schema_cookie,
+                schema_cookie
+            );
+            // Another connection changed the schema, load it again when reprepared.
+            conn.reload_database_schema(db)?;
+            return Err(LimboError::SchemaUpdated);
+        }
+        Ok(_) | Err(LimboError::Page1NotAlloc) => {}
+        Err(err) => return Err(err),
+    }
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_auto_commit(
     program: &Program,
     state: &mut ProgramState,
@@ -2171,8 +2363,20 @@ pub fn op_auto_commit(
     }
 
     if *auto_commit != conn.auto_commit.get() {
+        if *auto_commit {
+            if !*rollback && conn.deferred_fk_violations() > 0 {
+                // The transaction stays open so that the violations can still be resolved.
+                return Err(LimboError::TxError(
+                    "FOREIGN KEY constraint failed".to_string(),
+                ));
+            }
+            // COMMIT and ROLLBACK end the transaction together with all of its savepoints.
+            conn.clear_savepoints();
+            conn.end_deferred_fk_checks();
+        }
         if *rollback {
             // TODO(pere): add rollback I/O logic once we implement rollback journal
+            conn.end_attached_transactions(true)?;
             return_if_io!(pager.end_tx(true, &conn));
             conn.transaction_state.replace(TransactionState::None);
             conn.auto_commit.replace(true);
@@ -2197,6 +2401,52 @@ pub fn op_auto_commit(
         .map(Into::into)
 }
 
+pub fn op_savepoint(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    load_insn!(Savepoint { op, name }, insn);
+    let conn = program.connection.clone();
+    if state.commit_state == CommitState::Committing {
+        return program
+            .commit_txn(pager.clone(), state, mv_store, false)
+            .map(Into::into);
+    }
+
+    match op {
+        SavepointOp::Begin => {
+            conn.open_savepoint(name, mv_store)?;
+            // A savepoint opened outside of a transaction behaves like BEGIN DEFERRED.
+            conn.auto_commit.replace(false);
+        }
+        SavepointOp::Release | SavepointOp::Rollback => {
+            let Some(position) = conn.find_savepoint(name) else {
+                return Err(LimboError::TxError(format!("no such savepoint: {name}")));
+            };
+            if *op == SavepointOp::Rollback {
+                conn.rollback_to_savepoint(position, mv_store)?;
+            } else if conn.savepoint_starts_transaction(position)
+                && conn.deferred_fk_violations() > 0
+            {
+                return Err(LimboError::TxError(
+                    "FOREIGN KEY constraint failed".to_string(),
+                ));
+            } else if conn.release_savepoint(position)? {
+                // Releasing the savepoint that started the transaction commits it.
+                conn.auto_commit.replace(true);
+                return program
+                    .commit_txn(pager.clone(), state, mv_store, false)
+                    .map(Into::into);
+            }
+        }
+    }
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_goto(
     program: &Program,
     state: &mut ProgramState,
@@ -3300,6 +3550,9 @@ pub fn op_agg_step(
                     finalize_fn: *finalize,
                     finalized_value: None,
                 })),
+                ExtFunc::NativeAggregate { argc, init } => {
+                    Register::Aggregate(AggContext::Native(NativeAggState::new(init(), *argc)))
+                }
                 _ => unreachable!("scalar function called in aggregate context"),
             },
         };
@@ -3316,8 +3569,11 @@ pub fn op_agg_step(
             let AggContext::Avg(acc, count) = agg.borrow_mut() else {
                 unreachable!();
             };
-            *acc = acc.exec_add(col.get_value());
-            *count += 1;
+            // NULLs are ignored, like in sum()
+            if !matches!(col.get_value(), Value::Null) {
+                *acc = acc.exec_add(col.get_value());
+                *count += 1;
+            }
         }
         AggFunc::Sum | AggFunc::Total => {
             let col = state.registers[*col].clone();
@@ -3424,7 +3680,7 @@ pub fn op_agg_step(
             if *new_value != Value::Null
                 && acc.as_ref().is_none_or(|acc| {
                     use std::cmp::Ordering;
-                    compare_with_collation(new_value, acc, state.current_collation)
+                    compare_with_collation(new_value, acc, state.current_collation.as_ref())
                         == Ordering::Greater
                 })
             {
@@ -3448,7 +3704,7 @@ pub fn op_agg_step(
             if *new_value != Value::Null
                 && acc.as_ref().is_none_or(|acc| {
                     use std::cmp::Ordering;
-                    compare_with_collation(new_value, acc, state.current_collation)
+                    compare_with_collation(new_value, acc, state.current_collation.as_ref())
                         == Ordering::Less
                 })
             {
@@ -3528,6 +3784,17 @@ pub fn op_agg_step(
                 _ => unreachable!(),
             };
         }
+        AggFunc::External(func) if matches!(func.as_ref(), ExtFunc::NativeAggregate { .. }) => {
+            let Register::Aggregate(AggContext::Native(agg_state)) = &state.registers[*acc_reg]
+            else {
+                unreachable!();
+            };
+            let args: Vec<Value> = state.registers[*col..*col + agg_state.argc]
+                .iter()
+                .map(|reg| reg.get_value().clone())
+                .collect();
+            RefCell::borrow_mut(&agg_state.state).step(&args)?;
+        }
         AggFunc::External(_) => {
             let (step_fn, state_ptr, argc) = {
                 let Register::Aggregate(agg) = &state.registers[*acc_reg] else {
@@ -3667,13 +3934,8 @@ pub fn op_agg_final(
             }
             AggFunc::External(_) => {
                 agg.compute_external()?;
-                let AggContext::External(agg_state) = agg else {
-                    unreachable!();
-                };
-                match &agg_state.finalized_value {
-                    Some(value) => state.registers[*register] = Register::Value(value.clone()),
-                    None => state.registers[*register] = Register::Value(Value::Null),
-                }
+                let value = agg.final_value().clone();
+                state.registers[*register] = Register::Value(value);
             }
         },
         Register::Value(Value::Null) => {
@@ -3685,6 +3947,13 @@ pub fn op_agg_final(
                 AggFunc::Count | AggFunc::Count0 => {
                     state.registers[*register] = Register::Value(Value::Integer(0));
                 }
+                AggFunc::External(func) => {
+                    // Native aggregates decide what they return for no rows.
+                    if let ExtFunc::NativeAggregate { init, .. } = func.as_ref() {
+                        let value = init().finalize()?;
+                        state.registers[*register] = Register::Value(value);
+                    }
+                }
                 _ => {}
             }
         }
@@ -3696,6 +3965,36 @@ pub fn op_agg_final(
     Ok(InsnFunctionStepResult::Step)
 }
 
+pub fn op_agg_value(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    load_insn!(
+        AggValue {
+            acc_reg,
+            dest_reg,
+            func,
+        },
+        insn
+    );
+    if let AggFunc::External(_) = func {
+        // The state of an external aggregate is owned by the extension and cannot be copied.
+        return Err(LimboError::InternalError(
+            "AggValue is not supported for external aggregate functions".to_string(),
+        ));
+    }
+    // Finalize a copy of the accumulator, AggFinal also moves on to the next instruction.
+    state.registers[*dest_reg] = state.registers[*acc_reg].clone();
+    let final_insn = Insn::AggFinal {
+        register: *dest_reg,
+        func: func.clone(),
+    };
+    op_agg_final(program, state, &final_insn, pager, mv_store)
+}
+
 pub fn op_sorter_open(
     program: &Program,
     state: &mut ProgramState,
@@ -3729,7 +4028,7 @@ pub fn op_sorter_open(
         order,
         collations
             .iter()
-            .map(|collation| collation.unwrap_or_default())
+            .map(|collation| collation.clone().unwrap_or_default())
             .collect(),
         max_buffer_size_bytes,
         page_size,
@@ -4711,8 +5010,8 @@ pub fn op_function(
                 state.registers[*dest] = Register::Value(result)
             }
         },
-        crate::function::Func::External(f) => match f.func {
-            ExtFunc::Scalar(f) => {
+        crate::function::Func::External(f) => match &f.func {
+            &ExtFunc::Scalar(f) => {
                 if arg_count == 0 {
                     let result_c_value: ExtValue = unsafe { (f)(0, std::ptr::null()) };
                     match Value::from_ffi(result_c_value) {
@@ -4742,6 +5041,13 @@ pub fn op_function(
                     }
                 }
             }
+            ExtFunc::NativeScalar(f) => {
+                let args: Vec<Value> = state.registers[*start_reg..*start_reg + arg_count]
+                    .iter()
+                    .map(|reg| reg.get_value().clone())
+                    .collect();
+                state.registers[*dest] = Register::Value(f(&args)?);
+            }
             _ => unreachable!("aggregate called in scalar context"),
         },
         crate::function::Func::Math(math_func) => match math_func.arity() {
@@ -4907,6 +5213,20 @@ pub fn op_function(
                                     .to_string(),
                                 )
                             }
+                            ast::Stmt::CreateVirtualTable(mut create) => {
+                                let table_name = normalize_ident(create.tbl_name.name.as_str());
+
+                                if rename_from != table_name {
+                                    break 'sql None;
+                                }
+
+                                create.tbl_name = ast::QualifiedName {
+                                    db_name: None,
+                                    name: ast::Name::new(&rename_to),
+                                    alias: None,
+                                };
+                                Some(ast::Stmt::CreateVirtualTable(create).to_string())
+                            }
                             _ => todo!(),
                         }
                     };
@@ -5208,12 +5528,18 @@ pub fn op_insert(
 
                 turso_assert!(!flag.has(InsertFlags::REQUIRE_SEEK), "to capture old record accurately, we must be located at the correct position in the table");
 
+                let insert_key = match state.registers[*key_reg].get_value() {
+                    Value::Integer(i) => *i,
+                    _ => unreachable!("expected integer key"),
+                };
                 let old_record = {
                     let mut cursor = state.get_cursor(*cursor_id);
                     let cursor = cursor.as_btree_mut();
                     // Get the current key - for INSERT operations, there may not be a current row
                     let maybe_key = return_if_io!(cursor.rowid());
-                    if let Some(key) = maybe_key {
+                    // The cursor is positioned where the new row goes, which is only the row it
+                    // overwrites if that row has the same key.
+                    if let Some(key) = maybe_key.filter(|key| *key == insert_key) {
                         // Get the current record before deletion and extract values
                         let maybe_record = return_if_io!(cursor.record());
                         if let Some(record) = maybe_record {
@@ -5387,13 +5713,13 @@ pub fn op_insert(
                 if let Some((key, values)) = state.op_insert_state.old_record.take() {
                     for view_name in dependent_views.iter() {
                         let tx_state = tx_states.entry(view_name.clone()).or_default();
-                        tx_state.delta.delete(key, values.clone());
+                        tx_state.delete(table_name, key, values.clone());
                     }
                 }
                 for view_name in dependent_views.iter() {
                     let tx_state = tx_states.entry(view_name.clone()).or_default();
 
-                    tx_state.delta.insert(key, values.clone());
+                    tx_state.insert(table_name, key, values.clone());
                 }
 
                 break;
@@ -5525,7 +5851,7 @@ pub fn op_delete(
                     let mut tx_states = program.connection.view_transaction_states.borrow_mut();
                     for view_name in dependent_views {
                         let tx_state = tx_states.entry(view_name.clone()).or_default();
-                        tx_state.delta.delete(key, values.clone());
+                        tx_state.delete(table_name, key, values.clone());
                     }
                 }
                 break;
@@ -5615,7 +5941,8 @@ pub fn op_idx_delete(
                     return_if_io!(cursor.rowid())
                 };
 
-                if rowid.is_none() && *raise_error_if_no_matching_entry {
+                let has_rowid = state.get_cursor(*cursor_id).as_btree_mut().has_rowid();
+                if has_rowid && rowid.is_none() && *raise_error_if_no_matching_entry {
                     return Err(LimboError::Corrupt(format!(
                         "IdxDelete: no matching index entry found for record {:?}",
                         make_record(&state.registers, start_reg, num_regs)
@@ -5683,13 +6010,16 @@ pub fn op_idx_insert(
     match state.op_idx_insert_state {
         OpIdxInsertState::MaybeSeek => {
             let (_, cursor_type) = program.cursor_ref.get(cursor_id).unwrap();
-            let CursorType::BTreeIndex(index_meta) = cursor_type else {
-                panic!("IdxInsert: not a BTreeIndex cursor");
+            let unique = match cursor_type {
+                CursorType::BTreeIndex(index_meta) => index_meta.unique,
+                // The clustered index of a WITHOUT ROWID table is keyed by its primary key.
+                CursorType::BTreeTable(table) if !table.has_rowid => true,
+                _ => panic!("IdxInsert: not a BTreeIndex cursor"),
             };
 
             // TODO: currently we never pass USE_SEEK, so this other check is a bit redundant and we always seek,
             // but I guess it's FutureProofed™®
-            if !index_meta.unique && flags.has(IdxInsertFlags::USE_SEEK) {
+            if !unique && flags.has(IdxInsertFlags::USE_SEEK) {
                 state.op_idx_insert_state = OpIdxInsertState::Insert;
                 return Ok(InsnFunctionStepResult::Step);
             }
@@ -5705,7 +6035,7 @@ pub fn op_idx_insert(
                 SeekOp::GE { eq_only: true },
             )? {
                 SeekInternalResult::Found => {
-                    state.op_idx_insert_state = if index_meta.unique {
+                    state.op_idx_insert_state = if unique {
                         OpIdxInsertState::UniqueConstraintCheck
                     } else {
                         OpIdxInsertState::Insert
@@ -5774,6 +6104,11 @@ pub fn op_idx_insert(
             // Increment metrics for index write
             if flags.has(IdxInsertFlags::NCHANGE) {
                 state.metrics.rows_written = state.metrics.rows_written.saturating_add(1);
+                // a row inserted into a WITHOUT ROWID table counts as a change
+                let (_, cursor_type) = program.cursor_ref.get(cursor_id).unwrap();
+                if matches!(cursor_type, CursorType::BTreeTable(_)) {
+                    program.n_change.set(program.n_change.get() + 1);
+                }
             }
             state.op_idx_insert_state = OpIdxInsertState::MaybeSeek;
             state.pc += 1;
@@ -5816,14 +6151,12 @@ pub fn op_new_rowid(
         insn
     );
 
-    if let Some(mv_store) = mv_store {
-        let rowid = {
-            let mut cursor = state.get_cursor(*cursor);
-            let cursor = cursor.as_btree_mut();
-            let mvcc_cursor = cursor.get_mvcc_cursor();
-            let mut mvcc_cursor = RefCell::borrow_mut(&mvcc_cursor);
-            mvcc_cursor.get_next_rowid()
-        };
+    let mvcc_cursor = {
+        let mut cursor = state.get_cursor(*cursor);
+        cursor.as_btree_mut().get_mvcc_cursor()
+    };
+    if let Some(mvcc_cursor) = mvcc_cursor {
+        let rowid = RefCell::borrow_mut(&mvcc_cursor).get_next_rowid();
         state.registers[*rowid_reg] = Register::Value(Value::Integer(rowid));
         state.pc += 1;
         return Ok(InsnFunctionStepResult::Step);
@@ -6099,12 +6432,7 @@ pub fn op_not_exists(
         },
         insn
     );
-    let exists = if let Some(mv_store) = mv_store {
-        let mut cursor = must_be_btree_cursor!(*cursor, program.cursor_ref, state, "NotExists");
-        let cursor = cursor.as_btree_mut();
-        let mvcc_cursor = cursor.get_mvcc_cursor();
-        false
-    } else {
+    let exists = {
         let mut cursor = must_be_btree_cursor!(*cursor, program.cursor_ref, state, "NotExists");
         let cursor = cursor.as_btree_mut();
         return_if_io!(cursor.exists(state.registers[*rowid_reg].get_value()))
@@ -6177,10 +6505,12 @@ pub fn op_open_write(
         },
         insn
     );
-    if program.connection.is_readonly(*db) {
+    let (_, cursor_type) = program.cursor_ref.get(*cursor_id).unwrap();
+    let db = cursor_database(cursor_type, *db);
+    if program.connection.is_readonly(db) {
         return Err(LimboError::ReadOnly);
     }
-    let pager = program.get_pager_from_database_index(db);
+    let pager = program.get_pager_from_database_index(&db)?;
 
     let root_page = match root_page {
         RegisterOrLiteral::Literal(lit) => *lit as u64,
@@ -6193,23 +6523,14 @@ pub fn op_open_write(
             }
         },
     };
-    let (_, cursor_type) = program.cursor_ref.get(*cursor_id).unwrap();
     let mut cursors = state.cursors.borrow_mut();
     let maybe_index = match cursor_type {
-        CursorType::BTreeIndex(index) => Some(index),
+        CursorType::BTreeIndex(index) => Some(index.clone()),
+        // WITHOUT ROWID tables are written through their clustered primary key index.
+        CursorType::BTreeTable(table) if !table.has_rowid => table.clustered_index(),
         _ => None,
     };
-    let mv_cursor = match program.connection.mv_tx_id.get() {
-        Some(tx_id) => {
-            let table_id = root_page;
-            let mv_store = mv_store.unwrap().clone();
-            let mv_cursor = Rc::new(RefCell::new(
-                MvCursor::new(mv_store.clone(), tx_id, table_id, pager.clone()).unwrap(),
-            ));
-            Some(mv_cursor)
-        }
-        None => None,
-    };
+    let mv_cursor = new_mv_cursor(program, mv_store, cursor_type, root_page, &pager)?;
     if let Some(index) = maybe_index {
         let conn = program.connection.clone();
         let schema = conn.schema.borrow();
@@ -6277,15 +6598,10 @@ pub fn op_create_btree(
 ) -> Result<InsnFunctionStepResult> {
     load_insn!(CreateBtree { db, root, flags }, insn);
 
-    assert_eq!(*db, 0);
-
     if program.connection.is_readonly(*db) {
         return Err(LimboError::ReadOnly);
     }
-    if *db > 0 {
-        // TODO: implement temp databases
-        todo!("temp databases not implemented yet");
-    }
+    let pager = program.get_pager_from_database_index(db)?;
     // FIXME: handle page cache is full
     let root_page = return_if_io!(pager.btree_create(flags));
     state.registers[*root] = Register::Value(Value::Integer(root_page as i64));
@@ -6308,9 +6624,7 @@ pub fn op_destroy(
         },
         insn
     );
-    if *is_temp == 1 {
-        todo!("temp databases not implemented yet.");
-    }
+    let pager = program.get_pager_from_database_index(is_temp)?;
     // TODO not sure if should be BTreeCursor::new_table or BTreeCursor::new_index here or neither and just pass an emtpy vec
     let mut cursor = BTreeCursor::new(None, pager.clone(), *root, 0);
     let former_root_page_result = cursor.btree_destroy()?;
@@ -6330,13 +6644,11 @@ pub fn op_drop_table(
     mv_store: Option<&Arc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
     load_insn!(DropTable { db, table_name, .. }, insn);
-    if *db > 0 {
-        todo!("temp databases not implemented yet");
-    }
     let conn = program.connection.clone();
     {
-        conn.with_schema_mut(|schema| {
+        conn.with_database_schema_mut(*db, |schema| {
             schema.remove_indices_for_table(table_name);
+            schema.remove_triggers_for_table(table_name);
             schema.remove_table(table_name);
         });
     }
@@ -6352,11 +6664,13 @@ pub fn op_drop_view(
     _mv_store: Option<&Arc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
     load_insn!(DropView { db, view_name }, insn);
-    if *db > 0 {
-        todo!("temp databases not implemented yet");
+    if *db > 1 {
+        return Err(LimboError::ParseError(
+            "views in attached databases are not supported yet".to_string(),
+        ));
     }
     let conn = program.connection.clone();
-    conn.with_schema_mut(|schema| {
+    conn.with_database_schema_mut(*db, |schema| {
         schema.remove_view(view_name)?;
         Ok::<(), crate::LimboError>(())
     })?;
@@ -6406,7 +6720,7 @@ pub fn op_coll_seq(
     };
 
     // Set the current collation sequence for use by subsequent functions
-    state.current_collation = Some(*collation);
+    state.current_collation = Some(collation.clone());
 
     // If P1 is not zero, initialize that register to 0
     if let Some(reg_idx) = reg {
@@ -6446,23 +6760,30 @@ pub fn op_parse_schema(
     pager: &Rc<Pager>,
     mv_store: Option<&Arc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
-    load_insn!(
-        ParseSchema {
-            db: _,
-            where_clause,
-        },
-        insn
-    );
+    load_insn!(ParseSchema { db, where_clause }, insn);
     let conn = program.connection.clone();
     // set auto commit to false in order for parse schema to not commit changes as transaction state is stored in connection,
     // and we use the same connection for nested query.
     let previous_auto_commit = conn.auto_commit.get();
     conn.auto_commit.set(false);
 
+    // The objects of the temp database are described by its own schema table.
+    let (schema_table, temporary) = if *db == 1 {
+        (crate::schema::TEMP_SCHEMA_TABLE_NAME.to_string(), true)
+    } else if *db > 1 {
+        let Some(name) = conn.attached_database_name(*db) else {
+            return Err(LimboError::InternalError(format!(
+                "no database with index {db}"
+            )));
+        };
+        (format!("{name}.sqlite_schema"), false)
+    } else {
+        ("sqlite_schema".to_string(), false)
+    };
     let maybe_nested_stmt_err = if let Some(where_clause) = where_clause {
-        let stmt = conn.prepare(format!("SELECT * FROM sqlite_schema WHERE {where_clause}"))?;
+        let stmt = conn.prepare(format!("SELECT * FROM {schema_table} WHERE {where_clause}"))?;
 
-        conn.with_schema_mut(|schema| {
+        conn.with_database_schema_mut(*db, |schema| {
             // TODO: This function below is synchronous, make it async
             let existing_views = schema.materialized_views.clone();
             conn.is_nested_stmt.set(true);
@@ -6472,12 +6793,13 @@ pub fn op_parse_schema(
                 &conn.syms.borrow(),
                 program.connection.mv_tx_id.get(),
                 existing_views,
+                temporary,
             )
         })
     } else {
-        let stmt = conn.prepare("SELECT * FROM sqlite_schema")?;
+        let stmt = conn.prepare(format!("SELECT * FROM {schema_table}"))?;
 
-        conn.with_schema_mut(|schema| {
+        conn.with_database_schema_mut(*db, |schema| {
             // TODO: This function below is synchronous, make it async
             let existing_views = schema.materialized_views.clone();
             conn.is_nested_stmt.set(true);
@@ -6487,6 +6809,7 @@ pub fn op_parse_schema(
                 &conn.syms.borrow(),
                 program.connection.mv_tx_id.get(),
                 existing_views,
+                temporary,
             )
         })
     };
@@ -6497,6 +6820,61 @@ pub fn op_parse_schema(
     Ok(InsnFunctionStepResult::Step)
 }
 
+pub fn op_vacuum(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    load_insn!(Vacuum { db: _, dest_reg }, insn);
+    let conn = program.connection.clone();
+    if !conn.auto_commit.get() {
+        return Err(LimboError::TxError(
+            "cannot VACUUM from within a transaction".to_string(),
+        ));
+    }
+    let dest = match dest_reg {
+        Some(reg) => match state.registers[*reg].get_value() {
+            Value::Text(path) => Some(path.as_str().to_string()),
+            _ => return Err(LimboError::InvalidArgument("non-text filename".to_string())),
+        },
+        None => {
+            if mv_store.is_some() {
+                return Err(LimboError::ParseError(
+                    "VACUUM is not supported in MVCC mode".to_string(),
+                ));
+            }
+            None
+        }
+    };
+
+    // Like ParseSchema, read the database through nested statements that share the
+    // transaction of this program.
+    conn.auto_commit.set(false);
+    conn.is_nested_stmt.set(true);
+    let result = match &dest {
+        Some(path) => crate::vacuum::vacuum_into(&conn, path),
+        None => crate::vacuum::vacuum(&conn, pager),
+    };
+    conn.is_nested_stmt.set(false);
+    conn.auto_commit.set(true);
+    result?;
+
+    if dest.is_none() {
+        // Every root page may have moved: start over from an empty schema so that the
+        // following ParseSchema loads the new one.
+        conn.with_schema_mut(|schema| {
+            let mut new_schema = crate::schema::Schema::new(schema.indexes_enabled());
+            new_schema.schema_version = schema.schema_version;
+            new_schema.materialized_views = std::mem::take(&mut schema.materialized_views);
+            *schema = new_schema;
+        });
+    }
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_populate_materialized_views(
     program: &Program,
     state: &mut ProgramState,
@@ -6507,7 +6885,16 @@ pub fn op_populate_materialized_views(
     let conn = program.connection.clone();
     let schema = conn.schema.borrow();
 
-    return_if_io!(schema.populate_materialized_views(&conn));
+    // Like ParseSchema, read the base tables through nested statements that share the
+    // transaction of this program, so that the view's state is written as part of it
+    let previous_auto_commit = conn.auto_commit.get();
+    conn.auto_commit.set(false);
+    conn.is_nested_stmt.set(true);
+    let result = schema.populate_materialized_views(&conn);
+    conn.is_nested_stmt.set(false);
+    conn.auto_commit.set(previous_auto_commit);
+
+    return_if_io!(result);
     // All views populated, advance to next instruction
     state.pc += 1;
     Ok(InsnFunctionStepResult::Step)
@@ -6521,10 +6908,7 @@ pub fn op_read_cookie(
     mv_store: Option<&Arc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
     load_insn!(ReadCookie { db, dest, cookie }, insn);
-    if *db > 0 {
-        // TODO: implement temp databases
-        todo!("temp databases not implemented yet");
-    }
+    let pager = program.get_pager_from_database_index(db)?;
 
     let cookie_value = match pager.with_header(|header| match cookie {
         Cookie::ApplicationId => header.application_id.get().into(),
@@ -6559,9 +6943,10 @@ pub fn op_set_cookie(
         },
         insn
     );
-    if *db > 0 {
+    if *db == 1 {
         todo!("temp databases not implemented yet");
     }
+    let pager = program.get_pager_from_database_index(db)?;
 
     return_if_io!(pager.with_header_mut(|header| {
         match cookie {
@@ -6573,6 +6958,14 @@ pub fn op_set_cookie(
             Cookie::IncrementalVacuum => {
                 header.incremental_vacuum_enabled = (*value as u32).into()
             }
+            Cookie::SchemaVersion if *db > 1 => {
+                // The transaction state belongs to the main database, the new schema of an
+                // attached database is published when its transaction commits.
+                program
+                    .connection
+                    .with_database_schema_mut(*db, |schema| schema.schema_version = *value as u32);
+                header.schema_cookie = (*value as u32).into();
+            }
             Cookie::SchemaVersion => {
                 // we update transaction state to indicate that the schema has changed
                 match program.connection.transaction_state.get() {
@@ -6787,7 +7180,7 @@ pub fn op_open_ephemeral(
     state: &mut ProgramState,
     insn: &Insn,
     pager: &Rc<Pager>,
-    mv_store: Option<&Arc<MvStore>>,
+    _mv_store: Option<&Arc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
     let (cursor_id, is_table) = match insn {
         Insn::OpenEphemeral {
@@ -6862,17 +7255,10 @@ pub fn op_open_ephemeral(
             let root_page = return_if_io!(pager.btree_create(flag));
 
             let (_, cursor_type) = program.cursor_ref.get(cursor_id).unwrap();
-            let mv_cursor = match program.connection.mv_tx_id.get() {
-                Some(tx_id) => {
-                    let table_id = root_page as u64;
-                    let mv_store = mv_store.unwrap().clone();
-                    let mv_cursor = Rc::new(RefCell::new(
-                        MvCursor::new(mv_store.clone(), tx_id, table_id, pager.clone()).unwrap(),
-                    ));
-                    Some(mv_cursor)
-                }
-                None => None,
-            };
+            // Ephemeral tables live in a pager of their own and are private to the statement, so
+            // they are never versioned. Their root pages would clash with the tables of the
+            // MVCC store otherwise.
+            let mv_cursor = None;
 
             let num_columns = match cursor_type {
                 CursorType::BTreeTable(table_rc) => table_rc.columns.len(),
@@ -7208,11 +7594,11 @@ pub fn op_rename_table(
     pager: &Rc<Pager>,
     mv_store: Option<&Arc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
-    load_insn!(RenameTable { from, to }, insn);
+    load_insn!(RenameTable { db, from, to }, insn);
 
     let conn = program.connection.clone();
 
-    conn.with_schema_mut(|schema| {
+    conn.with_database_schema_mut(*db, |schema| {
         if let Some(mut indexes) = schema.indexes.remove(from) {
             indexes.iter_mut().for_each(|index| {
                 let index = Arc::make_mut(index);
@@ -7230,17 +7616,34 @@ pub fn op_rename_table(
         {
             let table = Arc::make_mut(&mut table);
 
-            let Table::BTree(btree) = table else {
-                panic!("only btree tables can be renamed");
-            };
-
-            let btree = Arc::make_mut(btree);
-            btree.name = to.to_owned();
+            match table {
+                Table::BTree(btree) => {
+                    let btree = Arc::make_mut(btree);
+                    btree.name = to.to_owned();
+                }
+                Table::Virtual(vtab) => {
+                    let vtab = Arc::make_mut(vtab);
+                    vtab.name = to.to_owned();
+                }
+                Table::FromClauseSubquery(_) => {
+                    panic!("only btree and virtual tables can be renamed");
+                }
+            }
         }
 
         schema.tables.insert(to.to_owned(), table);
     });
 
+    // Virtual tables are also registered by name with the connection
+    {
+        let mut syms = conn.syms.borrow_mut();
+        if let Some(vtab) = syms.vtabs.remove(from) {
+            let mut vtab = (*vtab).clone();
+            vtab.name = to.to_owned();
+            syms.vtabs.insert(to.to_owned(), Arc::new(vtab));
+        }
+    }
+
     state.pc += 1;
     Ok(InsnFunctionStepResult::Step)
 }
@@ -7254,6 +7657,7 @@ pub fn op_drop_column(
 ) -> Result<InsnFunctionStepResult> {
     load_insn!(
         DropColumn {
+            db,
             table,
             column_index
         },
@@ -7262,7 +7666,7 @@ pub fn op_drop_column(
 
     let conn = program.connection.clone();
 
-    conn.with_schema_mut(|schema| {
+    conn.with_database_schema_mut(*db, |schema| {
         let table = schema
             .tables
             .get_mut(table)
@@ -7275,7 +7679,11 @@ pub fn op_drop_column(
         };
 
         let btree = Arc::make_mut(btree);
-        btree.columns.remove(*column_index)
+        let column = btree.columns.remove(*column_index);
+        // CHECK constraints declared on the column are dropped with it
+        btree
+            .check_constraints
+            .retain(|check| check.column != column.name);
     });
 
     state.pc += 1;
@@ -7289,11 +7697,19 @@ pub fn op_add_column(
     pager: &Rc<Pager>,
     mv_store: Option<&Arc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
-    load_insn!(AddColumn { table, column }, insn);
+    load_insn!(
+        AddColumn {
+            db,
+            table,
+            column,
+            check_constraints
+        },
+        insn
+    );
 
     let conn = program.connection.clone();
 
-    conn.with_schema_mut(|schema| {
+    conn.with_database_schema_mut(*db, |schema| {
         let table = schema
             .tables
             .get_mut(table)
@@ -7306,7 +7722,10 @@ pub fn op_add_column(
         };
 
         let btree = Arc::make_mut(btree);
-        btree.columns.push(column.clone())
+        btree.columns.push(column.clone());
+        btree
+            .check_constraints
+            .extend(check_constraints.iter().cloned());
     });
 
     state.pc += 1;
@@ -7322,6 +7741,7 @@ pub fn op_alter_column(
 ) -> Result<InsnFunctionStepResult> {
     load_insn!(
         AlterColumn {
+            db,
             table: table_name,
             column_index,
             definition,
@@ -7332,9 +7752,9 @@ pub fn op_alter_column(
 
     let conn = program.connection.clone();
 
-    let new_column = crate::schema::Column::from(definition);
+    let new_column = crate::schema::Column::from_definition(definition, &conn.syms.borrow())?;
 
-    conn.with_schema_mut(|schema| {
+    conn.with_database_schema_mut(*db, |schema| {
         let table = schema
             .tables
             .get_mut(table_name)
@@ -8848,6 +9268,147 @@ pub fn op_journal_mode(
     Ok(InsnFunctionStepResult::Step)
 }
 
+/// Maximum nesting of trigger programs, reached by foreign key actions cascading through a
+/// self-referencing table.
+const MAX_TRIGGER_DEPTH: usize = 1000;
+
+pub fn op_program(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    load_insn!(
+        Program {
+            program: sub_program,
+            params,
+            ignore_jump_target,
+            ..
+        },
+        insn
+    );
+    if state.trigger_depth >= MAX_TRIGGER_DEPTH {
+        return Err(LimboError::Constraint(
+            "too many levels of trigger recursion".to_string(),
+        ));
+    }
+    let sub_program = sub_program.get();
+    let mut sub_state = ProgramState::new(sub_program.max_registers, sub_program.cursor_ref.len());
+    sub_state.trigger_depth = state.trigger_depth + 1;
+    for (index, reg) in params {
+        sub_state.bind_at(*index, state.registers[*reg].get_value().clone());
+    }
+
+    // The trigger body runs inside the transaction of the statement that fired it, so treat it
+    // like any other nested statement: it must neither begin, commit nor roll back a transaction.
+    // Foreign key violations counted by the sub-program belong to the calling statement
+    sub_state.fk_immediate_violations = state.fk_immediate_violations;
+
+    let conn = program.connection.clone();
+    let previous_auto_commit = conn.auto_commit.get();
+    let previous_nested_stmt = conn.is_nested_stmt.get();
+    conn.auto_commit.set(false);
+    conn.is_nested_stmt.set(true);
+    let result = loop {
+        match sub_program.step(&mut sub_state, mv_store.cloned(), pager.clone()) {
+            // Rows produced by SELECT statements in the trigger body are discarded
+            Ok(super::StepResult::Row) => continue,
+            Ok(super::StepResult::IO) => {
+                if let Err(err) = pager.io.run_once() {
+                    break Err(err);
+                }
+            }
+            Ok(super::StepResult::Done) => break Ok(()),
+            Ok(super::StepResult::Busy) => break Err(LimboError::Busy),
+            Ok(super::StepResult::Interrupt) => {
+                break Err(LimboError::InternalError(
+                    "trigger program interrupted".to_string(),
+                ))
+            }
+            Err(err) => break Err(err),
+        }
+    };
+    conn.is_nested_stmt.set(previous_nested_stmt);
+    conn.auto_commit.set(previous_auto_commit);
+    result?;
+    state.fk_immediate_violations = sub_state.fk_immediate_violations;
+
+    if sub_state.raise_ignore {
+        state.pc = ignore_jump_target.as_offset_int();
+    } else {
+        state.pc += 1;
+    }
+    Ok(InsnFunctionStepResult::Step)
+}
+
+pub fn op_drop_trigger(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    _pager: &Rc<Pager>,
+    _mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    load_insn!(DropTrigger { db, trigger_name }, insn);
+    let conn = program.connection.clone();
+    conn.with_database_schema_mut(*db, |schema| schema.remove_trigger(trigger_name))?;
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
+pub fn op_fk_counter(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    _pager: &Rc<Pager>,
+    _mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    load_insn!(
+        FkCounter {
+            increment_value,
+            deferred,
+        },
+        insn
+    );
+    let conn = &program.connection;
+    if *deferred || conn.get_defer_foreign_keys() {
+        conn.add_deferred_fk_violations(*increment_value);
+    } else {
+        state.fk_immediate_violations += *increment_value;
+    }
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
+pub fn op_fk_if_zero(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    _pager: &Rc<Pager>,
+    _mv_store: Option<&Arc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    load_insn!(
+        FkIfZero {
+            deferred,
+            target_pc,
+        },
+        insn
+    );
+    let conn = &program.connection;
+    let is_zero = if *deferred {
+        conn.deferred_fk_violations() == 0
+    } else {
+        state.fk_immediate_violations == 0
+            && (!conn.get_defer_foreign_keys() || conn.deferred_fk_violations() == 0)
+    };
+    if is_zero {
+        state.pc = target_pc.as_offset_int();
+    } else {
+        state.pc += 1;
+    }
+    Ok(InsnFunctionStepResult::Step)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -157,7 +157,7 @@ pub fn insn_to_str(
                 *start_reg_a as i32,
                 *start_reg_b as i32,
                 *count as i32,
-                Value::build_text(format!("k({count}, {})", collation.unwrap_or_default())),
+                Value::build_text(format!("k({count}, {})", collation.clone().unwrap_or_default())),
                 0,
                 format!(
                     "r[{}..{}]==r[{}..{}]",
@@ -229,7 +229,7 @@ pub fn insn_to_str(
                 *lhs as i32,
                 *rhs as i32,
                 target_pc.as_debug_int(),
-                Value::build_text(collation.map_or("".to_string(), |c| c.to_string())),
+                Value::build_text(collation.as_ref().map_or("".to_string(), |c| c.to_string())),
                 0,
                 format!(
                     "if r[{}]==r[{}] goto {}",
@@ -249,7 +249,7 @@ pub fn insn_to_str(
                 *lhs as i32,
                 *rhs as i32,
                 target_pc.as_debug_int(),
-                Value::build_text(collation.map_or("".to_string(), |c| c.to_string())),
+                Value::build_text(collation.as_ref().map_or("".to_string(), |c| c.to_string())),
                 0,
                 format!(
                     "if r[{}]!=r[{}] goto {}",
@@ -269,7 +269,7 @@ pub fn insn_to_str(
                 *lhs as i32,
                 *rhs as i32,
                 target_pc.as_debug_int(),
-                Value::build_text(collation.map_or("".to_string(), |c| c.to_string())),
+                Value::build_text(collation.as_ref().map_or("".to_string(), |c| c.to_string())),
                 0,
                 format!("if r[{}]<r[{}] goto {}", lhs, rhs, target_pc.as_debug_int()),
             ),
@@ -284,7 +284,7 @@ pub fn insn_to_str(
                 *lhs as i32,
                 *rhs as i32,
                 target_pc.as_debug_int(),
-                Value::build_text(collation.map_or("".to_string(), |c| c.to_string())),
+                Value::build_text(collation.as_ref().map_or("".to_string(), |c| c.to_string())),
                 0,
                 format!(
                     "if r[{}]<=r[{}] goto {}",
@@ -304,7 +304,7 @@ pub fn insn_to_str(
                 *lhs as i32,
                 *rhs as i32,
                 target_pc.as_debug_int(),
-                Value::build_text(collation.map_or("".to_string(), |c| c.to_string())),
+                Value::build_text(collation.as_ref().map_or("".to_string(), |c| c.to_string())),
                 0,
                 format!("if r[{}]>r[{}] goto {}", lhs, rhs, target_pc.as_debug_int()),
             ),
@@ -319,7 +319,7 @@ pub fn insn_to_str(
                 *lhs as i32,
                 *rhs as i32,
                 target_pc.as_debug_int(),
-                Value::build_text(collation.map_or("".to_string(), |c| c.to_string())),
+                Value::build_text(collation.as_ref().map_or("".to_string(), |c| c.to_string())),
                 0,
                 format!(
                     "if r[{}]>=r[{}] goto {}",
@@ -462,6 +462,15 @@ pub fn insn_to_str(
                 *conflict_action,
                 format!("args=r[{}..{}]", start_reg, start_reg + arg_count - 1),
             ),
+            Insn::VBegin { cursor_id } => (
+                "VBegin",
+                *cursor_id as i32,
+                0,
+                0,
+                Value::build_text(""),
+                0,
+                "".to_string(),
+            ),
             Insn::VNext {
                 cursor_id,
                 pc_if_next,
@@ -483,6 +492,18 @@ pub fn insn_to_str(
                 0,
                 "".to_string(),
             ),
+            Insn::VRename {
+                table_name,
+                new_name,
+            } => (
+                "VRename",
+                0,
+                0,
+                0,
+                Value::build_text(table_name),
+                0,
+                format!("rename_vtab({table_name}, {new_name})"),
+            ),
             Insn::OpenPseudo {
                 cursor_id,
                 content_reg,
@@ -634,6 +655,15 @@ pub fn insn_to_str(
                 0,
                 "".to_string(),
             ),
+            Insn::RaiseIgnore => (
+                "Halt",
+                0,
+                4,
+                0,
+                Value::build_text(""),
+                0,
+                "".to_string(),
+            ),
             Insn::HaltIfNull {
                 err_code,
                 target_reg,
@@ -947,6 +977,19 @@ pub fn insn_to_str(
                 0,
                 format!("accum=r[{}]", *register),
             ),
+            Insn::AggValue {
+                acc_reg,
+                dest_reg,
+                func,
+            } => (
+                "AggValue",
+                0,
+                *acc_reg as i32,
+                *dest_reg as i32,
+                Value::build_text(func.to_string()),
+                0,
+                format!("r[{}]=value(accum=r[{}])", *dest_reg, *acc_reg),
+            ),
             Insn::SorterOpen {
                 cursor_id,
                 columns,
@@ -963,7 +1006,7 @@ pub fn insn_to_str(
                             SortOrder::Desc => "-",
                         };
                         if collation.is_some() {
-                            format!("{sign}{}", collation.unwrap())
+                            format!("{sign}{}", collation.as_ref().unwrap())
                         } else {
                             format!("{sign}B")
                         }
@@ -1337,6 +1380,18 @@ pub fn insn_to_str(
                 0,
                 where_clause.clone().unwrap_or("NULL".to_string()),
             ),
+            Insn::Vacuum { db, dest_reg } => (
+                "Vacuum",
+                *db as i32,
+                dest_reg.map_or(0, |r| r as i32),
+                0,
+                Value::build_text(""),
+                0,
+                match dest_reg {
+                    Some(r) => format!("vacuum db={db} into r[{r}]"),
+                    None => format!("vacuum db={db}"),
+                },
+            ),
             Insn::PopulateMaterializedViews => (
                 "PopulateMaterializedViews",
                 0,
@@ -1486,6 +1541,15 @@ pub fn insn_to_str(
                 0,
                 format!("auto_commit={auto_commit}, rollback={rollback}"),
             ),
+            Insn::Savepoint { op, name } => (
+                "Savepoint",
+                *op as i32,
+                0,
+                0,
+                Value::build_text(name),
+                0,
+                format!("savepoint={name}, op={op:?}"),
+            ),
             Insn::OpenEphemeral {
                 cursor_id,
                 is_table,
@@ -1645,36 +1709,36 @@ pub fn insn_to_str(
                 0,
                 format!("affinity(r[{}]={:?})", *reg, affinity),
             ),
-            Insn::RenameTable { from, to } => (
+            Insn::RenameTable { db, from, to } => (
                 "RenameTable",
-                0,
+                *db as i32,
                 0,
                 0,
                 Value::build_text(""),
                 0,
                 format!("rename_table({from}, {to})"),
             ),
-            Insn::DropColumn { table, column_index } => (
+            Insn::DropColumn { db, table, column_index } => (
                 "DropColumn",
-                0,
+                *db as i32,
                 0,
                 0,
                 Value::build_text(""),
                 0,
                 format!("drop_column({table}, {column_index})"),
             ),
-            Insn::AddColumn { table, column } => (
+            Insn::AddColumn { db, table, column, .. } => (
                 "AddColumn",
-                0,
+                *db as i32,
                 0,
                 0,
                 Value::build_text(""),
                 0,
                 format!("add_column({table}, {column:?})"),
             ),
-            Insn::AlterColumn { table, column_index, definition: column, rename } => (
+            Insn::AlterColumn { db, table, column_index, definition: column, rename } => (
                 "AlterColumn",
-                0,
+                *db as i32,
                 0,
                 0,
                 Value::build_text(""),
@@ -1709,6 +1773,60 @@ pub fn insn_to_str(
                 0,
                 format!("collation={collation}"),
             ),
+            Insn::Program {
+                params,
+                ignore_jump_target,
+                trigger_name,
+                ..
+            } => (
+                "Program",
+                params.first().map_or(0, |(_, reg)| *reg as i32),
+                ignore_jump_target.as_debug_int(),
+                0,
+                Value::build_text(trigger_name),
+                0,
+                format!("trigger {trigger_name}"),
+            ),
+            Insn::DropTrigger { db, trigger_name } => (
+                "DropTrigger",
+                *db as i32,
+                0,
+                0,
+                Value::build_text(trigger_name),
+                0,
+                format!("DROP TRIGGER {trigger_name}"),
+            ),
+            Insn::FkCounter {
+                increment_value,
+                deferred,
+            } => (
+                "FkCounter",
+                *deferred as i32,
+                *increment_value as i32,
+                0,
+                Value::build_text(""),
+                0,
+                format!(
+                    "{} foreign key violations += {increment_value}",
+                    if *deferred { "deferred" } else { "immediate" }
+                ),
+            ),
+            Insn::FkIfZero {
+                deferred,
+                target_pc,
+            } => (
+                "FkIfZero",
+                *deferred as i32,
+                target_pc.as_debug_int(),
+                0,
+                Value::build_text(""),
+                0,
+                format!(
+                    "if {} foreign key violations == 0 goto {}",
+                    if *deferred { "deferred" } else { "immediate" },
+                    target_pc.as_debug_int()
+                ),
+            ),
         };
     format!(
         "{:<4}  {:<17}  {:<4}  {:<4}  {:<4}  {:<13}  {:<2}  {}",
@@ -5,9 +5,9 @@ use std::{
 
 use super::{execute, AggFunc, BranchOffset, CursorID, FuncCtx, InsnFunction, PageIdx};
 use crate::{
-    schema::{Affinity, BTreeTable, Column, Index},
+    schema::{Affinity, BTreeTable, CheckConstraint, Column, Index},
     storage::{pager::CreateBTreeFlags, wal::CheckpointMode},
-    translate::collate::CollationSeq,
+    translate::{collate::CollationSeq, trigger::TriggerProgram},
     Value,
 };
 use turso_macros::Description;
@@ -374,6 +374,12 @@ pub enum Insn {
         conflict_action: u16, // P4: Conflict resolution flags
     },
 
+    /// Make the virtual table of cursor P1 take part in the current transaction, calling its
+    /// xBegin method if it has not been written in the transaction yet.
+    VBegin {
+        cursor_id: CursorID,
+    },
+
     /// Advance the virtual table cursor to the next row.
     /// TODO: async
     VNext {
@@ -389,6 +395,12 @@ pub enum Insn {
         db: usize,
     },
 
+    /// Call the xRename method of the virtual table P4, before it is renamed to `new_name`.
+    VRename {
+        table_name: String,
+        new_name: String,
+    },
+
     /// Open a cursor for a pseudo-table that contains a single row.
     OpenPseudo {
         cursor_id: CursorID,
@@ -455,6 +467,10 @@ pub enum Insn {
         description: String,
     },
 
+    /// Stop the current trigger program because of RAISE(IGNORE). The rest of the triggering
+    /// statement's processing of the current row is skipped by the calling [Insn::Program].
+    RaiseIgnore,
+
     /// Halt the program if P3 is null.
     HaltIfNull {
         target_reg: usize,   // P3
@@ -475,6 +491,12 @@ pub enum Insn {
         rollback: bool,
     },
 
+    /// Open, release or roll back to the savepoint `name`.
+    Savepoint {
+        op: SavepointOp,
+        name: String,
+    },
+
     /// Branch to the given PC.
     Goto {
         target_pc: BranchOffset,
@@ -671,6 +693,14 @@ pub enum Insn {
         func: AggFunc,
     },
 
+    /// Store the current result of the aggregate in `acc_reg` into `dest_reg`, leaving the
+    /// accumulator untouched so that more rows can be added to it.
+    AggValue {
+        acc_reg: usize,
+        dest_reg: usize,
+        func: AggFunc,
+    },
+
     /// Open a sorter.
     SorterOpen {
         cursor_id: CursorID,                   // P1
@@ -897,6 +927,13 @@ pub enum Insn {
         where_clause: Option<String>,
     },
 
+    /// Rebuild database `db` without free pages. If `dest_reg` is set, the rebuilt copy is
+    /// written to the file named by that register instead (VACUUM INTO).
+    Vacuum {
+        db: usize,
+        dest_reg: Option<usize>,
+    },
+
     /// Populate all materialized views after schema parsing
     PopulateMaterializedViews,
 
@@ -1042,18 +1079,23 @@ pub enum Insn {
         message_register: usize,
     },
     RenameTable {
+        db: usize,
         from: String,
         to: String,
     },
     DropColumn {
+        db: usize,
         table: String,
         column_index: usize,
     },
     AddColumn {
+        db: usize,
         table: String,
         column: Column,
+        check_constraints: Vec<CheckConstraint>,
     },
     AlterColumn {
+        db: usize,
         table: String,
         column_index: usize,
         definition: turso_parser::ast::ColumnDefinition,
@@ -1076,6 +1118,35 @@ pub enum Insn {
         dest: usize,              // P2: output register for result
         new_mode: Option<String>, // P3: new journal mode (if setting)
     },
+    /// Execute the trigger sub-program P4. The value of each register in `params` is bound to
+    /// the corresponding sub-program parameter, which is how the trigger body sees the NEW and
+    /// OLD row. If the sub-program halts through RAISE(IGNORE), jump to `ignore_jump_target`.
+    Program {
+        program: TriggerProgram,
+        params: Vec<(NonZero<usize>, usize)>,
+        ignore_jump_target: BranchOffset,
+        /// Name of the trigger, used only for EXPLAIN
+        trigger_name: String,
+    },
+    /// Remove the trigger from the in-memory schema of database P1.
+    DropTrigger {
+        db: usize,
+        trigger_name: String,
+    },
+    /// Add `increment_value` to a counter of foreign key violations. The counter of the
+    /// connection is used if `deferred` is set or `PRAGMA defer_foreign_keys` is on, which
+    /// is checked when the transaction commits; otherwise the counter of the statement,
+    /// which is checked when the statement halts.
+    FkCounter {
+        increment_value: i64,
+        deferred: bool,
+    },
+    /// Jump to `target_pc` if the deferred (if `deferred` is set) or immediate foreign key
+    /// violation counter is zero. Used to skip checks that can only decrement the counter.
+    FkIfZero {
+        deferred: bool,
+        target_pc: BranchOffset,
+    },
 }
 
 impl Insn {
@@ -1114,8 +1185,10 @@ impl Insn {
             Insn::VFilter { .. } => execute::op_vfilter,
             Insn::VColumn { .. } => execute::op_vcolumn,
             Insn::VUpdate { .. } => execute::op_vupdate,
+            Insn::VBegin { .. } => execute::op_vbegin,
             Insn::VNext { .. } => execute::op_vnext,
             Insn::VDestroy { .. } => execute::op_vdestroy,
+            Insn::VRename { .. } => execute::op_vrename,
 
             Insn::OpenPseudo { .. } => execute::op_open_pseudo,
             Insn::Rewind { .. } => execute::op_rewind,
@@ -1127,9 +1200,11 @@ impl Insn {
             Insn::Next { .. } => execute::op_next,
             Insn::Prev { .. } => execute::op_prev,
             Insn::Halt { .. } => execute::op_halt,
+            Insn::RaiseIgnore => execute::op_raise_ignore,
             Insn::HaltIfNull { .. } => execute::op_halt_if_null,
             Insn::Transaction { .. } => execute::op_transaction,
             Insn::AutoCommit { .. } => execute::op_auto_commit,
+            Insn::Savepoint { .. } => execute::op_savepoint,
             Insn::Goto { .. } => execute::op_goto,
             Insn::Gosub { .. } => execute::op_gosub,
             Insn::Return { .. } => execute::op_return,
@@ -1155,6 +1230,7 @@ impl Insn {
             Insn::DecrJumpZero { .. } => execute::op_decr_jump_zero,
             Insn::AggStep { .. } => execute::op_agg_step,
             Insn::AggFinal { .. } => execute::op_agg_final,
+            Insn::AggValue { .. } => execute::op_agg_value,
             Insn::SorterOpen { .. } => execute::op_sorter_open,
             Insn::SorterInsert { .. } => execute::op_sorter_insert,
             Insn::SorterSort { .. } => execute::op_sorter_sort,
@@ -1186,6 +1262,7 @@ impl Insn {
             Insn::IsNull { .. } => execute::op_is_null,
             Insn::CollSeq { .. } => execute::op_coll_seq,
             Insn::ParseSchema { .. } => execute::op_parse_schema,
+            Insn::Vacuum { .. } => execute::op_vacuum,
             Insn::PopulateMaterializedViews => execute::op_populate_materialized_views,
             Insn::ShiftRight { .. } => execute::op_shift_right,
             Insn::ShiftLeft { .. } => execute::op_shift_left,
@@ -1213,10 +1290,25 @@ impl Insn {
             Insn::AlterColumn { .. } => execute::op_alter_column,
             Insn::MaxPgcnt { .. } => execute::op_max_pgcnt,
             Insn::JournalMode { .. } => execute::op_journal_mode,
+            Insn::Program { .. } => execute::op_program,
+            Insn::DropTrigger { .. } => execute::op_drop_trigger,
+            Insn::FkCounter { .. } => execute::op_fk_counter,
+            Insn::FkIfZero { .. } => execute::op_fk_if_zero,
         }
     }
 }
 
+/// The action performed by [Insn::Savepoint].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SavepointOp {
+    /// `SAVEPOINT name`
+    Begin = 0,
+    /// `RELEASE name`
+    Release = 1,
+    /// `ROLLBACK TO name`
+    Rollback = 2,
+}
+
 // TODO: Add remaining cookies.
 #[derive(Description, Debug, Clone, Copy)]
 pub enum Cookie {
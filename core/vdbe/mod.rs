@@ -277,6 +277,12 @@ pub struct ProgramState {
     current_collation: Option<CollationSeq>,
     op_column_state: OpColumnState,
     op_row_id_state: OpRowIdState,
+    /// Set when a trigger sub-program halts through RAISE(IGNORE)
+    raise_ignore: bool,
+    /// Number of trigger programs this program is nested in
+    trigger_depth: usize,
+    /// Number of immediate foreign key violations caused by the statement
+    fk_immediate_violations: i64,
 }
 
 impl ProgramState {
@@ -319,6 +325,9 @@ impl ProgramState {
             current_collation: None,
             op_column_state: OpColumnState::Start,
             op_row_id_state: OpRowIdState::Start,
+            raise_ignore: false,
+            trigger_depth: 0,
+            fk_immediate_violations: 0,
         }
     }
 
@@ -371,6 +380,8 @@ impl ProgramState {
         self.interrupted = false;
         self.parameters.clear();
         self.current_collation = None;
+        self.raise_ignore = false;
+        self.fk_immediate_violations = 0;
         #[cfg(feature = "json")]
         self.json_cache.clear()
     }
@@ -432,8 +443,18 @@ pub struct Program {
     pub accesses_db: bool,
 }
 
+impl std::fmt::Debug for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Program")
+            .field("sql", &self.sql)
+            .field("max_registers", &self.max_registers)
+            .field("insns", &self.insns.len())
+            .finish()
+    }
+}
+
 impl Program {
-    fn get_pager_from_database_index(&self, idx: &usize) -> Rc<Pager> {
+    fn get_pager_from_database_index(&self, idx: &usize) -> Result<Rc<Pager>> {
         self.connection.get_pager_from_database_index(idx)
     }
 
@@ -448,6 +469,7 @@ impl Program {
             if self.connection.closed.get() {
                 // Connection is closed for whatever reason, rollback the transaction.
                 let state = self.connection.transaction_state.get();
+                self.connection.end_attached_transactions(true)?;
                 if let TransactionState::Write { .. } = state {
                     pager.io.block(|| pager.end_tx(true, &self.connection))?;
                 }
@@ -511,9 +533,9 @@ impl Program {
     }
 
     #[instrument(skip_all, level = Level::DEBUG)]
-    fn apply_view_deltas(&self, rollback: bool) {
+    fn apply_view_deltas(&self, pager: &Rc<Pager>, rollback: bool) -> Result<()> {
         if self.connection.view_transaction_states.borrow().is_empty() {
-            return;
+            return Ok(());
         }
 
         let tx_states = self.connection.view_transaction_states.take();
@@ -524,10 +546,13 @@ impl Program {
             for (view_name, tx_state) in tx_states.iter() {
                 if let Some(view_mutex) = schema.get_materialized_view(view_name) {
                     let mut view = view_mutex.lock().unwrap();
-                    view.merge_delta(&tx_state.delta);
+                    view.merge_delta(&tx_state.deltas);
+                    // The state table is written by the transaction being committed
+                    view.persist_changes(&schema, pager)?;
                 }
             }
         }
+        Ok(())
     }
 
     pub fn commit_txn(
@@ -537,7 +562,32 @@ impl Program {
         mv_store: Option<&Arc<MvStore>>,
         rollback: bool,
     ) -> Result<IOResult<()>> {
-        self.apply_view_deltas(rollback);
+        // Virtual tables are synced before the databases commit, so that they can still abort
+        // the transaction, and committed once the databases have.
+        let ends_vtab_transactions =
+            self.connection.auto_commit.get() && !self.connection.is_nested_stmt.get();
+        if ends_vtab_transactions && program_state.commit_state != CommitState::Committing {
+            if rollback {
+                self.connection.rollback_vtab_transactions();
+            } else {
+                self.connection.sync_vtab_transactions()?;
+            }
+        }
+        let result = self.end_database_txn(pager, program_state, mv_store, rollback)?;
+        if ends_vtab_transactions && matches!(result, IOResult::Done(())) {
+            self.connection.commit_vtab_transactions()?;
+        }
+        Ok(result)
+    }
+
+    fn end_database_txn(
+        &self,
+        pager: Rc<Pager>,
+        program_state: &mut ProgramState,
+        mv_store: Option<&Arc<MvStore>>,
+        rollback: bool,
+    ) -> Result<IOResult<()>> {
+        self.apply_view_deltas(&pager, rollback)?;
 
         if self.connection.transaction_state.get() == TransactionState::None && mv_store.is_none() {
             // No need to do any work here if not in tx. Current MVCC logic doesn't work with this assumption,
@@ -550,12 +600,24 @@ impl Program {
             if auto_commit {
                 // FIXME: we don't want to commit stuff from other programs.
                 let mut mv_transactions = conn.mv_transactions.borrow_mut();
-                for tx_id in mv_transactions.iter() {
+                for tx_id in mv_transactions.clone() {
                     let mut state_machine =
-                        mv_store.commit_tx(*tx_id, pager.clone(), &conn).unwrap();
+                        mv_store.commit_tx(tx_id, pager.clone(), &conn).unwrap();
                     // TODO: sync IO hack
                     loop {
-                        let res = state_machine.step(mv_store)?;
+                        let res = match state_machine.step(mv_store) {
+                            Ok(res) => res,
+                            Err(LimboError::SerializationFailure) => {
+                                // The store already rolled the transaction back, so the
+                                // connection is left without a transaction, ready to retry.
+                                conn.mv_tx_id.set(None);
+                                conn.transaction_state.replace(TransactionState::None);
+                                conn.end_deferred_fk_checks();
+                                mv_transactions.clear();
+                                return Err(LimboError::SerializationFailure);
+                            }
+                            Err(e) => return Err(e),
+                        };
                         match res {
                             crate::state_machine::TransitionResult::Io(io) => {
                                 io.wait(conn._db.io.as_ref())?;
@@ -568,6 +630,7 @@ impl Program {
                 }
                 conn.mv_tx_id.set(None);
                 conn.transaction_state.replace(TransactionState::None);
+                conn.end_deferred_fk_checks();
                 mv_transactions.clear();
             }
             Ok(IOResult::Done(()))
@@ -592,6 +655,7 @@ impl Program {
             } else if auto_commit {
                 let current_state = connection.transaction_state.get();
                 tracing::trace!("Auto-commit state: {:?}", current_state);
+                connection.end_attached_transactions(rollback)?;
                 match current_state {
                     TransactionState::Write { .. } => self.step_end_write_txn(
                         &pager,
@@ -633,6 +697,8 @@ impl Program {
                     self.connection.set_changes(self.n_change.get());
                 }
                 connection.transaction_state.replace(TransactionState::None);
+                connection.end_deferred_fk_checks();
+                connection.finish_attached_commit()?;
                 *commit_state = CommitState::Ready;
             }
             IOResult::IO(io) => {
@@ -881,6 +947,7 @@ pub fn handle_program_error(
         // Table locked errors, e.g. trying to checkpoint in an interactive transaction, do not cause a rollback.
         LimboError::TableLocked => {}
         _ => {
+            connection.end_attached_transactions(true)?;
             pager
                 .io
                 .block(|| pager.end_tx(true, connection))
@@ -888,6 +955,9 @@ pub fn handle_program_error(
                     tracing::error!("end_tx failed: {e}");
                 })?;
             connection.transaction_state.replace(TransactionState::None);
+            connection.rollback_vtab_transactions();
+            connection.clear_savepoints();
+            connection.end_deferred_fk_checks();
         }
     }
     Ok(())
@@ -653,7 +653,7 @@ impl Ord for SortableImmutableRecord {
             let this_key_value = &self.key_values.borrow()[i];
             let other_key_value = &other.key_values.borrow()[i];
             let column_order = self.index_key_info[i].sort_order;
-            let collation = self.index_key_info[i].collation;
+            let collation = &self.index_key_info[i].collation;
 
             let cmp = match (this_key_value, other_key_value) {
                 (RefValue::Text(left), RefValue::Text(right)) => {
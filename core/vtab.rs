@@ -3,13 +3,25 @@ use crate::schema::Column;
 use crate::util::columns_from_create_table_body;
 use crate::{Connection, LimboError, SymbolTable, Value};
 
-use std::ffi::c_void;
+use std::ffi::{c_void, CString};
 use std::ptr::NonNull;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use turso_ext::{ConstraintInfo, IndexInfo, OrderByInfo, ResultCode, VTabKind, VTabModuleImpl};
 use turso_parser::{ast, parser::Parser};
 
+/// A step in the transaction of a virtual table, see [VirtualTable::transaction]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VTabTransactionOp {
+    Begin,
+    Sync,
+    Commit,
+    Rollback,
+    Savepoint(usize),
+    Release(usize),
+    RollbackTo(usize),
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum VirtualTableType {
     Pragma(PragmaVirtualTable),
@@ -40,7 +52,7 @@ impl VirtualTable {
             .map(|(tab, schema)| {
                 let vtab = VirtualTable {
                     name: format!("pragma_{}", tab.pragma_name),
-                    columns: Self::resolve_columns(schema)
+                    columns: Self::resolve_columns(schema, &SymbolTable::new())
                         .expect("built-in function schema resolution should not fail"),
                     kind: VTabKind::TableValuedFunction,
                     vtab_type: VirtualTableType::Pragma(tab),
@@ -63,7 +75,7 @@ impl VirtualTable {
 
         let vtab = VirtualTable {
             name: name.to_owned(),
-            columns: Self::resolve_columns(schema)?,
+            columns: Self::resolve_columns(schema, syms)?,
             kind: VTabKind::TableValuedFunction,
             vtab_type,
         };
@@ -81,7 +93,7 @@ impl VirtualTable {
             ExtVirtualTable::create(module_name, module, args, VTabKind::VirtualTable)?;
         let vtab = VirtualTable {
             name: tbl_name.unwrap_or(module_name).to_owned(),
-            columns: Self::resolve_columns(schema)?,
+            columns: Self::resolve_columns(schema, syms)?,
             kind: VTabKind::VirtualTable,
             vtab_type: VirtualTableType::External(table),
         };
@@ -103,12 +115,12 @@ impl VirtualTable {
         Ok(Arc::new(vtab))
     }
 
-    fn resolve_columns(schema: String) -> crate::Result<Vec<Column>> {
+    fn resolve_columns(schema: String, syms: &SymbolTable) -> crate::Result<Vec<Column>> {
         let mut parser = Parser::new(schema.as_bytes());
         if let ast::Cmd::Stmt(ast::Stmt::CreateTable { body, .. }) = parser.next_cmd()?.ok_or(
             LimboError::ParseError("Failed to parse schema from virtual table module".to_string()),
         )? {
-            columns_from_create_table_body(&body)
+            columns_from_create_table_body(&body, syms)
         } else {
             Err(LimboError::ParseError(
                 "Failed to parse schema from virtual table module".to_string(),
@@ -146,6 +158,34 @@ impl VirtualTable {
         }
     }
 
+    /// Whether both are the same table. Copies of a table made for a schema share its instance.
+    pub(crate) fn same_instance(&self, other: &VirtualTable) -> bool {
+        match (&self.vtab_type, &other.vtab_type) {
+            (VirtualTableType::External(a), VirtualTableType::External(b)) => {
+                a.table_ptr == b.table_ptr
+            }
+            _ => self.name == other.name,
+        }
+    }
+
+    /// Notifies the table of a step in the transaction that writes it. Only tables implemented
+    /// by extensions take part in transactions.
+    pub(crate) fn transaction(&self, op: VTabTransactionOp) -> crate::Result<()> {
+        match &self.vtab_type {
+            VirtualTableType::Pragma(_) => Ok(()),
+            VirtualTableType::External(table) => table.transaction(op),
+            VirtualTableType::View(_) => Ok(()),
+        }
+    }
+
+    pub(crate) fn rename(&self, new_name: &str) -> crate::Result<()> {
+        match &self.vtab_type {
+            VirtualTableType::Pragma(_) => Err(LimboError::ReadOnly),
+            VirtualTableType::External(table) => table.rename(new_name),
+            VirtualTableType::View(_) => Err(LimboError::ReadOnly),
+        }
+    }
+
     pub(crate) fn best_index(
         &self,
         constraints: &[ConstraintInfo],
@@ -215,7 +255,7 @@ pub(crate) struct ExtVirtualTable {
 
 impl ExtVirtualTable {
     pub(crate) fn readonly(&self) -> bool {
-        self.implementation.readonly
+        self.implementation.readonly || unsafe { (self.implementation.is_readonly)(self.table_ptr) }
     }
     fn best_index(
         &self,
@@ -311,6 +351,42 @@ impl ExtVirtualTable {
             _ => Err(LimboError::ExtensionError(rc.to_string())),
         }
     }
+
+    fn transaction(&self, op: VTabTransactionOp) -> crate::Result<()> {
+        let implementation = &self.implementation;
+        let rc = unsafe {
+            match op {
+                VTabTransactionOp::Begin => (implementation.begin)(self.table_ptr),
+                VTabTransactionOp::Sync => (implementation.sync)(self.table_ptr),
+                VTabTransactionOp::Commit => (implementation.commit)(self.table_ptr),
+                VTabTransactionOp::Rollback => (implementation.rollback)(self.table_ptr),
+                VTabTransactionOp::Savepoint(savepoint) => {
+                    (implementation.savepoint)(self.table_ptr, savepoint as i32)
+                }
+                VTabTransactionOp::Release(savepoint) => {
+                    (implementation.release)(self.table_ptr, savepoint as i32)
+                }
+                VTabTransactionOp::RollbackTo(savepoint) => {
+                    (implementation.rollback_to)(self.table_ptr, savepoint as i32)
+                }
+            }
+        };
+        match rc {
+            ResultCode::OK => Ok(()),
+            _ => Err(LimboError::ExtensionError(rc.to_string())),
+        }
+    }
+
+    fn rename(&self, new_name: &str) -> crate::Result<()> {
+        let new_name = CString::new(new_name).map_err(|_| {
+            LimboError::InvalidArgument("table name contains a NUL byte".to_string())
+        })?;
+        let rc = unsafe { (self.implementation.rename)(self.table_ptr, new_name.as_ptr()) };
+        match rc {
+            ResultCode::OK => Ok(()),
+            _ => Err(LimboError::ExtensionError(rc.to_string())),
+        }
+    }
 }
 
 pub struct ExtVirtualTableCursor {
@@ -369,7 +445,7 @@ impl ExtVirtualTableCursor {
         match rc {
             ResultCode::OK => Ok(true),
             ResultCode::EOF => Ok(false),
-            _ => Err(LimboError::ExtensionError(rc.to_string())),
+            _ => Err(self.error(rc.to_string())),
         }
     }
 
@@ -383,8 +459,18 @@ impl ExtVirtualTableCursor {
         match rc {
             ResultCode::OK => Ok(true),
             ResultCode::EOF => Ok(false),
-            _ => Err(LimboError::ExtensionError("Next failed".to_string())),
+            _ => Err(self.error("Next failed".to_string())),
+        }
+    }
+
+    /// The error the cursor failed with, described by `fallback` if the cursor has no message.
+    fn error(&self, fallback: String) -> LimboError {
+        let message = unsafe { (self.implementation.error_message)(self.cursor.as_ptr()) };
+        if message.is_null() {
+            return LimboError::ExtensionError(fallback);
         }
+        let message = unsafe { CString::from_raw(message as *mut _) };
+        LimboError::ExtensionError(message.to_string_lossy().into_owned())
     }
 }
 
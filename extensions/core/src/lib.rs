@@ -4,13 +4,14 @@ mod types;
 mod vfs_modules;
 mod vtabs;
 pub use functions::{
-    AggCtx, AggFunc, FinalizeFunction, InitAggFunction, ScalarFunction, StepFunction,
+    AggCtx, AggFunc, CollationFunction, FinalizeFunction, InitAggFunction, ScalarFunction,
+    StepFunction,
 };
-use functions::{RegisterAggFn, RegisterScalarFn};
+use functions::{RegisterAggFn, RegisterCollationFn, RegisterScalarFn};
 use std::os::raw::c_void;
 #[cfg(feature = "vfs")]
 pub use turso_macros::VfsDerive;
-pub use turso_macros::{register_extension, scalar, AggregateDerive, VTabModuleDerive};
+pub use turso_macros::{collation, register_extension, scalar, AggregateDerive, VTabModuleDerive};
 pub use types::{ResultCode, StepResult, Value, ValueType};
 #[cfg(feature = "vfs")]
 pub use vfs_modules::{
@@ -21,7 +22,7 @@ use vtabs::RegisterModuleFn;
 pub use vtabs::{
     Conn, Connection, ConstraintInfo, ConstraintOp, ConstraintUsage, ExtIndexInfo, IndexInfo,
     OrderByInfo, Statement, Stmt, VTabCreateResult, VTabCursor, VTabKind, VTabModule,
-    VTabModuleImpl, VTable,
+    VTabModuleImpl, VTable, ROWID_COLUMN_INDEX,
 };
 
 pub type ExtResult<T> = std::result::Result<T, ResultCode>;
@@ -36,6 +37,7 @@ pub struct ExtensionApi {
     pub register_vtab_module: RegisterModuleFn,
     #[cfg(feature = "vfs")]
     pub vfs_interface: VfsInterface,
+    pub register_collation: RegisterCollationFn,
 }
 
 unsafe impl Send for ExtensionApi {}
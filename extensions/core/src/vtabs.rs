@@ -28,6 +28,16 @@ pub struct VTabModuleImpl {
     pub rowid: VtabRowIDFn,
     pub destroy: VtabFnDestroy,
     pub best_idx: BestIdxFn,
+    pub begin: VtabFnBegin,
+    pub sync: VtabFnSync,
+    pub commit: VtabFnCommit,
+    pub rollback: VtabFnRollback,
+    pub rename: VtabFnRename,
+    pub savepoint: VtabFnSavepoint,
+    pub release: VtabFnRelease,
+    pub rollback_to: VtabFnRollbackTo,
+    pub is_readonly: VtabFnIsReadonly,
+    pub error_message: VtabFnErrorMessage,
 }
 
 #[repr(C)]
@@ -103,6 +113,30 @@ pub type VtabFnUpdate = unsafe extern "C" fn(
 
 pub type VtabFnDestroy = unsafe extern "C" fn(table: *const c_void) -> ResultCode;
 
+pub type VtabFnBegin = unsafe extern "C" fn(table: *const c_void) -> ResultCode;
+
+pub type VtabFnSync = unsafe extern "C" fn(table: *const c_void) -> ResultCode;
+
+pub type VtabFnCommit = unsafe extern "C" fn(table: *const c_void) -> ResultCode;
+
+pub type VtabFnRollback = unsafe extern "C" fn(table: *const c_void) -> ResultCode;
+
+pub type VtabFnRename =
+    unsafe extern "C" fn(table: *const c_void, new_name: *const c_char) -> ResultCode;
+
+pub type VtabFnSavepoint = unsafe extern "C" fn(table: *const c_void, savepoint: i32) -> ResultCode;
+
+pub type VtabFnRelease = unsafe extern "C" fn(table: *const c_void, savepoint: i32) -> ResultCode;
+
+pub type VtabFnRollbackTo =
+    unsafe extern "C" fn(table: *const c_void, savepoint: i32) -> ResultCode;
+
+pub type VtabFnIsReadonly = unsafe extern "C" fn(table: *const c_void) -> bool;
+
+/// Returns the message of the error the cursor last reported, or null. The caller owns the
+/// returned string.
+pub type VtabFnErrorMessage = unsafe extern "C" fn(cursor: *const c_void) -> *const c_char;
+
 pub type BestIdxFn = unsafe extern "C" fn(
     constraints: *const ConstraintInfo,
     constraint_len: i32,
@@ -148,6 +182,50 @@ pub trait VTable {
         Ok(())
     }
 
+    /// Called when the table is first written in a transaction. Every transaction started with
+    /// `begin` ends with either `commit` or `rollback`.
+    fn begin(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called before the transaction commits, on every table written in it. An error aborts the
+    /// commit, and the transaction is rolled back.
+    fn sync(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called once the transaction has committed.
+    fn commit(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called when the transaction rolls back, discarding the changes made since `begin`.
+    fn rollback(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called by `ALTER TABLE ... RENAME TO` before the table is renamed. An error prevents
+    /// the rename.
+    fn rename(&mut self, _new_name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called when savepoint number `savepoint` is opened in a transaction writing the table.
+    /// Savepoints are numbered from 0, the outermost one.
+    fn savepoint(&mut self, _savepoint: usize) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called when savepoint number `savepoint` and all savepoints opened after it are
+    /// released.
+    fn release(&mut self, _savepoint: usize) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Called on `ROLLBACK TO` savepoint number `savepoint`, which discards the changes made
+    /// since it was opened. The savepoint stays open.
+    fn rollback_to(&mut self, _savepoint: usize) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Whether `INSERT`, `UPDATE` and `DELETE` are rejected for this table, even though its
+    /// module isn't [VTabModule::READONLY].
+    fn readonly(&self) -> bool {
+        false
+    }
+
     /// The query planner may call this method multiple times during optimization, exploring
     /// different join orders. Each call asks the virtual table which constraints (WHERE clause
     /// terms) it can efficiently handle. Based on the incoming `ConstraintInfo`s, the virtual table
@@ -196,6 +274,11 @@ pub trait VTabCursor: Sized {
     fn close(&self) -> ResultCode {
         ResultCode::OK
     }
+    /// Describes the error that the last call to `filter` or `next` failed with, if there is more
+    /// to say than its result code.
+    fn error_message(&self) -> Option<String> {
+        None
+    }
 }
 
 #[repr(u8)]
@@ -375,12 +458,16 @@ pub struct ConstraintUsage {
     pub omit: bool,
 }
 
+/// The `column_index` of a [ConstraintInfo] on the rowid of a virtual table.
+pub const ROWID_COLUMN_INDEX: u32 = u32::MAX;
+
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 /// The primary argument to xBestIndex, which describes a constraint
 /// in a query involving a virtual table.
 pub struct ConstraintInfo {
-    /// The index of the column referenced in the WHERE clause.
+    /// The index of the column referenced in the WHERE clause, or [ROWID_COLUMN_INDEX] if the
+    /// constraint is on the rowid.
     pub column_index: u32,
     /// The operator used in the clause.
     pub op: ConstraintOp,
@@ -19,13 +19,34 @@
 //! - `header` — whether the first row contains column names;
 //!   accepts `yes`/`no`, `on`/`off`, `true`/`false`, or `1`/`0`
 //! - `columns` — number of columns
-//! - `schema` — optional custom SQL `CREATE TABLE` schema
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
+//! - `schema` — optional custom SQL `CREATE TABLE` schema; the declared column types give the
+//!   columns their affinities, as they would for a regular table
+//! - `writable` — whether `INSERT`, `UPDATE` and `DELETE` are allowed (requires `filename=`);
+//!   accepts the same values as `header`
+//!
+//! ## Writable tables
+//!
+//! The rows of a writable table are read into memory when a transaction first changes it. On
+//! commit, inserted rows are appended to the file, and if any row of the file was updated or
+//! deleted, the whole file is rewritten instead. The rowid of a row is its position in the file,
+//! so rowids of later rows change once a deletion is committed.
+//!
+//! ```sql
+//! CREATE VIRTUAL TABLE temp.staging USING csv(filename='out.csv', header=yes, writable=yes,
+//!     schema='CREATE TABLE x(id INTEGER, name TEXT)');
+//! INSERT INTO staging SELECT id, name FROM users;
+//! ```
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::rc::Rc;
 use std::sync::Arc;
 use turso_ext::{
-    register_extension, Connection, ResultCode, VTabCursor, VTabKind, VTabModule, VTabModuleDerive,
-    VTable, Value,
+    register_extension, Connection, ConstraintInfo, ConstraintOp, ConstraintUsage, IndexInfo,
+    OrderByInfo, ResultCode, VTabCursor, VTabKind, VTabModule, VTabModuleDerive, VTable, Value,
+    ValueType, ROWID_COLUMN_INDEX,
 };
 
 register_extension! {
@@ -102,13 +123,178 @@ impl CsvVTabModule {
     fn escape_double_quote(identifier: &str) -> String {
         identifier.replace('"', "\"\"")
     }
+
+    /// Returns the names and affinities of the columns declared by a `CREATE TABLE` statement.
+    fn parse_columns(schema: &str) -> Vec<(String, Affinity)> {
+        let (Some(start), Some(end)) = (schema.find('('), schema.rfind(')')) else {
+            return Vec::new();
+        };
+        if end < start {
+            return Vec::new();
+        }
+        Self::split_definitions(&schema[start + 1..end])
+            .into_iter()
+            .filter_map(|definition| {
+                let definition = definition.trim();
+                let first_word = definition.split_whitespace().next()?;
+                if TABLE_CONSTRAINTS
+                    .iter()
+                    .any(|keyword| first_word.eq_ignore_ascii_case(keyword))
+                {
+                    return None;
+                }
+                let (name, rest) = Self::split_column_name(definition);
+                let declared_type = rest
+                    .split_whitespace()
+                    .take_while(|word| {
+                        !COLUMN_CONSTRAINTS
+                            .iter()
+                            .any(|keyword| word.eq_ignore_ascii_case(keyword))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Some((name, Affinity::from_declared_type(&declared_type)))
+            })
+            .collect()
+    }
+
+    /// Splits the body of a `CREATE TABLE` statement on the commas that separate its definitions.
+    fn split_definitions(body: &str) -> Vec<&str> {
+        let mut definitions = Vec::new();
+        let mut depth = 0;
+        let mut quote = None;
+        let mut start = 0;
+        for (i, c) in body.char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'' | '`') => quote = Some(c),
+                (None, '[') => quote = Some(']'),
+                (None, '(') => depth += 1,
+                (None, ')') => depth -= 1,
+                (None, ',') if depth == 0 => {
+                    definitions.push(&body[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        definitions.push(&body[start..]);
+        definitions
+    }
+
+    /// Splits a column definition into the unquoted column name and the rest of the definition.
+    fn split_column_name(definition: &str) -> (String, &str) {
+        let mut chars = definition.char_indices().peekable();
+        let close = match chars.next() {
+            Some((_, q @ ('"' | '\'' | '`'))) => q,
+            Some((_, '[')) => ']',
+            _ => {
+                let end = definition
+                    .find(char::is_whitespace)
+                    .unwrap_or(definition.len());
+                return (definition[..end].to_owned(), &definition[end..]);
+            }
+        };
+        let mut name = String::new();
+        while let Some((i, c)) = chars.next() {
+            if c != close {
+                name.push(c);
+            } else if close != ']' && chars.peek().is_some_and(|&(_, next)| next == close) {
+                // Escaped quote ("" or '')
+                name.push(c);
+                chars.next();
+            } else {
+                return (name, &definition[i + 1..]);
+            }
+        }
+        (name, "")
+    }
+}
+
+/// The keywords that start a table constraint in a `CREATE TABLE` statement.
+const TABLE_CONSTRAINTS: [&str; 5] = ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"];
+
+/// The keywords that end the declared type of a column.
+const COLUMN_CONSTRAINTS: [&str; 11] = [
+    "CONSTRAINT",
+    "PRIMARY",
+    "NOT",
+    "NULL",
+    "UNIQUE",
+    "CHECK",
+    "DEFAULT",
+    "COLLATE",
+    "REFERENCES",
+    "GENERATED",
+    "AS",
+];
+
+/// The type affinity of a column, which determines how its CSV fields are converted to values.
+/// See <https://www.sqlite.org/datatype3.html#type_affinity>.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    fn from_declared_type(declared_type: &str) -> Self {
+        let declared_type = declared_type.to_ascii_uppercase();
+        if declared_type.contains("INT") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|s| declared_type.contains(s))
+        {
+            Affinity::Text
+        } else if declared_type.is_empty() || declared_type.contains("BLOB") {
+            Affinity::Blob
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|s| declared_type.contains(s))
+        {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    /// Converts a CSV field to a value of this affinity. Fields that don't look like numbers stay
+    /// text, even in numeric columns.
+    fn apply(self, field: &str) -> Value {
+        let text = field.trim();
+        let number = if !text.bytes().any(|b| b.is_ascii_digit()) {
+            None
+        } else if let Ok(i) = text.parse::<i64>() {
+            Some(Ok(i))
+        } else {
+            text.parse::<f64>().ok().map(Err)
+        };
+        match (self, number) {
+            (Affinity::Text | Affinity::Blob, _) | (_, None) => Value::from_text(field.to_owned()),
+            (Affinity::Real, Some(Ok(i))) => Value::from_float(i as f64),
+            (_, Some(Ok(i))) => Value::from_integer(i),
+            (Affinity::Real, Some(Err(f))) => Value::from_float(f),
+            (_, Some(Err(f))) => {
+                if f.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(&f) {
+                    Value::from_integer(f as i64)
+                } else {
+                    Value::from_float(f)
+                }
+            }
+        }
+    }
 }
 
 impl VTabModule for CsvVTabModule {
     type Table = CsvTable;
     const VTAB_KIND: VTabKind = VTabKind::VirtualTable;
     const NAME: &'static str = "csv";
-    const READONLY: bool = true;
+    const READONLY: bool = false;
 
     fn create(args: &[Value]) -> Result<(String, Self::Table), ResultCode> {
         if args.is_empty() {
@@ -120,6 +306,7 @@ impl VTabModule for CsvVTabModule {
         let mut schema = None;
         let mut column_count = None;
         let mut header = None;
+        let mut writable = None;
 
         for arg in args {
             let (name, value) = Self::parse_arg(arg)?;
@@ -158,6 +345,12 @@ impl VTabModule for CsvVTabModule {
                     }
                     header = Some(Self::parse_boolean(value).ok_or(ResultCode::InvalidArgs)?);
                 }
+                "writable" => {
+                    if writable.is_some() {
+                        return Err(ResultCode::InvalidArgs);
+                    }
+                    writable = Some(Self::parse_boolean(value).ok_or(ResultCode::InvalidArgs)?);
+                }
                 _ => {
                     return Err(ResultCode::InvalidArgs);
                 }
@@ -168,6 +361,15 @@ impl VTabModule for CsvVTabModule {
             return Err(ResultCode::InvalidArgs);
         }
 
+        let writable = writable.unwrap_or(false);
+        if writable {
+            // A writable table can be used to export rows, so its file doesn't need to exist yet.
+            let path = filename.as_deref().ok_or(ResultCode::InvalidArgs)?;
+            if !Path::new(path).exists() {
+                File::create(path).map_err(|_| ResultCode::Error)?;
+            }
+        }
+
         let mut columns: Vec<String> = Vec::new();
 
         let mut table = CsvTable {
@@ -175,7 +377,12 @@ impl VTabModule for CsvVTabModule {
             filename,
             data,
             header: header.unwrap_or(false),
-            first_row_position: csv::Position::new(),
+            column_names: Vec::new(),
+            affinities: Vec::new(),
+            writable,
+            pending: None,
+            savepoints: BTreeMap::new(),
+            row_positions: Rc::default(),
         };
 
         if table.header || (column_count.is_none() && schema.is_none()) {
@@ -188,7 +395,6 @@ impl VTabModule for CsvVTabModule {
                 if columns.is_empty() {
                     columns.push("(NULL)".to_owned());
                 }
-                table.first_row_position = reader.position().clone();
             } else {
                 let mut record = csv::ByteRecord::new();
                 if reader
@@ -223,7 +429,10 @@ impl VTabModule for CsvVTabModule {
             schema = Some(sql);
         }
 
-        Ok((schema.unwrap(), table))
+        let schema = schema.unwrap();
+        (table.column_names, table.affinities) = Self::parse_columns(&schema).into_iter().unzip();
+
+        Ok((schema, table))
     }
 }
 
@@ -232,7 +441,31 @@ struct CsvTable {
     data: Option<String>,
     header: bool,
     column_count: Option<u32>,
-    first_row_position: csv::Position,
+    column_names: Vec<String>,
+    affinities: Vec<Affinity>,
+    writable: bool,
+    /// The rows of a writable table, while a transaction is changing them.
+    pending: Option<PendingRows>,
+    /// The pending rows as they were when each open savepoint was started.
+    savepoints: BTreeMap<usize, PendingRows>,
+    /// The positions in the file of the rows read so far, by rowid - 1, so that a rowid seek
+    /// doesn't need to read the rows before it.
+    row_positions: Rc<RefCell<Vec<csv::Position>>>,
+}
+
+/// The rows of a writable table, as changed by the current transaction.
+#[derive(Clone)]
+struct PendingRows {
+    headers: Option<csv::StringRecord>,
+    /// The rows by rowid - 1, or `None` for deleted rows.
+    rows: Vec<Option<csv::StringRecord>>,
+    /// The number of rows in the file when the transaction started.
+    file_rows: usize,
+    /// The number of fields that new rows are padded to.
+    width: usize,
+    /// Whether a row of the file was updated or deleted, so that the file must be rewritten
+    /// instead of having the new rows appended to it.
+    rewrite: bool,
 }
 
 impl CsvTable {
@@ -252,6 +485,126 @@ impl CsvTable {
             _ => Err(ResultCode::Internal),
         }
     }
+
+    fn read_pending_rows(&self) -> Result<PendingRows, ResultCode> {
+        let mut reader = self.new_reader()?;
+        let mut headers = None;
+        if self.header {
+            let mut record = reader.headers().map_err(|_| ResultCode::Error)?.clone();
+            if record.is_empty() {
+                // The file is empty, so the header is written along with the first rows.
+                record = self.column_names.iter().collect();
+            }
+            headers = Some(record);
+        }
+        let rows = reader
+            .records()
+            .map(|record| record.map(Some))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ResultCode::Error)?;
+        let width = rows
+            .iter()
+            .flatten()
+            .map(|record| record.len())
+            .chain(headers.as_ref().map(|record| record.len()))
+            .chain([self.column_names.len()])
+            .max()
+            .unwrap_or_default();
+        Ok(PendingRows {
+            headers,
+            file_rows: rows.len(),
+            rows,
+            width,
+            rewrite: false,
+        })
+    }
+
+    fn pending_rows(&mut self) -> Result<&mut PendingRows, ResultCode> {
+        if !self.writable {
+            return Err(ResultCode::ReadOnly);
+        }
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => self.read_pending_rows()?,
+        };
+        Ok(self.pending.insert(pending))
+    }
+
+    /// Writes the pending rows to the file, appending the new rows if no row of the file changed.
+    fn write_pending_rows(&self, pending: &PendingRows) -> Result<(), ResultCode> {
+        let path = self.filename.as_deref().ok_or(ResultCode::Internal)?;
+        if pending.rewrite {
+            // The rows move when the file is rewritten, so their positions are read again.
+            self.row_positions.borrow_mut().clear();
+            // Write to a temporary file first, so that a failed write leaves the file intact.
+            let temp_path = format!("{path}.tmp");
+            let mut writer = csv::WriterBuilder::new()
+                .flexible(true)
+                .from_path(&temp_path)
+                .map_err(|_| ResultCode::Error)?;
+            let records = pending.headers.iter().chain(pending.rows.iter().flatten());
+            for record in records {
+                writer.write_record(record).map_err(|_| ResultCode::Error)?;
+            }
+            writer.flush().map_err(|_| ResultCode::Error)?;
+            drop(writer);
+            return std::fs::rename(&temp_path, path).map_err(|_| ResultCode::Error);
+        }
+
+        let new_rows = &pending.rows[pending.file_rows..];
+        if new_rows.iter().all(Option::is_none) {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(|_| ResultCode::Error)?;
+        let len = file.metadata().map_err(|_| ResultCode::Error)?.len();
+        if len > 0 {
+            let mut last = [0u8];
+            file.seek(SeekFrom::End(-1))
+                .and_then(|_| file.read_exact(&mut last))
+                .map_err(|_| ResultCode::Error)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n").map_err(|_| ResultCode::Error)?;
+            }
+        }
+        let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(file);
+        let headers = pending.headers.iter().filter(|_| len == 0);
+        for record in headers.chain(new_rows.iter().flatten()) {
+            writer.write_record(record).map_err(|_| ResultCode::Error)?;
+        }
+        writer.flush().map_err(|_| ResultCode::Error)
+    }
+
+    fn to_record(values: &[Value], width: usize) -> csv::StringRecord {
+        let mut record: csv::StringRecord = values
+            .iter()
+            .map(|value| match value.value_type() {
+                ValueType::Integer => value.to_integer().unwrap_or_default().to_string(),
+                ValueType::Float => value.to_float().unwrap_or_default().to_string(),
+                ValueType::Text => value.to_text().unwrap_or_default().to_owned(),
+                ValueType::Blob => {
+                    String::from_utf8_lossy(&value.to_blob().unwrap_or_default()).into_owned()
+                }
+                ValueType::Null | ValueType::Error => String::new(),
+            })
+            .collect();
+        while record.len() < width {
+            record.push_field("");
+        }
+        record
+    }
+
+    /// Returns the index of a row in the pending rows, if it wasn't deleted.
+    fn row_index(pending: &PendingRows, rowid: i64) -> Result<usize, ResultCode> {
+        rowid
+            .checked_sub(1)
+            .and_then(|index| usize::try_from(index).ok())
+            .filter(|&index| pending.rows.get(index).is_some_and(Option::is_some))
+            .ok_or(ResultCode::NotFound)
+    }
 }
 
 impl VTable for CsvTable {
@@ -259,13 +612,150 @@ impl VTable for CsvTable {
     type Error = ResultCode;
 
     fn open(&self, _conn: Option<Arc<Connection>>) -> Result<Self::Cursor, Self::Error> {
-        match self.new_reader() {
-            Ok(reader) => Ok(CsvCursor::new(reader, self)),
-            Err(_) => Err(ResultCode::Error),
+        if let Some(pending) = &self.pending {
+            let rows = pending
+                .rows
+                .iter()
+                .enumerate()
+                .filter_map(|(i, row)| row.clone().map(|record| (i + 1, record)))
+                .collect();
+            let source = CursorSource::Rows { rows, position: 0 };
+            return Ok(CsvCursor::new(source, csv::Position::new(), self));
         }
+        let mut reader = self.new_reader().map_err(|_| ResultCode::Error)?;
+        // The header of a writable table may have been written since the table was created, so
+        // the position of the first row is only known once the file is opened.
+        let mut first_row_position = csv::Position::new();
+        if self.header {
+            reader.headers().map_err(|_| ResultCode::Error)?;
+            first_row_position = reader.position().clone();
+        }
+        let source = CursorSource::Reader(reader);
+        Ok(CsvCursor::new(source, first_row_position, self))
+    }
+
+    fn readonly(&self) -> bool {
+        !self.writable
+    }
+
+    fn insert(&mut self, args: &[Value]) -> Result<i64, Self::Error> {
+        let pending = self.pending_rows()?;
+        let record = Self::to_record(args, pending.width);
+        pending.rows.push(Some(record));
+        Ok(pending.rows.len() as i64)
+    }
+
+    fn update(&mut self, rowid: i64, args: &[Value]) -> Result<(), Self::Error> {
+        let pending = self.pending_rows()?;
+        let index = Self::row_index(pending, rowid)?;
+        let mut record = Self::to_record(args, 0);
+        if let Some(old) = &pending.rows[index] {
+            // Keep the fields beyond the declared columns.
+            for field in old.iter().skip(record.len()) {
+                record.push_field(field);
+            }
+        }
+        while record.len() < pending.width {
+            record.push_field("");
+        }
+        pending.rows[index] = Some(record);
+        pending.rewrite |= index < pending.file_rows;
+        Ok(())
+    }
+
+    fn delete(&mut self, rowid: i64) -> Result<(), Self::Error> {
+        let pending = self.pending_rows()?;
+        let index = Self::row_index(pending, rowid)?;
+        pending.rows[index] = None;
+        pending.rewrite |= index < pending.file_rows;
+        Ok(())
+    }
+
+    fn begin(&mut self) -> Result<(), Self::Error> {
+        if self.writable {
+            self.pending_rows()?;
+        }
+        Ok(())
+    }
+
+    fn sync(&mut self) -> Result<(), Self::Error> {
+        match &self.pending {
+            Some(pending) => self.write_pending_rows(pending),
+            None => Ok(()),
+        }
+    }
+
+    fn commit(&mut self) -> Result<(), Self::Error> {
+        self.pending = None;
+        self.savepoints.clear();
+        Ok(())
+    }
+
+    fn rollback(&mut self) -> Result<(), Self::Error> {
+        self.pending = None;
+        self.savepoints.clear();
+        Ok(())
+    }
+
+    fn savepoint(&mut self, savepoint: usize) -> Result<(), Self::Error> {
+        if let Some(pending) = &self.pending {
+            self.savepoints.insert(savepoint, pending.clone());
+        }
+        Ok(())
+    }
+
+    fn release(&mut self, savepoint: usize) -> Result<(), Self::Error> {
+        self.savepoints.split_off(&savepoint);
+        Ok(())
+    }
+
+    fn rollback_to(&mut self, savepoint: usize) -> Result<(), Self::Error> {
+        self.savepoints.split_off(&(savepoint + 1));
+        if let Some(pending) = self.savepoints.get(&savepoint) {
+            self.pending = Some(pending.clone());
+        }
+        Ok(())
+    }
+
+    /// A `rowid = ?` constraint is consumed, so that the cursor returns only that row.
+    fn best_index(
+        constraints: &[ConstraintInfo],
+        _order_by: &[OrderByInfo],
+    ) -> Result<IndexInfo, ResultCode> {
+        let rowid_constraint = constraints.iter().position(|c| {
+            c.usable && c.op == ConstraintOp::Eq && c.column_index == ROWID_COLUMN_INDEX
+        });
+        let constraint_usages = constraints
+            .iter()
+            .enumerate()
+            .map(|(i, _)| ConstraintUsage {
+                argv_index: (Some(i) == rowid_constraint).then_some(1),
+                omit: Some(i) == rowid_constraint,
+            })
+            .collect();
+        Ok(IndexInfo {
+            // The idx_num only reaches filter along with an idx_str.
+            idx_num: rowid_constraint.map_or(0, |_| ROWID_SEEK),
+            idx_str: rowid_constraint.map(|_| "rowid".to_owned()),
+            order_by_consumed: false,
+            estimated_cost: if rowid_constraint.is_some() {
+                10.0
+            } else {
+                1_000_000.0
+            },
+            estimated_rows: if rowid_constraint.is_some() {
+                1
+            } else {
+                u32::MAX
+            },
+            constraint_usages,
+        })
     }
 }
 
+/// The `idx_num` of a plan that seeks a single row by its rowid.
+const ROWID_SEEK: i32 = 1;
+
 enum ReadSource {
     File(File),
     Memory(std::io::Cursor<Vec<u8>>),
@@ -289,24 +779,95 @@ impl Seek for ReadSource {
     }
 }
 
+enum CursorSource {
+    Reader(csv::Reader<ReadSource>),
+    /// The pending rows of a writable table with their rowids, as they were when the cursor was
+    /// opened.
+    Rows {
+        rows: Vec<(usize, csv::StringRecord)>,
+        position: usize,
+    },
+}
+
 struct CsvCursor {
     column_count: Option<u32>,
-    reader: csv::Reader<ReadSource>,
+    affinities: Vec<Affinity>,
+    source: CursorSource,
     row_number: usize,
     current_row: csv::StringRecord,
     eof: bool,
     first_row_position: csv::Position,
+    row_positions: Rc<RefCell<Vec<csv::Position>>>,
+    /// Whether the cursor seeked a single row by its rowid.
+    seeked: bool,
 }
 
 impl CsvCursor {
-    fn new(reader: csv::Reader<ReadSource>, table: &CsvTable) -> Self {
+    fn new(source: CursorSource, first_row_position: csv::Position, table: &CsvTable) -> Self {
         CsvCursor {
             column_count: table.column_count,
-            reader,
+            affinities: table.affinities.clone(),
+            source,
             row_number: 0,
             current_row: csv::StringRecord::new(),
             eof: false,
-            first_row_position: table.first_row_position.clone(),
+            first_row_position,
+            row_positions: table.row_positions.clone(),
+            seeked: false,
+        }
+    }
+
+    fn rowid_arg(value: &Value) -> Option<i64> {
+        match value.value_type() {
+            ValueType::Integer => value.to_integer(),
+            ValueType::Float => value
+                .to_float()
+                .filter(|f| f.fract() == 0.0)
+                .map(|f| f as i64),
+            ValueType::Text => value.to_text()?.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn seek(&mut self, rowid: Option<i64>) -> ResultCode {
+        let Some(rowid) = rowid.filter(|rowid| *rowid > 0) else {
+            self.eof = true;
+            return ResultCode::EOF;
+        };
+        match &mut self.source {
+            CursorSource::Reader(reader) => {
+                // Start from the row itself if its position is known, or else from the last row
+                // whose position is.
+                let known = self.row_positions.borrow().len();
+                let start = known.min(rowid as usize);
+                if start > 0 {
+                    let position = self.row_positions.borrow()[start - 1].clone();
+                    if reader.seek(position).is_err() {
+                        return ResultCode::Error;
+                    }
+                    self.row_number = start - 1;
+                }
+            }
+            CursorSource::Rows { rows, position } => {
+                // The rows are ordered by rowid.
+                *position = rows.partition_point(|(id, _)| (*id as i64) < rowid);
+            }
+        }
+        let mut rc = self.next();
+        while rc == ResultCode::OK && (self.row_number as i64) < rowid {
+            rc = self.next();
+        }
+        if rc == ResultCode::Error {
+            return rc;
+        }
+        if !self.eof && self.row_number as i64 != rowid {
+            self.eof = true;
+        }
+        self.seeked = true;
+        if self.eof {
+            ResultCode::EOF
+        } else {
+            ResultCode::OK
         }
     }
 }
@@ -314,12 +875,22 @@ impl CsvCursor {
 impl VTabCursor for CsvCursor {
     type Error = ResultCode;
 
-    fn filter(&mut self, _args: &[Value], _idx_info: Option<(&str, i32)>) -> ResultCode {
-        let offset_first_row = self.first_row_position.clone();
-        if self.reader.seek(offset_first_row).is_err() {
-            return ResultCode::Error;
-        };
+    fn filter(&mut self, args: &[Value], idx_info: Option<(&str, i32)>) -> ResultCode {
+        match &mut self.source {
+            CursorSource::Reader(reader) => {
+                let offset_first_row = self.first_row_position.clone();
+                if reader.seek(offset_first_row).is_err() {
+                    return ResultCode::Error;
+                };
+            }
+            CursorSource::Rows { position, .. } => *position = 0,
+        }
         self.row_number = 0;
+        self.eof = false;
+        self.seeked = false;
+        if idx_info.is_some_and(|(_, idx_num)| idx_num == ROWID_SEEK) {
+            return self.seek(args.first().and_then(Self::rowid_arg));
+        }
         self.next()
     }
 
@@ -333,10 +904,15 @@ impl VTabCursor for CsvCursor {
                 return Ok(Value::null());
             }
         }
+        let affinity = self
+            .affinities
+            .get(idx as usize)
+            .copied()
+            .unwrap_or(Affinity::Text);
         let value = self
             .current_row
             .get(idx as usize)
-            .map_or(Value::null(), |s| Value::from_text(s.to_owned()));
+            .map_or(Value::null(), |s| affinity.apply(s));
         Ok(value)
     }
 
@@ -345,24 +921,43 @@ impl VTabCursor for CsvCursor {
     }
 
     fn next(&mut self) -> ResultCode {
-        {
-            self.eof = self.reader.is_done();
-            if self.eof {
-                return ResultCode::EOF;
-            }
+        if self.seeked {
+            self.eof = true;
+            return ResultCode::EOF;
+        }
+        match &mut self.source {
+            CursorSource::Reader(reader) => {
+                self.eof = reader.is_done();
+                if self.eof {
+                    return ResultCode::EOF;
+                }
 
-            match self.reader.read_record(&mut self.current_row) {
-                Ok(more) => {
-                    self.eof = !more;
-                    if self.eof {
-                        return ResultCode::EOF;
+                let position = reader.position().clone();
+                match reader.read_record(&mut self.current_row) {
+                    Ok(more) => {
+                        self.eof = !more;
+                        if self.eof {
+                            return ResultCode::EOF;
+                        }
                     }
+                    Err(_) => return ResultCode::Error,
                 }
-                Err(_) => return ResultCode::Error,
+                let mut row_positions = self.row_positions.borrow_mut();
+                if row_positions.len() == self.row_number {
+                    row_positions.push(position);
+                }
+                self.row_number += 1;
+            }
+            CursorSource::Rows { rows, position } => {
+                let Some((rowid, record)) = rows.get(*position) else {
+                    self.eof = true;
+                    return ResultCode::EOF;
+                };
+                self.row_number = *rowid;
+                self.current_row = record.clone();
+                *position += 1;
             }
         }
-
-        self.row_number += 1;
         ResultCode::OK
     }
 }
@@ -404,6 +999,8 @@ mod tests {
                     Ok(v) => match v.value_type() {
                         ValueType::Null => None,
                         ValueType::Text => v.to_text().map(|s| s.to_owned()),
+                        ValueType::Integer => v.to_integer().map(|i| i.to_string()),
+                        ValueType::Float => v.to_float().map(|f| f.to_string()),
                         _ => panic!("Unexpected column type"),
                     },
                     Err(_) => panic!("Error reading column"),
@@ -852,4 +1449,219 @@ mod tests {
             "CREATE TABLE x (\"id\" TEXT, \"first\"\"name\" TEXT)"
         );
     }
+
+    fn text(s: &str) -> Value {
+        Value::from_text(s.to_owned())
+    }
+
+    fn commit(table: &mut CsvTable) {
+        table.sync().unwrap();
+        table.commit().unwrap();
+    }
+
+    #[test]
+    fn test_schema_affinities() {
+        let table = new_table(vec![
+            "data=1,2,3.5,04,x,5.0\n",
+            "schema=CREATE TABLE x (a INTEGER, b REAL, c NUMERIC, d TEXT, e INT NOT NULL, f)",
+        ]);
+        let mut cursor = table.open(None).unwrap();
+        cursor.filter(&[], None);
+
+        let a = cursor.column(0).unwrap();
+        assert_eq!(a.value_type(), ValueType::Integer);
+        assert_eq!(a.to_integer(), Some(1));
+        let b = cursor.column(1).unwrap();
+        assert_eq!(b.value_type(), ValueType::Float);
+        assert_eq!(b.to_float(), Some(2.0));
+        let c = cursor.column(2).unwrap();
+        assert_eq!(c.value_type(), ValueType::Float);
+        assert_eq!(c.to_float(), Some(3.5));
+        let d = cursor.column(3).unwrap();
+        assert_eq!(d.to_text(), Some("04"));
+        let e = cursor.column(4).unwrap();
+        assert_eq!(e.to_text(), Some("x"));
+        let f = cursor.column(5).unwrap();
+        assert_eq!(f.to_text(), Some("5.0"));
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        let columns = CsvVTabModule::parse_columns(
+            "CREATE TABLE x (\"a\"\"b\" VARCHAR(10, 2), [c d] DOUBLE DEFAULT 'point', \
+             e, PRIMARY KEY (a))",
+        );
+        assert_eq!(
+            columns,
+            vec![
+                ("a\"b".to_owned(), Affinity::Text),
+                ("c d".to_owned(), Affinity::Real),
+                ("e".to_owned(), Affinity::Blob),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_writable_requires_filename() {
+        let result = try_new_table(vec!["data=1,2\n", "writable=yes"]);
+        assert!(matches!(result, Err(ResultCode::InvalidArgs)));
+    }
+
+    #[test]
+    fn test_not_writable() {
+        let file = write_csv("1,Alice\n");
+        let mut table = new_table(vec![&format!("filename={}", file.path().to_string_lossy())]);
+        assert!(table.readonly());
+        assert!(matches!(
+            table.insert(&[text("2"), text("Bob")]),
+            Err(ResultCode::ReadOnly)
+        ));
+        assert!(matches!(table.delete(1), Err(ResultCode::ReadOnly)));
+    }
+
+    #[test]
+    fn test_insert_appends_to_file() {
+        let file = write_csv("id,name\n1,Alice");
+        let mut table = new_table(vec![
+            &format!("filename={}", file.path().to_string_lossy()),
+            "header=yes",
+            "writable=yes",
+        ]);
+        table.begin().unwrap();
+        assert_eq!(table.insert(&[Value::from_integer(2), text("Bob")]), Ok(2));
+        let rows = read_rows(table.open(None).unwrap(), 2);
+        assert_eq!(
+            rows,
+            vec![
+                vec![cell!("1"), cell!("Alice")],
+                vec![cell!("2"), cell!("Bob")]
+            ]
+        );
+        commit(&mut table);
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "id,name\n1,Alice\n2,Bob\n");
+        let rows = read_rows(table.open(None).unwrap(), 2);
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[test]
+    fn test_update_and_delete_rewrite_file() {
+        let file = write_csv("1,Alice\n2,Bob\n3,Carol\n");
+        let mut table = new_table(vec![
+            &format!("filename={}", file.path().to_string_lossy()),
+            "writable=1",
+        ]);
+        table.begin().unwrap();
+        table.update(1, &[text("1"), text("Alicia")]).unwrap();
+        table.delete(2).unwrap();
+        assert!(matches!(table.delete(2), Err(ResultCode::NotFound)));
+        commit(&mut table);
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "1,Alicia\n3,Carol\n");
+    }
+
+    #[test]
+    fn test_rollback_discards_changes() {
+        let file = write_csv("1,Alice\n");
+        let mut table = new_table(vec![
+            &format!("filename={}", file.path().to_string_lossy()),
+            "writable=1",
+        ]);
+        table.begin().unwrap();
+        table.insert(&[text("2"), text("Bob")]).unwrap();
+        table.savepoint(0).unwrap();
+        table.delete(1).unwrap();
+        table.rollback_to(0).unwrap();
+        let rows = read_rows(table.open(None).unwrap(), 2);
+        assert_eq!(
+            rows,
+            vec![
+                vec![cell!("1"), cell!("Alice")],
+                vec![cell!("2"), cell!("Bob")]
+            ]
+        );
+        table.rollback().unwrap();
+
+        let rows = read_rows(table.open(None).unwrap(), 2);
+        assert_eq!(rows, vec![vec![cell!("1"), cell!("Alice")]]);
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "1,Alice\n");
+    }
+
+    #[test]
+    fn test_writable_creates_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        let mut table = new_table(vec![
+            &format!("filename={}", path.to_string_lossy()),
+            "header=yes",
+            "writable=yes",
+            "schema=CREATE TABLE x (id INTEGER, name TEXT)",
+        ]);
+        table.begin().unwrap();
+        table
+            .insert(&[Value::from_integer(1), text("Alice")])
+            .unwrap();
+        commit(&mut table);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "id,name\n1,Alice\n");
+    }
+
+    #[test]
+    fn test_best_index_rowid() {
+        let constraint = |column_index, usable| ConstraintInfo {
+            column_index,
+            op: ConstraintOp::Eq,
+            usable,
+            index: 0,
+        };
+        let info = CsvTable::best_index(
+            &[constraint(0, true), constraint(ROWID_COLUMN_INDEX, true)],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(info.idx_num, ROWID_SEEK);
+        assert_eq!(info.constraint_usages[0].argv_index, None);
+        assert_eq!(info.constraint_usages[1].argv_index, Some(1));
+        assert!(info.constraint_usages[1].omit);
+
+        let info = CsvTable::best_index(&[constraint(ROWID_COLUMN_INDEX, false)], &[]).unwrap();
+        assert_eq!(info.idx_num, 0);
+        assert_eq!(info.constraint_usages[0].argv_index, None);
+    }
+
+    #[test]
+    fn test_rowid_seek() {
+        let table = new_table(vec!["data=a\nb\nc\n"]);
+        for (rowid, expected) in [(2, Some("b")), (3, Some("c")), (4, None), (0, None)] {
+            let mut cursor = table.open(None).unwrap();
+            cursor.filter(&[Value::from_integer(rowid)], Some(("rowid", ROWID_SEEK)));
+            if let Some(expected) = expected {
+                assert!(!cursor.eof());
+                assert_eq!(cursor.rowid(), rowid);
+                assert_eq!(cursor.column(0).unwrap().to_text(), Some(expected));
+                cursor.next();
+            }
+            assert!(cursor.eof());
+        }
+    }
+
+    #[test]
+    fn test_rowid_seek_uses_recorded_positions() {
+        let table = new_table(vec!["data=a,b\n1,2\n3,4\n5,6\n", "header=yes"]);
+        let mut cursor = table.open(None).unwrap();
+        cursor.filter(&[Value::from_integer(2)], Some(("rowid", ROWID_SEEK)));
+        assert_eq!(cursor.column(0).unwrap().to_text(), Some("3"));
+        assert_eq!(table.row_positions.borrow().len(), 2);
+
+        for (rowid, expected) in [(1, "1"), (3, "5"), (2, "3")] {
+            cursor.filter(&[Value::from_integer(rowid)], Some(("rowid", ROWID_SEEK)));
+            assert_eq!(cursor.rowid(), rowid);
+            assert_eq!(cursor.column(0).unwrap().to_text(), Some(expected));
+        }
+        assert_eq!(table.row_positions.borrow().len(), 3);
+    }
 }
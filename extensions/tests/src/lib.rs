@@ -6,9 +6,9 @@ use std::num::NonZeroUsize;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use turso_ext::{
-    register_extension, scalar, Connection, ConstraintInfo, ConstraintOp, ConstraintUsage,
-    ExtResult, IndexInfo, OrderByInfo, ResultCode, StepResult, VTabCursor, VTabKind, VTabModule,
-    VTabModuleDerive, VTable, Value,
+    collation, register_extension, scalar, Connection, ConstraintInfo, ConstraintOp,
+    ConstraintUsage, ExtResult, IndexInfo, OrderByInfo, ResultCode, StepResult, VTabCursor,
+    VTabKind, VTabModule, VTabModuleDerive, VTable, Value,
 };
 #[cfg(not(target_family = "wasm"))]
 use turso_ext::{BufferRef, Callback, VfsDerive, VfsExtension, VfsFile};
@@ -17,9 +17,11 @@ register_extension! {
     vtabs: { KVStoreVTabModule, TableStatsVtabModule },
     scalars: { test_scalar },
     vfs: { TestFS },
+    collations: { natural_collation },
 }
 
-type Store = Rc<RefCell<BTreeMap<i64, (String, String, String)>>>;
+type Rows = BTreeMap<i64, (String, String, String)>;
+type Store = Rc<RefCell<Rows>>;
 
 #[derive(VTabModuleDerive, Default)]
 pub struct KVStoreVTabModule;
@@ -53,6 +55,8 @@ impl VTabModule for KVStoreVTabModule {
             schema,
             KVStoreTable {
                 store: Rc::new(RefCell::new(BTreeMap::new())),
+                tx_snapshot: None,
+                savepoint_snapshots: BTreeMap::new(),
             },
         ))
     }
@@ -152,6 +156,10 @@ impl VTabCursor for KVStoreCursor {
 
 pub struct KVStoreTable {
     store: Store,
+    /// Contents of the store when the current transaction began, restored on rollback
+    tx_snapshot: Option<Rows>,
+    /// Contents of the store when each savepoint of the current transaction was opened
+    savepoint_snapshots: BTreeMap<usize, Rows>,
 }
 
 impl VTable for KVStoreTable {
@@ -263,6 +271,52 @@ impl VTable for KVStoreTable {
         log::debug!("VDestroy called");
         Ok(())
     }
+
+    fn begin(&mut self) -> Result<(), Self::Error> {
+        self.tx_snapshot = Some(self.store.borrow().clone());
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<(), Self::Error> {
+        self.tx_snapshot = None;
+        self.savepoint_snapshots.clear();
+        Ok(())
+    }
+
+    fn rollback(&mut self) -> Result<(), Self::Error> {
+        let snapshot = self
+            .tx_snapshot
+            .take()
+            .ok_or("rollback outside of a transaction")?;
+        *self.store.borrow_mut() = snapshot;
+        self.savepoint_snapshots.clear();
+        Ok(())
+    }
+
+    fn savepoint(&mut self, savepoint: usize) -> Result<(), Self::Error> {
+        self.savepoint_snapshots
+            .insert(savepoint, self.store.borrow().clone());
+        Ok(())
+    }
+
+    fn release(&mut self, savepoint: usize) -> Result<(), Self::Error> {
+        self.savepoint_snapshots.split_off(&savepoint);
+        Ok(())
+    }
+
+    fn rollback_to(&mut self, savepoint: usize) -> Result<(), Self::Error> {
+        self.savepoint_snapshots.split_off(&(savepoint + 1));
+        // The table was not written before the savepoints it was told about, so older ones
+        // hold the contents it had when the transaction began.
+        let snapshot = self
+            .savepoint_snapshots
+            .values()
+            .next_back()
+            .or(self.tx_snapshot.as_ref())
+            .ok_or("rollback outside of a transaction")?;
+        *self.store.borrow_mut() = snapshot.clone();
+        Ok(())
+    }
 }
 
 #[derive(Default, Clone)]
@@ -313,6 +367,37 @@ fn test_scalar(_args: turso_ext::Value) -> turso_ext::Value {
     turso_ext::Value::from_integer(42)
 }
 
+/// Orders runs of ASCII digits by their numeric value, so that 'file2' sorts before 'file10'.
+#[collation(name = "natural")]
+fn natural_collation(lhs: &str, rhs: &str) -> std::cmp::Ordering {
+    let (mut lhs, mut rhs) = (lhs, rhs);
+    loop {
+        let (Some(l), Some(r)) = (lhs.chars().next(), rhs.chars().next()) else {
+            return lhs.len().cmp(&rhs.len());
+        };
+        if l.is_ascii_digit() && r.is_ascii_digit() {
+            let l_end = lhs.find(|c: char| !c.is_ascii_digit()).unwrap_or(lhs.len());
+            let r_end = rhs.find(|c: char| !c.is_ascii_digit()).unwrap_or(rhs.len());
+            let l_digits = lhs[..l_end].trim_start_matches('0');
+            let r_digits = rhs[..r_end].trim_start_matches('0');
+            let ordering = l_digits
+                .len()
+                .cmp(&r_digits.len())
+                .then_with(|| l_digits.cmp(r_digits));
+            if ordering.is_ne() {
+                return ordering;
+            }
+            lhs = &lhs[l_end..];
+            rhs = &rhs[r_end..];
+        } else if l != r {
+            return l.cmp(&r);
+        } else {
+            lhs = &lhs[l.len_utf8()..];
+            rhs = &rhs[r.len_utf8()..];
+        }
+    }
+}
+
 #[cfg(not(target_family = "wasm"))]
 impl VfsExtension for TestFS {
     const NAME: &'static str = "testvfs";
@@ -0,0 +1 @@
+CREATE TABLE IF NOT EXISTS _init_table(x); DROP TABLE IF EXISTS _init_table;
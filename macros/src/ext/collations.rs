@@ -0,0 +1,78 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, ItemFn};
+
+use super::ScalarInfo;
+
+pub fn collation(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as ItemFn);
+    let fn_name = &ast.sig.ident;
+    let mut arg_names = ast.sig.inputs.iter().filter_map(|arg| match arg {
+        syn::FnArg::Typed(syn::PatType { pat, .. }) => match &**pat {
+            syn::Pat::Ident(ident) => Some(ident.ident.clone()),
+            _ => None,
+        },
+        syn::FnArg::Receiver(_) => None,
+    });
+    let lhs_name = arg_names.next().unwrap_or(format_ident!("lhs"));
+    let rhs_name = arg_names.next().unwrap_or(format_ident!("rhs"));
+    let collation_info = parse_macro_input!(attr as ScalarInfo);
+    let name = &collation_info.name;
+    let register_fn_name = format_ident!("register_{}", fn_name);
+    let fn_body = &ast.block;
+    let alias_check = if let Some(alias) = &collation_info.alias {
+        quote! {
+            let Ok(alias_c_name) = ::std::ffi::CString::new(#alias) else {
+                return ::turso_ext::ResultCode::Error;
+            };
+            let result = (api.register_collation)(api.ctx, alias_c_name.as_ptr(), #fn_name);
+            if !result.is_ok() {
+                return result;
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let expanded = quote! {
+        #[no_mangle]
+        pub unsafe extern "C" fn #register_fn_name(
+            api: *const ::turso_ext::ExtensionApi
+        ) -> ::turso_ext::ResultCode {
+            if api.is_null() {
+                return ::turso_ext::ResultCode::Error;
+            }
+            let api = unsafe { &*api };
+            let Ok(c_name) = ::std::ffi::CString::new(#name) else {
+                return ::turso_ext::ResultCode::Error;
+            };
+            let result = (api.register_collation)(api.ctx, c_name.as_ptr(), #fn_name);
+            if !result.is_ok() {
+                return result;
+            }
+            #alias_check
+            ::turso_ext::ResultCode::OK
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn #fn_name(
+            lhs_ptr: *const u8,
+            lhs_len: usize,
+            rhs_ptr: *const u8,
+            rhs_len: usize,
+        ) -> i32 {
+            unsafe fn as_str<'a>(ptr: *const u8, len: usize) -> &'a str {
+                if ptr.is_null() {
+                    return "";
+                }
+                // core only ever hands valid UTF-8 to collation functions
+                unsafe { ::std::str::from_utf8_unchecked(::std::slice::from_raw_parts(ptr, len)) }
+            }
+            fn compare(#lhs_name: &str, #rhs_name: &str) -> ::std::cmp::Ordering #fn_body
+            let (lhs, rhs) = unsafe { (as_str(lhs_ptr, lhs_len), as_str(rhs_ptr, rhs_len)) };
+            compare(lhs, rhs) as i32
+        }
+    };
+
+    TokenStream::from(expanded)
+}
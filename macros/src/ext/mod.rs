@@ -5,11 +5,13 @@ use syn::punctuated::Punctuated;
 use syn::token::Eq;
 use syn::{parse_macro_input, Ident, LitStr, Token};
 mod agg_derive;
+mod collations;
 mod match_ignore_ascii_case;
 mod scalars;
 mod vfs_derive;
 mod vtab_derive;
 pub use agg_derive::derive_agg_func;
+pub use collations::collation;
 pub use match_ignore_ascii_case::match_ignore_ascci_case;
 pub use scalars::scalar;
 pub use vfs_derive::derive_vfs_module;
@@ -22,6 +24,7 @@ pub fn register_extension(input: TokenStream) -> TokenStream {
         scalars,
         vtabs,
         vfs,
+        collations,
     } = input_ast;
 
     let scalar_calls = scalars.iter().map(|scalar_ident| {
@@ -58,6 +61,20 @@ pub fn register_extension(input: TokenStream) -> TokenStream {
             }
         }
     });
+    let collation_calls = collations.iter().map(|collation_ident| {
+        let register_fn = syn::Ident::new(
+            &format!("register_{collation_ident}"),
+            collation_ident.span(),
+        );
+        quote! {
+            {
+                let result = unsafe { #register_fn(api) };
+                if !result.is_ok() {
+                    return result;
+                }
+            }
+        }
+    });
     let vfs_calls = vfs.iter().map(|vfs_ident| {
         let register_fn = syn::Ident::new(&format!("register_{vfs_ident}"), vfs_ident.span());
         quote! {
@@ -84,6 +101,7 @@ pub fn register_extension(input: TokenStream) -> TokenStream {
     let static_aggregates = aggregate_calls.clone();
     let static_scalars = scalar_calls.clone();
     let static_vtabs = vtab_calls.clone();
+    let static_collations = collation_calls.clone();
 
     let expanded = quote! {
     #[cfg(not(target_family = "wasm"))]
@@ -99,6 +117,8 @@ pub fn register_extension(input: TokenStream) -> TokenStream {
 
                 #(#static_vtabs)*
 
+                #(#static_collations)*
+
                 #[cfg(not(target_family = "wasm"))]
                 #(#static_vfs)*
 
@@ -114,6 +134,8 @@ pub fn register_extension(input: TokenStream) -> TokenStream {
 
                 #(#vtab_calls)*
 
+                #(#collation_calls)*
+
                 #(#vfs_calls)*
 
                 ::turso_ext::ResultCode::OK
@@ -128,6 +150,7 @@ pub(crate) struct RegisterExtensionInput {
     pub scalars: Vec<Ident>,
     pub vtabs: Vec<Ident>,
     pub vfs: Vec<Ident>,
+    pub collations: Vec<Ident>,
 }
 
 impl syn::parse::Parse for RegisterExtensionInput {
@@ -136,11 +159,12 @@ impl syn::parse::Parse for RegisterExtensionInput {
         let mut scalars = Vec::new();
         let mut vtabs = Vec::new();
         let mut vfs = Vec::new();
+        let mut collations = Vec::new();
         while !input.is_empty() {
             if input.peek(syn::Ident) && input.peek2(Token![:]) {
                 let section_name: Ident = input.parse()?;
                 input.parse::<Token![:]>()?;
-                let names = ["aggregates", "scalars", "vtabs", "vfs"];
+                let names = ["aggregates", "scalars", "vtabs", "vfs", "collations"];
                 if names.contains(&section_name.to_string().as_str()) {
                     let content;
                     syn::braced!(content in input);
@@ -153,6 +177,7 @@ impl syn::parse::Parse for RegisterExtensionInput {
                         "scalars" => scalars = parsed_items,
                         "vtabs" => vtabs = parsed_items,
                         "vfs" => vfs = parsed_items,
+                        "collations" => collations = parsed_items,
                         _ => unreachable!(),
                     };
 
@@ -163,7 +188,8 @@ impl syn::parse::Parse for RegisterExtensionInput {
                     return Err(syn::Error::new(section_name.span(), "Unknown section"));
                 }
             } else {
-                return Err(input.error("Expected aggregates:, scalars:, or vtabs: section"));
+                return Err(input
+                    .error("Expected aggregates:, scalars:, vtabs:, vfs: or collations: section"));
             }
         }
 
@@ -172,6 +198,7 @@ impl syn::parse::Parse for RegisterExtensionInput {
             scalars,
             vtabs,
             vfs,
+            collations,
         })
     }
 }
@@ -18,6 +18,16 @@ pub fn derive_vtab_module(input: TokenStream) -> TokenStream {
     let rowid_fn_name = format_ident!("rowid_{}", struct_name);
     let destroy_fn_name = format_ident!("destroy_{}", struct_name);
     let best_idx_fn_name = format_ident!("best_idx_{}", struct_name);
+    let begin_fn_name = format_ident!("begin_{}", struct_name);
+    let sync_fn_name = format_ident!("sync_{}", struct_name);
+    let commit_fn_name = format_ident!("commit_{}", struct_name);
+    let rollback_fn_name = format_ident!("rollback_{}", struct_name);
+    let rename_fn_name = format_ident!("rename_{}", struct_name);
+    let savepoint_fn_name = format_ident!("savepoint_{}", struct_name);
+    let release_fn_name = format_ident!("release_{}", struct_name);
+    let rollback_to_fn_name = format_ident!("rollback_to_{}", struct_name);
+    let is_readonly_fn_name = format_ident!("is_readonly_{}", struct_name);
+    let error_message_fn_name = format_ident!("error_message_{}", struct_name);
 
     let expanded = quote! {
         impl #struct_name {
@@ -122,6 +132,22 @@ pub fn derive_vtab_module(input: TokenStream) -> TokenStream {
                 <<#struct_name as ::turso_ext::VTabModule>::Table as ::turso_ext::VTable>::Cursor::next(cursor)
             }
 
+            #[no_mangle]
+            unsafe extern "C" fn #error_message_fn_name(
+                cursor: *const ::std::ffi::c_void,
+            ) -> *const ::std::ffi::c_char {
+                if cursor.is_null() {
+                    return ::std::ptr::null();
+                }
+                let cursor = &*(cursor as *const <<#struct_name as ::turso_ext::VTabModule>::Table as ::turso_ext::VTable>::Cursor);
+                match <<#struct_name as ::turso_ext::VTabModule>::Table as ::turso_ext::VTable>::Cursor::error_message(cursor)
+                    .and_then(|message| ::std::ffi::CString::new(message).ok())
+                {
+                    Some(message) => message.into_raw(),
+                    None => ::std::ptr::null(),
+                }
+            }
+
             #[no_mangle]
             unsafe extern "C" fn #eof_fn_name(
                 cursor: *const ::std::ffi::c_void,
@@ -227,6 +253,136 @@ pub fn derive_vtab_module(input: TokenStream) -> TokenStream {
                 }
             }
 
+            #[no_mangle]
+            unsafe extern "C" fn #begin_fn_name(
+                table: *const ::std::ffi::c_void,
+            ) -> ::turso_ext::ResultCode {
+                if table.is_null() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                let table = &mut *(table as *mut <#struct_name as ::turso_ext::VTabModule>::Table);
+                if <#struct_name as ::turso_ext::VTabModule>::Table::begin(table).is_err() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                ::turso_ext::ResultCode::OK
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn #sync_fn_name(
+                table: *const ::std::ffi::c_void,
+            ) -> ::turso_ext::ResultCode {
+                if table.is_null() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                let table = &mut *(table as *mut <#struct_name as ::turso_ext::VTabModule>::Table);
+                if <#struct_name as ::turso_ext::VTabModule>::Table::sync(table).is_err() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                ::turso_ext::ResultCode::OK
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn #commit_fn_name(
+                table: *const ::std::ffi::c_void,
+            ) -> ::turso_ext::ResultCode {
+                if table.is_null() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                let table = &mut *(table as *mut <#struct_name as ::turso_ext::VTabModule>::Table);
+                if <#struct_name as ::turso_ext::VTabModule>::Table::commit(table).is_err() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                ::turso_ext::ResultCode::OK
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn #rollback_fn_name(
+                table: *const ::std::ffi::c_void,
+            ) -> ::turso_ext::ResultCode {
+                if table.is_null() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                let table = &mut *(table as *mut <#struct_name as ::turso_ext::VTabModule>::Table);
+                if <#struct_name as ::turso_ext::VTabModule>::Table::rollback(table).is_err() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                ::turso_ext::ResultCode::OK
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn #rename_fn_name(
+                table: *const ::std::ffi::c_void,
+                new_name: *const ::std::ffi::c_char,
+            ) -> ::turso_ext::ResultCode {
+                if table.is_null() || new_name.is_null() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                let table = &mut *(table as *mut <#struct_name as ::turso_ext::VTabModule>::Table);
+                let Ok(new_name) = ::std::ffi::CStr::from_ptr(new_name).to_str() else {
+                    return ::turso_ext::ResultCode::InvalidArgs;
+                };
+                if <#struct_name as ::turso_ext::VTabModule>::Table::rename(table, new_name).is_err() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                ::turso_ext::ResultCode::OK
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn #savepoint_fn_name(
+                table: *const ::std::ffi::c_void,
+                savepoint: i32,
+            ) -> ::turso_ext::ResultCode {
+                if table.is_null() || savepoint < 0 {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                let table = &mut *(table as *mut <#struct_name as ::turso_ext::VTabModule>::Table);
+                if <#struct_name as ::turso_ext::VTabModule>::Table::savepoint(table, savepoint as usize).is_err() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                ::turso_ext::ResultCode::OK
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn #release_fn_name(
+                table: *const ::std::ffi::c_void,
+                savepoint: i32,
+            ) -> ::turso_ext::ResultCode {
+                if table.is_null() || savepoint < 0 {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                let table = &mut *(table as *mut <#struct_name as ::turso_ext::VTabModule>::Table);
+                if <#struct_name as ::turso_ext::VTabModule>::Table::release(table, savepoint as usize).is_err() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                ::turso_ext::ResultCode::OK
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn #rollback_to_fn_name(
+                table: *const ::std::ffi::c_void,
+                savepoint: i32,
+            ) -> ::turso_ext::ResultCode {
+                if table.is_null() || savepoint < 0 {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                let table = &mut *(table as *mut <#struct_name as ::turso_ext::VTabModule>::Table);
+                if <#struct_name as ::turso_ext::VTabModule>::Table::rollback_to(table, savepoint as usize).is_err() {
+                    return ::turso_ext::ResultCode::Error;
+                }
+                ::turso_ext::ResultCode::OK
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn #is_readonly_fn_name(
+                table: *const ::std::ffi::c_void,
+            ) -> bool {
+                if table.is_null() {
+                    return true;
+                }
+                let table = &*(table as *const <#struct_name as ::turso_ext::VTabModule>::Table);
+                <#struct_name as ::turso_ext::VTabModule>::Table::readonly(table)
+            }
+
             #[no_mangle]
             pub unsafe extern "C" fn #register_fn_name(
                 api: *const ::turso_ext::ExtensionApi
@@ -251,6 +407,16 @@ pub fn derive_vtab_module(input: TokenStream) -> TokenStream {
                     rowid: Self::#rowid_fn_name,
                     destroy: Self::#destroy_fn_name,
                     best_idx: Self::#best_idx_fn_name,
+                    begin: Self::#begin_fn_name,
+                    sync: Self::#sync_fn_name,
+                    commit: Self::#commit_fn_name,
+                    rollback: Self::#rollback_fn_name,
+                    rename: Self::#rename_fn_name,
+                    savepoint: Self::#savepoint_fn_name,
+                    release: Self::#release_fn_name,
+                    rollback_to: Self::#rollback_to_fn_name,
+                    is_readonly: Self::#is_readonly_fn_name,
+                    error_message: Self::#error_message_fn_name,
                 };
                 (api.register_vtab_module)(api.ctx, name_c, module, <#struct_name as ::turso_ext::VTabModule>::VTAB_KIND)
             }
@@ -195,6 +195,21 @@ pub fn scalar(attr: TokenStream, input: TokenStream) -> TokenStream {
     ext::scalar(attr, input)
 }
 
+/// Declare a collation sequence for your extension, usable with `COLLATE example`.
+/// The function compares two strings and returns their `Ordering`.
+/// ```ignore
+/// use std::cmp::Ordering;
+/// use turso_ext::collation;
+/// #[collation(name = "length", alias = "len")] // you can provide an <optional> alias
+/// fn length(lhs: &str, rhs: &str) -> Ordering {
+///     lhs.chars().count().cmp(&rhs.chars().count())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn collation(attr: TokenStream, input: TokenStream) -> TokenStream {
+    ext::collation(attr, input)
+}
+
 /// Define an aggregate function for your extension by deriving
 /// AggregateDerive on a struct that implements the AggFunc trait.
 /// ```ignore
@@ -290,6 +305,20 @@ pub fn derive_agg_func(input: TokenStream) -> TokenStream {
 ///  fn destroy(&mut self) -> Result<(), Self::Error> {
 ///     Ok(())
 ///  }
+///
+///  /// Transaction callbacks, for tables that need to take part in the transactions that
+///  /// write them. `sync`, `rename`, `savepoint`, `release` and `rollback_to` work the same way.
+///  fn begin(&mut self) -> Result<(), Self::Error> {
+///     Ok(())
+///  }
+///
+///  fn commit(&mut self) -> Result<(), Self::Error> {
+///     Ok(())
+///  }
+///
+///  fn rollback(&mut self) -> Result<(), Self::Error> {
+///     Ok(())
+///  }
 /// }
 ///
 ///  #[derive(Debug)]
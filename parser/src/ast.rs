@@ -1320,12 +1320,22 @@ pub enum PragmaName {
     EncryptionCipher,
     /// List databases
     DatabaseList,
+    /// Defer all foreign key constraints until the transaction commits
+    DeferForeignKeys,
     /// Encoding - only support utf8
     Encoding,
+    /// Check the foreign key constraints of the database or of a table
+    ForeignKeyCheck,
+    /// Returns the foreign keys of a table
+    ForeignKeyList,
+    /// Enforce foreign key constraints
+    ForeignKeys,
     /// Current free page count.
     FreelistCount,
     /// Run integrity check on the database file
     IntegrityCheck,
+    /// Isolation level of MVCC transactions (SNAPSHOT | SERIALIZABLE)
+    IsolationLevel,
     /// `journal_mode` pragma
     JournalMode,
     /// encryption key for encrypted databases, specified as hexadecimal string.
@@ -1339,6 +1349,15 @@ pub enum PragmaName {
     /// `module_list` pragma
     /// `module_list` lists modules used by virtual tables.
     ModuleList,
+    /// Whether MVCC commits start garbage collections
+    MvccGc,
+    /// Bytes of row data in the versions committed since the last MVCC garbage collection that
+    /// start a new one
+    MvccGcMemoryThreshold,
+    /// Versions committed since the last MVCC garbage collection that start a new one
+    MvccGcVersionThreshold,
+    /// Version counters of the tables and indexes in the MVCC store
+    MvccStats,
     /// Return the total number of pages in the database file.
     PageCount,
     /// Return the page size of the database in bytes.
@@ -1351,6 +1370,8 @@ pub enum PragmaName {
     Synchronous,
     /// returns information about the columns of a table
     TableInfo,
+    /// Where temporary tables and indices are stored (DEFAULT | FILE | MEMORY)
+    TempStore,
     /// enable capture-changes logic for the connection
     UnstableCaptureDataChangesConn,
     /// Returns the user version of the database file.
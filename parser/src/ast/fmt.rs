@@ -103,6 +103,11 @@ impl<T: Write> TokenStream for WriteTokenStream<'_, T> {
             }
         }
     }
+
+    fn append_call_lp(&mut self) -> fmt::Result {
+        self.spaced = true;
+        self.append(TK_LP, None)
+    }
 }
 
 pub struct BlankContext;
@@ -117,6 +122,12 @@ pub trait TokenStream {
     /// Push token to this stream
     fn append(&mut self, ty: TokenType, value: Option<&str>) -> Result<(), Self::Error>;
 
+    /// Push the opening parenthesis of a function call, which follows the function name
+    /// without a space
+    fn append_call_lp(&mut self) -> Result<(), Self::Error> {
+        self.append(TK_LP, None)
+    }
+
     /// Interspace iterator with commas
     fn comma<I, C: ToSqlContext>(&mut self, items: I, context: &C) -> Result<(), Self::Error>
     where
@@ -768,7 +779,7 @@ impl ToTokens for Expr {
                 filter_over,
             } => {
                 name.to_tokens(s, context)?;
-                s.append(TK_LP, None)?;
+                s.append_call_lp()?;
                 if let Some(distinctness) = distinctness {
                     distinctness.to_tokens(s, context)?;
                 }
@@ -786,7 +797,7 @@ impl ToTokens for Expr {
             }
             Self::FunctionCallStar { name, filter_over } => {
                 name.to_tokens(s, context)?;
-                s.append(TK_LP, None)?;
+                s.append_call_lp()?;
                 s.append(TK_STAR, None)?;
                 s.append(TK_RP, None)?;
                 filter_over.to_tokens(s, context)?;
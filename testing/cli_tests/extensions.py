@@ -483,6 +483,79 @@ def _test_kv(exec_name, ext_path):
     limbo.quit()
 
 
+def test_kv_transactions():
+    limbo = TestTursoShell()
+    ext_path = "target/debug/libturso_ext_tests"
+    limbo.execute_dot(f".load {ext_path}")
+    limbo.execute_dot("create virtual table t using kv_store;")
+    limbo.run_test_fn(
+        "begin; insert into t values ('a', 'rolled back'); rollback;",
+        null,
+        "can roll back a transaction on kv_store",
+    )
+    limbo.run_test_fn("select count(*) from t;", lambda res: "0" == res, "rollback discards rows")
+    limbo.run_test_fn(
+        "begin; insert into t values ('b', 'committed'); commit;",
+        null,
+        "can commit a transaction on kv_store",
+    )
+    limbo.run_test_fn("select * from t;", lambda res: "b|committed" == res, "commit keeps rows")
+    limbo.run_test_fn(
+        "savepoint s1; insert into t values ('c', 'undone'); rollback to s1; release s1;",
+        null,
+        "can roll back to a savepoint on kv_store",
+    )
+    limbo.run_test_fn(
+        "select count(*) from t;",
+        lambda res: "1" == res,
+        "rollback to savepoint discards rows",
+    )
+    limbo.run_test_fn("alter table t rename to t2;", null, "can rename kv_store table")
+    limbo.run_test_fn(
+        "select * from t2;",
+        lambda res: "b|committed" == res,
+        "renamed kv_store table keeps its rows",
+    )
+    limbo.quit()
+
+
+def test_collation():
+    limbo = TestTursoShell()
+    ext_path = "target/debug/libturso_ext_tests"
+    limbo.run_test_fn(
+        "select 'file2' < 'file10' collate natural;",
+        lambda res: "no such collation sequence: natural" in res,
+        "natural collation is not available before loading the extension",
+    )
+    limbo.execute_dot(f".load {ext_path}")
+    limbo.run_test_fn(
+        "select 'file2' < 'file10' collate natural;",
+        lambda res: "1" == res,
+        "natural collation compares digit runs numerically",
+    )
+    limbo.execute_dot("create table files (name text);")
+    limbo.execute_dot("insert into files values ('file10'), ('file2'), ('file1');")
+    limbo.run_test_fn(
+        "select name from files order by name collate natural;",
+        lambda res: "file1\nfile2\nfile10" == res,
+        "order by uses natural collation",
+    )
+    limbo.execute_dot("create table sorted (name text collate natural);")
+    limbo.execute_dot("insert into sorted select name from files;")
+    limbo.execute_dot("create index sorted_name on sorted (name);")
+    limbo.run_test_fn(
+        "select name from sorted where name = 'file010';",
+        lambda res: "file10" == res,
+        "index seek uses the column's natural collation",
+    )
+    limbo.run_test_fn(
+        "select name from sorted where name > 'file1' order by name;",
+        lambda res: "file2\nfile10" == res,
+        "index range scan uses the column's natural collation",
+    )
+    limbo.quit()
+
+
 def test_ipaddr():
     limbo = TestTursoShell()
     ext_path = "./target/debug/liblimbo_ipaddr"
@@ -686,11 +759,57 @@ def test_csv():
     limbo.quit()
 
 
+def test_csv_writable():
+    if os.path.exists("testing/writable.csv"):
+        os.remove("testing/writable.csv")
+    limbo = TestTursoShell(init_commands="")
+    limbo.execute_dot(".load target/debug/liblimbo_csv")
+    limbo.run_test_fn(
+        "CREATE VIRTUAL TABLE temp.out USING csv(filename=testing/writable.csv, header=yes, "
+        "writable=yes, schema='CREATE TABLE x(id INTEGER, name TEXT)');",
+        null,
+        "Create writable CSV table for a file that doesn't exist",
+    )
+    limbo.run_test_fn(
+        "INSERT INTO temp.out VALUES (1, 'Alice'), (2, 'Bob'), (3, 'Carol');",
+        null,
+        "INSERT into writable CSV table",
+    )
+    limbo.run_test_fn(
+        "SELECT id + 1, name FROM temp.out WHERE rowid = 2;",
+        lambda res: res == "3|Bob",
+        "Seek writable CSV table by rowid, with the declared column affinities",
+    )
+    limbo.run_test_fn(
+        "BEGIN; DELETE FROM temp.out WHERE id = 1; ROLLBACK;",
+        null,
+        "Roll back DELETE from writable CSV table",
+    )
+    limbo.run_test_fn(
+        "UPDATE temp.out SET name = 'Robert' WHERE id = 2; DELETE FROM temp.out WHERE id = 3;",
+        null,
+        "UPDATE and DELETE writable CSV table",
+    )
+    limbo.run_test_fn(
+        "SELECT * FROM temp.out;",
+        lambda res: res == "1|Alice\n2|Robert",
+        "Read changed rows from writable CSV table",
+    )
+    limbo.quit()
+    with open("testing/writable.csv") as f:
+        content = f.read()
+    if content != "id,name\n1,Alice\n2,Robert\n":
+        raise Exception(f"writable CSV file has unexpected content: {content!r}")
+    console.test("Changes to writable CSV table are written to its file")
+
+
 def cleanup():
     if os.path.exists("testing/vfs.db"):
         os.remove("testing/vfs.db")
     if os.path.exists("testing/vfs.db-wal"):
         os.remove("testing/vfs.db-wal")
+    if os.path.exists("testing/writable.csv"):
+        os.remove("testing/writable.csv")
 
 
 def test_tablestats():
@@ -810,7 +929,10 @@ def main():
         test_vfs()
         test_sqlite_vfs_compat()
         test_kv()
+        test_kv_transactions()
+        test_collation()
         test_csv()
+        test_csv_writable()
         test_tablestats()
     except Exception as e:
         console.error(f"Test FAILED: {e}")
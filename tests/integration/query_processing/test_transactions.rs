@@ -24,3 +24,29 @@ fn test_txn_error_doesnt_rollback_txn() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_deferred_foreign_key_violation_keeps_txn_open() -> Result<()> {
+    let tmp_db = TempDatabase::new_with_rusqlite("create table p (id integer primary key);", false);
+    let conn = tmp_db.connect_limbo();
+
+    conn.execute("create table c (x references p(id) deferrable initially deferred)")?;
+    conn.execute("pragma foreign_keys = on")?;
+    conn.execute("begin")?;
+    conn.execute("insert into c values (1)")?;
+    // should fail, the parent row does not exist yet
+    assert!(conn
+        .execute("commit")
+        .inspect_err(|e| assert!(matches!(e, LimboError::TxError(_))))
+        .is_err());
+    // the transaction is still open, so the violation can be fixed
+    conn.execute("insert into p values (1)")?;
+    conn.execute("commit")?;
+    let mut stmt = conn.query("select count(*) from c")?.unwrap();
+    if let StepResult::Row = stmt.step()? {
+        let row = stmt.row().unwrap();
+        assert_eq!(*row.get::<&Value>(0).unwrap(), Value::Integer(1));
+    }
+
+    Ok(())
+}